@@ -22,6 +22,24 @@ use client::decl_runtime_apis;
 use rstd::vec::Vec;
 use sr_primitives::RuntimeDebug;
 
+/// The sr25519 keys authorities sign their addresses with.
+pub mod sr25519 {
+	mod app {
+		use app_crypto::{app_crypto, key_types::AUTHORITY_DISCOVERY, sr25519};
+		app_crypto!(sr25519, AUTHORITY_DISCOVERY);
+	}
+
+	/// An authority discovery keypair.
+	#[cfg(feature = "std")]
+	pub type AuthorityPair = app::Pair;
+
+	/// An authority discovery signature.
+	pub type AuthoritySignature = app::Signature;
+
+	/// An authority discovery identifier.
+	pub type AuthorityId = app::Public;
+}
+
 #[derive(codec::Encode, codec::Decode, Eq, PartialEq, Clone, RuntimeDebug)]
 #[cfg_attr(feature = "std", derive(Hash))]
 pub struct Signature(pub Vec<u8>);
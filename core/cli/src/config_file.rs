@@ -0,0 +1,346 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Configuration files given with `--config`.
+//!
+//! A configuration file is a TOML file, or a JSON file if its extension is `.json`, whose keys are
+//! the long names of the flags of the `run` command. Its options are turned into flags inserted
+//! after `--config`, except for the ones also given on the command line.
+
+use std::{collections::HashSet, ffi::OsString, fmt::Display, path::Path};
+use serde::Serializer;
+use serde_json::{Map, Value};
+use service::{config::{ColumnConfig, Compression, Configuration, KeepBlocks}, PruningMode};
+
+use crate::params::{Cors, RunCmd};
+
+/// Long names of the flags having a short name.
+const SHORT_FLAGS: &[(&str, &str)] = &[("d", "base-path"), ("l", "log")];
+
+/// Inserts the flags set by the configuration file given with `--config`, if any.
+pub fn expand_args(mut args: Vec<OsString>) -> Result<Vec<OsString>, String> {
+	let (path, end) = match find_config_path(&args) {
+		Some(found) => found,
+		None => return Ok(args),
+	};
+
+	let given = given_flags(&args);
+	let mut file_args = Vec::new();
+	for (key, value) in service::read_config_file(Path::new(&path))? {
+		if key == "config" {
+			return Err(format!("{}: `config` can't be set in a configuration file", path.to_string_lossy()));
+		}
+		if !given.contains(&key) {
+			push_flag(&mut file_args, &key, &value)
+				.map_err(|e| format!("{}: {}", path.to_string_lossy(), e))?;
+		}
+	}
+
+	args.splice(end..end, file_args);
+	Ok(args)
+}
+
+/// Returns the path given with `--config`, and the position of the argument following it.
+fn find_config_path(args: &[OsString]) -> Option<(OsString, usize)> {
+	for (i, arg) in args.iter().enumerate() {
+		if arg == "--config" {
+			return args.get(i + 1).map(|path| (path.clone(), i + 2));
+		}
+		if let Some(arg) = arg.to_str() {
+			if arg.starts_with("--config=") {
+				return Some((arg["--config=".len()..].into(), i + 1));
+			}
+		}
+	}
+	None
+}
+
+/// Returns the long names of the flags given on the command line.
+fn given_flags(args: &[OsString]) -> HashSet<String> {
+	args.iter()
+		.filter_map(|arg| arg.to_str())
+		.filter_map(|arg| if arg.starts_with("--") {
+			arg[2..].split('=').next().map(ToOwned::to_owned)
+		} else if arg.starts_with('-') {
+			SHORT_FLAGS.iter()
+				.find(|(short, _)| arg[1..].starts_with(short))
+				.map(|(_, long)| long.to_string())
+		} else {
+			None
+		})
+		.collect()
+}
+
+/// Turns an option of the configuration file into flags.
+///
+/// A `false` boolean sets nothing, and arrays set the flag once per element.
+fn push_flag(args: &mut Vec<OsString>, key: &str, value: &Value) -> Result<(), String> {
+	match value {
+		Value::Null | Value::Bool(false) => {},
+		Value::Bool(true) => args.push(format!("--{}", key).into()),
+		Value::Number(n) => args.push(format!("--{}={}", key, n).into()),
+		Value::String(s) => args.push(format!("--{}={}", key, s).into()),
+		Value::Array(values) => for value in values {
+			match value {
+				Value::Array(_) | Value::Object(_) =>
+					return Err(format!("`{}` must be a list of values", key)),
+				value => push_flag(args, key, value)?,
+			}
+		},
+		Value::Object(_) => return Err(format!("`{}` can't be a table", key)),
+	}
+	Ok(())
+}
+
+/// Per-context execution strategies, replaced by `execution` when it is given.
+const EXECUTION_CONTEXTS: &[&str] = &[
+	"execution-syncing",
+	"execution-import-block",
+	"execution-block-construction",
+	"execution-offchain-worker",
+	"execution-other",
+];
+
+/// The effective configuration of the node, as a configuration file.
+#[derive(Debug)]
+pub struct EffectiveConfig(Map<String, Value>);
+
+impl EffectiveConfig {
+	/// Gathers the options of `cli`, completed with the values the node derives from the chain
+	/// spec and from its other options.
+	pub fn new<C, G, E>(
+		cli: &RunCmd,
+		chain: String,
+		base_path: &Path,
+		config: &Configuration<C, G, E>,
+	) -> Result<Self, String> {
+		let mut options = options(cli)?;
+		options.insert("chain".into(), chain.into());
+		options.insert("base-path".into(), base_path.display().to_string().into());
+		options.insert("bootnodes".into(), config.network.boot_nodes.clone().into());
+		options.insert("telemetry-url".into(), config.telemetry_endpoints.iter()
+			.flat_map(|endpoints| endpoints.endpoints().iter())
+			.map(|(url, verbosity)| format!("{} {}", url, verbosity))
+			.collect::<Vec<_>>()
+			.into());
+		options.insert("pruning".into(), match config.pruning {
+			PruningMode::Constrained(ref constraints) =>
+				constraints.max_blocks.unwrap_or(0).to_string(),
+			PruningMode::ArchiveAll | PruningMode::ArchiveCanonical => "archive".into(),
+		}.into());
+		match config.rpc_cors {
+			None => { options.insert("rpc-cors".into(), "all".into()); },
+			Some(ref origins) if origins.is_empty() => { options.remove("rpc-cors"); },
+			Some(ref origins) => { options.insert("rpc-cors".into(), origins.join(",").into()); },
+		}
+
+		Ok(EffectiveConfig(options))
+	}
+
+	/// Renders the configuration as TOML, or JSON if `json` is set.
+	pub fn render(&self, json: bool) -> Result<String, String> {
+		if json {
+			serde_json::to_string_pretty(&self.0).map_err(|e| e.to_string())
+		} else {
+			toml::to_string(&self.0).map_err(|e| e.to_string())
+		}
+	}
+}
+
+/// Returns the options set by `cli`, with the same keys and formats as in a configuration file.
+///
+/// Options which aren't set are left out.
+fn options(cli: &RunCmd) -> Result<Map<String, Value>, String> {
+	let options = match serde_json::to_value(cli).map_err(|e| e.to_string())? {
+		Value::Object(options) => options,
+		_ => return Err("The options of the `run` command aren't a table".into()),
+	};
+	let all_contexts = options.get("execution").is_some();
+
+	Ok(options.into_iter()
+		.filter(|(key, value)| !value.is_null() && !(all_contexts && EXECUTION_CONTEXTS.contains(&&key[..])))
+		.collect())
+}
+
+/// Serializes a value parsed from its textual representation, such as an `arg_enum`.
+pub(crate) fn display<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+	serializer.collect_str(value)
+}
+
+/// Serializes an optional value parsed from its textual representation.
+pub(crate) fn display_opt<T: Display, S: Serializer>(
+	value: &Option<T>,
+	serializer: S,
+) -> Result<S::Ok, S::Error> {
+	match value {
+		Some(value) => serializer.collect_str(value),
+		None => serializer.serialize_none(),
+	}
+}
+
+/// Serializes the settings of the database columns as `COLUMN:SETTINGS`.
+pub(crate) fn column_configs<S: Serializer>(
+	columns: &[(String, ColumnConfig)],
+	serializer: S,
+) -> Result<S::Ok, S::Error> {
+	serializer.collect_seq(columns.iter().map(|(column, config)| {
+		let compression = match config.compression {
+			Compression::None => "none",
+			Compression::Snappy => "snappy",
+			Compression::Lz4 => "lz4",
+			Compression::Zstd => "zstd",
+		};
+		let cache = config.cache_share.map(|share| format!(",cache={}", share)).unwrap_or_default();
+		format!(
+			"{}:compression={}{},bloom={}",
+			column,
+			compression,
+			cache,
+			config.bloom_filter_bits.unwrap_or(0),
+		)
+	}))
+}
+
+/// Serializes a block data pruning mode, a number of blocks to keep or `archive`.
+pub(crate) fn keep_blocks<S: Serializer>(
+	keep: &Option<KeepBlocks>,
+	serializer: S,
+) -> Result<S::Ok, S::Error> {
+	match keep {
+		Some(KeepBlocks::All) => serializer.serialize_str("archive"),
+		Some(KeepBlocks::Some(blocks)) => serializer.collect_str(blocks),
+		None => serializer.serialize_none(),
+	}
+}
+
+/// Serializes the allowed origins as a comma-separated list, or `all`.
+pub(crate) fn cors<S: Serializer>(cors: &Option<Cors>, serializer: S) -> Result<S::Ok, S::Error> {
+	match cors {
+		Some(Cors::All) => serializer.serialize_str("all"),
+		Some(Cors::List(origins)) => serializer.serialize_str(&origins.join(",")),
+		None => serializer.serialize_none(),
+	}
+}
+
+/// Serializes the telemetry endpoints as `URL VERBOSITY`.
+pub(crate) fn telemetry_endpoints<S: Serializer>(
+	endpoints: &[(String, u8)],
+	serializer: S,
+) -> Result<S::Ok, S::Error> {
+	serializer.collect_seq(endpoints.iter().map(|(url, verbosity)| format!("{} {}", url, verbosity)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs;
+	use serde_json::json;
+	use structopt::{StructOpt, clap::App};
+	use tempdir::TempDir;
+	use crate::traits::AugmentClap;
+
+	fn args(args: &[&str]) -> Vec<OsString> {
+		args.iter().map(Into::into).collect()
+	}
+
+	fn run_cmd(args: Vec<OsString>) -> RunCmd {
+		RunCmd::from_clap(&RunCmd::augment_clap(App::new("substrate")).get_matches_from(args))
+	}
+
+	#[test]
+	fn flags_override_the_file() {
+		let dir = TempDir::new("config-file").unwrap();
+		let path = dir.path().join("node.toml");
+		fs::write(&path, "port = 30334\nname = \"file\"\nvalidator = true\nno-mdns = false\n\
+			bootnodes = [\"/ip4/127.0.0.1/tcp/30333\", \"/ip4/127.0.0.1/tcp/30334\"]\n").unwrap();
+		let config = format!("--config={}", path.display());
+
+		assert_eq!(
+			expand_args(args(&["substrate", &config, "--name", "flag"])).unwrap(),
+			args(&[
+				"substrate", &config,
+				"--bootnodes=/ip4/127.0.0.1/tcp/30333", "--bootnodes=/ip4/127.0.0.1/tcp/30334",
+				"--port=30334", "--validator",
+				"--name", "flag",
+			]),
+		);
+	}
+
+	#[test]
+	fn reads_json_files() {
+		let dir = TempDir::new("config-file").unwrap();
+		let path = dir.path().join("node.json");
+		fs::write(&path, r#"{"base-path": "/tmp/node", "pool-limit": 10}"#).unwrap();
+		let path = path.to_str().unwrap();
+
+		assert_eq!(
+			expand_args(args(&["substrate", "print-config", "--config", path, "-d", "/tmp/other"])).unwrap(),
+			args(&["substrate", "print-config", "--config", path, "--pool-limit=10", "-d", "/tmp/other"]),
+		);
+	}
+
+	#[test]
+	fn rejects_tables() {
+		let dir = TempDir::new("config-file").unwrap();
+		let path = dir.path().join("node.toml");
+		fs::write(&path, "[network]\nport = 30334\n").unwrap();
+
+		assert!(expand_args(args(&["substrate", "--config", path.to_str().unwrap()])).is_err());
+	}
+
+	#[test]
+	fn options_round_trip_through_a_file() {
+		let cli = run_cmd(args(&[
+			"substrate", "--database=sled", "--db-column=state:compression=lz4,cache=50,bloom=0",
+			"--pruning=1000", "--bodies-pruning=archive", "--justifications-pruning=AuthoritySetChanges",
+			"--changes-tries-pruning=64", "--node-key-file=/tmp/node-key", "--rpc-methods=Safe",
+			"--execution=Wasm", "--rpc-cors=all", "--telemetry-url=ws://telemetry 1", "--alice",
+		]));
+		let written = options(&cli).unwrap();
+
+		assert_eq!(written["database"], json!("Sled"));
+		assert_eq!(written["db-column"], json!(["state:compression=lz4,cache=50,bloom=0"]));
+		assert_eq!(written["pruning"], json!("1000"));
+		assert_eq!(written["bodies-pruning"], json!("archive"));
+		assert_eq!(written["justifications-pruning"], json!("AuthoritySetChanges"));
+		assert_eq!(written["changes-tries-pruning"], json!("64"));
+		assert_eq!(written["node-key-type"], json!("Ed25519"));
+		assert_eq!(written["node-key-file"], json!("/tmp/node-key"));
+		assert_eq!(written["rpc-methods"], json!("Safe"));
+		assert_eq!(written["execution"], json!("Wasm"));
+		assert_eq!(written["telemetry-url"], json!(["ws://telemetry 1"]));
+		assert_eq!(written["alice"], json!(true));
+		assert!(written.get("execution-syncing").is_none());
+		assert!(written.get("node-key").is_none());
+		assert!(written.get("config").is_none());
+
+		let secrets = options(&run_cmd(args(&[
+			"substrate", "--node-key=0000000000000000000000000000000000000000000000000000000000000001",
+			"--password=secret",
+		]))).unwrap();
+		assert!(secrets.get("node-key").is_none());
+		assert!(secrets.get("password").is_none());
+
+		let rendered = EffectiveConfig(written.clone()).render(false).unwrap();
+		assert_eq!(toml::from_str::<Map<String, Value>>(&rendered).unwrap(), written);
+
+		let dir = TempDir::new("config-file").unwrap();
+		let path = dir.path().join("node.toml");
+		fs::write(&path, rendered).unwrap();
+		let config = format!("--config={}", path.display());
+		assert_eq!(options(&run_cmd(expand_args(args(&["substrate", &config])).unwrap())).unwrap(), written);
+	}
+}
@@ -0,0 +1,277 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implementation of the `key` subcommands.
+
+use std::{fs::{self, File}, io::{Write, stdin, stdout}, path::Path};
+
+use keystore::{EncryptedKey, KeyScheme, Store};
+use primitives::{crypto::{KeyTypeId, Pair}, ed25519, sr25519, traits::BareCryptoStore};
+use service::SessionKeyStatus;
+
+use crate::error;
+use crate::params::{CryptoScheme, KeyExportCmd, KeyImportCmd, KeyRemoveCmd};
+
+fn key_type_name(key_type: KeyTypeId) -> String {
+	String::from_utf8_lossy(&key_type.0).into_owned()
+}
+
+fn parse_public(public: &str) -> error::Result<Vec<u8>> {
+	let public = if public.starts_with("0x") { &public[2..] } else { public };
+	hex::decode(public).map_err(|e| error::Error::Input(format!("Invalid public key: {}", e)))
+}
+
+fn keystore_error(e: keystore::Error) -> error::Error {
+	error::Error::Other(format!("Keystore error: {}", e))
+}
+
+fn read_export_password(file: Option<&Path>, confirm: bool) -> error::Result<String> {
+	if let Some(file) = file {
+		let mut password = fs::read_to_string(file)?;
+		// Files written by editors or `echo` end with a newline that isn't part of the password.
+		if password.ends_with('\n') {
+			password.pop();
+			if password.ends_with('\r') {
+				password.pop();
+			}
+		}
+		return Ok(password);
+	}
+
+	let read = |prompt| rpassword::read_password_from_tty(Some(prompt))
+		.map_err(|e| error::Error::Input(format!("{:?}", e)));
+	let password = read("Export password: ")?;
+	if confirm && read("Repeat export password: ")? != password {
+		return Err(error::Error::Input("Passwords don't match".into()));
+	}
+	Ok(password)
+}
+
+/// Returns the keys of the keystore, only those of `key_type` if given.
+pub fn keys(store: &Store, key_type: Option<KeyTypeId>) -> error::Result<Vec<(KeyTypeId, Vec<u8>)>> {
+	Ok(store.keys()
+		.map_err(keystore_error)?
+		.into_iter()
+		.filter(|(ty, _)| key_type.map_or(true, |key_type| *ty == key_type))
+		.collect())
+}
+
+/// Prints the keys of the keystore.
+pub fn list(store: &Store, key_type: Option<KeyTypeId>) -> error::Result<()> {
+	for (key_type, public) in keys(store, key_type)? {
+		println!("{} 0x{}", key_type_name(key_type), hex::encode(public));
+	}
+	Ok(())
+}
+
+/// Derives the key with the keystore password and checks its public key before inserting it.
+fn insert<P: Pair>(
+	store: &Store,
+	key_type: KeyTypeId,
+	suri: &str,
+	expected: Option<&[u8]>,
+) -> error::Result<Vec<u8>> {
+	let pair = P::from_string(suri, store.password())
+		.map_err(|_| error::Error::Input("Invalid secret URI".into()))?;
+	let public = pair.public().as_ref().to_vec();
+	if expected.map_or(false, |expected| expected != &public[..]) {
+		return Err(error::Error::Input(
+			"The public key of the imported key doesn't match the exported one, check that the \
+			keystore password is the one of the exporting keystore".into()
+		));
+	}
+
+	store.insert_by_type::<P>(key_type, suri).map_err(keystore_error)?;
+	Ok(public)
+}
+
+/// Inserts a key given by its secret URI, or exported by `export`, into the keystore.
+pub fn import(store: &Store, cmd: KeyImportCmd) -> error::Result<()> {
+	let password_file = cmd.export_password_filename.as_ref().map(|file| file.as_path());
+	let (key_type, scheme, suri, expected) = match (cmd.json, cmd.suri, cmd.key_type) {
+		(Some(json), _, _) => {
+			let exported: EncryptedKey = serde_json::from_reader(File::open(json)?)
+				.map_err(|e| error::Error::Input(format!("Invalid exported key: {}", e)))?;
+			let password = read_export_password(password_file, false)?;
+			let key_type = exported.key_type().map_err(keystore_error)?;
+			let public = exported.public().map_err(keystore_error)?;
+			let suri = exported.decrypt(&password).map_err(keystore_error)?;
+			(key_type, exported.scheme, suri, Some(public))
+		},
+		(None, Some(suri), Some(key_type)) => {
+			let scheme: KeyScheme = cmd.scheme.unwrap_or(CryptoScheme::Sr25519).into();
+			(key_type, scheme, suri, None)
+		},
+		_ => return Err(error::Error::Input(
+			"Either `--suri` and `--key-type` or `--json` is required".into()
+		)),
+	};
+
+	let expected = expected.as_ref().map(|public| &public[..]);
+	let public = match scheme {
+		KeyScheme::Sr25519 => insert::<sr25519::Pair>(store, key_type, &suri, expected)?,
+		KeyScheme::Ed25519 => insert::<ed25519::Pair>(store, key_type, &suri, expected)?,
+	};
+	println!("Imported {} 0x{}", key_type_name(key_type), hex::encode(public));
+	Ok(())
+}
+
+/// Derives the key of the given scheme with the keystore password, returning its public key.
+fn derive_public<P: Pair>(store: &Store, suri: &str) -> error::Result<Vec<u8>> {
+	P::from_string(suri, store.password())
+		.map(|pair| pair.public().as_ref().to_vec())
+		.map_err(|_| error::Error::Input("Invalid secret URI".into()))
+}
+
+/// Writes a key of the keystore, encrypted with a password, to the output or stdout.
+pub fn export(store: &Store, cmd: KeyExportCmd) -> error::Result<()> {
+	let public = parse_public(&cmd.public)?;
+	let suri = store.key_phrase_by_type(&public, cmd.key_type).map_err(keystore_error)?;
+	let scheme: KeyScheme = cmd.scheme.into();
+	let derived = match scheme {
+		KeyScheme::Sr25519 => derive_public::<sr25519::Pair>(store, &suri)?,
+		KeyScheme::Ed25519 => derive_public::<ed25519::Pair>(store, &suri)?,
+	};
+	if derived != public {
+		return Err(error::Error::Input(format!("The key isn't a {:?} key, check `--scheme`", scheme)));
+	}
+	let password_file = cmd.export_password_filename.as_ref().map(|file| file.as_path());
+	let password = read_export_password(password_file, true)?;
+
+	let exported = EncryptedKey::encrypt(cmd.key_type, &public, scheme, &suri, &password);
+	let json = serde_json::to_string_pretty(&exported)
+		.map_err(|e| error::Error::Other(format!("Failed to serialize the key: {}", e)))?;
+	match cmd.output {
+		Some(output) => fs::write(output, json)?,
+		None => println!("{}", json),
+	}
+	Ok(())
+}
+
+/// Removes a key from the keystore.
+pub fn remove(store: &Store, cmd: KeyRemoveCmd) -> error::Result<()> {
+	let public = parse_public(&cmd.public)?;
+	let name = format!("{} 0x{}", key_type_name(cmd.key_type), hex::encode(&public));
+
+	if !cmd.yes {
+		print!("Are you sure to remove the key {}? [y/N]: ", name);
+		stdout().flush().expect("failed to flush stdout");
+
+		let mut input = String::new();
+		stdin().read_line(&mut input)?;
+		match input.trim().chars().nth(0) {
+			Some('y') | Some('Y') => {},
+			_ => {
+				println!("Aborted");
+				return Ok(());
+			},
+		}
+	}
+
+	store.remove_by_type(&public, cmd.key_type).map_err(keystore_error)?;
+	println!("{} removed.", name);
+	Ok(())
+}
+
+/// Prints the on-chain status of session keys.
+pub fn print_session_keys_status(statuses: &[SessionKeyStatus]) {
+	for status in statuses {
+		let key = format!("{} 0x{}", key_type_name(status.key_type), hex::encode(&status.public));
+		match &status.owner {
+			Some(owner) => println!(
+				"{}: registered for validator 0x{}, {}",
+				key,
+				hex::encode(owner),
+				if status.active {
+					"in use in the current session"
+				} else if status.queued {
+					"queued for the next session"
+				} else {
+					"not in use"
+				},
+			),
+			None => println!("{}: not registered", key),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use structopt::StructOpt;
+	use tempdir::TempDir;
+	use crate::params::KeyCmd;
+
+	fn run(args: &[&str]) -> error::Result<()> {
+		let cmd = KeyCmd::from_iter(Some("key").into_iter().chain(args.iter().cloned()));
+		let params = cmd.keystore_params();
+		let password = params.password.clone().map(Into::into);
+		let store = Store::open(params.keystore_path.clone().unwrap(), password).unwrap();
+		let store = store.read();
+		match cmd {
+			KeyCmd::Import(cmd) => import(&store, cmd),
+			KeyCmd::Export(cmd) => export(&store, cmd),
+			_ => unreachable!(),
+		}
+	}
+
+	fn public_keys(keystore: &Path) -> Vec<(KeyTypeId, Vec<u8>)> {
+		let store = Store::open(keystore, None).unwrap();
+		let store = store.read();
+		keys(&store, None).unwrap()
+	}
+
+	#[test]
+	fn exported_key_is_imported_with_the_same_keystore_password() {
+		let dir = TempDir::new("key-export").unwrap();
+		let path = |name: &str| dir.path().join(name).to_str().unwrap().to_owned();
+		let (exporting, importing, other) = (path("exporting"), path("importing"), path("other"));
+		let (json, password_file) = (path("key.json"), path("export-password"));
+		fs::write(&password_file, "export password\n").unwrap();
+
+		run(&[
+			"import", "--suri", "//Alice", "--key-type", "babe",
+			"--keystore-path", &exporting, "--password", "keystore password",
+		]).unwrap();
+		let exported = public_keys(exporting.as_ref());
+		assert_eq!(exported.len(), 1);
+		let public = format!("0x{}", hex::encode(&exported[0].1));
+
+		run(&[
+			"export", "--key-type", "babe", "--public", &public, "--output", &json,
+			"--export-password-filename", &password_file,
+			"--keystore-path", &exporting, "--password", "keystore password",
+		]).unwrap();
+
+		// The trailing newline of the password file is ignored.
+		fs::write(&password_file, "export password").unwrap();
+		run(&[
+			"import", "--json", &json, "--export-password-filename", &password_file,
+			"--keystore-path", &importing, "--password", "keystore password",
+		]).unwrap();
+		assert_eq!(public_keys(importing.as_ref()), exported);
+
+		// The key derives to another public key with another keystore password.
+		match run(&[
+			"import", "--json", &json, "--export-password-filename", &password_file,
+			"--keystore-path", &other, "--password", "other password",
+		]) {
+			Err(error::Error::Input(e)) => assert!(e.contains("doesn't match"), "{}", e),
+			_ => panic!("the key is only imported with the password of the exporting keystore"),
+		}
+		assert!(public_keys(other.as_ref()).is_empty());
+	}
+}
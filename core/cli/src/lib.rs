@@ -23,14 +23,17 @@
 mod traits;
 mod params;
 mod execution_strategy;
+mod config_file;
+mod key;
 pub mod error;
 pub mod informant;
 
 use client::ExecutionStrategies;
 use service::{
-	config::{Configuration, DatabaseConfig},
-	ServiceBuilderExport, ServiceBuilderImport, ServiceBuilderRevert,
-	RuntimeGenesis, ChainSpecExtension, PruningMode, ChainSpec,
+	config::{Configuration, DatabaseConfig, PrometheusConfig},
+	ServiceBuilderExport, ServiceBuilderImport, ServiceBuilderRevert, ServiceBuilderCheckDb,
+	ServiceBuilderDryRunUpgrade, ServiceBuilderReplayBlocks, ServiceBuilderSessionKeys,
+	RuntimeGenesis, ChainSpecExtension, PruningMode, ChainSpec, SessionKeyStatus,
 };
 use network::{
 	self,
@@ -39,11 +42,12 @@ use network::{
 		NetworkConfiguration, TransportConfig, NonReservedPeerMode, NodeKeyConfig, build_multiaddr
 	},
 };
-use primitives::H256;
+use primitives::{H256, crypto::KeyTypeId, hexdisplay::HexDisplay};
+use sr_primitives::traits::{Block as BlockT, NumberFor};
 
 use std::{
 	io::{Write, Read, Seek, Cursor, stdin, stdout, ErrorKind}, iter, fs::{self, File},
-	net::{Ipv4Addr, SocketAddr}, path::{Path, PathBuf}, str::FromStr,
+	net::{Ipv4Addr, SocketAddr}, path::{Path, PathBuf}, str::FromStr, sync::RwLock,
 };
 
 use names::{Generator, Name};
@@ -52,7 +56,8 @@ use structopt::{StructOpt, clap::AppSettings};
 #[doc(hidden)]
 pub use structopt::clap::App;
 use params::{
-	RunCmd, PurgeChainCmd, RevertCmd, ImportBlocksCmd, ExportBlocksCmd, BuildSpecCmd,
+	RunCmd, PurgeChainCmd, RevertCmd, CheckDbCmd, DryRunUpgradeCmd, ReplayBlocksCmd, PrintConfigCmd,
+	ImportBlocksCmd, ExportBlocksCmd, BuildSpecCmd, KeyCmd, KeystoreParams,
 	NetworkConfigurationParams, MergeParameters, TransactionPoolParams,
 	NodeKeyParams, NodeKeyType, Cors,
 };
@@ -206,6 +211,11 @@ where
 
 	panic_handler::set(version.support_url, &full_version);
 
+	let args = args.into_iter().map(Into::into).collect::<Vec<std::ffi::OsString>>();
+	let args = config_file::expand_args(args).unwrap_or_else(|e|
+		structopt::clap::Error::with_description(&e, structopt::clap::ErrorKind::InvalidValue).exit()
+	);
+
 	let matches = CoreParams::<CC, RP>::clap()
 		.name(version.executable_name)
 		.author(version.author)
@@ -239,6 +249,21 @@ where
 		params::CoreParams::Revert(params) => ParseAndPrepare::RevertChain(
 			ParseAndPrepareRevert { params, version }
 		),
+		params::CoreParams::CheckDb(params) => ParseAndPrepare::CheckDb(
+			ParseAndPrepareCheckDb { params, version }
+		),
+		params::CoreParams::DryRunUpgrade(params) => ParseAndPrepare::DryRunUpgrade(
+			ParseAndPrepareDryRunUpgrade { params, version }
+		),
+		params::CoreParams::ReplayBlocks(params) => ParseAndPrepare::ReplayBlocks(
+			ParseAndPrepareReplayBlocks { params, version }
+		),
+		params::CoreParams::PrintConfig(params) => ParseAndPrepare::PrintConfig(
+			ParseAndPreparePrintConfig { params, impl_name, version }
+		),
+		params::CoreParams::Key(params) => ParseAndPrepare::Key(
+			ParseAndPrepareKey { params, version }
+		),
 		params::CoreParams::Custom(params) => ParseAndPrepare::CustomCommand(params),
 	}
 }
@@ -269,6 +294,16 @@ pub enum ParseAndPrepare<'a, CC, RP> {
 	PurgeChain(ParseAndPreparePurge<'a>),
 	/// Command ready to revert the chain.
 	RevertChain(ParseAndPrepareRevert<'a>),
+	/// Command ready to check the database.
+	CheckDb(ParseAndPrepareCheckDb<'a>),
+	/// Command ready to dry run a runtime upgrade.
+	DryRunUpgrade(ParseAndPrepareDryRunUpgrade<'a>),
+	/// Command ready to replay blocks.
+	ReplayBlocks(ParseAndPrepareReplayBlocks<'a>),
+	/// Command ready to print the configuration.
+	PrintConfig(ParseAndPreparePrintConfig<'a>),
+	/// Command ready to manage the keystore.
+	Key(ParseAndPrepareKey<'a>),
 	/// An additional custom command passed to `parse_and_prepare`.
 	CustomCommand(CC),
 }
@@ -352,6 +387,39 @@ impl<'a> ParseAndPrepareBuildSpec<'a> {
 	}
 }
 
+/// Command ready to print the configuration.
+pub struct ParseAndPreparePrintConfig<'a> {
+	params: PrintConfigCmd,
+	impl_name: &'static str,
+	version: &'a VersionInfo,
+}
+
+impl<'a> ParseAndPreparePrintConfig<'a> {
+	/// Runs the command and prints the configuration the node would run with.
+	pub fn run<C, G, S, E>(
+		self,
+		spec_factory: S
+	) -> error::Result<()> where
+		S: FnOnce(&str) -> Result<Option<ChainSpec<G, E>>, String>,
+		C: Default,
+		G: RuntimeGenesis,
+		E: ChainSpecExtension,
+	{
+		let cli = self.params.run;
+		let chain = get_chain_key(&cli.shared_params);
+		let base_path = base_path(&cli.shared_params, self.version);
+		let config = create_run_node_config::<C, G, E, _>(
+			cli.clone(), spec_factory, self.impl_name, self.version,
+		)?;
+
+		let effective = config_file::EffectiveConfig::new(&cli, chain, &base_path, &config)
+			.map_err(error::Error::Other)?;
+		println!("{}", effective.render(self.params.json).map_err(error::Error::Other)?);
+
+		Ok(())
+	}
+}
+
 /// Command ready to export the chain.
 pub struct ParseAndPrepareExport<'a> {
 	params: ExportBlocksCmd,
@@ -516,6 +584,12 @@ impl<'a> ParseAndPrepareRevert<'a> {
 		G: RuntimeGenesis,
 		E: ChainSpecExtension,
 	{
+		if self.params.unsafe_finalized {
+			return Err(error::Error::Input(
+				"Reverting finalized blocks is not supported by this node".into()
+			));
+		}
+
 		let config = create_config_with_db_path(
 			spec_factory, &self.params.shared_params, self.version
 		)?;
@@ -523,6 +597,369 @@ impl<'a> ParseAndPrepareRevert<'a> {
 		builder(config)?.revert_chain(blocks.into())?;
 		Ok(())
 	}
+
+	/// Runs the command and reverts the chain, allowing finalized blocks to be reverted
+	/// when `--unsafe-finalized` is given. In that case `revert_aux` is called with the
+	/// target block before any block is removed and returns the node's consensus data
+	/// rewound to it, which is written along with the reverted blocks. It must not write
+	/// anything itself, so that every consensus engine is checked before the chain changes.
+	pub fn run_with_builder_and_revert_aux<C, G, E, F, B, S, R>(
+		self,
+		builder: F,
+		revert_aux: R,
+		spec_factory: S
+	) -> error::Result<()> where
+		S: FnOnce(&str) -> Result<Option<ChainSpec<G, E>>, String>,
+		F: FnOnce(Configuration<C, G, E>) -> Result<B, error::Error>,
+		B: ServiceBuilderRevert,
+		R: FnOnce(
+			&B,
+			<B::Block as BlockT>::Hash,
+			NumberFor<B::Block>,
+		) -> Result<Vec<(Vec<u8>, Option<Vec<u8>>)>, service::Error>,
+		C: Default,
+		G: RuntimeGenesis,
+		E: ChainSpecExtension,
+	{
+		let config = create_config_with_db_path(
+			spec_factory, &self.params.shared_params, self.version
+		)?;
+		let blocks = self.params.num;
+		let builder = builder(config)?;
+
+		if self.params.unsafe_finalized {
+			builder.unsafe_revert_chain(
+				blocks.into(),
+				|hash, number| revert_aux(&builder, hash, number),
+			)?;
+		} else {
+			builder.revert_chain(blocks.into())?;
+		}
+		Ok(())
+	}
+}
+
+/// Command ready to check the database.
+pub struct ParseAndPrepareCheckDb<'a> {
+	params: CheckDbCmd,
+	version: &'a VersionInfo,
+}
+
+impl<'a> ParseAndPrepareCheckDb<'a> {
+	/// Runs the command, checks the database and offers to repair it.
+	pub fn run_with_builder<C, G, E, F, B, S>(
+		self,
+		builder: F,
+		spec_factory: S
+	) -> error::Result<()> where
+		S: FnOnce(&str) -> Result<Option<ChainSpec<G, E>>, String>,
+		F: FnOnce(Configuration<C, G, E>) -> Result<B, error::Error>,
+		B: ServiceBuilderCheckDb,
+		C: Default,
+		G: RuntimeGenesis,
+		E: ChainSpecExtension,
+	{
+		let config = create_config_with_db_path(
+			spec_factory, &self.params.shared_params, self.version
+		)?;
+		let builder = builder(config)?;
+		let report = builder.check_db(self.params.blocks.into())?;
+
+		println!(
+			"Checked {} blocks below #{} (finalized #{}).",
+			report.checked_blocks, report.best, report.finalized,
+		);
+		if report.is_consistent() {
+			println!("No inconsistency found.");
+			return Ok(());
+		}
+		for inconsistency in &report.inconsistencies {
+			match &inconsistency.block {
+				Some(number) => println!("#{}: {}", number, inconsistency.description),
+				None => println!("{}", inconsistency.description),
+			}
+		}
+
+		let last_consistent = match report.last_consistent {
+			Some(number) if number < report.best => number,
+			_ => return Err(error::Error::Other(
+				"The inconsistencies can't be repaired by reverting, purge the chain instead.".into()
+			)),
+		};
+		if last_consistent < report.finalized {
+			return Err(error::Error::Other(format!(
+				"Repairing the database requires reverting to #{}, below the finalized block #{} \
+				which can't be reverted, purge the chain instead.",
+				last_consistent,
+				report.finalized,
+			)));
+		}
+
+		let blocks = report.best - last_consistent;
+		if !self.params.yes {
+			print!("Revert {} blocks to #{}? [y/N]: ", blocks, last_consistent);
+			stdout().flush().expect("failed to flush stdout");
+
+			let mut input = String::new();
+			stdin().read_line(&mut input)?;
+			let input = input.trim();
+
+			match input.chars().nth(0) {
+				Some('y') | Some('Y') => {},
+				_ => {
+					println!("Aborted");
+					return Ok(());
+				},
+			}
+		}
+
+		builder.revert_chain(blocks)?;
+		Ok(())
+	}
+}
+
+/// Command ready to dry run a runtime upgrade.
+pub struct ParseAndPrepareDryRunUpgrade<'a> {
+	params: DryRunUpgradeCmd,
+	version: &'a VersionInfo,
+}
+
+impl<'a> ParseAndPrepareDryRunUpgrade<'a> {
+	/// Runs the command and prints how the upgraded runtime behaves compared to the current one.
+	pub fn run_with_builder<C, G, E, F, B, S>(
+		self,
+		builder: F,
+		spec_factory: S
+	) -> error::Result<()> where
+		S: FnOnce(&str) -> Result<Option<ChainSpec<G, E>>, String>,
+		F: FnOnce(Configuration<C, G, E>) -> Result<B, error::Error>,
+		B: ServiceBuilderDryRunUpgrade,
+		C: Default,
+		G: RuntimeGenesis,
+		E: ChainSpecExtension,
+	{
+		let mut config = create_config_with_db_path(
+			spec_factory, &self.params.shared_params, self.version
+		)?;
+		config.wasm_method = self.params.wasm_method.into();
+		config.default_heap_pages = self.params.default_heap_pages.map(Into::into);
+
+		let at = H256::from_str(self.params.at.trim_start_matches("0x"))
+			.map_err(|e| error::Error::Input(format!("Invalid block hash: {}", e)))?;
+		let mut hash = <<B::Block as BlockT>::Hash as Default>::default();
+		if hash.as_ref().len() != at.as_bytes().len() {
+			return Err(error::Error::Input("Invalid block hash length".into()));
+		}
+		hash.as_mut().copy_from_slice(at.as_bytes());
+
+		let code = fs::read(&self.params.wasm)?;
+		let report = builder(config)?.dry_run_upgrade(&code, hash, self.params.blocks)?;
+
+		let display_version = |version: &Option<client::runtime_api::RuntimeVersion>| match version {
+			Some(version) => format!("{}-{}", version.spec_name, version.spec_version),
+			None => "unknown version".into(),
+		};
+		println!(
+			"Runtime {} upgraded to {}, replayed {} blocks.",
+			display_version(&report.current_version),
+			display_version(&report.upgraded_version),
+			report.blocks.len(),
+		);
+
+		for block in &report.blocks {
+			let weight = match block.weight {
+				(Some(current), Some(upgraded)) if current != upgraded =>
+					format!(", weight {} -> {}", current, upgraded),
+				_ => String::new(),
+			};
+			if block.failed.is_empty() && block.changed.is_empty() && weight.is_empty() {
+				continue;
+			}
+
+			println!(
+				"#{}: {} failed extrinsics, {} changed outcomes{}",
+				block.number, block.failed.len(), block.changed.len(), weight,
+			);
+			for (index, current, upgraded) in &block.changed {
+				println!("  extrinsic {}: {} -> {}", index, current, upgraded);
+			}
+		}
+
+		if let Some((number, error)) = &report.current_error {
+			eprintln!("The current runtime failed to execute block #{}: {}", number, error);
+		}
+		if let Some((number, error)) = &report.upgraded_error {
+			eprintln!("The upgraded runtime failed to execute block #{}: {}", number, error);
+		}
+
+		println!("{} storage entries differ after the upgrade.", report.storage_diff.len());
+		for ((child, key), current, upgraded) in &report.storage_diff {
+			let display = |value: &Option<Vec<u8>>| match value {
+				Some(value) => format!("0x{}", HexDisplay::from(value)),
+				None => "none".into(),
+			};
+			let key = match child {
+				Some(child) => format!("0x{} of child trie 0x{}", HexDisplay::from(key), HexDisplay::from(child)),
+				None => format!("0x{}", HexDisplay::from(key)),
+			};
+			println!("  {}: {} -> {}", key, display(current), display(upgraded));
+		}
+
+		if report.upgraded_error.is_some() {
+			return Err(error::Error::Other("The upgraded runtime failed to execute the chain".into()));
+		}
+		Ok(())
+	}
+}
+
+/// Command ready to replay blocks.
+pub struct ParseAndPrepareReplayBlocks<'a> {
+	params: ReplayBlocksCmd,
+	version: &'a VersionInfo,
+}
+
+impl<'a> ParseAndPrepareReplayBlocks<'a> {
+	/// Runs the command and prints the blocks whose replay didn't yield the expected results.
+	pub fn run_with_builder<C, G, E, F, B, S>(
+		self,
+		builder: F,
+		spec_factory: S
+	) -> error::Result<()> where
+		S: FnOnce(&str) -> Result<Option<ChainSpec<G, E>>, String>,
+		F: FnOnce(Configuration<C, G, E>) -> Result<B, error::Error>,
+		B: ServiceBuilderReplayBlocks,
+		C: Default,
+		G: RuntimeGenesis,
+		E: ChainSpecExtension,
+	{
+		let mut config = create_config_with_db_path(
+			spec_factory, &self.params.shared_params, self.version
+		)?;
+		config.wasm_method = self.params.wasm_method.into();
+		config.default_heap_pages = self.params.default_heap_pages.map(Into::into);
+
+		let report = builder(config)?.replay_blocks(
+			self.params.from.into(),
+			self.params.to.into(),
+			self.params.execution.into(),
+		)?;
+
+		let display_value = |value: &Option<Vec<u8>>| match value {
+			Some(value) => format!("0x{}", HexDisplay::from(value)),
+			None => "none".into(),
+		};
+		let display_key = |(child, key): &(Option<Vec<u8>>, Vec<u8>)| match child {
+			Some(child) => format!("0x{} of child trie 0x{}", HexDisplay::from(key), HexDisplay::from(child)),
+			None => format!("0x{}", HexDisplay::from(key)),
+		};
+		for mismatch in &report.mismatches {
+			println!("#{} ({}):", mismatch.number, mismatch.hash);
+			for outcome in &mismatch.outcomes {
+				let runtime = if outcome.native { "native" } else { "Wasm" };
+				if let Some(error) = &outcome.error {
+					println!("  {} execution failed: {}", runtime, error);
+				}
+				if let Some(root) = &outcome.root_mismatch {
+					println!(
+						"  {} storage root {} doesn't match the header's {}",
+						runtime, root, mismatch.state_root,
+					);
+				}
+				if let Some((key, expected, value)) = &outcome.divergence {
+					println!(
+						"  {} first diverging key {}: {} (expected {})",
+						runtime, display_key(key), display_value(value), display_value(expected),
+					);
+				}
+			}
+			if let Some((key, native, wasm)) = &mismatch.native_wasm_divergence {
+				println!(
+					"  native and Wasm first diverge at key {}: {} (native) vs {} (Wasm)",
+					display_key(key), display_value(native), display_value(wasm),
+				);
+			}
+		}
+
+		println!("Replayed {} blocks, {} mismatched.", report.replayed, report.mismatches.len());
+		if !report.mismatches.is_empty() {
+			return Err(error::Error::Other(
+				format!("{} replayed blocks mismatched", report.mismatches.len())
+			));
+		}
+		Ok(())
+	}
+}
+
+/// Command ready to manage the keystore.
+pub struct ParseAndPrepareKey<'a> {
+	params: KeyCmd,
+	version: &'a VersionInfo,
+}
+
+impl<'a> ParseAndPrepareKey<'a> {
+	/// Runs the command on the keystore of the chain, for nodes whose runtime can't look up
+	/// session keys. Checking the session keys on-chain fails.
+	pub fn run<C, G, E, S>(self, spec_factory: S) -> error::Result<()> where
+		S: FnOnce(&str) -> Result<Option<ChainSpec<G, E>>, String>,
+		C: Default,
+		G: RuntimeGenesis,
+		E: ChainSpecExtension,
+	{
+		self.run_with(spec_factory, |_: Configuration<C, G, E>, _| Err(error::Error::Input(
+			"This node can't look up session keys on-chain".into()
+		)))
+	}
+
+	/// Runs the command on the keystore of the chain. The builder is only used to check the
+	/// session keys on-chain.
+	pub fn run_with_builder<C, G, E, F, B, S>(
+		self,
+		builder: F,
+		spec_factory: S
+	) -> error::Result<()> where
+		S: FnOnce(&str) -> Result<Option<ChainSpec<G, E>>, String>,
+		F: FnOnce(Configuration<C, G, E>) -> Result<B, error::Error>,
+		B: ServiceBuilderSessionKeys,
+		C: Default,
+		G: RuntimeGenesis,
+		E: ChainSpecExtension,
+	{
+		self.run_with(spec_factory, |config, keys| Ok(builder(config)?.session_keys_status(keys)?))
+	}
+
+	fn run_with<C, G, E, S, K>(self, spec_factory: S, session_keys_status: K) -> error::Result<()> where
+		S: FnOnce(&str) -> Result<Option<ChainSpec<G, E>>, String>,
+		K: FnOnce(Configuration<C, G, E>, &[(KeyTypeId, Vec<u8>)]) -> error::Result<Vec<SessionKeyStatus>>,
+		C: Default,
+		G: RuntimeGenesis,
+		E: ChainSpecExtension,
+	{
+		let mut config = create_config_with_db_path(
+			spec_factory, self.params.shared_params(), self.version
+		)?;
+		let keystore_params = self.params.keystore_params();
+		fill_config_keystore_password(&mut config, keystore_params)?;
+		config.keystore_path = keystore_params.keystore_path.clone()
+			.or_else(|| config.in_chain_config_dir(DEFAULT_KEYSTORE_CONFIG_PATH));
+
+		let keystore_path = config.keystore_path.clone().expect("We provided a base_path.");
+		let store = keystore::Store::open(keystore_path, config.keystore_password.clone())
+			.map_err(|e| format!("Failed to open the keystore: {}", e))?;
+		let store = store.read();
+
+		match self.params {
+			KeyCmd::List(cmd) => key::list(&store, cmd.key_type),
+			KeyCmd::Import(cmd) => key::import(&store, cmd),
+			KeyCmd::Export(cmd) => key::export(&store, cmd),
+			KeyCmd::Remove(cmd) => key::remove(&store, cmd),
+			KeyCmd::CheckSession(cmd) => {
+				let keys = key::keys(&store, cmd.key_type)?;
+				let statuses = session_keys_status(config, &keys)?;
+				key::print_session_keys_status(&statuses);
+				Ok(())
+			},
+		}
+	}
 }
 
 /// Create a `NodeKeyConfig` from the given `NodeKeyParams` in the context
@@ -641,7 +1078,7 @@ fn input_keystore_password() -> Result<String, String> {
 /// Fill the password field of the given config instance.
 fn fill_config_keystore_password<C, G, E>(
 	config: &mut service::Configuration<C, G, E>,
-	cli: &RunCmd,
+	cli: &KeystoreParams,
 ) -> Result<(), String> {
 	config.keystore_password = if cli.password_interactive {
 		Some(input_keystore_password()?.into())
@@ -669,7 +1106,7 @@ where
 	let base_path = base_path(&cli.shared_params, &version);
 	let mut config = service::Configuration::default_with_spec_and_base_path(spec.clone(), Some(base_path));
 
-	fill_config_keystore_password(&mut config, &cli)?;
+	fill_config_keystore_password(&mut config, &cli.keystore_params)?;
 
 	config.impl_name = impl_name;
 	config.impl_commit = version.commit;
@@ -691,11 +1128,14 @@ where
 		)?
 	}
 
-	config.keystore_path = cli.keystore_path.or_else(|| config.in_chain_config_dir(DEFAULT_KEYSTORE_CONFIG_PATH));
+	config.keystore_path = cli.keystore_params.keystore_path
+		.or_else(|| config.in_chain_config_dir(DEFAULT_KEYSTORE_CONFIG_PATH));
 
 	config.database = DatabaseConfig::Path {
 		path: config.in_chain_config_dir(DEFAULT_DB_CONFIG_PATH).expect("We provided a base_path."),
 		cache_size: cli.database_cache_size,
+		backend: cli.shared_params.database.clone().into(),
+		columns: database_columns(&cli.shared_params)?,
 	};
 	config.state_cache_size = cli.state_cache_size;
 
@@ -736,6 +1176,13 @@ where
 		},
 	};
 
+	let default_retention = service::config::RetentionPolicy::default();
+	config.retention = service::config::RetentionPolicy {
+		bodies: cli.bodies_pruning.unwrap_or(default_retention.bodies),
+		justifications: cli.justifications_pruning.into(),
+		changes_tries: cli.changes_tries_pruning.unwrap_or(default_retention.changes_tries),
+	};
+
 	config.wasm_method = cli.wasm_method.into();
 
 	let exec = cli.execution_strategies;
@@ -755,8 +1202,12 @@ where
 		(params::OffchainWorkerEnabled::WhenValidating, _) => false,
 	};
 
+	config.reload_config = cli.config;
+	config.log_filter_reload = Some(std::sync::Arc::new(reload_log_filter));
+
 	config.roles = role;
 	config.disable_grandpa = cli.no_grandpa;
+	config.grandpa_voting_rules = cli.grandpa_voting_rules;
 
 	let client_id = config.client_id();
 	fill_network_configuration(
@@ -766,6 +1217,8 @@ where
 		client_id,
 		is_dev,
 	)?;
+	config.network.sentry_nodes = cli.sentry_nodes;
+	config.network.protected_validators = cli.protected_validators;
 
 	fill_transaction_pool_configuration(&mut config, cli.pool_config)?;
 
@@ -785,6 +1238,7 @@ where
 	config.rpc_ws = Some(parse_address(&format!("{}:{}", ws_interface, 9944), cli.ws_port)?);
 
 	config.rpc_ws_max_connections = cli.ws_max_connections;
+	config.rpc_methods = cli.rpc_methods.into();
 	config.rpc_cors = cli.rpc_cors.unwrap_or_else(|| if is_dev {
 		log::warn!("Running in --dev mode, RPC CORS has been disabled.");
 		Cors::All
@@ -799,6 +1253,12 @@ where
 		])
 	}).into();
 
+	if let Some(port) = cli.prometheus_port {
+		let interface: &str = if cli.prometheus_external { "0.0.0.0" } else { "127.0.0.1" };
+		let address = parse_address(&format!("{}:{}", interface, port), None)?;
+		config.prometheus_config = Some(PrometheusConfig::new_with_default_registry(address));
+	}
+
 	// Override telemetry
 	if cli.no_telemetry {
 		config.telemetry_endpoints = None;
@@ -829,11 +1289,21 @@ where
 	config.database = DatabaseConfig::Path {
 		path: config.in_chain_config_dir(DEFAULT_DB_CONFIG_PATH).expect("We provided a base_path."),
 		cache_size: None,
+		backend: cli.database.clone().into(),
+		columns: database_columns(cli)?,
 	};
 
 	Ok(config)
 }
 
+/// The column settings of the database, which only the RocksDB backend supports.
+fn database_columns(cli: &SharedParams) -> error::Result<service::config::ColumnsConfig> {
+	if let (params::DatabaseBackend::Sled, false) = (&cli.database, cli.database_columns.is_empty()) {
+		return Err(error::Error::Input("`--db-column` isn't supported with `--database=sled`".into()));
+	}
+	Ok(cli.database_columns.iter().cloned().collect())
+}
+
 /// Internal trait used to cast to a dynamic type that implements Read and Seek.
 trait ReadPlusSeek: Read + Seek {}
 
@@ -853,7 +1323,63 @@ fn parse_address(
 	Ok(address)
 }
 
+lazy_static! {
+	/// The logger `ReloadableLogger` forwards to, `None` until `init_logger` registered it.
+	static ref LOGGER: RwLock<Option<env_logger::Logger>> = RwLock::new(None);
+}
+
+/// Global logger whose filter can be changed with `reload_log_filter`.
+struct ReloadableLogger;
+
+impl log::Log for ReloadableLogger {
+	fn enabled(&self, metadata: &log::Metadata) -> bool {
+		LOGGER.read().map(|logger| logger.as_ref().map_or(false, |l| l.enabled(metadata))).unwrap_or(false)
+	}
+
+	fn log(&self, record: &log::Record) {
+		if let Ok(logger) = LOGGER.read() {
+			if let Some(logger) = logger.as_ref() {
+				logger.log(record);
+			}
+		}
+	}
+
+	fn flush(&self) {
+		if let Ok(logger) = LOGGER.read() {
+			if let Some(logger) = logger.as_ref() {
+				logger.flush();
+			}
+		}
+	}
+}
+
 fn init_logger(pattern: &str) {
+	if log::set_boxed_logger(Box::new(ReloadableLogger)).is_err() {
+		info!("Not registering Substrate logger, as there is already a global logger registered!");
+		return;
+	}
+
+	let logger = build_logger(pattern);
+	log::set_max_level(logger.filter());
+	*LOGGER.write().expect("Logger lock is never poisoned; qed") = Some(logger);
+}
+
+/// Replaces the filter of the logger registered by `parse_and_prepare`.
+///
+/// The `pattern` is applied on top of the default filters and `RUST_LOG`, as the `--log` flag.
+pub fn reload_log_filter(pattern: &str) -> Result<(), String> {
+	let mut current = LOGGER.write().map_err(|_| "Logger lock is poisoned".to_string())?;
+	if current.is_none() {
+		return Err("The Substrate logger isn't registered".into());
+	}
+
+	let logger = build_logger(pattern);
+	log::set_max_level(logger.filter());
+	*current = Some(logger);
+	Ok(())
+}
+
+fn build_logger(pattern: &str) -> env_logger::Logger {
 	use ansi_term::Colour;
 
 	let mut builder = env_logger::Builder::new();
@@ -907,9 +1433,7 @@ fn init_logger(pattern: &str) {
 		writeln!(buf, "{}", output)
 	});
 
-	if builder.try_init().is_err() {
-		info!("Not registering Substrate logger, as there is already a global logger registered!");
-	}
+	builder.build()
 }
 
 fn kill_color(s: &str) -> String {
@@ -16,7 +16,10 @@
 
 use crate::traits::{AugmentClap, GetLogFilter};
 
-use std::path::PathBuf;
+use std::{convert::TryFrom, path::PathBuf};
+use primitives::crypto::KeyTypeId;
+use serde::{Serialize, Serializer, ser::SerializeMap};
+use service::config::{ColumnConfig, Compression, KeepBlocks};
 use structopt::{StructOpt, clap::{arg_enum, App, AppSettings, SubCommand, Arg}};
 
 pub use crate::execution_strategy::ExecutionStrategy;
@@ -80,6 +83,66 @@ impl Into<service::config::WasmExecutionMethod> for WasmExecutionMethod {
 	}
 }
 
+arg_enum! {
+	/// Key-value store of the database.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone)]
+	pub enum DatabaseBackend {
+		RocksDb,
+		Sled,
+	}
+}
+
+impl Into<service::config::DatabaseBackend> for DatabaseBackend {
+	fn into(self) -> service::config::DatabaseBackend {
+		match self {
+			DatabaseBackend::RocksDb => service::config::DatabaseBackend::RocksDb,
+			DatabaseBackend::Sled => service::config::DatabaseBackend::Sled,
+		}
+	}
+}
+
+arg_enum! {
+	/// Runtimes to replay blocks with.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone)]
+	pub enum ReplayExecution {
+		Native,
+		Wasm,
+		Both,
+	}
+}
+
+impl Into<service::ReplayExecution> for ReplayExecution {
+	fn into(self) -> service::ReplayExecution {
+		match self {
+			ReplayExecution::Native => service::ReplayExecution::Native,
+			ReplayExecution::Wasm => service::ReplayExecution::Wasm,
+			ReplayExecution::Both => service::ReplayExecution::Both,
+		}
+	}
+}
+
+arg_enum! {
+	/// Which justifications to keep.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone)]
+	pub enum JustificationsPruning {
+		Archive,
+		AuthoritySetChanges,
+	}
+}
+
+impl Into<service::config::KeepJustifications> for JustificationsPruning {
+	fn into(self) -> service::config::KeepJustifications {
+		match self {
+			JustificationsPruning::Archive => service::config::KeepJustifications::All,
+			JustificationsPruning::AuthoritySetChanges =>
+				service::config::KeepJustifications::AuthoritySetChanges,
+		}
+	}
+}
+
 arg_enum! {
 	/// Whether off-chain workers are enabled.
 	#[allow(missing_docs)]
@@ -91,8 +154,49 @@ arg_enum! {
 	}
 }
 
+arg_enum! {
+	/// Which RPC methods are exposed.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone)]
+	pub enum RpcMethods {
+		Auto,
+		Safe,
+		Unsafe,
+	}
+}
+
+impl Into<service::config::RpcMethods> for RpcMethods {
+	fn into(self) -> service::config::RpcMethods {
+		match self {
+			RpcMethods::Auto => service::config::RpcMethods::Auto,
+			RpcMethods::Safe => service::config::RpcMethods::Safe,
+			RpcMethods::Unsafe => service::config::RpcMethods::Unsafe,
+		}
+	}
+}
+
+arg_enum! {
+	/// Signature scheme of a key.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone)]
+	pub enum CryptoScheme {
+		Sr25519,
+		Ed25519,
+	}
+}
+
+impl Into<keystore::KeyScheme> for CryptoScheme {
+	fn into(self) -> keystore::KeyScheme {
+		match self {
+			CryptoScheme::Sr25519 => keystore::KeyScheme::Sr25519,
+			CryptoScheme::Ed25519 => keystore::KeyScheme::Ed25519,
+		}
+	}
+}
+
 /// Shared parameters used by all `CoreParams`.
-#[derive(Debug, StructOpt, Clone)]
+#[derive(Debug, StructOpt, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct SharedParams {
 	/// Specify the chain specification (one of dev, local or staging).
 	#[structopt(long = "chain", value_name = "CHAIN_SPEC")]
@@ -109,6 +213,26 @@ pub struct SharedParams {
 	/// Sets a custom logging filter.
 	#[structopt(short = "l", long = "log", value_name = "LOG_PATTERN")]
 	pub log: Option<String>,
+
+	/// Select the key-value store of the database.
+	#[structopt(
+		long = "database",
+		value_name = "DB",
+		possible_values = &DatabaseBackend::variants(),
+		case_insensitive = true,
+		default_value = "RocksDb"
+	)]
+	#[serde(serialize_with = "crate::config_file::display")]
+	pub database: DatabaseBackend,
+
+	/// Tune a database column, e.g. `state:compression=lz4,cache=50,bloom=10`.
+	///
+	/// `compression` is one of none, snappy, lz4 or zstd, `cache` the share of the database
+	/// cache reserved to the column in percent and `bloom` the bits per key of the bloom
+	/// filter of the column, 0 disabling it. Only supported by the RocksDB backend.
+	#[structopt(long = "db-column", value_name = "COLUMN:SETTINGS", parse(try_from_str = parse_column_config))]
+	#[serde(rename = "db-column", serialize_with = "crate::config_file::column_configs")]
+	pub database_columns: Vec<(String, ColumnConfig)>,
 }
 
 impl GetLogFilter for SharedParams {
@@ -118,7 +242,8 @@ impl GetLogFilter for SharedParams {
 }
 
 /// Parameters used to create the network configuration.
-#[derive(Debug, StructOpt, Clone)]
+#[derive(Debug, StructOpt, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct NetworkConfigurationParams {
 	/// Specify a list of bootnodes.
 	#[structopt(long = "bootnodes", value_name = "URL")]
@@ -169,6 +294,7 @@ pub struct NetworkConfigurationParams {
 
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
+	#[serde(flatten)]
 	pub node_key_params: NodeKeyParams
 }
 
@@ -182,7 +308,8 @@ arg_enum! {
 
 /// Parameters used to create the `NodeKeyConfig`, which determines the keypair
 /// used for libp2p networking.
-#[derive(Debug, StructOpt, Clone)]
+#[derive(Debug, StructOpt, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct NodeKeyParams {
 	/// The secret key to use for libp2p networking.
 	///
@@ -199,6 +326,7 @@ pub struct NodeKeyParams {
 	/// Use of this option should be limited to development and testing. To use
 	/// an externally managed secret key, use `--node-key-file` instead.
 	#[structopt(long = "node-key", value_name = "KEY")]
+	#[serde(skip)]
 	pub node_key: Option<String>,
 
 	/// The type of secret key to use for libp2p networking.
@@ -226,6 +354,7 @@ pub struct NodeKeyParams {
 		case_insensitive = true,
 		default_value = "Ed25519"
 	)]
+	#[serde(serialize_with = "crate::config_file::display")]
 	pub node_key_type: NodeKeyType,
 
 	/// The file from which to read the node's secret key to use for libp2p networking.
@@ -243,7 +372,8 @@ pub struct NodeKeyParams {
 }
 
 /// Parameters used to create the pool configuration.
-#[derive(Debug, StructOpt, Clone)]
+#[derive(Debug, StructOpt, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct TransactionPoolParams {
 	/// Maximum number of transactions in the transaction pool.
 	#[structopt(long = "pool-limit", value_name = "COUNT", default_value = "512")]
@@ -254,7 +384,8 @@ pub struct TransactionPoolParams {
 }
 
 /// Execution strategies parameters.
-#[derive(Debug, StructOpt, Clone)]
+#[derive(Debug, StructOpt, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct ExecutionStrategies {
 	/// The means of execution used when calling into the runtime while syncing blocks.
 	#[structopt(
@@ -264,6 +395,7 @@ pub struct ExecutionStrategies {
 		case_insensitive = true,
 		default_value = "NativeElseWasm"
 	)]
+	#[serde(serialize_with = "crate::config_file::display")]
 	pub execution_syncing: ExecutionStrategy,
 
 	/// The means of execution used when calling into the runtime while importing blocks.
@@ -274,6 +406,7 @@ pub struct ExecutionStrategies {
 		case_insensitive = true,
 		default_value = "NativeElseWasm"
 	)]
+	#[serde(serialize_with = "crate::config_file::display")]
 	pub execution_import_block: ExecutionStrategy,
 
 	/// The means of execution used when calling into the runtime while constructing blocks.
@@ -284,6 +417,7 @@ pub struct ExecutionStrategies {
 		case_insensitive = true,
 		default_value = "Wasm"
 	)]
+	#[serde(serialize_with = "crate::config_file::display")]
 	pub execution_block_construction: ExecutionStrategy,
 
 	/// The means of execution used when calling into the runtime while using an off-chain worker.
@@ -294,6 +428,7 @@ pub struct ExecutionStrategies {
 		case_insensitive = true,
 		default_value = "Native"
 	)]
+	#[serde(serialize_with = "crate::config_file::display")]
 	pub execution_offchain_worker: ExecutionStrategy,
 
 	/// The means of execution used when calling into the runtime while not syncing, importing or constructing blocks.
@@ -304,6 +439,7 @@ pub struct ExecutionStrategies {
 		case_insensitive = true,
 		default_value = "Native"
 	)]
+	#[serde(serialize_with = "crate::config_file::display")]
 	pub execution_other: ExecutionStrategy,
 
 	/// The execution strategy that should be used by all execution contexts.
@@ -320,11 +456,13 @@ pub struct ExecutionStrategies {
 			"execution-syncing",
 		]
 	)]
+	#[serde(serialize_with = "crate::config_file::display_opt")]
 	pub execution: Option<ExecutionStrategy>,
 }
 
 /// The `run` command used to run a node.
-#[derive(Debug, StructOpt, Clone)]
+#[derive(Debug, StructOpt, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct RunCmd {
 	/// Enable validator mode.
 	///
@@ -352,16 +490,49 @@ pub struct RunCmd {
 	)]
 	pub sentry: bool,
 
+	/// Specify the sentry nodes this validator hides behind.
+	///
+	/// The node will only connect to, and accept connections from, these
+	/// nodes. It doesn't look for other nodes and doesn't take part in
+	/// authority discovery, so that its address isn't revealed.
+	#[structopt(
+		long = "sentry-nodes",
+		value_name = "URL",
+		conflicts_with_all = &[ "sentry" ]
+	)]
+	pub sentry_nodes: Vec<String>,
+
+	/// Specify the validators protected by this sentry node.
+	///
+	/// The node will stay connected to them and relay their consensus gossip
+	/// to its other peers as soon as it is received.
+	#[structopt(
+		long = "protected-validators",
+		value_name = "URL",
+		requires = "sentry"
+	)]
+	pub protected_validators: Vec<String>,
+
 	/// Disable GRANDPA voter when running in validator mode, otherwise disables the GRANDPA observer.
 	#[structopt(long = "no-grandpa")]
 	pub no_grandpa: bool,
 
+	/// Voting rule restricting the blocks the GRANDPA voter votes on.
+	///
+	/// Can be passed several times, rules are applied in order. The available rules are
+	/// `before-best-block`, `three-quarters-of-the-unfinalized-chain` and `runtime`, which
+	/// lets the runtime restrict votes. Defaults to the first two.
+	#[structopt(long = "grandpa-voting-rule", value_name = "RULE")]
+	#[serde(rename = "grandpa-voting-rule")]
+	pub grandpa_voting_rules: Vec<String>,
+
 	/// Experimental: Run in light client mode.
 	#[structopt(long = "light")]
 	pub light: bool,
 
 	/// Limit the memory the database cache can use.
 	#[structopt(long = "db-cache", value_name = "MiB")]
+	#[serde(rename = "db-cache")]
 	pub database_cache_size: Option<u32>,
 
 	/// Specify the state cache size.
@@ -392,6 +563,18 @@ pub struct RunCmd {
 	#[structopt(long = "ws-max-connections", value_name = "COUNT")]
 	pub ws_max_connections: Option<usize>,
 
+	/// Serve Prometheus metrics on the given TCP port, at `/metrics`.
+	///
+	/// The exporter is disabled if no port is specified.
+	#[structopt(long = "prometheus-port", value_name = "PORT")]
+	pub prometheus_port: Option<u16>,
+
+	/// Listen to all Prometheus exporter interfaces.
+	///
+	/// Default is local.
+	#[structopt(long = "prometheus-external")]
+	pub prometheus_external: bool,
+
 	/// Specify browser Origins allowed to access the HTTP & WS RPC servers.
 	///
 	/// A comma-separated list of origins (protocol://domain or special `null`
@@ -400,8 +583,24 @@ pub struct RunCmd {
 	/// https://substrate-ui.parity.io origins. When running in --dev mode the
 	/// default is to allow all origins.
 	#[structopt(long = "rpc-cors", value_name = "ORIGINS", parse(try_from_str = parse_cors))]
+	#[serde(serialize_with = "crate::config_file::cors")]
 	pub rpc_cors: Option<Cors>,
 
+	/// RPC methods to expose.
+	///
+	/// `Unsafe` exposes every method. `Safe` leaves out the ones that are unsafe to expose
+	/// publicly, such as `dht_putRecord`. `Auto` behaves like `Unsafe` if the RPC servers only
+	/// listen on local interfaces, and like `Safe` otherwise.
+	#[structopt(
+		long = "rpc-methods",
+		value_name = "METHOD_SET",
+		possible_values = &RpcMethods::variants(),
+		case_insensitive = true,
+		default_value = "Auto"
+	)]
+	#[serde(serialize_with = "crate::config_file::display")]
+	pub rpc_methods: RpcMethods,
+
 	/// Specify the state pruning mode, a number of blocks to keep or 'archive'.
 	///
 	/// Default is to keep all block states if the node is running as a
@@ -418,6 +617,35 @@ pub struct RunCmd {
 	#[structopt(long = "unsafe-pruning")]
 	pub unsafe_pruning: bool,
 
+	/// Specify the block bodies pruning mode, a number of finalized blocks to keep or 'archive'.
+	///
+	/// Default is to keep the bodies of all blocks.
+	#[structopt(long = "bodies-pruning", value_name = "PRUNING_MODE", parse(try_from_str = parse_keep_blocks))]
+	#[serde(serialize_with = "crate::config_file::keep_blocks")]
+	pub bodies_pruning: Option<KeepBlocks>,
+
+	/// Specify which justifications to keep.
+	///
+	/// `AuthoritySetChanges` keeps only the justifications of authority set changes and the
+	/// latest one, which are enough to prove the finality of the chain.
+	#[structopt(
+		long = "justifications-pruning",
+		value_name = "PRUNING_MODE",
+		possible_values = &JustificationsPruning::variants(),
+		case_insensitive = true,
+		default_value = "Archive"
+	)]
+	#[serde(serialize_with = "crate::config_file::display")]
+	pub justifications_pruning: JustificationsPruning,
+
+	/// Specify the changes tries pruning mode, a number of finalized blocks to keep or 'archive'.
+	///
+	/// Default is to keep the changes tries of the last 32768 blocks, or of all blocks if the
+	/// state isn't pruned.
+	#[structopt(long = "changes-tries-pruning", value_name = "PRUNING_MODE", parse(try_from_str = parse_keep_blocks))]
+	#[serde(serialize_with = "crate::config_file::keep_blocks")]
+	pub changes_tries_pruning: Option<KeepBlocks>,
+
 	/// The human-readable name for this node.
 	///
 	/// The node name will be reported to the telemetry server, if enabled.
@@ -437,6 +665,7 @@ pub struct RunCmd {
 	/// the least verbosity. If no verbosity level is specified the default is
 	/// 0.
 	#[structopt(long = "telemetry-url", value_name = "URL VERBOSITY", parse(try_from_str = parse_telemetry_endpoints))]
+	#[serde(rename = "telemetry-url", serialize_with = "crate::config_file::telemetry_endpoints")]
 	pub telemetry_endpoints: Vec<(String, u8)>,
 
 	/// Should execute offchain workers on every block.
@@ -449,8 +678,22 @@ pub struct RunCmd {
 		case_insensitive = true,
 		default_value = "WhenValidating"
 	)]
+	#[serde(serialize_with = "crate::config_file::display")]
 	pub offchain_worker: OffchainWorkerEnabled,
 
+	/// Read options from this TOML or JSON file.
+	///
+	/// Its keys are the names of the flags, for example `port = 30333` or
+	/// `bootnodes = ["/ip4/..."]`. Flags given on the command line take
+	/// precedence over the file.
+	///
+	/// On SIGHUP, or when the `system_reloadConfig` RPC is called, the node
+	/// reads the file again and applies its `log`, `reserved-nodes`,
+	/// `pool-limit`, `pool-kbytes`, `offchain-worker` and `telemetry-url`.
+	#[structopt(long = "config", value_name = "PATH", parse(from_os_str))]
+	#[serde(skip)]
+	pub config: Option<PathBuf>,
+
 	/// Method for executing Wasm runtime code.
 	#[structopt(
 		long = "wasm-execution",
@@ -459,32 +702,48 @@ pub struct RunCmd {
 		case_insensitive = true,
 		default_value = "Interpreted"
 	)]
+	#[serde(rename = "wasm-execution", serialize_with = "crate::config_file::display")]
 	pub wasm_method: WasmExecutionMethod,
 
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
+	#[serde(flatten)]
 	pub execution_strategies: ExecutionStrategies,
 
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
+	#[serde(flatten)]
 	pub shared_params: SharedParams,
 
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
+	#[serde(flatten)]
 	pub network_config: NetworkConfigurationParams,
 
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
+	#[serde(flatten)]
 	pub pool_config: TransactionPoolParams,
 
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
+	#[serde(flatten)]
 	pub keyring: Keyring,
 
 	/// Enable authoring even when offline.
 	#[structopt(long = "force-authoring")]
 	pub force_authoring: bool,
 
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	#[serde(flatten)]
+	pub keystore_params: KeystoreParams,
+}
+
+/// Parameters of the keystore.
+#[derive(Debug, StructOpt, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct KeystoreParams {
 	/// Specify custom keystore path.
 	#[structopt(long = "keystore-path", value_name = "PATH", parse(from_os_str))]
 	pub keystore_path: Option<PathBuf>,
@@ -501,6 +760,7 @@ pub struct RunCmd {
 		long = "password",
 		conflicts_with_all = &[ "password-interactive", "password-filename" ]
 	)]
+	#[serde(skip)]
 	pub password: Option<String>,
 
 	/// File that contains the password used by the keystore.
@@ -510,7 +770,7 @@ pub struct RunCmd {
 		parse(from_os_str),
 		conflicts_with_all = &[ "password-interactive", "password" ]
 	)]
-	pub password_filename: Option<PathBuf>
+	pub password_filename: Option<PathBuf>,
 }
 
 /// Stores all required Cli values for a keyring test account.
@@ -581,6 +841,17 @@ impl AugmentClap for Keyring {
 	}
 }
 
+/// Serializes the test account as its flag, e.g. `alice = true`.
+impl Serialize for Keyring {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let mut map = serializer.serialize_map(None)?;
+		if let Some(account) = self.account {
+			map.serialize_entry(&account.to_string().to_lowercase(), &true)?;
+		}
+		map.end()
+	}
+}
+
 impl Keyring {
 	fn is_subcommand() -> bool {
 		false
@@ -602,6 +873,38 @@ fn parse_telemetry_endpoints(s: &str) -> Result<(String, u8), Box<dyn std::error
 	}
 }
 
+/// Parse a block data pruning mode, a number of blocks to keep or 'archive'.
+fn parse_keep_blocks(s: &str) -> Result<KeepBlocks, Box<dyn std::error::Error>> {
+	match s {
+		"archive" => Ok(KeepBlocks::All),
+		blocks => Ok(KeepBlocks::Some(blocks.parse()?)),
+	}
+}
+
+/// Parse the settings of a database column.
+fn parse_column_config(s: &str) -> Result<(String, ColumnConfig), Box<dyn std::error::Error>> {
+	let pos = s.find(':').ok_or("Expected `COLUMN:SETTINGS`")?;
+	let mut config = ColumnConfig::default();
+	for setting in s[pos + 1..].split(',').filter(|s| !s.is_empty()) {
+		let mut parts = setting.splitn(2, '=');
+		let (name, value) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+		match name {
+			"compression" => config.compression = value.parse::<Compression>()?,
+			"cache" => match value.parse()? {
+				share if share <= 100 => config.cache_share = Some(share),
+				_ => return Err("The cache share of a column is a percentage".into()),
+			},
+			"bloom" => config.bloom_filter_bits = match value.parse()? {
+				0 => None,
+				bits => Some(bits),
+			},
+			_ => return Err(format!("Unknown column setting `{}`", name).into()),
+		}
+	}
+
+	Ok((s[..pos].to_owned(), config))
+}
+
 /// CORS setting
 ///
 /// The type is introduced to overcome `Option<Option<T>>`
@@ -745,6 +1048,13 @@ pub struct RevertCmd {
 	#[structopt(default_value = "256")]
 	pub num: u32,
 
+	/// Also revert finalized blocks, as far back as their state is available.
+	///
+	/// Finality and consensus data (authority sets, epochs) are rewound as well. Unless all
+	/// nodes of the network do the same, the node will end up on a fork.
+	#[structopt(long = "unsafe-finalized")]
+	pub unsafe_finalized: bool,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub shared_params: SharedParams,
@@ -752,6 +1062,125 @@ pub struct RevertCmd {
 
 impl_get_log_filter!(RevertCmd);
 
+/// The `check-db` command used to check the integrity of the database.
+#[derive(Debug, StructOpt, Clone)]
+pub struct CheckDbCmd {
+	/// Number of blocks of the best chain to check.
+	#[structopt(long = "blocks", value_name = "COUNT", default_value = "256")]
+	pub blocks: u32,
+
+	/// Repair the database without prompting, by reverting to the last consistent block.
+	#[structopt(short = "y")]
+	pub yes: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl_get_log_filter!(CheckDbCmd);
+
+/// The `dry-run-upgrade` command used to try out a runtime upgrade against the chain.
+#[derive(Debug, StructOpt, Clone)]
+pub struct DryRunUpgradeCmd {
+	/// Wasm blob of the runtime to upgrade to.
+	#[structopt(long = "wasm", value_name = "PATH", parse(from_os_str))]
+	pub wasm: PathBuf,
+
+	/// Hash of the block at which the upgrade is enacted.
+	#[structopt(long = "at", value_name = "HASH")]
+	pub at: String,
+
+	/// Number of following blocks of the best chain to replay through the upgraded runtime.
+	#[structopt(long = "blocks", value_name = "COUNT", default_value = "10")]
+	pub blocks: u32,
+
+	/// The default number of 64KB pages to ever allocate for Wasm execution.
+	///
+	/// Don't alter this unless you know what you're doing.
+	#[structopt(long = "default-heap-pages", value_name = "COUNT")]
+	pub default_heap_pages: Option<u32>,
+
+	/// Method for executing Wasm runtime code.
+	#[structopt(
+		long = "wasm-execution",
+		value_name = "METHOD",
+		possible_values = &WasmExecutionMethod::variants(),
+		case_insensitive = true,
+		default_value = "Interpreted"
+	)]
+	pub wasm_method: WasmExecutionMethod,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl_get_log_filter!(DryRunUpgradeCmd);
+
+/// The `print-config` command used to print the effective configuration of the node.
+#[derive(Debug, StructOpt, Clone)]
+pub struct PrintConfigCmd {
+	/// Print the configuration as JSON instead of TOML.
+	#[structopt(long = "json")]
+	pub json: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub run: RunCmd,
+}
+
+impl GetLogFilter for PrintConfigCmd {
+	fn get_log_filter(&self) -> Option<String> {
+		self.run.get_log_filter()
+	}
+}
+
+/// The `replay-blocks` command used to re-execute blocks of the chain.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ReplayBlocksCmd {
+	/// First block to replay.
+	#[structopt(long = "from", value_name = "BLOCK")]
+	pub from: u32,
+
+	/// Last block to replay.
+	#[structopt(long = "to", value_name = "BLOCK")]
+	pub to: u32,
+
+	/// Runtimes to replay the blocks with. With `Both`, the results of the runtimes are also
+	/// compared with each other.
+	#[structopt(
+		long = "execution",
+		value_name = "RUNTIME",
+		possible_values = &ReplayExecution::variants(),
+		case_insensitive = true,
+		default_value = "Both"
+	)]
+	pub execution: ReplayExecution,
+
+	/// The default number of 64KB pages to ever allocate for Wasm execution.
+	///
+	/// Don't alter this unless you know what you're doing.
+	#[structopt(long = "default-heap-pages", value_name = "COUNT")]
+	pub default_heap_pages: Option<u32>,
+
+	/// Method for executing Wasm runtime code.
+	#[structopt(
+		long = "wasm-execution",
+		value_name = "METHOD",
+		possible_values = &WasmExecutionMethod::variants(),
+		case_insensitive = true,
+		default_value = "Interpreted"
+	)]
+	pub wasm_method: WasmExecutionMethod,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl_get_log_filter!(ReplayBlocksCmd);
+
 /// The `purge-chain` command used to remove the whole chain.
 #[derive(Debug, StructOpt, Clone)]
 pub struct PurgeChainCmd {
@@ -766,6 +1195,204 @@ pub struct PurgeChainCmd {
 
 impl_get_log_filter!(PurgeChainCmd);
 
+fn parse_key_type(key_type: &str) -> Result<KeyTypeId, String> {
+	KeyTypeId::try_from(key_type)
+		.map_err(|_| format!("Invalid key type '{}', expected 4 characters like `babe`", key_type))
+}
+
+/// The `key list` command used to list the keys of the keystore.
+#[derive(Debug, StructOpt, Clone)]
+pub struct KeyListCmd {
+	/// Only list the keys of this type, e.g. `babe` or `gran`.
+	#[structopt(long = "key-type", value_name = "TYPE", parse(try_from_str = parse_key_type))]
+	pub key_type: Option<KeyTypeId>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub keystore_params: KeystoreParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+/// The `key import` command used to insert a key into the keystore.
+#[derive(Debug, StructOpt, Clone)]
+pub struct KeyImportCmd {
+	/// Secret URI of the key, e.g. a recovery phrase or `//Alice`.
+	#[structopt(long = "suri", value_name = "SURI", required_unless = "json", conflicts_with = "json")]
+	pub suri: Option<String>,
+
+	/// File with a key exported by `key export`.
+	#[structopt(long = "json", value_name = "PATH", parse(from_os_str))]
+	pub json: Option<PathBuf>,
+
+	/// Type of the key, e.g. `babe` or `gran`. Taken from the file with `--json`.
+	#[structopt(
+		long = "key-type",
+		value_name = "TYPE",
+		parse(try_from_str = parse_key_type),
+		required_unless = "json",
+		conflicts_with = "json"
+	)]
+	pub key_type: Option<KeyTypeId>,
+
+	/// Signature scheme of the key, `Sr25519` by default. Taken from the file with `--json`.
+	#[structopt(
+		long = "scheme",
+		value_name = "SCHEME",
+		possible_values = &CryptoScheme::variants(),
+		case_insensitive = true,
+		conflicts_with = "json"
+	)]
+	pub scheme: Option<CryptoScheme>,
+
+	/// File that contains the password the key was exported with. Asked interactively if not given.
+	#[structopt(long = "export-password-filename", value_name = "PATH", parse(from_os_str))]
+	pub export_password_filename: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub keystore_params: KeystoreParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+/// The `key export` command used to export a key of the keystore, encrypted with a password.
+#[derive(Debug, StructOpt, Clone)]
+pub struct KeyExportCmd {
+	/// Type of the key, e.g. `babe` or `gran`.
+	#[structopt(long = "key-type", value_name = "TYPE", parse(try_from_str = parse_key_type))]
+	pub key_type: KeyTypeId,
+
+	/// Hex encoded public key.
+	#[structopt(long = "public", value_name = "PUBLIC")]
+	pub public: String,
+
+	/// Signature scheme of the key.
+	#[structopt(
+		long = "scheme",
+		value_name = "SCHEME",
+		possible_values = &CryptoScheme::variants(),
+		case_insensitive = true,
+		default_value = "Sr25519"
+	)]
+	pub scheme: CryptoScheme,
+
+	/// File to write the exported key to, instead of stdout.
+	#[structopt(long = "output", value_name = "PATH", parse(from_os_str))]
+	pub output: Option<PathBuf>,
+
+	/// File that contains the password to encrypt the key with. Asked interactively if not given.
+	#[structopt(long = "export-password-filename", value_name = "PATH", parse(from_os_str))]
+	pub export_password_filename: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub keystore_params: KeystoreParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+/// The `key remove` command used to remove a key from the keystore.
+#[derive(Debug, StructOpt, Clone)]
+pub struct KeyRemoveCmd {
+	/// Type of the key, e.g. `babe` or `gran`.
+	#[structopt(long = "key-type", value_name = "TYPE", parse(try_from_str = parse_key_type))]
+	pub key_type: KeyTypeId,
+
+	/// Hex encoded public key.
+	#[structopt(long = "public", value_name = "PUBLIC")]
+	pub public: String,
+
+	/// Skip interactive prompt by answering yes automatically.
+	#[structopt(short = "y")]
+	pub yes: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub keystore_params: KeystoreParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+/// The `key check-session` command used to look up the keys of the keystore on-chain.
+#[derive(Debug, StructOpt, Clone)]
+pub struct KeyCheckSessionCmd {
+	/// Only check the keys of this type, e.g. `babe` or `gran`.
+	#[structopt(long = "key-type", value_name = "TYPE", parse(try_from_str = parse_key_type))]
+	pub key_type: Option<KeyTypeId>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub keystore_params: KeystoreParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+/// The `key` command used to manage the keys of the keystore.
+#[derive(Debug, StructOpt, Clone)]
+pub enum KeyCmd {
+	/// List the keys of the keystore.
+	#[structopt(name = "list")]
+	List(KeyListCmd),
+
+	/// Insert a key into the keystore, from a secret URI or a file written by `key export`.
+	#[structopt(name = "import")]
+	Import(KeyImportCmd),
+
+	/// Export a key of the keystore, encrypted with a password.
+	#[structopt(name = "export")]
+	Export(KeyExportCmd),
+
+	/// Remove a key from the keystore.
+	#[structopt(name = "remove")]
+	Remove(KeyRemoveCmd),
+
+	/// Check which keys of the keystore are registered as session keys at the best block, and
+	/// whether they are in use in the current session or queued for the next one.
+	#[structopt(name = "check-session")]
+	CheckSession(KeyCheckSessionCmd),
+}
+
+impl KeyCmd {
+	/// Parameters shared by all the `key` subcommands.
+	pub fn shared_params(&self) -> &SharedParams {
+		match self {
+			KeyCmd::List(c) => &c.shared_params,
+			KeyCmd::Import(c) => &c.shared_params,
+			KeyCmd::Export(c) => &c.shared_params,
+			KeyCmd::Remove(c) => &c.shared_params,
+			KeyCmd::CheckSession(c) => &c.shared_params,
+		}
+	}
+
+	/// Keystore parameters shared by all the `key` subcommands.
+	pub fn keystore_params(&self) -> &KeystoreParams {
+		match self {
+			KeyCmd::List(c) => &c.keystore_params,
+			KeyCmd::Import(c) => &c.keystore_params,
+			KeyCmd::Export(c) => &c.keystore_params,
+			KeyCmd::Remove(c) => &c.keystore_params,
+			KeyCmd::CheckSession(c) => &c.keystore_params,
+		}
+	}
+}
+
+impl GetLogFilter for KeyCmd {
+	fn get_log_filter(&self) -> Option<String> {
+		self.shared_params().get_log_filter()
+	}
+}
+
 /// All core commands that are provided by default.
 ///
 /// The core commands are split into multiple subcommands and `Run` is the default subcommand. From
@@ -788,6 +1415,21 @@ pub enum CoreParams<CC, RP> {
 	/// Revert chain to the previous state.
 	Revert(RevertCmd),
 
+	/// Check the integrity of the database.
+	CheckDb(CheckDbCmd),
+
+	/// Try out a runtime upgrade against the chain.
+	DryRunUpgrade(DryRunUpgradeCmd),
+
+	/// Re-execute blocks of the chain.
+	ReplayBlocks(ReplayBlocksCmd),
+
+	/// Print the effective configuration of the node.
+	PrintConfig(PrintConfigCmd),
+
+	/// Manage the keys of the keystore.
+	Key(KeyCmd),
+
 	/// Remove the whole chain data.
 	PurgeChain(PurgeChainCmd),
 
@@ -822,6 +1464,31 @@ impl<CC, RP> StructOpt for CoreParams<CC, RP> where
 			RevertCmd::augment_clap(SubCommand::with_name("revert"))
 				.about("Revert chain to the previous state.")
 		)
+		.subcommand(
+			CheckDbCmd::augment_clap(SubCommand::with_name("check-db"))
+				.about("Check the integrity of the database and repair it by reverting to the \
+						last consistent block.")
+		)
+		.subcommand(
+			DryRunUpgradeCmd::augment_clap(SubCommand::with_name("dry-run-upgrade"))
+				.about("Replay the blocks following a given block through a new runtime, as if it had \
+						been enacted there, and report how it differs from the current one.")
+		)
+		.subcommand(
+			ReplayBlocksCmd::augment_clap(SubCommand::with_name("replay-blocks"))
+				.about("Re-execute blocks with the native runtime, the Wasm runtime or both and report \
+						the blocks whose results don't match. Requires an archive node.")
+		)
+		.subcommand(
+			PrintConfigCmd::augment_clap(SubCommand::with_name("print-config"))
+				.about("Print the configuration the node would run with, including the defaults \
+						taken from the chain spec, in the format of `--config` files.")
+		)
+		.subcommand(
+			KeyCmd::clap().name("key")
+				.about("Manage the keys of the keystore: list, import, export and remove keys, and \
+						check which of them are registered as session keys on-chain.")
+		)
 		.subcommand(
 			PurgeChainCmd::augment_clap(SubCommand::with_name("purge-chain"))
 				.about("Remove the whole chain data.")
@@ -837,6 +1504,14 @@ impl<CC, RP> StructOpt for CoreParams<CC, RP> where
 			("import-blocks", Some(matches)) =>
 				CoreParams::ImportBlocks(ImportBlocksCmd::from_clap(matches)),
 			("revert", Some(matches)) => CoreParams::Revert(RevertCmd::from_clap(matches)),
+			("check-db", Some(matches)) => CoreParams::CheckDb(CheckDbCmd::from_clap(matches)),
+			("dry-run-upgrade", Some(matches)) =>
+				CoreParams::DryRunUpgrade(DryRunUpgradeCmd::from_clap(matches)),
+			("replay-blocks", Some(matches)) =>
+				CoreParams::ReplayBlocks(ReplayBlocksCmd::from_clap(matches)),
+			("print-config", Some(matches)) =>
+				CoreParams::PrintConfig(PrintConfigCmd::from_clap(matches)),
+			("key", Some(matches)) => CoreParams::Key(KeyCmd::from_clap(matches)),
 			("purge-chain", Some(matches)) =>
 				CoreParams::PurgeChain(PurgeChainCmd::from_clap(matches)),
 			(_, None) => CoreParams::Run(MergeParameters::from_clap(matches)),
@@ -854,6 +1529,11 @@ impl<CC, RP> GetLogFilter for CoreParams<CC, RP> where CC: GetLogFilter {
 			CoreParams::ImportBlocks(c) => c.get_log_filter(),
 			CoreParams::PurgeChain(c) => c.get_log_filter(),
 			CoreParams::Revert(c) => c.get_log_filter(),
+			CoreParams::CheckDb(c) => c.get_log_filter(),
+			CoreParams::DryRunUpgrade(c) => c.get_log_filter(),
+			CoreParams::ReplayBlocks(c) => c.get_log_filter(),
+			CoreParams::PrintConfig(c) => c.get_log_filter(),
+			CoreParams::Key(c) => c.get_log_filter(),
 			CoreParams::Custom(c) => c.get_log_filter(),
 		}
 	}
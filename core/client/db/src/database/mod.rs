@@ -0,0 +1,225 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Database backends of the client.
+//!
+//! The storages only talk to the database through the [`KeyValueDB`] trait, so any
+//! key-value store implementing it can be plugged in with `DatabaseSettingsSrc::Custom`.
+//! Backends opened from a path are picked with [`DatabaseBackend`] and each of their
+//! columns can be tuned separately with a [`ColumnConfig`], which allows comparing
+//! layouts suited to trie nodes against layouts suited to block bodies.
+
+#[cfg(feature = "rocksdb")]
+mod rocks;
+#[cfg(feature = "sled")]
+mod sled_tree;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use kvdb::KeyValueDB;
+
+/// Database cache size used when none is given, in MiB.
+const DEFAULT_CACHE_SIZE: usize = 128;
+
+/// Key-value store used by a database opened from a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatabaseBackend {
+	/// RocksDB, each column in its own column family. Supports all column settings.
+	RocksDb,
+	/// Sled, an embedded database written in Rust. All columns share a single tree,
+	/// so column settings are refused.
+	Sled,
+}
+
+impl Default for DatabaseBackend {
+	fn default() -> Self {
+		DatabaseBackend::RocksDb
+	}
+}
+
+impl fmt::Display for DatabaseBackend {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			DatabaseBackend::RocksDb => write!(f, "rocksdb"),
+			DatabaseBackend::Sled => write!(f, "sled"),
+		}
+	}
+}
+
+/// Compression of the values stored in a column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+	/// Values are stored as they are.
+	None,
+	/// Snappy compression.
+	Snappy,
+	/// LZ4 compression.
+	Lz4,
+	/// Zstandard compression.
+	Zstd,
+}
+
+impl FromStr for Compression {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"none" => Ok(Compression::None),
+			"snappy" => Ok(Compression::Snappy),
+			"lz4" => Ok(Compression::Lz4),
+			"zstd" => Ok(Compression::Zstd),
+			_ => Err(format!("Unknown compression `{}`, expected none, snappy, lz4 or zstd", s)),
+		}
+	}
+}
+
+/// Tuning of a single database column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnConfig {
+	/// Compression of the values.
+	pub compression: Compression,
+	/// Share of the database cache reserved to the column, in percent. Columns without
+	/// a share split what is left of the cache evenly.
+	pub cache_share: Option<u8>,
+	/// Bits per key of the bloom filter of the column. `None` disables the filter.
+	pub bloom_filter_bits: Option<u32>,
+}
+
+impl Default for ColumnConfig {
+	fn default() -> Self {
+		ColumnConfig {
+			compression: Compression::Snappy,
+			cache_share: None,
+			bloom_filter_bits: Some(10),
+		}
+	}
+}
+
+/// Column settings by column name. Columns which aren't listed use the default settings.
+pub type ColumnsConfig = BTreeMap<String, ColumnConfig>;
+
+/// Settings of a single column, resolved for a given database layout.
+pub(crate) struct ResolvedColumn {
+	/// Index of the column.
+	pub index: u32,
+	/// Settings of the column.
+	pub config: ColumnConfig,
+	/// Cache of the column, in bytes.
+	pub cache_size: usize,
+}
+
+/// Resolve the `columns` settings given by name against the `names` of the columns
+/// of a database with `num_columns` columns, and split `cache_size` (in MiB) between them.
+pub(crate) fn resolve_columns(
+	names: &[(&str, Option<u32>)],
+	num_columns: u32,
+	columns: &ColumnsConfig,
+	cache_size: Option<usize>,
+) -> Result<Vec<ResolvedColumn>, String> {
+	let mut resolved: Vec<_> = (0..num_columns).map(|index| ResolvedColumn {
+		index,
+		config: ColumnConfig::default(),
+		cache_size: 0,
+	}).collect();
+
+	for (name, config) in columns {
+		let index = names.iter()
+			.find(|(n, _)| n == name)
+			.and_then(|(_, index)| *index)
+			.ok_or_else(|| format!(
+				"Unknown database column `{}`, expected one of: {}",
+				name,
+				names.iter().map(|(n, _)| *n).collect::<Vec<_>>().join(", "),
+			))?;
+		resolved[index as usize].config = config.clone();
+	}
+
+	let total = cache_size.unwrap_or(DEFAULT_CACHE_SIZE) * 1024 * 1024;
+	let reserved: usize = resolved.iter()
+		.filter_map(|c| c.config.cache_share)
+		.map(usize::from)
+		.sum();
+	if reserved > 100 {
+		return Err(format!("Database columns cache shares add up to {}%", reserved));
+	}
+	let unreserved = resolved.iter().filter(|c| c.config.cache_share.is_none()).count();
+	for column in &mut resolved {
+		column.cache_size = match column.config.cache_share {
+			Some(share) => total / 100 * usize::from(share),
+			None => total / 100 * (100 - reserved) / unreserved,
+		};
+	}
+
+	Ok(resolved)
+}
+
+/// Open the database at `path` with the given `backend`.
+pub(crate) fn open(
+	backend: DatabaseBackend,
+	path: &Path,
+	columns: Vec<ResolvedColumn>,
+) -> Result<Arc<dyn KeyValueDB>, String> {
+	match backend {
+		#[cfg(feature = "rocksdb")]
+		DatabaseBackend::RocksDb => rocks::RocksDb::open(path, columns)
+			.map(|db| Arc::new(db) as Arc<_>)
+			.map_err(|e| e.to_string()),
+		#[cfg(feature = "sled")]
+		DatabaseBackend::Sled => sled_tree::SledDb::open(path, columns)
+			.map(|db| Arc::new(db) as Arc<_>),
+		#[allow(unreachable_patterns)]
+		backend => {
+			let _ = (path, columns);
+			Err(format!("Try to open {} database with {} disabled", backend, backend))
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const NAMES: &[(&str, Option<u32>)] = &[("meta", Some(0)), ("state", Some(1)), ("body", Some(2))];
+
+	#[test]
+	fn cache_is_split_between_columns() {
+		let mut columns = ColumnsConfig::new();
+		columns.insert("state".into(), ColumnConfig { cache_share: Some(50), ..Default::default() });
+		let resolved = resolve_columns(NAMES, 3, &columns, Some(100)).unwrap();
+
+		let mib = 1024 * 1024;
+		assert_eq!(resolved[1].cache_size, 50 * mib);
+		assert_eq!(resolved[0].cache_size, 25 * mib);
+		assert_eq!(resolved[2].cache_size, 25 * mib);
+		assert_eq!(resolved[2].config, ColumnConfig::default());
+	}
+
+	#[test]
+	fn invalid_columns_are_rejected() {
+		let mut columns = ColumnsConfig::new();
+		columns.insert("bodies".into(), ColumnConfig::default());
+		assert!(resolve_columns(NAMES, 3, &columns, None).is_err());
+
+		let mut columns = ColumnsConfig::new();
+		columns.insert("state".into(), ColumnConfig { cache_share: Some(80), ..Default::default() });
+		columns.insert("body".into(), ColumnConfig { cache_share: Some(30), ..Default::default() });
+		assert!(resolve_columns(NAMES, 3, &columns, None).is_err());
+	}
+}
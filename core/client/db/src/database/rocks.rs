@@ -0,0 +1,486 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RocksDB backend with per-column options.
+//!
+//! Columns are stored in column families named like the ones of `kvdb-rocksdb`,
+//! so databases created by either can be opened by the other. The database and
+//! column tuning is the one `kvdb-rocksdb` applies, on top of which the column
+//! settings are applied. As in `kvdb-rocksdb`, buffered writes are kept in memory
+//! until the database is flushed, and restoring a database replaces its files.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+use kvdb::{KeyValueDB, DBTransaction, DBOp, DBValue};
+use parking_lot::RwLock;
+use rocksdb::{
+	BlockBasedOptions, ColumnFamily, ColumnFamilyDescriptor, DBCompressionType, Direction,
+	IteratorMode, Options, WriteBatch, DB,
+};
+
+use super::{Compression, ResolvedColumn};
+
+fn other_io_err<E: ToString>(e: E) -> io::Error {
+	io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+/// Size of the SST files of level 1, in bytes.
+const TARGET_FILE_SIZE_BASE: u64 = 64 * 1024 * 1024;
+/// Size of the data blocks, in bytes.
+const BLOCK_SIZE: usize = 16 * 1024;
+/// Write buffer shared by all the columns, in bytes.
+const DB_WRITE_BUFFER_SIZE: usize = 128 * 1024 * 1024;
+/// Number of entries read from the database at once by iterators.
+const ITER_CHUNK_SIZE: usize = 1024;
+
+fn column_name(index: u32) -> String {
+	format!("col{}", index)
+}
+
+fn column_options(column: &ResolvedColumn) -> Options {
+	let mut block_opts = BlockBasedOptions::default();
+	block_opts.set_block_size(BLOCK_SIZE);
+	block_opts.set_lru_cache(column.cache_size);
+	block_opts.set_cache_index_and_filter_blocks(true);
+	block_opts.set_pin_l0_filter_and_index_blocks_in_cache(true);
+	if let Some(bits) = column.config.bloom_filter_bits {
+		block_opts.set_bloom_filter(bits as _, true);
+	}
+
+	let mut opts = Options::default();
+	opts.set_level_compaction_dynamic_level_bytes(true);
+	opts.optimize_level_style_compaction(column.cache_size);
+	opts.set_target_file_size_base(TARGET_FILE_SIZE_BASE);
+	opts.set_compression_type(match column.config.compression {
+		Compression::None => DBCompressionType::None,
+		Compression::Snappy => DBCompressionType::Snappy,
+		Compression::Lz4 => DBCompressionType::Lz4,
+		Compression::Zstd => DBCompressionType::Zstd,
+	});
+	opts.set_block_based_table_factory(&block_opts);
+	opts
+}
+
+fn open_db(path: &Path, columns: &[ResolvedColumn]) -> Result<DB, rocksdb::Error> {
+	let mut opts = Options::default();
+	opts.create_if_missing(true);
+	opts.create_missing_column_families(true);
+	opts.set_use_fsync(false);
+	opts.set_max_open_files(512);
+	opts.set_keep_log_file_num(1);
+	opts.set_bytes_per_sync(1024 * 1024);
+	opts.set_db_write_buffer_size(DB_WRITE_BUFFER_SIZE);
+	opts.increase_parallelism(std::cmp::max(1, num_cpus::get() as i32 / 2));
+
+	let descriptors = columns.iter()
+		.map(|column| ColumnFamilyDescriptor::new(column_name(column.index), column_options(column)))
+		.collect();
+	DB::open_cf_descriptors(&opts, path, descriptors)
+}
+
+// replace the files of the database at `path` with the ones at `new_db`, keeping the
+// current ones if they can't be moved.
+fn replace_files(path: &Path, new_db: &str) -> io::Result<()> {
+	let backup = path.with_extension("backup");
+	let existed = match fs::rename(path, &backup) {
+		Ok(()) => true,
+		Err(ref e) if e.kind() == io::ErrorKind::NotFound => false,
+		Err(e) => return Err(e),
+	};
+
+	match fs::rename(new_db, path) {
+		Ok(()) => {
+			if existed {
+				fs::remove_dir_all(&backup)?;
+			}
+			Ok(())
+		},
+		Err(e) => {
+			if existed {
+				fs::rename(&backup, path)?;
+			}
+			Err(e)
+		},
+	}
+}
+
+// index of a column in the overlay, the `None` column coming first.
+fn overlay_index(col: Option<u32>) -> usize {
+	col.map_or(0, |col| col as usize + 1)
+}
+
+/// Buffered changes of a column, `None` for deleted keys.
+type ColumnOverlay = BTreeMap<Vec<u8>, Option<DBValue>>;
+
+/// RocksDB database, each column in its own column family.
+pub struct RocksDb {
+	path: PathBuf,
+	columns: Vec<ResolvedColumn>,
+	/// `None` while restoring fails to reopen the database.
+	db: RwLock<Option<DB>>,
+	/// Changes written with `write_buffered` and not flushed yet, by column.
+	overlay: RwLock<Vec<ColumnOverlay>>,
+}
+
+impl RocksDb {
+	/// Open or create the database at `path`.
+	pub fn open(path: &Path, columns: Vec<ResolvedColumn>) -> Result<Self, rocksdb::Error> {
+		let db = open_db(path, &columns)?;
+		Ok(RocksDb {
+			path: path.to_owned(),
+			overlay: RwLock::new(vec![ColumnOverlay::new(); columns.len() + 1]),
+			columns,
+			db: RwLock::new(Some(db)),
+		})
+	}
+
+	fn cf(&self, db: &DB, col: u32) -> io::Result<ColumnFamily> {
+		self.columns.get(col as usize)
+			.and_then(|column| db.cf_handle(&column_name(column.index)))
+			.ok_or_else(|| other_io_err(format!("Unknown column {}", col)))
+	}
+
+	// adds the changes of `transaction` to the overlay, or none of them if it touches an
+	// unknown column.
+	fn buffer(&self, transaction: DBTransaction) -> io::Result<()> {
+		let mut overlay = self.overlay.write();
+		let unknown = transaction.ops.iter().find(|op| overlay_index(op.col()) >= overlay.len());
+		if let Some(op) = unknown {
+			return Err(other_io_err(format!("Unknown column {:?}", op.col())));
+		}
+
+		for op in transaction.ops {
+			match op {
+				DBOp::Insert { col, key, value } => overlay[overlay_index(col)].insert(key.to_vec(), Some(value)),
+				DBOp::Delete { col, key } => overlay[overlay_index(col)].insert(key.to_vec(), None),
+			};
+		}
+		Ok(())
+	}
+
+	// reads the entries of `col` above `from`, `ITER_CHUNK_SIZE` of them from the database,
+	// with the overlay applied. Returns them with the last key read from the database if
+	// there may be more entries.
+	fn read_chunk(
+		&self,
+		col: Option<u32>,
+		from: &Bound<Vec<u8>>,
+	) -> io::Result<(Vec<(Box<[u8]>, Box<[u8]>)>, Option<Vec<u8>>)> {
+		let overlay = self.overlay.read();
+		let db = self.db.read();
+		let db = db.as_ref().ok_or_else(|| other_io_err("The database is closed"))?;
+
+		let start = match from {
+			Bound::Included(key) | Bound::Excluded(key) => &key[..],
+			Bound::Unbounded => &[][..],
+		};
+		let mode = IteratorMode::From(start, Direction::Forward);
+		let iter = match col {
+			None => db.iterator(mode),
+			Some(col) => db.iterator_cf(self.cf(db, col)?, mode).map_err(other_io_err)?,
+		};
+		let mut entries = BTreeMap::new();
+		let mut last = None;
+		for (key, value) in iter
+			.filter(|(key, _)| match from {
+				Bound::Excluded(from) => key[..] != from[..],
+				_ => true,
+			})
+			.take(ITER_CHUNK_SIZE)
+		{
+			let key = key.into_vec();
+			last = Some(key.clone());
+			entries.insert(key, value);
+		}
+
+		let last = if entries.len() == ITER_CHUNK_SIZE { last } else { None };
+		let to = last.clone().map_or(Bound::Unbounded, Bound::Included);
+		let column_overlay = overlay.get(overlay_index(col))
+			.ok_or_else(|| other_io_err(format!("Unknown column {:?}", col)))?;
+		for (key, value) in column_overlay.range((from.clone(), to)) {
+			match value {
+				Some(value) => entries.insert(key.clone(), value.to_vec().into_boxed_slice()),
+				None => entries.remove(key),
+			};
+		}
+
+		Ok((entries.into_iter().map(|(key, value)| (key.into_boxed_slice(), value)).collect(), last))
+	}
+}
+
+/// Iterator over the entries of a column, read from the database by chunks so that the
+/// database isn't locked while iterating.
+struct Iter<'a> {
+	db: &'a RocksDb,
+	col: Option<u32>,
+	/// Where the next chunk starts, `None` once the last chunk is read.
+	from: Option<Bound<Vec<u8>>>,
+	chunk: std::vec::IntoIter<(Box<[u8]>, Box<[u8]>)>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+	type Item = (Box<[u8]>, Box<[u8]>);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(entry) = self.chunk.next() {
+				return Some(entry);
+			}
+
+			let from = self.from.take()?;
+			match self.db.read_chunk(self.col, &from) {
+				Ok((chunk, last)) => {
+					self.chunk = chunk.into_iter();
+					self.from = last.map(Bound::Excluded);
+				},
+				Err(e) => {
+					log::warn!(target: "db", "Failed to iterate over column {:?}: {}", self.col, e);
+					return None;
+				},
+			}
+		}
+	}
+}
+
+impl KeyValueDB for RocksDb {
+	fn get(&self, col: Option<u32>, key: &[u8]) -> io::Result<Option<DBValue>> {
+		if let Some(value) = self.overlay.read().get(overlay_index(col)).and_then(|overlay| overlay.get(key)) {
+			return Ok(value.clone());
+		}
+
+		let db = self.db.read();
+		let db = db.as_ref().ok_or_else(|| other_io_err("The database is closed"))?;
+		let value = match col {
+			None => db.get(key),
+			Some(col) => db.get_cf(self.cf(db, col)?, key),
+		};
+		value.map(|v| v.map(|v| DBValue::from_slice(&v))).map_err(other_io_err)
+	}
+
+	fn get_by_prefix(&self, col: Option<u32>, prefix: &[u8]) -> Option<Box<[u8]>> {
+		self.iter_from_prefix(col, prefix).next().map(|(_, value)| value)
+	}
+
+	fn write_buffered(&self, transaction: DBTransaction) {
+		if let Err(e) = self.buffer(transaction) {
+			log::error!(target: "db", "Failed to write to the database: {}", e);
+		}
+	}
+
+	fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+		self.buffer(transaction)?;
+		self.flush()
+	}
+
+	fn flush(&self) -> io::Result<()> {
+		let mut overlay = self.overlay.write();
+		if overlay.iter().all(|column| column.is_empty()) {
+			return Ok(());
+		}
+
+		let db = self.db.read();
+		let db = db.as_ref().ok_or_else(|| other_io_err("The database is closed"))?;
+		let mut batch = WriteBatch::default();
+		for (index, column) in overlay.iter().enumerate() {
+			let cf = match index {
+				0 => None,
+				index => Some(self.cf(db, index as u32 - 1)?),
+			};
+			for (key, value) in column {
+				match (cf, value) {
+					(None, Some(value)) => batch.put(key, value),
+					(Some(cf), Some(value)) => batch.put_cf(cf, key, value),
+					(None, None) => batch.delete(key),
+					(Some(cf), None) => batch.delete_cf(cf, key),
+				}.map_err(other_io_err)?;
+			}
+		}
+		db.write(batch).map_err(other_io_err)?;
+
+		overlay.iter_mut().for_each(ColumnOverlay::clear);
+		Ok(())
+	}
+
+	fn iter<'a>(&'a self, col: Option<u32>) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+		Box::new(Iter { db: self, col, from: Some(Bound::Unbounded), chunk: Vec::new().into_iter() })
+	}
+
+	fn iter_from_prefix<'a>(
+		&'a self,
+		col: Option<u32>,
+		prefix: &'a [u8],
+	) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+		let iter = Iter {
+			db: self,
+			col,
+			from: Some(Bound::Included(prefix.to_vec())),
+			chunk: Vec::new().into_iter(),
+		};
+		Box::new(iter.take_while(move |(key, _)| key.starts_with(prefix)))
+	}
+
+	fn restore(&self, new_db: &str) -> io::Result<()> {
+		let mut overlay = self.overlay.write();
+		let mut db = self.db.write();
+
+		// the database is closed while its files are replaced, and the changes not flushed
+		// yet are dropped with it.
+		*db = None;
+		overlay.iter_mut().for_each(ColumnOverlay::clear);
+		let replaced = replace_files(&self.path, new_db);
+		*db = Some(open_db(&self.path, &self.columns).map_err(other_io_err)?);
+		replaced
+	}
+}
+
+impl Drop for RocksDb {
+	fn drop(&mut self) {
+		if let Err(e) = self.flush() {
+			log::error!(target: "db", "Failed to flush the database: {}", e);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::database::ColumnConfig;
+
+	fn resolved_columns(num_columns: u32) -> Vec<ResolvedColumn> {
+		(0..num_columns).map(|index| ResolvedColumn {
+			index,
+			config: ColumnConfig::default(),
+			cache_size: 8 * 1024 * 1024,
+		}).collect()
+	}
+
+	#[test]
+	fn opens_database_created_by_kvdb_rocksdb() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().to_str().unwrap();
+
+		{
+			let config = kvdb_rocksdb::DatabaseConfig::with_columns(Some(3));
+			let db = kvdb_rocksdb::Database::open(&config, path).unwrap();
+			let mut transaction = db.transaction();
+			transaction.put(Some(0), b"meta", b"meta value");
+			transaction.put(Some(2), b"body", b"body value");
+			db.write(transaction).unwrap();
+		}
+
+		let db = RocksDb::open(dir.path(), resolved_columns(3)).unwrap();
+		assert_eq!(db.get(Some(0), b"meta").unwrap().unwrap().to_vec(), b"meta value".to_vec());
+		assert_eq!(db.get(Some(2), b"body").unwrap().unwrap().to_vec(), b"body value".to_vec());
+		assert!(db.get(Some(1), b"meta").unwrap().is_none());
+
+		let mut transaction = DBTransaction::new();
+		transaction.put(Some(1), b"state", b"state value");
+		db.write(transaction).unwrap();
+		drop(db);
+
+		let config = kvdb_rocksdb::DatabaseConfig::with_columns(Some(3));
+		let db = kvdb_rocksdb::Database::open(&config, path).unwrap();
+		assert_eq!(db.get(Some(1), b"state").unwrap().unwrap().to_vec(), b"state value".to_vec());
+	}
+
+	fn entries(iter: Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + '_>) -> Vec<(Vec<u8>, Vec<u8>)> {
+		iter.map(|(key, value)| (key.to_vec(), value.to_vec())).collect()
+	}
+
+	#[test]
+	fn buffered_writes_are_only_written_on_flush() {
+		let dir = tempfile::tempdir().unwrap();
+		let db = RocksDb::open(dir.path(), resolved_columns(2)).unwrap();
+
+		let mut transaction = DBTransaction::new();
+		transaction.put(Some(0), b"key1", b"written");
+		transaction.put(Some(0), b"key3", b"deleted");
+		db.write(transaction).unwrap();
+
+		let mut transaction = DBTransaction::new();
+		transaction.put(Some(0), b"key2", b"buffered");
+		transaction.delete(Some(0), b"key3");
+		db.write_buffered(transaction);
+
+		let expected = vec![(b"key1".to_vec(), b"written".to_vec()), (b"key2".to_vec(), b"buffered".to_vec())];
+		assert_eq!(db.get(Some(0), b"key2").unwrap().unwrap().to_vec(), b"buffered".to_vec());
+		assert!(db.get(Some(0), b"key3").unwrap().is_none());
+		assert_eq!(entries(db.iter(Some(0))), expected);
+		assert_eq!(entries(db.iter_from_prefix(Some(0), b"key2")), expected[1..].to_vec());
+		{
+			let db = db.db.read();
+			let db = db.as_ref().unwrap();
+			let cf = db.cf_handle("col0").unwrap();
+			assert!(db.get_cf(cf, b"key2").unwrap().is_none());
+			assert!(db.get_cf(cf, b"key3").unwrap().is_some());
+		}
+
+		db.flush().unwrap();
+		drop(db);
+		let db = RocksDb::open(dir.path(), resolved_columns(2)).unwrap();
+		assert_eq!(entries(db.iter(Some(0))), expected);
+	}
+
+	#[test]
+	fn iterates_over_several_chunks() {
+		let dir = tempfile::tempdir().unwrap();
+		let db = RocksDb::open(dir.path(), resolved_columns(1)).unwrap();
+
+		let count = ITER_CHUNK_SIZE as u32 * 2 + 10;
+		let mut transaction = DBTransaction::new();
+		for i in 0..count {
+			transaction.put(Some(0), &i.to_be_bytes(), b"value");
+		}
+		db.write(transaction).unwrap();
+		let mut transaction = DBTransaction::new();
+		transaction.delete(Some(0), &(ITER_CHUNK_SIZE as u32).to_be_bytes());
+		db.write_buffered(transaction);
+
+		let keys = db.iter(Some(0)).map(|(key, _)| key.to_vec()).collect::<Vec<_>>();
+		let expected = (0..count)
+			.filter(|i| *i != ITER_CHUNK_SIZE as u32)
+			.map(|i| i.to_be_bytes().to_vec())
+			.collect::<Vec<_>>();
+		assert_eq!(keys, expected);
+	}
+
+	#[test]
+	fn restore_replaces_the_database() {
+		let dir = tempfile::tempdir().unwrap();
+		let (path, backup_path) = (dir.path().join("db"), dir.path().join("new"));
+		{
+			let backup = RocksDb::open(&backup_path, resolved_columns(1)).unwrap();
+			let mut transaction = DBTransaction::new();
+			transaction.put(Some(0), b"key", b"restored");
+			backup.write(transaction).unwrap();
+		}
+
+		let db = RocksDb::open(&path, resolved_columns(1)).unwrap();
+		let mut transaction = DBTransaction::new();
+		transaction.put(Some(0), b"key", b"replaced");
+		transaction.put(Some(0), b"other", b"replaced");
+		db.write(transaction).unwrap();
+
+		db.restore(backup_path.to_str().unwrap()).unwrap();
+		assert_eq!(db.get(Some(0), b"key").unwrap().unwrap().to_vec(), b"restored".to_vec());
+		assert!(db.get(Some(0), b"other").unwrap().is_none());
+		assert!(!backup_path.exists());
+	}
+}
@@ -0,0 +1,168 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Sled backend.
+//!
+//! All columns are kept in the default tree of the database, the keys being prefixed
+//! with the column, so that a transaction touching several columns is applied atomically.
+//! Sled has no per-tree settings, so databases with column settings are refused.
+
+use std::io;
+use std::path::Path;
+
+use kvdb::{KeyValueDB, DBTransaction, DBOp, DBValue};
+use super::{ColumnConfig, ResolvedColumn};
+
+/// Prefix of the keys of the `None` column. The other columns are prefixed with their
+/// index, starting from 1.
+const NONE_COLUMN_PREFIX: u8 = 0;
+
+fn other_io_err<E: ToString>(e: E) -> io::Error {
+	io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+fn column_prefix(col: Option<u32>) -> u8 {
+	col.map_or(NONE_COLUMN_PREFIX, |col| col as u8 + 1)
+}
+
+fn prefixed_key(col: Option<u32>, key: &[u8]) -> Vec<u8> {
+	let mut prefixed = Vec::with_capacity(key.len() + 1);
+	prefixed.push(column_prefix(col));
+	prefixed.extend_from_slice(key);
+	prefixed
+}
+
+/// Sled database, with all the columns in a single tree.
+pub struct SledDb {
+	db: sled::Db,
+}
+
+impl SledDb {
+	/// Open or create the database at `path`.
+	pub fn open(path: &Path, columns: Vec<ResolvedColumn>) -> Result<Self, String> {
+		if columns.iter().any(|c| c.config != ColumnConfig::default()) {
+			return Err("Column settings are not supported by the sled backend".into());
+		}
+		let cache_size = columns.iter().map(|c| c.cache_size as u64).sum();
+
+		let config = sled::ConfigBuilder::new()
+			.path(path)
+			.cache_capacity(cache_size)
+			.build();
+		Ok(SledDb { db: sled::Db::start(config).map_err(|e| e.to_string())? })
+	}
+}
+
+impl KeyValueDB for SledDb {
+	fn get(&self, col: Option<u32>, key: &[u8]) -> io::Result<Option<DBValue>> {
+		self.db.get(prefixed_key(col, key))
+			.map(|v| v.map(|v| DBValue::from_slice(&v)))
+			.map_err(other_io_err)
+	}
+
+	fn get_by_prefix(&self, col: Option<u32>, prefix: &[u8]) -> Option<Box<[u8]>> {
+		self.iter_from_prefix(col, prefix).next().map(|(_, value)| value)
+	}
+
+	fn write_buffered(&self, transaction: DBTransaction) {
+		if let Err(e) = self.write(transaction) {
+			log::error!(target: "db", "Failed to write to the database: {}", e);
+		}
+	}
+
+	fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+		let mut batch = sled::Batch::default();
+		for op in transaction.ops {
+			match op {
+				DBOp::Insert { col, key, value } => batch.insert(prefixed_key(col, &key), &*value),
+				DBOp::Delete { col, key } => batch.remove(prefixed_key(col, &key)),
+			}
+		}
+		self.db.apply_batch(batch).map_err(other_io_err)
+	}
+
+	fn flush(&self) -> io::Result<()> {
+		self.db.flush().map(|_| ()).map_err(other_io_err)
+	}
+
+	fn iter<'a>(&'a self, col: Option<u32>) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+		self.iter_from_prefix(col, &[])
+	}
+
+	fn iter_from_prefix<'a>(
+		&'a self,
+		col: Option<u32>,
+		prefix: &'a [u8],
+	) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+		Box::new(self.db.scan_prefix(prefixed_key(col, prefix)).filter_map(|item| match item {
+			Ok((key, value)) => Some((Box::from(&key[1..]), Box::from(&*value))),
+			Err(e) => {
+				log::warn!(target: "db", "Failed to iterate over the database: {}", e);
+				None
+			},
+		}))
+	}
+
+	fn restore(&self, _new_db: &str) -> io::Result<()> {
+		Err(other_io_err("Restoring is not supported by the sled backend"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::database::{ColumnsConfig, resolve_columns};
+
+	#[test]
+	fn columns_are_kept_apart() {
+		let tmp = tempfile::tempdir().unwrap();
+		let columns = resolve_columns(&[("a", Some(0)), ("b", Some(1))], 2, &ColumnsConfig::new(), Some(1))
+			.unwrap();
+		let db = SledDb::open(tmp.path(), columns).unwrap();
+
+		let mut transaction = DBTransaction::new();
+		transaction.put(Some(0), b"key1", b"a1");
+		transaction.put(Some(0), b"key2", b"a2");
+		transaction.put(Some(1), b"key1", b"b1");
+		transaction.put(None, b"key1", b"none");
+		db.write(transaction).unwrap();
+
+		assert_eq!(db.get(Some(0), b"key1").unwrap().unwrap().to_vec(), b"a1".to_vec());
+		assert_eq!(db.get(Some(1), b"key1").unwrap().unwrap().to_vec(), b"b1".to_vec());
+		assert_eq!(db.get(None, b"key1").unwrap().unwrap().to_vec(), b"none".to_vec());
+		assert_eq!(db.iter(Some(0)).count(), 2);
+		assert_eq!(
+			db.iter_from_prefix(Some(0), b"key2").map(|(k, v)| (k.to_vec(), v.to_vec())).collect::<Vec<_>>(),
+			vec![(b"key2".to_vec(), b"a2".to_vec())],
+		);
+
+		let mut transaction = DBTransaction::new();
+		transaction.delete(Some(0), b"key1");
+		db.write(transaction).unwrap();
+		assert!(db.get(Some(0), b"key1").unwrap().is_none());
+		assert!(db.get(Some(1), b"key1").unwrap().is_some());
+	}
+
+	#[test]
+	fn column_settings_are_refused() {
+		let tmp = tempfile::tempdir().unwrap();
+		let mut config = ColumnsConfig::new();
+		config.insert("b".into(), ColumnConfig { bloom_filter_bits: None, ..Default::default() });
+		let columns = resolve_columns(&[("a", Some(0)), ("b", Some(1))], 2, &config, Some(1)).unwrap();
+
+		assert!(SledDb::open(tmp.path(), columns).is_err());
+	}
+}
@@ -0,0 +1,299 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Integrity checks of the full database.
+//!
+//! The checks walk the best chain down from the best block and verify that the headers are
+//! linked, that the key lookups point at them, that the block data which should be kept is
+//! there and decodes, and that the trie of every block whose state isn't pruned is complete.
+//! The whole trie of the best block is walked, while only the nodes which differ from the
+//! tries already walked are visited for the blocks below it, and the child tries whose roots are
+//! stored in a trie are walked with it. The meta data, the leaves set and the state database
+//! journal are checked as well.
+
+use std::collections::HashSet;
+
+use client::backend::{Inconsistency, IntegrityReport};
+use client::blockchain::{Backend as BlockchainBackend, HeaderBackend};
+use client::children;
+use client::error::Result as ClientResult;
+use hash_db::Prefix;
+use primitives::{H256, Blake2Hasher, hexdisplay::HexDisplay, storage::well_known_keys};
+use sr_primitives::generic::BlockId;
+use sr_primitives::traits::{Block as BlockT, Header as HeaderT, NumberFor, One, Zero, SaturatedConversion};
+use trie::{Layout, TrieConfiguration, NodeCodec};
+use trie_db::{NibbleVec, NodeCodec as NodeCodecT, node::Node};
+
+use crate::utils::meta_keys;
+use crate::{columns, Backend, KeepBlocks, StorageDb};
+
+/// Walks tries of the state database, skipping the subtries which were already walked.
+struct TrieWalker<'a, Block: BlockT> {
+	storage: &'a StorageDb<Block>,
+	/// Roots of the subtries known to be complete.
+	complete: HashSet<H256>,
+}
+
+impl<'a, Block: BlockT> TrieWalker<'a, Block> {
+	fn new(storage: &'a StorageDb<Block>) -> Self {
+		TrieWalker { storage, complete: HashSet::new() }
+	}
+
+	// walk the trie with the given root, returning the first error.
+	fn check(&mut self, root: &H256) -> Result<(), String> {
+		// the empty trie has no node in the database.
+		if *root == Layout::<Blake2Hasher>::trie_root(Vec::<(Vec<u8>, Vec<u8>)>::new()) {
+			return Ok(());
+		}
+
+		self.check_hashed_node(root, &mut NibbleVec::new())
+	}
+
+	fn check_hashed_node(&mut self, hash: &H256, key: &mut NibbleVec) -> Result<(), String> {
+		if self.complete.contains(hash) {
+			return Ok(());
+		}
+
+		let prefix: Prefix = key.as_prefix();
+		let data = state_machine::Storage::<Blake2Hasher>::get(self.storage, hash, prefix)?
+			.ok_or_else(|| format!("Node {} is missing", hash))?;
+		self.check_node(&data, key).map_err(|e| format!("{} (in node {})", e, hash))?;
+
+		self.complete.insert(*hash);
+		Ok(())
+	}
+
+	fn check_node(&mut self, data: &[u8], key: &mut NibbleVec) -> Result<(), String> {
+		let node = <NodeCodec<Blake2Hasher> as NodeCodecT<Blake2Hasher>>::decode(data)
+			.map_err(|e| format!("Node can't be decoded: {:?}", e))?;
+		match node {
+			Node::Empty => Ok(()),
+			Node::Leaf(partial, value) => {
+				let len = key.len();
+				key.append_partial(partial.right());
+				let result = self.check_value(key, value);
+				key.drop_lasts(key.len() - len);
+				result
+			},
+			Node::Extension(partial, child) => {
+				let len = key.len();
+				key.append_partial(partial.right());
+				let result = self.check_child(child, key);
+				key.drop_lasts(key.len() - len);
+				result
+			},
+			Node::Branch(children, value) => self.check_branch(&children, value, key),
+			Node::NibbledBranch(partial, children, value) => {
+				let len = key.len();
+				key.append_partial(partial.right());
+				let result = self.check_branch(&children, value, key);
+				key.drop_lasts(key.len() - len);
+				result
+			},
+		}
+	}
+
+	fn check_branch(
+		&mut self,
+		children: &[Option<&[u8]>; 16],
+		value: Option<&[u8]>,
+		key: &mut NibbleVec,
+	) -> Result<(), String> {
+		if let Some(value) = value {
+			self.check_value(key, value)?;
+		}
+		self.check_children(children, key)
+	}
+
+	fn check_children(&mut self, children: &[Option<&[u8]>; 16], key: &mut NibbleVec) -> Result<(), String> {
+		for (nibble, child) in children.iter().enumerate() {
+			if let Some(child) = child {
+				key.push(nibble as u8);
+				let result = self.check_child(child, key);
+				key.pop();
+				result?;
+			}
+		}
+		Ok(())
+	}
+
+	// values stored under a child storage key are the roots of child tries, walked as well.
+	fn check_value(&mut self, key: &NibbleVec, value: &[u8]) -> Result<(), String> {
+		if key.len() % 2 != 0 || key.len() < 2 * well_known_keys::CHILD_STORAGE_KEY_PREFIX.len() {
+			return Ok(());
+		}
+		let key = (0..key.len() / 2)
+			.map(|i| key.at(2 * i) << 4 | key.at(2 * i + 1))
+			.collect::<Vec<u8>>();
+		if !well_known_keys::is_child_storage_key(&key) {
+			return Ok(());
+		}
+
+		if value.len() != std::mem::size_of::<H256>() {
+			return Err(format!("Root of child trie {} isn't a hash", HexDisplay::from(&key)));
+		}
+		self.check(&H256::from_slice(value))
+			.map_err(|e| format!("{} (in child trie {})", e, HexDisplay::from(&key)))
+	}
+
+	// children are either referenced by their hash or inlined in their parent.
+	fn check_child(&mut self, child: &[u8], key: &mut NibbleVec) -> Result<(), String> {
+		match <NodeCodec<Blake2Hasher> as NodeCodecT<Blake2Hasher>>::try_decode_hash(child) {
+			Some(hash) => self.check_hashed_node(&hash, key),
+			None => self.check_node(child, key),
+		}
+	}
+}
+
+fn inconsistency<N: std::fmt::Display>(block: Option<N>, description: String) -> Inconsistency<N> {
+	match &block {
+		Some(number) => log::warn!(target: "db", "Inconsistency at #{}: {}", number, description),
+		None => log::warn!(target: "db", "Inconsistency: {}", description),
+	}
+	Inconsistency { block, description }
+}
+
+/// Check the integrity of the last `blocks` blocks of the best chain and of the
+/// structures shared by all the blocks.
+pub(crate) fn check_integrity<Block: BlockT<Hash=H256>>(
+	backend: &Backend<Block>,
+	blocks: NumberFor<Block>,
+) -> ClientResult<IntegrityReport<NumberFor<Block>>> {
+	let blockchain = &backend.blockchain;
+	let info = blockchain.info();
+	let mut inconsistencies = Vec::new();
+
+	// meta data.
+	if info.finalized_number > info.best_number {
+		inconsistencies.push(inconsistency(None, format!(
+			"Finalized block #{} is above the best block #{}", info.finalized_number, info.best_number,
+		)));
+	}
+	match blockchain.hash(info.finalized_number)? {
+		Some(hash) if hash == info.finalized_hash => {},
+		hash => inconsistencies.push(inconsistency(Some(info.finalized_number), format!(
+			"Finalized block is {}, but the canonical block at its height is {:?}", info.finalized_hash, hash,
+		))),
+	}
+
+	// leaves set.
+	let leaves = blockchain.leaves.read().hashes();
+	if !leaves.contains(&info.best_hash) {
+		inconsistencies.push(inconsistency(None, format!("Best block {} isn't in the leaves set", info.best_hash)));
+	}
+	for leaf in leaves {
+		match blockchain.header(BlockId::Hash(leaf)) {
+			Ok(Some(_)) => {},
+			Ok(None) => inconsistencies.push(inconsistency(None, format!("Header of leaf {} is missing", leaf))),
+			Err(e) => inconsistencies.push(inconsistency(None, format!("Header of leaf {} can't be read: {}", leaf, e))),
+		}
+		let children: Vec<Block::Hash> = children::read_children(
+			&*blockchain.db, columns::META, meta_keys::CHILDREN_PREFIX, leaf,
+		)?;
+		if !children.is_empty() {
+			inconsistencies.push(inconsistency(None, format!("Leaf {} has {} children", leaf, children.len())));
+		}
+	}
+
+	// state database journal.
+	if let Some(canonical) = backend.storage.state_db.best_canonical() {
+		if canonical > info.best_number.saturated_into::<u64>() {
+			inconsistencies.push(inconsistency(None, format!(
+				"State of block #{} is canonicalized, above the best block #{}", canonical, info.best_number,
+			)));
+		}
+	}
+
+	// best chain, from the best block down.
+	let mut tries = TrieWalker::new(&backend.storage);
+	let mut hash = info.best_hash;
+	let mut number = info.best_number;
+	let mut checked_blocks = 0u64;
+	loop {
+		checked_blocks += 1;
+		let mut block_inconsistency = |description| inconsistencies.push(inconsistency(Some(number), description));
+
+		let header = match blockchain.header(BlockId::Hash(hash)) {
+			Ok(Some(header)) => header,
+			Ok(None) => {
+				block_inconsistency(format!("Header of {} is missing", hash));
+				break;
+			},
+			Err(e) => {
+				block_inconsistency(format!("Header of {} can't be read: {}", hash, e));
+				break;
+			},
+		};
+		if header.hash() != hash || *header.number() != number {
+			block_inconsistency(format!(
+				"Header stored for {} is the one of #{} ({})", hash, header.number(), header.hash(),
+			));
+			break;
+		}
+
+		match blockchain.hash(number) {
+			Ok(Some(canonical)) if canonical == hash => {},
+			Ok(canonical) => block_inconsistency(format!(
+				"Canonical block lookup points at {:?} instead of {}", canonical, hash,
+			)),
+			Err(e) => block_inconsistency(format!("Canonical block lookup failed: {}", e)),
+		}
+
+		let body_kept = match backend.retention.bodies {
+			KeepBlocks::All => true,
+			KeepBlocks::Some(keep_blocks) => number + keep_blocks.into() > info.finalized_number,
+		};
+		match blockchain.body(BlockId::Hash(hash)) {
+			Ok(None) if body_kept => block_inconsistency("Body is missing".into()),
+			Ok(_) => {},
+			Err(e) => block_inconsistency(format!("Body can't be read: {}", e)),
+		}
+		if let Err(e) = blockchain.justification(BlockId::Hash(hash)) {
+			block_inconsistency(format!("Justification can't be read: {}", e));
+		}
+
+		if !backend.storage.state_db.is_pruned(&hash, number.saturated_into::<u64>()) {
+			if let Err(e) = tries.check(header.state_root()) {
+				block_inconsistency(format!("State trie is incomplete: {}", e));
+			}
+		} else if number == info.best_number {
+			block_inconsistency("State of the best block is pruned".into());
+		}
+
+		if number.is_zero() || checked_blocks >= blocks.saturated_into::<u64>() {
+			break;
+		}
+		hash = *header.parent_hash();
+		number -= One::one();
+	}
+
+	// reverting below the lowest inconsistent block drops all the inconsistent block data.
+	let lowest_inconsistent = inconsistencies.iter().filter_map(|i| i.block).min();
+	let last_consistent = match lowest_inconsistent {
+		Some(number) if number.is_zero() => None,
+		Some(number) => Some(number - One::one()),
+		None if inconsistencies.is_empty() => Some(info.best_number),
+		None => None,
+	};
+
+	Ok(IntegrityReport {
+		best: info.best_number,
+		finalized: info.finalized_number,
+		checked_blocks,
+		inconsistencies,
+		last_consistent,
+	})
+}
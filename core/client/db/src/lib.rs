@@ -30,6 +30,8 @@ pub mod light;
 pub mod offchain;
 
 mod cache;
+mod database;
+mod integrity;
 mod storage_cache;
 mod utils;
 
@@ -70,6 +72,7 @@ use header_metadata::{CachedHeaderMetadata, HeaderMetadata, HeaderMetadataCache}
 use crate::storage_cache::{CachingState, SharedCache, new_shared_cache};
 use log::{trace, debug, warn};
 pub use state_db::PruningMode;
+pub use crate::database::{ColumnConfig, ColumnsConfig, Compression, DatabaseBackend};
 
 #[cfg(feature = "test-helpers")]
 use client::in_mem::Backend as InMemoryBackend;
@@ -200,18 +203,69 @@ pub struct DatabaseSettings {
 	pub state_cache_child_ratio: Option<(usize, usize)>,
 	/// Pruning mode.
 	pub pruning: PruningMode,
+	/// Retention of the block data which isn't covered by the state pruning.
+	pub retention: RetentionPolicy,
 	/// Where to find the database.
 	pub source: DatabaseSettingsSrc,
 }
 
+/// How long block data is kept once its block is finalized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepBlocks {
+	/// Keep the data of all the blocks.
+	All,
+	/// Keep the data of the given number of most recently finalized blocks.
+	Some(u32),
+}
+
+/// Which justifications are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepJustifications {
+	/// Keep all the justifications.
+	All,
+	/// Keep the justifications marked as required, which are the ones of authority set
+	/// changes, and the latest justification.
+	AuthoritySetChanges,
+}
+
+/// Retention policy of the block data, enforced when blocks are finalized.
+///
+/// Changing the policy of an existing database doesn't remove the data of the blocks
+/// which were finalized before.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+	/// Retention of the block bodies.
+	pub bodies: KeepBlocks,
+	/// Retention of the justifications.
+	pub justifications: KeepJustifications,
+	/// Retention of the changes tries. Changes tries are never pruned when the state
+	/// isn't pruned either.
+	pub changes_tries: KeepBlocks,
+}
+
+impl Default for RetentionPolicy {
+	fn default() -> Self {
+		RetentionPolicy {
+			bodies: KeepBlocks::All,
+			justifications: KeepJustifications::All,
+			changes_tries: KeepBlocks::Some(MIN_BLOCKS_TO_KEEP_CHANGES_TRIES_FOR),
+		}
+	}
+}
+
 /// Where to find the database..
 pub enum DatabaseSettingsSrc {
 	/// Load a database from a given path. Recommended for most uses.
 	Path {
 		/// Path to the database.
 		path: PathBuf,
-		/// Cache size in bytes. If `None` default is used.
+		/// Cache size in MiB. If `None` default is used.
 		cache_size: Option<usize>,
+		/// Key-value store to use.
+		backend: DatabaseBackend,
+		/// Settings of the columns, by name. See [`COLUMN_NAMES`] and
+		/// [`light::COLUMN_NAMES`] for the columns of the full and the light databases.
+		columns: ColumnsConfig,
 	},
 
 	/// Use a custom already-open database.
@@ -265,6 +319,20 @@ pub(crate) mod columns {
 	pub const OFFCHAIN: Option<u32> = Some(9);
 }
 
+/// Names of the columns of the full database, which can be used to configure them.
+pub const COLUMN_NAMES: &[(&str, Option<u32>)] = &[
+	("meta", columns::META),
+	("state", columns::STATE),
+	("state_meta", columns::STATE_META),
+	("key_lookup", columns::KEY_LOOKUP),
+	("header", columns::HEADER),
+	("body", columns::BODY),
+	("justification", columns::JUSTIFICATION),
+	("changes_trie", columns::CHANGES_TRIE),
+	("aux", columns::AUX),
+	("offchain", columns::OFFCHAIN),
+];
+
 struct PendingBlock<Block: BlockT> {
 	header: Block::Header,
 	justification: Option<Justification>,
@@ -456,6 +524,7 @@ pub struct BlockImportOperation<Block: BlockT, H: Hasher> {
 	pending_block: Option<PendingBlock<Block>>,
 	aux_ops: Vec<(Vec<u8>, Option<Vec<u8>>)>,
 	finalized_blocks: Vec<(BlockId<Block>, Option<Justification>)>,
+	required_justifications: Vec<BlockId<Block>>,
 	set_head: Option<BlockId<Block>>,
 	commit_state: bool,
 }
@@ -572,6 +641,11 @@ impl<Block> client::backend::BlockImportOperation<Block, Blake2Hasher>
 		self.set_head = Some(block);
 		Ok(())
 	}
+
+	fn mark_justification_required(&mut self, block: BlockId<Block>) -> ClientResult<()> {
+		self.required_justifications.push(block);
+		Ok(())
+	}
 }
 
 struct StorageDb<Block: BlockT> {
@@ -786,6 +860,7 @@ pub struct Backend<Block: BlockT> {
 	shared_cache: SharedCache<Block, Blake2Hasher>,
 	import_lock: Mutex<()>,
 	is_archive: bool,
+	retention: RetentionPolicy,
 }
 
 impl<Block: BlockT<Hash=H256>> Backend<Block> {
@@ -793,7 +868,7 @@ impl<Block: BlockT<Hash=H256>> Backend<Block> {
 	///
 	/// The pruning window is how old a block must be before the state is pruned.
 	pub fn new(config: DatabaseSettings, canonicalization_delay: u64) -> ClientResult<Self> {
-		let db = crate::utils::open_database(&config, columns::META, "full")?;
+		let db = crate::utils::open_database(&config, COLUMN_NAMES, columns::META, "full")?;
 		Self::from_kvdb(db as Arc<_>, canonicalization_delay, &config)
 	}
 
@@ -805,6 +880,7 @@ impl<Block: BlockT<Hash=H256>> Backend<Block> {
 			state_cache_size: 16777216,
 			state_cache_child_ratio: Some((50, 100)),
 			pruning: PruningMode::keep_blocks(keep_blocks),
+			retention: Default::default(),
 			source: DatabaseSettingsSrc::Custom(db),
 		};
 
@@ -829,7 +905,10 @@ impl<Block: BlockT<Hash=H256>> Backend<Block> {
 		let changes_tries_storage = DbChangesTrieStorage {
 			db,
 			meta,
-			min_blocks_to_keep: if is_archive_pruning { None } else { Some(MIN_BLOCKS_TO_KEEP_CHANGES_TRIES_FOR) },
+			min_blocks_to_keep: match config.retention.changes_tries {
+				KeepBlocks::Some(blocks) if !is_archive_pruning => Some(blocks),
+				_ => None,
+			},
 			cache: RwLock::new(ChangesTrieBuildCache::new()),
 			_phantom: Default::default(),
 		};
@@ -847,6 +926,7 @@ impl<Block: BlockT<Hash=H256>> Backend<Block> {
 			),
 			import_lock: Default::default(),
 			is_archive: is_archive_pruning,
+			retention: config.retention.clone(),
 		})
 	}
 
@@ -1015,6 +1095,8 @@ impl<Block: BlockT<Hash=H256>> Backend<Block> {
 		header: &Block::Header,
 		last_finalized: Option<Block::Hash>,
 		justification: Option<Justification>,
+		justification_required: bool,
+		prunable_justification: &mut Option<Vec<u8>>,
 		finalization_displaced: &mut Option<FinalizationDisplaced<Block::Hash, NumberFor<Block>>>,
 	) -> ClientResult<(Block::Hash, <Block::Header as HeaderT>::Number, bool, bool)> {
 		// TODO: ensure best chain contains this block.
@@ -1028,15 +1110,60 @@ impl<Block: BlockT<Hash=H256>> Backend<Block> {
 		)?;
 
 		if let Some(justification) = justification {
-			transaction.put(
-				columns::JUSTIFICATION,
+			self.write_justification(
+				transaction,
 				&utils::number_and_hash_to_lookup_key(number, hash)?,
-				&justification.encode(),
-			);
+				&justification,
+				justification_required,
+				prunable_justification,
+			)?;
 		}
 		Ok((*hash, number, false, true))
 	}
 
+	// lookup key of the justification to prune when the next justification is written.
+	// it is read once per transaction and then tracked by `write_justification`, since
+	// the transaction may write several justifications.
+	fn prunable_justification(&self) -> ClientResult<Option<Vec<u8>>> {
+		if self.retention.justifications != KeepJustifications::AuthoritySetChanges {
+			return Ok(None);
+		}
+
+		self.storage.db.get(columns::META, meta_keys::PRUNABLE_JUSTIFICATION)
+			.map(|prunable| prunable.map(|prunable| prunable.to_vec()))
+			.map_err(db_err)
+	}
+
+	// write a justification. unless it is required, it is pruned when the next justification
+	// is written if only the justifications of authority set changes are kept.
+	fn write_justification(
+		&self,
+		transaction: &mut DBTransaction,
+		lookup_key: &[u8],
+		justification: &Justification,
+		required: bool,
+		prunable_justification: &mut Option<Vec<u8>>,
+	) -> ClientResult<()> {
+		transaction.put(columns::JUSTIFICATION, lookup_key, &justification.encode());
+
+		if self.retention.justifications == KeepJustifications::AuthoritySetChanges {
+			if let Some(prunable) = prunable_justification.take() {
+				if &prunable[..] != lookup_key {
+					transaction.delete(columns::JUSTIFICATION, &prunable);
+				}
+			}
+
+			if required {
+				transaction.delete(columns::META, meta_keys::PRUNABLE_JUSTIFICATION);
+			} else {
+				transaction.put(columns::META, meta_keys::PRUNABLE_JUSTIFICATION, lookup_key);
+				*prunable_justification = Some(lookup_key.to_vec());
+			}
+		}
+
+		Ok(())
+	}
+
 	// performs forced canonicaliziation with a delay after importing a non-finalized block.
 	fn force_delayed_canonicalize(
 		&self,
@@ -1081,6 +1208,11 @@ impl<Block: BlockT<Hash=H256>> Backend<Block> {
 
 		let mut meta_updates = Vec::with_capacity(operation.finalized_blocks.len());
 		let mut last_finalized_hash = self.blockchain.meta.read().finalized_hash;
+		let mut prunable_justification = self.prunable_justification()?;
+
+		let required_justifications = operation.required_justifications.iter()
+			.map(|block| self.blockchain.expect_block_hash_from_id(block))
+			.collect::<ClientResult<HashSet<_>>>()?;
 
 		for (block, justification) in operation.finalized_blocks {
 			let block_hash = self.blockchain.expect_block_hash_from_id(&block)?;
@@ -1092,6 +1224,8 @@ impl<Block: BlockT<Hash=H256>> Backend<Block> {
 				&block_header,
 				Some(last_finalized_hash),
 				justification,
+				required_justifications.contains(&block_hash),
+				&mut prunable_justification,
 				&mut finalization_displaced_leaves,
 			)?);
 			last_finalized_hash = block_hash;
@@ -1129,7 +1263,13 @@ impl<Block: BlockT<Hash=H256>> Backend<Block> {
 				transaction.put(columns::BODY, &lookup_key, &body.encode());
 			}
 			if let Some(justification) = pending_block.justification {
-				transaction.put(columns::JUSTIFICATION, &lookup_key, &justification.encode());
+				self.write_justification(
+					&mut transaction,
+					&lookup_key,
+					&justification,
+					required_justifications.contains(&hash),
+					&mut prunable_justification,
+				)?;
 			}
 
 			if number.is_zero() {
@@ -1293,6 +1433,22 @@ impl<Block: BlockT<Hash=H256>> Backend<Block> {
 			}
 		}
 
+		// prune the body of the block leaving the retention window.
+		if let KeepBlocks::Some(keep_blocks) = self.retention.bodies {
+			if f_num >= keep_blocks.into() {
+				let prune_num = f_num - keep_blocks.into();
+				let prune_hash = if prune_num == f_num {
+					Some(f_hash)
+				} else {
+					::client::blockchain::HeaderBackend::hash(&self.blockchain, prune_num)?
+				};
+				if let Some(prune_hash) = prune_hash {
+					let lookup_key = utils::number_and_hash_to_lookup_key(prune_num, prune_hash)?;
+					transaction.delete(columns::BODY, &lookup_key);
+				}
+			}
+		}
+
 		let new_displaced = self.blockchain.leaves.write().finalize_height(f_num);
 		match displaced {
 			x @ &mut None => *x = Some(new_displaced),
@@ -1361,6 +1517,7 @@ impl<Block> client::backend::Backend<Block, Blake2Hasher> for Backend<Block> whe
 			changes_trie_cache_update: None,
 			aux_ops: Vec::new(),
 			finalized_blocks: Vec::new(),
+			required_justifications: Vec::new(),
 			set_head: None,
 			commit_state: false,
 		})
@@ -1397,6 +1554,7 @@ impl<Block> client::backend::Backend<Block, Blake2Hasher> for Backend<Block> whe
 		let mut transaction = DBTransaction::new();
 		let hash = self.blockchain.expect_block_hash_from_id(&block)?;
 		let header = self.blockchain.expect_header(block)?;
+		let mut prunable_justification = self.prunable_justification()?;
 		let mut displaced = None;
 		let commit = |displaced| {
 			let (hash, number, is_best, is_finalized) = self.finalize_block_with_transaction(
@@ -1405,6 +1563,8 @@ impl<Block> client::backend::Backend<Block, Blake2Hasher> for Backend<Block> whe
 				&header,
 				None,
 				justification,
+				false,
+				&mut prunable_justification,
 				displaced,
 			)?;
 			self.storage.db.write(transaction).map_err(db_err)?;
@@ -1468,6 +1628,91 @@ impl<Block> client::backend::Backend<Block, Blake2Hasher> for Backend<Block> whe
 		Ok(n)
 	}
 
+	fn unsafe_revert(
+		&self,
+		n: NumberFor<Block>,
+		aux: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+	) -> ClientResult<()> {
+		let info = self.blockchain.info();
+		if n > info.best_number {
+			return Err(client::error::Error::Backend(
+				format!("Cannot revert {} blocks, the best block is #{}", n, info.best_number)
+			));
+		}
+		if let Some(revertible) = self.storage.state_db.revertible_blocks(true) {
+			if revertible < n.saturated_into::<u64>() {
+				return Err(client::error::Error::Backend(format!(
+					"Cannot revert {} blocks, the state is only available to revert {} blocks",
+					n, revertible,
+				)));
+			}
+		}
+
+		// the headers are all looked up before anything changes.
+		let mut removed = Vec::new();
+		let mut best = info.best_number;
+		for _ in 0 .. n.saturated_into::<u64>() {
+			let header = self.blockchain.header(BlockId::Number(best))?.ok_or_else(
+				|| client::error::Error::UnknownBlock(
+					format!("Error reverting to {}. Block hash not found.", best)))?;
+			best -= One::one();	// prev block
+			removed.push(header);
+		}
+		let best_hash = removed.last().map_or(info.best_hash, |header| *header.parent_hash());
+
+		// the blocks are all reverted in a single transaction, along with the aux data.
+		let mut transaction = DBTransaction::new();
+		for header in &removed {
+			transaction.delete(columns::KEY_LOOKUP, header.hash().as_ref());
+			children::remove_children(&mut transaction, columns::META, meta_keys::CHILDREN_PREFIX, *header.parent_hash());
+		}
+
+		let key = utils::number_and_hash_to_lookup_key(best, &best_hash)?;
+		transaction.put(columns::META, meta_keys::BEST_BLOCK, &key);
+		let is_finalized = best <= info.finalized_number;
+		if is_finalized {
+			transaction.put(columns::META, meta_keys::FINALIZED_BLOCK, &key);
+		}
+		for (key, value) in aux {
+			match value {
+				Some(value) => transaction.put_vec(columns::AUX, &key, value),
+				None => transaction.delete(columns::AUX, &key),
+			}
+		}
+
+		// the leaves and the state are only changed in memory once the transaction is written.
+		let mut leaves = self.blockchain.leaves.write();
+		let mut reverted_leaves = leaves.clone();
+		for header in &removed {
+			reverted_leaves.revert(header.hash(), *header.number(), *header.parent_hash());
+		}
+		reverted_leaves.prepare_transaction(&mut transaction, columns::META, meta_keys::LEAF_PREFIX);
+
+		let parents = removed.iter().map(|header| *header.parent_hash()).collect::<Vec<_>>();
+		let reverted = self.storage.state_db.revert(&parents, |commits| {
+			for commit in commits {
+				apply_state_commit(&mut transaction, commit);
+			}
+			self.storage.db.write(transaction).map_err(db_err)
+		})?;
+		if !reverted {
+			return Err(client::error::Error::Backend(
+				format!("Error reverting the state of the last {} blocks", n)
+			));
+		}
+
+		*leaves = reverted_leaves;
+		self.blockchain.update_meta(best_hash, best, true, is_finalized);
+		Ok(())
+	}
+
+	fn check_integrity(
+		&self,
+		blocks: NumberFor<Block>,
+	) -> ClientResult<client::backend::IntegrityReport<NumberFor<Block>>> {
+		integrity::check_integrity(self, blocks)
+	}
+
 	fn blockchain(&self) -> &BlockchainDb<Block> {
 		&self.blockchain
 	}
@@ -1477,6 +1722,10 @@ impl<Block> client::backend::Backend<Block, Blake2Hasher> for Backend<Block> whe
 		Some(used)
 	}
 
+	fn state_cache_stats(&self) -> Option<client::backend::StateCacheStats> {
+		Some(self.shared_cache.lock().stats())
+	}
+
 	fn state_at(&self, block: BlockId<Block>) -> ClientResult<Self::State> {
 		use client::blockchain::HeaderBackend as BcHeaderBackend;
 
@@ -1663,6 +1912,7 @@ mod tests {
 			state_cache_size: 16777216,
 			state_cache_child_ratio: Some((50, 100)),
 			pruning: PruningMode::keep_blocks(1),
+			retention: Default::default(),
 			source: DatabaseSettingsSrc::Custom(backing),
 		}, 0).unwrap();
 		assert_eq!(backend.blockchain().info().best_number, 9);
@@ -2389,4 +2639,263 @@ mod tests {
 			backend.commit_operation(op).unwrap_err();
 		}
 	}
+
+	fn backend_with_retention(retention: RetentionPolicy) -> Backend<Block> {
+		let db = Arc::new(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		Backend::new(DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			pruning: PruningMode::keep_blocks(10),
+			retention,
+			source: DatabaseSettingsSrc::Custom(db),
+		}, 10).unwrap()
+	}
+
+	#[test]
+	fn prunes_bodies_of_old_finalized_blocks() {
+		use client::blockchain::{Backend as BlockChainBackend};
+
+		let backend = backend_with_retention(RetentionPolicy {
+			bodies: KeepBlocks::Some(2),
+			..Default::default()
+		});
+
+		let mut blocks = vec![insert_header(&backend, 0, Default::default(), Default::default(), Default::default())];
+		for number in 1..5 {
+			let parent = *blocks.last().unwrap();
+			blocks.push(insert_header(&backend, number, parent, Default::default(), Default::default()));
+		}
+		for block in &blocks[1..] {
+			backend.finalize_block(BlockId::Hash(*block), None).unwrap();
+		}
+
+		for (number, block) in blocks.iter().enumerate() {
+			let body = backend.blockchain().body(BlockId::Hash(*block)).unwrap();
+			assert_eq!(body.is_some(), number >= 3, "body of block #{}", number);
+			assert!(backend.blockchain().header(BlockId::Hash(*block)).unwrap().is_some());
+		}
+	}
+
+	#[test]
+	fn reverts_finalized_blocks_only_when_asked() {
+		use client::backend::AuxStore;
+		use client::blockchain::{Backend as BlockChainBackend};
+
+		let backend = backend_with_retention(Default::default());
+		let mut blocks = vec![insert_header(&backend, 0, Default::default(), Default::default(), Default::default())];
+		for number in 1..6 {
+			let parent = *blocks.last().unwrap();
+			blocks.push(insert_header(&backend, number, parent, Default::default(), Default::default()));
+		}
+		for block in &blocks[1..5] {
+			backend.finalize_block(BlockId::Hash(*block), None).unwrap();
+		}
+
+		assert_eq!(backend.revert(3).unwrap(), 1);
+		assert_eq!(backend.blockchain().info().best_hash, blocks[4]);
+
+		// reverting below the genesis block fails without changing anything.
+		assert!(backend.unsafe_revert(5, vec![(b"aux".to_vec(), Some(b"too far".to_vec()))]).is_err());
+		assert_eq!(backend.blockchain().info().best_hash, blocks[4]);
+		assert_eq!(backend.get_aux(b"aux").unwrap(), None);
+
+		backend.unsafe_revert(2, vec![(b"aux".to_vec(), Some(b"reverted".to_vec()))]).unwrap();
+		assert_eq!(backend.get_aux(b"aux").unwrap(), Some(b"reverted".to_vec()));
+		let info = backend.blockchain().info();
+		assert_eq!((info.best_number, info.best_hash), (2, blocks[2]));
+		assert_eq!((info.finalized_number, info.finalized_hash), (2, blocks[2]));
+		assert!(backend.have_state_at(&blocks[2], 2));
+		assert!(backend.blockchain().header(BlockId::Hash(blocks[3])).unwrap().is_none());
+
+		// the chain can be continued from the block it was reverted to.
+		let block3 = insert_header(&backend, 3, blocks[2], Default::default(), H256::random());
+		backend.finalize_block(BlockId::Hash(block3), None).unwrap();
+		assert_eq!(backend.blockchain().info().finalized_hash, block3);
+	}
+
+	#[test]
+	fn keeps_only_required_and_latest_justifications() {
+		use client::blockchain::{Backend as BlockChainBackend};
+
+		let backend = backend_with_retention(RetentionPolicy {
+			justifications: KeepJustifications::AuthoritySetChanges,
+			..Default::default()
+		});
+
+		let block0 = insert_header(&backend, 0, Default::default(), Default::default(), Default::default());
+		let block1 = insert_header(&backend, 1, block0, Default::default(), Default::default());
+		let block2 = insert_header(&backend, 2, block1, Default::default(), Default::default());
+		let block3 = insert_header(&backend, 3, block2, Default::default(), Default::default());
+		let justification = |block| backend.blockchain().justification(BlockId::Hash(block)).unwrap();
+
+		backend.finalize_block(BlockId::Hash(block1), Some(vec![1])).unwrap();
+		assert_eq!(justification(block1), Some(vec![1]));
+
+		{
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(block1)).unwrap();
+			op.mark_finalized(BlockId::Hash(block2), Some(vec![2])).unwrap();
+			op.mark_justification_required(BlockId::Hash(block2)).unwrap();
+			backend.commit_operation(op).unwrap();
+		}
+		assert_eq!(justification(block1), None);
+		assert_eq!(justification(block2), Some(vec![2]));
+
+		backend.finalize_block(BlockId::Hash(block3), Some(vec![3])).unwrap();
+		assert_eq!(justification(block2), Some(vec![2]));
+		assert_eq!(justification(block3), Some(vec![3]));
+	}
+
+	#[test]
+	fn prunes_justifications_written_in_the_same_operation() {
+		use client::blockchain::{Backend as BlockChainBackend};
+
+		let backend = backend_with_retention(RetentionPolicy {
+			justifications: KeepJustifications::AuthoritySetChanges,
+			..Default::default()
+		});
+
+		let block0 = insert_header(&backend, 0, Default::default(), Default::default(), Default::default());
+		let block1 = insert_header(&backend, 1, block0, Default::default(), Default::default());
+		let block2 = insert_header(&backend, 2, block1, Default::default(), Default::default());
+		let block3 = insert_header(&backend, 3, block2, Default::default(), Default::default());
+		let block4 = insert_header(&backend, 4, block3, Default::default(), Default::default());
+		let justification = |block| backend.blockchain().justification(BlockId::Hash(block)).unwrap();
+
+		{
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(block0)).unwrap();
+			op.mark_finalized(BlockId::Hash(block1), Some(vec![1])).unwrap();
+			op.mark_finalized(BlockId::Hash(block2), Some(vec![2])).unwrap();
+			op.mark_finalized(BlockId::Hash(block3), Some(vec![3])).unwrap();
+			backend.commit_operation(op).unwrap();
+		}
+		assert_eq!(justification(block1), None);
+		assert_eq!(justification(block2), None);
+		assert_eq!(justification(block3), Some(vec![3]));
+
+		backend.finalize_block(BlockId::Hash(block4), Some(vec![4])).unwrap();
+		assert_eq!(justification(block3), None);
+		assert_eq!(justification(block4), Some(vec![4]));
+	}
+
+	#[test]
+	fn integrity_check_finds_missing_block_data() {
+		let backend = Backend::<Block>::new_test(10, 10);
+		let block0 = insert_header(&backend, 0, Default::default(), Default::default(), Default::default());
+		let block1 = insert_header(&backend, 1, block0, Default::default(), Default::default());
+		let _ = insert_header(&backend, 2, block1, Default::default(), Default::default());
+
+		let report = backend.check_integrity(10).unwrap();
+		assert!(report.is_consistent(), "{:?}", report.inconsistencies);
+		assert_eq!(report.checked_blocks, 3);
+		assert_eq!(report.last_consistent, Some(2));
+
+		let mut transaction = DBTransaction::new();
+		transaction.delete(columns::BODY, &utils::number_and_hash_to_lookup_key(1, block1).unwrap());
+		backend.storage.db.write(transaction).unwrap();
+
+		let report = backend.check_integrity(10).unwrap();
+		assert_eq!(report.inconsistencies.len(), 1);
+		assert_eq!(report.inconsistencies[0].block, Some(1));
+		assert_eq!(report.last_consistent, Some(0));
+
+		let report = backend.check_integrity(1).unwrap();
+		assert!(report.is_consistent());
+		assert_eq!(report.checked_blocks, 1);
+	}
+
+	#[test]
+	fn integrity_check_finds_missing_state_of_older_blocks() {
+		let backend = Backend::<Block>::new_test(10, 0);
+		let mut roots = Vec::new();
+		let mut parent_hash = Default::default();
+		for number in 0..2u64 {
+			let mut op = backend.begin_operation().unwrap();
+			let parent = if number == 0 { BlockId::Hash(Default::default()) } else { BlockId::Number(0) };
+			backend.begin_state_operation(&mut op, parent).unwrap();
+
+			// values are long enough for the leaves not to be inlined in their parent.
+			let root = if number == 0 {
+				let storage = vec![
+					(vec![1, 2, 3], vec![1; 40]),
+					(vec![1, 3, 5], vec![2; 40]),
+					(vec![5, 5, 5], vec![3; 40]),
+				];
+				let root = op.old_state.storage_root(storage.iter().cloned().map(|(k, v)| (k, Some(v)))).0;
+				op.reset_storage(storage.into_iter().collect(), Default::default()).unwrap();
+				root
+			} else {
+				let (root, overlay) = op.old_state.storage_root(vec![(vec![5, 5, 5], Some(vec![4; 40]))]);
+				op.update_db_storage(overlay).unwrap();
+				root
+			};
+
+			let header = Header {
+				number,
+				parent_hash,
+				state_root: root.into(),
+				digest: Default::default(),
+				extrinsics_root: Default::default(),
+			};
+			parent_hash = header.hash();
+			roots.push(root);
+
+			op.set_block_data(header, Some(vec![]), None, NewBlockState::Best).unwrap();
+			backend.commit_operation(op).unwrap();
+		}
+
+		let report = backend.check_integrity(10).unwrap();
+		assert!(report.is_consistent(), "{:?}", report.inconsistencies);
+
+		// the root node of the genesis state isn't shared with the state of block #1.
+		let mut transaction = DBTransaction::new();
+		transaction.delete(columns::STATE, &prefixed_key::<Blake2Hasher>(&roots[0], EMPTY_PREFIX));
+		backend.storage.db.write(transaction).unwrap();
+
+		let report = backend.check_integrity(10).unwrap();
+		assert_eq!(report.inconsistencies.len(), 1, "{:?}", report.inconsistencies);
+		assert_eq!(report.inconsistencies[0].block, Some(0));
+		assert_eq!(report.last_consistent, None);
+	}
+
+	#[test]
+	fn integrity_check_walks_child_tries() {
+		let backend = Backend::<Block>::new_test(10, 0);
+		let child_key = b":child_storage:default:integrity".to_vec();
+		let mut op = backend.begin_operation().unwrap();
+		backend.begin_state_operation(&mut op, BlockId::Hash(Default::default())).unwrap();
+
+		// values are long enough for the leaves not to be inlined in their parent.
+		let child = vec![(vec![1, 2, 3], vec![1; 40]), (vec![5, 5, 5], vec![2; 40])];
+		let root = op.reset_storage(
+			vec![(vec![1, 2, 3], vec![3; 40])].into_iter().collect(),
+			vec![(child_key.clone(), child.into_iter().collect())].into_iter().collect(),
+		).unwrap();
+		let header = Header {
+			number: 0,
+			parent_hash: Default::default(),
+			state_root: root,
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+		op.set_block_data(header, Some(vec![]), None, NewBlockState::Best).unwrap();
+		backend.commit_operation(op).unwrap();
+
+		let report = backend.check_integrity(10).unwrap();
+		assert!(report.is_consistent(), "{:?}", report.inconsistencies);
+
+		let child_root = backend.state_at(BlockId::Number(0)).unwrap().storage(&child_key).unwrap().unwrap();
+		let mut transaction = DBTransaction::new();
+		transaction.delete(columns::STATE, &prefixed_key::<Blake2Hasher>(
+			&H256::from_slice(&child_root),
+			EMPTY_PREFIX,
+		));
+		backend.storage.db.write(transaction).unwrap();
+
+		let report = backend.check_integrity(10).unwrap();
+		assert_eq!(report.inconsistencies.len(), 1, "{:?}", report.inconsistencies);
+		assert_eq!(report.inconsistencies[0].block, Some(0));
+		assert!(report.inconsistencies[0].description.contains("child trie"));
+	}
 }
@@ -50,6 +50,16 @@ pub(crate) mod columns {
 	pub const AUX: Option<u32> = Some(5);
 }
 
+/// Names of the columns of the light database, which can be used to configure them.
+pub const COLUMN_NAMES: &[(&str, Option<u32>)] = &[
+	("meta", columns::META),
+	("key_lookup", columns::KEY_LOOKUP),
+	("header", columns::HEADER),
+	("cache", columns::CACHE),
+	("cht", columns::CHT),
+	("aux", columns::AUX),
+];
+
 /// Prefix for headers CHT.
 const HEADER_CHT_PREFIX: u8 = 0;
 /// Prefix for changes tries roots CHT.
@@ -70,7 +80,7 @@ impl<Block> LightStorage<Block>
 {
 	/// Create new storage with given settings.
 	pub fn new(config: DatabaseSettings) -> ClientResult<Self> {
-		let db = crate::utils::open_database(&config, columns::META, "light")?;
+		let db = crate::utils::open_database(&config, COLUMN_NAMES, columns::META, "light")?;
 		Self::from_kvdb(db as Arc<_>)
 	}
 
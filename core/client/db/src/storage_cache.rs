@@ -25,6 +25,7 @@ use sr_primitives::traits::{Block as BlockT, Header};
 use primitives::hexdisplay::HexDisplay;
 use state_machine::{backend::Backend as StateBackend, TrieBackend};
 use log::trace;
+use client::backend::StateCacheStats;
 use super::{StorageCollection, ChildStorageCollection};
 use std::hash::Hash as StdHash;
 const STATE_CACHE_BLOCKS: usize = 12;
@@ -44,6 +45,8 @@ pub struct Cache<B: BlockT, H: Hasher> {
 	/// Information on the modifications in recently committed blocks; specifically which keys
 	/// changed in which block. Ordered by block number.
 	modifications: VecDeque<BlockChanges<B::Header>>,
+	/// Hits and misses of lookups in the shared cache.
+	stats: StateCacheStats,
 }
 
 struct LRUMap<K, V>(LinkedHashMap<K, V>, usize, usize);
@@ -153,6 +156,11 @@ impl<B: BlockT, H: Hasher> Cache<B, H> {
 			//  ignore small hashes storage and self.lru_hashes.used_size()
 	}
 
+	/// Returns the hits and misses of lookups in the storage cache.
+	pub fn stats(&self) -> StateCacheStats {
+		self.stats
+	}
+
 	/// Synchronize the shared cache with the best block state.
 	/// This function updates the shared cache by removing entries
 	/// that are invalidated by chain reorganization. It should be called
@@ -230,6 +238,7 @@ pub fn new_shared_cache<B: BlockT, H: Hasher>(
 		lru_child_storage: LRUMap(LinkedHashMap::new(), 0,
 			shared_cache_size * child_ratio.0 / child_ratio.1),
 		modifications: VecDeque::new(),
+		stats: Default::default(),
 	}))
 }
 
@@ -478,9 +487,11 @@ impl<H: Hasher, S: StateBackend<H>, B: BlockT> StateBackend<H> for CachingState<
 		if Self::is_allowed(Some(key), None, &self.cache.parent_hash, &cache.modifications) {
 			if let Some(entry) = cache.lru_storage.get(key).map(|a| a.clone()) {
 				trace!("Found in shared cache: {:?}", HexDisplay::from(&key));
+				cache.stats.hits += 1;
 				return Ok(entry)
 			}
 		}
+		cache.stats.misses += 1;
 		trace!("Cache miss: {:?}", HexDisplay::from(&key));
 		let value = self.state.storage(key)?;
 		RwLockUpgradableReadGuard::upgrade(local_cache).storage.insert(key.to_vec(), value.clone());
@@ -497,9 +508,11 @@ impl<H: Hasher, S: StateBackend<H>, B: BlockT> StateBackend<H> for CachingState<
 		if Self::is_allowed(Some(key), None, &self.cache.parent_hash, &cache.modifications) {
 			if let Some(entry) = cache.lru_hashes.get(key).map(|a| a.0.clone()) {
 				trace!("Found hash in shared cache: {:?}", HexDisplay::from(&key));
+				cache.stats.hits += 1;
 				return Ok(entry)
 			}
 		}
+		cache.stats.misses += 1;
 		trace!("Cache hash miss: {:?}", HexDisplay::from(&key));
 		let hash = self.state.storage_hash(key)?;
 		RwLockUpgradableReadGuard::upgrade(local_cache).hashes.insert(key.to_vec(), hash.clone());
@@ -517,9 +530,11 @@ impl<H: Hasher, S: StateBackend<H>, B: BlockT> StateBackend<H> for CachingState<
 		if Self::is_allowed(None, Some(&key), &self.cache.parent_hash, &cache.modifications) {
 			if let Some(entry) = cache.lru_child_storage.get(&key).map(|a| a.clone()) {
 				trace!("Found in shared cache: {:?}", key);
+				cache.stats.hits += 1;
 				return Ok(entry)
 			}
 		}
+		cache.stats.misses += 1;
 		trace!("Cache miss: {:?}", key);
 		let value = self.state.child_storage(storage_key, &key.1[..])?;
 		RwLockUpgradableReadGuard::upgrade(local_cache).child_storage.insert(key, value.clone());
@@ -690,6 +705,28 @@ mod tests {
 		assert_eq!(shared.lock().used_storage_cache_size(), 101 /* bytes */);
 	}
 
+	#[test]
+	fn should_count_shared_cache_hits_and_misses() {
+		let root_parent = H256::random();
+		let shared = new_shared_cache::<Block, Blake2Hasher>(256*1024, (0, 1));
+		let h0 = H256::random();
+
+		let key = H256::random()[..].to_vec();
+		let mut s = CachingState::new(InMemory::<Blake2Hasher>::default(), shared.clone(), Some(root_parent));
+		s.cache.sync_cache(&[], &[], vec![(key.clone(), Some(vec![1]))], vec![], Some(h0.clone()), Some(0), || true);
+
+		let s = CachingState::new(InMemory::<Blake2Hasher>::default(), shared.clone(), Some(h0));
+		assert_eq!(s.storage(&key).unwrap(), Some(vec![1]));
+		assert_eq!(s.storage(&key).unwrap(), Some(vec![1]));
+
+		// misses are kept in the local cache of `s`.
+		let unknown = H256::random()[..].to_vec();
+		assert!(s.storage(&unknown).unwrap().is_none());
+		assert!(s.storage(&unknown).unwrap().is_none());
+
+		assert_eq!(shared.lock().stats(), StateCacheStats { hits: 2, misses: 1 });
+	}
+
 	#[test]
 	fn should_remove_lru_items_based_on_tracking_used_size() {
 		let root_parent = H256::random();
@@ -21,8 +21,6 @@ use std::sync::Arc;
 use std::{io, convert::TryInto};
 
 use kvdb::{KeyValueDB, DBTransaction};
-#[cfg(feature = "kvdb-rocksdb")]
-use kvdb_rocksdb::{Database, DatabaseConfig};
 use log::debug;
 
 use client;
@@ -33,7 +31,7 @@ use sr_primitives::traits::{
 	Block as BlockT, Header as HeaderT, Zero,
 	UniqueSaturatedFrom, UniqueSaturatedInto,
 };
-use crate::{DatabaseSettings, DatabaseSettingsSrc};
+use crate::{database, DatabaseSettings, DatabaseSettingsSrc};
 
 /// Number of columns in the db. Must be the same for both full && light dbs.
 /// Otherwise RocksDb will fail to open database && check its type.
@@ -57,6 +55,8 @@ pub mod meta_keys {
 	pub const LEAF_PREFIX: &[u8; 4] = b"leaf";
 	/// Children prefix list key.
 	pub const CHILDREN_PREFIX: &[u8; 8] = b"children";
+	/// Lookup key of the justification to prune when the next one is written.
+	pub const PRUNABLE_JUSTIFICATION: &[u8; 18] = b"prunable_justified";
 }
 
 /// Database metadata.
@@ -203,24 +203,18 @@ pub fn db_err(err: io::Error) -> client::error::Error {
 	client::error::Error::Backend(format!("{}", err))
 }
 
-/// Open RocksDB database.
+/// Open the database, `column_names` naming its columns for the column settings.
 pub fn open_database(
 	config: &DatabaseSettings,
+	column_names: &[(&str, Option<u32>)],
 	col_meta: Option<u32>,
 	db_type: &str
 ) -> client::error::Result<Arc<dyn KeyValueDB>> {
 	let db: Arc<dyn KeyValueDB> = match &config.source {
-		#[cfg(feature = "kvdb-rocksdb")]
-		DatabaseSettingsSrc::Path { path, cache_size } => {
-			let mut db_config = DatabaseConfig::with_columns(Some(NUM_COLUMNS));
-			db_config.memory_budget = *cache_size;
-			let path = path.to_str().ok_or_else(|| client::error::Error::Backend("Invalid database path".into()))?;
-			Arc::new(Database::open(&db_config, &path).map_err(db_err)?)
-		},
-		#[cfg(not(feature = "kvdb-rocksdb"))]
-		DatabaseSettingsSrc::Path { .. } => {
-			let msg = "Try to open RocksDB database with RocksDB disabled".into();
-			return Err(client::error::Error::Backend(msg));
+		DatabaseSettingsSrc::Path { path, cache_size, backend, columns } => {
+			let columns = database::resolve_columns(column_names, NUM_COLUMNS, columns, *cache_size)
+				.map_err(client::error::Error::Backend)?;
+			database::open(*backend, path, columns).map_err(client::error::Error::Backend)?
 		},
 		DatabaseSettingsSrc::Custom(db) => db.clone(),
 	};
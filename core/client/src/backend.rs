@@ -56,6 +56,18 @@ pub struct ClientImportOperation<
 	pub(crate) notify_finalized: Vec<Block::Hash>,
 }
 
+impl<Block, H, B> ClientImportOperation<Block, H, B> where
+	Block: BlockT,
+	H: Hasher<Out=Block::Hash>,
+	B: Backend<Block, H>,
+{
+	/// Mark the justification of a block finalized by this operation as required to verify
+	/// the finality of its descendants.
+	pub fn mark_justification_required(&mut self, id: BlockId<Block>) -> error::Result<()> {
+		self.op.mark_justification_required(id)
+	}
+}
+
 /// State of a new block.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NewBlockState {
@@ -138,6 +150,12 @@ pub trait BlockImportOperation<Block, H> where
 	fn mark_finalized(&mut self, id: BlockId<Block>, justification: Option<Justification>) -> error::Result<()>;
 	/// Mark a block as new head. If both block import and set head are specified, set head overrides block import's best block rule.
 	fn mark_head(&mut self, id: BlockId<Block>) -> error::Result<()>;
+	/// Mark the justification of a block as required to verify the finality of its descendants,
+	/// e.g. because the block enacts an authority set change. Backends pruning justifications
+	/// keep the required ones.
+	fn mark_justification_required(&mut self, _id: BlockId<Block>) -> error::Result<()> {
+		Ok(())
+	}
 }
 
 /// Finalize Facilities
@@ -199,6 +217,48 @@ pub trait AuxStore {
 	fn get_aux(&self, key: &[u8]) -> error::Result<Option<Vec<u8>>>;
 }
 
+/// Hits and misses of the shared state cache since the backend was created.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StateCacheStats {
+	/// Reads served from the cache.
+	pub hits: u64,
+	/// Reads which had to go to the state database.
+	pub misses: u64,
+}
+
+/// Inconsistency found by checking the integrity of the backend data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inconsistency<N> {
+	/// Number of the block whose data is inconsistent, `None` if it isn't tied to a block.
+	pub block: Option<N>,
+	/// Description of the inconsistency.
+	pub description: String,
+}
+
+/// Outcome of checking the integrity of the backend data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityReport<N> {
+	/// Number of the best block.
+	pub best: N,
+	/// Number of the last finalized block.
+	pub finalized: N,
+	/// Number of checked blocks, from the best block down.
+	pub checked_blocks: u64,
+	/// Found inconsistencies.
+	pub inconsistencies: Vec<Inconsistency<N>>,
+	/// Best block below all the blocks with inconsistent data, which the chain can be
+	/// reverted to. `None` if the inconsistencies aren't tied to blocks, and reverting
+	/// doesn't repair them.
+	pub last_consistent: Option<N>,
+}
+
+impl<N> IntegrityReport<N> {
+	/// Whether no inconsistency was found.
+	pub fn is_consistent(&self) -> bool {
+		self.inconsistencies.is_empty()
+	}
+}
+
 /// Client backend.
 ///
 /// Manages the data layer.
@@ -246,6 +306,11 @@ pub trait Backend<Block, H>: AuxStore + Send + Sync where
 	/// Returns the used state cache, if existent.
 	fn used_state_cache_size(&self) -> Option<usize>;
 
+	/// Returns the hits and misses of the state cache, if existent.
+	fn state_cache_stats(&self) -> Option<StateCacheStats> {
+		None
+	}
+
 	/// Returns reference to changes trie storage.
 	fn changes_trie_storage(&self) -> Option<&Self::ChangesTrieStorage>;
 
@@ -270,6 +335,22 @@ pub trait Backend<Block, H>: AuxStore + Send + Sync where
 	/// Returns the number of blocks that were successfully reverted.
 	fn revert(&self, n: NumberFor<Block>) -> error::Result<NumberFor<Block>>;
 
+	/// Reverts the best chain by exactly `n` blocks, finalized blocks included, and applies
+	/// the `aux` changes in the same transaction. Nothing is changed if the chain can't be
+	/// reverted that far.
+	fn unsafe_revert(
+		&self,
+		_n: NumberFor<Block>,
+		_aux: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+	) -> error::Result<()> {
+		Err(error::Error::Backend("The backend doesn't support reverting finalized blocks".into()))
+	}
+
+	/// Check the integrity of the data of the last `blocks` blocks of the best chain.
+	fn check_integrity(&self, _blocks: NumberFor<Block>) -> error::Result<IntegrityReport<NumberFor<Block>>> {
+		Err(error::Error::Backend("The backend doesn't support integrity checks".into()))
+	}
+
 	/// Insert auxiliary data into key-value store.
 	fn insert_aux<
 		'a,
@@ -315,6 +396,15 @@ pub trait OffchainStorage: Clone + Send + Sync {
 	) -> bool;
 }
 
+/// Provides access to the local storage of offchain workers.
+pub trait ProvideOffchainStorage {
+	/// The offchain workers local storage.
+	type OffchainStorage: OffchainStorage;
+
+	/// Returns the offchain workers local storage, if the backend has one.
+	fn offchain_storage(&self) -> Option<Self::OffchainStorage>;
+}
+
 /// Changes trie storage that supports pruning.
 pub trait PrunableStateChangesTrieStorage<Block: BlockT, H: Hasher>:
 	StateChangesTrieStorage<H, NumberFor<Block>>
@@ -60,7 +60,7 @@ use crate::{
 	},
 	backend::{
 		self, BlockImportOperation, PrunableStateChangesTrieStorage,
-		ClientImportOperation, Finalizer, ImportSummary,
+		ClientImportOperation, Finalizer, ImportSummary, IntegrityReport, StateCacheStats,
 	},
 	blockchain::{
 		self, Info as ChainInfo, Backend as ChainBackend,
@@ -176,6 +176,8 @@ pub struct ClientInfo<Block: BlockT> {
 	pub chain: ChainInfo<Block>,
 	/// State Cache Size currently used by the backend
 	pub used_state_cache_size: Option<usize>,
+	/// Hits and misses of the state cache of the backend
+	pub state_cache_stats: Option<StateCacheStats>,
 }
 
 /// Summary of an imported block
@@ -1219,12 +1221,33 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		Ok(self.backend.revert(n)?)
 	}
 
+	/// Reverts the chain by exactly `n` blocks, finalized blocks included, and applies the
+	/// `aux` changes in the same transaction. Fails without changing anything if the state
+	/// needed to revert that far isn't available.
+	///
+	/// This breaks the finality guarantees given to other nodes and is only meant for recovering
+	/// from a finalized chain that can't be continued. `aux` must rewind the auxiliary data of
+	/// the consensus engines accordingly.
+	pub fn unsafe_revert(
+		&self,
+		n: NumberFor<Block>,
+		aux: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+	) -> error::Result<()> {
+		self.backend.unsafe_revert(n, aux)
+	}
+
+	/// Checks the integrity of the data of the last `blocks` blocks of the best chain.
+	pub fn check_integrity(&self, blocks: NumberFor<Block>) -> error::Result<IntegrityReport<NumberFor<Block>>> {
+		self.backend.check_integrity(blocks)
+	}
+
 	/// Get blockchain info.
 	pub fn info(&self) -> ClientInfo<Block> {
 		let info = self.backend.blockchain().info();
 		ClientInfo {
 			chain: info,
 			used_state_cache_size: self.backend.used_state_cache_size(),
+			state_cache_stats: self.backend.state_cache_stats(),
 		}
 	}
 
@@ -1754,6 +1777,19 @@ impl<B, E, Block, RA> BlockBody<Block> for Client<B, E, Block, RA>
 	}
 }
 
+impl<B, E, Block, RA> backend::ProvideOffchainStorage for Client<B, E, Block, RA>
+	where
+		B: backend::Backend<Block, Blake2Hasher>,
+		E: CallExecutor<Block, Blake2Hasher>,
+		Block: BlockT<Hash=H256>,
+{
+	type OffchainStorage = B::OffchainStorage;
+
+	fn offchain_storage(&self) -> Option<Self::OffchainStorage> {
+		self.backend.offchain_storage()
+	}
+}
+
 impl<B, E, Block, RA> backend::AuxStore for Client<B, E, Block, RA>
 	where
 		B: backend::Backend<Block, Blake2Hasher>,
@@ -2774,9 +2810,12 @@ pub(crate) mod tests {
 				state_cache_size: 1 << 20,
 				state_cache_child_ratio: None,
 				pruning: PruningMode::ArchiveAll,
+				retention: Default::default(),
 				source: DatabaseSettingsSrc::Path {
 					path: tmp.path().into(),
 					cache_size: None,
+					backend: Default::default(),
+					columns: Default::default(),
 				}
 			},
 			u64::max_value(),
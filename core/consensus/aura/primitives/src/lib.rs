@@ -21,7 +21,9 @@
 use codec::{Encode, Decode, Codec};
 use substrate_client::decl_runtime_apis;
 use rstd::vec::Vec;
-use sr_primitives::ConsensusEngineId;
+use sr_primitives::{ConsensusEngineId, RuntimeDebug};
+use sr_primitives::traits::Header as HeaderT;
+use app_crypto::RuntimeAppPublic;
 
 pub mod sr25519 {
 	mod app_sr25519 {
@@ -74,6 +76,98 @@ pub enum ConsensusLog<AuthorityId: Codec> {
 	OnDisabled(AuthorityIndex),
 }
 
+/// The offchain local storage key under which the block import queues the slot equivocations
+/// it has detected, as an encoded `Vec<EquivocationProof<Header, AuthorityId>>`. The queue is
+/// drained by the offchain worker of the runtime module.
+pub const EQUIVOCATIONS_KEY: &[u8] = b"srml/aura-equivocations";
+
+/// Proof that an authority has authored two different blocks in the same slot.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct EquivocationProof<H, AuthorityId> {
+	/// The authority that equivocated.
+	pub offender: AuthorityId,
+	/// The slot both blocks were authored in.
+	pub slot_number: u64,
+	/// The first sealed header.
+	pub first_header: H,
+	/// The second sealed header.
+	pub second_header: H,
+}
+
+impl<H: HeaderT, AuthorityId: RuntimeAppPublic> EquivocationProof<H, AuthorityId> {
+	/// Check that both headers claim the slot of the proof, are sealed by the offender
+	/// and are different.
+	pub fn check(&self) -> bool {
+		let first = check_seal(self.first_header.clone(), self.slot_number, &self.offender);
+		let second = check_seal(self.second_header.clone(), self.slot_number, &self.offender);
+
+		match (first, second) {
+			(Some(first), Some(second)) => first != second,
+			_ => false,
+		}
+	}
+}
+
+/// Check that the given header has an Aura pre-digest for `slot_number` and is sealed by
+/// `author`, returning the hash of the header without its seal.
+fn check_seal<H: HeaderT, AuthorityId: RuntimeAppPublic>(
+	header: H,
+	slot_number: u64,
+	author: &AuthorityId,
+) -> Option<H::Hash> {
+	let (pre_hash, slot, signature) = split_seal::<_, AuthorityId>(header)?;
+
+	if slot != slot_number {
+		return None;
+	}
+
+	if author.verify(&pre_hash, &signature) {
+		Some(pre_hash)
+	} else {
+		None
+	}
+}
+
+/// Check that the given header has an Aura pre-digest and is sealed by the authority
+/// expected to author in its slot, out of `authorities`, returning the slot number and
+/// the index of the author.
+pub fn check_header_seal<H: HeaderT, AuthorityId: RuntimeAppPublic>(
+	header: H,
+	authorities: &[AuthorityId],
+) -> Option<(u64, AuthorityIndex)> {
+	if authorities.is_empty() {
+		return None;
+	}
+
+	let (pre_hash, slot, signature) = split_seal::<_, AuthorityId>(header)?;
+	let index = (slot % authorities.len() as u64) as usize;
+
+	if authorities[index].verify(&pre_hash, &signature) {
+		Some((slot, index as AuthorityIndex))
+	} else {
+		None
+	}
+}
+
+/// Split the Aura seal off the given header, returning the hash of the header without
+/// its seal, the slot of its Aura pre-digest and the seal signature.
+fn split_seal<H: HeaderT, AuthorityId: RuntimeAppPublic>(
+	mut header: H,
+) -> Option<(H::Hash, u64, AuthorityId::Signature)> {
+	let signature = match header.digest_mut().pop()?.as_seal() {
+		Some((id, signature)) if id == AURA_ENGINE_ID =>
+			AuthorityId::Signature::decode(&mut &signature[..]).ok()?,
+		_ => return None,
+	};
+
+	let slot = header.digest().logs().iter()
+		.filter_map(|log| log.as_pre_runtime())
+		.find(|(id, _)| *id == AURA_ENGINE_ID)
+		.and_then(|(_, data)| u64::decode(&mut &data[..]).ok())?;
+
+	Some((header.hash(), slot, signature))
+}
+
 decl_runtime_apis! {
 	/// API necessary for block authorship with aura.
 	pub trait AuraApi<AuthorityId: Codec> {
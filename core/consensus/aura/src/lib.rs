@@ -39,13 +39,13 @@ use consensus_common::import_queue::{
 	Verifier, BasicQueue, BoxBlockImport, BoxJustificationImport, BoxFinalityProofImport,
 };
 use client::{
-	block_builder::api::BlockBuilder as BlockBuilderApi, blockchain::ProvideCache,
-	runtime_api::ApiExt, error::Result as CResult, backend::AuxStore, BlockOf,
+	block_builder::api::BlockBuilder as BlockBuilderApi, blockchain::{HeaderBackend, ProvideCache},
+	runtime_api::ApiExt, error::Result as CResult, backend::{AuxStore, ProvideOffchainStorage}, BlockOf,
 	well_known_cache_keys::{self, Id as CacheKeyId},
 };
 
 use sr_primitives::{generic::{BlockId, OpaqueDigestItemId}, Justification};
-use sr_primitives::traits::{Block as BlockT, Header, DigestItemFor, ProvideRuntimeApi, Zero, Member};
+use sr_primitives::traits::{Block as BlockT, Header, DigestItemFor, NumberFor, ProvideRuntimeApi, Zero, Member};
 
 use primitives::crypto::Pair;
 use inherents::{InherentDataProviders, InherentData, RuntimeString};
@@ -61,13 +61,15 @@ use srml_aura::{
 use substrate_telemetry::{telemetry, CONSENSUS_TRACE, CONSENSUS_DEBUG, CONSENSUS_INFO};
 
 use slots::{CheckedHeader, SlotData, SlotWorker, SlotInfo, SlotCompatible};
-use slots::check_equivocation;
+use slots::{check_equivocation, queue_equivocation_report};
 
 use keystore::KeyStorePtr;
+use prometheus::Registry;
 
 pub use aura_primitives::*;
 pub use consensus_common::SyncOracle;
 pub use digest::CompatibleDigestItem;
+pub use slots::{BackoffAuthoringBlocksStrategy, BackoffAuthoringOnFinalizedHeadLagging};
 
 mod digest;
 
@@ -130,7 +132,10 @@ impl SlotCompatible for AuraSlotCompatible {
 }
 
 /// Start the aura worker. The returned future should be run in a futures executor.
-pub fn start_aura<B, C, SC, E, I, P, SO, Error, H>(
+///
+/// If a `backoff_authoring_blocks` strategy is given, claimed slots are skipped
+/// according to it, e.g. when finality lags behind.
+pub fn start_aura<B, C, SC, E, I, P, SO, BS, Error, H>(
 	slot_duration: SlotDuration,
 	client: Arc<C>,
 	select_chain: SC,
@@ -140,9 +145,10 @@ pub fn start_aura<B, C, SC, E, I, P, SO, Error, H>(
 	inherent_data_providers: InherentDataProviders,
 	force_authoring: bool,
 	keystore: KeyStorePtr,
+	backoff_authoring_blocks: Option<BS>,
 ) -> Result<impl futures01::Future<Item = (), Error = ()>, consensus_common::Error> where
 	B: BlockT<Header=H>,
-	C: ProvideRuntimeApi + BlockOf + ProvideCache<B> + AuxStore + Send + Sync,
+	C: ProvideRuntimeApi + BlockOf + ProvideCache<B> + HeaderBackend<B> + AuxStore + Send + Sync,
 	C::Api: AuraApi<B, AuthorityId<P>>,
 	SC: SelectChain<B>,
 	E: Environment<B, Error=Error> + Send + Sync + 'static,
@@ -155,6 +161,7 @@ pub fn start_aura<B, C, SC, E, I, P, SO, Error, H>(
 	I: BlockImport<B> + Send + Sync + 'static,
 	Error: ::std::error::Error + Send + From<::consensus_common::Error> + From<I::Error> + 'static,
 	SO: SyncOracle + Send + Sync + Clone,
+	BS: BackoffAuthoringBlocksStrategy<NumberFor<B>> + Send + Sync + 'static,
 {
 	let worker = AuraWorker {
 		client: client.clone(),
@@ -163,6 +170,7 @@ pub fn start_aura<B, C, SC, E, I, P, SO, Error, H>(
 		keystore,
 		sync_oracle: sync_oracle.clone(),
 		force_authoring,
+		backoff_authoring_blocks,
 		_key_type: PhantomData::<P>,
 	};
 	register_aura_inherent_data_provider(
@@ -179,19 +187,20 @@ pub fn start_aura<B, C, SC, E, I, P, SO, Error, H>(
 	).map(|()| Ok::<(), ()>(())).compat())
 }
 
-struct AuraWorker<C, E, I, P, SO> {
+struct AuraWorker<C, E, I, P, SO, BS> {
 	client: Arc<C>,
 	block_import: Arc<Mutex<I>>,
 	env: E,
 	keystore: KeyStorePtr,
 	sync_oracle: SO,
 	force_authoring: bool,
+	backoff_authoring_blocks: Option<BS>,
 	_key_type: PhantomData<P>,
 }
 
-impl<H, B, C, E, I, P, Error, SO> slots::SimpleSlotWorker<B> for AuraWorker<C, E, I, P, SO> where
+impl<H, B, C, E, I, P, Error, SO, BS> slots::SimpleSlotWorker<B> for AuraWorker<C, E, I, P, SO, BS> where
 	B: BlockT<Header=H>,
-	C: ProvideRuntimeApi + BlockOf + ProvideCache<B> + Sync,
+	C: ProvideRuntimeApi + BlockOf + ProvideCache<B> + HeaderBackend<B> + Sync,
 	C::Api: AuraApi<B, AuthorityId<P>>,
 	E: Environment<B, Error=Error>,
 	E::Proposer: Proposer<B, Error=Error>,
@@ -202,6 +211,7 @@ impl<H, B, C, E, I, P, Error, SO> slots::SimpleSlotWorker<B> for AuraWorker<C, E
 	P::Public: Member + Encode + Decode + Hash,
 	P::Signature: Member + Encode + Decode + Hash + Debug,
 	SO: SyncOracle + Send + Clone,
+	BS: BackoffAuthoringBlocksStrategy<NumberFor<B>>,
 	Error: ::std::error::Error + Send + From<::consensus_common::Error> + From<I::Error> + 'static,
 {
 	type EpochData = Vec<AuthorityId<P>>;
@@ -285,11 +295,23 @@ impl<H, B, C, E, I, P, Error, SO> slots::SimpleSlotWorker<B> for AuraWorker<C, E
 			consensus_common::Error::ClientImport(format!("{:?}", e)).into()
 		})
 	}
+
+	fn backoff_authoring_blocks(&self) -> Option<&dyn BackoffAuthoringBlocksStrategy<NumberFor<B>>> {
+		self.backoff_authoring_blocks.as_ref().map(|s| s as &dyn BackoffAuthoringBlocksStrategy<_>)
+	}
+
+	fn slot_of(&self, header: &B::Header) -> Option<u64> {
+		find_pre_digest::<B, P>(header).ok()
+	}
+
+	fn finalized_number(&self) -> Option<NumberFor<B>> {
+		Some(self.client.info().finalized_number)
+	}
 }
 
-impl<H, B: BlockT, C, E, I, P, Error, SO> SlotWorker<B> for AuraWorker<C, E, I, P, SO> where
+impl<H, B: BlockT, C, E, I, P, Error, SO, BS> SlotWorker<B> for AuraWorker<C, E, I, P, SO, BS> where
 	B: BlockT<Header=H>,
-	C: ProvideRuntimeApi + BlockOf + ProvideCache<B> + Sync + Send,
+	C: ProvideRuntimeApi + BlockOf + ProvideCache<B> + HeaderBackend<B> + Sync + Send,
 	C::Api: AuraApi<B, AuthorityId<P>>,
 	E: Environment<B, Error=Error> + Send + Sync,
 	E::Proposer: Proposer<B, Error=Error>,
@@ -300,6 +322,7 @@ impl<H, B: BlockT, C, E, I, P, Error, SO> SlotWorker<B> for AuraWorker<C, E, I,
 	P::Public: Member + Encode + Decode + Hash,
 	P::Signature: Member + Encode + Decode + Hash + Debug,
 	SO: SyncOracle + Send + Sync + Clone,
+	BS: BackoffAuthoringBlocksStrategy<NumberFor<B>> + Send + Sync,
 	Error: ::std::error::Error + Send + From<::consensus_common::Error> + From<I::Error> + 'static,
 {
 	type OnSlot = Pin<Box<dyn Future<Output = Result<(), consensus_common::Error>> + Send>>;
@@ -369,8 +392,8 @@ fn check_header<C, B: BlockT, P: Pair, T>(
 ) -> Result<CheckedHeader<B::Header, (u64, DigestItemFor<B>)>, Error<B>> where
 	DigestItemFor<B>: CompatibleDigestItem<P>,
 	P::Signature: Decode,
-	C: client::backend::AuxStore,
-	P::Public: Encode + Decode + PartialEq + Clone,
+	C: AuxStore + ProvideOffchainStorage,
+	P::Public: Encode + Decode + PartialEq + Clone + Debug,
 	T: Send + Sync + 'static,
 {
 	let seal = match header.digest_mut().pop() {
@@ -398,11 +421,15 @@ fn check_header<C, B: BlockT, P: Pair, T>(
 		let pre_hash = header.hash();
 
 		if P::verify(&sig, pre_hash.as_ref(), expected_author) {
+			// keep the sealed header, so that equivocations can be checked by the runtime.
+			let mut sealed_header = header.clone();
+			sealed_header.digest_mut().push(seal.clone());
+
 			if let Some(equivocation_proof) = check_equivocation(
 				client,
 				slot_now,
 				slot_num,
-				&header,
+				&sealed_header,
 				expected_author,
 			).map_err(Error::Client)? {
 				info!(
@@ -411,6 +438,20 @@ fn check_header<C, B: BlockT, P: Pair, T>(
 					equivocation_proof.fst_header().hash(),
 					equivocation_proof.snd_header().hash(),
 				);
+
+				// queue the equivocation for the offchain worker to report it on-chain.
+				if let Some(mut storage) = client.offchain_storage() {
+					queue_equivocation_report(
+						&mut storage,
+						aura_primitives::EQUIVOCATIONS_KEY,
+						aura_primitives::EquivocationProof {
+							offender: expected_author.clone(),
+							slot_number: equivocation_proof.slot(),
+							first_header: equivocation_proof.fst_header().clone(),
+							second_header: equivocation_proof.snd_header().clone(),
+						},
+					);
+				}
 			}
 
 			Ok(CheckedHeader::Checked(header, (slot_num, seal)))
@@ -484,7 +525,7 @@ impl<C, P, T> AuraVerifier<C, P, T>
 
 #[forbid(deprecated)]
 impl<B: BlockT, C, P, T> Verifier<B> for AuraVerifier<C, P, T> where
-	C: ProvideRuntimeApi + Send + Sync + client::backend::AuxStore + ProvideCache<B> + BlockOf,
+	C: ProvideRuntimeApi + Send + Sync + AuxStore + ProvideOffchainStorage + ProvideCache<B> + BlockOf,
 	C::Api: BlockBuilderApi<B> + AuraApi<B, AuthorityId<P>>,
 	DigestItemFor<B>: CompatibleDigestItem<P>,
 	P: Pair + Send + Sync + 'static,
@@ -664,9 +705,10 @@ pub fn import_queue<B, C, P, T>(
 	client: Arc<C>,
 	inherent_data_providers: InherentDataProviders,
 	transaction_pool: Option<Arc<T>>,
+	prometheus_registry: Option<&Registry>,
 ) -> Result<AuraImportQueue<B>, consensus_common::Error> where
 	B: BlockT,
-	C: 'static + ProvideRuntimeApi + BlockOf + ProvideCache<B> + Send + Sync + AuxStore,
+	C: 'static + ProvideRuntimeApi + BlockOf + ProvideCache<B> + Send + Sync + AuxStore + ProvideOffchainStorage,
 	C::Api: BlockBuilderApi<B> + AuraApi<B, AuthorityId<P>>,
 	DigestItemFor<B>: CompatibleDigestItem<P>,
 	P: Pair + Send + Sync + 'static,
@@ -688,6 +730,7 @@ pub fn import_queue<B, C, P, T>(
 		block_import,
 		justification_import,
 		finality_proof_import,
+		prometheus_registry,
 	))
 }
 
@@ -844,7 +887,7 @@ mod tests {
 				&inherent_data_providers, slot_duration.get()
 			).expect("Registers aura inherent data provider");
 
-			let aura = start_aura::<_, _, _, _, _, AuthorityPair, _, _, _>(
+			let aura = start_aura::<_, _, _, _, _, AuthorityPair, _, _, _, _>(
 				slot_duration,
 				client.clone(),
 				select_chain,
@@ -854,6 +897,7 @@ mod tests {
 				inherent_data_providers,
 				false,
 				keystore,
+				Option::<()>::None,
 			).expect("Starts aura");
 
 			runtime.spawn(aura);
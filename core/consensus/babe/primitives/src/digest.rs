@@ -38,7 +38,8 @@ use rstd::vec::Vec;
 
 /// A BABE pre-runtime digest. This contains all data required to validate a
 /// block and for the BABE runtime module. Slots can be assigned to a primary
-/// (VRF based) and to a secondary (slot number based).
+/// (VRF based) and to a secondary (slot number based). Secondary slots can
+/// optionally carry a VRF output, which is then included in the epoch randomness.
 #[cfg(feature = "std")]
 #[derive(Clone, Debug)]
 pub enum BabePreDigest {
@@ -60,6 +61,17 @@ pub enum BabePreDigest {
 		/// Slot number
 		slot_number: SlotNumber,
 	},
+	/// A secondary deterministic slot assignment with a VRF output.
+	SecondaryVRF {
+		/// Authority index
+		authority_index: super::AuthorityIndex,
+		/// Slot number
+		slot_number: SlotNumber,
+		/// VRF output
+		vrf_output: VRFOutput,
+		/// VRF proof
+		vrf_proof: VRFProof,
+	},
 }
 
 #[cfg(feature = "std")]
@@ -69,6 +81,7 @@ impl BabePreDigest {
 		match self {
 			BabePreDigest::Primary { authority_index, .. } => *authority_index,
 			BabePreDigest::Secondary { authority_index, .. } => *authority_index,
+			BabePreDigest::SecondaryVRF { authority_index, .. } => *authority_index,
 		}
 	}
 
@@ -77,6 +90,7 @@ impl BabePreDigest {
 		match self {
 			BabePreDigest::Primary { slot_number, .. } => *slot_number,
 			BabePreDigest::Secondary { slot_number, .. } => *slot_number,
+			BabePreDigest::SecondaryVRF { slot_number, .. } => *slot_number,
 		}
 	}
 
@@ -85,7 +99,16 @@ impl BabePreDigest {
 	pub fn added_weight(&self) -> crate::BabeBlockWeight {
 		match self {
 			BabePreDigest::Primary { .. } => 1,
-			BabePreDigest::Secondary { .. } => 0,
+			BabePreDigest::Secondary { .. } | BabePreDigest::SecondaryVRF { .. } => 0,
+		}
+	}
+
+	/// Returns the VRF output, if the slot was claimed with a VRF.
+	pub fn vrf_output(&self) -> Option<&VRFOutput> {
+		match self {
+			BabePreDigest::Primary { vrf_output, .. } => Some(vrf_output),
+			BabePreDigest::SecondaryVRF { vrf_output, .. } => Some(vrf_output),
+			BabePreDigest::Secondary { .. } => None,
 		}
 	}
 }
@@ -121,14 +144,45 @@ pub enum RawBabePreDigest {
 		/// Slot number
 		slot_number: SlotNumber,
 	},
+	/// A secondary deterministic slot assignment with a VRF output.
+	#[codec(index = "3")]
+	SecondaryVRF {
+		/// Authority index
+		authority_index: AuthorityIndex,
+		/// Slot number
+		slot_number: SlotNumber,
+		/// VRF output
+		vrf_output: [u8; VRF_OUTPUT_LENGTH],
+		/// VRF proof
+		vrf_proof: [u8; VRF_PROOF_LENGTH],
+	},
 }
 
 impl RawBabePreDigest {
+	/// Returns the index of the authority that authored the block.
+	pub fn authority_index(&self) -> AuthorityIndex {
+		match self {
+			RawBabePreDigest::Primary { authority_index, .. } => *authority_index,
+			RawBabePreDigest::Secondary { authority_index, .. } => *authority_index,
+			RawBabePreDigest::SecondaryVRF { authority_index, .. } => *authority_index,
+		}
+	}
+
 	/// Returns the slot number of the pre digest.
 	pub fn slot_number(&self) -> SlotNumber {
 		match self {
 			RawBabePreDigest::Primary { slot_number, .. } => *slot_number,
 			RawBabePreDigest::Secondary { slot_number, .. } => *slot_number,
+			RawBabePreDigest::SecondaryVRF { slot_number, .. } => *slot_number,
+		}
+	}
+
+	/// Returns the VRF output, if the slot was claimed with a VRF.
+	pub fn vrf_output(&self) -> Option<&[u8; VRF_OUTPUT_LENGTH]> {
+		match self {
+			RawBabePreDigest::Primary { vrf_output, .. } => Some(vrf_output),
+			RawBabePreDigest::SecondaryVRF { vrf_output, .. } => Some(vrf_output),
+			RawBabePreDigest::Secondary { .. } => None,
 		}
 	}
 }
@@ -159,6 +213,19 @@ impl Encode for BabePreDigest {
 					slot_number: *slot_number,
 				}
 			},
+			BabePreDigest::SecondaryVRF {
+				authority_index,
+				slot_number,
+				vrf_output,
+				vrf_proof,
+			} => {
+				RawBabePreDigest::SecondaryVRF {
+					authority_index: *authority_index,
+					slot_number: *slot_number,
+					vrf_output: *vrf_output.as_bytes(),
+					vrf_proof: vrf_proof.to_bytes(),
+				}
+			},
 		};
 
 		codec::Encode::encode(&raw)
@@ -187,6 +254,14 @@ impl Decode for BabePreDigest {
 			RawBabePreDigest::Secondary { authority_index, slot_number } => {
 				BabePreDigest::Secondary { authority_index, slot_number }
 			},
+			RawBabePreDigest::SecondaryVRF { authority_index, slot_number, vrf_output, vrf_proof } => {
+				BabePreDigest::SecondaryVRF {
+					authority_index,
+					slot_number,
+					vrf_output: VRFOutput::from_bytes(&vrf_output).map_err(convert_error)?,
+					vrf_proof: VRFProof::from_bytes(&vrf_proof).map_err(convert_error)?,
+				}
+			},
 		};
 
 		Ok(pre_digest)
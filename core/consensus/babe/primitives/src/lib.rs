@@ -24,6 +24,8 @@ mod digest;
 use codec::{Encode, Decode};
 use rstd::vec::Vec;
 use sr_primitives::{ConsensusEngineId, RuntimeDebug};
+use sr_primitives::traits::Header as HeaderT;
+use app_crypto::RuntimeAppPublic;
 use substrate_client::decl_runtime_apis;
 
 #[cfg(feature = "std")]
@@ -125,6 +127,90 @@ pub enum ConsensusLog {
 	OnDisabled(AuthorityIndex),
 }
 
+/// The offchain local storage key under which the block import queues the slot equivocations
+/// it has detected, as an encoded `Vec<EquivocationProof<Header>>`. The queue is drained by the
+/// offchain worker of the runtime module, which turns each entry into an on-chain report.
+pub const EQUIVOCATIONS_KEY: &[u8] = b"srml/babe-equivocations";
+
+/// Proof that an authority has authored two different blocks in the same slot.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct EquivocationProof<H> {
+	/// The authority that equivocated.
+	pub offender: AuthorityId,
+	/// The slot both blocks were authored in.
+	pub slot_number: SlotNumber,
+	/// The first sealed header.
+	pub first_header: H,
+	/// The second sealed header.
+	pub second_header: H,
+}
+
+impl<H: HeaderT> EquivocationProof<H> {
+	/// Check that both headers claim the slot of the proof, are sealed by the offender
+	/// and are different.
+	pub fn check(&self) -> bool {
+		let first = check_seal(self.first_header.clone(), self.slot_number, &self.offender);
+		let second = check_seal(self.second_header.clone(), self.slot_number, &self.offender);
+
+		match (first, second) {
+			(Some(first), Some(second)) => first != second,
+			_ => false,
+		}
+	}
+}
+
+/// Check that the given header has a BABE pre-digest for `slot_number` and is sealed by
+/// `author`, returning the hash of the header without its seal.
+fn check_seal<H: HeaderT>(header: H, slot_number: SlotNumber, author: &AuthorityId) -> Option<H::Hash> {
+	let (pre_hash, pre_digest, signature) = split_seal(header)?;
+
+	if pre_digest.slot_number() != slot_number {
+		return None;
+	}
+
+	if author.verify(&pre_hash, &signature) {
+		Some(pre_hash)
+	} else {
+		None
+	}
+}
+
+/// Check that the given header has a BABE pre-digest and is sealed by the authority the
+/// pre-digest claims, out of `authorities`, returning the pre-digest.
+///
+/// The VRF output of primary slots isn't checked, so this doesn't prove that the author was
+/// allowed to author in the slot, only that the header was authored by one of `authorities`.
+pub fn check_header_seal<H: HeaderT>(
+	header: H,
+	authorities: &[(AuthorityId, BabeAuthorityWeight)],
+) -> Option<RawBabePreDigest> {
+	let (pre_hash, pre_digest, signature) = split_seal(header)?;
+	let (author, _) = authorities.get(pre_digest.authority_index() as usize)?;
+
+	if author.verify(&pre_hash, &signature) {
+		Some(pre_digest)
+	} else {
+		None
+	}
+}
+
+/// Split the BABE seal off the given header, returning the hash of the header without
+/// its seal, its BABE pre-digest and the seal signature.
+fn split_seal<H: HeaderT>(mut header: H) -> Option<(H::Hash, RawBabePreDigest, AuthoritySignature)> {
+	let signature = match header.digest_mut().pop()?.as_seal() {
+		Some((id, signature)) if id == BABE_ENGINE_ID =>
+			AuthoritySignature::decode(&mut &signature[..]).ok()?,
+		_ => return None,
+	};
+
+	let pre_digest = header.digest().logs().iter()
+		.filter_map(|log| log.as_pre_runtime())
+		.find(|(id, _)| *id == BABE_ENGINE_ID)
+		.and_then(|(_, data)| RawBabePreDigest::decode(&mut &data[..]).ok())?;
+
+	Some((header.hash(), pre_digest, signature))
+}
+
 /// Configuration data used by the BABE consensus engine.
 #[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug)]
 pub struct BabeConfiguration {
@@ -154,6 +240,51 @@ pub struct BabeConfiguration {
 	/// Whether this chain should run with secondary slots, which are assigned
 	/// in round-robin manner.
 	pub secondary_slots: bool,
+
+	/// Whether blocks authored in secondary slots should carry a VRF output,
+	/// which is then included in the epoch randomness like the output of a
+	/// primary slot. Has no effect unless `secondary_slots` is set.
+	///
+	/// Like the rest of the configuration, only the value at genesis is used,
+	/// so enabling it in a runtime upgrade has no effect on existing chains.
+	pub secondary_vrf_slots: bool,
+}
+
+/// The configuration returned by version 1 of the `BabeApi`, before secondary
+/// slots with a VRF output were introduced.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug)]
+pub struct BabeConfigurationV1 {
+	/// The slot duration in milliseconds for BABE.
+	pub slot_duration: u64,
+
+	/// The duration of epochs in slots.
+	pub epoch_length: SlotNumber,
+
+	/// A constant value that is used in the threshold calculation formula.
+	pub c: (u64, u64),
+
+	/// The authorities for the genesis epoch.
+	pub genesis_authorities: Vec<(AuthorityId, BabeAuthorityWeight)>,
+
+	/// The randomness for the genesis epoch.
+	pub randomness: [u8; VRF_OUTPUT_LENGTH],
+
+	/// Whether this chain should run with secondary slots.
+	pub secondary_slots: bool,
+}
+
+impl From<BabeConfigurationV1> for BabeConfiguration {
+	fn from(v1: BabeConfigurationV1) -> Self {
+		BabeConfiguration {
+			slot_duration: v1.slot_duration,
+			epoch_length: v1.epoch_length,
+			c: v1.c,
+			genesis_authorities: v1.genesis_authorities,
+			randomness: v1.randomness,
+			secondary_slots: v1.secondary_slots,
+			secondary_vrf_slots: false,
+		}
+	}
 }
 
 #[cfg(feature = "std")]
@@ -167,11 +298,18 @@ impl slots::SlotData for BabeConfiguration {
 
 decl_runtime_apis! {
 	/// API necessary for block authorship with BABE.
+	#[api_version(2)]
 	pub trait BabeApi {
 		/// Return the configuration for BABE. Currently,
 		/// only the value provided by this type at genesis will be used.
 		///
 		/// Dynamic configuration may be supported in the future.
 		fn configuration() -> BabeConfiguration;
+
+		/// Return the configuration for BABE, before `secondary_vrf_slots` was added.
+		///
+		/// Is callable by `configuration_before_version_2`.
+		#[changed_in(2)]
+		fn configuration() -> BabeConfigurationV1;
 	}
 }
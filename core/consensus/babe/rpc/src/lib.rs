@@ -0,0 +1,271 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC API for the BABE consensus algorithm.
+//!
+//! Exposes the randomness of the current and next epoch, as seen from the best
+//! block, and the slots of these epochs that can be claimed with the keys held
+//! in the local keystore. The latter reveals which slots the node will author
+//! in, so it is unsafe to expose publicly.
+
+#![warn(missing_docs)]
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use babe::{BabeLink, BabePreDigest, Epoch};
+use client::{blockchain::HeaderBackend, error::Error as ClientError};
+use header_metadata::HeaderMetadata;
+use jsonrpc_derive::rpc;
+use keystore::KeyStorePtr;
+use primitives::H256;
+use rpc_api::{DenyUnsafe, UnsafeRpcError};
+use serde::{Serialize, Deserialize};
+use sr_primitives::generic::BlockId;
+use sr_primitives::traits::Block as BlockT;
+
+pub use self::gen_client::Client as BabeClient;
+
+/// BABE RPC Result type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// BABE RPC errors.
+#[derive(Debug, derive_more::Display, derive_more::From)]
+pub enum Error {
+	/// The header of the best block isn't available.
+	#[display(fmt = "Best block header is unavailable")]
+	BestHeaderUnavailable,
+	/// Client error.
+	#[display(fmt = "Client error: {}", _0)]
+	Client(ClientError),
+	/// An unsafe method was called on a public interface.
+	#[display(fmt = "{}", _0)]
+	Unsafe(UnsafeRpcError),
+}
+
+impl std::error::Error for Error {}
+
+/// Base code for all BABE errors.
+const BASE_ERROR: i64 = 7000;
+
+impl From<Error> for jsonrpc_core::Error {
+	fn from(e: Error) -> Self {
+		match e {
+			Error::BestHeaderUnavailable => jsonrpc_core::Error {
+				code: jsonrpc_core::ErrorCode::ServerError(BASE_ERROR + 1),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::Client(_) => jsonrpc_core::Error {
+				code: jsonrpc_core::ErrorCode::ServerError(BASE_ERROR + 2),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::Unsafe(e) => e.into(),
+		}
+	}
+}
+
+/// The randomness of the current epoch and, once announced, of the next one.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochRandomness {
+	/// The index of the current epoch.
+	pub epoch_index: u64,
+	/// The randomness of the current epoch.
+	pub current: H256,
+	/// The randomness of the next epoch, if it was already announced.
+	pub next: Option<H256>,
+}
+
+/// The maximum number of slots of each epoch `babe_slotAssignments` tries to claim.
+pub const MAX_ASSIGNED_SLOTS: u64 = 2400;
+
+/// The slots of an epoch that can be claimed with one of the local keys.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotAssignments {
+	/// Slots won with a primary VRF claim.
+	pub primary: Vec<u64>,
+	/// Secondary slots.
+	pub secondary: Vec<u64>,
+	/// Secondary slots which carry a VRF output.
+	pub secondary_vrf: Vec<u64>,
+}
+
+/// BABE RPC API
+#[rpc]
+pub trait BabeApi {
+	/// Returns the randomness of the current epoch and of the next epoch, if
+	/// it was already announced.
+	#[rpc(name = "babe_epochRandomness")]
+	fn epoch_randomness(&self) -> Result<EpochRandomness>;
+
+	/// Returns the slots of the current epoch and, if it was already announced,
+	/// of the next epoch that can be claimed with the keys held in the local
+	/// keystore, by public key. Only the first `MAX_ASSIGNED_SLOTS` slots of each
+	/// epoch are listed.
+	///
+	/// Each slot is only listed once, for the key that would claim it. Denied on
+	/// public RPC interfaces.
+	#[rpc(name = "babe_slotAssignments")]
+	fn slot_assignments(&self) -> Result<BTreeMap<String, SlotAssignments>>;
+}
+
+/// Implementation of the BABE RPC API.
+pub struct Babe<Block: BlockT, C> {
+	client: Arc<C>,
+	link: BabeLink<Block>,
+	keystore: KeyStorePtr,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl<Block: BlockT, C> Babe<Block, C> {
+	/// Create new `Babe` with the given reference to the client, the link
+	/// returned by `babe::block_import` and the local keystore.
+	///
+	/// The slot assignments are refused when `deny_unsafe` is `Yes`.
+	pub fn new(client: Arc<C>, link: BabeLink<Block>, keystore: KeyStorePtr, deny_unsafe: DenyUnsafe) -> Self {
+		Babe { client, link, keystore, deny_unsafe }
+	}
+}
+
+impl<Block, C> Babe<Block, C> where
+	Block: BlockT<Hash=H256>,
+	C: HeaderBackend<Block> + HeaderMetadata<Block, Error=ClientError>,
+{
+	/// The current and next epoch as seen from the best block.
+	fn epochs(&self) -> Result<(Epoch, Option<Epoch>)> {
+		let best_hash = self.client.info().best_hash;
+		let best = self.client.header(BlockId::Hash(best_hash))?
+			.ok_or(Error::BestHeaderUnavailable)?;
+
+		Ok(self.link.epochs_at(&*self.client, &best)?)
+	}
+
+	/// Adds the slots of `epoch` that can be claimed with the local keys to `assignments`.
+	fn assign_slots(&self, epoch: &Epoch, assignments: &mut BTreeMap<String, SlotAssignments>) {
+		let end_slot = epoch.end_slot().min(epoch.start_slot.saturating_add(MAX_ASSIGNED_SLOTS));
+		for slot_number in epoch.start_slot..end_slot {
+			let pre_digest = match self.link.claim_slot(slot_number, epoch, &self.keystore) {
+				Some(pre_digest) => pre_digest,
+				None => continue,
+			};
+
+			let author = match epoch.authorities.get(pre_digest.authority_index() as usize) {
+				Some((author, _)) => author.to_string(),
+				None => continue,
+			};
+
+			let slots = assignments.entry(author).or_default();
+			match pre_digest {
+				BabePreDigest::Primary { .. } => slots.primary.push(slot_number),
+				BabePreDigest::Secondary { .. } => slots.secondary.push(slot_number),
+				BabePreDigest::SecondaryVRF { .. } => slots.secondary_vrf.push(slot_number),
+			}
+		}
+	}
+}
+
+impl<Block, C> BabeApi for Babe<Block, C> where
+	Block: BlockT<Hash=H256> + 'static,
+	C: HeaderBackend<Block> + HeaderMetadata<Block, Error=ClientError> + Send + Sync + 'static,
+{
+	fn epoch_randomness(&self) -> Result<EpochRandomness> {
+		let (current, next) = self.epochs()?;
+
+		Ok(EpochRandomness {
+			epoch_index: current.epoch_index,
+			current: current.randomness.into(),
+			next: next.map(|next| next.randomness.into()),
+		})
+	}
+
+	fn slot_assignments(&self) -> Result<BTreeMap<String, SlotAssignments>> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let (current, next) = self.epochs()?;
+		let mut assignments = BTreeMap::<String, SlotAssignments>::new();
+		self.assign_slots(&current, &mut assignments);
+		if let Some(next) = next {
+			self.assign_slots(&next, &mut assignments);
+		}
+
+		Ok(assignments)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use babe::AuthorityPair;
+	use jsonrpc_core::IoHandler;
+
+	#[test]
+	fn serves_epoch_randomness_and_slot_assignments() {
+		let client = Arc::new(test_client::new());
+		let config = babe::Config::get_or_compute(&*client).unwrap();
+		let (_, link) = babe::block_import(
+			config.clone(),
+			client.clone(),
+			client.clone(),
+			client.clone(),
+		).unwrap();
+
+		let keystore_path = tempfile::tempdir().expect("Creates keystore path");
+		let keystore = keystore::Store::open(keystore_path.path(), None).expect("Creates keystore");
+		for seed in &["//Alice", "//Bob", "//Charlie"] {
+			keystore.write().insert_ephemeral_from_seed::<AuthorityPair>(seed)
+				.expect("Generates authority pair");
+		}
+
+		let mut io = IoHandler::new();
+		io.extend_with(BabeApi::to_delegate(Babe::new(client.clone(), link.clone(), keystore.clone(), DenyUnsafe::No)));
+
+		let request = r#"{"jsonrpc":"2.0","method":"babe_epochRandomness","params":[],"id":1}"#;
+		let response = io.handle_request_sync(request).unwrap();
+		let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+		assert_eq!(response["result"]["epochIndex"], 0);
+		assert_eq!(
+			response["result"]["current"],
+			serde_json::to_value(H256::from(config.randomness)).unwrap(),
+		);
+		// the next epoch is only announced once block #1 is imported.
+		assert_eq!(response["result"]["next"], serde_json::Value::Null);
+
+		// the keystore holds all genesis authorities and secondary slots are
+		// enabled, so every slot of the epoch is assigned to one of them. The
+		// next epoch isn't announced yet, so it has no assignments.
+		let request = r#"{"jsonrpc":"2.0","method":"babe_slotAssignments","params":[],"id":2}"#;
+		let response = io.handle_request_sync(request).unwrap();
+		let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+		let assigned: usize = response["result"].as_object().unwrap().values()
+			.map(|slots| {
+				slots["primary"].as_array().unwrap().len() +
+					slots["secondary"].as_array().unwrap().len() +
+					slots["secondaryVrf"].as_array().unwrap().len()
+			})
+			.sum();
+		assert_eq!(assigned as u64, config.epoch_length);
+
+		// public interfaces don't reveal when the node authors blocks.
+		let mut public_io = IoHandler::new();
+		public_io.extend_with(BabeApi::to_delegate(Babe::new(client, link, keystore, DenyUnsafe::Yes)));
+		let response = public_io.handle_request_sync(request).unwrap();
+		let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+		assert_eq!(response["error"]["code"], -32601);
+	}
+}
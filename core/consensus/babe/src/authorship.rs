@@ -98,13 +98,16 @@ pub(super) fn make_transcript(
 
 /// Claim a secondary slot if it is our turn to propose, returning the
 /// pre-digest to use when authoring the block, or `None` if it is not our turn
-/// to propose.
+/// to propose. If `author_secondary_vrf` is set, the pre-digest carries a VRF
+/// output computed over the same transcript as for primary slots.
 fn claim_secondary_slot(
 	slot_number: SlotNumber,
-	authorities: &[(AuthorityId, BabeAuthorityWeight)],
+	epoch: &Epoch,
 	keystore: &KeyStorePtr,
-	randomness: [u8; 32],
+	author_secondary_vrf: bool,
 ) -> Option<(BabePreDigest, AuthorityPair)> {
+	let Epoch { authorities, randomness, epoch_index, .. } = epoch;
+
 	if authorities.is_empty() {
 		return None;
 	}
@@ -112,7 +115,7 @@ fn claim_secondary_slot(
 	let expected_author = super::authorship::secondary_slot_author(
 		slot_number,
 		authorities,
-		randomness,
+		*randomness,
 	)?;
 
 	let keystore = keystore.read();
@@ -124,9 +127,21 @@ fn claim_secondary_slot(
 		})
 	{
 		if pair.public() == *expected_author {
-			let pre_digest = BabePreDigest::Secondary {
-				slot_number,
-				authority_index: authority_index as u32,
+			let pre_digest = if author_secondary_vrf {
+				let transcript = super::authorship::make_transcript(randomness, slot_number, *epoch_index);
+				let (inout, vrf_proof, _) = get_keypair(&pair).vrf_sign(transcript);
+
+				BabePreDigest::SecondaryVRF {
+					slot_number,
+					authority_index: authority_index as u32,
+					vrf_output: inout.to_output(),
+					vrf_proof,
+				}
+			} else {
+				BabePreDigest::Secondary {
+					slot_number,
+					authority_index: authority_index as u32,
+				}
 			};
 
 			return Some((pre_digest, pair));
@@ -151,9 +166,9 @@ pub(super) fn claim_slot(
 			if config.secondary_slots {
 				claim_secondary_slot(
 					slot_number,
-					&epoch.authorities,
+					epoch,
 					keystore,
-					epoch.randomness,
+					config.secondary_vrf_slots,
 				)
 			} else {
 				None
@@ -223,6 +223,12 @@ impl<Hash, Number> EpochChanges<Hash, Number> where
 		Ok(())
 	}
 
+	/// Revert to the given block number, removing all epoch changes signaled
+	/// above it. Epochs that were pruned on finalization are not restored.
+	pub fn revert(&mut self, number: Number) {
+		self.inner.revert(&number);
+	}
+
 	/// Finds the epoch for a child of the given block, assuming the given slot number.
 	///
 	/// If the returned epoch is an `UnimportedGenesis` epoch, it should be imported into the
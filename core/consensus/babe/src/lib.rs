@@ -60,6 +60,7 @@
 #![warn(missing_docs)]
 pub use babe_primitives::*;
 pub use consensus_common::SyncOracle;
+pub use slots::{BackoffAuthoringBlocksStrategy, BackoffAuthoringOnFinalizedHeadLagging};
 use std::{collections::HashMap, sync::Arc, u64, pin::Pin, time::{Instant, Duration}};
 use babe_primitives;
 use consensus_common::ImportResult;
@@ -68,8 +69,8 @@ use consensus_common::import_queue::{
 };
 use sr_primitives::{generic::{BlockId, OpaqueDigestItemId}, Justification, RuntimeString};
 use sr_primitives::traits::{
-	Block as BlockT, Header, DigestItemFor, ProvideRuntimeApi,
-	Zero,
+	Block as BlockT, Header, DigestItemFor, NumberFor, ProvideRuntimeApi,
+	Zero, ApiRef,
 };
 use keystore::KeyStorePtr;
 use parking_lot::Mutex;
@@ -93,16 +94,17 @@ use consensus_common::import_queue::{Verifier, BasicQueue, CacheKeyId};
 use client::{
 	block_builder::api::BlockBuilder as BlockBuilderApi,
 	blockchain::{self, HeaderBackend, ProvideCache}, BlockchainEvents, CallExecutor, Client,
-	error::Result as ClientResult, error::Error as ClientError, backend::{AuxStore, Backend},
-	ProvideUncles,
+	error::Result as ClientResult, error::Error as ClientError,
+	backend::{AuxStore, Backend, ProvideOffchainStorage}, ProvideUncles, runtime_api::ApiExt,
 };
-use slots::{CheckedHeader, check_equivocation};
+use slots::{CheckedHeader, check_equivocation, queue_equivocation_report};
 use futures::prelude::*;
 use log::{warn, debug, info, trace};
 use slots::{SlotWorker, SlotData, SlotInfo, SlotCompatible};
 use epoch_changes::descendent_query;
 use header_metadata::HeaderMetadata;
 use schnorrkel::SignatureError;
+use prometheus::Registry;
 
 mod aux_schema;
 mod verification;
@@ -142,6 +144,10 @@ enum Error<B: BlockT> {
 	SlotAuthorNotFound,
 	#[display(fmt = "Secondary slot assignments are disabled for the current epoch.")]
 	SecondarySlotAssignmentsDisabled,
+	#[display(fmt = "Secondary slot assignments must carry a VRF output.")]
+	SecondarySlotVRFRequired,
+	#[display(fmt = "Secondary slot assignments with a VRF output are disabled.")]
+	SecondarySlotVRFDisabled,
 	#[display(fmt = "Bad signature on {:?}", _0)]
 	BadSignature(B::Hash),
 	#[display(fmt = "Invalid author: Expected secondary author: {:?}, got: {:?}.", _0, _1)]
@@ -205,7 +211,21 @@ impl Config {
 		C: AuxStore + ProvideRuntimeApi, C::Api: BabeApi<B>,
 	{
 		trace!(target: "babe", "Getting slot duration");
-		match slots::SlotDuration::get_or_compute(client, |a, b| a.configuration(b)).map(Self) {
+		migrate_cached_config(client)?;
+
+		let configuration = |api: ApiRef<C::Api>, at: &BlockId<B>| {
+			// runtimes of the first API version return the configuration without
+			// `secondary_vrf_slots`.
+			if api.has_api_with::<dyn BabeApi<B>, _>(at, |v| v >= 2)? {
+				api.configuration(at)
+			} else {
+				#[allow(deprecated)]
+				let configuration = api.configuration_before_version_2(at)?;
+				Ok(configuration.into())
+			}
+		};
+
+		match slots::SlotDuration::get_or_compute(client, configuration).map(Self) {
 			Ok(s) => Ok(s),
 			Err(s) => {
 				warn!(target: "babe", "Failed to get slot duration");
@@ -227,6 +247,30 @@ impl Config {
 	}
 }
 
+/// Upgrade the configuration cached in the auxiliary storage by `Config::get_or_compute`
+/// if it was written before `secondary_vrf_slots` was added to it.
+fn migrate_cached_config<C: AuxStore>(client: &C) -> ClientResult<()> {
+	use codec::{Decode, Encode};
+
+	let cached = match client.get_aux(BabeConfiguration::SLOT_KEY)? {
+		Some(cached) => cached,
+		None => return Ok(()),
+	};
+
+	// the old layout is a prefix of the new one, so it only decodes as the old one.
+	if BabeConfiguration::decode(&mut &cached[..]).is_ok() {
+		return Ok(());
+	}
+
+	if let Ok(v1) = BabeConfigurationV1::decode(&mut &cached[..]) {
+		info!(target: "babe", "Upgrading the cached BABE configuration");
+		let config = BabeConfiguration::from(v1);
+		config.using_encoded(|encoded| client.insert_aux(&[(BabeConfiguration::SLOT_KEY, encoded)], &[]))?;
+	}
+
+	Ok(())
+}
+
 impl std::ops::Deref for Config {
 	type Target = BabeConfiguration;
 
@@ -236,7 +280,7 @@ impl std::ops::Deref for Config {
 }
 
 /// Parameters for BABE.
-pub struct BabeParams<B: BlockT, C, E, I, SO, SC> {
+pub struct BabeParams<B: BlockT, C, E, I, SO, SC, BS> {
 	/// The keystore that manages the keys of the node.
 	pub keystore: KeyStorePtr,
 
@@ -265,10 +309,14 @@ pub struct BabeParams<B: BlockT, C, E, I, SO, SC> {
 
 	/// The source of timestamps for relative slots
 	pub babe_link: BabeLink<B>,
+
+	/// Strategy to back off authoring when finality lags, `None` to author on
+	/// every claimed slot.
+	pub backoff_authoring_blocks: Option<BS>,
 }
 
 /// Start the babe worker. The returned future should be run in a tokio runtime.
-pub fn start_babe<B, C, SC, E, I, SO, Error>(BabeParams {
+pub fn start_babe<B, C, SC, E, I, SO, BS, Error>(BabeParams {
 	keystore,
 	client,
 	select_chain,
@@ -278,7 +326,8 @@ pub fn start_babe<B, C, SC, E, I, SO, Error>(BabeParams {
 	inherent_data_providers,
 	force_authoring,
 	babe_link,
-}: BabeParams<B, C, E, I, SO, SC>) -> Result<
+	backoff_authoring_blocks,
+}: BabeParams<B, C, E, I, SO, SC, BS>) -> Result<
 	impl futures01::Future<Item=(), Error=()>,
 	consensus_common::Error,
 > where
@@ -293,6 +342,7 @@ pub fn start_babe<B, C, SC, E, I, SO, Error>(BabeParams {
 	I: BlockImport<B,Error=ConsensusError> + Send + Sync + 'static,
 	Error: std::error::Error + Send + From<::consensus_common::Error> + From<I::Error> + 'static,
 	SO: SyncOracle + Send + Sync + Clone,
+	BS: BackoffAuthoringBlocksStrategy<NumberFor<B>> + Send + Sync + 'static,
 {
 	let config = babe_link.config;
 	let worker = BabeWorker {
@@ -304,6 +354,7 @@ pub fn start_babe<B, C, SC, E, I, SO, Error>(BabeParams {
 		keystore,
 		epoch_changes: babe_link.epoch_changes.clone(),
 		config: config.clone(),
+		backoff_authoring_blocks,
 	};
 
 	register_babe_inherent_data_provider(&inherent_data_providers, config.slot_duration())?;
@@ -326,7 +377,7 @@ pub fn start_babe<B, C, SC, E, I, SO, Error>(BabeParams {
 	Ok(slot_worker.map(|_| Ok::<(), ()>(())).compat())
 }
 
-struct BabeWorker<B: BlockT, C, E, I, SO> {
+struct BabeWorker<B: BlockT, C, E, I, SO, BS> {
 	client: Arc<C>,
 	block_import: Arc<Mutex<I>>,
 	env: E,
@@ -335,17 +386,20 @@ struct BabeWorker<B: BlockT, C, E, I, SO> {
 	keystore: KeyStorePtr,
 	epoch_changes: SharedEpochChanges<B>,
 	config: Config,
+	backoff_authoring_blocks: Option<BS>,
 }
 
-impl<B, C, E, I, Error, SO> slots::SimpleSlotWorker<B> for BabeWorker<B, C, E, I, SO> where
+impl<B, C, E, I, Error, SO, BS> slots::SimpleSlotWorker<B> for BabeWorker<B, C, E, I, SO, BS> where
 	B: BlockT<Hash=H256>,
-	C: ProvideRuntimeApi + ProvideCache<B> + HeaderBackend<B> + HeaderMetadata<B, Error=ClientError>,
+	C: ProvideRuntimeApi + ProvideCache<B> + ProvideUncles<B> + HeaderBackend<B>
+		+ HeaderMetadata<B, Error=ClientError>,
 	C::Api: BabeApi<B>,
 	E: Environment<B, Error=Error>,
 	E::Proposer: Proposer<B, Error=Error>,
 	<E::Proposer as Proposer<B>>::Create: Unpin + Send + 'static,
 	I: BlockImport<B> + Send + Sync + 'static,
 	SO: SyncOracle + Send + Clone,
+	BS: BackoffAuthoringBlocksStrategy<NumberFor<B>>,
 	Error: std::error::Error + Send + From<::consensus_common::Error> + From<I::Error> + 'static,
 {
 	type EpochData = Epoch;
@@ -448,17 +502,37 @@ impl<B, C, E, I, Error, SO> slots::SimpleSlotWorker<B> for BabeWorker<B, C, E, I
 			consensus_common::Error::ClientImport(format!("{:?}", e))
 		})
 	}
+
+	fn backoff_authoring_blocks(&self) -> Option<&dyn BackoffAuthoringBlocksStrategy<NumberFor<B>>> {
+		self.backoff_authoring_blocks.as_ref().map(|s| s as &dyn BackoffAuthoringBlocksStrategy<_>)
+	}
+
+	fn slot_of(&self, header: &B::Header) -> Option<u64> {
+		find_pre_digest::<B>(header).ok().map(|pre_digest| pre_digest.slot_number())
+	}
+
+	fn finalized_number(&self) -> Option<NumberFor<B>> {
+		Some(self.client.info().finalized_number)
+	}
+
+	fn update_inherent_data(&self, chain_head: &B::Header, inherent_data: &mut InherentData) {
+		// the uncles inherent data provider picks uncles for the best block of
+		// the select chain when the slot starts, which may not be `chain_head`.
+		uncles::replace_uncles_inherent_data(&*self.client, chain_head.hash(), inherent_data);
+	}
 }
 
-impl<B, C, E, I, Error, SO> SlotWorker<B> for BabeWorker<B, C, E, I, SO> where
+impl<B, C, E, I, Error, SO, BS> SlotWorker<B> for BabeWorker<B, C, E, I, SO, BS> where
 	B: BlockT<Hash=H256>,
-	C: ProvideRuntimeApi + ProvideCache<B> + HeaderBackend<B> + HeaderMetadata<B, Error=ClientError> + Send + Sync,
+	C: ProvideRuntimeApi + ProvideCache<B> + ProvideUncles<B> + HeaderBackend<B>
+		+ HeaderMetadata<B, Error=ClientError> + Send + Sync,
 	C::Api: BabeApi<B>,
 	E: Environment<B, Error=Error> + Send + Sync,
 	E::Proposer: Proposer<B, Error=Error>,
 	<E::Proposer as Proposer<B>>::Create: Unpin + Send + 'static,
 	I: BlockImport<B> + Send + Sync + 'static,
 	SO: SyncOracle + Send + Sync + Clone,
+	BS: BackoffAuthoringBlocksStrategy<NumberFor<B>> + Send + Sync,
 	Error: std::error::Error + Send + From<::consensus_common::Error> + From<I::Error> + 'static,
 {
 	type OnSlot = Pin<Box<dyn Future<Output = Result<(), consensus_common::Error>> + Send>>;
@@ -537,6 +611,59 @@ pub struct BabeLink<Block: BlockT> {
 	epoch_changes: SharedEpochChanges<Block>,
 	config: Config,
 }
+
+impl<Block: BlockT<Hash=H256>> BabeLink<Block> {
+	/// Get the epoch changes of this link.
+	pub fn epoch_changes(&self) -> &SharedEpochChanges<Block> {
+		&self.epoch_changes
+	}
+
+	/// Get the config of this link.
+	pub fn config(&self) -> &Config {
+		&self.config
+	}
+
+	/// Get the epoch of the given block and, if it was already announced,
+	/// the epoch following it.
+	pub fn epochs_at<C>(&self, client: &C, header: &Block::Header) -> ClientResult<(Epoch, Option<Epoch>)> where
+		C: HeaderBackend<Block> + HeaderMetadata<Block, Error=ClientError>,
+	{
+		let slot_number = find_pre_digest::<Block>(header)
+			.map_err(|e| ClientError::Consensus(ConsensusError::ChainLookup(e.to_string())))?
+			.slot_number();
+
+		let epoch_for_slot = |slot_number| self.epoch_changes.lock().epoch_for_child_of(
+			descendent_query(client),
+			&header.hash(),
+			header.number().clone(),
+			slot_number,
+			|slot| self.config.genesis_epoch(slot),
+		)
+			.map_err(|e| ClientError::Consensus(ConsensusError::ChainLookup(format!("{:?}", e))))
+			.map(|epoch| epoch.map(|e| e.into_inner()));
+
+		let current = epoch_for_slot(slot_number)?
+			.ok_or_else(|| ClientError::Consensus(ConsensusError::InvalidAuthoritiesSet))?;
+
+		// the next epoch is announced in the first block of the current one.
+		let next = epoch_for_slot(current.end_slot())?
+			.filter(|next| next.epoch_index == current.epoch_index + 1);
+
+		Ok((current, next))
+	}
+
+	/// Try to claim the given slot of the epoch with the keys in the keystore,
+	/// returning the pre-digest the block would be authored with.
+	pub fn claim_slot(
+		&self,
+		slot_number: SlotNumber,
+		epoch: &Epoch,
+		keystore: &KeyStorePtr,
+	) -> Option<BabePreDigest> {
+		authorship::claim_slot(slot_number, epoch, &self.config, keystore)
+			.map(|(pre_digest, _)| pre_digest)
+	}
+}
 /// A verifier for Babe blocks.
 pub struct BabeVerifier<B, E, Block: BlockT, RA, PRA> {
 	client: Arc<Client<B, E, Block, RA>>,
@@ -705,6 +832,20 @@ impl<B, E, Block, RA, PRA> Verifier<Block> for BabeVerifier<B, E, Block, RA, PRA
 						equivocation_proof.fst_header().hash(),
 						equivocation_proof.snd_header().hash(),
 					);
+
+					// queue the equivocation for the offchain worker to report it on-chain.
+					if let Some(mut storage) = self.client.offchain_storage() {
+						queue_equivocation_report(
+							&mut storage,
+							babe_primitives::EQUIVOCATIONS_KEY,
+							babe_primitives::EquivocationProof {
+								offender: author.clone(),
+								slot_number: equivocation_proof.slot(),
+								first_header: equivocation_proof.fst_header().clone(),
+								second_header: equivocation_proof.snd_header().clone(),
+							},
+						);
+					}
 				}
 
 				// if the body is passed through, we need to use the runtime
@@ -1077,6 +1218,59 @@ fn prune_finalized<B, E, Block, RA>(
 	Ok(())
 }
 
+/// Rewind the persisted epoch changes so that they match the chain once the best
+/// chain has been reverted to the given block, which may be below the last
+/// finalized block. Fails if the epoch data needed to author or import children
+/// of that block has already been pruned.
+///
+/// Nothing is written: the returned auxiliary data must be written along with the
+/// reverted blocks, see `Client::unsafe_revert`.
+pub fn revert_aux<B, E, Block, RA>(
+	client: &Client<B, E, Block, RA>,
+	hash: Block::Hash,
+	number: NumberFor<Block>,
+) -> ClientResult<Vec<(Vec<u8>, Option<Vec<u8>>)>> where
+	Block: BlockT<Hash=H256>,
+	E: CallExecutor<Block, Blake2Hasher> + Send + Sync,
+	B: Backend<Block, Blake2Hasher>,
+	RA: Send + Sync,
+{
+	let epoch_changes = aux_schema::load_epoch_changes(client)?;
+	let mut epoch_changes = epoch_changes.lock();
+	epoch_changes.revert(number);
+
+	if !number.is_zero() {
+		let header = client.header(&BlockId::Hash(hash))?
+			.ok_or_else(|| ClientError::UnknownBlock(format!("{:?}", hash)))?;
+		let slot_number = find_pre_digest::<Block>(&header)
+			.map_err(|e| ClientError::Msg(format!("{:?}", e)))?
+			.slot_number();
+
+		let epoch = epoch_changes.epoch_for_child_of(
+			descendent_query(client),
+			&hash,
+			number,
+			slot_number + 1,
+			|_| unreachable!("genesis epoch is only created for children of block #0; qed"),
+		).map_err(|e| ClientError::Msg(format!("{:?}", e)))?;
+
+		if epoch.is_none() {
+			return Err(ClientError::Msg(
+				format!("BABE epoch data of block #{} has been pruned already", number)
+			));
+		}
+	}
+
+	let aux = aux_schema::write_epoch_changes::<Block, _, _>(
+		&*epoch_changes,
+		|values| values.iter().map(|(key, value)| (key.to_vec(), Some(value.to_vec()))).collect(),
+	);
+
+	info!(target: "babe", "Reverting BABE epoch changes to block #{} ({:?})", number, hash);
+
+	Ok(aux)
+}
+
 /// Produce a BABE block-import object to be used later on in the construction of
 /// an import-queue.
 ///
@@ -1135,6 +1329,7 @@ pub fn import_queue<B, E, Block: BlockT<Hash=H256>, I, RA, PRA>(
 	client: Arc<Client<B, E, Block, RA>>,
 	api: Arc<PRA>,
 	inherent_data_providers: InherentDataProviders,
+	prometheus_registry: Option<&Registry>,
 ) -> ClientResult<BabeImportQueue<Block>> where
 	B: Backend<Block, Blake2Hasher> + 'static,
 	I: BlockImport<Block,Error=ConsensusError> + Send + Sync + 'static,
@@ -1159,6 +1354,7 @@ pub fn import_queue<B, E, Block: BlockT<Hash=H256>, I, RA, PRA>(
 		Box::new(block_import),
 		justification_import,
 		finality_proof_import,
+		prometheus_registry,
 	))
 }
 
@@ -404,6 +404,7 @@ fn run_one_test(
 			force_authoring: false,
 			babe_link: data.link.clone(),
 			keystore,
+			backoff_authoring_blocks: Option::<()>::None,
 		}).expect("Starts babe"));
 	}
 
@@ -503,6 +504,7 @@ fn can_author_block() {
 		genesis_authorities: Vec::new(),
 		randomness: [0; 32],
 		secondary_slots: true,
+		secondary_vrf_slots: false,
 	};
 
 	// with secondary slots enabled it should never be empty
@@ -525,6 +527,50 @@ fn can_author_block() {
 	}
 }
 
+#[test]
+fn secondary_vrf_slots_carry_vrf_output() {
+	let _ = env_logger::try_init();
+	let keystore_path = tempfile::tempdir().expect("Creates keystore path");
+	let keystore = keystore::Store::open(keystore_path.path(), None).expect("Creates keystore");
+	let pair = keystore.write().insert_ephemeral_from_seed::<AuthorityPair>("//Alice")
+		.expect("Generates authority pair");
+
+	let epoch = Epoch {
+		start_slot: 0,
+		authorities: vec![(pair.public(), 1)],
+		randomness: [0; 32],
+		epoch_index: 1,
+		duration: 100,
+	};
+
+	// with `c = 0` primary slots are never claimed.
+	let config = crate::BabeConfiguration {
+		slot_duration: 1000,
+		epoch_length: 100,
+		c: (0, 1),
+		genesis_authorities: Vec::new(),
+		randomness: [0; 32],
+		secondary_slots: true,
+		secondary_vrf_slots: true,
+	};
+
+	let (pre_digest, _) = claim_slot(10, &epoch, &config, &keystore)
+		.expect("the only authority is always the secondary author");
+
+	match pre_digest {
+		BabePreDigest::SecondaryVRF { authority_index, slot_number, vrf_output, vrf_proof } => {
+			assert_eq!(authority_index, 0);
+			assert_eq!(slot_number, 10);
+
+			let transcript = authorship::make_transcript(&epoch.randomness, 10, epoch.epoch_index);
+			schnorrkel::PublicKey::from_bytes(pair.public().as_slice())
+				.and_then(|p| p.vrf_verify(transcript, &vrf_output, &vrf_proof))
+				.expect("VRF output of the secondary slot is valid");
+		},
+		pre_digest => panic!("expected secondary VRF pre-digest, got {:?}", pre_digest),
+	}
+}
+
 // Propose and import a new BABE block on top of the given parent.
 fn propose_and_import_block(
 	parent: &TestHeader,
@@ -777,3 +823,28 @@ fn verify_slots_are_strictly_increasing() {
 		&mut block_import,
 	);
 }
+
+#[test]
+fn configuration_cached_before_secondary_vrf_slots_is_upgraded() {
+	use codec::{Encode, Decode};
+
+	let client = test_client::new();
+	let cached = BabeConfigurationV1 {
+		slot_duration: 1000,
+		epoch_length: 10,
+		c: (3, 10),
+		genesis_authorities: Vec::new(),
+		randomness: [1; VRF_OUTPUT_LENGTH],
+		secondary_slots: true,
+	};
+	client.insert_aux(&[(BabeConfiguration::SLOT_KEY, &cached.encode()[..])], &[]).unwrap();
+
+	// the cached configuration is used instead of the one of the genesis runtime.
+	let config = Config::get_or_compute(&client).unwrap();
+	assert_eq!(*config, BabeConfiguration::from(cached.clone()));
+	assert!(!config.secondary_vrf_slots);
+
+	// and kept in the new layout.
+	let stored = client.get_aux(BabeConfiguration::SLOT_KEY).unwrap().unwrap();
+	assert_eq!(BabeConfiguration::decode(&mut &stored[..]).unwrap(), BabeConfiguration::from(cached));
+}
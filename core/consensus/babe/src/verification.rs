@@ -15,7 +15,7 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 //! Verification for BABE headers.
-use schnorrkel::vrf::{VRFOutput, VRFProof};
+use schnorrkel::vrf::{VRFInOut, VRFOutput, VRFProof};
 use sr_primitives::{traits::Header, traits::DigestItemFor};
 use primitives::{Pair, Public};
 use babe_primitives::{Epoch, BabePreDigest, CompatibleDigestItem, AuthorityId};
@@ -106,7 +106,18 @@ pub(super) fn check_header<B: BlockT + Sized>(
 				config.c,
 			)?;
 		},
-		BabePreDigest::Secondary { authority_index, slot_number } if config.secondary_slots => {
+		BabePreDigest::Secondary { .. } | BabePreDigest::SecondaryVRF { .. }
+			if !config.secondary_slots =>
+		{
+			return Err(babe_err(Error::SecondarySlotAssignmentsDisabled));
+		},
+		BabePreDigest::Secondary { .. } if config.secondary_vrf_slots => {
+			return Err(babe_err(Error::SecondarySlotVRFRequired));
+		},
+		BabePreDigest::SecondaryVRF { .. } if !config.secondary_vrf_slots => {
+			return Err(babe_err(Error::SecondarySlotVRFDisabled));
+		},
+		BabePreDigest::Secondary { authority_index, slot_number } => {
 			debug!(target: "babe", "Verifying Secondary block");
 
 			let digest = (*authority_index, *slot_number);
@@ -118,9 +129,23 @@ pub(super) fn check_header<B: BlockT + Sized>(
 				&epoch,
 			)?;
 		},
-		_ => {
-			return Err(babe_err(Error::SecondarySlotAssignmentsDisabled));
-		}
+		BabePreDigest::SecondaryVRF { authority_index, slot_number, vrf_output, vrf_proof } => {
+			debug!(target: "babe", "Verifying Secondary VRF block");
+
+			let digest = (*authority_index, *slot_number);
+
+			check_secondary_header::<B>(
+				pre_hash,
+				digest,
+				sig,
+				&epoch,
+			)?;
+
+			check_secondary_vrf::<B>(
+				(vrf_output, vrf_proof, *authority_index, *slot_number),
+				&epoch,
+			)?;
+		},
 	}
 
 	let info = VerifiedHeaderInfo {
@@ -153,19 +178,7 @@ fn check_primary_header<B: BlockT + Sized>(
 	let author = &epoch.authorities[authority_index as usize].0;
 
 	if AuthorityPair::verify(&signature, pre_hash, &author) {
-		let (inout, _) = {
-			let transcript = make_transcript(
-				&epoch.randomness,
-				slot_number,
-				epoch.epoch_index,
-			);
-
-			schnorrkel::PublicKey::from_bytes(author.as_slice()).and_then(|p| {
-				p.vrf_verify(transcript, vrf_output, vrf_proof)
-			}).map_err(|s| {
-				babe_err(Error::VRFVerificationFailed(s))
-			})?
-		};
+		let inout = verify_vrf::<B>(author, vrf_output, vrf_proof, slot_number, epoch)?;
 
 		let threshold = calculate_primary_threshold(
 			c,
@@ -215,3 +228,37 @@ fn check_secondary_header<B: BlockT>(
 		Err(Error::BadSignature(pre_hash))
 	}
 }
+
+/// Check the VRF output of a secondary slot proposal header. The secondary
+/// author was already checked by `check_secondary_header`, the output only
+/// needs to be valid, without any threshold.
+fn check_secondary_vrf<B: BlockT>(
+	pre_digest: (&VRFOutput, &VRFProof, AuthorityIndex, SlotNumber),
+	epoch: &Epoch,
+) -> Result<(), Error<B>> {
+	let (vrf_output, vrf_proof, authority_index, slot_number) = pre_digest;
+
+	let author = &epoch.authorities[authority_index as usize].0;
+	verify_vrf::<B>(author, vrf_output, vrf_proof, slot_number, epoch).map(|_| ())
+}
+
+/// Verify a VRF output of the given author over the transcript of the slot.
+fn verify_vrf<B: BlockT>(
+	author: &AuthorityId,
+	vrf_output: &VRFOutput,
+	vrf_proof: &VRFProof,
+	slot_number: SlotNumber,
+	epoch: &Epoch,
+) -> Result<VRFInOut, Error<B>> {
+	let transcript = make_transcript(
+		&epoch.randomness,
+		slot_number,
+		epoch.epoch_index,
+	);
+
+	schnorrkel::PublicKey::from_bytes(author.as_slice()).and_then(|p| {
+		p.vrf_verify(transcript, vrf_output, vrf_proof)
+	}).map(|(inout, _)| inout).map_err(|s| {
+		babe_err(Error::VRFVerificationFailed(s))
+	})
+}
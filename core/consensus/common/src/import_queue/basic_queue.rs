@@ -14,9 +14,10 @@
 // You should have received a copy of the GNU General Public License
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{mem, pin::Pin, time::Duration};
+use std::{mem, pin::Pin, time::{Duration, Instant}};
 use futures::{prelude::*, channel::mpsc, task::Context, task::Poll};
 use futures_timer::Delay;
+use prometheus::{Histogram, HistogramOpts, IntCounterVec, Opts, PrometheusError, Registry, register};
 use sr_primitives::{Justification, traits::{Block as BlockT, Header as HeaderT, NumberFor}};
 
 use crate::block_import::BlockOrigin;
@@ -47,12 +48,21 @@ impl<B: BlockT> BasicQueue<B> {
 	///
 	/// This creates a background task, and calls `on_start` on the justification importer and
 	/// finality proof importer.
+	///
+	/// The time it takes to import blocks is reported to `prometheus_registry`, if any.
 	pub fn new<V: 'static + Verifier<B>>(
 		verifier: V,
 		block_import: BoxBlockImport<B>,
 		justification_import: Option<BoxJustificationImport<B>>,
 		finality_proof_import: Option<BoxFinalityProofImport<B>>,
+		prometheus_registry: Option<&Registry>,
 	) -> Self {
+		let metrics = prometheus_registry.and_then(|registry| {
+			Metrics::register(registry)
+				.map_err(|e| warn!(target: "sync", "Failed to register import queue metrics: {}", e))
+				.ok()
+		});
+
 		let (result_sender, result_port) = buffered_link::buffered_link();
 		let (future, worker_sender) = BlockImportWorker::new(
 			result_sender,
@@ -60,6 +70,7 @@ impl<B: BlockT> BasicQueue<B> {
 			block_import,
 			justification_import,
 			finality_proof_import,
+			metrics,
 		);
 
 		let mut pool = futures::executor::ThreadPool::builder()
@@ -132,11 +143,52 @@ enum ToWorkerMsg<B: BlockT> {
 	ImportFinalityProof(Origin, B::Hash, NumberFor<B>, Vec<u8>),
 }
 
+/// Prometheus metrics of the import queue.
+#[derive(Clone)]
+struct Metrics {
+	/// Time it takes to verify and import a block.
+	import_duration: Histogram,
+	/// Number of blocks processed, by result.
+	processed: IntCounterVec,
+}
+
+impl Metrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Metrics {
+			import_duration: register(Histogram::with_opts(HistogramOpts::new(
+				"block_import_duration_seconds",
+				"Time it takes to verify and import a block",
+			))?, registry)?,
+			processed: register(IntCounterVec::new(
+				Opts::new("block_import_processed_total", "Number of blocks processed by the import queue"),
+				&["result"],
+			)?, registry)?,
+		})
+	}
+
+	fn report_import<N>(&self, result: &Result<BlockImportResult<N>, BlockImportError>, elapsed: Duration) {
+		let label = match result {
+			Ok(_) => {
+				self.import_duration.observe(elapsed.as_secs_f64());
+				"success"
+			},
+			Err(BlockImportError::IncompleteHeader(_)) => "incomplete_header",
+			Err(BlockImportError::VerificationFailed(_, _)) => "verification_failed",
+			Err(BlockImportError::BadBlock(_)) => "bad_block",
+			Err(BlockImportError::UnknownParent) => "unknown_parent",
+			Err(BlockImportError::Cancelled) => "cancelled",
+			Err(BlockImportError::Other(_)) => "other",
+		};
+		self.processed.with_label_values(&[label]).inc();
+	}
+}
+
 struct BlockImportWorker<B: BlockT> {
 	result_sender: BufferedLinkSender<B>,
 	justification_import: Option<BoxJustificationImport<B>>,
 	finality_proof_import: Option<BoxFinalityProofImport<B>>,
 	delay_between_blocks: Duration,
+	metrics: Option<Metrics>,
 }
 
 impl<B: BlockT> BlockImportWorker<B> {
@@ -146,6 +198,7 @@ impl<B: BlockT> BlockImportWorker<B> {
 		block_import: BoxBlockImport<B>,
 		justification_import: Option<BoxJustificationImport<B>>,
 		finality_proof_import: Option<BoxFinalityProofImport<B>>,
+		metrics: Option<Metrics>,
 	) -> (impl Future<Output = ()> + Send, mpsc::UnboundedSender<ToWorkerMsg<B>>) {
 		let (sender, mut port) = mpsc::unbounded();
 
@@ -154,6 +207,7 @@ impl<B: BlockT> BlockImportWorker<B> {
 			justification_import,
 			finality_proof_import,
 			delay_between_blocks: Duration::new(0, 0),
+			metrics,
 		};
 
 		// Let's initialize `justification_import` and `finality_proof_import`.
@@ -244,7 +298,14 @@ impl<B: BlockT> BlockImportWorker<B> {
 	) -> impl Future<Output = (BoxBlockImport<B>, V)> {
 		let mut result_sender = self.result_sender.clone();
 
-		import_many_blocks(block_import, origin, blocks, verifier, self.delay_between_blocks)
+		import_many_blocks(
+			block_import,
+			origin,
+			blocks,
+			verifier,
+			self.delay_between_blocks,
+			self.metrics.clone(),
+		)
 			.then(move |(imported, count, results, block_import, verifier)| {
 				result_sender.blocks_processed(imported, count, results);
 				future::ready((block_import, verifier))
@@ -315,6 +376,7 @@ fn import_many_blocks<B: BlockT, V: Verifier<B>>(
 	blocks: Vec<IncomingBlock<B>>,
 	verifier: V,
 	delay_between_blocks: Duration,
+	metrics: Option<Metrics>,
 ) -> impl Future<Output = (usize, usize, Vec<(
 	Result<BlockImportResult<NumberFor<B>>, BlockImportError>,
 	B::Hash,
@@ -376,6 +438,7 @@ fn import_many_blocks<B: BlockT, V: Verifier<B>>(
 
 		let block_number = block.header.as_ref().map(|h| h.number().clone());
 		let block_hash = block.hash;
+		let started = Instant::now();
 		let import_result = if has_error {
 			Err(BlockImportError::Cancelled)
 		} else {
@@ -388,6 +451,10 @@ fn import_many_blocks<B: BlockT, V: Verifier<B>>(
 			)
 		};
 
+		if let Some(metrics) = &metrics {
+			metrics.report_import(&import_result, started.elapsed());
+		}
+
 		if import_result.is_ok() {
 			trace!(target: "sync", "Block imported successfully {:?} ({})", block_number, block_hash);
 			imported += 1;
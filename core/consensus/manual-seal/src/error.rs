@@ -0,0 +1,88 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Errors of the manual seal engine.
+
+use client::error::Error as ClientError;
+use consensus_common::{Error as ConsensusError, ImportResult};
+use sr_primitives::RuntimeString;
+
+/// Manual seal errors.
+#[derive(Debug, derive_more::Display)]
+pub enum Error {
+	/// An empty block was requested while empty blocks are not allowed.
+	#[display(fmt = "Transaction pool is empty, set create_empty to true to create empty blocks")]
+	EmptyTransactionPool,
+	/// The block to build on or to finalize is unknown.
+	#[display(fmt = "Block {} was not found", _0)]
+	BlockNotFound(String),
+	/// The block was built but not imported.
+	#[display(fmt = "Block import failed: {:?}", _0)]
+	BlockImportError(ImportResult),
+	/// Creating the inherent data failed.
+	#[display(fmt = "Creating inherents failed: {}", _0)]
+	InherentError(RuntimeString),
+	/// Building the block failed.
+	#[display(fmt = "Proposing the block failed: {}", _0)]
+	ProposerError(String),
+	/// Consensus error.
+	#[display(fmt = "Consensus error: {}", _0)]
+	ConsensusError(ConsensusError),
+	/// Client error.
+	#[display(fmt = "Client error: {}", _0)]
+	ClientError(ClientError),
+	/// The engine isn't running anymore.
+	#[display(fmt = "Manual seal engine is shutting down")]
+	EngineShutdown,
+}
+
+impl std::error::Error for Error {}
+
+impl From<ConsensusError> for Error {
+	fn from(e: ConsensusError) -> Self {
+		Error::ConsensusError(e)
+	}
+}
+
+impl From<ClientError> for Error {
+	fn from(e: ClientError) -> Self {
+		Error::ClientError(e)
+	}
+}
+
+/// Base code for all manual seal errors.
+const BASE_ERROR: i64 = 8000;
+
+impl From<Error> for jsonrpc_core::Error {
+	fn from(e: Error) -> Self {
+		let code = match e {
+			Error::EmptyTransactionPool => 1,
+			Error::BlockNotFound(_) => 2,
+			Error::BlockImportError(_) => 3,
+			Error::InherentError(_) => 4,
+			Error::ProposerError(_) => 5,
+			Error::ConsensusError(_) => 6,
+			Error::ClientError(_) => 7,
+			Error::EngineShutdown => 8,
+		};
+
+		jsonrpc_core::Error {
+			code: jsonrpc_core::ErrorCode::ServerError(BASE_ERROR + code),
+			message: format!("{}", e),
+			data: None,
+		}
+	}
+}
@@ -0,0 +1,395 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Manual and instant seal consensus, for development and testing.
+//!
+//! Blocks are authored by the local node alone, without any seal or
+//! authorship checks, so this engine must never be used for a live network.
+//!
+//! `run_manual_seal` builds a block whenever it is sent an `EngineCommand`,
+//! which the `engine_createBlock` and `engine_finalizeBlock` RPC calls of the
+//! `rpc` module do. `run_instant_seal` builds a block as soon as transactions
+//! are imported into the transaction pool. In both cases, blocks can be
+//! finalized on import. As nothing proves who authored a block, the import
+//! queue returned by `import_queue` rejects all blocks received from the
+//! network, so a manual seal node can't be fed blocks by its peers.
+
+#![warn(missing_docs)]
+
+mod error;
+pub mod rpc;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use client::backend::{Backend as ClientBackend, Finalizer};
+use client::blockchain::HeaderBackend;
+use consensus_common::{
+	BlockImport, BlockImportParams, BlockOrigin, Environment, ForkChoiceStrategy,
+	ImportResult, Proposer, SelectChain,
+};
+use consensus_common::import_queue::{BasicQueue, BoxBlockImport, CacheKeyId, Verifier};
+use futures::{Stream, StreamExt};
+use inherents::InherentDataProviders;
+use log::info;
+use primitives::{Blake2Hasher, H256};
+use prometheus::Registry;
+use sr_primitives::Justification;
+use sr_primitives::generic::BlockId;
+use sr_primitives::traits::{Block as BlockT, Header as HeaderT};
+use transaction_pool::txpool;
+
+pub use error::Error;
+pub use rpc::{CreatedBlock, EngineCommand};
+
+/// The maximum time to spend building a block.
+const MAX_PROPOSAL_DURATION: Duration = Duration::from_secs(10);
+
+/// A verifier which rejects blocks received from the network, as manual seal
+/// blocks carry no seal, and accepts all others.
+struct ManualSealVerifier;
+
+impl<B: BlockT> Verifier<B> for ManualSealVerifier {
+	fn verify(
+		&mut self,
+		origin: BlockOrigin,
+		header: B::Header,
+		justification: Option<Justification>,
+		body: Option<Vec<B::Extrinsic>>,
+	) -> Result<(BlockImportParams<B>, Option<Vec<(CacheKeyId, Vec<u8>)>>), String> {
+		match origin {
+			BlockOrigin::NetworkInitialSync |
+			BlockOrigin::NetworkBroadcast |
+			BlockOrigin::ConsensusBroadcast =>
+				return Err(format!("Rejecting block {:?} from the network, manual seal blocks are unsealed", header.hash())),
+			BlockOrigin::Genesis | BlockOrigin::Own | BlockOrigin::File => {},
+		}
+
+		let import_block = BlockImportParams {
+			origin,
+			header,
+			justification,
+			post_digests: Vec::new(),
+			body,
+			finalized: false,
+			auxiliary: Vec::new(),
+			fork_choice: ForkChoiceStrategy::LongestChain,
+			allow_missing_state: false,
+		};
+
+		Ok((import_block, None))
+	}
+}
+
+/// Import queue for the manual seal engine.
+pub fn import_queue<B: BlockT>(
+	block_import: BoxBlockImport<B>,
+	prometheus_registry: Option<&Registry>,
+) -> BasicQueue<B> {
+	BasicQueue::new(ManualSealVerifier, block_import, None, None, prometheus_registry)
+}
+
+/// Run the manual seal engine, which builds and finalizes blocks as instructed
+/// by the `commands` stream, typically fed by the `rpc::ManualSeal` RPC handler.
+///
+/// Blocks are imported with `block_import`, which should be the one given to
+/// the import queue.
+pub async fn run_manual_seal<B, CB, E, A, C, S, SC>(
+	mut block_import: BoxBlockImport<B>,
+	mut env: E,
+	client: Arc<C>,
+	pool: Arc<txpool::Pool<A>>,
+	mut commands: S,
+	select_chain: SC,
+	inherent_data_providers: InherentDataProviders,
+) where
+	B: BlockT<Hash=H256>,
+	CB: ClientBackend<B, Blake2Hasher>,
+	E: Environment<B>,
+	E::Error: std::fmt::Debug,
+	A: txpool::ChainApi<Block=B>,
+	C: HeaderBackend<B> + Finalizer<B, Blake2Hasher, CB>,
+	S: Stream<Item=EngineCommand<B::Hash>> + Unpin,
+	SC: SelectChain<B>,
+{
+	while let Some(command) = commands.next().await {
+		match command {
+			EngineCommand::SealNewBlock { create_empty, finalize, parent_hash, sender } => {
+				let result = seal_new_block(
+					&mut block_import,
+					&mut env,
+					&*client,
+					&*pool,
+					&select_chain,
+					&inherent_data_providers,
+					create_empty,
+					finalize,
+					parent_hash,
+				).await;
+
+				rpc::send_result(sender, result);
+			},
+			EngineCommand::FinalizeBlock { hash, justification, sender } => {
+				let result = finalize_block(&*client, hash, justification);
+
+				rpc::send_result(sender, result);
+			},
+		}
+	}
+}
+
+/// Run the instant seal engine, which builds a block whenever transactions are
+/// imported into the transaction pool, and finalizes it if `finalize` is set.
+///
+/// No empty blocks are built.
+pub async fn run_instant_seal<B, CB, E, A, C, SC>(
+	block_import: BoxBlockImport<B>,
+	env: E,
+	client: Arc<C>,
+	pool: Arc<txpool::Pool<A>>,
+	select_chain: SC,
+	inherent_data_providers: InherentDataProviders,
+	finalize: bool,
+) where
+	B: BlockT<Hash=H256>,
+	CB: ClientBackend<B, Blake2Hasher>,
+	E: Environment<B>,
+	E::Error: std::fmt::Debug,
+	A: txpool::ChainApi<Block=B>,
+	C: HeaderBackend<B> + Finalizer<B, Blake2Hasher, CB>,
+	SC: SelectChain<B>,
+{
+	let commands = pool.import_notification_stream()
+		.map(move |()| EngineCommand::SealNewBlock {
+			create_empty: false,
+			finalize,
+			parent_hash: None,
+			sender: None,
+		});
+
+	run_manual_seal(
+		block_import,
+		env,
+		client,
+		pool,
+		commands,
+		select_chain,
+		inherent_data_providers,
+	).await
+}
+
+/// Build a block on top of `parent_hash`, or of the best block, and import it.
+async fn seal_new_block<B, E, A, C, SC>(
+	block_import: &mut BoxBlockImport<B>,
+	env: &mut E,
+	client: &C,
+	pool: &txpool::Pool<A>,
+	select_chain: &SC,
+	inherent_data_providers: &InherentDataProviders,
+	create_empty: bool,
+	finalize: bool,
+	parent_hash: Option<B::Hash>,
+) -> Result<CreatedBlock<B::Hash>, Error> where
+	B: BlockT,
+	E: Environment<B>,
+	E::Error: std::fmt::Debug,
+	A: txpool::ChainApi<Block=B>,
+	C: HeaderBackend<B>,
+	SC: SelectChain<B>,
+{
+	if !create_empty && pool.status().ready == 0 {
+		return Err(Error::EmptyTransactionPool);
+	}
+
+	let parent = match parent_hash {
+		Some(hash) => client.header(BlockId::Hash(hash))?
+			.ok_or_else(|| Error::BlockNotFound(format!("{:?}", hash)))?,
+		None => select_chain.best_chain()?,
+	};
+
+	let inherent_data = inherent_data_providers.create_inherent_data()
+		.map_err(Error::InherentError)?;
+
+	// the proposer isn't necessarily `Send`, so it mustn't be held across
+	// the await point.
+	let proposal = {
+		let mut proposer = env.init(&parent)
+			.map_err(|e| Error::ProposerError(format!("{:?}", e)))?;
+		proposer.propose(inherent_data, Default::default(), MAX_PROPOSAL_DURATION)
+	};
+	let block = proposal.await
+		.map_err(|e| Error::ProposerError(format!("{:?}", e)))?;
+
+	let (header, body) = block.deconstruct();
+	let hash = header.hash();
+	let number = *header.number();
+
+	let import_block = BlockImportParams {
+		origin: BlockOrigin::Own,
+		header,
+		justification: None,
+		post_digests: Vec::new(),
+		body: Some(body),
+		finalized: finalize,
+		auxiliary: Vec::new(),
+		fork_choice: ForkChoiceStrategy::LongestChain,
+		allow_missing_state: false,
+	};
+
+	match block_import.import_block(import_block, HashMap::new())? {
+		ImportResult::Imported(_) => {
+			info!(
+				target: "manual-seal",
+				"Sealed block #{} ({}){}",
+				number,
+				hash,
+				if finalize { ", finalized" } else { "" },
+			);
+
+			Ok(CreatedBlock { hash })
+		},
+		other => Err(Error::BlockImportError(other)),
+	}
+}
+
+/// Finalize the given block, and all of its ancestors.
+fn finalize_block<B, CB, C>(
+	client: &C,
+	hash: B::Hash,
+	justification: Option<Justification>,
+) -> Result<(), Error> where
+	B: BlockT<Hash=H256>,
+	CB: ClientBackend<B, Blake2Hasher>,
+	C: Finalizer<B, Blake2Hasher, CB>,
+{
+	client.finalize_block(BlockId::Hash(hash), justification, true)?;
+	info!(target: "manual-seal", "Finalized block {}", hash);
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::channel::{mpsc, oneshot};
+	use futures::SinkExt;
+	use test_client::{
+		AccountKeyring, DefaultTestClientBuilderExt, TestClientBuilderExt,
+		runtime::{Extrinsic, Transfer},
+	};
+
+	fn extrinsic(nonce: u64) -> Extrinsic {
+		Transfer {
+			amount: Default::default(),
+			nonce,
+			from: AccountKeyring::Alice.into(),
+			to: Default::default(),
+		}.into_signed_tx()
+	}
+
+	#[test]
+	fn verifier_rejects_network_blocks() {
+		let client = test_client::new();
+		let header = client.header(&BlockId::number(0)).unwrap().unwrap();
+		let verify = |origin| <ManualSealVerifier as Verifier<test_client::runtime::Block>>::verify(
+			&mut ManualSealVerifier,
+			origin,
+			header.clone(),
+			None,
+			Some(Vec::new()),
+		);
+
+		assert!(verify(BlockOrigin::NetworkInitialSync).is_err());
+		assert!(verify(BlockOrigin::NetworkBroadcast).is_err());
+		assert!(verify(BlockOrigin::ConsensusBroadcast).is_err());
+		assert!(verify(BlockOrigin::File).is_ok());
+		assert!(verify(BlockOrigin::Own).is_ok());
+	}
+
+	#[test]
+	fn seals_and_finalizes_blocks_on_command() {
+		let (client, select_chain) = test_client::TestClientBuilder::new().build_with_longest_chain();
+		let client = Arc::new(client);
+		let pool = Arc::new(txpool::Pool::new(
+			Default::default(),
+			transaction_pool::FullChainApi::new(client.clone()),
+		));
+		let env = basic_authorship::ProposerFactory {
+			client: client.clone(),
+			transaction_pool: pool.clone(),
+		};
+
+		let (mut commands, stream) = mpsc::channel(16);
+		let engine = run_manual_seal(
+			Box::new(client.clone()),
+			env,
+			client.clone(),
+			pool.clone(),
+			stream,
+			select_chain,
+			InherentDataProviders::new(),
+		);
+
+		let seal = |create_empty, finalize, parent_hash| {
+			let (sender, receiver) = oneshot::channel();
+			let command = EngineCommand::SealNewBlock {
+				create_empty,
+				finalize,
+				parent_hash,
+				sender: Some(sender),
+			};
+			(command, receiver)
+		};
+
+		let driver = async move {
+			// nothing to include yet.
+			let (command, receiver) = seal(false, false, None);
+			commands.send(command).await.unwrap();
+			match receiver.await.unwrap() {
+				Err(Error::EmptyTransactionPool) => (),
+				other => panic!("unexpected result: {:?}", other),
+			}
+
+			pool.submit_one(&BlockId::number(0), extrinsic(0)).await.unwrap();
+			let (command, receiver) = seal(false, false, None);
+			commands.send(command).await.unwrap();
+			let first = receiver.await.unwrap().unwrap();
+			assert_eq!(client.info().best_hash, first.hash);
+			assert_eq!(client.info().finalized_number, 0);
+
+			let (sender, receiver) = oneshot::channel();
+			commands.send(EngineCommand::FinalizeBlock {
+				hash: first.hash,
+				justification: None,
+				sender: Some(sender),
+			}).await.unwrap();
+			receiver.await.unwrap().unwrap();
+			assert_eq!(client.info().finalized_hash, first.hash);
+
+			let (command, receiver) = seal(true, true, Some(first.hash));
+			commands.send(command).await.unwrap();
+			let second = receiver.await.unwrap().unwrap();
+			assert_eq!(client.info().best_number, 2);
+			assert_eq!(client.info().finalized_hash, second.hash);
+
+			// closing the channel stops the engine.
+			drop(commands);
+		};
+
+		futures::executor::block_on(futures::future::join(engine, driver));
+	}
+}
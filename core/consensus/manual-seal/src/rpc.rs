@@ -0,0 +1,220 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC API of the manual seal engine, which turns calls into `EngineCommand`s.
+
+use futures::{
+	channel::{mpsc, oneshot},
+	FutureExt, SinkExt, TryFutureExt,
+};
+use jsonrpc_derive::rpc;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use sr_primitives::Justification;
+
+use crate::error::Error;
+
+pub use self::gen_client::Client as ManualSealClient;
+
+/// Future's type for jsonrpc.
+type FutureResult<T> = Box<dyn jsonrpc_core::futures::Future<Item = T, Error = jsonrpc_core::Error> + Send>;
+
+/// Sender the engine reports the result of a command with. Commands which
+/// are not issued through the RPC, such as the ones of instant seal, have none.
+pub type Sender<T> = Option<oneshot::Sender<Result<T, Error>>>;
+
+/// Commands the manual seal engine accepts.
+pub enum EngineCommand<Hash> {
+	/// Build and import a new block.
+	SealNewBlock {
+		/// Whether to build a block even if there are no ready transactions.
+		create_empty: bool,
+		/// Whether to finalize the block once imported.
+		finalize: bool,
+		/// The block to build on. Defaults to the best block.
+		parent_hash: Option<Hash>,
+		/// Sender to report the result with.
+		sender: Sender<CreatedBlock<Hash>>,
+	},
+	/// Finalize a block, and all of its ancestors.
+	FinalizeBlock {
+		/// The hash of the block.
+		hash: Hash,
+		/// The justification to store with the block.
+		justification: Option<Justification>,
+		/// Sender to report the result with.
+		sender: Sender<()>,
+	},
+}
+
+/// A block created by the manual seal engine.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreatedBlock<Hash> {
+	/// The hash of the block.
+	pub hash: Hash,
+}
+
+/// Manual seal RPC API
+#[rpc]
+pub trait ManualSealApi<Hash> {
+	/// Build a new block on top of the given block, or the best block, and
+	/// import it. The block is finalized on import if `finalize` is set.
+	#[rpc(name = "engine_createBlock")]
+	fn create_block(
+		&self,
+		create_empty: bool,
+		finalize: bool,
+		parent_hash: Option<Hash>,
+	) -> FutureResult<CreatedBlock<Hash>>;
+
+	/// Finalize the given block, and all of its ancestors.
+	#[rpc(name = "engine_finalizeBlock")]
+	fn finalize_block(
+		&self,
+		hash: Hash,
+		justification: Option<Justification>,
+	) -> FutureResult<bool>;
+}
+
+/// Implementation of the manual seal RPC API.
+pub struct ManualSeal<Hash> {
+	commands: mpsc::Sender<EngineCommand<Hash>>,
+}
+
+impl<Hash> ManualSeal<Hash> {
+	/// Create new `ManualSeal` with the sending half of the channel the engine
+	/// reads its commands from.
+	pub fn new(commands: mpsc::Sender<EngineCommand<Hash>>) -> Self {
+		ManualSeal { commands }
+	}
+
+	/// Send a command to the engine and wait for its result.
+	fn send<T, F>(&self, command: F) -> FutureResult<T> where
+		T: Send + 'static,
+		Hash: Send + 'static,
+		F: FnOnce(Sender<T>) -> EngineCommand<Hash>,
+	{
+		let (sender, receiver) = oneshot::channel();
+		let mut commands = self.commands.clone();
+		let command = command(Some(sender));
+
+		let future = async move {
+			commands.send(command).await.map_err(|_| Error::EngineShutdown)?;
+			receiver.await.map_err(|_| Error::EngineShutdown)?
+		};
+
+		Box::new(future.boxed().map_err(jsonrpc_core::Error::from).compat())
+	}
+}
+
+impl<Hash: Send + 'static> ManualSealApi<Hash> for ManualSeal<Hash> {
+	fn create_block(
+		&self,
+		create_empty: bool,
+		finalize: bool,
+		parent_hash: Option<Hash>,
+	) -> FutureResult<CreatedBlock<Hash>> {
+		self.send(|sender| EngineCommand::SealNewBlock {
+			create_empty,
+			finalize,
+			parent_hash,
+			sender,
+		})
+	}
+
+	fn finalize_block(
+		&self,
+		hash: Hash,
+		justification: Option<Justification>,
+	) -> FutureResult<bool> {
+		let future = self.send(|sender| EngineCommand::FinalizeBlock {
+			hash,
+			justification,
+			sender,
+		});
+
+		Box::new(jsonrpc_core::futures::Future::map(future, |()| true))
+	}
+}
+
+/// Report the result of a command to its sender or, without one, to the log.
+pub fn send_result<T: std::fmt::Debug>(sender: Sender<T>, result: Result<T, Error>) {
+	match sender {
+		Some(sender) => if sender.send(result).is_err() {
+			warn!(target: "manual-seal", "Result of a command was dropped, the RPC call was cancelled");
+		},
+		None => match result {
+			Ok(result) => info!(target: "manual-seal", "Command succeeded: {:?}", result),
+			Err(e) => error!(target: "manual-seal", "Command failed: {}", e),
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::StreamExt;
+	use jsonrpc_core::IoHandler;
+	use primitives::H256;
+
+	#[test]
+	fn forwards_calls_to_the_engine() {
+		let (commands, mut engine) = mpsc::channel(16);
+		let mut io = IoHandler::new();
+		io.extend_with(ManualSealApi::to_delegate(ManualSeal::<H256>::new(commands)));
+
+		let block_hash = H256::repeat_byte(1);
+		let engine = std::thread::spawn(move || futures::executor::block_on(async move {
+			match engine.next().await {
+				Some(EngineCommand::SealNewBlock { create_empty, finalize, parent_hash, sender }) => {
+					assert!(create_empty);
+					assert!(!finalize);
+					assert_eq!(parent_hash, None);
+					send_result(sender, Ok(CreatedBlock { hash: block_hash }));
+				},
+				_ => panic!("expected a seal command"),
+			}
+			match engine.next().await {
+				Some(EngineCommand::FinalizeBlock { hash, justification, sender }) => {
+					assert_eq!(hash, block_hash);
+					assert_eq!(justification, Some(vec![42]));
+					send_result(sender, Err(Error::BlockNotFound(format!("{:?}", hash))));
+				},
+				_ => panic!("expected a finalize command"),
+			}
+		}));
+
+		let request = r#"{"jsonrpc":"2.0","method":"engine_createBlock","params":[true, false, null],"id":1}"#;
+		let response: serde_json::Value = serde_json::from_str(
+			&io.handle_request_sync(request).unwrap()
+		).unwrap();
+		assert_eq!(
+			response["result"]["hash"],
+			serde_json::to_value(block_hash).unwrap(),
+		);
+
+		let request = format!(
+			r#"{{"jsonrpc":"2.0","method":"engine_finalizeBlock","params":[{}, [42]],"id":2}}"#,
+			serde_json::to_string(&block_hash).unwrap(),
+		);
+		let response: serde_json::Value = serde_json::from_str(
+			&io.handle_request_sync(&request).unwrap()
+		).unwrap();
+		assert_eq!(response["error"]["code"], 8002);
+
+		engine.join().unwrap();
+	}
+}
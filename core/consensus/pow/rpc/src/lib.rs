@@ -0,0 +1,159 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC API for external PoW miners.
+//!
+//! Miners fetch the work the node is currently mining, seal it and submit the
+//! seal back to the node, which imports the sealed block.
+
+#![warn(missing_docs)]
+
+use std::sync::Arc;
+
+use jsonrpc_core::Result;
+use jsonrpc_derive::rpc;
+use pow::{MiningWork, MiningWorkerApi};
+use primitives::{Bytes, H256};
+use serde::{Serialize, Deserialize};
+
+pub use self::gen_client::Client as PowClient;
+
+/// Work to be sealed by an external miner.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Work<Difficulty> {
+	/// The hash of the block the work builds on.
+	pub parent_hash: H256,
+	/// The hash of the header to seal.
+	pub pre_hash: H256,
+	/// The difficulty the seal has to satisfy.
+	pub difficulty: Difficulty,
+}
+
+impl<Difficulty> From<MiningWork<Difficulty>> for Work<Difficulty> {
+	fn from(work: MiningWork<Difficulty>) -> Self {
+		Work {
+			parent_hash: work.parent_hash,
+			pre_hash: work.pre_hash,
+			difficulty: work.difficulty,
+		}
+	}
+}
+
+/// PoW mining RPC methods.
+#[rpc]
+pub trait PowApi<Difficulty> {
+	/// Returns the work the node is currently mining, if any.
+	#[rpc(name = "pow_getWork")]
+	fn work(&self) -> Result<Option<Work<Difficulty>>>;
+
+	/// Submits a seal for the work with the given pre-hash. Returns whether the
+	/// seal was valid and the sealed block was imported.
+	#[rpc(name = "pow_submitSeal")]
+	fn submit_seal(&self, pre_hash: H256, seal: Bytes) -> Result<bool>;
+
+	/// Returns the hash rate of the local CPU miner, in hashes per second.
+	#[rpc(name = "pow_hashRate")]
+	fn hash_rate(&self) -> Result<u64>;
+}
+
+/// Implementation of the PoW mining RPC API.
+pub struct Pow<W> {
+	worker: Arc<W>,
+}
+
+impl<W> Pow<W> {
+	/// Create new `Pow` with the handle returned by `start_mine`.
+	pub fn new(worker: Arc<W>) -> Self {
+		Pow { worker }
+	}
+}
+
+impl<W> PowApi<W::Difficulty> for Pow<W> where
+	W: MiningWorkerApi + 'static,
+	W::Difficulty: Serialize + Send + Sync + 'static,
+{
+	fn work(&self) -> Result<Option<Work<W::Difficulty>>> {
+		Ok(self.worker.work().map(Into::into))
+	}
+
+	fn submit_seal(&self, pre_hash: H256, seal: Bytes) -> Result<bool> {
+		Ok(self.worker.submit(pre_hash, seal.0))
+	}
+
+	fn hash_rate(&self) -> Result<u64> {
+		Ok(self.worker.hash_rate())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::Mutex;
+	use jsonrpc_core::IoHandler;
+	use pow_primitives::Seal;
+
+	#[derive(Default)]
+	struct TestWorker {
+		submitted: Mutex<Vec<(H256, Seal)>>,
+	}
+
+	impl MiningWorkerApi for TestWorker {
+		type Difficulty = u64;
+
+		fn work(&self) -> Option<MiningWork<u64>> {
+			Some(MiningWork {
+				parent_hash: H256::repeat_byte(1),
+				pre_hash: H256::repeat_byte(2),
+				difficulty: 100,
+			})
+		}
+
+		fn submit(&self, pre_hash: H256, seal: Seal) -> bool {
+			self.submitted.lock().unwrap().push((pre_hash, seal));
+			pre_hash == H256::repeat_byte(2)
+		}
+
+		fn hash_rate(&self) -> u64 {
+			42
+		}
+	}
+
+	#[test]
+	fn serves_work_and_accepts_seals() {
+		let worker = Arc::new(TestWorker::default());
+		let mut io = IoHandler::new();
+		io.extend_with(PowApi::to_delegate(Pow::new(worker.clone())));
+
+		let request = r#"{"jsonrpc":"2.0","method":"pow_getWork","params":[],"id":1}"#;
+		let response = io.handle_request_sync(request).unwrap();
+		let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+		assert_eq!(response["result"]["difficulty"], 100);
+		assert_eq!(
+			response["result"]["preHash"],
+			"0x0202020202020202020202020202020202020202020202020202020202020202",
+		);
+
+		let request = r#"{"jsonrpc":"2.0","method":"pow_submitSeal","params":["0x0202020202020202020202020202020202020202020202020202020202020202","0x0102"],"id":2}"#;
+		let response = r#"{"jsonrpc":"2.0","result":true,"id":2}"#;
+		assert_eq!(io.handle_request_sync(request), Some(response.into()));
+		assert_eq!(*worker.submitted.lock().unwrap(), vec![(H256::repeat_byte(2), vec![1, 2])]);
+
+		let request = r#"{"jsonrpc":"2.0","method":"pow_hashRate","params":[],"id":3}"#;
+		let response = r#"{"jsonrpc":"2.0","result":42,"id":3}"#;
+		assert_eq!(io.handle_request_sync(request), Some(response.into()));
+	}
+}
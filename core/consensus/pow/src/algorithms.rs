@@ -0,0 +1,189 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reference hash-based proof of work algorithms.
+//!
+//! A seal is valid if the hash of the pre-hash, the difficulty and a nonce, read as a
+//! number, multiplied by the difficulty doesn't overflow 256 bits. These algorithms are
+//! meant for testing and as a starting point: they are not memory-hard, so they are
+//! easily mined on specialized hardware.
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+use client::{backend::AuxStore, blockchain::HeaderBackend};
+use codec::{Encode, Decode};
+use pow_primitives::{Seal, TimestampApi};
+use primitives::{H256, U256};
+use rand::{thread_rng, RngCore};
+use sha3::{Sha3_256, Digest};
+use sr_primitives::generic::BlockId;
+use sr_primitives::traits::{Block as BlockT, ProvideRuntimeApi};
+
+use crate::{Error, PowAlgorithm};
+use crate::difficulty::{Retarget, block_timings};
+
+/// A hash function to seal blocks with.
+pub trait SealHasher {
+	/// Hash the given data.
+	fn hash(data: &[u8]) -> H256;
+}
+
+/// The SHA3-256 hash function.
+pub struct Sha3;
+
+impl SealHasher for Sha3 {
+	fn hash(data: &[u8]) -> H256 {
+		H256::from_slice(Sha3_256::digest(data).as_slice())
+	}
+}
+
+/// The Blake2-256 hash function.
+pub struct Blake2;
+
+impl SealHasher for Blake2 {
+	fn hash(data: &[u8]) -> H256 {
+		primitives::blake2_256(data).into()
+	}
+}
+
+/// The seal of a hash-based proof of work.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
+pub struct HashSeal {
+	/// The difficulty the block was mined at.
+	pub difficulty: U256,
+	/// The hash of the pre-hash, the difficulty and the nonce.
+	pub work: H256,
+	/// The nonce.
+	pub nonce: H256,
+}
+
+/// Check whether the given hash satisfies the difficulty.
+pub fn hash_meets_difficulty(hash: &H256, difficulty: U256) -> bool {
+	let num_hash = U256::from(&hash[..]);
+	let (_, overflowed) = num_hash.overflowing_mul(difficulty);
+
+	!overflowed
+}
+
+/// A hash-based proof of work with the hash function `H`, retargeting the difficulty with `R`.
+///
+/// Block timestamps are read with the `TimestampApi` runtime API, which must return the
+/// timestamp in the same unit as the target block time of the retargeting algorithm.
+pub struct HashPow<H, C, R> {
+	client: Arc<C>,
+	retarget: R,
+	_hasher: PhantomData<H>,
+}
+
+impl<H, C, R> HashPow<H, C, R> {
+	/// Create a new proof of work algorithm.
+	pub fn new(client: Arc<C>, retarget: R) -> Self {
+		HashPow { client, retarget, _hasher: PhantomData }
+	}
+}
+
+impl<H, C, R: Clone> Clone for HashPow<H, C, R> {
+	fn clone(&self) -> Self {
+		HashPow::new(self.client.clone(), self.retarget.clone())
+	}
+}
+
+/// The SHA3-256 proof of work.
+pub type Sha3Pow<C, R> = HashPow<Sha3, C, R>;
+
+/// The Blake2-256 proof of work.
+pub type Blake2Pow<C, R> = HashPow<Blake2, C, R>;
+
+impl<B, H, C, R> PowAlgorithm<B> for HashPow<H, C, R> where
+	B: BlockT<Hash=H256>,
+	H: SealHasher,
+	C: HeaderBackend<B> + AuxStore + ProvideRuntimeApi,
+	C::Api: TimestampApi<B, u64>,
+	R: Retarget<U256>,
+{
+	type Difficulty = U256;
+
+	fn difficulty(&self, parent: &BlockId<B>) -> Result<U256, Error<B>> {
+		let timings = block_timings(self.client.as_ref(), parent, self.retarget.window())?;
+		Ok(self.retarget.next_difficulty(&timings))
+	}
+
+	fn timestamp(&self, block: &BlockId<B>) -> Result<u64, Error<B>> {
+		self.client.runtime_api().timestamp(block)
+			.map_err(|e| Error::Timestamp(format!("{:?}", e)))
+	}
+
+	fn verify(
+		&self,
+		_parent: &BlockId<B>,
+		pre_hash: &H256,
+		seal: &Seal,
+		difficulty: U256,
+	) -> Result<bool, Error<B>> {
+		let seal = match HashSeal::decode(&mut &seal[..]) {
+			Ok(seal) => seal,
+			Err(_) => return Ok(false),
+		};
+
+		if seal.difficulty != difficulty || !hash_meets_difficulty(&seal.work, difficulty) {
+			return Ok(false);
+		}
+
+		Ok(seal.work == H::hash(&(pre_hash, difficulty, seal.nonce).encode()))
+	}
+
+	fn mine(
+		&self,
+		_parent: &BlockId<B>,
+		pre_hash: &H256,
+		difficulty: U256,
+		round: u32,
+	) -> Result<Option<Seal>, Error<B>> {
+		let mut rng = thread_rng();
+
+		for _ in 0..round {
+			let mut nonce = H256::default();
+			rng.fill_bytes(nonce.as_bytes_mut());
+
+			let work = H::hash(&(pre_hash, difficulty, nonce).encode());
+			if hash_meets_difficulty(&work, difficulty) {
+				return Ok(Some(HashSeal { difficulty, work, nonce }.encode()));
+			}
+		}
+
+		Ok(None)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn difficulty_is_checked_against_the_hash() {
+		assert!(hash_meets_difficulty(&H256::repeat_byte(0xff), 1.into()));
+		assert!(!hash_meets_difficulty(&H256::repeat_byte(0xff), 2.into()));
+
+		let mut hash = H256::zero();
+		hash.as_bytes_mut()[0] = 0x7f;
+		assert!(hash_meets_difficulty(&hash, 2.into()));
+	}
+
+	#[test]
+	fn hashers_differ() {
+		assert_ne!(Sha3::hash(b"substrate"), Blake2::hash(b"substrate"));
+	}
+}
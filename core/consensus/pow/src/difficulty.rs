@@ -0,0 +1,232 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Difficulty adjustment algorithms.
+//!
+//! The algorithms retarget the difficulty from the solve times and difficulties of
+//! a window of past blocks, which are read from the `PowAux` stored for each block.
+//! Since the aux of a block records the timestamp of its parent, the solve time of
+//! the latest block is only known once a child is imported, so the window always
+//! ends with the parent of the latest block.
+
+use client::{backend::AuxStore, blockchain::HeaderBackend};
+use primitives::{H256, U256};
+use sr_primitives::generic::BlockId;
+use sr_primitives::traits::{Block as BlockT, Header as HeaderT, One};
+
+use crate::{Error, PowAux};
+
+/// Solve time and difficulty of a past block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockTiming<Difficulty> {
+	/// Time between the block and its parent, in the unit of the runtime timestamps.
+	pub solve_time: u64,
+	/// Difficulty the block was mined at.
+	pub difficulty: Difficulty,
+}
+
+/// A difficulty retargeting algorithm.
+pub trait Retarget<Difficulty> {
+	/// Number of past blocks the algorithm looks at.
+	fn window(&self) -> usize;
+
+	/// Difficulty of the next block, given the timings of up to `window` past blocks,
+	/// oldest first. Chains shorter than the window yield fewer timings.
+	fn next_difficulty(&self, timings: &[BlockTiming<Difficulty>]) -> Difficulty;
+}
+
+/// Parameters shared by the retargeting algorithms.
+#[derive(Clone, Debug)]
+pub struct RetargetParams {
+	/// The block time to aim for, in the unit of the runtime timestamps.
+	pub target_block_time: u64,
+	/// Number of past blocks to average over.
+	pub window: usize,
+	/// Difficulty used until enough blocks have been mined.
+	pub initial_difficulty: U256,
+	/// The difficulty never drops below this value.
+	pub min_difficulty: U256,
+}
+
+impl RetargetParams {
+	/// Solve times are clamped to `[1, MAX_SOLVE_TIME_FACTOR * target_block_time]`, which
+	/// limits the effect of timestamp manipulation and of long gaps in mining.
+	const MAX_SOLVE_TIME_FACTOR: u64 = 6;
+
+	fn clamp_solve_time(&self, solve_time: u64) -> u64 {
+		solve_time.max(1).min(self.target_block_time.saturating_mul(Self::MAX_SOLVE_TIME_FACTOR))
+	}
+
+	fn bounded(&self, difficulty: U256) -> U256 {
+		difficulty.max(self.min_difficulty)
+	}
+}
+
+/// Simple moving average: the average difficulty of the window, scaled by the ratio of
+/// the target block time to the average solve time.
+#[derive(Clone, Debug)]
+pub struct MovingAverage(pub RetargetParams);
+
+impl Retarget<U256> for MovingAverage {
+	fn window(&self) -> usize {
+		self.0.window
+	}
+
+	fn next_difficulty(&self, timings: &[BlockTiming<U256>]) -> U256 {
+		if timings.is_empty() {
+			return self.0.initial_difficulty;
+		}
+
+		let (total_difficulty, total_time) = timings.iter().fold(
+			(U256::zero(), 0u64),
+			|(difficulty, time), timing| (
+				difficulty.saturating_add(timing.difficulty),
+				time.saturating_add(self.0.clamp_solve_time(timing.solve_time)),
+			),
+		);
+
+		// sum(D) / n * T / (sum(t) / n)
+		let next = total_difficulty.saturating_mul(U256::from(self.0.target_block_time)) /
+			U256::from(total_time);
+		self.0.bounded(next)
+	}
+}
+
+/// Linearly weighted moving average, which weights recent solve times more heavily
+/// so that it reacts faster to changes in hash rate than a simple moving average.
+#[derive(Clone, Debug)]
+pub struct Lwma(pub RetargetParams);
+
+impl Retarget<U256> for Lwma {
+	fn window(&self) -> usize {
+		self.0.window
+	}
+
+	fn next_difficulty(&self, timings: &[BlockTiming<U256>]) -> U256 {
+		if timings.is_empty() {
+			return self.0.initial_difficulty;
+		}
+
+		let n = timings.len() as u64;
+		let target = self.0.target_block_time;
+
+		let (total_difficulty, weighted_time) = timings.iter().zip(1u64..).fold(
+			(U256::zero(), 0u64),
+			|(difficulty, time), (timing, weight)| (
+				difficulty.saturating_add(timing.difficulty),
+				time.saturating_add(weight.saturating_mul(self.0.clamp_solve_time(timing.solve_time))),
+			),
+		);
+
+		// a floor on the weighted solve times keeps the difficulty from jumping on
+		// a burst of fast blocks.
+		let min_weighted_time = n.saturating_mul(n + 1) / 2 * target / 10;
+		let weighted_time = weighted_time.max(min_weighted_time).max(1);
+
+		// sum(D) / n * T * (n * (n + 1) / 2) / sum(i * t_i)
+		let next = total_difficulty
+			.saturating_mul(U256::from(target))
+			.saturating_mul(U256::from(n + 1)) /
+			U256::from(weighted_time.saturating_mul(2));
+		self.0.bounded(next)
+	}
+}
+
+/// Collect the timings of up to `window` ancestors of the given block, oldest first,
+/// from the `PowAux` stored for each block.
+///
+/// The first block of the chain is skipped, since the time since genesis isn't a
+/// meaningful solve time.
+pub fn block_timings<B, C, Difficulty>(
+	client: &C,
+	parent: &BlockId<B>,
+	window: usize,
+) -> Result<Vec<BlockTiming<Difficulty>>, Error<B>> where
+	B: BlockT<Hash=H256>,
+	C: HeaderBackend<B> + AuxStore,
+	Difficulty: codec::Decode + Default,
+{
+	let header = |id: BlockId<B>| client.header(id.clone())
+		.map_err(Error::Client)?
+		.ok_or(Error::HeaderNotFound(id));
+
+	let mut current = header(parent.clone())?;
+	let mut next_timestamp = PowAux::<Difficulty>::read::<_, B>(client, &current.hash())?.parent_timestamp;
+	let mut timings = Vec::with_capacity(window);
+
+	while timings.len() < window && *current.number() > One::one() {
+		current = header(BlockId::Hash(*current.parent_hash()))?;
+		if *current.number() == One::one() {
+			break;
+		}
+
+		let aux = PowAux::<Difficulty>::read::<_, B>(client, &current.hash())?;
+
+		timings.push(BlockTiming {
+			solve_time: next_timestamp.saturating_sub(aux.parent_timestamp),
+			difficulty: aux.difficulty,
+		});
+		next_timestamp = aux.parent_timestamp;
+	}
+
+	timings.reverse();
+	Ok(timings)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn params() -> RetargetParams {
+		RetargetParams {
+			target_block_time: 10,
+			window: 4,
+			initial_difficulty: 1000.into(),
+			min_difficulty: 10.into(),
+		}
+	}
+
+	fn timings(solve_times: &[u64]) -> Vec<BlockTiming<U256>> {
+		solve_times.iter()
+			.map(|solve_time| BlockTiming { solve_time: *solve_time, difficulty: 1000.into() })
+			.collect()
+	}
+
+	#[test]
+	fn retargeting_keeps_difficulty_on_target() {
+		for algorithm in &[&MovingAverage(params()) as &dyn Retarget<U256>, &Lwma(params())] {
+			assert_eq!(algorithm.next_difficulty(&[]), 1000.into());
+			assert_eq!(algorithm.next_difficulty(&timings(&[10, 10, 10, 10])), 1000.into());
+
+			// blocks twice as fast double the difficulty, twice as slow halve it.
+			assert_eq!(algorithm.next_difficulty(&timings(&[5, 5, 5, 5])), 2000.into());
+			assert_eq!(algorithm.next_difficulty(&timings(&[20, 20, 20, 20])), 500.into());
+
+			// the difficulty doesn't drop below the minimum.
+			let slow = BlockTiming { solve_time: 60, difficulty: 10.into() };
+			assert_eq!(algorithm.next_difficulty(&[slow; 4]), 10.into());
+		}
+	}
+
+	#[test]
+	fn lwma_weights_recent_blocks() {
+		let sma = MovingAverage(params()).next_difficulty(&timings(&[20, 20, 5, 5]));
+		let lwma = Lwma(params()).next_difficulty(&timings(&[20, 20, 5, 5]));
+
+		// the recent blocks are fast, so the weighted average retargets higher.
+		assert!(lwma > sma);
+	}
+}
@@ -17,26 +17,45 @@
 //! Proof of work consensus for Substrate.
 //!
 //! To use this engine, you can need to have a struct that implements
-//! `PowAlgorithm`. After that, pass an instance of the struct, along
-//! with other necessary client references to `import_queue` to setup
-//! the queue. Use the `start_mine` function for basic CPU mining.
+//! `PowAlgorithm`. After that, wrap the block import in a `PowBlockImport`,
+//! and pass it along with an instance of the struct and other necessary
+//! client references to `import_queue` to setup the queue. Use the
+//! `start_mine` function for CPU mining, and the handle it returns to let
+//! external miners fetch work and submit seals.
 //!
-//! The auxiliary storage for PoW engine only stores the total difficulty.
-//! For other storage requirements for particular PoW algorithm (such as
-//! the actual difficulty for each particular blocks), you can take a client
+//! `PowBlockImport` computes the total difficulty of imported blocks and
+//! picks the best chain by it, then hands the block to the inner block
+//! import. The inner block import can be the client itself, or another
+//! block import wrapping it, such as the GRANDPA block import for chains
+//! where blocks are finalized by a GRANDPA committee. Chains which don't
+//! contain the last finalized block are never picked as the best chain.
+//!
+//! The `algorithms` module provides reference Sha3 and Blake2 algorithms,
+//! and the `difficulty` module reusable difficulty adjustment algorithms.
+//!
+//! The auxiliary storage for PoW engine stores the difficulty, the total
+//! difficulty and the timestamp of the parent of each block, which is
+//! enough for timestamp-based difficulty adjustment. For other storage
+//! requirements for particular PoW algorithm, you can take a client
 //! reference in your `PowAlgorithm` implementation, and use a separate prefix
 //! for the auxiliary storage. It is also possible to just use the runtime
 //! as the storage, but it is not recommended as it won't work well with light
 //! clients.
 
+pub mod algorithms;
+pub mod difficulty;
+mod worker;
+
 use std::sync::Arc;
 use std::thread;
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use client::{
 	BlockOf, blockchain::{HeaderBackend, ProvideCache},
 	block_builder::api::BlockBuilder as BlockBuilderApi, backend::AuxStore,
 	well_known_cache_keys::Id as CacheKeyId,
 };
+use header_metadata::HeaderMetadata;
 use sr_primitives::{Justification, RuntimeString};
 use sr_primitives::generic::{BlockId, Digest, DigestItem};
 use sr_primitives::traits::{Block as BlockT, Header as HeaderT, ProvideRuntimeApi};
@@ -45,12 +64,19 @@ use pow_primitives::{Seal, TotalDifficulty, POW_ENGINE_ID};
 use primitives::H256;
 use inherents::{InherentDataProviders, InherentData};
 use consensus_common::{
-	BlockImportParams, BlockOrigin, ForkChoiceStrategy, SyncOracle, Environment, Proposer,
-	SelectChain, Error as ConsensusError
+	BlockImport, BlockImportParams, BlockCheckParams, BlockOrigin, ForkChoiceStrategy,
+	ImportResult, SyncOracle, Environment, Proposer, SelectChain, Error as ConsensusError
+};
+use consensus_common::import_queue::{
+	BoxBlockImport, BoxJustificationImport, BasicQueue, Verifier,
 };
-use consensus_common::import_queue::{BoxBlockImport, BasicQueue, Verifier};
 use codec::{Encode, Decode};
 use log::*;
+use parking_lot::Mutex;
+use prometheus::Registry;
+
+pub use worker::{MiningWork, MiningWorker, MiningWorkerApi, MiningHandle};
+use worker::MiningBuild;
 
 #[derive(derive_more::Display, Debug)]
 pub enum Error<B: BlockT> {
@@ -68,6 +94,10 @@ pub enum Error<B: BlockT> {
 	BestHeader(client::error::Error),
 	#[display(fmt = "Best header does not exist")]
 	NoBestHeader,
+	#[display(fmt = "Header of block {:?} not found", _0)]
+	HeaderNotFound(BlockId<B>),
+	#[display(fmt = "Fetching block timestamp failed: {}", _0)]
+	Timestamp(String),
 	#[display(fmt = "Block proposing error: {:?}", _0)]
 	BlockProposingError(String),
 	#[display(fmt = "Fetch best hash failed via select chain: {:?}", _0)]
@@ -90,36 +120,70 @@ impl<B: BlockT> std::convert::From<Error<B>> for String {
 	}
 }
 
+impl<B: BlockT> std::convert::From<Error<B>> for ConsensusError {
+	fn from(error: Error<B>) -> ConsensusError {
+		ConsensusError::ClientImport(error.to_string())
+	}
+}
+
 /// Auxiliary storage prefix for PoW engine.
 pub const POW_AUX_PREFIX: [u8; 4] = *b"PoW:";
 
+/// Auxiliary storage prefix for the parent timestamps recorded by the PoW engine.
+pub const POW_TIMESTAMP_AUX_PREFIX: [u8; 7] = *b"PoW:ts:";
+
 /// Get the auxiliary storage key used by engine to store total difficulty.
-fn aux_key(hash: &H256) -> Vec<u8> {
+pub(crate) fn aux_key(hash: &H256) -> Vec<u8> {
 	POW_AUX_PREFIX.iter().chain(&hash[..])
 		.cloned().collect::<Vec<_>>()
 }
 
+/// Get the auxiliary storage key used by engine to store the parent timestamp.
+pub(crate) fn timestamp_aux_key(hash: &H256) -> Vec<u8> {
+	POW_TIMESTAMP_AUX_PREFIX.iter().chain(&hash[..])
+		.cloned().collect::<Vec<_>>()
+}
+
 /// Auxiliary storage data for PoW.
+///
+/// The parent timestamp is stored under its own key, so that the difficulties
+/// stored by earlier versions of the engine can still be read. Blocks imported
+/// by those versions have a parent timestamp of zero.
 #[derive(Encode, Decode, Clone, Debug, Default)]
 pub struct PowAux<Difficulty> {
 	/// Difficulty of the current block.
 	pub difficulty: Difficulty,
 	/// Total difficulty up to current block.
 	pub total_difficulty: Difficulty,
+	/// Timestamp of the parent block, as returned by `PowAlgorithm::timestamp`.
+	#[codec(skip)]
+	pub parent_timestamp: u64,
 }
 
 impl<Difficulty> PowAux<Difficulty> where
-	Difficulty: Decode + Default,
+	Difficulty: Encode + Decode + Default,
 {
 	/// Read the auxiliary from client.
 	pub fn read<C: AuxStore, B: BlockT>(client: &C, hash: &H256) -> Result<Self, Error<B>> {
-		let key = aux_key(hash);
-
-		match client.get_aux(&key).map_err(Error::Client)? {
+		let mut aux = match client.get_aux(&aux_key(hash)).map_err(Error::Client)? {
 			Some(bytes) => Self::decode(&mut &bytes[..])
-				.map_err(Error::Codec),
-			None => Ok(Self::default()),
+				.map_err(Error::Codec)?,
+			None => Self::default(),
+		};
+
+		if let Some(bytes) = client.get_aux(&timestamp_aux_key(hash)).map_err(Error::Client)? {
+			aux.parent_timestamp = u64::decode(&mut &bytes[..]).map_err(Error::Codec)?;
 		}
+
+		Ok(aux)
+	}
+
+	/// The auxiliary storage entries of the given block, to be written on import.
+	pub fn to_aux(&self, hash: &H256) -> Vec<(Vec<u8>, Option<Vec<u8>>)> {
+		vec![
+			(aux_key(hash), Some(self.encode())),
+			(timestamp_aux_key(hash), Some(self.parent_timestamp.encode())),
+		]
 	}
 }
 
@@ -130,6 +194,12 @@ pub trait PowAlgorithm<B: BlockT> {
 
 	/// Get the next block's difficulty.
 	fn difficulty(&self, parent: &BlockId<B>) -> Result<Self::Difficulty, Error<B>>;
+	/// Get the timestamp of the given block, which is recorded in the `PowAux` of its
+	/// children for timestamp-based difficulty adjustment. Algorithms that don't use
+	/// timestamps can keep the default.
+	fn timestamp(&self, _block: &BlockId<B>) -> Result<u64, Error<B>> {
+		Ok(0)
+	}
 	/// Verify proof of work against the given difficulty.
 	fn verify(
 		&self,
@@ -148,31 +218,135 @@ pub trait PowAlgorithm<B: BlockT> {
 	) -> Result<Option<Seal>, Error<B>>;
 }
 
+/// A block importer for PoW.
+///
+/// It computes the difficulty and total difficulty of imported blocks, stores
+/// them in the auxiliary storage, and picks the best chain by total difficulty,
+/// never switching to a chain which doesn't contain the last finalized block.
+/// The block is then imported by the inner block import.
+pub struct PowBlockImport<B: BlockT<Hash=H256>, I, C, S, Algorithm> {
+	inner: I,
+	client: Arc<C>,
+	algorithm: Algorithm,
+	select_chain: Option<S>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<B: BlockT<Hash=H256>, I: Clone, C, S: Clone, Algorithm: Clone> Clone
+	for PowBlockImport<B, I, C, S, Algorithm>
+{
+	fn clone(&self) -> Self {
+		PowBlockImport {
+			inner: self.inner.clone(),
+			client: self.client.clone(),
+			algorithm: self.algorithm.clone(),
+			select_chain: self.select_chain.clone(),
+			_marker: Default::default(),
+		}
+	}
+}
+
+impl<B, I, C, S, Algorithm> PowBlockImport<B, I, C, S, Algorithm> where
+	B: BlockT<Hash=H256>,
+	C: HeaderBackend<B> + AuxStore + HeaderMetadata<B, Error=client::error::Error>,
+	S: SelectChain<B>,
+	Algorithm: PowAlgorithm<B>,
+{
+	/// Create a new block import wrapping `inner`, which can be the client or
+	/// another block import wrapping it.
+	pub fn new(
+		inner: I,
+		client: Arc<C>,
+		algorithm: Algorithm,
+		select_chain: Option<S>,
+	) -> Self {
+		Self { inner, client, algorithm, select_chain, _marker: Default::default() }
+	}
+
+	/// Whether the given block is the last finalized block or one of its descendants.
+	fn descends_from_finalized(&self, hash: H256) -> Result<bool, Error<B>> {
+		let finalized_hash = self.client.info().finalized_hash;
+		let ancestor = header_metadata::lowest_common_ancestor(
+			self.client.as_ref(),
+			hash,
+			finalized_hash,
+		).map_err(Error::Client)?;
+
+		Ok(ancestor.hash == finalized_hash)
+	}
+}
+
+impl<B, I, C, S, Algorithm> BlockImport<B> for PowBlockImport<B, I, C, S, Algorithm> where
+	B: BlockT<Hash=H256>,
+	I: BlockImport<B> + Send + Sync,
+	I::Error: Into<ConsensusError>,
+	C: HeaderBackend<B> + AuxStore + HeaderMetadata<B, Error=client::error::Error>,
+	S: SelectChain<B>,
+	Algorithm: PowAlgorithm<B>,
+{
+	type Error = ConsensusError;
+
+	fn check_block(
+		&mut self,
+		block: BlockCheckParams<B>,
+	) -> Result<ImportResult, Self::Error> {
+		self.inner.check_block(block).map_err(Into::into)
+	}
+
+	fn import_block(
+		&mut self,
+		mut block: BlockImportParams<B>,
+		new_cache: HashMap<CacheKeyId, Vec<u8>>,
+	) -> Result<ImportResult, Self::Error> {
+		let hash = block.post_header().hash();
+		let parent_hash = *block.header.parent_hash();
+		let parent_id = BlockId::Hash(parent_hash);
+
+		let best_hash = best_hash(self.client.as_ref(), self.select_chain.as_ref())?;
+		let best_aux = PowAux::read::<_, B>(self.client.as_ref(), &best_hash)?;
+		let mut aux = PowAux::read::<_, B>(self.client.as_ref(), &parent_hash)?;
+
+		let difficulty = self.algorithm.difficulty(&parent_id)?;
+		aux.difficulty = difficulty;
+		aux.total_difficulty.increment(difficulty);
+		aux.parent_timestamp = self.algorithm.timestamp(&parent_id)?;
+
+		block.auxiliary.extend(aux.to_aux(&hash));
+		block.fork_choice = ForkChoiceStrategy::Custom(
+			aux.total_difficulty > best_aux.total_difficulty &&
+				self.descends_from_finalized(parent_hash)?
+		);
+
+		self.inner.import_block(block, new_cache).map_err(Into::into)
+	}
+}
+
 /// A verifier for PoW blocks.
-pub struct PowVerifier<B: BlockT<Hash=H256>, C, S, Algorithm> {
+///
+/// It checks the seal and the inherents of blocks, leaving the fork choice
+/// to the `PowBlockImport` the import queue imports blocks with.
+pub struct PowVerifier<B: BlockT<Hash=H256>, C, Algorithm> {
 	client: Arc<C>,
 	algorithm: Algorithm,
 	inherent_data_providers: inherents::InherentDataProviders,
-	select_chain: Option<S>,
 	check_inherents_after: <<B as BlockT>::Header as HeaderT>::Number,
 }
 
-impl<B: BlockT<Hash=H256>, C, S, Algorithm> PowVerifier<B, C, S, Algorithm> {
+impl<B: BlockT<Hash=H256>, C, Algorithm> PowVerifier<B, C, Algorithm> {
 	pub fn new(
 		client: Arc<C>,
 		algorithm: Algorithm,
 		check_inherents_after: <<B as BlockT>::Header as HeaderT>::Number,
-		select_chain: Option<S>,
 		inherent_data_providers: inherents::InherentDataProviders,
 	) -> Self {
-		Self { client, algorithm, inherent_data_providers, select_chain, check_inherents_after }
+		Self { client, algorithm, inherent_data_providers, check_inherents_after }
 	}
 
 	fn check_header(
 		&self,
 		mut header: B::Header,
 		parent_block_id: BlockId<B>,
-	) -> Result<(B::Header, Algorithm::Difficulty, DigestItem<H256>), Error<B>> where
+	) -> Result<(B::Header, DigestItem<H256>), Error<B>> where
 		Algorithm: PowAlgorithm<B>,
 	{
 		let hash = header.hash();
@@ -200,7 +374,7 @@ impl<B: BlockT<Hash=H256>, C, S, Algorithm> PowVerifier<B, C, S, Algorithm> {
 			return Err(Error::InvalidSeal);
 		}
 
-		Ok((header, difficulty, seal))
+		Ok((header, seal))
 	}
 
 	fn check_inherents(
@@ -246,10 +420,9 @@ impl<B: BlockT<Hash=H256>, C, S, Algorithm> PowVerifier<B, C, S, Algorithm> {
 	}
 }
 
-impl<B: BlockT<Hash=H256>, C, S, Algorithm> Verifier<B> for PowVerifier<B, C, S, Algorithm> where
+impl<B: BlockT<Hash=H256>, C, Algorithm> Verifier<B> for PowVerifier<B, C, Algorithm> where
 	C: ProvideRuntimeApi + Send + Sync + HeaderBackend<B> + AuxStore + ProvideCache<B> + BlockOf,
 	C::Api: BlockBuilderApi<B>,
-	S: SelectChain<B>,
 	Algorithm: PowAlgorithm<B> + Send + Sync,
 {
 	fn verify(
@@ -263,23 +436,12 @@ impl<B: BlockT<Hash=H256>, C, S, Algorithm> Verifier<B> for PowVerifier<B, C, S,
 			.create_inherent_data().map_err(String::from)?;
 		let timestamp_now = inherent_data.timestamp_inherent_data().map_err(String::from)?;
 
-		let best_hash = match self.select_chain.as_ref() {
-			Some(select_chain) => select_chain.best_chain()
-				.map_err(|e| format!("Fetch best chain failed via select chain: {:?}", e))?
-				.hash(),
-			None => self.client.info().best_hash,
-		};
-		let hash = header.hash();
 		let parent_hash = *header.parent_hash();
-		let best_aux = PowAux::read::<_, B>(self.client.as_ref(), &best_hash)?;
-		let mut aux = PowAux::read::<_, B>(self.client.as_ref(), &parent_hash)?;
 
-		let (checked_header, difficulty, seal) = self.check_header(
+		let (checked_header, seal) = self.check_header(
 			header,
 			BlockId::Hash(parent_hash),
 		)?;
-		aux.difficulty = difficulty;
-		aux.total_difficulty.increment(difficulty);
 
 		if let Some(inner_body) = body.take() {
 			let block = B::new(checked_header.clone(), inner_body);
@@ -294,7 +456,6 @@ impl<B: BlockT<Hash=H256>, C, S, Algorithm> Verifier<B> for PowVerifier<B, C, S,
 			let (_, inner_body) = block.deconstruct();
 			body = Some(inner_body);
 		}
-		let key = aux_key(&hash);
 		let import_block = BlockImportParams {
 			origin,
 			header: checked_header,
@@ -302,8 +463,9 @@ impl<B: BlockT<Hash=H256>, C, S, Algorithm> Verifier<B> for PowVerifier<B, C, S,
 			body,
 			finalized: false,
 			justification,
-			auxiliary: vec![(key, Some(aux.encode()))],
-			fork_choice: ForkChoiceStrategy::Custom(aux.total_difficulty > best_aux.total_difficulty),
+			auxiliary: Vec::new(),
+			// overridden by `PowBlockImport`.
+			fork_choice: ForkChoiceStrategy::LongestChain,
 			allow_missing_state: false,
 		};
 
@@ -328,21 +490,24 @@ pub fn register_pow_inherent_data_provider(
 /// The PoW import queue type.
 pub type PowImportQueue<B> = BasicQueue<B>;
 
-/// Import queue for PoW engine.
-pub fn import_queue<B, C, S, Algorithm>(
+/// Import queue for PoW engine. The block import should be a `PowBlockImport`.
+///
+/// When the chain is finalized by GRANDPA, the GRANDPA block import should be
+/// given as the justification import.
+pub fn import_queue<B, C, Algorithm>(
 	block_import: BoxBlockImport<B>,
+	justification_import: Option<BoxJustificationImport<B>>,
 	client: Arc<C>,
 	algorithm: Algorithm,
 	check_inherents_after: <<B as BlockT>::Header as HeaderT>::Number,
-	select_chain: Option<S>,
 	inherent_data_providers: InherentDataProviders,
+	prometheus_registry: Option<&Registry>,
 ) -> Result<PowImportQueue<B>, consensus_common::Error> where
 	B: BlockT<Hash=H256>,
 	C: ProvideRuntimeApi + HeaderBackend<B> + BlockOf + ProvideCache<B> + AuxStore,
 	C: Send + Sync + AuxStore + 'static,
 	C::Api: BlockBuilderApi<B>,
 	Algorithm: PowAlgorithm<B> + Send + Sync + 'static,
-	S: SelectChain<B> + 'static,
 {
 	register_pow_inherent_data_provider(&inherent_data_providers)?;
 
@@ -350,41 +515,63 @@ pub fn import_queue<B, C, S, Algorithm>(
 		client.clone(),
 		algorithm,
 		check_inherents_after,
-		select_chain,
 		inherent_data_providers,
 	);
 
 	Ok(BasicQueue::new(
 		verifier,
 		block_import,
+		justification_import,
 		None,
-		None
+		prometheus_registry,
 	))
 }
 
-/// Start the background mining thread for PoW. Note that because PoW mining
+/// Get the hash of the best block, from the select chain if there is one.
+fn best_hash<B, C, S>(client: &C, select_chain: Option<&S>) -> Result<H256, Error<B>> where
+	B: BlockT<Hash=H256>,
+	C: HeaderBackend<B>,
+	S: SelectChain<B>,
+{
+	match select_chain {
+		Some(select_chain) => select_chain.best_chain()
+			.map(|header| header.hash())
+			.map_err(Error::BestHashSelectChain),
+		None => Ok(client.info().best_hash),
+	}
+}
+
+/// Start the background mining threads for PoW. Note that because PoW mining
 /// is CPU-intensive, it is not possible to use an async future to define this.
 /// However, it's not recommended to use background threads in the rest of the
 /// codebase.
 ///
+/// One thread builds blocks on top of the best block, and `threads` threads mine
+/// them. With no mining threads, blocks are only sealed by external miners through
+/// the returned handle, which also reports the hash rate of the mining threads.
+///
 /// `preruntime` is a parameter that allows a custom additional pre-runtime
 /// digest to be inserted for blocks being built. This can encode authorship
 /// information, or just be a graffiti. `round` is for number of rounds the
 /// CPU miner runs each time. This parameter should be tweaked so that each
 /// mining round is within sub-second time.
+///
+/// Sealed blocks are imported with `block_import`, which should be the
+/// `PowBlockImport` given to the import queue.
 pub fn start_mine<B: BlockT<Hash=H256>, C, Algorithm, E, SO, S>(
-	mut block_import: BoxBlockImport<B>,
+	block_import: BoxBlockImport<B>,
 	client: Arc<C>,
 	algorithm: Algorithm,
 	mut env: E,
 	preruntime: Option<Vec<u8>>,
 	round: u32,
+	threads: usize,
 	mut sync_oracle: SO,
 	build_time: std::time::Duration,
 	select_chain: Option<S>,
 	inherent_data_providers: inherents::InherentDataProviders,
-) where
-	C: HeaderBackend<B> + AuxStore + 'static,
+) -> MiningHandle<B, Algorithm> where
+	C: HeaderBackend<B> + Send + Sync + 'static,
 	Algorithm: PowAlgorithm<B> + Send + Sync + 'static,
 	E: Environment<B> + Send + Sync + 'static,
 	E::Error: std::fmt::Debug,
@@ -395,15 +582,55 @@ pub fn start_mine<B: BlockT<Hash=H256>, C, Algorithm, E, SO, S>(
 		warn!("Registering inherent data provider for timestamp failed");
 	}
 
+	let algorithm = Arc::new(algorithm);
+	let worker = Arc::new(Mutex::new(MiningWorker::new(
+		algorithm.clone(),
+		block_import,
+	)));
+
+	for _ in 0..threads {
+		let worker = worker.clone();
+		let algorithm = algorithm.clone();
+
+		thread::spawn(move || {
+			loop {
+				let work = match worker.lock().work() {
+					Some(work) => work,
+					None => {
+						thread::sleep(Duration::from_millis(100));
+						continue
+					},
+				};
+
+				let seal = algorithm.mine(
+					&BlockId::Hash(work.parent_hash),
+					&work.pre_hash,
+					work.difficulty,
+					round,
+				);
+
+				worker.lock().note_hashes(round as u64);
+				match seal {
+					Ok(Some(seal)) => { worker.submit(work.pre_hash, seal); },
+					Ok(None) => (),
+					Err(e) => {
+						error!("Mining block failed with {:?}. Sleep for 1 second before restarting...", e);
+						thread::sleep(Duration::from_secs(1));
+					},
+				}
+			}
+		});
+	}
+
+	let handle = worker.clone();
 	thread::spawn(move || {
 		loop {
-			match mine_loop(
-				&mut block_import,
+			match build_loop(
+				&worker,
 				client.as_ref(),
-				&algorithm,
+				algorithm.as_ref(),
 				&mut env,
 				preruntime.as_ref(),
-				round,
 				&mut sync_oracle,
 				build_time.clone(),
 				select_chain.as_ref(),
@@ -411,57 +638,66 @@ pub fn start_mine<B: BlockT<Hash=H256>, C, Algorithm, E, SO, S>(
 			) {
 				Ok(()) => (),
 				Err(e) => error!(
-					"Mining block failed with {:?}. Sleep for 1 second before restarting...",
+					"Building block failed with {:?}. Sleep for 1 second before restarting...",
 					e
 				),
 			}
-			std::thread::sleep(std::time::Duration::new(1, 0));
+			thread::sleep(Duration::new(1, 0));
 		}
 	});
+
+	handle
 }
 
-fn mine_loop<B: BlockT<Hash=H256>, C, Algorithm, E, SO, S>(
-	block_import: &mut BoxBlockImport<B>,
+/// Keep the work of the mining worker on top of the best block, and update the hash rate.
+fn build_loop<B: BlockT<Hash=H256>, C, Algorithm, E, SO, S>(
+	worker: &Mutex<MiningWorker<B, Algorithm>>,
 	client: &C,
 	algorithm: &Algorithm,
 	env: &mut E,
 	preruntime: Option<&Vec<u8>>,
-	round: u32,
 	sync_oracle: &mut SO,
 	build_time: std::time::Duration,
 	select_chain: Option<&S>,
 	inherent_data_providers: &inherents::InherentDataProviders,
 ) -> Result<(), Error<B>> where
-	C: HeaderBackend<B> + AuxStore,
+	C: HeaderBackend<B>,
 	Algorithm: PowAlgorithm<B>,
 	E: Environment<B>,
 	E::Error: std::fmt::Debug,
 	SO: SyncOracle,
 	S: SelectChain<B>,
 {
-	'outer: loop {
+	const HASH_RATE_LOG_INTERVAL: u32 = 30;
+	// work is rebuilt at least this often, to include transactions which arrived since.
+	const MAX_WORK_AGE: Duration = Duration::from_secs(10);
+
+	let mut iterations = 0u32;
+	loop {
+		iterations = iterations.wrapping_add(1);
+		{
+			let hash_rate = worker.lock().update_hash_rate();
+			if iterations % HASH_RATE_LOG_INTERVAL == 0 && hash_rate > 0 {
+				info!(target: "pow", "Mining at {} hashes per second", hash_rate);
+			}
+		}
+
 		if sync_oracle.is_major_syncing() {
 			debug!(target: "pow", "Skipping proposal due to sync.");
-			std::thread::sleep(std::time::Duration::new(1, 0));
-			continue 'outer
+			worker.lock().set_build(None);
+			thread::sleep(Duration::new(1, 0));
+			continue
 		}
 
-		let (best_hash, best_header) = match select_chain {
-			Some(select_chain) => {
-				let header = select_chain.best_chain()
-					.map_err(Error::BestHeaderSelectChain)?;
-				let hash = header.hash();
-				(hash, header)
-			},
-			None => {
-				let hash = client.info().best_hash;
-				let header = client.header(BlockId::Hash(hash))
-					.map_err(Error::BestHeader)?
-					.ok_or(Error::NoBestHeader)?;
-				(hash, header)
-			},
-		};
-		let mut aux = PowAux::read(client, &best_hash)?;
+		let best_hash = best_hash(client, select_chain)?;
+		if !worker.lock().needs_rebuild(best_hash, MAX_WORK_AGE) {
+			thread::sleep(Duration::new(1, 0));
+			continue
+		}
+
+		let best_header = client.header(BlockId::Hash(best_hash))
+			.map_err(Error::BestHeader)?
+			.ok_or(Error::NoBestHeader)?;
 		let mut proposer = env.init(&best_header)
 			.map_err(|e| Error::Environment(format!("{:?}", e)))?;
 
@@ -478,64 +714,155 @@ fn mine_loop<B: BlockT<Hash=H256>, C, Algorithm, E, SO, S>(
 		)).map_err(|e| Error::BlockProposingError(format!("{:?}", e)))?;
 
 		let (header, body) = block.deconstruct();
-		let (difficulty, seal) = {
-			let difficulty = algorithm.difficulty(
-				&BlockId::Hash(best_hash),
-			)?;
+		let difficulty = algorithm.difficulty(&BlockId::Hash(best_hash))?;
 
-			loop {
-				let seal = algorithm.mine(
-					&BlockId::Hash(best_hash),
-					&header.hash(),
-					difficulty,
-					round,
-				)?;
+		let work = MiningWork {
+			parent_hash: best_hash,
+			pre_hash: header.hash(),
+			difficulty,
+		};
+		debug!(target: "pow", "Mining block on top of {:?} at difficulty {:?}", best_hash, difficulty);
 
-				if let Some(seal) = seal {
-					break (difficulty, seal)
-				}
+		worker.lock().set_build(Some(MiningBuild { work, header, body, built_at: Instant::now() }));
+	}
+}
 
-				if best_hash != client.info().best_hash {
-					continue 'outer
-				}
-			}
-		};
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use client::LongestChain;
+	use primitives::U256;
+	use test_client::{BlockBuilderExt, ClientExt, Backend, TestClient, runtime::Block};
 
-		aux.difficulty = difficulty;
-		aux.total_difficulty.increment(difficulty);
-		let hash = {
-			let mut header = header.clone();
-			header.digest_mut().push(DigestItem::Seal(POW_ENGINE_ID, seal.clone()));
-			header.hash()
-		};
+	/// An algorithm whose difficulty is set by the test, for fork choice tests. Seals aren't
+	/// checked by the block import.
+	#[derive(Clone, Default)]
+	struct TestAlgorithm(Arc<Mutex<u128>>);
 
-		let key = aux_key(&hash);
-		let best_hash = match select_chain {
-			Some(select_chain) => select_chain.best_chain()
-				.map_err(Error::BestHashSelectChain)?
-				.hash(),
-			None => client.info().best_hash,
-		};
-		let best_aux = PowAux::<Algorithm::Difficulty>::read(client, &best_hash)?;
+	impl PowAlgorithm<Block> for TestAlgorithm {
+		type Difficulty = u128;
 
-		// if the best block has changed in the meantime drop our proposal
-		if best_aux.total_difficulty > aux.total_difficulty {
-			continue 'outer
+		fn difficulty(&self, _parent: &BlockId<Block>) -> Result<u128, Error<Block>> {
+			Ok(*self.0.lock())
 		}
 
-		let import_block = BlockImportParams {
+		fn verify(&self, _: &BlockId<Block>, _: &H256, _: &Seal, _: u128) -> Result<bool, Error<Block>> {
+			Ok(true)
+		}
+
+		fn mine(&self, _: &BlockId<Block>, _: &H256, _: u128, _: u32) -> Result<Option<Seal>, Error<Block>> {
+			Ok(None)
+		}
+	}
+
+	type TestBlockImport<'a> = PowBlockImport<Block, &'a TestClient, TestClient, LongestChain<Backend, Block>, TestAlgorithm>;
+
+	/// Import a block on top of `parent` at the given difficulty, returning its hash. Blocks
+	/// of different forks are told apart by `fork`.
+	fn import_at(
+		client: &TestClient,
+		block_import: &mut TestBlockImport,
+		algorithm: &TestAlgorithm,
+		parent: H256,
+		fork: u8,
+		difficulty: u128,
+	) -> H256 {
+		let mut builder = client.new_block_at(&BlockId::Hash(parent), Default::default()).unwrap();
+		builder.push_storage_change(vec![fork], Some(vec![fork])).unwrap();
+		let (header, body) = builder.bake().unwrap().deconstruct();
+		let hash = header.hash();
+
+		*algorithm.0.lock() = difficulty;
+		let import = BlockImportParams {
 			origin: BlockOrigin::Own,
 			header,
 			justification: None,
-			post_digests: vec![DigestItem::Seal(POW_ENGINE_ID, seal)],
+			post_digests: Vec::new(),
 			body: Some(body),
 			finalized: false,
-			auxiliary: vec![(key, Some(aux.encode()))],
-			fork_choice: ForkChoiceStrategy::Custom(true),
+			auxiliary: Vec::new(),
+			// overridden by `PowBlockImport`.
+			fork_choice: ForkChoiceStrategy::LongestChain,
 			allow_missing_state: false,
 		};
+		match block_import.import_block(import, HashMap::new()).unwrap() {
+			ImportResult::Imported(_) => hash,
+			result => panic!("block {:?} not imported: {:?}", hash, result),
+		}
+	}
+
+	#[test]
+	fn heaviest_chain_is_best() {
+		let client = Arc::new(test_client::new());
+		let algorithm = TestAlgorithm::default();
+		let mut block_import: TestBlockImport = PowBlockImport::new(&*client, client.clone(), algorithm.clone(), None);
+		let mut import = |parent, fork, difficulty| import_at(&client, &mut block_import, &algorithm, parent, fork, difficulty);
+		let genesis = client.info().best_hash;
+
+		let a1 = import(genesis, 1, 2);
+		assert_eq!(client.info().best_hash, a1);
+
+		// a fork of the same total difficulty doesn't replace the best block.
+		let b1 = import(genesis, 2, 1);
+		let b2 = import(b1, 2, 1);
+		assert_eq!(client.info().best_hash, a1);
+
+		// a heavier one does.
+		let b3 = import(b2, 2, 1);
+		assert_eq!(client.info().best_hash, b3);
+
+		// and a shorter chain with more total difficulty takes over again.
+		let a2 = import(a1, 1, 5);
+		assert_eq!(client.info().best_hash, a2);
+		assert_eq!(PowAux::<u128>::read::<_, Block>(&*client, &a2).unwrap().total_difficulty, 7);
+	}
+
+	#[test]
+	fn heavier_forks_not_descending_from_finality_are_not_best() {
+		let client = Arc::new(test_client::new());
+		let algorithm = TestAlgorithm::default();
+		let mut block_import: TestBlockImport = PowBlockImport::new(&*client, client.clone(), algorithm.clone(), None);
+		let mut import = |parent, fork, difficulty| import_at(&client, &mut block_import, &algorithm, parent, fork, difficulty);
+		let genesis = client.info().best_hash;
+
+		let a1 = import(genesis, 1, 1);
+		let a2 = import(a1, 1, 1);
+		let b1 = import(genesis, 2, 1);
+		let b2 = import(b1, 2, 1);
+		assert_eq!(client.info().best_hash, a2);
+
+		client.finalize_block(BlockId::Hash(a1), None).unwrap();
+
+		// the fork is heavier, but switching to it would revert finality.
+		let b3 = import(b2, 2, 10);
+		assert_eq!(client.info().best_hash, a2);
+		assert!(PowAux::<u128>::read::<_, Block>(&*client, &b3).unwrap().total_difficulty > 2);
+
+		// blocks on top of the finalized chain are still picked by total difficulty.
+		let a3 = import(a2, 1, 1);
+		assert_eq!(client.info().best_hash, a3);
+	}
+
+	#[test]
+	fn aux_without_parent_timestamp_is_still_read() {
+		let client = test_client::new();
+		let hash = H256::repeat_byte(1);
+
+		// the layout written before parent timestamps were recorded.
+		let legacy = (U256::from(10), U256::from(30)).encode();
+		client.insert_aux(&[(&aux_key(&hash)[..], &legacy[..])], &[]).unwrap();
+
+		let aux = PowAux::<U256>::read::<_, Block>(&client, &hash).unwrap();
+		assert_eq!((aux.difficulty, aux.total_difficulty, aux.parent_timestamp), (10.into(), 30.into(), 0));
+
+		let written = PowAux { difficulty: U256::from(20), total_difficulty: U256::from(50), parent_timestamp: 42 };
+		let entries = written.to_aux(&hash);
+		let entries = entries.iter()
+			.map(|(key, value)| (&key[..], value.as_ref().map(Vec::as_slice).unwrap()))
+			.collect::<Vec<_>>();
+		client.insert_aux(&entries, &[]).unwrap();
 
-		block_import.import_block(import_block, HashMap::default())
-			.map_err(|e| Error::BlockBuiltError(best_hash, e))?;
+		let aux = PowAux::<U256>::read::<_, Block>(&client, &hash).unwrap();
+		assert_eq!((aux.difficulty, aux.total_difficulty, aux.parent_timestamp), (20.into(), 50.into(), 42));
 	}
 }
@@ -0,0 +1,270 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The mining worker, which holds the block currently being mined.
+//!
+//! The worker is shared between the thread building blocks on top of the best block,
+//! the local CPU mining threads and external miners, which fetch the work and submit
+//! seals through the RPC.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use consensus_common::{BlockImportParams, BlockOrigin, ForkChoiceStrategy, ImportResult};
+use consensus_common::import_queue::BoxBlockImport;
+use log::*;
+use parking_lot::Mutex;
+use pow_primitives::{Seal, POW_ENGINE_ID};
+use primitives::H256;
+use sr_primitives::generic::{BlockId, DigestItem};
+use sr_primitives::traits::Block as BlockT;
+
+use crate::{Error, PowAlgorithm};
+
+/// Work to be sealed by a miner.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MiningWork<Difficulty> {
+	/// The hash of the block the work builds on.
+	pub parent_hash: H256,
+	/// The hash of the header to seal, without the seal.
+	pub pre_hash: H256,
+	/// The difficulty the seal has to satisfy.
+	pub difficulty: Difficulty,
+}
+
+/// A block built on top of the best block, waiting for a seal.
+pub(crate) struct MiningBuild<B: BlockT, Difficulty> {
+	pub(crate) work: MiningWork<Difficulty>,
+	pub(crate) header: B::Header,
+	pub(crate) body: Vec<B::Extrinsic>,
+	pub(crate) built_at: Instant,
+}
+
+/// Interface of the mining worker for external miners.
+pub trait MiningWorkerApi: Send + Sync {
+	/// The difficulty type of the proof of work algorithm.
+	type Difficulty;
+
+	/// The work currently being mined, if any.
+	fn work(&self) -> Option<MiningWork<Self::Difficulty>>;
+
+	/// Submit a seal for the work with the given pre-hash. Returns whether the seal was
+	/// valid for the current work and the sealed block was imported.
+	fn submit(&self, pre_hash: H256, seal: Seal) -> bool;
+
+	/// The hash rate of the local CPU mining threads, in hashes per second.
+	fn hash_rate(&self) -> u64;
+}
+
+/// The mining worker.
+///
+/// Sealed blocks are imported with the block import given to `start_mine`, which
+/// should be a `PowBlockImport` so that the fork choice takes total difficulty into
+/// account. The block import has its own lock, so that the worker isn't locked while
+/// a sealed block is imported.
+pub struct MiningWorker<B: BlockT<Hash=H256>, Algorithm: PowAlgorithm<B>> {
+	algorithm: Arc<Algorithm>,
+	block_import: Arc<Mutex<BoxBlockImport<B>>>,
+	build: Option<MiningBuild<B, Algorithm::Difficulty>>,
+	hashes: u64,
+	hash_rate: u64,
+	rate_updated: Instant,
+}
+
+/// A shared handle to the mining worker.
+pub type MiningHandle<B, Algorithm> = Arc<Mutex<MiningWorker<B, Algorithm>>>;
+
+impl<B, Algorithm> MiningWorker<B, Algorithm> where
+	B: BlockT<Hash=H256>,
+	Algorithm: PowAlgorithm<B>,
+{
+	pub(crate) fn new(
+		algorithm: Arc<Algorithm>,
+		block_import: BoxBlockImport<B>,
+	) -> Self {
+		MiningWorker {
+			algorithm,
+			block_import: Arc::new(Mutex::new(block_import)),
+			build: None,
+			hashes: 0,
+			hash_rate: 0,
+			rate_updated: Instant::now(),
+		}
+	}
+
+	/// The work currently being mined, if any.
+	pub fn work(&self) -> Option<MiningWork<Algorithm::Difficulty>> {
+		self.build.as_ref().map(|build| build.work.clone())
+	}
+
+	/// The hash rate of the local CPU mining threads, in hashes per second.
+	pub fn hash_rate(&self) -> u64 {
+		self.hash_rate
+	}
+
+	/// Take the current work out of the worker to import it with a seal for the given
+	/// pre-hash, along with what is needed to import it without holding the worker's lock.
+	fn take_build(&mut self, pre_hash: H256) -> Option<SealedImport<B, Algorithm>> {
+		match self.build.take() {
+			Some(build) if build.work.pre_hash == pre_hash => Some(SealedImport {
+				build,
+				algorithm: self.algorithm.clone(),
+				block_import: self.block_import.clone(),
+			}),
+			build => {
+				debug!(target: "pow", "Dropping seal for outdated work {:?}", pre_hash);
+				self.build = build;
+				None
+			},
+		}
+	}
+
+	/// Put back work whose seal turned out to be invalid, unless new work has been set
+	/// in the meantime.
+	fn restore_build(&mut self, build: MiningBuild<B, Algorithm::Difficulty>) {
+		if self.build.is_none() {
+			self.build = Some(build);
+		}
+	}
+
+	/// Whether the current work should be replaced by a block built on `best_hash`, either
+	/// because it builds on another block or because it is older than `max_age` and may be
+	/// missing transactions which arrived since.
+	pub(crate) fn needs_rebuild(&self, best_hash: H256, max_age: Duration) -> bool {
+		match self.build {
+			Some(ref build) => build.work.parent_hash != best_hash || build.built_at.elapsed() >= max_age,
+			None => true,
+		}
+	}
+
+	/// Replace the current work.
+	pub(crate) fn set_build(&mut self, build: Option<MiningBuild<B, Algorithm::Difficulty>>) {
+		self.build = build;
+	}
+
+	/// Account for hashes computed by a local mining thread.
+	pub(crate) fn note_hashes(&mut self, hashes: u64) {
+		self.hashes = self.hashes.saturating_add(hashes);
+	}
+
+	/// Recompute the hash rate from the hashes noted since the last update.
+	pub(crate) fn update_hash_rate(&mut self) -> u64 {
+		let elapsed = self.rate_updated.elapsed();
+		if elapsed >= Duration::from_secs(1) {
+			self.hash_rate = self.hashes * 1000 / elapsed.as_millis().max(1) as u64;
+			self.hashes = 0;
+			self.rate_updated = Instant::now();
+		}
+
+		self.hash_rate
+	}
+}
+
+/// Work taken out of the worker to be imported with a seal.
+struct SealedImport<B: BlockT<Hash=H256>, Algorithm: PowAlgorithm<B>> {
+	build: MiningBuild<B, Algorithm::Difficulty>,
+	algorithm: Arc<Algorithm>,
+	block_import: Arc<Mutex<BoxBlockImport<B>>>,
+}
+
+impl<B, Algorithm> SealedImport<B, Algorithm> where
+	B: BlockT<Hash=H256>,
+	Algorithm: PowAlgorithm<B>,
+{
+	/// Whether the given seal is valid for the work.
+	fn verify(&self, seal: &Seal) -> bool {
+		let work = &self.build.work;
+		match self.algorithm.verify(&BlockId::Hash(work.parent_hash), &work.pre_hash, seal, work.difficulty) {
+			Ok(true) => true,
+			Ok(false) => {
+				warn!(target: "pow", "Rejecting invalid seal for {:?}", work.pre_hash);
+				false
+			},
+			Err(e) => {
+				warn!(target: "pow", "Verifying seal for {:?} failed: {:?}", work.pre_hash, e);
+				false
+			},
+		}
+	}
+
+	/// Import the block sealed with the given seal.
+	fn import(self, seal: Seal) -> Result<bool, Error<B>> {
+		let SealedImport { build, block_import, .. } = self;
+
+		let parent_hash = build.work.parent_hash;
+		let seal = DigestItem::Seal(POW_ENGINE_ID, seal);
+		let import_block = BlockImportParams {
+			origin: BlockOrigin::Own,
+			header: build.header,
+			justification: None,
+			post_digests: vec![seal],
+			body: Some(build.body),
+			finalized: false,
+			auxiliary: Vec::new(),
+			// overridden by `PowBlockImport`.
+			fork_choice: ForkChoiceStrategy::LongestChain,
+			allow_missing_state: false,
+		};
+
+		match block_import.lock().import_block(import_block, HashMap::default())
+			.map_err(|e| Error::BlockBuiltError(parent_hash, e))?
+		{
+			ImportResult::Imported(_) => Ok(true),
+			result => {
+				debug!(target: "pow", "Mined block on {:?} not imported: {:?}", parent_hash, result);
+				Ok(false)
+			},
+		}
+	}
+}
+
+impl<B, Algorithm> MiningWorkerApi for Mutex<MiningWorker<B, Algorithm>> where
+	B: BlockT<Hash=H256>,
+	Algorithm: PowAlgorithm<B> + Send + Sync,
+{
+	type Difficulty = Algorithm::Difficulty;
+
+	fn work(&self) -> Option<MiningWork<Algorithm::Difficulty>> {
+		self.lock().work()
+	}
+
+	fn submit(&self, pre_hash: H256, seal: Seal) -> bool {
+		// the worker is only locked to take the work, so that the mining threads and the
+		// build loop aren't blocked while the sealed block is imported.
+		let sealed = match self.lock().take_build(pre_hash) {
+			Some(sealed) => sealed,
+			None => return false,
+		};
+
+		// invalid seals keep the work for further seals.
+		if !sealed.verify(&seal) {
+			self.lock().restore_build(sealed.build);
+			return false;
+		}
+
+		match sealed.import(seal) {
+			Ok(imported) => imported,
+			Err(e) => {
+				warn!(target: "pow", "Importing mined block failed: {:?}", e);
+				false
+			},
+		}
+	}
+
+	fn hash_rate(&self) -> u64 {
+		self.lock().hash_rate()
+	}
+}
@@ -0,0 +1,226 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Strategies to back off authoring when finality lags behind the best block.
+//!
+//! Authoring on every claimed slot while finality is stalled grows a long
+//! unfinalized chain, which is costly to reorg and keeps the pruning of state
+//! and of the consensus data structures on hold. Backing off gives finality a
+//! chance to catch up, while still producing blocks now and then so that the
+//! chain doesn't stall completely.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use log::{info, warn};
+use prometheus::{IntCounter, PrometheusError, Registry, register};
+use sr_primitives::traits::{SimpleArithmetic, UniqueSaturatedInto};
+use substrate_telemetry::{telemetry, CONSENSUS_INFO};
+
+/// A strategy to decide whether to skip authoring in a slot.
+pub trait BackoffAuthoringBlocksStrategy<N> {
+	/// Returns whether to skip authoring in `slot_now` on top of the best block,
+	/// given the number and slot of the best block and the number of the last
+	/// finalized block.
+	fn should_backoff(
+		&self,
+		chain_head_number: N,
+		chain_head_slot: u64,
+		finalized_number: N,
+		slot_now: u64,
+		logging_target: &str,
+	) -> bool;
+}
+
+/// Never back off.
+impl<N> BackoffAuthoringBlocksStrategy<N> for () {
+	fn should_backoff(&self, _: N, _: u64, _: N, _: u64, _: &str) -> bool {
+		false
+	}
+}
+
+/// Back off authoring when the gap between the best and the last finalized block
+/// grows, by waiting for an increasing number of slots after the best block.
+///
+/// Once more than `unfinalized_slack` blocks are unfinalized, a slot is only
+/// authored on if it is more than `(unfinalized - unfinalized_slack) / authoring_bias`
+/// slots after the slot of the best block, the interval being capped at
+/// `max_interval` slots.
+///
+/// Clones share the count of skipped slots, which is also reported to Prometheus
+/// once `with_prometheus_registry` is called.
+#[derive(Clone, Debug)]
+pub struct BackoffAuthoringOnFinalizedHeadLagging<N> {
+	/// The maximum number of slots to wait for between blocks.
+	pub max_interval: N,
+	/// The number of unfinalized blocks tolerated before backing off.
+	pub unfinalized_slack: N,
+	/// The number of unfinalized blocks beyond the slack it takes to wait one
+	/// more slot. Higher values back off more slowly.
+	pub authoring_bias: N,
+	skipped_slots: Arc<AtomicU64>,
+	metrics: Option<Metrics>,
+}
+
+#[derive(Clone, Debug)]
+struct Metrics {
+	/// Number of claimed slots skipped to back off authoring.
+	skipped_slots: IntCounter,
+}
+
+impl Metrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Metrics {
+			skipped_slots: register(IntCounter::new(
+				"slots_backoff_skipped_total",
+				"Number of claimed slots skipped because finality lags behind",
+			)?, registry)?,
+		})
+	}
+}
+
+impl<N: SimpleArithmetic> BackoffAuthoringOnFinalizedHeadLagging<N> {
+	/// Create a new strategy with the given parameters.
+	pub fn new(max_interval: N, unfinalized_slack: N, authoring_bias: N) -> Self {
+		BackoffAuthoringOnFinalizedHeadLagging {
+			max_interval,
+			unfinalized_slack,
+			authoring_bias,
+			skipped_slots: Default::default(),
+			metrics: None,
+		}
+	}
+
+	/// Report the skipped slots to `prometheus_registry`, if any.
+	pub fn with_prometheus_registry(mut self, prometheus_registry: Option<&Registry>) -> Self {
+		self.metrics = prometheus_registry.and_then(|registry| {
+			Metrics::register(registry)
+				.map_err(|e| warn!(target: "slots", "Failed to register backoff metrics: {}", e))
+				.ok()
+		});
+		self
+	}
+
+	/// The number of slots skipped so far by this strategy and its clones.
+	pub fn skipped_slots(&self) -> u64 {
+		self.skipped_slots.load(Ordering::Relaxed)
+	}
+}
+
+impl<N: SimpleArithmetic> Default for BackoffAuthoringOnFinalizedHeadLagging<N> {
+	fn default() -> Self {
+		// with 6 second slots, blocks are authored at most every 10 minutes once
+		// 250 blocks are unfinalized.
+		Self::new(N::from(100u32), N::from(50u32), N::from(2u32))
+	}
+}
+
+impl<N> BackoffAuthoringBlocksStrategy<N> for BackoffAuthoringOnFinalizedHeadLagging<N> where
+	N: SimpleArithmetic + Copy,
+{
+	fn should_backoff(
+		&self,
+		chain_head_number: N,
+		chain_head_slot: u64,
+		finalized_number: N,
+		slot_now: u64,
+		logging_target: &str,
+	) -> bool {
+		// this can only happen with clock drift, the slot worker won't author
+		// on top of a block from the same or a later slot anyway.
+		if slot_now <= chain_head_slot {
+			return false;
+		}
+
+		let unfinalized = chain_head_number.saturating_sub(finalized_number);
+		let interval = unfinalized.saturating_sub(self.unfinalized_slack)
+			.checked_div(&self.authoring_bias)
+			.unwrap_or(unfinalized)
+			.min(self.max_interval);
+		let interval: u64 = interval.unique_saturated_into();
+
+		if slot_now > chain_head_slot.saturating_add(interval) {
+			return false;
+		}
+
+		let skipped = self.skipped_slots.fetch_add(1, Ordering::Relaxed) + 1;
+		if let Some(metrics) = &self.metrics {
+			metrics.skipped_slots.inc();
+		}
+		let unfinalized: u64 = unfinalized.unique_saturated_into();
+
+		info!(
+			target: logging_target,
+			"Backing off authoring in slot {}: {} blocks are unfinalized, waiting for {} slots after the best block.",
+			slot_now,
+			unfinalized,
+			interval,
+		);
+		telemetry!(CONSENSUS_INFO; "slots.backing_off_authoring";
+			"slot" => slot_now,
+			"unfinalized" => unfinalized,
+			"interval" => interval,
+			"skipped_slots" => skipped,
+		);
+
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn backs_off_more_as_finality_lags() {
+		let strategy = BackoffAuthoringOnFinalizedHeadLagging::<u64>::new(10, 5, 2);
+		let should_backoff = |head_number, finalized_number, slot_now| {
+			strategy.should_backoff(head_number, 100, finalized_number, slot_now, "test")
+		};
+
+		// within the slack every slot is authored on.
+		assert!(!should_backoff(105, 100, 101));
+
+		// 9 unfinalized blocks beyond the slack wait for 4 slots.
+		assert!(should_backoff(114, 100, 104));
+		assert!(!should_backoff(114, 100, 105));
+
+		// the interval is capped.
+		assert!(should_backoff(1000, 100, 110));
+		assert!(!should_backoff(1000, 100, 111));
+
+		// the best block's slot or earlier is never skipped.
+		assert!(!should_backoff(1000, 100, 100));
+
+		assert_eq!(strategy.clone().skipped_slots(), 2);
+	}
+
+	#[test]
+	fn skipped_slots_are_reported_to_prometheus() {
+		let registry = prometheus::new_registry();
+		let strategy = BackoffAuthoringOnFinalizedHeadLagging::<u64>::new(10, 5, 2)
+			.with_prometheus_registry(Some(&registry));
+
+		assert!(strategy.should_backoff(1000, 100, 100, 101, "test"));
+
+		let metrics = String::from_utf8(prometheus::encode(&registry).unwrap()).unwrap();
+		assert!(metrics.contains("substrate_slots_backoff_skipped_total 1"));
+	}
+
+	#[test]
+	fn unit_strategy_never_backs_off() {
+		assert!(!BackoffAuthoringBlocksStrategy::<u64>::should_backoff(&(), 1000, 1, 0, 2, "test"));
+	}
+}
@@ -25,10 +25,13 @@
 
 mod slots;
 mod aux_schema;
+mod backoff;
 
 pub use slots::{SignedDuration, SlotInfo};
 use slots::Slots;
 pub use aux_schema::{check_equivocation, MAX_SLOT_CAPACITY, PRUNING_BOUND};
+pub use offchain_primitives::equivocation::{queue_equivocation_report, MAX_QUEUED_EQUIVOCATIONS};
+pub use backoff::{BackoffAuthoringBlocksStrategy, BackoffAuthoringOnFinalizedHeadLagging};
 
 use codec::{Decode, Encode};
 use consensus_common::{BlockImport, Proposer, SyncOracle, SelectChain};
@@ -37,7 +40,7 @@ use futures_timer::Delay;
 use inherents::{InherentData, InherentDataProviders};
 use log::{debug, error, info, warn};
 use sr_primitives::generic::BlockId;
-use sr_primitives::traits::{ApiRef, Block as BlockT, Header, ProvideRuntimeApi};
+use sr_primitives::traits::{ApiRef, Block as BlockT, Header, NumberFor, ProvideRuntimeApi};
 use std::{fmt::Debug, ops::Deref, pin::Pin, sync::Arc};
 use substrate_telemetry::{telemetry, CONSENSUS_DEBUG, CONSENSUS_WARN, CONSENSUS_INFO};
 use parking_lot::Mutex;
@@ -112,6 +115,48 @@ pub trait SimpleSlotWorker<B: BlockT> {
 	/// Returns a `Proposer` to author on top of the given block.
 	fn proposer(&mut self, block: &B::Header) -> Result<Self::Proposer, consensus_common::Error>;
 
+	/// The strategy to back off authoring with when finality lags behind, if any.
+	/// Defaults to authoring on every claimed slot.
+	fn backoff_authoring_blocks(&self) -> Option<&dyn BackoffAuthoringBlocksStrategy<NumberFor<B>>> {
+		None
+	}
+
+	/// Returns the slot the given block was authored in, if it can be found.
+	/// Only needed to back off authoring.
+	fn slot_of(&self, _header: &B::Header) -> Option<u64> {
+		None
+	}
+
+	/// Returns the number of the last finalized block, if it is known.
+	/// Only needed to back off authoring.
+	fn finalized_number(&self) -> Option<NumberFor<B>> {
+		None
+	}
+
+	/// Whether to skip authoring in the given claimed slot on top of the given
+	/// block, according to the `backoff_authoring_blocks` strategy.
+	fn should_backoff(&self, slot_number: u64, chain_head: &B::Header) -> bool {
+		let strategy = match self.backoff_authoring_blocks() {
+			Some(strategy) => strategy,
+			None => return false,
+		};
+
+		match (self.slot_of(chain_head), self.finalized_number()) {
+			(Some(chain_head_slot), Some(finalized_number)) => strategy.should_backoff(
+				*chain_head.number(),
+				chain_head_slot,
+				finalized_number,
+				slot_number,
+				self.logging_target(),
+			),
+			_ => false,
+		}
+	}
+
+	/// Adapt the inherent data of a slot, which was created before the block to author on
+	/// was known, to a block authored on top of the given block. Defaults to keeping it as is.
+	fn update_inherent_data(&self, _chain_head: &B::Header, _inherent_data: &mut InherentData) {}
+
 	/// Implements the `on_slot` functionality from `SlotWorker`.
 	fn on_slot(&mut self, chain_head: B::Header, slot_info: SlotInfo)
 		-> Pin<Box<dyn Future<Output = Result<(), consensus_common::Error>> + Send>> where
@@ -154,6 +199,10 @@ pub trait SimpleSlotWorker<B: BlockT> {
 			Some(claim) => claim,
 		};
 
+		if self.should_backoff(slot_number, &chain_head) {
+			return Box::pin(future::ready(Ok(())));
+		}
+
 		debug!(
 			target: self.logging_target(), "Starting authorship at slot {}; timestamp = {}",
 			slot_number,
@@ -181,10 +230,13 @@ pub trait SimpleSlotWorker<B: BlockT> {
 		let remaining_duration = slot_info.remaining_duration();
 		let logs = self.pre_digest_data(slot_number, &claim);
 
+		let mut inherent_data = slot_info.inherent_data;
+		self.update_inherent_data(&chain_head, &mut inherent_data);
+
 		// deadline our production to approx. the end of the slot
 		let proposal_work = futures::future::select(
 			proposer.propose(
-				slot_info.inherent_data,
+				inherent_data,
 				sr_primitives::generic::Digest {
 					logs,
 				},
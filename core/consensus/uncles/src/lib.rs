@@ -20,7 +20,7 @@
 #![forbid(unsafe_code, missing_docs)]
 
 use consensus_common::SelectChain;
-use inherents::{InherentDataProviders};
+use inherents::{InherentData, InherentDataProviders};
 use log::warn;
 use client::ProvideUncles;
 use sr_primitives::traits::{Block as BlockT, Header};
@@ -29,6 +29,37 @@ use std::sync::Arc;
 /// Maximum uncles generations we may provide to the runtime.
 const MAX_UNCLE_GENERATIONS: u32 = 8;
 
+/// Get the uncles a child of the given block may include.
+pub fn uncles_of<B, C>(client: &C, parent_hash: B::Hash) -> Vec<B::Header> where
+	B: BlockT,
+	C: ProvideUncles<B>,
+{
+	match client.uncles(parent_hash, MAX_UNCLE_GENERATIONS.into()) {
+		Ok(uncles) => uncles,
+		Err(e) => {
+			warn!(target: "uncles", "Unable to get uncles: {:?}", e);
+			Vec::new()
+		}
+	}
+}
+
+/// Replace the uncles in the given inherent data by the uncles a child of the given
+/// block may include.
+///
+/// The registered inherent data provider picks uncles for a child of the best block,
+/// so this should be used when authoring on top of another block, or when the inherent
+/// data was created before the block to author on was known.
+pub fn replace_uncles_inherent_data<B, C>(
+	client: &C,
+	parent_hash: B::Hash,
+	inherent_data: &mut InherentData,
+) where
+	B: BlockT,
+	C: ProvideUncles<B>,
+{
+	inherent_data.replace_data(srml_authorship::INHERENT_IDENTIFIER, &uncles_of(client, parent_hash));
+}
+
 /// Register uncles inherent data provider, if not registered already.
 pub fn register_uncles_inherent_data_provider<B, C, SC>(
 	client: Arc<C>,
@@ -50,17 +81,10 @@ pub fn register_uncles_inherent_data_provider<B, C, SC>(
 							return Vec::new();
 						}
 					};
-					match client.uncles(chain_head.hash(), MAX_UNCLE_GENERATIONS.into()) {
-						Ok(uncles) => uncles,
-						Err(e) => {
-							warn!(target: "uncles", "Unable to get uncles: {:?}", e);
-							Vec::new()
-						}
-					}
+					uncles_of(&*client, chain_head.hash())
 				}
 			}))
 		.map_err(|err| consensus_common::Error::InherentData(err.into()))?;
 	}
 	Ok(())
 }
-
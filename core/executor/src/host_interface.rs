@@ -18,7 +18,7 @@
 //!
 //! These are the host functions callable from within the Substrate runtime.
 
-use codec::Encode;
+use codec::{Encode, Decode};
 use std::{convert::TryFrom, str};
 use primitives::{
 	blake2_128, blake2_256, twox_64, twox_128, twox_256, ed25519, sr25519, Blake2Hasher, Pair,
@@ -1068,6 +1068,54 @@ impl_wasm_host_interface! {
 				}
 			})
 		}
+
+		ext_dht_put(
+			key: Pointer<u8>,
+			key_len: WordSize,
+			record: Pointer<u8>,
+			record_len: WordSize,
+			deadline: u64,
+		) -> u32 {
+			let key = context.read_memory(key, key_len)
+				.map_err(|_| "OOB while ext_dht_put: wasm")?;
+			let record = context.read_memory(record, record_len)
+				.map_err(|_| "OOB while ext_dht_put: wasm")?;
+			let record = offchain::DhtRecord::decode(&mut &record[..])
+				.map_err(|_| "Invalid record in ext_dht_put")?;
+
+			let res = runtime_io::dht_put(&key, record, deadline_to_timestamp(deadline));
+
+			Ok(if res.is_ok() { 0 } else { 1 })
+		}
+
+		ext_dht_get(
+			key: Pointer<u8>,
+			key_len: WordSize,
+			deadline: u64,
+			written_out: Pointer<u32>,
+		) -> Pointer<u8> {
+			let key = context.read_memory(key, key_len)
+				.map_err(|_| "OOB while ext_dht_get: wasm")?;
+
+			let res = runtime_io::dht_get(&key, deadline_to_timestamp(deadline));
+
+			let (offset, len) = match res {
+				Ok(records) => {
+					let encoded = records.encode();
+					let len = encoded.len() as u32;
+					let offset = context.allocate_memory(len)?;
+					context.write_memory(offset, &encoded)
+						.map_err(|_| "Invalid attempt to set memory in ext_dht_get")?;
+					(offset, len)
+				},
+				Err(()) => (Pointer::null(), u32::max_value()),
+			};
+
+			context.write_primitive(written_out, len)
+				.map_err(|_| "Invalid attempt to write written_out in ext_dht_get")?;
+
+			Ok(offset)
+		}
 	}
 }
 
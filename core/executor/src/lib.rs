@@ -45,7 +45,9 @@ mod integration_tests;
 
 pub mod error;
 pub use wasmi;
-pub use native_executor::{with_native_environment, NativeExecutor, NativeExecutionDispatch};
+pub use native_executor::{
+	with_native_environment, NativeExecutor, NativeExecutionDispatch, DEFAULT_HEAP_PAGES,
+};
 pub use runtime_version::{RuntimeVersion, NativeVersion};
 pub use codec::Codec;
 #[doc(hidden)]
@@ -14,13 +14,14 @@
 // You should have received a copy of the GNU General Public License
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{result, cell::RefCell, panic::{UnwindSafe, AssertUnwindSafe}};
+use std::{fmt, result, cell::RefCell, panic::{UnwindSafe, AssertUnwindSafe}, time::Instant};
 use crate::error::{Error, Result};
 use crate::wasm_runtime::{RuntimesCache, WasmExecutionMethod, WasmRuntime};
 use crate::RuntimeInfo;
 use runtime_version::{NativeVersion, RuntimeVersion};
 use codec::{Decode, Encode};
 use primitives::{NativeOrEncoded, traits::{CodeExecutor, Externalities}};
+use prometheus::{HistogramOpts, HistogramVec, PrometheusError, Registry, register};
 use log::{trace, warn};
 
 thread_local! {
@@ -28,7 +29,7 @@ thread_local! {
 }
 
 /// Default num of pages for the heap
-const DEFAULT_HEAP_PAGES: u64 = 1024;
+pub const DEFAULT_HEAP_PAGES: u64 = 1024;
 
 pub(crate) fn safe_call<F, U>(f: F) -> Result<U>
 	where F: UnwindSafe + FnOnce() -> U
@@ -72,6 +73,32 @@ pub struct NativeExecutor<D> {
 	native_version: NativeVersion,
 	/// The number of 64KB pages to allocate for Wasm execution.
 	default_heap_pages: u64,
+	/// Prometheus metrics, if a registry was given.
+	metrics: Option<Metrics>,
+}
+
+/// Prometheus metrics of the executor.
+#[derive(Clone)]
+struct Metrics {
+	/// Duration of runtime calls, by method and by whether they were executed natively.
+	call_duration: HistogramVec,
+}
+
+impl Metrics {
+	fn register(registry: &Registry) -> result::Result<Self, PrometheusError> {
+		Ok(Metrics {
+			call_duration: register(HistogramVec::new(
+				HistogramOpts::new("executor_call_duration_seconds", "Duration of runtime calls"),
+				&["method", "native"],
+			)?, registry)?,
+		})
+	}
+}
+
+impl fmt::Debug for Metrics {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("Metrics")
+	}
 }
 
 impl<D: NativeExecutionDispatch> NativeExecutor<D> {
@@ -89,7 +116,19 @@ impl<D: NativeExecutionDispatch> NativeExecutor<D> {
 			fallback_method,
 			native_version: D::native_version(),
 			default_heap_pages: default_heap_pages.unwrap_or(DEFAULT_HEAP_PAGES),
+			metrics: None,
+		}
+	}
+
+	/// Report the duration of runtime calls to the given Prometheus registry.
+	///
+	/// Clones of the executor report to the same metrics.
+	pub fn with_prometheus_registry(mut self, registry: &Registry) -> Self {
+		match Metrics::register(registry) {
+			Ok(metrics) => self.metrics = Some(metrics),
+			Err(e) => warn!(target: "executor", "Failed to register executor metrics: {}", e),
 		}
+		self
 	}
 
 	/// Execute the given closure `f` with the latest runtime (based on the `CODE` key in `ext`).
@@ -143,6 +182,7 @@ impl<D: NativeExecutionDispatch> Clone for NativeExecutor<D> {
 			fallback_method: self.fallback_method,
 			native_version: D::native_version(),
 			default_heap_pages: self.default_heap_pages,
+			metrics: self.metrics.clone(),
 		}
 	}
 }
@@ -182,6 +222,7 @@ impl<D: NativeExecutionDispatch> CodeExecutor for NativeExecutor<D> {
 		use_native: bool,
 		native_call: Option<NC>,
 	) -> (Result<NativeOrEncoded<R>>, bool){
+		let started = Instant::now();
 		let mut used_native = false;
 		let result = self.with_runtime(ext, |mut runtime, onchain_version, mut ext| {
 			match (
@@ -236,6 +277,13 @@ impl<D: NativeExecutionDispatch> CodeExecutor for NativeExecutor<D> {
 				}
 			}
 		});
+
+		if let Some(metrics) = &self.metrics {
+			metrics.call_duration
+				.with_label_values(&[method, if used_native { "true" } else { "false" }])
+				.observe(started.elapsed().as_secs_f64());
+		}
+
 		(result, used_native)
 	}
 }
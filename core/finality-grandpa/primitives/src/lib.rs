@@ -25,6 +25,8 @@ extern crate alloc;
 use serde::Serialize;
 use codec::{Encode, Decode, Input, Codec};
 use sr_primitives::{ConsensusEngineId, RuntimeDebug};
+use sr_primitives::traits::NumberFor;
+use app_crypto::RuntimeAppPublic;
 use client::decl_runtime_apis;
 use rstd::borrow::Cow;
 use rstd::vec::Vec;
@@ -157,6 +159,93 @@ impl<N: Codec> ConsensusLog<N> {
 	}
 }
 
+/// The offchain local storage key under which the voter queues the equivocations it has
+/// detected, as an encoded `Vec<EquivocationProof<Hash, Number>>`. The queue is drained by the
+/// offchain worker of the runtime module, which turns each entry into an on-chain report.
+pub const EQUIVOCATIONS_KEY: &[u8] = b"srml/grandpa-equivocations";
+
+/// The phase of a round a vote was cast in.
+///
+/// Encodes the same way as the index of the corresponding `finality_grandpa::Message` variant,
+/// so that signatures over votes can be checked without depending on the GRANDPA crate.
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub enum VotePhase {
+	/// A prevote.
+	Prevote,
+	/// A precommit.
+	Precommit,
+}
+
+/// A vote target, along with the signature of the authority that cast the vote.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct SignedVote<H, N> {
+	/// The hash of the target block.
+	pub target_hash: H,
+	/// The number of the target block.
+	pub target_number: N,
+	/// The signature of the authority on the localized vote.
+	pub signature: AuthoritySignature,
+}
+
+/// Proof that an authority has cast two different votes in the same phase of a round.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct EquivocationProof<H, N> {
+	/// The set id the round belongs to.
+	pub set_id: SetId,
+	/// The round the equivocation happened in.
+	pub round: RoundNumber,
+	/// The phase of the round both votes were cast in.
+	pub phase: VotePhase,
+	/// The authority that equivocated.
+	pub offender: AuthorityId,
+	/// The first vote.
+	pub first: SignedVote<H, N>,
+	/// The second vote.
+	pub second: SignedVote<H, N>,
+}
+
+impl<H: Encode + PartialEq, N: Encode + PartialEq> EquivocationProof<H, N> {
+	/// Check that both votes target different blocks and are signed by the offender.
+	pub fn check(&self) -> bool {
+		if self.first.target_hash == self.second.target_hash &&
+			self.first.target_number == self.second.target_number
+		{
+			return false;
+		}
+
+		let check_signature = |vote: &SignedVote<H, N>| {
+			let payload = localized_vote_payload(
+				self.phase,
+				&vote.target_hash,
+				&vote.target_number,
+				self.round,
+				self.set_id,
+			);
+			self.offender.verify(&payload, &vote.signature)
+		};
+
+		check_signature(&self.first) && check_signature(&self.second)
+	}
+}
+
+/// The payload signed by an authority when casting a vote, i.e. the encoded message
+/// localized to the given round and set.
+pub fn localized_vote_payload<H: Encode, N: Encode>(
+	phase: VotePhase,
+	target_hash: &H,
+	target_number: &N,
+	round: RoundNumber,
+	set_id: SetId,
+) -> Vec<u8> {
+	(phase, target_hash, target_number, round, set_id).encode()
+}
+
+/// An equivocation proof for the given block type.
+pub type BlockEquivocationProof<Block> = EquivocationProof<
+	<Block as sr_primitives::traits::Block>::Hash,
+	NumberFor<Block>,
+>;
+
 /// WASM function call to check for pending changes.
 pub const PENDING_CHANGE_CALL: &str = "grandpa_pending_change";
 /// WASM function call to get current GRANDPA authorities.
@@ -231,4 +320,15 @@ decl_runtime_apis! {
 		/// is finalized by the authorities from block B-1.
 		fn grandpa_authorities() -> AuthorityList;
 	}
+
+	/// API for runtimes restricting the blocks the GRANDPA voters vote on.
+	///
+	/// This is queried by the `RuntimeVotingRule` at the best block, allowing
+	/// the runtime to delay finality, e.g. until some data it depends on is
+	/// available, or to keep a number of blocks unfinalized.
+	pub trait GrandpaVotingRuleApi {
+		/// Get the number of the highest block of the current chain that may be
+		/// voted on, or `None` if votes shouldn't be restricted.
+		fn max_vote_target() -> Option<NumberFor<Block>>;
+	}
 }
@@ -0,0 +1,331 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC API for the GRANDPA finality gadget.
+//!
+//! Exposes the state of the round the local voter is taking part in, the
+//! best finalized block and the justifications stored by the node.
+
+#![warn(missing_docs)]
+
+use std::sync::Arc;
+
+use client::{CallExecutor, Client, backend::Backend};
+use futures::{Future, Sink, Stream};
+use grandpa::{GrandpaJustificationStream, SharedVoterState};
+use jsonrpc_derive::rpc;
+use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
+use log::warn;
+use primitives::{Blake2Hasher, Bytes, H256};
+use rpc::Subscriptions;
+use serde::{Serialize, Deserialize};
+use sr_primitives::generic::BlockId;
+use sr_primitives::traits::{Block as BlockT, NumberFor};
+
+pub use self::gen_client::Client as GrandpaClient;
+
+/// GRANDPA RPC Result type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// GRANDPA RPC errors.
+#[derive(Debug, derive_more::Display, derive_more::From)]
+pub enum Error {
+	/// The node is not running a GRANDPA voter, or it hasn't started a round yet.
+	#[display(fmt = "GRANDPA voter is not running")]
+	VoterNotRunning,
+	/// Client error.
+	#[display(fmt = "Client error: {}", _0)]
+	Client(client::error::Error),
+}
+
+impl std::error::Error for Error {}
+
+/// Base code for all GRANDPA errors.
+const BASE_ERROR: i64 = 6000;
+
+impl From<Error> for jsonrpc_core::Error {
+	fn from(e: Error) -> Self {
+		match e {
+			Error::VoterNotRunning => jsonrpc_core::Error {
+				code: jsonrpc_core::ErrorCode::ServerError(BASE_ERROR + 1),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::Client(_) => jsonrpc_core::Error {
+				code: jsonrpc_core::ErrorCode::ServerError(BASE_ERROR + 2),
+				message: format!("{}", e),
+				data: None,
+			},
+		}
+	}
+}
+
+/// The participation of the voters in one of the phases of a round.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Participation {
+	/// The weight of the voters that have voted.
+	pub current_weight: u64,
+	/// The voters we have seen a vote from.
+	pub voted: Vec<String>,
+	/// The voters we haven't seen a vote from yet.
+	pub missing: Vec<String>,
+}
+
+/// The state of the round the local voter is taking part in.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundState {
+	/// The current authority set id.
+	pub set_id: u64,
+	/// The current round number.
+	pub round: u64,
+	/// The total weight of all voters.
+	pub total_weight: u64,
+	/// The weight required for a supermajority.
+	pub threshold_weight: u64,
+	/// Prevote participation.
+	pub prevotes: Participation,
+	/// Precommit participation.
+	pub precommits: Participation,
+}
+
+impl From<grandpa::RoundState> for RoundState {
+	fn from(state: grandpa::RoundState) -> Self {
+		let participation = |voted: &std::collections::HashSet<grandpa::AuthorityId>, weight| {
+			let (voted, missing): (Vec<_>, Vec<_>) = state.voters.iter()
+				.map(|(id, _)| id)
+				.partition(|id| voted.contains(*id));
+			Participation {
+				current_weight: weight,
+				voted: voted.into_iter().map(ToString::to_string).collect(),
+				missing: missing.into_iter().map(ToString::to_string).collect(),
+			}
+		};
+
+		RoundState {
+			set_id: state.set_id,
+			round: state.round,
+			total_weight: state.total_weight,
+			threshold_weight: state.threshold_weight,
+			prevotes: participation(&state.prevotes, state.prevote_weight()),
+			precommits: participation(&state.precommits, state.precommit_weight()),
+		}
+	}
+}
+
+/// A block hash and number.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockInfo<Hash, Number> {
+	/// The block hash.
+	pub hash: Hash,
+	/// The block number.
+	pub number: Number,
+}
+
+/// A justification stored by the node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Justification<Hash, Number> {
+	/// The finalized block.
+	pub block: BlockInfo<Hash, Number>,
+	/// The SCALE-encoded GRANDPA justification.
+	pub justification: Bytes,
+}
+
+/// GRANDPA RPC API
+#[rpc]
+pub trait GrandpaApi<Hash, Number> {
+	/// RPC metadata
+	type Metadata;
+
+	/// Returns the set id, round and vote participation of the round the
+	/// local voter is taking part in.
+	#[rpc(name = "grandpa_roundState")]
+	fn round_state(&self) -> Result<RoundState>;
+
+	/// Returns the best finalized block.
+	#[rpc(name = "grandpa_bestFinalized")]
+	fn best_finalized(&self) -> Result<BlockInfo<Hash, Number>>;
+
+	/// Returns the encoded justification stored for the given finalized block, if any.
+	///
+	/// Justifications are only stored for blocks enacting authority set
+	/// changes and periodically, not for every finalized block.
+	#[rpc(name = "grandpa_justification")]
+	fn justification(&self, hash: Hash) -> Result<Option<Bytes>>;
+
+	/// Subscribe to the justifications stored by the node.
+	#[pubsub(
+		subscription = "grandpa_justifications",
+		subscribe,
+		name = "grandpa_subscribeJustifications"
+	)]
+	fn subscribe_justifications(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<Justification<Hash, Number>>,
+	);
+
+	/// Unsubscribe from justifications.
+	#[pubsub(
+		subscription = "grandpa_justifications",
+		unsubscribe,
+		name = "grandpa_unsubscribeJustifications"
+	)]
+	fn unsubscribe_justifications(
+		&self,
+		metadata: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> jsonrpc_core::Result<bool>;
+}
+
+/// Implementation of the GRANDPA RPC API.
+pub struct Grandpa<B, E, Block: BlockT, RA> {
+	client: Arc<Client<B, E, Block, RA>>,
+	voter_state: SharedVoterState,
+	justification_stream: GrandpaJustificationStream<Block>,
+	subscriptions: Subscriptions,
+}
+
+impl<B, E, Block: BlockT, RA> Grandpa<B, E, Block, RA> {
+	/// Create new `Grandpa` with the given reference to the client and the
+	/// handles taken from the GRANDPA `LinkHalf`.
+	pub fn new(
+		client: Arc<Client<B, E, Block, RA>>,
+		voter_state: SharedVoterState,
+		justification_stream: GrandpaJustificationStream<Block>,
+		subscriptions: Subscriptions,
+	) -> Self {
+		Grandpa {
+			client,
+			voter_state,
+			justification_stream,
+			subscriptions,
+		}
+	}
+}
+
+impl<B, E, Block, RA> GrandpaApi<Block::Hash, NumberFor<Block>> for Grandpa<B, E, Block, RA> where
+	Block: BlockT<Hash=H256> + 'static,
+	B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+	E: CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+{
+	type Metadata = rpc::Metadata;
+
+	fn round_state(&self) -> Result<RoundState> {
+		self.voter_state.round_state()
+			.map(Into::into)
+			.ok_or(Error::VoterNotRunning)
+	}
+
+	fn best_finalized(&self) -> Result<BlockInfo<Block::Hash, NumberFor<Block>>> {
+		let info = self.client.info().chain;
+		Ok(BlockInfo {
+			hash: info.finalized_hash,
+			number: info.finalized_number,
+		})
+	}
+
+	fn justification(&self, hash: Block::Hash) -> Result<Option<Bytes>> {
+		Ok(self.client.justification(&BlockId::Hash(hash))?.map(Into::into))
+	}
+
+	fn subscribe_justifications(
+		&self,
+		_metadata: Self::Metadata,
+		subscriber: Subscriber<Justification<Block::Hash, NumberFor<Block>>>,
+	) {
+		let stream = self.justification_stream.subscribe()
+			.map(|notification| Ok(Justification {
+				block: BlockInfo {
+					hash: notification.hash,
+					number: notification.number,
+				},
+				justification: notification.justification.into(),
+			}));
+
+		self.subscriptions.add(subscriber, |sink| {
+			sink
+				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+				.send_all(stream)
+				// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
+				.map(|_| ())
+		});
+	}
+
+	fn unsubscribe_justifications(
+		&self,
+		_metadata: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> jsonrpc_core::Result<bool> {
+		Ok(self.subscriptions.cancel(id))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_client::{
+		prelude::*,
+		consensus::BlockOrigin,
+	};
+
+	#[test]
+	fn round_state_requires_a_running_voter() {
+		let runtime = tokio::runtime::Runtime::new().unwrap();
+		let client = Arc::new(test_client::new());
+		let (_, justification_stream) = GrandpaJustificationStream::channel();
+		let api = Grandpa::new(
+			client,
+			SharedVoterState::empty(),
+			justification_stream,
+			Subscriptions::new(Arc::new(runtime.executor())),
+		);
+
+		match api.round_state() {
+			Err(Error::VoterNotRunning) => {},
+			other => panic!("Unexpected round state: {:?}", other),
+		}
+	}
+
+	#[test]
+	fn returns_best_finalized_block_and_its_justification() {
+		let runtime = tokio::runtime::Runtime::new().unwrap();
+		let client = Arc::new(test_client::new());
+		let (_, justification_stream) = GrandpaJustificationStream::channel();
+		let api = Grandpa::new(
+			client.clone(),
+			SharedVoterState::empty(),
+			justification_stream,
+			Subscriptions::new(Arc::new(runtime.executor())),
+		);
+
+		let block = client.new_block(Default::default()).unwrap().bake().unwrap();
+		let hash = block.hash();
+		client.import(BlockOrigin::Own, block).unwrap();
+
+		assert_eq!(api.best_finalized().unwrap(), BlockInfo { hash: client.genesis_hash(), number: 0 });
+		assert_eq!(api.justification(hash).unwrap(), None);
+
+		client.finalize_block(BlockId::Hash(hash), Some(vec![1, 2, 3])).unwrap();
+
+		assert_eq!(api.best_finalized().unwrap(), BlockInfo { hash, number: 1 });
+		assert_eq!(api.justification(hash).unwrap(), Some(vec![1, 2, 3].into()));
+	}
+}
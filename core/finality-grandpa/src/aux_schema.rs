@@ -417,6 +417,61 @@ pub(crate) fn update_consensus_changes<H, N, F, R>(
 	write_aux(&[(CONSENSUS_CHANGES_KEY, set.encode().as_slice())])
 }
 
+/// Overwrite the authority set, voter set state and consensus changes after the
+/// chain has been reverted to the given block.
+///
+/// If the authority set is still the one of `previous`, the voter resumes after
+/// the last round it completed or voted in, based on the revert target, so that
+/// it never signs a round of the set a second time. Otherwise it starts the
+/// rounds of the restored set from scratch.
+pub(crate) fn write_reverted<Block: BlockT, F, R>(
+	set: &AuthoritySet<Block::Hash, NumberFor<Block>>,
+	previous: &VoterSetState<Block>,
+	base: (Block::Hash, NumberFor<Block>),
+	consensus_changes: &ConsensusChanges<Block::Hash, NumberFor<Block>>,
+	write_aux: F,
+) -> R where
+	F: FnOnce(&[(&'static [u8], &[u8])]) -> R,
+{
+	let completed_rounds = previous.completed_rounds();
+	let set_state = if completed_rounds.set_info().0 == set.set_id {
+		let last_voted = match previous {
+			VoterSetState::Live { current_rounds, .. } => current_rounds.iter()
+				.filter(|(_, has_voted)| **has_voted != HasVoted::No)
+				.map(|(round, _)| *round)
+				.max(),
+			VoterSetState::Paused { .. } => None,
+		};
+		let last_round = std::cmp::max(completed_rounds.last().number, last_voted.unwrap_or(0));
+
+		// the blocks the rounds refer to might have been reverted, so only their
+		// numbering is kept.
+		let completed_rounds = CompletedRounds::new(
+			CompletedRound {
+				number: last_round,
+				state: RoundState::genesis(base),
+				base,
+				votes: Vec::new(),
+			},
+			set.set_id,
+			set,
+		);
+
+		let mut current_rounds = CurrentRounds::new();
+		current_rounds.insert(last_round + 1, HasVoted::No);
+
+		VoterSetState::Live { completed_rounds, current_rounds }
+	} else {
+		VoterSetState::<Block>::live(set.set_id, set, base)
+	};
+
+	write_aux(&[
+		(AUTHORITY_SET_KEY, set.encode().as_slice()),
+		(SET_STATE_KEY, set_state.encode().as_slice()),
+		(CONSENSUS_CHANGES_KEY, consensus_changes.encode().as_slice()),
+	])
+}
+
 #[cfg(test)]
 pub(crate) fn load_authorities<B: AuxStore, H: Decode, N: Decode>(backend: &B)
 	-> Option<AuthoritySet<H, N>> {
@@ -608,4 +663,62 @@ mod test {
 			},
 		);
 	}
+
+	#[test]
+	fn write_reverted_never_restarts_a_round_of_the_same_set() {
+		type Block = test_client::runtime::Block;
+
+		let authorities = vec![(AuthorityId::default(), 100)];
+		let set = AuthoritySet::<H256, u64> {
+			current_authorities: authorities,
+			pending_standard_changes: ForkTree::new(),
+			pending_forced_changes: Vec::new(),
+			set_id: 3,
+		};
+		let base = (H256::random(), 10);
+		let round = |number| CompletedRound {
+			number,
+			state: RoundState::genesis((H256::random(), 20)),
+			base: (H256::random(), 20),
+			votes: vec![],
+		};
+		let decode = |values: &[(&'static [u8], &[u8])]| values.iter()
+			.find(|(key, _)| *key == SET_STATE_KEY)
+			.map(|(_, value)| VoterSetState::<Block>::decode(&mut &value[..]).unwrap())
+			.unwrap();
+
+		// the voter completed round 5 and voted in round 6 of the set.
+		let mut current_rounds = CurrentRounds::new();
+		current_rounds.insert(6, HasVoted::Yes(
+			AuthorityId::default(),
+			crate::environment::Vote::Propose(grandpa::PrimaryPropose {
+				target_hash: H256::random(),
+				target_number: 20,
+			}),
+		));
+		current_rounds.insert(7, HasVoted::No);
+		let previous = VoterSetState::<Block>::Live {
+			completed_rounds: CompletedRounds::new(round(5), 3, &set),
+			current_rounds,
+		};
+
+		let reverted = write_reverted::<Block, _, _>(&set, &previous, base, &ConsensusChanges::empty(), decode);
+		let mut expected_rounds = CurrentRounds::new();
+		expected_rounds.insert(7, HasVoted::No);
+		assert_eq!(reverted, VoterSetState::Live {
+			completed_rounds: CompletedRounds::new(
+				CompletedRound { number: 6, state: RoundState::genesis(base), base, votes: vec![] },
+				3,
+				&set,
+			),
+			current_rounds: expected_rounds,
+		});
+
+		// a different set starts its rounds from scratch.
+		let previous = VoterSetState::<Block>::Paused {
+			completed_rounds: CompletedRounds::new(round(5), 4, &set),
+		};
+		let reverted = write_reverted::<Block, _, _>(&set, &previous, base, &ConsensusChanges::empty(), decode);
+		assert_eq!(reverted, VoterSetState::live(3, &set, base));
+	}
 }
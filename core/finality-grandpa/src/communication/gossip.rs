@@ -84,7 +84,7 @@
 
 use sr_primitives::traits::{NumberFor, Block as BlockT, Zero};
 use network::consensus_gossip::{self as network_gossip, MessageIntent, ValidatorContext};
-use network::{config::Roles, PeerId};
+use network::{clock, config::Roles, PeerId};
 use codec::{Encode, Decode};
 use fg_primitives::AuthorityId;
 
@@ -591,7 +591,7 @@ impl<Block: BlockT> Inner<Block> {
 			local_view: None,
 			peers: Peers::default(),
 			live_topics: KeepTopics::new(),
-			next_rebroadcast: Instant::now() + REBROADCAST_AFTER,
+			next_rebroadcast: clock::now() + REBROADCAST_AFTER,
 			authorities: Vec::new(),
 			pending_catch_up: PendingCatchUp::None,
 			catch_up_config,
@@ -984,7 +984,7 @@ impl<Block: BlockT> Inner<Block> {
 		self.pending_catch_up = PendingCatchUp::Requesting {
 			who: who.clone(),
 			request: catch_up_request.clone(),
-			instant: Instant::now(),
+			instant: clock::now(),
 		};
 
 		(true, report)
@@ -1286,7 +1286,7 @@ impl<Block: BlockT> network_gossip::Validator<Block> for GossipValidator<Block>
 			use parking_lot::RwLockWriteGuard;
 
 			let mut inner = self.inner.write();
-			let now = Instant::now();
+			let now = clock::now();
 			let do_rebroadcast = if now >= inner.next_rebroadcast {
 				inner.next_rebroadcast = now + REBROADCAST_AFTER;
 				true
@@ -22,9 +22,9 @@ use codec::Encode;
 use futures::prelude::*;
 use futures::sync::mpsc;
 use log::{debug, warn};
-use tokio_timer::Delay;
 
 use network::PeerId;
+use network::clock::{self, Delay};
 use sr_primitives::traits::{NumberFor, Block as BlockT};
 use super::{gossip::{NeighborPacket, GossipMessage}, Network};
 
@@ -32,7 +32,7 @@ use super::{gossip::{NeighborPacket, GossipMessage}, Network};
 const REBROADCAST_AFTER: Duration = Duration::from_secs(2 * 60);
 
 fn rebroadcast_instant() -> Instant {
-	Instant::now() + REBROADCAST_AFTER
+	clock::now() + REBROADCAST_AFTER
 }
 
 /// A sender used to send neighbor packets to a background job.
@@ -67,7 +67,7 @@ pub(super) fn neighbor_packet_worker<B, N>(net: N) -> (
 {
 	let mut last = None;
 	let (tx, mut rx) = mpsc::unbounded::<(Vec<PeerId>, NeighborPacket<NumberFor<B>>)>();
-	let mut delay = Delay::new(rebroadcast_instant());
+	let mut delay = Delay::new_at(rebroadcast_instant());
 
 	let work = futures::future::poll_fn(move || {
 		loop {
@@ -78,7 +78,7 @@ pub(super) fn neighbor_packet_worker<B, N>(net: N) -> (
 					net.send_message(to.clone(), GossipMessage::<B>::from(packet.clone()).encode());
 
 					// rebroadcasting network.
-					delay.reset(rebroadcast_instant());
+					delay.reset_at(rebroadcast_instant());
 					last = Some((to, packet));
 				}
 				Async::NotReady => break,
@@ -91,10 +91,10 @@ pub(super) fn neighbor_packet_worker<B, N>(net: N) -> (
 			match delay.poll() {
 				Err(e) => {
 					warn!(target: "afg", "Could not rebroadcast neighbor packets: {:?}", e);
-					delay.reset(rebroadcast_instant());
+					delay.reset_at(rebroadcast_instant());
 				}
 				Ok(Async::Ready(())) => {
-					delay.reset(rebroadcast_instant());
+					delay.reset_at(rebroadcast_instant());
 
 					if let Some((ref to, ref packet)) = last {
 						// send to peers.
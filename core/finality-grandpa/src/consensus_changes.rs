@@ -45,6 +45,11 @@ impl<H: Copy + PartialEq, N: Copy + Ord> ConsensusChanges<H, N> {
 		self.pending_changes.insert(idx, at);
 	}
 
+	/// Drop all pending changes signaled above the given block number.
+	pub(crate) fn revert(&mut self, number: N) {
+		self.pending_changes.retain(|change| change.0 <= number);
+	}
+
 	/// Finalize all pending consensus changes that are finalized by given block.
 	/// Returns true if there any changes were finalized.
 	pub(crate) fn finalize<F: Fn(N) -> ::client::error::Result<Option<H>>>(
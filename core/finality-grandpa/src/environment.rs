@@ -17,16 +17,16 @@
 use std::collections::BTreeMap;
 use std::iter::FromIterator;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use log::{debug, warn, info};
 use codec::{Decode, Encode};
 use futures::prelude::*;
-use tokio_timer::Delay;
 use parking_lot::RwLock;
+use network::clock::{self, Delay};
 
 use client::{
-	backend::Backend, apply_aux, BlockchainEvents, CallExecutor,
+	backend::{Backend, ProvideOffchainStorage}, apply_aux, BlockchainEvents, CallExecutor,
 	Client, error::Error as ClientError, utils::is_descendent_of,
 	blockchain::HeaderBackend, backend::Finalizer,
 };
@@ -35,6 +35,7 @@ use grandpa::{
 	voter, voter_set::VoterSet,
 };
 use primitives::{Blake2Hasher, H256, Pair};
+use prometheus::{IntGauge, PrometheusError, Registry, register};
 use sr_primitives::generic::BlockId;
 use sr_primitives::traits::{
 	Block as BlockT, Header as HeaderT, NumberFor, One, Zero,
@@ -51,9 +52,15 @@ use consensus_common::SelectChain;
 use crate::authorities::{AuthoritySet, SharedAuthoritySet};
 use crate::consensus_changes::SharedConsensusChanges;
 use crate::justification::GrandpaJustification;
+use crate::notification::{GrandpaJustificationSender, JustificationNotification};
 use crate::until_imported::UntilVoteTargetImported;
+use crate::voter_state::{SharedVoterState, VoteKind};
 use crate::voting_rule::VotingRule;
-use fg_primitives::{AuthorityId, AuthoritySignature, SetId, RoundNumber};
+use fg_primitives::{
+	AuthorityId, AuthoritySignature, SetId, RoundNumber, BlockEquivocationProof, EquivocationProof,
+	SignedVote, VotePhase, EQUIVOCATIONS_KEY,
+};
+use offchain_primitives::equivocation::queue_equivocation_report;
 
 type HistoricalVotes<Block> = grandpa::HistoricalVotes<
 	<Block as BlockT>::Hash,
@@ -368,6 +375,26 @@ impl<Block: BlockT> SharedVoterSetState<Block> {
 	}
 }
 
+/// Prometheus metrics of the voter.
+#[derive(Clone)]
+pub(crate) struct Metrics {
+	round: IntGauge,
+	set_id: IntGauge,
+}
+
+impl Metrics {
+	pub(crate) fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Metrics {
+			round: register(IntGauge::new(
+				"finality_grandpa_round", "Highest GRANDPA round the voter started",
+			)?, registry)?,
+			set_id: register(IntGauge::new(
+				"finality_grandpa_set_id", "Id of the current GRANDPA authority set",
+			)?, registry)?,
+		})
+	}
+}
+
 /// The environment we run GRANDPA in.
 pub(crate) struct Environment<B, E, Block: BlockT, N: Network<Block>, RA, SC, VR> {
 	pub(crate) client: Arc<Client<B, E, Block, RA>>,
@@ -380,6 +407,9 @@ pub(crate) struct Environment<B, E, Block: BlockT, N: Network<Block>, RA, SC, VR
 	pub(crate) set_id: SetId,
 	pub(crate) voter_set_state: SharedVoterSetState<Block>,
 	pub(crate) voting_rule: VR,
+	pub(crate) voter_state: SharedVoterState,
+	pub(crate) justification_sender: GrandpaJustificationSender<Block>,
+	pub(crate) metrics: Option<Metrics>,
 }
 
 impl<B, E, Block: BlockT, N: Network<Block>, RA, SC, VR> Environment<B, E, Block, N, RA, SC, VR> {
@@ -573,9 +603,9 @@ where
 		&self,
 		round: RoundNumber,
 	) -> voter::RoundData<Self::Id, Self::Timer, Self::In, Self::Out> {
-		let now = Instant::now();
-		let prevote_timer = Delay::new(now + self.config.gossip_duration * 2);
-		let precommit_timer = Delay::new(now + self.config.gossip_duration * 4);
+		let now = clock::now();
+		let prevote_timer = Delay::new_at(now + self.config.gossip_duration * 2);
+		let precommit_timer = Delay::new_at(now + self.config.gossip_duration * 4);
 
 		let local_key = crate::is_voter(&self.voters, &self.config.keystore);
 
@@ -598,6 +628,25 @@ where
 			has_voted,
 		);
 
+		if let Some(metrics) = &self.metrics {
+			metrics.round.set(round as i64);
+			metrics.set_id.set(self.set_id as i64);
+		}
+
+		// keep track of the votes of the latest round for inspection. this
+		// includes our own votes, which are fed back into the incoming stream.
+		self.voter_state.note_round(self.set_id, round, &self.voters);
+		let voter_state = self.voter_state.clone();
+		let set_id = self.set_id;
+		let incoming = incoming.inspect(move |signed| {
+			let kind = match signed.message {
+				grandpa::Message::Prevote(_) => VoteKind::Prevote,
+				grandpa::Message::Precommit(_) => VoteKind::Precommit,
+				grandpa::Message::PrimaryPropose(_) => return,
+			};
+			voter_state.note_vote(set_id, round, &signed.id, kind);
+		});
+
 		// schedule incoming messages from the network to be held until
 		// corresponding blocks are imported.
 		let incoming = Box::new(UntilVoteTargetImported::new(
@@ -820,6 +869,7 @@ where
 			&*self.client,
 			&self.authority_set,
 			&self.consensus_changes,
+			&self.justification_sender,
 			Some(self.config.justification_period.into()),
 			hash,
 			number,
@@ -828,13 +878,9 @@ where
 	}
 
 	fn round_commit_timer(&self) -> Self::Timer {
-		use rand::{thread_rng, Rng};
-
 		//random between 0-1 seconds.
-		let delay: u64 = thread_rng().gen_range(0, 1000);
-		Box::new(Delay::new(
-			Instant::now() + Duration::from_millis(delay)
-		).map_err(|e| Error::Timer(e).into()))
+		let delay = clock::random_delay(Duration::from_secs(1));
+		Box::new(Delay::new(delay).map_err(|e| Error::Timer(e).into()))
 	}
 
 	fn prevote_equivocation(
@@ -843,7 +889,16 @@ where
 		equivocation: ::grandpa::Equivocation<Self::Id, Prevote<Block>, Self::Signature>
 	) {
 		warn!(target: "afg", "Detected prevote equivocation in the finality worker: {:?}", equivocation);
-		// nothing yet; this could craft misbehavior reports of some kind.
+
+		let (first, second) = (equivocation.first, equivocation.second);
+		self.report_equivocation(EquivocationProof {
+			set_id: self.set_id,
+			round: equivocation.round_number,
+			phase: VotePhase::Prevote,
+			offender: equivocation.identity,
+			first: SignedVote { target_hash: first.0.target_hash, target_number: first.0.target_number, signature: first.1 },
+			second: SignedVote { target_hash: second.0.target_hash, target_number: second.0.target_number, signature: second.1 },
+		});
 	}
 
 	fn precommit_equivocation(
@@ -852,7 +907,29 @@ where
 		equivocation: Equivocation<Self::Id, Precommit<Block>, Self::Signature>
 	) {
 		warn!(target: "afg", "Detected precommit equivocation in the finality worker: {:?}", equivocation);
-		// nothing yet
+
+		let (first, second) = (equivocation.first, equivocation.second);
+		self.report_equivocation(EquivocationProof {
+			set_id: self.set_id,
+			round: equivocation.round_number,
+			phase: VotePhase::Precommit,
+			offender: equivocation.identity,
+			first: SignedVote { target_hash: first.0.target_hash, target_number: first.0.target_number, signature: first.1 },
+			second: SignedVote { target_hash: second.0.target_hash, target_number: second.0.target_number, signature: second.1 },
+		});
+	}
+}
+
+impl<B, E, Block: BlockT<Hash=H256>, N: Network<Block>, RA, SC, VR> Environment<B, E, Block, N, RA, SC, VR> where
+	B: Backend<Block, Blake2Hasher>,
+	E: CallExecutor<Block, Blake2Hasher>,
+{
+	/// Queue the given equivocation to be reported on-chain by the offchain worker of the runtime.
+	fn report_equivocation(&self, proof: BlockEquivocationProof<Block>) {
+		match self.client.offchain_storage() {
+			Some(mut storage) => queue_equivocation_report(&mut storage, EQUIVOCATIONS_KEY, proof),
+			None => debug!(target: "afg", "No offchain storage available, equivocation won't be reported"),
+		}
 	}
 }
 
@@ -881,6 +958,7 @@ pub(crate) fn finalize_block<B, Block: BlockT<Hash=H256>, E, RA>(
 	client: &Client<B, E, Block, RA>,
 	authority_set: &SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
 	consensus_changes: &SharedConsensusChanges<Block::Hash, NumberFor<Block>>,
+	justification_sender: &GrandpaJustificationSender<Block>,
 	justification_period: Option<NumberFor<Block>>,
 	hash: Block::Hash,
 	number: NumberFor<Block>,
@@ -921,6 +999,9 @@ pub(crate) fn finalize_block<B, Block: BlockT<Hash=H256>, E, RA>(
 		canonical_at_height(client, (hash, number), true, canon_number)
 	};
 
+	// the justification stored with the block, announced once finality is applied.
+	let mut stored_justification = None;
+
 	let update_res: Result<_, Error> = client.lock_import_and_run(|import_op| {
 		let status = authority_set.apply_standard_changes(
 			hash,
@@ -991,6 +1072,14 @@ pub(crate) fn finalize_block<B, Block: BlockT<Hash=H256>, E, RA>(
 
 		debug!(target: "afg", "Finalizing blocks up to ({:?}, {})", number, hash);
 
+		stored_justification = justification.clone();
+
+		// the justifications of authority set changes are needed to verify the finality of
+		// the following blocks, so they must outlive justification pruning.
+		if justification.is_some() && (status.new_set_block.is_some() || finalizes_consensus_changes) {
+			import_op.mark_justification_required(BlockId::Hash(hash))?;
+		}
+
 		// ideally some handle to a synchronization oracle would be used
 		// to avoid unconditionally notifying.
 		client.apply_finality(import_op, BlockId::Hash(hash), justification, true).map_err(|e| {
@@ -1044,6 +1133,10 @@ pub(crate) fn finalize_block<B, Block: BlockT<Hash=H256>, E, RA>(
 		Ok(new_authorities.map(VoterCommand::ChangeAuthorities))
 	});
 
+	if let (Ok(_), Some(justification)) = (&update_res, stored_justification) {
+		justification_sender.notify(JustificationNotification { hash, number, justification });
+	}
+
 	match update_res {
 		Ok(Some(command)) => Err(CommandOrError::VoterCommand(command)),
 		Ok(None) => Ok(()),
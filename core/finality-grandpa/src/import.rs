@@ -43,6 +43,7 @@ use crate::authorities::{AuthoritySet, SharedAuthoritySet, DelayKind, PendingCha
 use crate::consensus_changes::SharedConsensusChanges;
 use crate::environment::finalize_block;
 use crate::justification::GrandpaJustification;
+use crate::notification::GrandpaJustificationSender;
 
 /// A block-import handler for GRANDPA.
 ///
@@ -59,6 +60,7 @@ pub struct GrandpaBlockImport<B, E, Block: BlockT<Hash=H256>, RA, SC> {
 	authority_set: SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
 	send_voter_commands: mpsc::UnboundedSender<VoterCommand<Block::Hash, NumberFor<Block>>>,
 	consensus_changes: SharedConsensusChanges<Block::Hash, NumberFor<Block>>,
+	justification_sender: GrandpaJustificationSender<Block>,
 }
 
 impl<B, E, Block: BlockT<Hash=H256>, RA, SC: Clone> Clone for
@@ -71,6 +73,7 @@ impl<B, E, Block: BlockT<Hash=H256>, RA, SC: Clone> Clone for
 			authority_set: self.authority_set.clone(),
 			send_voter_commands: self.send_voter_commands.clone(),
 			consensus_changes: self.consensus_changes.clone(),
+			justification_sender: self.justification_sender.clone(),
 		}
 	}
 }
@@ -198,6 +201,32 @@ fn find_forced_change<B: BlockT>(header: &B::Header)
 	header.digest().convert_first(|l| l.try_to(id).and_then(filter_log))
 }
 
+/// Find the authority set change signaled by the given header, if any.
+pub(crate) fn find_pending_change<B: BlockT>(header: &B::Header, hash: B::Hash)
+	-> Option<PendingChange<B::Hash, NumberFor<B>>>
+{
+	// check for forced change.
+	if let Some((median_last_finalized, change)) = find_forced_change::<B>(header) {
+		return Some(PendingChange {
+			next_authorities: change.next_authorities,
+			delay: change.delay,
+			canon_height: *header.number(),
+			canon_hash: hash,
+			delay_kind: DelayKind::Best { median_last_finalized },
+		});
+	}
+
+	// check normal scheduled change.
+	let change = find_scheduled_change::<B>(header)?;
+	Some(PendingChange {
+		next_authorities: change.next_authorities,
+		delay: change.delay,
+		canon_height: *header.number(),
+		canon_hash: hash,
+		delay_kind: DelayKind::Finalized,
+	})
+}
+
 impl<B, E, Block: BlockT<Hash=H256>, RA, SC>
 	GrandpaBlockImport<B, E, Block, RA, SC>
 where
@@ -211,26 +240,7 @@ where
 	fn check_new_change(&self, header: &Block::Header, hash: Block::Hash)
 		-> Option<PendingChange<Block::Hash, NumberFor<Block>>>
 	{
-		// check for forced change.
-		if let Some((median_last_finalized, change)) = find_forced_change::<Block>(header) {
-			return Some(PendingChange {
-				next_authorities: change.next_authorities,
-				delay: change.delay,
-				canon_height: *header.number(),
-				canon_hash: hash,
-				delay_kind: DelayKind::Best { median_last_finalized },
-			});
-		}
-
-		// check normal scheduled change.
-		let change = find_scheduled_change::<Block>(header)?;
-		Some(PendingChange {
-			next_authorities: change.next_authorities,
-			delay: change.delay,
-			canon_height: *header.number(),
-			canon_hash: hash,
-			delay_kind: DelayKind::Finalized,
-		})
+		find_pending_change::<Block>(header, hash)
 	}
 
 	fn make_authorities_changes<'a>(&'a self, block: &mut BlockImportParams<Block>, hash: Block::Hash)
@@ -515,6 +525,7 @@ impl<B, E, Block: BlockT<Hash=H256>, RA, SC>
 		authority_set: SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
 		send_voter_commands: mpsc::UnboundedSender<VoterCommand<Block::Hash, NumberFor<Block>>>,
 		consensus_changes: SharedConsensusChanges<Block::Hash, NumberFor<Block>>,
+		justification_sender: GrandpaJustificationSender<Block>,
 	) -> GrandpaBlockImport<B, E, Block, RA, SC> {
 		GrandpaBlockImport {
 			inner,
@@ -522,6 +533,7 @@ impl<B, E, Block: BlockT<Hash=H256>, RA, SC>
 			authority_set,
 			send_voter_commands,
 			consensus_changes,
+			justification_sender,
 		}
 	}
 }
@@ -562,6 +574,7 @@ where
 			&*self.inner,
 			&self.authority_set,
 			&self.consensus_changes,
+			&self.justification_sender,
 			None,
 			hash,
 			number,
@@ -53,16 +53,16 @@
 //! included in the newly-finalized chain.
 
 use futures::prelude::*;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use futures::sync::mpsc;
 use client::{
 	BlockchainEvents, CallExecutor, Client, backend::Backend, error::Error as ClientError,
-	ExecutionStrategy,
+	ExecutionStrategy, utils::is_descendent_of,
 };
 use client::blockchain::HeaderBackend;
 use codec::{Decode, Encode};
 use sr_primitives::generic::BlockId;
-use sr_primitives::traits::{NumberFor, Block as BlockT, DigestFor, Zero};
+use sr_primitives::traits::{NumberFor, Block as BlockT, DigestFor, Header as HeaderT, One, Zero};
 use keystore::KeyStorePtr;
 use inherents::InherentDataProviders;
 use consensus_common::SelectChain;
@@ -88,17 +88,24 @@ mod finality_proof;
 mod import;
 mod justification;
 mod light_import;
+mod notification;
 mod observer;
 mod until_imported;
+mod voter_state;
 mod voting_rule;
 
 pub use communication::Network;
 pub use finality_proof::FinalityProofProvider;
 pub use justification::GrandpaJustification;
 pub use light_import::light_block_import;
+pub use notification::{
+	GrandpaJustificationSender, GrandpaJustificationStream, JustificationNotification,
+};
 pub use observer::run_grandpa_observer;
+pub use voter_state::{RoundState, SharedVoterState};
 pub use voting_rule::{
-	BeforeBestBlock, ThreeQuartersOfTheUnfinalizedChain, VotingRule, VotingRulesBuilder
+	BeforeBestBlock, ThreeQuartersOfTheUnfinalizedChain, RuntimeVotingRule, VotingRule,
+	VotingRulesBuilder, NAMED_VOTING_RULES,
 };
 
 use aux_schema::PersistentData;
@@ -232,7 +239,7 @@ pub enum Error {
 	/// An invariant has been violated (e.g. not finalizing pending change blocks in-order)
 	Safety(String),
 	/// A timer failed to fire.
-	Timer(tokio_timer::Error),
+	Timer(std::io::Error),
 }
 
 impl From<GrandpaError> for Error {
@@ -363,6 +370,21 @@ pub struct LinkHalf<B, E, Block: BlockT<Hash=H256>, RA, SC> {
 	select_chain: SC,
 	persistent_data: PersistentData<Block>,
 	voter_commands_rx: mpsc::UnboundedReceiver<VoterCommand<Block::Hash, NumberFor<Block>>>,
+	justification_sender: GrandpaJustificationSender<Block>,
+	justification_stream: GrandpaJustificationStream<Block>,
+	voter_state: SharedVoterState,
+}
+
+impl<B, E, Block: BlockT<Hash=H256>, RA, SC> LinkHalf<B, E, Block, RA, SC> {
+	/// Get a handle to the state of the rounds of the voter driven by this link.
+	pub fn shared_voter_state(&self) -> SharedVoterState {
+		self.voter_state.clone()
+	}
+
+	/// Get a handle to subscribe to the justifications stored by this node.
+	pub fn justification_stream(&self) -> GrandpaJustificationStream<Block> {
+		self.justification_stream.clone()
+	}
 }
 
 /// Provider for the Grandpa authority set configured on the genesis block.
@@ -378,24 +400,147 @@ impl<B, E, Block: BlockT<Hash=H256>, RA> GenesisAuthoritySetProvider<Block> for
 		RA: Send + Sync,
 {
 	fn get(&self) -> Result<AuthorityList, ClientError> {
-		// This implementation uses the Grandpa runtime API instead of reading directly from the
-		// `GRANDPA_AUTHORITIES_KEY` as the data may have been migrated since the genesis block of
-		// the chain, whereas the runtime API is backwards compatible.
-		self.executor()
-			.call(
-				&BlockId::Number(Zero::zero()),
-				"GrandpaApi_grandpa_authorities",
-				&[],
-				ExecutionStrategy::NativeElseWasm,
-				None,
-			)
-			.and_then(|call_result| {
-				Decode::decode(&mut &call_result[..])
-					.map_err(|err| ClientError::CallResultDecode(
-						"failed to decode GRANDPA authorities set proof".into(), err
-					))
-			})
+		authorities_at(self, &BlockId::Number(Zero::zero()))
+	}
+}
+
+/// Fetch the GRANDPA authorities from the runtime state at the given block.
+fn authorities_at<B, E, Block: BlockT<Hash=H256>, RA>(
+	client: &Client<B, E, Block, RA>,
+	at: &BlockId<Block>,
+) -> Result<AuthorityList, ClientError>
+	where
+		B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+		E: CallExecutor<Block, Blake2Hasher> + 'static + Clone + Send + Sync,
+		RA: Send + Sync,
+{
+	// This implementation uses the Grandpa runtime API instead of reading directly from the
+	// `GRANDPA_AUTHORITIES_KEY` as the data may have been migrated since the genesis block of
+	// the chain, whereas the runtime API is backwards compatible.
+	client.executor()
+		.call(
+			at,
+			"GrandpaApi_grandpa_authorities",
+			&[],
+			ExecutionStrategy::NativeElseWasm,
+			None,
+		)
+		.and_then(|call_result| {
+			Decode::decode(&mut &call_result[..])
+				.map_err(|err| ClientError::CallResultDecode(
+					"failed to decode GRANDPA authorities set proof".into(), err
+				))
+		})
+}
+
+/// Rewind the persisted GRANDPA state so that it matches the chain once the
+/// best chain has been reverted to the given block, which may be below the last
+/// finalized block.
+///
+/// Authority set changes that were enacted above the target are undone, the set
+/// id is decreased accordingly and the authorities are re-read from the runtime
+/// state at the target (which must still be available). Changes signaled above
+/// the target are dropped, while a change signaled at or below it whose
+/// enactment is reverted becomes pending again. The voter restarts from a blank
+/// round based on the target block.
+///
+/// Nothing is written: the returned auxiliary data must be written along with the
+/// reverted blocks, see `Client::unsafe_revert`. This must be called before the
+/// blocks are removed from the backend.
+pub fn revert_aux<B, E, Block: BlockT<Hash=H256>, RA>(
+	client: &Client<B, E, Block, RA>,
+	hash: Block::Hash,
+	number: NumberFor<Block>,
+) -> Result<Vec<(Vec<u8>, Option<Vec<u8>>)>, ClientError>
+	where
+		B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+		E: CallExecutor<Block, Blake2Hasher> + 'static + Clone + Send + Sync,
+		RA: Send + Sync,
+{
+	let info = client.info().chain;
+	let persistent_data = aux_schema::load_persistent(
+		client,
+		info.genesis_hash,
+		<NumberFor<Block>>::zero(),
+		|| authorities_at(client, &BlockId::Number(Zero::zero())),
+	)?;
+
+	let mut set = persistent_data.authority_set.inner().read().clone();
+	let mut consensus_changes = persistent_data.consensus_changes.lock().clone();
+	let pending = set.pending_changes().map(|change| change.canon_hash).collect::<Vec<_>>();
+
+	let header_at = |n: NumberFor<Block>| client.header(&BlockId::Number(n))?
+		.ok_or_else(|| ClientError::UnknownBlock(format!("Missing header of block #{}", n)));
+
+	// every change signaled above the target which is no longer pending has
+	// been enacted and must be undone.
+	let mut enacted = 0u64;
+	let mut current = info.best_number;
+	while current > number {
+		let header = header_at(current)?;
+		let header_hash = header.hash();
+		if import::find_pending_change::<Block>(&header, header_hash).is_some()
+			&& !pending.contains(&header_hash)
+		{
+			enacted += 1;
+		}
+		current -= One::one();
+	}
+
+	let target = header_at(number)?;
+	if target.hash() != hash {
+		return Err(ClientError::Msg(
+			format!("Block #{} is not {:?} on the best chain", number, hash)
+		));
 	}
+
+	// the last change signaled at or below the target might have been enacted
+	// above it, in which case it becomes pending again.
+	let mut restored = None;
+	let mut current = number;
+	loop {
+		let header = header_at(current)?;
+		if let Some(change) = import::find_pending_change::<Block>(&header, header.hash()) {
+			if change.effective_number() > number && !pending.contains(&change.canon_hash) {
+				enacted += 1;
+				restored = Some(change);
+			}
+			break;
+		}
+
+		if current.is_zero() {
+			break;
+		}
+		current -= One::one();
+	}
+
+	set.set_id = set.set_id.checked_sub(enacted).ok_or_else(|| ClientError::Msg(
+		format!("Cannot undo {} GRANDPA authority set changes at set id {}", enacted, set.set_id)
+	))?;
+	if enacted > 0 {
+		set.current_authorities = authorities_at(client, &BlockId::Hash(hash))?;
+	}
+
+	set.pending_standard_changes.revert(&number);
+	set.pending_forced_changes.retain(|change| change.canon_height <= number);
+	if let Some(change) = restored {
+		set.add_pending_change(change, &is_descendent_of::<_, _, Block::Hash>(client, None))
+			.map_err(|e| ClientError::Msg(format!("{:?}", e)))?;
+	}
+	consensus_changes.revert(number);
+
+	let aux = aux_schema::write_reverted::<Block, _, _>(
+		&set,
+		&*persistent_data.set_state.read(),
+		(hash, number),
+		&consensus_changes,
+		|values| values.iter().map(|(key, value)| (key.to_vec(), Some(value.to_vec()))).collect(),
+	);
+
+	info!(target: "afg", "Reverting GRANDPA state to block #{} ({:?}), undoing {} authority set changes",
+		number, hash, enacted);
+
+	Ok(aux)
 }
 
 /// Make block importer and link half necessary to tie the background voter
@@ -431,6 +576,7 @@ where
 	)?;
 
 	let (voter_commands_tx, voter_commands_rx) = mpsc::unbounded();
+	let (justification_sender, justification_stream) = GrandpaJustificationStream::channel();
 
 	Ok((
 		GrandpaBlockImport::new(
@@ -439,12 +585,16 @@ where
 			persistent_data.authority_set.clone(),
 			voter_commands_tx,
 			persistent_data.consensus_changes.clone(),
+			justification_sender.clone(),
 		),
 		LinkHalf {
 			client,
 			select_chain,
 			persistent_data,
 			voter_commands_rx,
+			justification_sender,
+			justification_stream,
+			voter_state: SharedVoterState::empty(),
 		},
 	))
 }
@@ -540,6 +690,8 @@ pub struct GrandpaParams<B, E, Block: BlockT<Hash=H256>, N, RA, SC, VR, X> {
 	pub telemetry_on_connect: Option<mpsc::UnboundedReceiver<()>>,
 	/// A voting rule used to potentially restrict target votes.
 	pub voting_rule: VR,
+	/// Registry to report the progress of the voter to, if any.
+	pub prometheus_registry: Option<prometheus::Registry>,
 }
 
 /// Run a GRANDPA voter as a task. Provide configuration and a link to a
@@ -567,6 +719,7 @@ pub fn run_grandpa_voter<B, E, Block: BlockT<Hash=H256>, N, RA, SC, VR, X>(
 		on_exit,
 		telemetry_on_connect,
 		voting_rule,
+		prometheus_registry,
 	} = grandpa_params;
 
 	let LinkHalf {
@@ -574,6 +727,9 @@ pub fn run_grandpa_voter<B, E, Block: BlockT<Hash=H256>, N, RA, SC, VR, X>(
 		select_chain,
 		persistent_data,
 		voter_commands_rx,
+		justification_sender,
+		justification_stream: _,
+		voter_state,
 	} = link;
 
 	let (network, network_startup) = NetworkBridge::new(
@@ -613,6 +769,12 @@ pub fn run_grandpa_voter<B, E, Block: BlockT<Hash=H256>, N, RA, SC, VR, X>(
 		futures::future::Either::B(futures::future::empty())
 	};
 
+	let metrics = prometheus_registry.and_then(|registry| {
+		environment::Metrics::register(&registry)
+			.map_err(|e| warn!(target: "afg", "Failed to register GRANDPA metrics: {}", e))
+			.ok()
+	});
+
 	let voter_work = VoterWork::new(
 		client,
 		config,
@@ -621,6 +783,9 @@ pub fn run_grandpa_voter<B, E, Block: BlockT<Hash=H256>, N, RA, SC, VR, X>(
 		voting_rule,
 		persistent_data,
 		voter_commands_rx,
+		voter_state,
+		justification_sender,
+		metrics,
 	);
 
 	let voter_work = voter_work
@@ -667,6 +832,9 @@ where
 		voting_rule: VR,
 		persistent_data: PersistentData<Block>,
 		voter_commands_rx: mpsc::UnboundedReceiver<VoterCommand<Block::Hash, NumberFor<Block>>>,
+		voter_state: SharedVoterState,
+		justification_sender: GrandpaJustificationSender<Block>,
+		metrics: Option<environment::Metrics>,
 	) -> Self {
 
 		let voters = persistent_data.authority_set.current_authorities();
@@ -681,6 +849,9 @@ where
 			authority_set: persistent_data.authority_set.clone(),
 			consensus_changes: persistent_data.consensus_changes.clone(),
 			voter_set_state: persistent_data.set_state.clone(),
+			voter_state,
+			justification_sender,
+			metrics,
 		});
 
 		let mut work = VoterWork {
@@ -798,6 +969,9 @@ where
 					consensus_changes: self.env.consensus_changes.clone(),
 					network: self.env.network.clone(),
 					voting_rule: self.env.voting_rule.clone(),
+					voter_state: self.env.voter_state.clone(),
+					justification_sender: self.env.justification_sender.clone(),
+					metrics: self.env.metrics.clone(),
 				});
 
 				self.rebuild_voter();
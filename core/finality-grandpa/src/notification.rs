@@ -0,0 +1,86 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Notifications about the GRANDPA justifications stored by this node.
+
+use std::sync::Arc;
+
+use futures::sync::mpsc;
+use parking_lot::Mutex;
+use sr_primitives::Justification;
+use sr_primitives::traits::{Block as BlockT, NumberFor};
+
+/// A justification that has been stored alongside the block it finalizes.
+#[derive(Clone, Debug)]
+pub struct JustificationNotification<Block: BlockT> {
+	/// Hash of the finalized block.
+	pub hash: Block::Hash,
+	/// Number of the finalized block.
+	pub number: NumberFor<Block>,
+	/// The encoded `GrandpaJustification`.
+	pub justification: Justification,
+}
+
+type Subscribers<Block> = Arc<Mutex<Vec<mpsc::UnboundedSender<JustificationNotification<Block>>>>>;
+
+/// The sending half of the justification notifications, used by the voter,
+/// the observer and the block import whenever they store a justification.
+pub struct GrandpaJustificationSender<Block: BlockT> {
+	subscribers: Subscribers<Block>,
+}
+
+impl<Block: BlockT> Clone for GrandpaJustificationSender<Block> {
+	fn clone(&self) -> Self {
+		GrandpaJustificationSender { subscribers: self.subscribers.clone() }
+	}
+}
+
+impl<Block: BlockT> GrandpaJustificationSender<Block> {
+	/// Send a notification to all subscribers, dropping the ones that have gone away.
+	pub(crate) fn notify(&self, notification: JustificationNotification<Block>) {
+		self.subscribers.lock()
+			.retain(|subscriber| subscriber.unbounded_send(notification.clone()).is_ok());
+	}
+}
+
+/// A handle to subscribe to the justifications stored by this node.
+pub struct GrandpaJustificationStream<Block: BlockT> {
+	subscribers: Subscribers<Block>,
+}
+
+impl<Block: BlockT> Clone for GrandpaJustificationStream<Block> {
+	fn clone(&self) -> Self {
+		GrandpaJustificationStream { subscribers: self.subscribers.clone() }
+	}
+}
+
+impl<Block: BlockT> GrandpaJustificationStream<Block> {
+	/// Create a new pair of connected sender and stream handles.
+	pub fn channel() -> (GrandpaJustificationSender<Block>, Self) {
+		let subscribers = Subscribers::<Block>::default();
+		(
+			GrandpaJustificationSender { subscribers: subscribers.clone() },
+			GrandpaJustificationStream { subscribers },
+		)
+	}
+
+	/// Subscribe to the justifications stored from now on.
+	pub fn subscribe(&self) -> mpsc::UnboundedReceiver<JustificationNotification<Block>> {
+		let (tx, rx) = mpsc::unbounded();
+		self.subscribers.lock().push(tx);
+		rx
+	}
+}
@@ -36,6 +36,7 @@ use crate::{
 use crate::authorities::SharedAuthoritySet;
 use crate::communication::NetworkBridge;
 use crate::consensus_changes::SharedConsensusChanges;
+use crate::notification::GrandpaJustificationSender;
 use fg_primitives::AuthorityId;
 
 struct ObserverChain<'a, Block: BlockT, B, E, RA>(&'a Client<B, E, Block, RA>);
@@ -60,6 +61,7 @@ fn grandpa_observer<B, E, Block: BlockT<Hash=H256>, RA, S, F>(
 	client: &Arc<Client<B, E, Block, RA>>,
 	authority_set: &SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
 	consensus_changes: &SharedConsensusChanges<Block::Hash, NumberFor<Block>>,
+	justification_sender: &GrandpaJustificationSender<Block>,
 	voters: &Arc<VoterSet<AuthorityId>>,
 	last_finalized_number: NumberFor<Block>,
 	commits: S,
@@ -77,6 +79,7 @@ fn grandpa_observer<B, E, Block: BlockT<Hash=H256>, RA, S, F>(
 {
 	let authority_set = authority_set.clone();
 	let consensus_changes = consensus_changes.clone();
+	let justification_sender = justification_sender.clone();
 	let client = client.clone();
 	let voters = voters.clone();
 
@@ -116,6 +119,7 @@ fn grandpa_observer<B, E, Block: BlockT<Hash=H256>, RA, S, F>(
 				&client,
 				&authority_set,
 				&consensus_changes,
+				&justification_sender,
 				None,
 				finalized_hash,
 				finalized_number,
@@ -169,6 +173,8 @@ pub fn run_grandpa_observer<B, E, Block: BlockT<Hash=H256>, N, RA, SC>(
 		select_chain: _,
 		persistent_data,
 		voter_commands_rx,
+		justification_sender,
+		voter_state: _,
 	} = link;
 
 	let (network, network_startup) = NetworkBridge::new(
@@ -183,7 +189,8 @@ pub fn run_grandpa_observer<B, E, Block: BlockT<Hash=H256>, N, RA, SC>(
 		network,
 		persistent_data,
 		config.keystore.clone(),
-		voter_commands_rx
+		voter_commands_rx,
+		justification_sender,
 	);
 
 	let observer_work = observer_work
@@ -206,6 +213,7 @@ struct ObserverWork<B: BlockT<Hash=H256>, N: Network<B>, E, Backend, RA> {
 	persistent_data: PersistentData<B>,
 	keystore: Option<keystore::KeyStorePtr>,
 	voter_commands_rx: mpsc::UnboundedReceiver<VoterCommand<B::Hash, NumberFor<B>>>,
+	justification_sender: GrandpaJustificationSender<B>,
 }
 
 impl<B, N, E, Bk, RA> ObserverWork<B, N, E, Bk, RA>
@@ -224,6 +232,7 @@ where
 		persistent_data: PersistentData<B>,
 		keystore: Option<keystore::KeyStorePtr>,
 		voter_commands_rx: mpsc::UnboundedReceiver<VoterCommand<B::Hash, NumberFor<B>>>,
+		justification_sender: GrandpaJustificationSender<B>,
 	) -> Self {
 
 		let mut work = ObserverWork {
@@ -235,6 +244,7 @@ where
 			persistent_data,
 			keystore,
 			voter_commands_rx,
+			justification_sender,
 		};
 		work.rebuild_observer();
 		work
@@ -278,6 +288,7 @@ where
 			&self.client,
 			&self.persistent_data.authority_set,
 			&self.persistent_data.consensus_changes,
+			&self.justification_sender,
 			&voters,
 			last_finalized_number,
 			global_in,
@@ -20,6 +20,7 @@ use super::*;
 use environment::HasVoted;
 use network::test::{Block, DummySpecialization, Hash, TestNetFactory, Peer, PeersClient};
 use network::test::{PassThroughVerifier};
+use network::simulation::{SimulatedNetwork, LinkConditions};
 use network::config::{ProtocolConfig, Roles, BoxFinalityProofRequestBuilder};
 use parking_lot::Mutex;
 use futures03::{StreamExt as _, TryStreamExt as _};
@@ -63,13 +64,26 @@ type GrandpaPeer = Peer<PeerData, DummySpecialization>;
 struct GrandpaTestNet {
 	peers: Vec<GrandpaPeer>,
 	test_config: TestApi,
+	simulation: Option<SimulatedNetwork>,
 }
 
 impl GrandpaTestNet {
 	fn new(test_config: TestApi, n_peers: usize) -> Self {
+		Self::new_with_simulation(test_config, n_peers, None)
+	}
+
+	fn new_with_simulation(
+		test_config: TestApi,
+		n_peers: usize,
+		simulation: Option<SimulatedNetwork>,
+	) -> Self {
+		if let Some(ref simulation) = simulation {
+			simulation.install_clock();
+		}
 		let mut net = GrandpaTestNet {
 			peers: Vec::with_capacity(n_peers),
 			test_config,
+			simulation,
 		};
 		let config = Self::default_config();
 		for _ in 0..n_peers {
@@ -89,9 +103,14 @@ impl TestNetFactory for GrandpaTestNet {
 		GrandpaTestNet {
 			peers: Vec::new(),
 			test_config: Default::default(),
+			simulation: None,
 		}
 	}
 
+	fn simulation(&self) -> Option<SimulatedNetwork> {
+		self.simulation.clone()
+	}
+
 	fn default_config() -> ProtocolConfig {
 		// the authority role ensures gossip hits all nodes here.
 		let mut config = ProtocolConfig::default();
@@ -405,6 +424,7 @@ fn run_to_completion_with<F>(
 			on_exit: Exit,
 			telemetry_on_connect: None,
 			voting_rule: (),
+			prometheus_registry: None,
 		};
 		let voter = run_grandpa_voter(grandpa_params).expect("all in order with client and network");
 
@@ -478,6 +498,28 @@ fn finalize_3_voters_no_observers() {
 	);
 }
 
+#[test]
+fn finalize_3_voters_over_lossy_links() {
+	let _ = env_logger::try_init();
+	let mut runtime = current_thread::Runtime::new().unwrap();
+	let peers = &[Ed25519Keyring::Alice, Ed25519Keyring::Bob, Ed25519Keyring::Charlie];
+	let voters = make_ids(peers);
+
+	let simulation = SimulatedNetwork::new(42);
+	simulation.set_default_conditions(LinkConditions {
+		latency: Duration::from_millis(150),
+		bandwidth: Some(500_000),
+		packet_loss: 0.05,
+		retransmit_timeout: Duration::from_millis(300),
+	});
+	let mut net = GrandpaTestNet::new_with_simulation(TestApi::new(voters), 3, Some(simulation));
+	net.peer(0).push_blocks(20, false);
+	net.block_until_sync(&mut runtime);
+
+	let net = Arc::new(Mutex::new(net));
+	assert_eq!(run_to_completion(&mut runtime, 20, net.clone(), peers), 20);
+}
+
 #[test]
 fn finalize_3_voters_1_full_observer() {
 	let mut runtime = current_thread::Runtime::new().unwrap();
@@ -539,6 +581,7 @@ fn finalize_3_voters_1_full_observer() {
 			on_exit: Exit,
 			telemetry_on_connect: None,
 			voting_rule: (),
+			prometheus_registry: None,
 		};
 		let voter = run_grandpa_voter(grandpa_params).expect("all in order with client and network");
 
@@ -702,6 +745,7 @@ fn transition_3_voters_twice_1_full_observer() {
 			on_exit: Exit,
 			telemetry_on_connect: None,
 			voting_rule: (),
+			prometheus_registry: None,
 		};
 		let voter = run_grandpa_voter(grandpa_params).expect("all in order with client and network");
 
@@ -755,6 +799,41 @@ fn justification_is_generated_periodically() {
 	}
 }
 
+#[test]
+fn justifications_and_round_state_are_exposed() {
+	let mut runtime = current_thread::Runtime::new().unwrap();
+	let peers = &[Ed25519Keyring::Alice, Ed25519Keyring::Bob, Ed25519Keyring::Charlie];
+	let voters = make_ids(peers);
+
+	let mut net = GrandpaTestNet::new(TestApi::new(voters), 3);
+	net.peer(0).push_blocks(32, false);
+	net.block_until_sync(&mut runtime);
+
+	let (justifications, voter_state) = {
+		let link = net.peers[0].data.lock();
+		let link = link.as_ref().expect("link initialized at startup; qed");
+		(link.justification_stream().subscribe(), link.shared_voter_state())
+	};
+
+	let net = Arc::new(Mutex::new(net));
+	run_to_completion(&mut runtime, 32, net.clone(), peers);
+
+	// the periodic justification of block#32 is announced to subscribers
+	let (notification, _) = runtime.block_on(justifications.into_future()).map_err(|_| ()).unwrap();
+	let notification = notification.unwrap();
+	assert_eq!(notification.number, 32);
+	assert_eq!(
+		Some(notification.justification),
+		net.lock().peer(0).client().justification(&BlockId::Number(32)).unwrap(),
+	);
+
+	// and the voter has been tracking the votes of its rounds
+	let round_state = voter_state.round_state().unwrap();
+	assert_eq!(round_state.set_id, 0);
+	assert!(round_state.round >= 1);
+	assert_eq!(round_state.total_weight, 3);
+}
+
 #[test]
 fn consensus_changes_works() {
 	let mut changes = ConsensusChanges::<H256, u64>::empty();
@@ -996,6 +1075,57 @@ fn allows_reimporting_change_blocks() {
 	);
 }
 
+#[test]
+fn revert_drops_changes_signaled_above_target() {
+	let peers_a = &[Ed25519Keyring::Alice, Ed25519Keyring::Bob, Ed25519Keyring::Charlie];
+	let peers_b = &[Ed25519Keyring::Alice, Ed25519Keyring::Bob];
+	let voters = make_ids(peers_a);
+	let api = TestApi::new(voters);
+	let mut net = GrandpaTestNet::new(api.clone(), 3);
+
+	let client = net.peer(0).client().clone();
+	let (mut block_import, ..) = net.make_block_import(client.clone());
+
+	let full_client = client.as_full().unwrap();
+	let genesis_hash = full_client.info().chain.genesis_hash;
+	let builder = full_client.new_block_at(&BlockId::Number(0), Default::default()).unwrap();
+	let mut block = builder.bake().unwrap();
+	add_scheduled_change(&mut block, ScheduledChange {
+		next_authorities: make_ids(peers_b),
+		delay: 0,
+	});
+
+	block_import.import_block(BlockImportParams {
+		origin: BlockOrigin::File,
+		header: block.header,
+		justification: None,
+		post_digests: Vec::new(),
+		body: Some(block.extrinsics),
+		finalized: false,
+		auxiliary: Vec::new(),
+		fork_choice: ForkChoiceStrategy::LongestChain,
+		allow_missing_state: false,
+	}, HashMap::new()).unwrap();
+
+	let set: AuthoritySet<Hash, BlockNumber> = crate::aux_schema::load_authorities(&*full_client).unwrap();
+	assert_eq!(set.pending_changes().count(), 1);
+
+	// reverting to a block which isn't on the best chain fails.
+	assert!(crate::revert_aux(&*full_client, Default::default(), 0).is_err());
+
+	// nothing is written until the blocks are reverted.
+	let aux = crate::revert_aux(&*full_client, genesis_hash, 0).unwrap();
+	let set: AuthoritySet<Hash, BlockNumber> = crate::aux_schema::load_authorities(&*full_client).unwrap();
+	assert_eq!(set.pending_changes().count(), 1);
+
+	full_client.unsafe_revert(1, aux).unwrap();
+	assert_eq!(full_client.info().chain.best_hash, genesis_hash);
+
+	let set: AuthoritySet<Hash, BlockNumber> = crate::aux_schema::load_authorities(&*full_client).unwrap();
+	assert_eq!(set.current(), (0, make_ids(peers_a).as_slice()));
+	assert_eq!(set.pending_changes().count(), 0);
+}
+
 #[test]
 fn test_bad_justification() {
 	let peers_a = &[Ed25519Keyring::Alice, Ed25519Keyring::Bob, Ed25519Keyring::Charlie];
@@ -1130,6 +1260,7 @@ fn voter_persists_its_votes() {
 							on_exit: Exit,
 							telemetry_on_connect: None,
 							voting_rule: VotingRulesBuilder::default().build(),
+							prometheus_registry: None,
 						};
 
 						let voter = run_grandpa_voter(grandpa_params)
@@ -1466,6 +1597,7 @@ fn voter_catches_up_to_latest_round_when_behind() {
 			on_exit: Exit,
 			telemetry_on_connect: None,
 			voting_rule: (),
+			prometheus_registry: None,
 		};
 
 		Box::new(run_grandpa_voter(grandpa_params).expect("all in order with client and network"))
@@ -1597,6 +1729,9 @@ fn grandpa_environment_respects_voting_rules() {
 			voters: Arc::new(authority_set.current_authorities()),
 			network,
 			voting_rule,
+			voter_state: link.voter_state.clone(),
+			justification_sender: link.justification_sender.clone(),
+			metrics: None,
 		}
 	};
 
@@ -1617,6 +1752,11 @@ fn grandpa_environment_respects_voting_rules() {
 		VotingRulesBuilder::default().build()
 	));
 
+	// the test runtime doesn't restrict votes until told to, so neither does the runtime rule
+	let runtime_env = environment(Box::new(
+		VotingRulesBuilder::new().add_named(&["runtime"]).unwrap().build()
+	));
+
 	// the unrestricted environment should just return the best block
 	assert_eq!(
 		unrestricted_env.best_chain_containing(
@@ -1625,6 +1765,13 @@ fn grandpa_environment_respects_voting_rules() {
 		20,
 	);
 
+	assert_eq!(
+		runtime_env.best_chain_containing(
+			peer.client().info().chain.finalized_hash
+		).unwrap().1,
+		20,
+	);
+
 	// both the other environments should return block 15, which is 3/4 of the
 	// way in the unfinalized chain
 	assert_eq!(
@@ -1660,6 +1807,48 @@ fn grandpa_environment_respects_voting_rules() {
 		).unwrap().1,
 		19,
 	);
+
+	// the runtime now only allows votes up to block 20, on top of which we build block 21.
+	peer.generate_blocks(1, BlockOrigin::File, |mut builder| {
+		use codec::Encode;
+		use test_client::BlockBuilderExt;
+
+		builder.push_storage_change(
+			test_client::runtime::system::MAX_VOTE_TARGET_KEY.to_vec(),
+			Some(20u64.encode()),
+		).unwrap();
+		builder.bake().unwrap()
+	});
+
+	assert_eq!(
+		unrestricted_env.best_chain_containing(
+			peer.client().info().chain.finalized_hash
+		).unwrap().1,
+		21,
+	);
+
+	assert_eq!(
+		runtime_env.best_chain_containing(
+			peer.client().info().chain.finalized_hash
+		).unwrap().1,
+		20,
+	);
+}
+
+#[test]
+fn voting_rules_are_added_by_name() {
+	use network::test::TestClient;
+
+	assert!(VotingRulesBuilder::<Block, TestClient>::new().add_named(NAMED_VOTING_RULES).is_ok());
+	assert!(VotingRulesBuilder::<Block, TestClient>::from_names(Vec::<String>::new()).is_ok());
+	assert_eq!(
+		VotingRulesBuilder::<Block, TestClient>::from_names(&["runtime", "unknown"]).err(),
+		Some("unknown".to_string()),
+	);
+	assert_eq!(
+		VotingRulesBuilder::<Block, TestClient>::new().add_named(&["unknown"]).err(),
+		Some("unknown".to_string()),
+	);
 }
 
 #[test]
@@ -1743,3 +1932,45 @@ fn imports_justification_for_regular_blocks_on_import() {
 		client.justification(&BlockId::Hash(block_hash)).unwrap().is_some(),
 	);
 }
+
+#[test]
+fn equivocation_proofs_are_checkable_and_queued_once() {
+	use client::backend::OffchainStorage;
+	use fg_primitives::{EquivocationProof, SignedVote, VotePhase, EQUIVOCATIONS_KEY};
+	use offchain_primitives::{STORAGE_PREFIX, equivocation::queue_equivocation_report};
+
+	let peer = Ed25519Keyring::Alice;
+	let (round, set_id) = (2, 1);
+
+	// sign the votes the same way the voter does.
+	let vote = |target_hash: Hash, target_number: BlockNumber| {
+		let msg = grandpa::Message::Prevote(grandpa::Prevote { target_hash, target_number });
+		let encoded = communication::localized_payload(round, set_id, &msg);
+		SignedVote { target_hash, target_number, signature: peer.sign(&encoded[..]).into() }
+	};
+
+	let proof = EquivocationProof {
+		set_id,
+		round,
+		phase: VotePhase::Prevote,
+		offender: peer.public().into(),
+		first: vote(Hash::repeat_byte(1), 1),
+		second: vote(Hash::repeat_byte(2), 1),
+	};
+	assert!(proof.check());
+
+	// the signatures don't hold for a different phase.
+	let mut precommit_proof = proof.clone();
+	precommit_proof.phase = VotePhase::Precommit;
+	assert!(!precommit_proof.check());
+
+	let mut storage = client::in_mem::OffchainStorage::default();
+	queue_equivocation_report(&mut storage, EQUIVOCATIONS_KEY, proof.clone());
+	queue_equivocation_report(&mut storage, EQUIVOCATIONS_KEY, proof.clone());
+
+	let queued = storage.get(STORAGE_PREFIX, EQUIVOCATIONS_KEY).unwrap();
+	assert_eq!(
+		Vec::<EquivocationProof<Hash, BlockNumber>>::decode(&mut &queued[..]).unwrap(),
+		vec![proof],
+	);
+}
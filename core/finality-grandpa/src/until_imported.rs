@@ -36,7 +36,7 @@ use futures03::{StreamExt as _, TryStreamExt as _};
 use grandpa::voter;
 use parking_lot::Mutex;
 use sr_primitives::traits::{Block as BlockT, Header as HeaderT, NumberFor};
-use tokio_timer::Interval;
+use network::clock::{self, Interval};
 
 use std::collections::{HashMap, VecDeque};
 use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
@@ -104,9 +104,9 @@ impl<Block, BlockStatus, BlockSyncRequester, I, M> UntilImported<Block, BlockSta
 		// the import notifications interval takes care of most of this; this is
 		// used in the event of missed import notifications
 		const CHECK_PENDING_INTERVAL: Duration = Duration::from_secs(5);
-		let now = Instant::now();
+		let now = clock::now();
 
-		let check_pending = Interval::new(now + CHECK_PENDING_INTERVAL, CHECK_PENDING_INTERVAL);
+		let check_pending = Interval::new_at(now + CHECK_PENDING_INTERVAL, CHECK_PENDING_INTERVAL);
 		UntilImported {
 			import_notifications: {
 				let stream = import_notifications.map::<_, fn(_) -> _>(|v| Ok::<_, ()>(v)).compat();
@@ -147,7 +147,7 @@ impl<Block, BStatus, BSyncRequester, I, M> Stream for UntilImported<Block, BStat
 						&self.status_check,
 						|target_hash, target_number, wait| pending
 							.entry(target_hash)
-							.or_insert_with(|| (target_number, Instant::now(), Vec::new()))
+							.or_insert_with(|| (target_number, clock::now(), Vec::new()))
 							.2
 							.push(wait),
 						|ready_item| ready.push_back(ready_item),
@@ -187,7 +187,7 @@ impl<Block, BStatus, BSyncRequester, I, M> Stream for UntilImported<Block, BStat
 					known_keys.push((block_hash, number));
 				} else {
 					let next_log = *last_log + LOG_PENDING_INTERVAL;
-					if Instant::now() >= next_log {
+					if clock::now() >= next_log {
 						debug!(
 							target: "afg",
 							"Waiting to import block {} before {} {} messages can be imported. \
@@ -0,0 +1,171 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tracking of the votes seen by the local voter in the latest round it has
+//! started, so that the state of the voter can be inspected from outside
+//! (e.g. through RPC) while it is running.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use grandpa::voter_set::VoterSet;
+use parking_lot::RwLock;
+use fg_primitives::{AuthorityId, RoundNumber, SetId};
+
+/// The votes observed in a round, as seen by the local voter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundState {
+	/// The authority set id the round belongs to.
+	pub set_id: SetId,
+	/// The round number.
+	pub round: RoundNumber,
+	/// The voters of the round and their weights.
+	pub voters: Vec<(AuthorityId, u64)>,
+	/// The total weight of all voters.
+	pub total_weight: u64,
+	/// The weight required for a supermajority.
+	pub threshold_weight: u64,
+	/// The voters we have seen a prevote from.
+	pub prevotes: HashSet<AuthorityId>,
+	/// The voters we have seen a precommit from.
+	pub precommits: HashSet<AuthorityId>,
+}
+
+impl RoundState {
+	/// The weight of the voters that have prevoted.
+	pub fn prevote_weight(&self) -> u64 {
+		self.weight_of(&self.prevotes)
+	}
+
+	/// The weight of the voters that have precommitted.
+	pub fn precommit_weight(&self) -> u64 {
+		self.weight_of(&self.precommits)
+	}
+
+	fn weight_of(&self, voted: &HashSet<AuthorityId>) -> u64 {
+		self.voters.iter()
+			.filter(|(id, _)| voted.contains(id))
+			.map(|(_, weight)| weight)
+			.sum()
+	}
+}
+
+/// The kind of vote observed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum VoteKind {
+	Prevote,
+	Precommit,
+}
+
+/// Shared handle to the state of the latest round started by the local voter.
+///
+/// Observers never start rounds, so the state stays empty on nodes that are
+/// not running a voter.
+#[derive(Clone, Default)]
+pub struct SharedVoterState {
+	inner: Arc<RwLock<Option<RoundState>>>,
+}
+
+impl SharedVoterState {
+	/// Create a new empty voter state.
+	pub fn empty() -> Self {
+		Self::default()
+	}
+
+	/// The state of the latest round started by the voter, if any.
+	pub fn round_state(&self) -> Option<RoundState> {
+		self.inner.read().clone()
+	}
+
+	/// Note that the voter has started the given round. Rounds older than the
+	/// one currently tracked are ignored, since the voter keeps a few previous
+	/// rounds running in the background.
+	pub(crate) fn note_round(&self, set_id: SetId, round: RoundNumber, voters: &VoterSet<AuthorityId>) {
+		let mut inner = self.inner.write();
+		if let Some(current) = inner.as_ref() {
+			if current.set_id == set_id && current.round >= round {
+				return;
+			}
+		}
+
+		*inner = Some(RoundState {
+			set_id,
+			round,
+			voters: voters.voters().to_vec(),
+			total_weight: voters.total_weight(),
+			threshold_weight: voters.threshold(),
+			prevotes: HashSet::new(),
+			precommits: HashSet::new(),
+		});
+	}
+
+	/// Note a vote cast by the given voter in the given round.
+	pub(crate) fn note_vote(&self, set_id: SetId, round: RoundNumber, voter: &AuthorityId, kind: VoteKind) {
+		let mut inner = self.inner.write();
+		let state = match inner.as_mut() {
+			Some(state) if state.set_id == set_id && state.round == round => state,
+			_ => return,
+		};
+
+		match kind {
+			VoteKind::Prevote => state.prevotes.insert(voter.clone()),
+			VoteKind::Precommit => state.precommits.insert(voter.clone()),
+		};
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use keyring::Ed25519Keyring;
+
+	fn voters() -> VoterSet<AuthorityId> {
+		vec![
+			(Ed25519Keyring::Alice.public().into(), 1),
+			(Ed25519Keyring::Bob.public().into(), 1),
+			(Ed25519Keyring::Charlie.public().into(), 1),
+		].into_iter().collect()
+	}
+
+	#[test]
+	fn tracks_votes_of_the_latest_round_only() {
+		let state = SharedVoterState::empty();
+		let alice: AuthorityId = Ed25519Keyring::Alice.public().into();
+		let bob: AuthorityId = Ed25519Keyring::Bob.public().into();
+
+		state.note_round(0, 2, &voters());
+		state.note_vote(0, 2, &alice, VoteKind::Prevote);
+		state.note_vote(0, 2, &alice, VoteKind::Precommit);
+		state.note_vote(0, 2, &bob, VoteKind::Prevote);
+
+		// votes for a background round and older rounds being restarted are ignored.
+		state.note_vote(0, 1, &bob, VoteKind::Precommit);
+		state.note_round(0, 1, &voters());
+
+		let round_state = state.round_state().unwrap();
+		assert_eq!(round_state.round, 2);
+		assert_eq!(round_state.total_weight, 3);
+		assert_eq!(round_state.threshold_weight, 3);
+		assert_eq!(round_state.prevote_weight(), 2);
+		assert_eq!(round_state.precommit_weight(), 1);
+
+		// a new set resets the round.
+		state.note_round(1, 1, &voters());
+		let round_state = state.round_state().unwrap();
+		assert_eq!((round_state.set_id, round_state.round), (1, 1));
+		assert!(round_state.prevotes.is_empty());
+	}
+}
@@ -19,10 +19,18 @@
 //! This exposes the `VotingRule` trait used to implement arbitrary voting
 //! restrictions that are taken into account by the GRANDPA environment when
 //! selecting a finality target to vote on.
+//!
+//! Rules can also be picked by name, e.g. from the command line, with
+//! `VotingRulesBuilder::add_named`.
 
 use std::sync::Arc;
 
-use client::blockchain::HeaderBackend;
+use client::{CallExecutor, Client, backend::Backend, blockchain::HeaderBackend};
+use client::error::Error as ClientError;
+use codec::Decode;
+use fg_primitives::GrandpaVotingRuleApi;
+use log::debug;
+use primitives::{Blake2Hasher, ExecutionStrategy, H256};
 use sr_primitives::generic::BlockId;
 use sr_primitives::traits::{Block as BlockT, Header, NumberFor, One, Zero};
 
@@ -130,26 +138,111 @@ impl<Block, B> VotingRule<Block, B> for ThreeQuartersOfTheUnfinalizedChain where
 			return None;
 		}
 
-		let mut target_header = current_target.clone();
-		let mut target_hash = current_target.hash();
-
-		// walk backwards until we find the target block
-		loop {
-			if *target_header.number() < target_number {
-				unreachable!(
-					"we are traversing backwards from a known block; \
-					 blocks are stored contiguously; \
-					 qed"
-				);
-			}
-			if *target_header.number() == target_number {
-				return Some((target_hash, target_number));
-			}
-
-			target_hash = *target_header.parent_hash();
-			target_header = backend.header(BlockId::Hash(target_hash)).ok()?
-				.expect("Header known to exist due to the existence of one of its descendents; qed");
+		find_target(backend, target_number, current_target)
+	}
+}
+
+/// A custom voting rule that restricts votes to the highest block allowed by the
+/// runtime, which is queried with the `GrandpaVotingRuleApi` at the best block.
+/// Votes aren't restricted if the runtime doesn't implement the API.
+#[derive(Clone)]
+pub struct RuntimeVotingRule;
+
+impl RuntimeVotingRule {
+	fn max_vote_target<B, E, Block, RA>(
+		client: &Client<B, E, Block, RA>,
+		at: &BlockId<Block>,
+	) -> Result<Option<NumberFor<Block>>, ClientError> where
+		B: Backend<Block, Blake2Hasher>,
+		E: CallExecutor<Block, Blake2Hasher>,
+		Block: BlockT<Hash=H256>,
+	{
+		if !client.runtime_version_at(at)?.has_api::<dyn GrandpaVotingRuleApi<Block>>() {
+			return Ok(None);
+		}
+
+		// the API is called by name so that runtimes which don't implement it can
+		// still use the other voting rules.
+		client.executor()
+			.call(
+				at,
+				"GrandpaVotingRuleApi_max_vote_target",
+				&[],
+				ExecutionStrategy::NativeElseWasm,
+				None,
+			)
+			.and_then(|call_result| {
+				Decode::decode(&mut &call_result[..])
+					.map_err(|err| ClientError::CallResultDecode(
+						"failed to decode GRANDPA max vote target", err
+					))
+			})
+	}
+}
+
+impl<B, E, Block, RA> VotingRule<Block, Client<B, E, Block, RA>> for RuntimeVotingRule where
+	B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+	E: CallExecutor<Block, Blake2Hasher> + Send + Sync,
+	Block: BlockT<Hash=H256>,
+	RA: Send + Sync,
+{
+	fn restrict_vote(
+		&self,
+		backend: &Client<B, E, Block, RA>,
+		base: &Block::Header,
+		best_target: &Block::Header,
+		current_target: &Block::Header,
+	) -> Option<(Block::Hash, NumberFor<Block>)> {
+		let at = BlockId::Hash(best_target.hash());
+		let target_number = match Self::max_vote_target(backend, &at) {
+			Ok(Some(target_number)) => target_number,
+			Ok(None) => return None,
+			Err(e) => {
+				debug!(target: "afg", "Failed to query runtime voting rule at {:?}: {:?}", at, e);
+				return None;
+			},
+		};
+
+		// we never vote below the base, which is already finalized or about to be.
+		let target_number = target_number.max(*base.number());
+
+		// our current target is already lower than this rule would restrict
+		if target_number >= *current_target.number() {
+			return None;
+		}
+
+		find_target(backend, target_number, current_target)
+	}
+}
+
+/// Walk back from `current_target` to its ancestor with the given number.
+fn find_target<Block, B>(
+	backend: &B,
+	target_number: NumberFor<Block>,
+	current_target: &Block::Header,
+) -> Option<(Block::Hash, NumberFor<Block>)> where
+	Block: BlockT,
+	B: HeaderBackend<Block>,
+{
+	let mut target_header = current_target.clone();
+	let mut target_hash = current_target.hash();
+
+	// walk backwards until we find the target block
+	loop {
+		if *target_header.number() < target_number {
+			unreachable!(
+				"we are traversing backwards from a known block; \
+				 blocks are stored contiguously; \
+				 qed"
+			);
+		}
+		if *target_header.number() == target_number {
+			return Some((target_hash, target_number));
 		}
+
+		target_hash = *target_header.parent_hash();
+		target_header = backend.header(BlockId::Hash(target_hash)).ok()?
+			.expect("Header known to exist due to the existence of one of its descendents; qed");
 	}
 }
 
@@ -201,6 +294,19 @@ impl<Block, B> VotingRule<Block, B> for VotingRules<Block, B> where
 	}
 }
 
+const BEFORE_BEST_BLOCK: &str = "before-best-block";
+const THREE_QUARTERS_OF_THE_UNFINALIZED_CHAIN: &str = "three-quarters-of-the-unfinalized-chain";
+const RUNTIME: &str = "runtime";
+
+/// The names of the voting rules which can be added with `VotingRulesBuilder::add_named`,
+/// respectively `BeforeBestBlock`, `ThreeQuartersOfTheUnfinalizedChain` and
+/// `RuntimeVotingRule`.
+pub const NAMED_VOTING_RULES: &[&str] = &[
+	BEFORE_BEST_BLOCK,
+	THREE_QUARTERS_OF_THE_UNFINALIZED_CHAIN,
+	RUNTIME,
+];
+
 /// A builder of a composite voting rule that applies a set of rules to
 /// progressively restrict the vote.
 pub struct VotingRulesBuilder<Block, B> {
@@ -254,6 +360,47 @@ impl<Block, B> VotingRulesBuilder<Block, B> where
 	}
 }
 
+impl<B, E, Block, RA> VotingRulesBuilder<Block, Client<B, E, Block, RA>> where
+	B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+	E: CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+	Block: BlockT<Hash=H256>,
+	RA: Send + Sync + 'static,
+{
+	/// Add the voting rules with the given names, in order. The available rules
+	/// are listed in `NAMED_VOTING_RULES`.
+	///
+	/// Returns the first unknown name as an error.
+	pub fn add_named<I, S>(mut self, names: I) -> Result<Self, String> where
+		I: IntoIterator<Item=S>,
+		S: AsRef<str>,
+	{
+		for name in names {
+			self = match name.as_ref() {
+				BEFORE_BEST_BLOCK => self.add(BeforeBestBlock),
+				THREE_QUARTERS_OF_THE_UNFINALIZED_CHAIN => self.add(ThreeQuartersOfTheUnfinalizedChain),
+				RUNTIME => self.add(RuntimeVotingRule),
+				name => return Err(name.to_string()),
+			};
+		}
+
+		Ok(self)
+	}
+
+	/// Create a builder with the voting rules with the given names, in order, or with
+	/// the default voting rules if no names are given. See `add_named`.
+	pub fn from_names<I, S>(names: I) -> Result<Self, String> where
+		I: IntoIterator<Item=S>,
+		S: AsRef<str>,
+	{
+		let mut names = names.into_iter().peekable();
+		if names.peek().is_none() {
+			Ok(Self::default())
+		} else {
+			Self::new().add_named(names)
+		}
+	}
+}
+
 impl<Block, B> VotingRule<Block, B> for Box<dyn VotingRule<Block, B>> where
 	Block: BlockT,
 	B: HeaderBackend<Block>,
@@ -0,0 +1,269 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate. If not, see <http://www.gnu.org/licenses/>.
+
+//! Password protected form of a key, used to move keys between keystores.
+//!
+//! The secret URI is encrypted with AES-128-CTR. The encryption key and the key of the
+//! HMAC-SHA256 authenticating the key type, public key, signature scheme and ciphertext are
+//! derived from the password with PBKDF2.
+
+use std::convert::TryFrom;
+
+use aes_ctr::{Aes128Ctr, stream_cipher::{NewStreamCipher, SyncStreamCipher, generic_array::GenericArray}};
+use hmac::{Hmac, Mac};
+use primitives::crypto::KeyTypeId;
+use rand::{RngCore, rngs::OsRng};
+use serde::{Serialize, Deserialize};
+use sha2::Sha256;
+
+use crate::{Error, Result};
+
+/// Number of PBKDF2 iterations used for new exports.
+pub const DEFAULT_ITERATIONS: u32 = 262_144;
+
+/// Lowest number of PBKDF2 iterations accepted when decrypting.
+pub const MIN_ITERATIONS: u32 = 65_536;
+
+/// Highest number of PBKDF2 iterations accepted when decrypting.
+pub const MAX_ITERATIONS: u32 = 16_777_216;
+
+/// Signature scheme of an exported key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyScheme {
+	/// Schnorrkel/Ristretto x25519.
+	Sr25519,
+	/// Ed25519.
+	Ed25519,
+}
+
+impl KeyScheme {
+	fn name(self) -> &'static [u8] {
+		match self {
+			KeyScheme::Sr25519 => b"sr25519",
+			KeyScheme::Ed25519 => b"ed25519",
+		}
+	}
+}
+
+/// A key encrypted with a password.
+///
+/// All binary fields are hex encoded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedKey {
+	/// Key type, e.g. `babe` or `gran`.
+	pub key_type: String,
+	/// Public key.
+	pub public: String,
+	/// Signature scheme of the key.
+	pub scheme: KeyScheme,
+	/// PBKDF2 iterations.
+	pub iterations: u32,
+	/// PBKDF2 salt.
+	pub salt: String,
+	/// AES-128-CTR initialization vector.
+	pub iv: String,
+	/// Encrypted secret URI.
+	pub ciphertext: String,
+	/// HMAC-SHA256 of the key type, public key, scheme, initialization vector and ciphertext.
+	pub mac: String,
+}
+
+impl EncryptedKey {
+	/// Encrypt the secret URI of the key with the given key type, public key and scheme.
+	pub fn encrypt(
+		key_type: KeyTypeId,
+		public: &[u8],
+		scheme: KeyScheme,
+		suri: &str,
+		password: &str,
+	) -> Self {
+		Self::encrypt_with_iterations(key_type, public, scheme, suri, password, DEFAULT_ITERATIONS)
+	}
+
+	fn encrypt_with_iterations(
+		key_type: KeyTypeId,
+		public: &[u8],
+		scheme: KeyScheme,
+		suri: &str,
+		password: &str,
+		iterations: u32,
+	) -> Self {
+		let mut salt = [0u8; 32];
+		let mut iv = [0u8; 16];
+		OsRng.fill_bytes(&mut salt);
+		OsRng.fill_bytes(&mut iv);
+
+		let (cipher_key, mac_key) = derive_keys(password, &salt, iterations);
+		let mut ciphertext = suri.as_bytes().to_vec();
+		Aes128Ctr::new(GenericArray::from_slice(&cipher_key), GenericArray::from_slice(&iv))
+			.apply_keystream(&mut ciphertext);
+		let mac = mac(&mac_key, key_type, public, scheme, &iv, &ciphertext).result().code();
+
+		EncryptedKey {
+			key_type: String::from_utf8_lossy(&key_type.0).into_owned(),
+			public: hex::encode(public),
+			scheme,
+			iterations,
+			salt: hex::encode(salt),
+			iv: hex::encode(iv),
+			ciphertext: hex::encode(ciphertext),
+			mac: hex::encode(mac),
+		}
+	}
+
+	/// The key type of the key.
+	pub fn key_type(&self) -> Result<KeyTypeId> {
+		KeyTypeId::try_from(self.key_type.as_str()).map_err(|_| Error::InvalidSeed)
+	}
+
+	/// The raw public key.
+	pub fn public(&self) -> Result<Vec<u8>> {
+		decode_hex(&self.public)
+	}
+
+	/// Decrypt the secret URI.
+	///
+	/// Fails with `Error::InvalidPassword` if the password does not match or if the key type,
+	/// public key or scheme have been altered.
+	pub fn decrypt(&self, password: &str) -> Result<String> {
+		if self.iterations < MIN_ITERATIONS || self.iterations > MAX_ITERATIONS {
+			return Err(Error::InvalidIterations)
+		}
+		let key_type = self.key_type()?;
+		let public = self.public()?;
+		let salt = decode_hex(&self.salt)?;
+		let iv = decode_hex(&self.iv)?;
+		let mut ciphertext = decode_hex(&self.ciphertext)?;
+		if iv.len() != 16 {
+			return Err(Error::InvalidSeed)
+		}
+
+		let (cipher_key, mac_key) = derive_keys(password, &salt, self.iterations);
+		mac(&mac_key, key_type, &public, self.scheme, &iv, &ciphertext)
+			.verify(&decode_hex(&self.mac)?)
+			.map_err(|_| Error::InvalidPassword)?;
+
+		Aes128Ctr::new(GenericArray::from_slice(&cipher_key), GenericArray::from_slice(&iv))
+			.apply_keystream(&mut ciphertext);
+		String::from_utf8(ciphertext).map_err(|_| Error::InvalidPhrase)
+	}
+}
+
+fn decode_hex(data: &str) -> Result<Vec<u8>> {
+	hex::decode(data).map_err(|_| Error::InvalidSeed)
+}
+
+/// Derive the cipher key and the MAC key from the password.
+fn derive_keys(password: &str, salt: &[u8], iterations: u32) -> ([u8; 16], [u8; 16]) {
+	let mut derived = [0u8; 32];
+	pbkdf2::pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, iterations as usize, &mut derived);
+
+	let mut cipher_key = [0u8; 16];
+	let mut mac_key = [0u8; 16];
+	cipher_key.copy_from_slice(&derived[..16]);
+	mac_key.copy_from_slice(&derived[16..]);
+	(cipher_key, mac_key)
+}
+
+/// The MAC of an exported key. The variable length fields are prefixed with their length.
+fn mac(
+	key: &[u8],
+	key_type: KeyTypeId,
+	public: &[u8],
+	scheme: KeyScheme,
+	iv: &[u8],
+	ciphertext: &[u8],
+) -> Hmac<Sha256> {
+	let mut mac = Hmac::<Sha256>::new_varkey(key).expect("HMAC accepts keys of any length; qed");
+	mac.input(&key_type.0);
+	mac.input(&(public.len() as u32).to_le_bytes());
+	mac.input(public);
+	mac.input(&(scheme.name().len() as u32).to_le_bytes());
+	mac.input(scheme.name());
+	mac.input(iv);
+	mac.input(ciphertext);
+	mac
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use primitives::testing::{ED25519, SR25519};
+
+	fn exported() -> EncryptedKey {
+		EncryptedKey::encrypt_with_iterations(
+			SR25519,
+			&[1, 2, 3],
+			KeyScheme::Sr25519,
+			"//Alice",
+			"password",
+			MIN_ITERATIONS,
+		)
+	}
+
+	fn is_invalid_password(result: Result<String>) -> bool {
+		match result {
+			Err(Error::InvalidPassword) => true,
+			_ => false,
+		}
+	}
+
+	#[test]
+	fn encrypt_and_decrypt() {
+		let exported = exported();
+
+		assert_eq!(exported.key_type().unwrap(), SR25519);
+		assert_eq!(exported.public().unwrap(), vec![1, 2, 3]);
+		assert_eq!(exported.scheme, KeyScheme::Sr25519);
+		assert_eq!(exported.decrypt("password").unwrap(), "//Alice");
+		assert!(is_invalid_password(exported.decrypt("wrong")));
+
+		let json = serde_json::to_string(&exported).unwrap();
+		assert!(json.contains(r#""scheme":"sr25519""#));
+		assert_eq!(serde_json::from_str::<EncryptedKey>(&json).unwrap(), exported);
+	}
+
+	#[test]
+	fn authenticates_key_type_public_key_and_scheme() {
+		let exported = exported();
+
+		let mut altered = exported.clone();
+		altered.key_type = String::from_utf8_lossy(&ED25519.0).into_owned();
+		assert!(is_invalid_password(altered.decrypt("password")));
+
+		let mut altered = exported.clone();
+		altered.public = hex::encode([1, 2, 4]);
+		assert!(is_invalid_password(altered.decrypt("password")));
+
+		let mut altered = exported;
+		altered.scheme = KeyScheme::Ed25519;
+		assert!(is_invalid_password(altered.decrypt("password")));
+	}
+
+	#[test]
+	fn rejects_iterations_out_of_bounds() {
+		for iterations in &[0, 1_000, MIN_ITERATIONS - 1, MAX_ITERATIONS + 1, u32::max_value()] {
+			let mut altered = exported();
+			altered.iterations = *iterations;
+			assert!(match altered.decrypt("password") {
+				Err(Error::InvalidIterations) => true,
+				_ => false,
+			});
+		}
+	}
+}
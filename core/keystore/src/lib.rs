@@ -28,6 +28,10 @@ use app_crypto::{AppKey, AppPublic, AppPair, ed25519, sr25519};
 
 use parking_lot::RwLock;
 
+mod export;
+
+pub use export::{EncryptedKey, KeyScheme};
+
 /// Keystore pointer
 pub type KeyStorePtr = Arc<RwLock<Store>>;
 
@@ -50,6 +54,9 @@ pub enum Error {
 	/// Keystore unavailable
 	#[display(fmt="Keystore unavailable")]
 	Unavailable,
+	/// The number of iterations of an exported key is too low or too high.
+	#[display(fmt="Unsupported number of key derivation iterations")]
+	InvalidIterations,
 }
 
 /// Keystore Result
@@ -250,6 +257,42 @@ impl Store {
 			.map(|v| v.into_iter().map(Into::into).collect())
 	}
 
+	/// Get the key types and raw public keys of all keys placed in the file system store.
+	pub fn keys(&self) -> Result<Vec<(KeyTypeId, Vec<u8>)>> {
+		let mut keys = Vec::new();
+		for entry in fs::read_dir(&self.path)? {
+			let path = entry?.path();
+
+			// skip directories and non-unicode file names (hex is unicode)
+			if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+				match hex::decode(name) {
+					Ok(ref hex) if hex.len() > 4 => {
+						let mut key_type = KeyTypeId::default();
+						key_type.0.copy_from_slice(&hex[0..4]);
+						keys.push((key_type, hex[4..].to_vec()));
+					}
+					_ => continue,
+				}
+			}
+		}
+
+		keys.sort();
+		Ok(keys)
+	}
+
+	/// Get the secret URI the key with the given public key and key type was stored with.
+	///
+	/// The password of the store is not part of it.
+	pub fn key_phrase_by_type(&self, public: &[u8], key_type: KeyTypeId) -> Result<String> {
+		let file = File::open(self.key_file_path(public, key_type))?;
+		serde_json::from_reader(&file).map_err(Into::into)
+	}
+
+	/// Remove the key with the given public key and key type from the file system store.
+	pub fn remove_by_type(&self, public: &[u8], key_type: KeyTypeId) -> Result<()> {
+		fs::remove_file(self.key_file_path(public, key_type)).map_err(Into::into)
+	}
+
 	/// Returns the file path for the given public key and key type.
 	fn key_file_path(&self, public: &[u8], key_type: KeyTypeId) -> PathBuf {
 		let mut buf = self.path.clone();
@@ -422,4 +465,19 @@ mod tests {
 
 		assert_eq!(key_pair.public(), store_key_pair.public());
 	}
+
+	#[test]
+	fn keys_are_listed_and_removed() {
+		let temp_dir = TempDir::new("keystore").unwrap();
+		let store = Store::open(temp_dir.path(), None).unwrap();
+
+		let pair = store.write().insert_by_type::<sr25519::Pair>(SR25519, "//Alice").unwrap();
+		let public = pair.public().as_ref().to_vec();
+
+		assert_eq!(store.read().keys().unwrap(), vec![(SR25519, public.clone())]);
+		assert_eq!(store.read().key_phrase_by_type(&public, SR25519).unwrap(), "//Alice");
+
+		store.write().remove_by_type(&public, SR25519).unwrap();
+		assert!(store.read().keys().unwrap().is_empty());
+	}
 }
@@ -28,7 +28,7 @@ use libp2p::swarm::{NetworkBehaviourAction, NetworkBehaviourEventProcess};
 use libp2p::core::{nodes::Substream, muxing::StreamMuxerBox};
 use log::{debug, warn};
 use sr_primitives::traits::Block as BlockT;
-use std::iter;
+use std::{collections::HashSet, iter};
 use void;
 
 /// General behaviour of the network. Combines all protocols together.
@@ -56,17 +56,27 @@ pub enum BehaviourOut<B: BlockT> {
 
 impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Behaviour<B, S, H> {
 	/// Builds a new `Behaviour`.
+	///
+	/// `hidden_peers` are never announced to the other nodes of the DHT.
 	pub fn new(
 		substrate: Protocol<B, S, H>,
 		user_agent: String,
 		local_public_key: PublicKey,
 		known_addresses: Vec<(PeerId, Multiaddr)>,
+		hidden_peers: HashSet<PeerId>,
+		enable_random_walk: bool,
 		enable_mdns: bool,
 	) -> Self {
 		Behaviour {
 			substrate,
 			debug_info: debug_info::DebugInfoBehaviour::new(user_agent, local_public_key.clone()),
-			discovery: DiscoveryBehaviour::new(local_public_key, known_addresses, enable_mdns),
+			discovery: DiscoveryBehaviour::new(
+				local_public_key,
+				known_addresses,
+				hidden_peers,
+				enable_random_walk,
+				enable_mdns,
+			),
 			events: Vec::new(),
 		}
 	}
@@ -0,0 +1,160 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Clock of the networking code and of the protocols built on top of it.
+//!
+//! This is the wall clock, except on threads where a simulated network has installed its virtual
+//! clock (see `SimulatedNetwork::install_clock`, only available with the `test-helpers` feature).
+//! Timeouts that must follow the simulated time take their instants from [`now`] and are built
+//! with [`Delay`] and [`Interval`]. Random delays are drawn with [`random_delay`], so that they
+//! come from the seed of the simulated network when there is one.
+
+use futures::prelude::*;
+use futures03::{compat::Compat, TryFutureExt as _};
+use rand::Rng;
+use std::{fmt, io, time::{Duration, Instant}};
+
+#[cfg(any(test, feature = "test-helpers"))]
+use crate::simulation::{self, SimulatedNetwork};
+
+/// Returns the current instant.
+pub fn now() -> Instant {
+	#[cfg(any(test, feature = "test-helpers"))]
+	{
+		if let Some(clock) = simulation::current_clock() {
+			return clock.instant();
+		}
+	}
+
+	Instant::now()
+}
+
+/// Returns a random duration between zero, included, and `max`, excluded.
+pub fn random_delay(max: Duration) -> Duration {
+	let max = max.as_millis() as u64;
+	if max == 0 {
+		return Duration::from_millis(0);
+	}
+
+	#[cfg(any(test, feature = "test-helpers"))]
+	{
+		if let Some(clock) = simulation::current_clock() {
+			return Duration::from_millis(clock.gen_range(0, max));
+		}
+	}
+
+	Duration::from_millis(rand::thread_rng().gen_range(0, max))
+}
+
+/// Future that resolves at a given instant.
+pub struct Delay(DelayInner);
+
+enum DelayInner {
+	Real(Compat<futures_timer::Delay>),
+	#[cfg(any(test, feature = "test-helpers"))]
+	Simulated(SimulatedNetwork, Instant),
+}
+
+impl Delay {
+	/// Creates a future that resolves after `duration`.
+	pub fn new(duration: Duration) -> Self {
+		Self::new_at(now() + duration)
+	}
+
+	/// Creates a future that resolves at `at`.
+	pub fn new_at(at: Instant) -> Self {
+		#[cfg(any(test, feature = "test-helpers"))]
+		{
+			if let Some(clock) = simulation::current_clock() {
+				return Delay(DelayInner::Simulated(clock, at));
+			}
+		}
+
+		Delay(DelayInner::Real(futures_timer::Delay::new_at(at).compat()))
+	}
+
+	/// Makes the future resolve at `at` instead, whether or not it has already resolved.
+	pub fn reset_at(&mut self, at: Instant) {
+		*self = Self::new_at(at);
+	}
+}
+
+impl fmt::Debug for Delay {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.0 {
+			DelayInner::Real(_) => f.debug_tuple("Delay").finish(),
+			#[cfg(any(test, feature = "test-helpers"))]
+			DelayInner::Simulated(_, at) => f.debug_tuple("Delay").field(&at).finish(),
+		}
+	}
+}
+
+impl Future for Delay {
+	type Item = ();
+	type Error = io::Error;
+
+	fn poll(&mut self) -> Poll<(), io::Error> {
+		match self.0 {
+			DelayInner::Real(ref mut delay) => delay.poll(),
+			#[cfg(any(test, feature = "test-helpers"))]
+			DelayInner::Simulated(ref clock, at) =>
+				Ok(if clock.poll_instant(at) { Async::Ready(()) } else { Async::NotReady }),
+		}
+	}
+}
+
+/// Stream that yields at a fixed period.
+pub struct Interval {
+	delay: Delay,
+	next: Instant,
+	period: Duration,
+}
+
+impl Interval {
+	/// Creates a stream that first yields after `period`, then every `period`.
+	pub fn new(period: Duration) -> Self {
+		Self::new_at(now() + period, period)
+	}
+
+	/// Creates a stream that first yields at `at`, then every `period`.
+	pub fn new_at(at: Instant, period: Duration) -> Self {
+		Interval {
+			delay: Delay::new_at(at),
+			next: at,
+			period,
+		}
+	}
+}
+
+impl Stream for Interval {
+	type Item = ();
+	type Error = io::Error;
+
+	fn poll(&mut self) -> Poll<Option<()>, io::Error> {
+		if let Async::NotReady = self.delay.poll()? {
+			return Ok(Async::NotReady);
+		}
+
+		// Ticks that were missed are skipped rather than yielded in a burst.
+		let now = now();
+		self.next += self.period;
+		if self.next <= now {
+			self.next = now + self.period;
+		}
+		self.delay.reset_at(self.next);
+		Ok(Async::Ready(Some(())))
+	}
+}
@@ -255,6 +255,16 @@ pub struct NetworkConfiguration {
 	pub reserved_nodes: Vec<String>,
 	/// The non-reserved peer mode.
 	pub non_reserved_mode: NonReservedPeerMode,
+	/// List of sentry node addresses of this validator.
+	///
+	/// If not empty, connections are only made to and accepted from these nodes, whatever the
+	/// `non_reserved_mode` is, and neither mDNS nor the Kademlia random walk are used.
+	pub sentry_nodes: Vec<String>,
+	/// List of addresses of the validators this sentry node protects.
+	///
+	/// The node stays connected to them and relays their consensus gossip to its other peers as
+	/// soon as it is received.
+	pub protected_validators: Vec<String>,
 	/// Client identifier. Sent over the wire for debugging purposes.
 	pub client_version: String,
 	/// Name of the node. Sent over the wire for debugging purposes.
@@ -278,6 +288,8 @@ impl Default for NetworkConfiguration {
 			out_peers: 75,
 			reserved_nodes: Vec::new(),
 			non_reserved_mode: NonReservedPeerMode::Accept,
+			sentry_nodes: Vec::new(),
+			protected_validators: Vec::new(),
 			client_version: "unknown".into(),
 			node_name: "unknown".into(),
 			transport: TransportConfig::Normal {
@@ -339,6 +351,14 @@ pub enum TransportConfig {
 	/// Only allow connections within the same process.
 	/// Only addresses of the form `/memory/...` will be supported.
 	MemoryOnly,
+
+	/// Only allow connections within the same process, through the given simulated network.
+	/// Only addresses of the form `/memory/...` will be supported.
+	///
+	/// The simulated network applies latency, bandwidth limits, packet loss and partitions to
+	/// the connections. Meant for tests only.
+	#[cfg(any(test, feature = "test-helpers"))]
+	Simulated(crate::simulation::SimulatedEndpoint),
 }
 
 /// The policy for connections to non-reserved peers.
@@ -16,7 +16,6 @@
 
 use fnv::FnvHashMap;
 use futures::prelude::*;
-use futures03::{StreamExt as _, TryStreamExt as _};
 use libp2p::Multiaddr;
 use libp2p::core::{ConnectedPoint, either::EitherOutput, PeerId, PublicKey};
 use libp2p::swarm::{IntoProtocolsHandler, IntoProtocolsHandlerSelect, ProtocolsHandler};
@@ -27,7 +26,7 @@ use log::{debug, trace, error};
 use std::collections::hash_map::Entry;
 use std::time::{Duration, Instant};
 use tokio_io::{AsyncRead, AsyncWrite};
-use futures_timer::Interval;
+use crate::clock;
 
 /// Time after we disconnect from a node before we purge its information from the cache.
 const CACHE_EXPIRE: Duration = Duration::from_secs(10 * 60);
@@ -76,7 +75,7 @@ impl<TSubstream> DebugInfoBehaviour<TSubstream> {
 			ping: Ping::new(PingConfig::new()),
 			identify,
 			nodes_info: FnvHashMap::default(),
-			garbage_collect: Box::new(Interval::new(GARBAGE_COLLECT_INTERVAL).map(|()| Ok(())).compat()),
+			garbage_collect: Box::new(clock::Interval::new(GARBAGE_COLLECT_INTERVAL).map_err(|_| ())),
 		}
 	}
 
@@ -181,7 +180,7 @@ where TSubstream: AsyncRead + AsyncWrite {
 			}
 			Entry::Occupied(e) => {
 				let e = e.into_mut();
-				if e.info_expire.as_ref().map(|exp| *exp < Instant::now()).unwrap_or(false) {
+				if e.info_expire.as_ref().map(|exp| *exp < clock::now()).unwrap_or(false) {
 					e.client_version = None;
 					e.latest_ping = None;
 				}
@@ -196,7 +195,7 @@ where TSubstream: AsyncRead + AsyncWrite {
 		self.identify.inject_disconnected(peer_id, endpoint);
 
 		if let Some(entry) = self.nodes_info.get_mut(peer_id) {
-			entry.info_expire = Some(Instant::now() + CACHE_EXPIRE);
+			entry.info_expire = Some(clock::now() + CACHE_EXPIRE);
 		} else {
 			error!(target: "sub-libp2p",
 				"Disconnected from node we were not connected to {:?}", peer_id);
@@ -313,7 +312,7 @@ where TSubstream: AsyncRead + AsyncWrite {
 
 		while let Ok(Async::Ready(Some(_))) = self.garbage_collect.poll() {
 			self.nodes_info.retain(|_, node| {
-				node.info_expire.as_ref().map(|exp| *exp >= Instant::now()).unwrap_or(true)
+				node.info_expire.as_ref().map(|exp| *exp >= clock::now()).unwrap_or(true)
 			});
 		}
 
@@ -0,0 +1,393 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Request/response style access to signed and expiring DHT records.
+//!
+//! The [`NetworkService`](crate::NetworkService) only exposes fire-and-forget `get_value` and
+//! `put_value` methods, with the results arriving later as [`DhtEvent`]s on the
+//! [`NetworkWorker`](crate::NetworkWorker) stream. The [`DhtClient`] matches these events with
+//! the requests that caused them, so that offchain workers and RPC handlers can simply wait on a
+//! future.
+//!
+//! Every value that goes through the client is an encoded
+//! [`DhtRecord`](primitives::offchain::DhtRecord). Records are checked before being published,
+//! and records with an invalid signature or that have expired are dropped on retrieval. User keys
+//! are hashed into their own namespace so that they can't collide with the records of other
+//! subsystems such as authority discovery.
+
+use crate::{DhtEvent, ExHashT, NetworkService, clock, specialization::NetworkSpecialization};
+use codec::{Decode, Encode};
+use futures03::{
+	channel::oneshot, compat::{Compat01As03, Future01CompatExt as _},
+	future::{self, Future, FutureExt}, task::{Context, Poll},
+};
+pub use libp2p::kad::record::Key;
+use parking_lot::Mutex;
+use primitives::offchain::{DhtRecord, Timestamp, MAX_DHT_RECORD_TTL};
+use sr_primitives::traits::Block as BlockT;
+use std::{collections::{HashMap, hash_map::Entry}, pin::Pin, sync::Arc, time::Duration};
+
+/// Prefix of the keys of the records stored through the [`DhtClient`].
+const KEY_PREFIX: &[u8] = b"substrate-dht-record:";
+
+/// Time after which a request that the network didn't answer fails.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Error returned by the [`DhtClient`].
+#[derive(Debug, derive_more::Display)]
+pub enum Error {
+	/// The record's signature doesn't match the key and value.
+	#[display(fmt = "Invalid record signature.")]
+	InvalidSignature,
+	/// The record has already expired.
+	#[display(fmt = "Record has expired.")]
+	Expired,
+	/// The record expires further in the future than `MAX_DHT_RECORD_TTL` allows.
+	#[display(fmt = "Record expires too far in the future.")]
+	ExpiresTooLate,
+	/// The network failed to process the request.
+	#[display(fmt = "Network request failed.")]
+	RequestFailed,
+	/// The network didn't answer the request in time.
+	#[display(fmt = "Network request timed out.")]
+	Timeout,
+}
+
+impl std::error::Error for Error {}
+
+/// Access to the DHT of the network.
+pub trait DhtNetwork: Send + Sync {
+	/// Start getting a value from the DHT.
+	fn get_value(&self, key: &Key);
+	/// Start putting a value in the DHT.
+	fn put_value(&self, key: Key, value: Vec<u8>);
+}
+
+impl<B, S, H> DhtNetwork for NetworkService<B, S, H>
+where
+	B: BlockT + 'static,
+	S: NetworkSpecialization<B>,
+	H: ExHashT,
+{
+	fn get_value(&self, key: &Key) {
+		NetworkService::get_value(self, key)
+	}
+
+	fn put_value(&self, key: Key, value: Vec<u8>) {
+		NetworkService::put_value(self, key, value)
+	}
+}
+
+/// Requests waiting for a network event, by DHT key.
+type Pending<T> = Arc<Mutex<HashMap<Key, Vec<oneshot::Sender<Result<T, Error>>>>>>;
+
+/// Client for the DHT records. See the module-level documentation.
+pub struct DhtClient {
+	network: Arc<dyn DhtNetwork>,
+	pending_gets: Pending<Vec<Vec<u8>>>,
+	pending_puts: Pending<()>,
+}
+
+impl DhtClient {
+	/// Creates a new client on top of the given network.
+	///
+	/// The owner of the network event stream must call `on_event` for every [`DhtEvent`].
+	pub fn new(network: Arc<dyn DhtNetwork>) -> Self {
+		DhtClient {
+			network,
+			pending_gets: Default::default(),
+			pending_puts: Default::default(),
+		}
+	}
+
+	/// Returns the DHT key under which the records for `key` are stored.
+	pub fn dht_key(key: &[u8]) -> Key {
+		let mut prefixed = KEY_PREFIX.to_vec();
+		prefixed.extend_from_slice(key);
+		Key::new(&primitives::blake2_256(&prefixed))
+	}
+
+	/// Publishes `record` under `key`.
+	///
+	/// The record is checked against `now` before being sent to the network. The returned
+	/// future resolves once the network reports that the record has been stored or not, or
+	/// fails after `REQUEST_TIMEOUT`.
+	pub fn put_record(
+		&self,
+		key: &[u8],
+		record: DhtRecord,
+		now: Timestamp,
+	) -> impl Future<Output = Result<(), Error>> {
+		if let Err(err) = check_record(key, &record, now) {
+			return future::ready(Err(err)).left_future();
+		}
+
+		let dht_key = Self::dht_key(key);
+		let (tx, rx) = oneshot::channel();
+		self.pending_puts.lock().entry(dht_key.clone()).or_default().push(tx);
+		self.network.put_value(dht_key.clone(), record.encode());
+
+		Response::new(self.pending_puts.clone(), dht_key, rx).right_future()
+	}
+
+	/// Fetches the records stored under `key`.
+	///
+	/// Records that fail to decode, carry an invalid signature or have expired at `now` are
+	/// dropped. A key that isn't found in the DHT resolves to an empty list. The request fails
+	/// after `REQUEST_TIMEOUT`.
+	pub fn get_records(
+		&self,
+		key: &[u8],
+		now: Timestamp,
+	) -> impl Future<Output = Result<Vec<DhtRecord>, Error>> {
+		let key = key.to_vec();
+		let dht_key = Self::dht_key(&key);
+		let (tx, rx) = oneshot::channel();
+		let is_first = {
+			let mut pending_gets = self.pending_gets.lock();
+			let senders = pending_gets.entry(dht_key.clone()).or_default();
+			senders.push(tx);
+			senders.len() == 1
+		};
+		// Concurrent requests for the same key are answered by the same network request.
+		if is_first {
+			self.network.get_value(&dht_key);
+		}
+
+		Response::new(self.pending_gets.clone(), dht_key, rx).map(move |res| {
+			let values = res?;
+			Ok(values.into_iter()
+				.filter_map(|value| DhtRecord::decode(&mut &value[..]).ok())
+				.filter(|record| check_record(&key, record, now).is_ok())
+				.collect())
+		})
+	}
+
+	/// Processes an event generated by the network, resolving the matching requests.
+	pub fn on_event(&self, event: &DhtEvent) {
+		match event {
+			DhtEvent::ValueFound(values) => {
+				let mut found = HashMap::<_, Vec<_>>::new();
+				for (key, value) in values {
+					found.entry(key.clone()).or_default().push(value.clone());
+				}
+				let mut pending_gets = self.pending_gets.lock();
+				for (key, values) in found {
+					for sender in pending_gets.remove(&key).unwrap_or_default() {
+						let _ = sender.send(Ok(values.clone()));
+					}
+				}
+			},
+			DhtEvent::ValueNotFound(key) => {
+				for sender in self.pending_gets.lock().remove(key).unwrap_or_default() {
+					let _ = sender.send(Ok(Vec::new()));
+				}
+			},
+			DhtEvent::ValuePut(key) => {
+				for sender in self.pending_puts.lock().remove(key).unwrap_or_default() {
+					let _ = sender.send(Ok(()));
+				}
+			},
+			DhtEvent::ValuePutFailed(key) => {
+				for sender in self.pending_puts.lock().remove(key).unwrap_or_default() {
+					let _ = sender.send(Err(Error::RequestFailed));
+				}
+			},
+		}
+	}
+}
+
+/// Future waiting for the answer to a request.
+///
+/// Dropping it, whether it has timed out or not, removes its sender from the pending requests.
+struct Response<T> {
+	pending: Pending<T>,
+	key: Key,
+	rx: Option<oneshot::Receiver<Result<T, Error>>>,
+	timeout: Compat01As03<clock::Delay>,
+}
+
+impl<T> Response<T> {
+	fn new(pending: Pending<T>, key: Key, rx: oneshot::Receiver<Result<T, Error>>) -> Self {
+		Response {
+			pending,
+			key,
+			rx: Some(rx),
+			timeout: clock::Delay::new(REQUEST_TIMEOUT).compat(),
+		}
+	}
+}
+
+impl<T> Future for Response<T> {
+	type Output = Result<T, Error>;
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+		let this = &mut *self;
+		if let Some(rx) = this.rx.as_mut() {
+			if let Poll::Ready(res) = rx.poll_unpin(cx) {
+				this.rx = None;
+				return Poll::Ready(res.unwrap_or(Err(Error::RequestFailed)));
+			}
+		}
+
+		match this.timeout.poll_unpin(cx) {
+			Poll::Ready(_) => Poll::Ready(Err(Error::Timeout)),
+			Poll::Pending => Poll::Pending,
+		}
+	}
+}
+
+impl<T> Drop for Response<T> {
+	fn drop(&mut self) {
+		match self.rx.take() {
+			Some(rx) => drop(rx),
+			None => return,
+		}
+
+		let mut pending = self.pending.lock();
+		if let Entry::Occupied(mut entry) = pending.entry(self.key.clone()) {
+			entry.get_mut().retain(|sender| !sender.is_canceled());
+			if entry.get().is_empty() {
+				entry.remove();
+			}
+		}
+	}
+}
+
+/// Checks that `record` can be stored under `key` at `now`.
+fn check_record(key: &[u8], record: &DhtRecord, now: Timestamp) -> Result<(), Error> {
+	if record.is_expired(now) {
+		return Err(Error::Expired);
+	}
+	if record.expires_at > now.add(MAX_DHT_RECORD_TTL) {
+		return Err(Error::ExpiresTooLate);
+	}
+	if !record.verify(key) {
+		return Err(Error::InvalidSignature);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures03::executor::block_on;
+	use primitives::{crypto::Pair as _, sr25519};
+
+	#[derive(Default)]
+	struct TestNetwork {
+		gets: Mutex<Vec<Key>>,
+		puts: Mutex<Vec<(Key, Vec<u8>)>>,
+	}
+
+	impl DhtNetwork for TestNetwork {
+		fn get_value(&self, key: &Key) {
+			self.gets.lock().push(key.clone());
+		}
+
+		fn put_value(&self, key: Key, value: Vec<u8>) {
+			self.puts.lock().push((key, value));
+		}
+	}
+
+	fn signed_record(key: &[u8], value: &[u8], expires_at: u64) -> DhtRecord {
+		let pair = sr25519::Pair::from_seed(&[7u8; 32]);
+		let expires_at = Timestamp::from_unix_millis(expires_at);
+		DhtRecord {
+			value: value.to_vec(),
+			expires_at,
+			signer: pair.public(),
+			signature: pair.sign(&DhtRecord::signing_payload(key, value, expires_at)),
+		}
+	}
+
+	#[test]
+	fn put_resolves_on_network_event() {
+		let network = Arc::new(TestNetwork::default());
+		let client = DhtClient::new(network.clone());
+		let now = Timestamp::from_unix_millis(0);
+
+		let put = client.put_record(b"key", signed_record(b"key", b"value", 1_000), now);
+		let (dht_key, _) = network.puts.lock()[0].clone();
+		assert_eq!(dht_key, DhtClient::dht_key(b"key"));
+
+		client.on_event(&DhtEvent::ValuePut(dht_key));
+		assert!(block_on(put).is_ok());
+	}
+
+	#[test]
+	fn invalid_records_are_not_published() {
+		let network = Arc::new(TestNetwork::default());
+		let client = DhtClient::new(network.clone());
+		let now = Timestamp::from_unix_millis(2_000);
+
+		let expired = client.put_record(b"key", signed_record(b"key", b"value", 1_000), now);
+		match block_on(expired) {
+			Err(Error::Expired) => {},
+			res => panic!("Unexpected result: {:?}", res),
+		}
+
+		let wrong_key = client.put_record(b"other", signed_record(b"key", b"value", 3_000), now);
+		match block_on(wrong_key) {
+			Err(Error::InvalidSignature) => {},
+			res => panic!("Unexpected result: {:?}", res),
+		}
+
+		assert!(network.puts.lock().is_empty());
+	}
+
+	#[test]
+	fn get_filters_invalid_records() {
+		let network = Arc::new(TestNetwork::default());
+		let client = DhtClient::new(network.clone());
+		let now = Timestamp::from_unix_millis(2_000);
+
+		let get = client.get_records(b"key", now);
+		let dht_key = network.gets.lock()[0].clone();
+		let valid = signed_record(b"key", b"valid", 3_000);
+		client.on_event(&DhtEvent::ValueFound(vec![
+			(dht_key.clone(), valid.encode()),
+			(dht_key.clone(), signed_record(b"key", b"expired", 1_000).encode()),
+			(dht_key.clone(), signed_record(b"other", b"forged", 3_000).encode()),
+			(dht_key, b"garbage".to_vec()),
+		]));
+
+		assert_eq!(block_on(get).unwrap(), vec![valid]);
+	}
+
+	#[test]
+	fn dropped_requests_are_forgotten() {
+		let network = Arc::new(TestNetwork::default());
+		let client = DhtClient::new(network.clone());
+		let now = Timestamp::from_unix_millis(2_000);
+
+		let first = client.get_records(b"key", now);
+		let second = client.get_records(b"key", now);
+		let put = client.put_record(b"key", signed_record(b"key", b"value", 3_000), now);
+		assert_eq!(network.gets.lock().len(), 1);
+
+		drop(first);
+		assert_eq!(client.pending_gets.lock()[&DhtClient::dht_key(b"key")].len(), 1);
+		drop(second);
+		drop(put);
+		assert!(client.pending_gets.lock().is_empty());
+		assert!(client.pending_puts.lock().is_empty());
+
+		// The next request goes to the network again.
+		let _get = client.get_records(b"key", now);
+		assert_eq!(network.gets.lock().len(), 2);
+	}
+}
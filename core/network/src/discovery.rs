@@ -30,6 +30,9 @@
 //! order for nodes to propagate to us their view of the network. This is performed automatically
 //! by the `DiscoveryBehaviour`.
 //!
+//! A validator behind sentry nodes disables mDNS and the random walk, and its sentry nodes never
+//! put it in their routing table, so that the rest of the network can't discover it.
+//!
 //! Additionally, the `DiscoveryBehaviour` is also capable of storing and loading value in the
 //! network-wide DHT.
 //!
@@ -45,9 +48,8 @@
 //! of a node's address, you must call `add_self_reported_address`.
 //!
 
+use crate::clock::Delay;
 use futures::prelude::*;
-use futures_timer::Delay;
-use futures03::{compat::Compat, TryFutureExt as _};
 use libp2p::core::{ConnectedPoint, Multiaddr, PeerId, PublicKey};
 use libp2p::swarm::{ProtocolsHandler, NetworkBehaviour, NetworkBehaviourAction, PollParameters};
 use libp2p::kad::{Kademlia, KademliaEvent, Quorum, Record};
@@ -61,7 +63,7 @@ use libp2p::core::{nodes::Substream, muxing::StreamMuxerBox};
 use libp2p::mdns::{Mdns, MdnsEvent};
 use libp2p::multiaddr::Protocol;
 use log::{debug, info, trace, warn};
-use std::{cmp, collections::VecDeque, time::Duration};
+use std::{cmp, collections::{HashSet, VecDeque}, time::Duration};
 use tokio_io::{AsyncRead, AsyncWrite};
 use primitives::hexdisplay::HexDisplay;
 
@@ -75,8 +77,12 @@ pub struct DiscoveryBehaviour<TSubstream> {
 	/// Discovers nodes on the local network.
 	#[cfg(not(target_os = "unknown"))]
 	mdns: Toggle<Mdns<Substream<StreamMuxerBox>>>,
-	/// Stream that fires when we need to perform the next random Kademlia query.
-	next_kad_random_query: Compat<Delay>,
+	/// Peers that are never put in the Kademlia routing table, so that other nodes can't
+	/// discover them through us.
+	hidden_peers: HashSet<PeerId>,
+	/// Stream that fires when we need to perform the next random Kademlia query. `None` if the
+	/// random walk is disabled.
+	next_kad_random_query: Option<Delay>,
 	/// After `next_kad_random_query` triggers, the next one triggers after this duration.
 	duration_to_next_kad: Duration,
 	/// Discovered nodes to return.
@@ -90,11 +96,14 @@ pub struct DiscoveryBehaviour<TSubstream> {
 impl<TSubstream> DiscoveryBehaviour<TSubstream> {
 	/// Builds a new `DiscoveryBehaviour`.
 	///
-	/// `user_defined` is a list of known address for nodes that never expire.
+	/// `user_defined` is a list of known address for nodes that never expire. The addresses of
+	/// `hidden_peers` are only used to connect to them.
 	pub fn new(
 		local_public_key: PublicKey,
 		user_defined: Vec<(PeerId, Multiaddr)>,
-		enable_mdns: bool
+		hidden_peers: HashSet<PeerId>,
+		enable_random_walk: bool,
+		enable_mdns: bool,
 	) -> Self {
 		if enable_mdns {
 			#[cfg(target_os = "unknown")]
@@ -105,13 +114,20 @@ impl<TSubstream> DiscoveryBehaviour<TSubstream> {
 		let store = MemoryStore::new(local_id.clone());
 		let mut kademlia = Kademlia::new(local_id.clone(), store);
 		for (peer_id, addr) in &user_defined {
-			kademlia.add_address(peer_id, addr.clone());
+			if !hidden_peers.contains(peer_id) {
+				kademlia.add_address(peer_id, addr.clone());
+			}
 		}
 
 		DiscoveryBehaviour {
 			user_defined,
 			kademlia,
-			next_kad_random_query: Delay::new(Duration::new(0, 0)).compat(),
+			hidden_peers,
+			next_kad_random_query: if enable_random_walk {
+				Some(Delay::new(Duration::new(0, 0)))
+			} else {
+				None
+			},
 			duration_to_next_kad: Duration::from_secs(1),
 			discoveries: VecDeque::new(),
 			local_peer_id: local_public_key.into_peer_id(),
@@ -153,7 +169,9 @@ impl<TSubstream> DiscoveryBehaviour<TSubstream> {
 	/// **Note**: It is important that you call this method, otherwise the discovery mechanism will
 	/// not properly work.
 	pub fn add_self_reported_address(&mut self, peer_id: &PeerId, addr: Multiaddr) {
-		self.kademlia.add_address(peer_id, addr);
+		if !self.hidden_peers.contains(peer_id) {
+			self.kademlia.add_address(peer_id, addr);
+		}
 	}
 
 	/// Start fetching a record from the DHT.
@@ -232,6 +250,12 @@ where
 
 	fn inject_connected(&mut self, peer_id: PeerId, endpoint: ConnectedPoint) {
 		self.num_connections += 1;
+		// Kademlia puts the peers we dial in its routing table, but not the ones dialing us.
+		let endpoint = match endpoint {
+			ConnectedPoint::Dialer { address } if self.hidden_peers.contains(&peer_id) =>
+				ConnectedPoint::Listener { local_addr: address.clone(), send_back_addr: address },
+			endpoint => endpoint,
+		};
 		NetworkBehaviour::inject_connected(&mut self.kademlia, peer_id, endpoint)
 	}
 
@@ -278,8 +302,8 @@ where
 		}
 
 		// Poll the stream that fires when we need to start a random Kademlia query.
-		loop {
-			match self.next_kad_random_query.poll() {
+		while let Some(next_kad_random_query) = self.next_kad_random_query.as_mut() {
+			match next_kad_random_query.poll() {
 				Ok(Async::NotReady) => break,
 				Ok(Async::Ready(_)) => {
 					let random_peer_id = PeerId::random();
@@ -290,7 +314,7 @@ where
 
 					// Schedule the next random query with exponentially increasing delay,
 					// capped at 60 seconds.
-					self.next_kad_random_query = Delay::new(self.duration_to_next_kad).compat();
+					*next_kad_random_query = Delay::new(self.duration_to_next_kad);
 					self.duration_to_next_kad = cmp::min(self.duration_to_next_kad * 2,
 						Duration::from_secs(60));
 				},
@@ -428,6 +452,9 @@ mod tests {
 	use libp2p::core::upgrade::{InboundUpgradeExt, OutboundUpgradeExt};
 	use libp2p::swarm::Swarm;
 	use std::collections::HashSet;
+	use libp2p::PeerId;
+	use libp2p::core::{ConnectedPoint, nodes::Substream, muxing::StreamMuxerBox};
+	use libp2p::swarm::NetworkBehaviour;
 	use super::{DiscoveryBehaviour, DiscoveryOut};
 
 	#[test]
@@ -457,7 +484,13 @@ mod tests {
 					upgrade::apply(stream, upgrade, endpoint, libp2p::core::upgrade::Version::V1)
 				});
 
-			let behaviour = DiscoveryBehaviour::new(keypair.public(), user_defined.clone(), false);
+			let behaviour = DiscoveryBehaviour::new(
+				keypair.public(),
+				user_defined.clone(),
+				HashSet::new(),
+				true,
+				false,
+			);
 			let mut swarm = Swarm::new(transport, behaviour, keypair.public().into_peer_id());
 			let listen_addr: Multiaddr = format!("/memory/{}", rand::random::<u64>()).parse().unwrap();
 
@@ -515,4 +548,27 @@ mod tests {
 
 		tokio::runtime::Runtime::new().unwrap().block_on(fut).unwrap();
 	}
+
+	#[test]
+	fn hidden_peers_are_not_in_the_routing_table() {
+		let address = |n: u64| -> Multiaddr { format!("/memory/{}", n).parse().unwrap() };
+		let (validator, other) = (PeerId::random(), PeerId::random());
+		let keypair = Keypair::generate_ed25519();
+		let mut sentry = DiscoveryBehaviour::<Substream<StreamMuxerBox>>::new(
+			keypair.public(),
+			vec![(validator.clone(), address(1)), (other.clone(), address(2))],
+			Some(validator.clone()).into_iter().collect(),
+			true,
+			false,
+		);
+
+		// The validator is dialed, and reports its addresses once connected.
+		assert_eq!(sentry.addresses_of_peer(&validator), vec![address(1)]);
+		sentry.inject_connected(validator.clone(), ConnectedPoint::Dialer { address: address(1) });
+		sentry.add_self_reported_address(&validator, address(3));
+
+		let known = sentry.known_peers().cloned().collect::<HashSet<_>>();
+		assert!(known.contains(&other));
+		assert!(!known.contains(&validator));
+	}
 }
@@ -14,13 +14,13 @@
 // You should have received a copy of the GNU General Public License
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::{DiscoveryNetBehaviour, config::ProtocolId};
+use crate::{DiscoveryNetBehaviour, clock, config::ProtocolId};
 use crate::legacy_proto::handler::{CustomProtoHandlerProto, CustomProtoHandlerOut, CustomProtoHandlerIn};
 use crate::legacy_proto::upgrade::RegisteredProtocol;
 use bytes::BytesMut;
 use fnv::FnvHashMap;
 use futures::prelude::*;
-use futures03::{compat::Compat, TryFutureExt as _, StreamExt as _, TryStreamExt as _};
+use futures03::{StreamExt as _, TryStreamExt as _};
 use libp2p::core::{ConnectedPoint, Multiaddr, PeerId};
 use libp2p::swarm::{NetworkBehaviour, NetworkBehaviourAction, PollParameters};
 use log::{debug, error, trace, warn};
@@ -103,7 +103,7 @@ enum PeerState {
 	/// The peerset requested that we connect to this peer. We are not connected to this node.
 	PendingRequest {
 		/// When to actually start dialing.
-		timer: Compat<futures_timer::Delay>,
+		timer: clock::Delay,
 		/// When the `timer` will trigger.
 		timer_deadline: Instant,
 	},
@@ -135,7 +135,7 @@ enum PeerState {
 		/// state mismatch.
 		open: bool,
 		/// When to enable this remote.
-		timer: Compat<futures_timer::Delay>,
+		timer: clock::Delay,
 		/// When the `timer` will trigger.
 		timer_deadline: Instant,
 	},
@@ -282,7 +282,7 @@ impl<TSubstream> LegacyProto<TSubstream> {
 				debug!(target: "sub-libp2p", "PSM <= Dropped({:?})", peer_id);
 				self.peerset.dropped(peer_id.clone());
 				let banned_until = Some(if let Some(ban) = ban {
-					cmp::max(timer_deadline, Instant::now() + ban)
+					cmp::max(timer_deadline, clock::now() + ban)
 				} else {
 					timer_deadline
 				});
@@ -298,7 +298,7 @@ impl<TSubstream> LegacyProto<TSubstream> {
 					peer_id: peer_id.clone(),
 					event: CustomProtoHandlerIn::Disable,
 				});
-				let banned_until = ban.map(|dur| Instant::now() + dur);
+				let banned_until = ban.map(|dur| clock::now() + dur);
 				*entry.into_mut() = PeerState::Disabled { open, connected_point, banned_until }
 			},
 
@@ -319,7 +319,7 @@ impl<TSubstream> LegacyProto<TSubstream> {
 					peer_id: peer_id.clone(),
 					event: CustomProtoHandlerIn::Disable,
 				});
-				let banned_until = ban.map(|dur| Instant::now() + dur);
+				let banned_until = ban.map(|dur| clock::now() + dur);
 				*entry.into_mut() = PeerState::Disabled { open: false, connected_point, banned_until }
 			},
 
@@ -384,11 +384,11 @@ impl<TSubstream> LegacyProto<TSubstream> {
 		};
 
 		match mem::replace(occ_entry.get_mut(), PeerState::Poisoned) {
-			PeerState::Banned { ref until } if *until > Instant::now() => {
+			PeerState::Banned { ref until } if *until > clock::now() => {
 				debug!(target: "sub-libp2p", "PSM => Connect({:?}): Will start to connect at \
 					until {:?}", occ_entry.key(), until);
 				*occ_entry.into_mut() = PeerState::PendingRequest {
-					timer: futures_timer::Delay::new_at(until.clone()).compat(),
+					timer: clock::Delay::new_at(until.clone()),
 					timer_deadline: until.clone(),
 				};
 			},
@@ -401,13 +401,13 @@ impl<TSubstream> LegacyProto<TSubstream> {
 			},
 
 			PeerState::Disabled { open, ref connected_point, banned_until: Some(ref banned) }
-				if *banned > Instant::now() => {
+				if *banned > clock::now() => {
 				debug!(target: "sub-libp2p", "PSM => Connect({:?}): Has idle connection through \
 					{:?} but node is banned until {:?}", occ_entry.key(), connected_point, banned);
 				*occ_entry.into_mut() = PeerState::DisabledPendingEnable {
 					connected_point: connected_point.clone(),
 					open,
-					timer: futures_timer::Delay::new_at(banned.clone()).compat(),
+					timer: clock::Delay::new_at(banned.clone()),
 					timer_deadline: banned.clone(),
 				};
 			},
@@ -746,7 +746,7 @@ where
 
 				let ban_dur = Uniform::new(5, 10).sample(&mut rand::thread_rng());
 				self.peers.insert(peer_id.clone(), PeerState::Banned {
-					until: Instant::now() + Duration::from_secs(ban_dur)
+					until: clock::now() + Duration::from_secs(ban_dur)
 				});
 
 				if open {
@@ -795,7 +795,7 @@ where
 				PeerState::Requested | PeerState::PendingRequest { .. } => {
 					debug!(target: "sub-libp2p", "Libp2p => Dial failure for {:?}", peer_id);
 					*entry.into_mut() = PeerState::Banned {
-						until: Instant::now() + Duration::from_secs(5)
+						until: clock::now() + Duration::from_secs(5)
 					};
 					debug!(target: "sub-libp2p", "PSM <= Dropped({:?})", peer_id);
 					self.peerset.dropped(peer_id.clone())
@@ -179,7 +179,12 @@ mod service;
 mod transport;
 
 pub mod config;
+pub mod dht_client;
 pub mod error;
+pub mod clock;
+
+#[cfg(any(test, feature = "test-helpers"))]
+pub mod simulation;
 
 #[cfg(any(test, feature = "test-helpers"))]
 pub mod test;
@@ -191,6 +196,11 @@ pub use service::{
 };
 pub use protocol::{PeerInfo, Context, consensus_gossip, message, specialization};
 pub use protocol::event::{Event, DhtEvent};
+pub use protocol::notifications::{
+	NotificationProtocolConfig, NotificationProtocolName, NotificationValidator,
+	NotificationValidation, NotificationEvent,
+};
+pub use dht_client::{DhtClient, DhtNetwork};
 pub use protocol::sync::SyncState;
 pub use libp2p::{Multiaddr, PeerId};
 #[doc(inline)]
@@ -14,11 +14,10 @@
 // You should have received a copy of the GNU General Public License
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::{DiscoveryNetBehaviour, config::ProtocolId};
+use crate::{DiscoveryNetBehaviour, clock, config::ProtocolId};
 use crate::legacy_proto::{LegacyProto, LegacyProtoOut};
 use bytes::BytesMut;
 use futures::prelude::*;
-use futures03::{StreamExt as _, TryStreamExt as _};
 use libp2p::{Multiaddr, PeerId};
 use libp2p::core::{ConnectedPoint, nodes::Substream, muxing::StreamMuxerBox};
 use libp2p::swarm::{ProtocolsHandler, IntoProtocolsHandler};
@@ -38,13 +37,14 @@ use sr_primitives::traits::{
 use message::{BlockAnnounce, BlockAttributes, Direction, FromBlock, Message, RequestId};
 use message::generic::{Message as GenericMessage, ConsensusMessage};
 use consensus_gossip::{ConsensusGossip, MessageRecipient as GossipMessageRecipient};
+use notifications::{Notifications, NotificationProtocolConfig, NotificationEvent, Outgoing as OutgoingNotification};
 use light_dispatch::{LightDispatch, LightDispatchNetwork, RequestData};
 use specialization::NetworkSpecialization;
 use sync::{ChainSync, SyncState};
 use crate::service::{TransactionPool, ExHashT};
 use crate::config::{BoxFinalityProofRequestBuilder, Roles};
 use rustc_hex::ToHex;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 use std::fmt::Write;
 use std::{cmp, num::NonZeroUsize, time};
@@ -59,6 +59,7 @@ pub mod consensus_gossip;
 pub mod message;
 pub mod event;
 pub mod light_dispatch;
+pub mod notifications;
 pub mod specialization;
 pub mod sync;
 
@@ -69,7 +70,7 @@ const TICK_TIMEOUT: time::Duration = time::Duration::from_millis(1100);
 const PROPAGATE_TIMEOUT: time::Duration = time::Duration::from_millis(2900);
 
 /// Current protocol version.
-pub(crate) const CURRENT_VERSION: u32 = 5;
+pub(crate) const CURRENT_VERSION: u32 = 6;
 /// Lowest version we support
 pub(crate) const MIN_VERSION: u32 = 3;
 
@@ -113,6 +114,8 @@ pub struct Protocol<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> {
 	sync: ChainSync<B>,
 	specialization: S,
 	consensus_gossip: ConsensusGossip<B>,
+	/// Application-defined notification protocols.
+	notifications: Notifications,
 	context_data: ContextData<B, H>,
 	// Connected peers pending Status message.
 	handshaking_peers: HashMap<PeerId, HandshakingPeer>,
@@ -425,8 +428,8 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 		let behaviour = LegacyProto::new(protocol_id, versions, peerset);
 
 		let protocol = Protocol {
-			tick_timeout: Box::new(futures_timer::Interval::new(TICK_TIMEOUT).map(|v| Ok::<_, ()>(v)).compat()),
-			propagate_timeout: Box::new(futures_timer::Interval::new(PROPAGATE_TIMEOUT).map(|v| Ok::<_, ()>(v)).compat()),
+			tick_timeout: Box::new(clock::Interval::new(TICK_TIMEOUT).map_err(|_| ())),
+			propagate_timeout: Box::new(clock::Interval::new(PROPAGATE_TIMEOUT).map_err(|_| ())),
 			config,
 			context_data: ContextData {
 				peers: HashMap::new(),
@@ -438,6 +441,7 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 			sync,
 			specialization,
 			consensus_gossip: ConsensusGossip::new(),
+			notifications: Notifications::default(),
 			handshaking_peers: HashMap::new(),
 			transaction_pool,
 			finality_proof_provider,
@@ -633,6 +637,16 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 					messages,
 				);
 			}
+			GenericMessage::NotificationHandshake(msg) => {
+				if let Some(reputation) = self.notifications.on_handshake(who.clone(), msg) {
+					self.peerset_handle.report_peer(who, reputation);
+				}
+			},
+			GenericMessage::Notification(msg) => {
+				if let Some(reputation) = self.notifications.on_notification(who.clone(), msg) {
+					self.peerset_handle.report_peer(who, reputation);
+				}
+			},
 			GenericMessage::ChainSpecific(msg) => self.specialization.on_message(
 				&mut ProtocolContext::new(&mut self.context_data, &mut self.behaviour, &self.peerset_handle),
 				who,
@@ -662,6 +676,11 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 		);
 	}
 
+	/// Sets the peers whose consensus gossip is relayed to our other peers as soon as it is received.
+	pub fn set_priority_gossip_peers(&mut self, peers: HashSet<PeerId>) {
+		self.consensus_gossip.set_priority_peers(peers);
+	}
+
 	/// Locks `self` and returns a context plus the `ConsensusGossip` struct.
 	pub fn consensus_gossip_lock<'a>(
 		&'a mut self,
@@ -698,10 +717,37 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 		}
 	}
 
+	/// Registers a notification protocol. The events of the protocol are sent on `events_tx`.
+	pub fn register_notification_protocol(
+		&mut self,
+		config: NotificationProtocolConfig,
+		events_tx: futures::sync::mpsc::Sender<NotificationEvent>,
+	) {
+		let outgoing = self.notifications.register(config, events_tx);
+		self.send_notifications(outgoing);
+	}
+
+	/// Sends a notification to a peer, if the notification protocol is open towards it.
+	pub fn write_notification(&mut self, who: PeerId, protocol: &[u8], message: Vec<u8>) {
+		let outgoing = self.notifications.write(who, protocol, message);
+		self.send_notifications(outgoing);
+	}
+
+	fn send_notifications(&mut self, outgoing: impl IntoIterator<Item = OutgoingNotification>) {
+		for message in outgoing {
+			match message {
+				OutgoingNotification::Handshake(who, message) =>
+					self.send_message(&who, GenericMessage::NotificationHandshake(message)),
+				OutgoingNotification::Notification(who, message) =>
+					self.send_message(&who, GenericMessage::Notification(message)),
+			}
+		}
+	}
+
 	/// Called when a new peer is connected
 	pub fn on_peer_connected(&mut self, who: PeerId) {
 		trace!(target: "sync", "Connecting {}", who);
-		self.handshaking_peers.insert(who.clone(), HandshakingPeer { timestamp: time::Instant::now() });
+		self.handshaking_peers.insert(who.clone(), HandshakingPeer { timestamp: clock::now() });
 		self.send_status(who);
 	}
 
@@ -713,6 +759,7 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 			self.handshaking_peers.remove(&peer);
 			self.context_data.peers.remove(&peer)
 		};
+		self.notifications.peer_disconnected(&peer);
 		if let Some(peer_data) = removed {
 			let mut context = ProtocolContext::new(&mut self.context_data, &mut self.behaviour, &self.peerset_handle);
 			if peer_data.info.protocol_version > 2 {
@@ -883,6 +930,7 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 		self.consensus_gossip.tick(
 			&mut ProtocolContext::new(&mut self.context_data, &mut self.behaviour, &self.peerset_handle)
 		);
+		self.notifications.flush();
 		self.maintain_peers();
 		self.light_dispatch.maintain_peers(LightDispatchIn {
 			behaviour: &mut self.behaviour,
@@ -891,7 +939,7 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 	}
 
 	fn maintain_peers(&mut self) {
-		let tick = time::Instant::now();
+		let tick = clock::now();
 		let mut aborting = Vec::new();
 		{
 			for (who, peer) in self.context_data.peers.iter() {
@@ -1017,6 +1065,10 @@ impl<B: BlockT, S: NetworkSpecialization<B>, H: ExHashT> Protocol<B, S, H> {
 				self.peerset_handle.report_peer(id, repu)
 			}
 		}
+		if protocol_version > 5 {
+			let handshakes = self.notifications.new_peer(who.clone());
+			self.send_notifications(handshakes);
+		}
 		let mut context = ProtocolContext::new(&mut self.context_data, &mut self.behaviour, &self.peerset_handle);
 		if protocol_version > 2 {
 			self.consensus_gossip.new_peer(&mut context, who.clone(), status.roles);
@@ -1713,7 +1765,7 @@ fn send_request<B: BlockT, H: ExHashT>(
 				trace!(target: "sync", "Request {} for {} is now obsolete.", request.id, who);
 				peer.obsolete_requests.insert(request.id, timestamp);
 			}
-			peer.block_request = Some((time::Instant::now(), r.clone()));
+			peer.block_request = Some((clock::now(), r.clone()));
 		}
 	}
 	send_message::<B>(behaviour, stats, who, message)
@@ -55,6 +55,7 @@ use sr_primitives::traits::{Block as BlockT, Hash, HashFor};
 use sr_primitives::ConsensusEngineId;
 pub use crate::message::generic::{Message, ConsensusMessage};
 use crate::protocol::Context;
+use crate::clock;
 use crate::config::Roles;
 
 // FIXME: Add additional spam/DoS attack protection: https://github.com/paritytech/substrate/issues/1115
@@ -289,6 +290,9 @@ pub struct ConsensusGossip<B: BlockT> {
 	known_messages: LruCache<B::Hash, ()>,
 	validators: HashMap<ConsensusEngineId, Arc<dyn Validator<B>>>,
 	next_broadcast: time::Instant,
+	/// Peers whose messages are propagated as soon as they are received, rather than on the next
+	/// topic broadcast or periodic rebroadcast.
+	priority_peers: HashSet<PeerId>,
 }
 
 impl<B: BlockT> ConsensusGossip<B> {
@@ -300,10 +304,17 @@ impl<B: BlockT> ConsensusGossip<B> {
 			messages: Default::default(),
 			known_messages: LruCache::new(KNOWN_MESSAGES_CACHE_SIZE),
 			validators: Default::default(),
-			next_broadcast: time::Instant::now() + REBROADCAST_INTERVAL,
+			next_broadcast: clock::now() + REBROADCAST_INTERVAL,
+			priority_peers: HashSet::new(),
 		}
 	}
 
+	/// Sets the peers whose messages are propagated to the other peers as soon as they are
+	/// received. Used by sentry nodes to relay the gossip of the validators they protect.
+	pub fn set_priority_peers(&mut self, peers: HashSet<PeerId>) {
+		self.priority_peers = peers;
+	}
+
 	/// Closes all notification streams.
 	pub fn abort(&mut self) {
 		self.live_message_sinks.clear();
@@ -389,9 +400,9 @@ impl<B: BlockT> ConsensusGossip<B> {
 	/// Perform periodic maintenance
 	pub fn tick(&mut self, protocol: &mut dyn Context<B>) {
 		self.collect_garbage();
-		if time::Instant::now() >= self.next_broadcast {
+		if clock::now() >= self.next_broadcast {
 			self.rebroadcast(protocol);
-			self.next_broadcast = time::Instant::now() + REBROADCAST_INTERVAL;
+			self.next_broadcast = clock::now() + REBROADCAST_INTERVAL;
 		}
 	}
 
@@ -532,6 +543,15 @@ impl<B: BlockT> ConsensusGossip<B> {
 						}
 					}
 					if keep {
+						if self.priority_peers.contains(&who) {
+							propagate(
+								protocol,
+								iter::once((&message_hash, &topic, &message)),
+								MessageIntent::broadcast(),
+								&mut self.peers,
+								&self.validators,
+							);
+						}
 						self.register_message_hashed(message_hash, topic, message, Some(who.clone()));
 					}
 				} else {
@@ -916,4 +936,45 @@ mod tests {
 			(data.clone(), MessageIntent::PeriodicRebroadcast),
 		);
 	}
+
+	#[test]
+	fn relays_messages_from_priority_peers_immediately() {
+		#[derive(Default)]
+		struct RecordingContext {
+			sent: Vec<(PeerId, Vec<ConsensusMessage>)>,
+		}
+		impl<B: BlockT> Context<B> for RecordingContext {
+			fn report_peer(&mut self, _who: PeerId, _reputation: i32) {}
+			fn disconnect_peer(&mut self, _who: PeerId) {}
+			fn send_consensus(&mut self, who: PeerId, consensus: Vec<ConsensusMessage>) {
+				self.sent.push((who, consensus));
+			}
+			fn send_chain_specific(&mut self, _who: PeerId, _message: Vec<u8>) {}
+		}
+
+		let validator = PeerId::random();
+		let other_validator = PeerId::random();
+		let peer = PeerId::random();
+
+		let mut context = RecordingContext::default();
+		let mut consensus = ConsensusGossip::<Block>::new();
+		consensus.register_validator_internal([0, 0, 0, 0], Arc::new(AllowAll));
+		consensus.set_priority_peers(vec![validator.clone()].into_iter().collect());
+		consensus.new_peer(&mut context, validator.clone(), Roles::AUTHORITY);
+		consensus.new_peer(&mut context, other_validator.clone(), Roles::AUTHORITY);
+		consensus.new_peer(&mut context, peer.clone(), Roles::FULL);
+
+		// messages from a non-priority peer wait for the next broadcast.
+		let msg = ConsensusMessage { data: vec![1], engine_id: [0, 0, 0, 0] };
+		consensus.on_incoming(&mut context, other_validator.clone(), vec![msg]);
+		assert!(context.sent.is_empty());
+
+		// messages from a priority peer are relayed to everyone except the sender.
+		let msg = ConsensusMessage { data: vec![2], engine_id: [0, 0, 0, 0] };
+		consensus.on_incoming(&mut context, validator.clone(), vec![msg.clone()]);
+		let recipients: HashSet<_> = context.sent.iter().map(|(who, _)| who.clone()).collect();
+		assert_eq!(recipients, vec![other_validator, peer].into_iter().collect());
+		assert_eq!(context.sent.len(), 2);
+		assert!(context.sent.iter().all(|(_, messages)| messages == &vec![msg.clone()]));
+	}
 }
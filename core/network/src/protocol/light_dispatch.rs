@@ -29,6 +29,7 @@ use client::error::Error as ClientError;
 use client::light::fetcher::{FetchChecker, RemoteHeaderRequest,
 	RemoteCallRequest, RemoteReadRequest, RemoteChangesRequest, ChangesProof,
 	RemoteReadChildRequest, RemoteBodyRequest, StorageProof};
+use crate::clock;
 use crate::message::{self, BlockAttributes, Direction, FromBlock, RequestId};
 use libp2p::PeerId;
 use crate::config::Roles;
@@ -248,7 +249,7 @@ impl<B: BlockT> LightDispatch<B> where
 
 		self.pending_requests.push_back(Request {
 			id: request_id,
-			timestamp: Instant::now(),
+			timestamp: clock::now(),
 			retry_count,
 			data,
 		});
@@ -340,7 +341,7 @@ impl<B: BlockT> LightDispatch<B> where
 
 	/// Must be called periodically in order to perform maintenance.
 	pub fn maintain_peers(&mut self, mut network: impl LightDispatchNetwork<B>) {
-		let now = Instant::now();
+		let now = clock::now();
 
 		loop {
 			match self.active_peers.front() {
@@ -576,7 +577,7 @@ impl<B: BlockT> LightDispatch<B> where
 			last_peer = self.idle_peers.back().cloned();
 
 			let mut request = self.pending_requests.pop_front().expect("checked in loop condition; qed");
-			request.timestamp = Instant::now();
+			request.timestamp = clock::now();
 			trace!(target: "sync", "Dispatching remote request {} to peer {}", request.id, peer);
 			request.send_to(&mut network, &peer);
 			self.active_peers.insert(peer, request);
@@ -154,6 +154,15 @@ pub mod generic {
 		pub data: Vec<u8>,
 	}
 
+	/// Message of an application-defined notification protocol.
+	#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+	pub struct NotificationMessage {
+		/// Name of the protocol.
+		pub protocol: Vec<u8>,
+		/// Message payload.
+		pub data: Vec<u8>,
+	}
+
 	/// Block data sent in the response.
 	#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
 	pub struct BlockData<Header, Hash, Extrinsic> {
@@ -219,6 +228,10 @@ pub mod generic {
 		FinalityProofResponse(FinalityProofResponse<Hash>),
 		/// Batch of consensus protocol messages.
 		ConsensusBatch(Vec<ConsensusMessage>),
+		/// Handshake of a notification protocol.
+		NotificationHandshake(NotificationMessage),
+		/// Notification protocol message.
+		Notification(NotificationMessage),
 		/// Chain-specific message.
 		#[codec(index = "255")]
 		ChainSpecific(Vec<u8>),
@@ -246,6 +259,8 @@ pub mod generic {
 				Message::FinalityProofRequest(_) => "FinalityProofRequest",
 				Message::FinalityProofResponse(_) => "FinalityProofResponse",
 				Message::ConsensusBatch(_) => "ConsensusBatch",
+				Message::NotificationHandshake(_) => "NotificationHandshake",
+				Message::Notification(_) => "Notification",
 				Message::ChainSpecific(_) => "ChainSpecific",
 			}
 		}
@@ -0,0 +1,443 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Application-defined notification protocols.
+//!
+//! A notification protocol is identified by a name of arbitrary length and carries opaque
+//! messages between peers, without any of the topic or consensus-engine semantics of the
+//! consensus gossip. Protocols are registered at runtime through
+//! [`NetworkService::register_notification_protocol`](crate::NetworkService::register_notification_protocol).
+//!
+//! Once the status handshake with a peer has completed, each side sends the handshake of every
+//! protocol it has registered. A protocol is *open* towards a peer once we have received and
+//! accepted that peer's handshake for it. Notifications are only sent to, and only accepted
+//! from, peers towards which the protocol is open.
+//!
+//! Events are delivered to the user through a bounded channel. If the user doesn't keep up,
+//! incoming notifications are dropped, while `Opened` and `Closed` events are kept aside and
+//! delivered as soon as there is room again.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use futures::sync::mpsc;
+use libp2p::PeerId;
+use log::{trace, debug};
+
+use crate::protocol::message::generic::NotificationMessage;
+
+/// Reputation change for a peer sending us a notification that the validator rejected.
+const BAD_NOTIFICATION_REPUTATION_CHANGE: i32 = -(1 << 10);
+/// Reputation change for a peer sending us a handshake that the validator refused.
+const BAD_HANDSHAKE_REPUTATION_CHANGE: i32 = -(1 << 10);
+/// Reputation change for a peer sending us a notification on a protocol that isn't open.
+const UNEXPECTED_NOTIFICATION_REPUTATION_CHANGE: i32 = -(1 << 8);
+
+/// Name of a notification protocol.
+pub type NotificationProtocolName = Cow<'static, [u8]>;
+
+/// Configuration of a notification protocol.
+pub struct NotificationProtocolConfig {
+	/// Name of the protocol. Must be unique on the node, for example `b"/oracle/prices/1"`.
+	pub name: NotificationProtocolName,
+	/// Handshake sent to every peer we connect to.
+	pub handshake: Vec<u8>,
+	/// Validates the handshakes and notifications received from peers.
+	pub validator: Arc<dyn NotificationValidator>,
+	/// Maximum number of events buffered for the user before incoming notifications are dropped.
+	pub buffer_size: usize,
+}
+
+/// Result of validating a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationValidation {
+	/// The notification is passed on to the user.
+	Accept,
+	/// The notification is silently ignored.
+	Discard,
+	/// The notification is ignored and the sender's reputation is lowered.
+	Reject,
+}
+
+/// Validates what peers send us on a notification protocol.
+pub trait NotificationValidator: Send + Sync {
+	/// Returns true if the protocol can be opened with a peer that sent us this handshake.
+	fn validate_handshake(&self, _who: &PeerId, _handshake: &[u8]) -> bool {
+		true
+	}
+
+	/// Validates a notification sent by a peer.
+	fn validate(&self, who: &PeerId, message: &[u8]) -> NotificationValidation;
+}
+
+/// Event generated by a notification protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationEvent {
+	/// The protocol has been opened with a peer, which sent us the given handshake.
+	Opened {
+		/// The peer.
+		remote: PeerId,
+		/// The handshake it sent.
+		handshake: Vec<u8>,
+	},
+	/// The protocol with a peer has been closed.
+	Closed {
+		/// The peer.
+		remote: PeerId,
+	},
+	/// A peer sent us a notification that the validator accepted.
+	Notification {
+		/// The peer.
+		remote: PeerId,
+		/// The notification.
+		message: Vec<u8>,
+	},
+}
+
+struct RegisteredProtocol {
+	handshake: Vec<u8>,
+	validator: Arc<dyn NotificationValidator>,
+	events_tx: mpsc::Sender<NotificationEvent>,
+	/// Events that couldn't be delivered yet because the channel was full.
+	pending: VecDeque<NotificationEvent>,
+	/// Peers whose handshake we accepted.
+	open: HashSet<PeerId>,
+}
+
+impl RegisteredProtocol {
+	/// Delivers an event to the user. Returns false if the user is gone.
+	fn deliver(&mut self, event: NotificationEvent) -> bool {
+		if !self.flush() {
+			return false;
+		}
+
+		let event = if self.pending.is_empty() {
+			match self.events_tx.try_send(event) {
+				Ok(()) => return true,
+				Err(ref err) if err.is_disconnected() => return false,
+				Err(err) => err.into_inner(),
+			}
+		} else {
+			event
+		};
+
+		match event {
+			NotificationEvent::Notification { remote, .. } =>
+				debug!(target: "sync", "Dropping notification from {}: user is lagging behind", remote),
+			event => self.pending.push_back(event),
+		}
+		true
+	}
+
+	/// Delivers the pending events. Returns false if the user is gone.
+	fn flush(&mut self) -> bool {
+		while let Some(event) = self.pending.pop_front() {
+			match self.events_tx.try_send(event) {
+				Ok(()) => {},
+				Err(ref err) if err.is_disconnected() => return false,
+				Err(err) => {
+					self.pending.push_front(err.into_inner());
+					break;
+				},
+			}
+		}
+		true
+	}
+}
+
+/// Set of notification protocols registered on the node.
+#[derive(Default)]
+pub(crate) struct Notifications {
+	protocols: HashMap<NotificationProtocolName, RegisteredProtocol>,
+	/// Connected peers that support notification protocols.
+	peers: HashSet<PeerId>,
+}
+
+/// Message to send to a peer as a result of a call on `Notifications`.
+pub(crate) enum Outgoing {
+	/// Handshake of one of our protocols.
+	Handshake(PeerId, NotificationMessage),
+	/// A notification.
+	Notification(PeerId, NotificationMessage),
+}
+
+impl Notifications {
+	/// Registers a new protocol. Returns the handshakes to send to the peers we are connected to.
+	///
+	/// If a protocol with the same name is already registered, the new registration is ignored
+	/// and `events_tx` is dropped.
+	pub fn register(
+		&mut self,
+		config: NotificationProtocolConfig,
+		events_tx: mpsc::Sender<NotificationEvent>,
+	) -> Vec<Outgoing> {
+		if self.protocols.contains_key(&config.name) {
+			debug!(
+				target: "sync",
+				"Notification protocol {} is already registered",
+				String::from_utf8_lossy(&config.name),
+			);
+			return Vec::new();
+		}
+
+		let outgoing = self.peers.iter()
+			.map(|who| Outgoing::Handshake(who.clone(), NotificationMessage {
+				protocol: config.name.to_vec(),
+				data: config.handshake.clone(),
+			}))
+			.collect();
+		self.protocols.insert(config.name, RegisteredProtocol {
+			handshake: config.handshake,
+			validator: config.validator,
+			events_tx,
+			pending: VecDeque::new(),
+			open: HashSet::new(),
+		});
+		outgoing
+	}
+
+	/// Called when a peer supporting notifications is connected. Returns the handshakes to send.
+	pub fn new_peer(&mut self, who: PeerId) -> Vec<Outgoing> {
+		self.peers.insert(who.clone());
+		self.protocols.iter()
+			.map(|(name, protocol)| Outgoing::Handshake(who.clone(), NotificationMessage {
+				protocol: name.to_vec(),
+				data: protocol.handshake.clone(),
+			}))
+			.collect()
+	}
+
+	/// Called when a peer is disconnected.
+	pub fn peer_disconnected(&mut self, who: &PeerId) {
+		if !self.peers.remove(who) {
+			return;
+		}
+		self.protocols.retain(|_, protocol| {
+			if protocol.open.remove(who) {
+				protocol.deliver(NotificationEvent::Closed { remote: who.clone() })
+			} else {
+				true
+			}
+		});
+	}
+
+	/// Called when a peer sends us a handshake. Returns the reputation change to apply, if any.
+	pub fn on_handshake(&mut self, who: PeerId, message: NotificationMessage) -> Option<i32> {
+		if !self.peers.contains(&who) {
+			return None;
+		}
+		let alive = {
+			let protocol = match self.protocols.get_mut(&message.protocol[..]) {
+				Some(protocol) => protocol,
+				// We don't speak this protocol; the peer won't hear from us on it.
+				None => return None,
+			};
+			if protocol.open.contains(&who) {
+				return Some(UNEXPECTED_NOTIFICATION_REPUTATION_CHANGE);
+			}
+			if !protocol.validator.validate_handshake(&who, &message.data) {
+				trace!(target: "sync", "Refused notification handshake from {}", who);
+				return Some(BAD_HANDSHAKE_REPUTATION_CHANGE);
+			}
+			protocol.open.insert(who.clone());
+			protocol.deliver(NotificationEvent::Opened { remote: who, handshake: message.data })
+		};
+		if !alive {
+			self.protocols.remove(&message.protocol[..]);
+		}
+		None
+	}
+
+	/// Called when a peer sends us a notification. Returns the reputation change to apply, if any.
+	pub fn on_notification(&mut self, who: PeerId, message: NotificationMessage) -> Option<i32> {
+		let alive = {
+			let protocol = match self.protocols.get_mut(&message.protocol[..]) {
+				Some(protocol) if protocol.open.contains(&who) => protocol,
+				_ => return Some(UNEXPECTED_NOTIFICATION_REPUTATION_CHANGE),
+			};
+			match protocol.validator.validate(&who, &message.data) {
+				NotificationValidation::Accept => protocol.deliver(NotificationEvent::Notification {
+					remote: who,
+					message: message.data,
+				}),
+				NotificationValidation::Discard => return None,
+				NotificationValidation::Reject => return Some(BAD_NOTIFICATION_REPUTATION_CHANGE),
+			}
+		};
+		if !alive {
+			self.protocols.remove(&message.protocol[..]);
+		}
+		None
+	}
+
+	/// Builds a notification to send to a peer, if the protocol is open towards it.
+	pub fn write(&self, who: PeerId, protocol: &[u8], data: Vec<u8>) -> Option<Outgoing> {
+		match self.protocols.get(protocol) {
+			Some(registered) if registered.open.contains(&who) =>
+				Some(Outgoing::Notification(who, NotificationMessage { protocol: protocol.to_vec(), data })),
+			_ => {
+				trace!(target: "sync", "Not sending notification to {}: protocol not open", who);
+				None
+			},
+		}
+	}
+
+	/// Delivers the events held back because of back-pressure, and forgets the protocols whose
+	/// user is gone.
+	pub fn flush(&mut self) {
+		self.protocols.retain(|_, protocol| protocol.flush());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{Async, Future, Stream};
+
+	struct AcceptEven;
+
+	impl NotificationValidator for AcceptEven {
+		fn validate_handshake(&self, _who: &PeerId, handshake: &[u8]) -> bool {
+			handshake == b"hello"
+		}
+
+		fn validate(&self, _who: &PeerId, message: &[u8]) -> NotificationValidation {
+			if message[0] % 2 == 0 {
+				NotificationValidation::Accept
+			} else {
+				NotificationValidation::Reject
+			}
+		}
+	}
+
+	const NAME: &[u8] = b"/test/1";
+
+	fn register(notifications: &mut Notifications, buffer_size: usize)
+		-> (Vec<Outgoing>, mpsc::Receiver<NotificationEvent>)
+	{
+		let (tx, rx) = mpsc::channel(buffer_size);
+		let config = NotificationProtocolConfig {
+			name: NAME.into(),
+			handshake: b"hello".to_vec(),
+			validator: Arc::new(AcceptEven),
+			buffer_size,
+		};
+		(notifications.register(config, tx), rx)
+	}
+
+	fn message(data: &[u8]) -> NotificationMessage {
+		NotificationMessage { protocol: NAME.to_vec(), data: data.to_vec() }
+	}
+
+	fn next(rx: &mut mpsc::Receiver<NotificationEvent>) -> Option<NotificationEvent> {
+		match rx.poll() {
+			Ok(Async::Ready(event)) => event,
+			_ => None,
+		}
+	}
+
+	#[test]
+	fn handshake_opens_protocol() {
+		futures::future::lazy(|| -> Result<(), ()> {
+			let mut notifications = Notifications::default();
+			let peer = PeerId::random();
+			let (_, mut rx) = register(&mut notifications, 8);
+
+			assert_eq!(notifications.new_peer(peer.clone()).len(), 1);
+			assert!(notifications.write(peer.clone(), NAME, vec![0]).is_none());
+			assert_eq!(
+				notifications.on_notification(peer.clone(), message(&[0])),
+				Some(UNEXPECTED_NOTIFICATION_REPUTATION_CHANGE),
+			);
+
+			assert_eq!(notifications.on_handshake(peer.clone(), message(b"hello")), None);
+			assert_eq!(
+				next(&mut rx),
+				Some(NotificationEvent::Opened { remote: peer.clone(), handshake: b"hello".to_vec() }),
+			);
+			assert!(notifications.write(peer.clone(), NAME, vec![0]).is_some());
+
+			assert_eq!(notifications.on_notification(peer.clone(), message(&[2])), None);
+			assert_eq!(
+				notifications.on_notification(peer.clone(), message(&[3])),
+				Some(BAD_NOTIFICATION_REPUTATION_CHANGE),
+			);
+			assert_eq!(
+				next(&mut rx),
+				Some(NotificationEvent::Notification { remote: peer.clone(), message: vec![2] }),
+			);
+
+			notifications.peer_disconnected(&peer);
+			assert_eq!(next(&mut rx), Some(NotificationEvent::Closed { remote: peer }));
+			Ok(())
+		}).wait().unwrap();
+	}
+
+	#[test]
+	fn refused_handshake_keeps_protocol_closed() {
+		let mut notifications = Notifications::default();
+		let peer = PeerId::random();
+		let (_, _rx) = register(&mut notifications, 8);
+
+		notifications.new_peer(peer.clone());
+		assert_eq!(
+			notifications.on_handshake(peer.clone(), message(b"bad")),
+			Some(BAD_HANDSHAKE_REPUTATION_CHANGE),
+		);
+		assert!(notifications.write(peer, NAME, vec![0]).is_none());
+	}
+
+	#[test]
+	fn lagging_user_loses_notifications_but_not_state_changes() {
+		futures::future::lazy(|| -> Result<(), ()> {
+			let mut notifications = Notifications::default();
+			let peer = PeerId::random();
+			let (_, mut rx) = register(&mut notifications, 0);
+
+			notifications.new_peer(peer.clone());
+			notifications.on_handshake(peer.clone(), message(b"hello"));
+			for _ in 0..10 {
+				notifications.on_notification(peer.clone(), message(&[0]));
+			}
+			notifications.peer_disconnected(&peer);
+
+			let mut events = Vec::new();
+			while let Some(event) = next(&mut rx) {
+				events.push(event);
+				notifications.flush();
+			}
+			assert_eq!(events.first(), Some(&NotificationEvent::Opened {
+				remote: peer.clone(),
+				handshake: b"hello".to_vec(),
+			}));
+			assert_eq!(events.last(), Some(&NotificationEvent::Closed { remote: peer }));
+			assert!(events.len() < 12);
+			Ok(())
+		}).wait().unwrap();
+	}
+
+	#[test]
+	fn dropped_receiver_unregisters_protocol() {
+		let mut notifications = Notifications::default();
+		let peer = PeerId::random();
+		let (_, rx) = register(&mut notifications, 8);
+		drop(rx);
+
+		notifications.new_peer(peer.clone());
+		notifications.on_handshake(peer.clone(), message(b"hello"));
+		assert!(notifications.protocols.is_empty());
+	}
+}
@@ -15,6 +15,7 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 use client::error::Error as ClientError;
+use crate::clock;
 use crate::protocol::sync::{PeerSync, PeerSyncState};
 use fork_tree::ForkTree;
 use libp2p::PeerId;
@@ -116,7 +117,7 @@ impl<B: BlockT> ExtraRequests<B> {
 				self.importing_requests.insert(request);
 				return Some((who, request.0, request.1, r))
 			}
-			self.failed_requests.entry(request).or_insert(Vec::new()).push((who, Instant::now()));
+			self.failed_requests.entry(request).or_insert(Vec::new()).push((who, clock::now()));
 			self.pending_requests.push_front(request);
 		}
 		None
@@ -246,8 +247,9 @@ impl<'a, B: BlockT> Matcher<'a, B> {
 		}
 
 		// clean up previously failed requests so we can retry again
+		let now = clock::now();
 		for requests in self.extras.failed_requests.values_mut() {
-			requests.retain(|(_, instant)| instant.elapsed() < EXTRA_RETRY_WAIT);
+			requests.retain(|(_, instant)| now.duration_since(*instant) < EXTRA_RETRY_WAIT);
 		}
 
 		while let Some(request) = self.extras.pending_requests.pop_front() {
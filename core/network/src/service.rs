@@ -47,6 +47,9 @@ use crate::config::{Params, TransportConfig};
 use crate::error::Error;
 use crate::protocol::{self, Protocol, Context, CustomMessageOutcome, PeerInfo};
 use crate::protocol::consensus_gossip::{ConsensusGossip, MessageRecipient as GossipMessageRecipient};
+use crate::protocol::notifications::{
+	NotificationProtocolConfig, NotificationProtocolName, NotificationEvent,
+};
 use crate::protocol::{event::Event, light_dispatch::{AlwaysBadChecker, RequestData}};
 use crate::protocol::specialization::NetworkSpecialization;
 use crate::protocol::sync::SyncState;
@@ -177,11 +180,46 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkWorker
 			}
 		}
 
+		// A validator behind sentries only ever talks to its sentries.
+		let behind_sentries = !params.network_config.sentry_nodes.is_empty();
+		let mut sentries = HashSet::new();
+		for sentry in params.network_config.sentry_nodes.iter() {
+			if let Ok((peer_id, addr)) = parse_str_addr(sentry) {
+				reserved_nodes.push(peer_id.clone());
+				sentries.insert(peer_id.clone());
+				known_addresses.push((peer_id, addr));
+			} else {
+				warn!(target: "sub-libp2p", "Not a valid sentry node address: {}", sentry);
+			}
+		}
+
+		// A sentry stays connected to the validators it protects.
+		let mut protected_validators = HashSet::new();
+		for validator in params.network_config.protected_validators.iter() {
+			if let Ok((peer_id, addr)) = parse_str_addr(validator) {
+				reserved_nodes.push(peer_id.clone());
+				protected_validators.insert(peer_id.clone());
+				known_addresses.push((peer_id, addr));
+			} else {
+				warn!(target: "sub-libp2p", "Not a valid protected validator address: {}", validator);
+			}
+		}
+
+		if behind_sentries {
+			reserved_nodes.retain(|peer_id| {
+				if !sentries.contains(peer_id) {
+					warn!(target: "sub-libp2p", "Ignoring reserved node {}, which isn't a sentry node", peer_id);
+				}
+				sentries.contains(peer_id)
+			});
+		}
+
 		let peerset_config = peerset::PeersetConfig {
 			in_peers: params.network_config.in_peers,
 			out_peers: params.network_config.out_peers,
 			bootnodes,
-			reserved_only: params.network_config.non_reserved_mode == NonReservedPeerMode::Deny,
+			reserved_only: params.network_config.non_reserved_mode == NonReservedPeerMode::Deny
+				|| behind_sentries,
 			reserved_nodes,
 		};
 
@@ -193,7 +231,7 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkWorker
 
 		let num_connected = Arc::new(AtomicUsize::new(0));
 		let is_major_syncing = Arc::new(AtomicBool::new(false));
-		let (protocol, peerset_handle) = Protocol::new(
+		let (mut protocol, peerset_handle) = Protocol::new(
 			protocol::ProtocolConfig {
 				roles: params.roles,
 				max_parallel_downloads: params.network_config.max_parallel_downloads,
@@ -209,6 +247,7 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkWorker
 			peerset_config,
 			params.block_announce_validator
 		)?;
+		protocol.set_priority_gossip_peers(protected_validators.clone());
 
 		// Build the swarm.
 		let (mut swarm, bandwidth) = {
@@ -222,18 +261,27 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkWorker
 				user_agent,
 				local_public,
 				known_addresses,
+				// A sentry doesn't reveal the validators it protects, which in turn don't look
+				// for other nodes.
+				protected_validators,
+				!behind_sentries,
 				match params.network_config.transport {
 					TransportConfig::MemoryOnly => false,
-					TransportConfig::Normal { enable_mdns, .. } => enable_mdns,
+					#[cfg(any(test, feature = "test-helpers"))]
+					TransportConfig::Simulated(_) => false,
+					TransportConfig::Normal { enable_mdns, .. } => enable_mdns && !behind_sentries,
 				}
 			);
 			let (transport, bandwidth) = {
-				let (config_mem, config_wasm) = match params.network_config.transport {
-					TransportConfig::MemoryOnly => (true, None),
+				let (config_mem, config_wasm, config_sim) = match params.network_config.transport {
+					TransportConfig::MemoryOnly => (true, None, None),
 					TransportConfig::Normal { wasm_external_transport, .. } =>
-						(false, wasm_external_transport)
+						(false, wasm_external_transport, None),
+					#[cfg(any(test, feature = "test-helpers"))]
+					TransportConfig::Simulated(endpoint) => (true, None, Some(endpoint)),
 				};
-				transport::build_transport(local_identity, config_mem, config_wasm)
+				let allowed_peers = if behind_sentries { Some(sentries) } else { None };
+				transport::build_transport(local_identity, config_mem, config_wasm, config_sim, allowed_peers)
 			};
 			(Swarm::<B, S, H>::new(transport, behaviour, local_peer_id.clone()), bandwidth)
 		};
@@ -443,6 +491,38 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> NetworkServic
 			));
 	}
 
+	/// Registers a notification protocol.
+	///
+	/// Returns the stream of events of the protocol. At most `config.buffer_size` events are
+	/// buffered; past that, incoming notifications are dropped until the stream is polled again.
+	/// If a protocol with the same name is already registered, the returned stream ends
+	/// immediately. Dropping the stream unregisters the protocol.
+	pub fn register_notification_protocol(
+		&self,
+		config: NotificationProtocolConfig,
+	) -> mpsc::Receiver<NotificationEvent> {
+		let (tx, rx) = mpsc::channel(config.buffer_size);
+		let _ = self
+			.to_worker
+			.unbounded_send(ServerToWorkerMsg::RegisterNotificationProtocol(config, tx));
+		rx
+	}
+
+	/// Sends a notification to a peer.
+	///
+	/// The notification is silently dropped if the protocol isn't open with this peer, i.e. if
+	/// we haven't received and accepted the handshake of the peer for this protocol.
+	pub fn write_notification(
+		&self,
+		target: PeerId,
+		protocol: NotificationProtocolName,
+		message: Vec<u8>,
+	) {
+		let _ = self
+			.to_worker
+			.unbounded_send(ServerToWorkerMsg::WriteNotification(target, protocol, message));
+	}
+
 	/// Report a given peer as either beneficial (+) or costly (-) according to the
 	/// given scalar.
 	pub fn report_peer(&self, who: PeerId, cost_benefit: i32) {
@@ -628,6 +708,8 @@ enum ServerToWorkerMsg<B: BlockT, S: NetworkSpecialization<B>> {
 	ExecuteWithSpec(Box<dyn FnOnce(&mut S, &mut dyn Context<B>) + Send>),
 	ExecuteWithGossip(Box<dyn FnOnce(&mut ConsensusGossip<B>, &mut dyn Context<B>) + Send>),
 	GossipConsensusMessage(B::Hash, ConsensusEngineId, Vec<u8>, GossipMessageRecipient),
+	RegisterNotificationProtocol(NotificationProtocolConfig, mpsc::Sender<NotificationEvent>),
+	WriteNotification(PeerId, NotificationProtocolName, Vec<u8>),
 	GetValue(record::Key),
 	PutValue(record::Key, Vec<u8>),
 	AddKnownAddress(PeerId, Multiaddr),
@@ -698,6 +780,10 @@ impl<B: BlockT + 'static, S: NetworkSpecialization<B>, H: ExHashT> Stream for Ne
 				}
 				ServerToWorkerMsg::GossipConsensusMessage(topic, engine_id, message, recipient) =>
 					self.network_service.user_protocol_mut().gossip_consensus_message(topic, engine_id, message, recipient),
+				ServerToWorkerMsg::RegisterNotificationProtocol(config, tx) =>
+					self.network_service.user_protocol_mut().register_notification_protocol(config, tx),
+				ServerToWorkerMsg::WriteNotification(target, protocol, message) =>
+					self.network_service.user_protocol_mut().write_notification(target, &protocol, message),
 				ServerToWorkerMsg::AnnounceBlock(hash, data) =>
 					self.network_service.user_protocol_mut().announce_block(hash, data),
 				ServerToWorkerMsg::RequestJustification(hash, number) =>
@@ -0,0 +1,693 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Simulated links between in-process nodes.
+//!
+//! A [`SimulatedNetwork`] sits between nodes that communicate through the in-memory transport and
+//! delays the bytes flowing between them according to per-link [`LinkConditions`]: latency,
+//! bandwidth, packet loss and partitions. Time is virtual and only advances when
+//! [`SimulatedNetwork::advance`] is called, and all randomness is drawn from an RNG seeded at
+//! creation. Given the same seed and the same sequence of polls, the same bytes are delivered at
+//! the same virtual instants.
+//!
+//! Once [`SimulatedNetwork::install_clock`] has been called on a thread, the timers of the
+//! networking code created on that thread, such as the sync request timeouts, the substream
+//! deadlines of the connection handlers and the GRANDPA round timers, follow the virtual clock
+//! as well (see the `clock` module).
+//!
+//! Each node is attached to the network through a [`SimulatedEndpoint`], which is passed to the
+//! network worker as `TransportConfig::Simulated`. Connections can still be established between
+//! nodes that are partitioned from each other, but no data crosses the partition until it is
+//! healed.
+//!
+//! The links behave like a reliable stream transport: a lost packet is not dropped but delays
+//! itself and everything behind it by the retransmission timeout, as a TCP connection would.
+//!
+//! **Note**: This is meant for tests and should never be used in production.
+
+use futures::{prelude::*, task::{self, Task}};
+use libp2p::core::ConnectedPoint;
+use parking_lot::Mutex;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::{cell::RefCell, cmp, fmt, io, collections::{HashMap, VecDeque}, sync::{Arc, Weak}};
+use std::time::{Duration, Instant};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+/// Maximum number of times a single packet can be lost in a row. Bounds the delay that a link
+/// with a very high loss rate can introduce.
+const MAX_RETRANSMISSIONS: u32 = 16;
+
+/// Maximum size of the packets a stream is cut into when it is written.
+const MAX_PACKET_SIZE: usize = 1500;
+
+/// Size of the header that precedes each packet on the underlying stream: the virtual time of
+/// delivery in nanoseconds, followed by the length of the packet.
+const PACKET_HEADER_SIZE: usize = 12;
+
+/// Identifier of a node within a [`SimulatedNetwork`].
+pub type NodeIndex = usize;
+
+/// Conditions applied to the bytes sent in one direction of a link.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkConditions {
+	/// Time between a packet being sent and it being available on the other side.
+	pub latency: Duration,
+	/// Number of bytes per second that the link can carry. `None` means unlimited.
+	pub bandwidth: Option<u64>,
+	/// Probability, between 0 and 1, that a packet is lost and has to be retransmitted.
+	pub packet_loss: f64,
+	/// Additional delay that a lost packet incurs before being retransmitted.
+	pub retransmit_timeout: Duration,
+}
+
+impl Default for LinkConditions {
+	fn default() -> Self {
+		LinkConditions {
+			latency: Duration::from_millis(0),
+			bandwidth: None,
+			packet_loss: 0.0,
+			retransmit_timeout: Duration::from_millis(200),
+		}
+	}
+}
+
+impl LinkConditions {
+	/// Conditions that only add the given latency.
+	pub fn with_latency(latency: Duration) -> Self {
+		LinkConditions {
+			latency,
+			..Default::default()
+		}
+	}
+}
+
+thread_local! {
+	/// Network whose virtual clock the timers created on this thread follow.
+	static CLOCK: RefCell<Weak<Mutex<Inner>>> = RefCell::new(Weak::new());
+}
+
+/// Returns the network whose clock was installed on the current thread, if it still exists.
+pub(crate) fn current_clock() -> Option<SimulatedNetwork> {
+	CLOCK.with(|clock| clock.borrow().upgrade()).map(|inner| SimulatedNetwork { inner })
+}
+
+/// Shared state of the simulation.
+struct Inner {
+	/// Real instant that corresponds to the start of the virtual time.
+	origin: Instant,
+	/// Current virtual time, counted from the creation of the network.
+	now: Duration,
+	/// Amount of virtual time that `advance_tick` moves the clock by.
+	tick: Duration,
+	/// Source of all randomness in the simulation.
+	rng: StdRng,
+	/// Number of nodes attached so far.
+	num_nodes: usize,
+	/// Conditions applied to links that have no specific entry in `links`.
+	default_conditions: LinkConditions,
+	/// Conditions for specific `(from, to)` directions.
+	links: HashMap<(NodeIndex, NodeIndex), LinkConditions>,
+	/// Group each node belongs to, if the network is partitioned. Nodes that are absent are in a
+	/// group of their own.
+	partitions: Option<HashMap<NodeIndex, usize>>,
+	/// Virtual time at which each `(from, to)` direction finishes sending what it was given.
+	busy_until: HashMap<(NodeIndex, NodeIndex), Duration>,
+	/// Identifier given to the next connection.
+	next_connection: u64,
+	/// Connections that are open on at least one side.
+	connections: HashMap<u64, Connection>,
+	/// Tasks waiting for the clock to advance or for a partition to be healed.
+	waiting: Vec<Task>,
+}
+
+impl Inner {
+	fn conditions(&self, from: NodeIndex, to: NodeIndex) -> &LinkConditions {
+		self.links.get(&(from, to)).unwrap_or(&self.default_conditions)
+	}
+
+	fn is_partitioned(&self, a: NodeIndex, b: NodeIndex) -> bool {
+		match self.partitions {
+			Some(ref partitions) if a != b => match (partitions.get(&a), partitions.get(&b)) {
+				(Some(a), Some(b)) => a != b,
+				_ => true,
+			},
+			_ => false,
+		}
+	}
+
+	/// Returns the virtual time at which a packet of `len` bytes sent now from `from` to `to`
+	/// becomes available on the other side.
+	fn schedule(&mut self, from: NodeIndex, to: NodeIndex, len: usize) -> Duration {
+		let conditions = self.conditions(from, to).clone();
+
+		let transmission = match conditions.bandwidth {
+			Some(bandwidth) =>
+				Duration::from_nanos(len as u64 * 1_000_000_000 / cmp::max(bandwidth, 1)),
+			None => Duration::from_millis(0),
+		};
+
+		let busy_until = self.busy_until.entry((from, to)).or_insert(Duration::from_millis(0));
+		let sent_at = cmp::max(*busy_until, self.now) + transmission;
+		*busy_until = sent_at;
+
+		let mut retransmissions = 0;
+		if conditions.packet_loss > 0.0 {
+			let packet_loss = conditions.packet_loss.min(1.0);
+			while retransmissions < MAX_RETRANSMISSIONS && self.rng.gen_bool(packet_loss) {
+				retransmissions += 1;
+			}
+		}
+
+		sent_at + conditions.latency + conditions.retransmit_timeout * retransmissions
+	}
+
+	/// Marks one side of `connection` as closed, dropping what was in flight towards it, and
+	/// forgets about the connection once both sides are closed.
+	fn close(&mut self, connection: u64, side: Side) {
+		let closed = match self.connections.get_mut(&connection) {
+			Some(state) => {
+				state.closed[side as usize] = true;
+				state.in_flight[side as usize] = 0;
+				state.closed.iter().all(|closed| *closed)
+			},
+			None => false,
+		};
+		if closed {
+			self.connections.remove(&connection);
+		}
+	}
+
+	fn wake_all(&mut self) {
+		for task in self.waiting.drain(..) {
+			task.notify();
+		}
+	}
+}
+
+/// Side of a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+	Dialer = 0,
+	Listener = 1,
+}
+
+impl Side {
+	fn opposite(self) -> Side {
+		match self {
+			Side::Dialer => Side::Listener,
+			Side::Listener => Side::Dialer,
+		}
+	}
+}
+
+/// State of a connection shared by its two sides, indexed by `Side`.
+#[derive(Default)]
+struct Connection {
+	/// Whether each side has been dropped.
+	closed: [bool; 2],
+	/// Number of packets sent towards each side and not yet delivered.
+	in_flight: [usize; 2],
+}
+
+/// Handle to a simulated network. Cheap to clone.
+#[derive(Clone)]
+pub struct SimulatedNetwork {
+	inner: Arc<Mutex<Inner>>,
+}
+
+impl SimulatedNetwork {
+	/// Creates a new simulated network whose randomness is derived from `seed`.
+	///
+	/// Links have no latency, no bandwidth limit and no loss until configured otherwise.
+	pub fn new(seed: u64) -> Self {
+		SimulatedNetwork {
+			inner: Arc::new(Mutex::new(Inner {
+				origin: Instant::now(),
+				now: Duration::from_millis(0),
+				tick: Duration::from_millis(10),
+				rng: StdRng::seed_from_u64(seed),
+				num_nodes: 0,
+				default_conditions: LinkConditions::default(),
+				links: HashMap::new(),
+				partitions: None,
+				busy_until: HashMap::new(),
+				next_connection: 0,
+				connections: HashMap::new(),
+				waiting: Vec::new(),
+			})),
+		}
+	}
+
+	/// Attaches a new node to the network and returns its endpoint.
+	pub fn add_node(&self) -> SimulatedEndpoint {
+		let mut inner = self.inner.lock();
+		let node = inner.num_nodes;
+		inner.num_nodes += 1;
+		SimulatedEndpoint {
+			network: self.clone(),
+			node,
+		}
+	}
+
+	/// Number of nodes attached to the network.
+	pub fn num_nodes(&self) -> usize {
+		self.inner.lock().num_nodes
+	}
+
+	/// Current virtual time, counted from the creation of the network.
+	pub fn now(&self) -> Duration {
+		self.inner.lock().now
+	}
+
+	/// Makes the timers subsequently created on the current thread by the networking code follow
+	/// the virtual clock of this network, for as long as the network exists.
+	pub fn install_clock(&self) {
+		CLOCK.with(|clock| *clock.borrow_mut() = Arc::downgrade(&self.inner));
+	}
+
+	/// Current virtual time, as an instant.
+	pub fn instant(&self) -> Instant {
+		let inner = self.inner.lock();
+		inner.origin + inner.now
+	}
+
+	/// Returns true if the virtual clock has reached `at`. Otherwise, the current task is woken up
+	/// when the clock advances.
+	pub(crate) fn poll_instant(&self, at: Instant) -> bool {
+		let mut inner = self.inner.lock();
+		if inner.origin + inner.now >= at {
+			return true;
+		}
+		inner.waiting.push(task::current());
+		false
+	}
+
+	/// Draws a random number between `low`, included, and `high`, excluded, from the seeded RNG.
+	pub(crate) fn gen_range(&self, low: u64, high: u64) -> u64 {
+		self.inner.lock().rng.gen_range(low, high)
+	}
+
+	/// Sets the amount of virtual time that `advance_tick` moves the clock by.
+	pub fn set_tick(&self, tick: Duration) {
+		self.inner.lock().tick = tick;
+	}
+
+	/// Sets the conditions of every link that has not been configured individually.
+	pub fn set_default_conditions(&self, conditions: LinkConditions) {
+		self.inner.lock().default_conditions = conditions;
+	}
+
+	/// Sets the conditions of the bytes sent from `from` to `to`.
+	///
+	/// The opposite direction is not affected.
+	pub fn set_link_conditions(&self, from: NodeIndex, to: NodeIndex, conditions: LinkConditions) {
+		self.inner.lock().links.insert((from, to), conditions);
+	}
+
+	/// Sets the conditions of both directions between `a` and `b`.
+	pub fn set_symmetric_conditions(&self, a: NodeIndex, b: NodeIndex, conditions: LinkConditions) {
+		let mut inner = self.inner.lock();
+		inner.links.insert((a, b), conditions.clone());
+		inner.links.insert((b, a), conditions);
+	}
+
+	/// Splits the network into the given groups. Nodes can only exchange data with nodes of the
+	/// same group. Each node that doesn't appear in any group is isolated from all the others.
+	pub fn partition(&self, groups: &[&[NodeIndex]]) {
+		let mut partitions = HashMap::new();
+		for (index, group) in groups.iter().enumerate() {
+			for node in group.iter() {
+				partitions.insert(*node, index);
+			}
+		}
+
+		let mut inner = self.inner.lock();
+		inner.partitions = Some(partitions);
+		inner.wake_all();
+	}
+
+	/// Removes all partitions. Data held back by a partition is delivered as soon as its
+	/// scheduled time is reached.
+	pub fn heal(&self) {
+		let mut inner = self.inner.lock();
+		inner.partitions = None;
+		inner.wake_all();
+	}
+
+	/// Returns true if some data has been sent and not yet delivered.
+	pub fn has_pending_deliveries(&self) -> bool {
+		self.inner.lock().connections.values()
+			.any(|connection| connection.in_flight.iter().any(|in_flight| *in_flight != 0))
+	}
+
+	/// Moves the virtual clock forward by `by` and wakes up the connections that may now have
+	/// data to deliver.
+	pub fn advance(&self, by: Duration) {
+		let mut inner = self.inner.lock();
+		inner.now += by;
+		inner.wake_all();
+	}
+
+	/// Moves the virtual clock forward by the configured tick.
+	pub fn advance_tick(&self) {
+		let tick = self.inner.lock().tick;
+		self.advance(tick);
+	}
+}
+
+impl fmt::Debug for SimulatedNetwork {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let inner = self.inner.lock();
+		f.debug_struct("SimulatedNetwork")
+			.field("now", &inner.now)
+			.field("num_nodes", &inner.num_nodes)
+			.field("connections", &inner.connections.len())
+			.finish()
+	}
+}
+
+/// Attachment point of a single node to a [`SimulatedNetwork`].
+#[derive(Clone)]
+pub struct SimulatedEndpoint {
+	network: SimulatedNetwork,
+	node: NodeIndex,
+}
+
+impl SimulatedEndpoint {
+	/// Index of the node within the network.
+	pub fn node(&self) -> NodeIndex {
+		self.node
+	}
+
+	/// Network this endpoint is attached to.
+	pub fn network(&self) -> &SimulatedNetwork {
+		&self.network
+	}
+
+	/// Upgrades a freshly-opened in-memory connection into a simulated one.
+	///
+	/// Both sides start by exchanging their node index, which is how each side learns which link
+	/// conditions apply to the data it sends. The dialer also picks the identifier under which the
+	/// connection is tracked by the network.
+	pub(crate) fn upgrade<T>(self, stream: T, endpoint: ConnectedPoint)
+		-> impl Future<Item = SimulatedStream<T>, Error = io::Error>
+	where
+		T: AsyncRead + AsyncWrite,
+	{
+		let local = self.node;
+		let side = match endpoint {
+			ConnectedPoint::Dialer { .. } => Side::Dialer,
+			ConnectedPoint::Listener { .. } => Side::Listener,
+		};
+		let proposed = match side {
+			Side::Dialer => {
+				let mut inner = self.network.inner.lock();
+				inner.next_connection += 1;
+				inner.next_connection
+			},
+			Side::Listener => 0,
+		};
+
+		let mut handshake = [0u8; 16];
+		handshake[..8].copy_from_slice(&(local as u64).to_le_bytes());
+		handshake[8..].copy_from_slice(&proposed.to_le_bytes());
+
+		tokio_io::io::write_all(stream, handshake)
+			.and_then(|(stream, _)| tokio_io::io::read_exact(stream, [0u8; 16]))
+			.map(move |(stream, handshake)| {
+				let mut remote = [0u8; 8];
+				remote.copy_from_slice(&handshake[..8]);
+				let connection = match side {
+					Side::Dialer => proposed,
+					Side::Listener => {
+						let mut connection = [0u8; 8];
+						connection.copy_from_slice(&handshake[8..]);
+						u64::from_le_bytes(connection)
+					},
+				};
+				self.network.inner.lock().connections.entry(connection).or_default();
+
+				SimulatedStream {
+					inner: stream,
+					network: self.network,
+					local,
+					remote: u64::from_le_bytes(remote) as NodeIndex,
+					connection,
+					side,
+					write_buf: Vec::new(),
+					last_deliver_at: Duration::from_millis(0),
+					read_buf: Vec::new(),
+					queue: VecDeque::new(),
+					eof: false,
+				}
+			})
+	}
+}
+
+impl fmt::Debug for SimulatedEndpoint {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("SimulatedEndpoint").field(&self.node).finish()
+	}
+}
+
+/// Packet waiting to be delivered to the reading side of a `SimulatedStream`.
+struct Packet {
+	deliver_at: Duration,
+	data: Vec<u8>,
+	/// Number of bytes of `data` already handed out.
+	offset: usize,
+}
+
+/// Stream between two nodes of a simulated network.
+///
+/// Each write is scheduled when it is made, according to the conditions of the link towards the
+/// remote, and goes to the underlying stream together with its delivery time. Packets read from
+/// the underlying stream are held back until the virtual clock reaches that time.
+pub struct SimulatedStream<T> {
+	inner: T,
+	network: SimulatedNetwork,
+	local: NodeIndex,
+	remote: NodeIndex,
+	/// Identifier of the connection within the network.
+	connection: u64,
+	/// Side of the connection this stream is.
+	side: Side,
+	/// Encoded packets not yet fully written to the underlying stream.
+	write_buf: Vec<u8>,
+	/// Delivery time of the last packet sent. A packet can never overtake the one in front of it.
+	last_deliver_at: Duration,
+	/// Bytes read from the underlying stream that don't form a complete packet yet.
+	read_buf: Vec<u8>,
+	/// Packets received from the underlying stream and not yet delivered, in order.
+	queue: VecDeque<Packet>,
+	/// True if the underlying stream has reached EOF.
+	eof: bool,
+}
+
+impl<T: AsyncRead> SimulatedStream<T> {
+	/// Pulls everything currently available from the underlying stream into `queue`.
+	fn fill_queue(&mut self) -> io::Result<()> {
+		let mut buf = [0u8; PACKET_HEADER_SIZE + MAX_PACKET_SIZE];
+		while !self.eof {
+			match self.inner.read(&mut buf) {
+				Ok(0) => self.eof = true,
+				Ok(n) => self.read_buf.extend_from_slice(&buf[..n]),
+				Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => break,
+				Err(err) => return Err(err),
+			}
+		}
+
+		while self.read_buf.len() >= PACKET_HEADER_SIZE {
+			let mut deliver_at = [0u8; 8];
+			deliver_at.copy_from_slice(&self.read_buf[..8]);
+			let mut len = [0u8; 4];
+			len.copy_from_slice(&self.read_buf[8..PACKET_HEADER_SIZE]);
+			let len = u32::from_le_bytes(len) as usize;
+			if self.read_buf.len() < PACKET_HEADER_SIZE + len {
+				break;
+			}
+
+			let data = self.read_buf[PACKET_HEADER_SIZE..PACKET_HEADER_SIZE + len].to_vec();
+			self.read_buf.drain(..PACKET_HEADER_SIZE + len);
+			self.queue.push_back(Packet {
+				deliver_at: Duration::from_nanos(u64::from_le_bytes(deliver_at)),
+				data,
+				offset: 0,
+			});
+		}
+		Ok(())
+	}
+}
+
+impl<T: AsyncRead> io::Read for SimulatedStream<T> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.fill_queue()?;
+
+		let mut inner = self.network.inner.lock();
+		let ready = match self.queue.front() {
+			Some(packet) =>
+				packet.deliver_at <= inner.now && !inner.is_partitioned(self.local, self.remote),
+			// Everything has been delivered; if we are not at EOF, the underlying stream has
+			// registered the current task.
+			None if self.eof => return Ok(0),
+			None => return Err(io::ErrorKind::WouldBlock.into()),
+		};
+
+		if !ready {
+			inner.waiting.push(task::current());
+			return Err(io::ErrorKind::WouldBlock.into());
+		}
+
+		let packet = self.queue.front_mut().expect("checked above that the queue is not empty; qed");
+		let len = cmp::min(buf.len(), packet.data.len() - packet.offset);
+		buf[..len].copy_from_slice(&packet.data[packet.offset..packet.offset + len]);
+		packet.offset += len;
+		if packet.offset == packet.data.len() {
+			self.queue.pop_front();
+			if let Some(connection) = inner.connections.get_mut(&self.connection) {
+				let in_flight = &mut connection.in_flight[self.side as usize];
+				*in_flight = in_flight.saturating_sub(1);
+			}
+		}
+		Ok(len)
+	}
+}
+
+impl<T: AsyncRead> AsyncRead for SimulatedStream<T> {}
+
+impl<T: AsyncWrite> SimulatedStream<T> {
+	/// Writes as much of `write_buf` as possible to the underlying stream. Returns false if some
+	/// of it is left, in which case the underlying stream has registered the current task.
+	fn flush_write_buf(&mut self) -> io::Result<bool> {
+		while !self.write_buf.is_empty() {
+			match self.inner.write(&self.write_buf) {
+				Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+				Ok(n) => { self.write_buf.drain(..n); },
+				Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+				Err(err) => return Err(err),
+			}
+		}
+		Ok(true)
+	}
+}
+
+impl<T: AsyncWrite> io::Write for SimulatedStream<T> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if !self.flush_write_buf()? {
+			return Err(io::ErrorKind::WouldBlock.into());
+		}
+		if buf.is_empty() {
+			return Ok(0);
+		}
+
+		let len = cmp::min(buf.len(), MAX_PACKET_SIZE);
+		{
+			let mut inner = self.network.inner.lock();
+			let scheduled = inner.schedule(self.local, self.remote, len);
+			self.last_deliver_at = cmp::max(self.last_deliver_at, scheduled);
+			let connection = inner.connections.entry(self.connection).or_default();
+			let remote_side = self.side.opposite() as usize;
+			if !connection.closed[remote_side] {
+				connection.in_flight[remote_side] += 1;
+			}
+		}
+
+		self.write_buf.extend_from_slice(&(self.last_deliver_at.as_nanos() as u64).to_le_bytes());
+		self.write_buf.extend_from_slice(&(len as u32).to_le_bytes());
+		self.write_buf.extend_from_slice(&buf[..len]);
+		// The packet is accepted even if the underlying stream doesn't take all of it now; the
+		// rest is written before anything else.
+		self.flush_write_buf()?;
+		Ok(len)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		if !self.flush_write_buf()? {
+			return Err(io::ErrorKind::WouldBlock.into());
+		}
+		self.inner.flush()
+	}
+}
+
+impl<T: AsyncWrite> AsyncWrite for SimulatedStream<T> {
+	fn shutdown(&mut self) -> Poll<(), io::Error> {
+		if !self.flush_write_buf()? {
+			return Ok(Async::NotReady);
+		}
+		self.inner.shutdown()
+	}
+}
+
+impl<T> Drop for SimulatedStream<T> {
+	fn drop(&mut self) {
+		self.network.inner.lock().close(self.connection, self.side);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn schedule_many(seed: u64) -> Vec<Duration> {
+		let network = SimulatedNetwork::new(seed);
+		network.set_default_conditions(LinkConditions {
+			latency: Duration::from_millis(50),
+			bandwidth: Some(1_000_000),
+			packet_loss: 0.3,
+			retransmit_timeout: Duration::from_millis(200),
+		});
+		let mut inner = network.inner.lock();
+		(0..100).map(|_| inner.schedule(0, 1, 1000)).collect()
+	}
+
+	#[test]
+	fn same_seed_gives_same_schedule() {
+		assert_eq!(schedule_many(42), schedule_many(42));
+		assert_ne!(schedule_many(42), schedule_many(43));
+	}
+
+	#[test]
+	fn bandwidth_serializes_packets() {
+		let network = SimulatedNetwork::new(0);
+		network.set_link_conditions(0, 1, LinkConditions {
+			latency: Duration::from_millis(10),
+			bandwidth: Some(1000),
+			..Default::default()
+		});
+		let mut inner = network.inner.lock();
+		assert_eq!(inner.schedule(0, 1, 500), Duration::from_millis(510));
+		assert_eq!(inner.schedule(0, 1, 500), Duration::from_millis(1010));
+		// The opposite direction is unaffected.
+		assert_eq!(inner.schedule(1, 0, 500), Duration::from_millis(0));
+	}
+
+	#[test]
+	fn partitions_separate_groups() {
+		let network = SimulatedNetwork::new(0);
+		network.partition(&[&[0, 1], &[2]]);
+		{
+			let inner = network.inner.lock();
+			assert!(!inner.is_partitioned(0, 1));
+			assert!(inner.is_partitioned(1, 2));
+			assert!(inner.is_partitioned(0, 3));
+			// Nodes that aren't in any group are isolated, including from each other.
+			assert!(inner.is_partitioned(3, 4));
+			assert!(!inner.is_partitioned(3, 3));
+		}
+		network.heal();
+		assert!(!network.inner.lock().is_partitioned(1, 2));
+	}
+}
@@ -19,6 +19,10 @@
 #[cfg(test)]
 mod block_import;
 #[cfg(test)]
+mod notifications;
+#[cfg(test)]
+mod simulation;
+#[cfg(test)]
 mod sync;
 
 use std::collections::HashMap;
@@ -49,6 +53,7 @@ use futures::prelude::*;
 use futures03::{StreamExt as _, TryStreamExt as _};
 use crate::{NetworkWorker, NetworkService, ReportHandle, config::ProtocolId};
 use crate::config::{NetworkConfiguration, TransportConfig, BoxFinalityProofRequestBuilder};
+use crate::simulation::SimulatedNetwork;
 use libp2p::PeerId;
 use parking_lot::Mutex;
 use primitives::H256;
@@ -504,10 +509,26 @@ pub trait TestNetFactory: Sized {
 		None
 	}
 
+	/// Get the simulated network the peers are connected through (if any).
+	///
+	/// When this returns `Some`, new peers are attached to the simulated network and `poll`
+	/// advances its virtual clock by one tick.
+	fn simulation(&self) -> Option<SimulatedNetwork> {
+		None
+	}
+
 	fn default_config() -> ProtocolConfig {
 		ProtocolConfig::default()
 	}
 
+	/// Get the transport configuration for a new peer.
+	fn make_transport_config(&self) -> TransportConfig {
+		match self.simulation() {
+			Some(simulation) => TransportConfig::Simulated(simulation.add_node()),
+			None => TransportConfig::MemoryOnly,
+		}
+	}
+
 	/// Create new test network with this many peers.
 	fn new(n: usize) -> Self {
 		trace!(target: "test_network", "Creating test network");
@@ -556,6 +577,7 @@ pub trait TestNetFactory: Sized {
 			Box::new(block_import.clone()),
 			justification_import,
 			finality_proof_import,
+			None,
 		));
 
 		let listen_addr = build_multiaddr![Memory(rand::random::<u64>())];
@@ -564,7 +586,7 @@ pub trait TestNetFactory: Sized {
 			roles: config.roles,
 			network_config: NetworkConfiguration {
 				listen_addresses: vec![listen_addr.clone()],
-				transport: TransportConfig::MemoryOnly,
+				transport: self.make_transport_config(),
 				..NetworkConfiguration::default()
 			},
 			chain: client.clone(),
@@ -630,6 +652,7 @@ pub trait TestNetFactory: Sized {
 			Box::new(block_import.clone()),
 			justification_import,
 			finality_proof_import,
+			None,
 		));
 
 		let listen_addr = build_multiaddr![Memory(rand::random::<u64>())];
@@ -638,7 +661,7 @@ pub trait TestNetFactory: Sized {
 			roles: config.roles,
 			network_config: NetworkConfiguration {
 				listen_addresses: vec![listen_addr.clone()],
-				transport: TransportConfig::MemoryOnly,
+				transport: self.make_transport_config(),
 				..NetworkConfiguration::default()
 			},
 			chain: client.clone(),
@@ -727,6 +750,13 @@ pub trait TestNetFactory: Sized {
 				}
 			}
 		});
+
+		if let Some(simulation) = self.simulation() {
+			simulation.advance_tick();
+			// Timers follow the virtual clock, so nothing else would wake us up to advance it
+			// again.
+			futures::task::current().notify();
+		}
 	}
 }
 
@@ -765,6 +795,65 @@ impl TestNetFactory for TestNet {
 	}
 }
 
+/// A `TestNet` whose peers are connected through a `SimulatedNetwork`.
+pub struct SimulatedTestNet {
+	net: TestNet,
+	simulation: SimulatedNetwork,
+}
+
+impl SimulatedTestNet {
+	/// Create a new test network with `n` peers connected through `simulation`.
+	///
+	/// The virtual clock of `simulation` is installed on the current thread, which is expected to
+	/// be the one polling the network.
+	pub fn with_simulation(n: usize, simulation: SimulatedNetwork) -> Self {
+		simulation.install_clock();
+		let config = Self::default_config();
+		let mut net = SimulatedTestNet {
+			net: TestNet::from_config(&config),
+			simulation,
+		};
+
+		for _ in 0..n {
+			net.add_full_peer(&config);
+		}
+		net
+	}
+}
+
+impl TestNetFactory for SimulatedTestNet {
+	type Specialization = DummySpecialization;
+	type Verifier = PassThroughVerifier;
+	type PeerData = ();
+
+	fn from_config(config: &ProtocolConfig) -> Self {
+		SimulatedTestNet {
+			net: TestNet::from_config(config),
+			simulation: SimulatedNetwork::new(0),
+		}
+	}
+
+	fn make_verifier(&self, client: PeersClient, config: &ProtocolConfig, peer_data: &()) -> Self::Verifier {
+		self.net.make_verifier(client, config, peer_data)
+	}
+
+	fn peer(&mut self, i: usize) -> &mut Peer<Self::PeerData, Self::Specialization> {
+		self.net.peer(i)
+	}
+
+	fn peers(&self) -> &Vec<Peer<Self::PeerData, Self::Specialization>> {
+		self.net.peers()
+	}
+
+	fn mut_peers<F: FnOnce(&mut Vec<Peer<Self::PeerData, Self::Specialization>>)>(&mut self, closure: F) {
+		self.net.mut_peers(closure)
+	}
+
+	fn simulation(&self) -> Option<SimulatedNetwork> {
+		Some(self.simulation.clone())
+	}
+}
+
 pub struct ForceFinalized(PeersClient);
 
 impl JustificationImport<Block> for ForceFinalized {
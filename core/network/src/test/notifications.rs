@@ -0,0 +1,88 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::{
+	NotificationProtocolConfig, NotificationValidator, NotificationValidation, NotificationEvent,
+};
+use tokio::runtime::current_thread;
+use super::*;
+
+const PROTOCOL: &[u8] = b"/oracle/prices/1";
+
+struct AcceptAll;
+
+impl NotificationValidator for AcceptAll {
+	fn validate(&self, _: &PeerId, _: &[u8]) -> NotificationValidation {
+		NotificationValidation::Accept
+	}
+}
+
+fn config() -> NotificationProtocolConfig {
+	NotificationProtocolConfig {
+		name: PROTOCOL.into(),
+		handshake: b"price-feed".to_vec(),
+		validator: Arc::new(AcceptAll),
+		buffer_size: 16,
+	}
+}
+
+#[test]
+fn notifications_are_delivered_after_handshake() {
+	let _ = ::env_logger::try_init();
+	let mut runtime = current_thread::Runtime::new().unwrap();
+	let mut net = TestNet::new(2);
+
+	let mut events_0 = net.peer(0).network_service().register_notification_protocol(config());
+	let mut events_1 = net.peer(1).network_service().register_notification_protocol(config());
+	let peer_id_0 = net.peer(0).network_service().local_peer_id();
+
+	// Both sides open the protocol.
+	let mut opened = (false, false);
+	runtime.block_on(futures::future::poll_fn::<(), (), _>(|| -> Result<_, ()> {
+		net.poll();
+		while let Async::Ready(Some(event)) = events_0.poll().unwrap() {
+			if let NotificationEvent::Opened { handshake, .. } = event {
+				assert_eq!(handshake, b"price-feed".to_vec());
+				opened.0 = true;
+			}
+		}
+		while let Async::Ready(Some(event)) = events_1.poll().unwrap() {
+			if let NotificationEvent::Opened { .. } = event {
+				opened.1 = true;
+			}
+		}
+		if opened == (true, true) {
+			Ok(Async::Ready(()))
+		} else {
+			Ok(Async::NotReady)
+		}
+	})).unwrap();
+
+	let peer_id_1 = net.peer(1).network_service().local_peer_id();
+	net.peer(0).network_service().write_notification(peer_id_1, PROTOCOL.into(), vec![1, 2, 3]);
+
+	runtime.block_on(futures::future::poll_fn::<(), (), _>(|| -> Result<_, ()> {
+		net.poll();
+		while let Async::Ready(Some(event)) = events_1.poll().unwrap() {
+			if let NotificationEvent::Notification { remote, message } = event {
+				assert_eq!(remote, peer_id_0);
+				assert_eq!(message, vec![1, 2, 3]);
+				return Ok(Async::Ready(()));
+			}
+		}
+		Ok(Async::NotReady)
+	})).unwrap();
+}
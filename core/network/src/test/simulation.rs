@@ -0,0 +1,100 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::simulation::LinkConditions;
+use std::time::Duration;
+use tokio::runtime::current_thread;
+use super::*;
+
+#[test]
+fn sync_over_lossy_links() {
+	let _ = ::env_logger::try_init();
+	let mut runtime = current_thread::Runtime::new().unwrap();
+	let simulation = SimulatedNetwork::new(7);
+	simulation.set_default_conditions(LinkConditions {
+		latency: Duration::from_millis(100),
+		bandwidth: Some(1_000_000),
+		packet_loss: 0.1,
+		retransmit_timeout: Duration::from_millis(300),
+	});
+	let mut net = SimulatedTestNet::with_simulation(3, simulation.clone());
+
+	net.peer(0).push_blocks(50, false);
+	net.block_until_sync(&mut runtime);
+
+	for i in 1..3 {
+		assert_eq!(net.peer(i).client().info().chain.best_number, 50);
+	}
+	// Block requests and responses went through the simulated links.
+	assert!(simulation.now() >= Duration::from_millis(200));
+}
+
+#[test]
+fn partition_stalls_sync_until_healed() {
+	let _ = ::env_logger::try_init();
+	let mut runtime = current_thread::Runtime::new().unwrap();
+	let simulation = SimulatedNetwork::new(7);
+	simulation.set_default_conditions(LinkConditions::with_latency(Duration::from_millis(50)));
+	let mut net = SimulatedTestNet::with_simulation(2, simulation.clone());
+
+	// Wait for the peers to know about each other.
+	runtime.block_on(futures::future::poll_fn::<(), (), _>(|| -> Result<_, ()> {
+		net.poll();
+		for peer in 0..2 {
+			if net.peer(peer).num_peers() != 1 {
+				return Ok(Async::NotReady)
+			}
+		}
+		Ok(Async::Ready(()))
+	})).unwrap();
+
+	simulation.partition(&[&[0], &[1]]);
+	net.peer(0).push_blocks(10, false);
+
+	// However long we run, nothing crosses the partition.
+	let until = simulation.now() + Duration::from_secs(5);
+	runtime.block_on(futures::future::poll_fn::<(), (), _>(|| -> Result<_, ()> {
+		net.poll();
+		futures::task::current().notify();
+		if simulation.now() < until {
+			return Ok(Async::NotReady)
+		}
+		Ok(Async::Ready(()))
+	})).unwrap();
+	assert_eq!(net.peer(1).client().info().chain.best_number, 0);
+
+	simulation.heal();
+	net.block_until_sync(&mut runtime);
+	assert_eq!(net.peer(1).client().info().chain.best_number, 10);
+}
+
+#[test]
+fn timers_follow_virtual_clock() {
+	let mut runtime = current_thread::Runtime::new().unwrap();
+	let simulation = SimulatedNetwork::new(7);
+	simulation.install_clock();
+	assert_eq!(crate::clock::now(), simulation.instant());
+
+	let mut delay = crate::clock::Delay::new(Duration::from_secs(3600));
+	runtime.block_on(futures::future::poll_fn::<(), (), _>(|| -> Result<_, ()> {
+		assert!(delay.poll().unwrap().is_not_ready());
+		simulation.advance(Duration::from_secs(3599));
+		assert!(delay.poll().unwrap().is_not_ready());
+		simulation.advance(Duration::from_secs(1));
+		assert!(delay.poll().unwrap().is_ready());
+		Ok(Async::Ready(()))
+	})).unwrap();
+}
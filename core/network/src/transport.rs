@@ -24,7 +24,11 @@ use libp2p::{tcp, dns, websocket, noise};
 #[cfg(not(target_os = "unknown"))]
 use libp2p::core::{either::EitherError, either::EitherOutput};
 use libp2p::core::{self, upgrade, transport::boxed::Boxed, transport::OptionalTransport, muxing::StreamMuxerBox};
-use std::{io, sync::Arc, time::Duration, usize};
+use std::{collections::HashSet, io, sync::Arc, time::Duration, usize};
+#[cfg(any(test, feature = "test-helpers"))]
+use crate::simulation::SimulatedEndpoint;
+#[cfg(not(any(test, feature = "test-helpers")))]
+type SimulatedEndpoint = void::Void;
 
 pub use self::bandwidth::BandwidthSinks;
 
@@ -33,12 +37,20 @@ pub use self::bandwidth::BandwidthSinks;
 /// If `memory_only` is true, then only communication within the same process are allowed. Only
 /// addresses with the format `/memory/...` are allowed.
 ///
+/// If `simulation` is `Some`, in-process connections go through the given simulated network.
+///
+/// If `allowed_peers` is `Some`, connections to or from any other peer are closed as soon as the
+/// identity of the remote is known. This is how a validator behind sentry nodes only talks to
+/// them.
+///
 /// Returns a `BandwidthSinks` object that allows querying the average bandwidth produced by all
 /// the connections spawned with this transport.
 pub fn build_transport(
 	keypair: identity::Keypair,
 	memory_only: bool,
-	wasm_external_transport: Option<wasm_ext::ExtTransport>
+	wasm_external_transport: Option<wasm_ext::ExtTransport>,
+	simulation: Option<SimulatedEndpoint>,
+	allowed_peers: Option<HashSet<PeerId>>,
 ) -> (Boxed<(PeerId, StreamMuxerBox), io::Error>, Arc<bandwidth::BandwidthSinks>) {
 	// Build configuration objects for encryption mechanisms.
 	#[cfg(not(target_os = "unknown"))]
@@ -76,12 +88,20 @@ pub fn build_transport(
 		OptionalTransport::none()
 	});
 
-	let transport = transport.or_transport(if memory_only {
+	let transport = transport.or_transport(if memory_only && simulation.is_none() {
 		OptionalTransport::some(libp2p::core::transport::MemoryTransport::default())
 	} else {
 		OptionalTransport::none()
 	});
 
+	#[cfg(any(test, feature = "test-helpers"))]
+	let transport = transport.or_transport(if let Some(endpoint) = simulation {
+		OptionalTransport::some(libp2p::core::transport::MemoryTransport::default()
+			.and_then(move |stream, connected_point| endpoint.clone().upgrade(stream, connected_point)))
+	} else {
+		OptionalTransport::none()
+	});
+
 	let (transport, sinks) = bandwidth::BandwidthLogging::new(transport, Duration::from_secs(5));
 
 	// Encryption
@@ -113,6 +133,16 @@ pub fn build_transport(
 			.and_then(|(id, stream)| Ok((stream, id)))
 	});
 
+	// Restriction to the allowed peers
+	let allowed_peers = allowed_peers.map(Arc::new);
+	let transport = transport.and_then(move |(stream, peer_id), _| match allowed_peers {
+		Some(ref allowed) if !allowed.contains(&peer_id) => Err(io::Error::new(
+			io::ErrorKind::PermissionDenied,
+			format!("Connections to {} are not allowed", peer_id),
+		)),
+		_ => Ok((stream, peer_id)),
+	});
+
 	// Multiplexing
 	let transport = transport.and_then(move |(stream, peer_id), endpoint| {
 			let peer_id2 = peer_id.clone();
@@ -130,3 +160,50 @@ pub fn build_transport(
 
 	(transport, sinks)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use libp2p::core::{Multiaddr, transport::ListenerEvent};
+
+	#[test]
+	fn only_allowed_peers_are_connected() {
+		let keypairs = (0..3).map(|_| identity::Keypair::generate_ed25519()).collect::<Vec<_>>();
+		let peer_ids = keypairs.iter().map(|k| k.public().into_peer_id()).collect::<Vec<_>>();
+		let (validator, sentry, other) = (0, 1, 2);
+		let transports = keypairs.into_iter().enumerate().map(|(n, keypair)| {
+			let allowed_peers = if n == validator {
+				Some(Some(peer_ids[sentry].clone()).into_iter().collect())
+			} else {
+				None
+			};
+			build_transport(keypair, true, None, None, allowed_peers).0
+		}).collect::<Vec<_>>();
+
+		let mut runtime = tokio::runtime::Runtime::new().unwrap();
+		let addresses = (0..3).map(|n| {
+			let address: Multiaddr = format!("/memory/{}", rand::random::<u64>()).parse().unwrap();
+			let listener = transports[n].clone().listen_on(address.clone()).unwrap()
+				.for_each(|event| {
+					if let ListenerEvent::Upgrade { upgrade, .. } = event {
+						tokio::spawn(upgrade.map(|_| ()).map_err(|_| ()));
+					}
+					Ok(())
+				})
+				.map_err(|_| ());
+			runtime.spawn(listener);
+			address
+		}).collect::<Vec<_>>();
+
+		let mut dial = |from: usize, to: usize| runtime.block_on(transports[from].clone()
+			.dial(addresses[to].clone())
+			.unwrap()
+			.map(|(peer_id, _)| peer_id));
+
+		assert_eq!(dial(sentry, validator).unwrap(), peer_ids[validator]);
+		assert_eq!(dial(validator, sentry).unwrap(), peer_ids[sentry]);
+		assert!(dial(other, validator).is_err());
+		assert!(dial(validator, other).is_err());
+		assert_eq!(dial(other, sentry).unwrap(), peer_ids[sentry]);
+	}
+}
@@ -0,0 +1,80 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Queueing of equivocation reports for the offchain workers of the runtime.
+//!
+//! Consensus engines detecting an equivocation of an authority queue the proof in the offchain
+//! local storage, where the offchain worker of the runtime module of the engine picks it up
+//! and turns it into an on-chain report.
+
+use codec::{Codec, Encode, Decode};
+use client::backend::OffchainStorage;
+use log::warn;
+use crate::STORAGE_PREFIX;
+
+/// The maximum number of equivocations waiting in the offchain local storage to be reported.
+/// Further equivocations are dropped until the runtime's offchain worker has drained the queue.
+pub const MAX_QUEUED_EQUIVOCATIONS: usize = 256;
+
+/// Append an equivocation proof to the queue kept under `key` in the offchain local storage,
+/// unless it is already queued or the queue is full.
+///
+/// The queue is an encoded `Vec` of proofs, which the offchain worker of the runtime module
+/// of the consensus engine takes and turns into on-chain reports.
+pub fn queue_equivocation_report<S, P>(storage: &mut S, key: &[u8], proof: P) where
+	S: OffchainStorage,
+	P: Codec + Clone + PartialEq + std::fmt::Debug,
+{
+	loop {
+		let queued = storage.get(STORAGE_PREFIX, key);
+		let mut proofs = queued.as_ref()
+			.and_then(|queued| Vec::<P>::decode(&mut &queued[..]).ok())
+			.unwrap_or_default();
+
+		if proofs.contains(&proof) {
+			return;
+		}
+
+		if proofs.len() >= MAX_QUEUED_EQUIVOCATIONS {
+			warn!(target: "offchain", "Too many equivocations waiting to be reported, dropping {:?}", proof);
+			return;
+		}
+
+		proofs.push(proof.clone());
+		let encoded = proofs.encode();
+
+		// the offchain worker may have taken the queue in the meantime, retry if so.
+		if storage.compare_and_set(STORAGE_PREFIX, key, queued.as_ref().map(Vec::as_slice), &encoded) {
+			return;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn proofs_are_queued_once() {
+		let mut storage = client::in_mem::OffchainStorage::default();
+		queue_equivocation_report(&mut storage, b"key", 1u64);
+		queue_equivocation_report(&mut storage, b"key", 2u64);
+		queue_equivocation_report(&mut storage, b"key", 1u64);
+
+		let queued = storage.get(STORAGE_PREFIX, b"key").unwrap();
+		assert_eq!(Vec::<u64>::decode(&mut &queued[..]).unwrap(), vec![1, 2]);
+	}
+}
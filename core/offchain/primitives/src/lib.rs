@@ -22,6 +22,9 @@
 use client::decl_runtime_apis;
 use sr_primitives::traits::NumberFor;
 
+#[cfg(feature = "std")]
+pub mod equivocation;
+
 /// Local Storage Prefix used by the Offchain Worker API to
 pub const STORAGE_PREFIX: &[u8] = b"storage";
 
@@ -24,11 +24,11 @@ use std::{
 use client::backend::OffchainStorage;
 use futures::{StreamExt as _, Future, FutureExt as _, future, channel::mpsc};
 use log::{info, debug, warn, error};
-use network::{PeerId, Multiaddr, NetworkStateInfo};
+use network::{PeerId, Multiaddr, NetworkStateInfo, DhtClient};
 use codec::{Encode, Decode};
 use primitives::offchain::{
 	Externalities as OffchainExt, HttpRequestId, Timestamp, HttpRequestStatus, HttpError,
-	OpaqueNetworkState, OpaquePeerId, OpaqueMultiaddr, StorageKind,
+	OpaqueNetworkState, OpaquePeerId, OpaqueMultiaddr, StorageKind, DhtRecord,
 };
 pub use offchain_primitives::STORAGE_PREFIX;
 use sr_primitives::{generic::BlockId, traits::{self, Extrinsic}};
@@ -56,6 +56,8 @@ pub(crate) struct Api<Storage, Block: traits::Block> {
 	sender: mpsc::UnboundedSender<ExtMessage>,
 	db: Storage,
 	network_state: Arc<dyn NetworkStateInfo + Send + Sync>,
+	/// Access to the records stored in the DHT.
+	dht: Arc<DhtClient>,
 	_at: BlockId<Block>,
 	/// Is this node a potential validator?
 	is_validator: bool,
@@ -190,6 +192,47 @@ where
 	) -> Result<usize, HttpError> {
 		self.http.response_read_body(request_id, buffer, deadline)
 	}
+
+	fn dht_put(
+		&mut self,
+		key: &[u8],
+		record: DhtRecord,
+		deadline: Option<Timestamp>
+	) -> Result<(), ()> {
+		let mut put = future::maybe_done(self.dht.put_record(key, record, timestamp::now()));
+		let mut deadline = timestamp::deadline_to_future(deadline);
+		futures::executor::block_on(future::select(&mut put, &mut deadline));
+
+		match put {
+			future::MaybeDone::Done(Ok(())) => Ok(()),
+			future::MaybeDone::Done(Err(e)) => {
+				debug!("Failed to put DHT record: {}", e);
+				Err(())
+			},
+			future::MaybeDone::Future(_) |
+			future::MaybeDone::Gone => Err(()),
+		}
+	}
+
+	fn dht_get(
+		&mut self,
+		key: &[u8],
+		deadline: Option<Timestamp>
+	) -> Result<Vec<DhtRecord>, ()> {
+		let mut get = future::maybe_done(self.dht.get_records(key, timestamp::now()));
+		let mut deadline = timestamp::deadline_to_future(deadline);
+		futures::executor::block_on(future::select(&mut get, &mut deadline));
+
+		match get {
+			future::MaybeDone::Done(Ok(records)) => Ok(records),
+			future::MaybeDone::Done(Err(e)) => {
+				debug!("Failed to get DHT records: {}", e);
+				Err(())
+			},
+			future::MaybeDone::Future(_) |
+			future::MaybeDone::Gone => Err(()),
+		}
+	}
 }
 
 /// Information about the local node's network state.
@@ -275,6 +318,7 @@ impl<A: ChainApi> AsyncApi<A> {
 		db: S,
 		at: BlockId<A::Block>,
 		network_state: Arc<dyn NetworkStateInfo + Send + Sync>,
+		dht: Arc<DhtClient>,
 		is_validator: bool,
 	) -> (Api<S, A::Block>, AsyncApi<A>) {
 		let (sender, rx) = mpsc::unbounded();
@@ -285,6 +329,7 @@ impl<A: ChainApi> AsyncApi<A> {
 			sender,
 			db,
 			network_state,
+			dht,
 			_at: at,
 			is_validator,
 			http: http_api,
@@ -340,11 +385,19 @@ mod tests {
 	use std::{convert::{TryFrom, TryInto}, time::SystemTime};
 	use sr_primitives::traits::Zero;
 	use client_db::offchain::LocalStorage;
-	use network::PeerId;
+	use network::{PeerId, DhtNetwork};
 	use test_client::runtime::Block;
 
 	struct MockNetworkStateInfo();
 
+	struct MockDhtNetwork();
+
+	impl DhtNetwork for MockDhtNetwork {
+		fn get_value(&self, _key: &network::dht_client::Key) {}
+
+		fn put_value(&self, _key: network::dht_client::Key, _value: Vec<u8>) {}
+	}
+
 	impl NetworkStateInfo for MockNetworkStateInfo {
 		fn external_addresses(&self) -> Vec<Multiaddr> {
 			Vec::new()
@@ -364,15 +417,26 @@ mod tests {
 		);
 
 		let mock = Arc::new(MockNetworkStateInfo());
+		let dht = Arc::new(DhtClient::new(Arc::new(MockDhtNetwork())));
 		AsyncApi::new(
 			pool,
 			db,
 			BlockId::Number(Zero::zero()),
 			mock,
+			dht,
 			false,
 		)
 	}
 
+	#[test]
+	fn dht_get_should_respect_deadline() {
+		let mut api = offchain_api().0;
+
+		// The mock network never answers.
+		let deadline = api.timestamp().add(primitives::offchain::Duration::from_millis(100));
+		assert_eq!(api.dht_get(b"key", Some(deadline)), Err(()));
+	}
+
 	#[test]
 	fn should_get_timestamp() {
 		let mut api = offchain_api().0;
@@ -44,7 +44,7 @@ use threadpool::ThreadPool;
 use client::runtime_api::ApiExt;
 use futures::future::Future;
 use log::{debug, warn};
-use network::NetworkStateInfo;
+use network::{NetworkStateInfo, DhtClient};
 use primitives::{offchain, ExecutionContext};
 use sr_primitives::{generic::BlockId, traits::{self, ProvideRuntimeApi}};
 use transaction_pool::txpool::{Pool, ChainApi};
@@ -102,6 +102,7 @@ impl<Client, Storage, Block> OffchainWorkers<
 		number: &<Block::Header as traits::Header>::Number,
 		pool: &Arc<Pool<A>>,
 		network_state: Arc<dyn NetworkStateInfo + Send + Sync>,
+		dht: Arc<DhtClient>,
 		is_validator: bool,
 	) -> impl Future<Output = ()> where A: ChainApi<Block=Block> + 'static {
 		let runtime = self.client.runtime_api();
@@ -115,6 +116,7 @@ impl<Client, Storage, Block> OffchainWorkers<
 				self.db.clone(),
 				at.clone(),
 				network_state.clone(),
+				dht,
 				is_validator,
 			);
 			debug!("Spawning offchain workers at {:?}", at);
@@ -155,10 +157,18 @@ impl<Client, Storage, Block> OffchainWorkers<
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use network::{Multiaddr, PeerId};
+	use network::{Multiaddr, PeerId, DhtNetwork, dht_client::Key};
 
 	struct MockNetworkStateInfo();
 
+	struct MockDhtNetwork();
+
+	impl DhtNetwork for MockDhtNetwork {
+		fn get_value(&self, _key: &Key) {}
+
+		fn put_value(&self, _key: Key, _value: Vec<u8>) {}
+	}
+
 	impl NetworkStateInfo for MockNetworkStateInfo {
 		fn external_addresses(&self) -> Vec<Multiaddr> {
 			Vec::new()
@@ -177,10 +187,11 @@ mod tests {
 		let pool = Arc::new(Pool::new(Default::default(), transaction_pool::FullChainApi::new(client.clone())));
 		let db = client_db::offchain::LocalStorage::new_test();
 		let network_state = Arc::new(MockNetworkStateInfo());
+		let dht = Arc::new(DhtClient::new(Arc::new(MockDhtNetwork())));
 
 		// when
 		let offchain = OffchainWorkers::new(client, db);
-		futures::executor::block_on(offchain.on_block_imported(&0u64, &pool, network_state, false));
+		futures::executor::block_on(offchain.on_block_imported(&0u64, &pool, network_state, dht, false));
 
 		// then
 		assert_eq!(pool.status().ready, 1);
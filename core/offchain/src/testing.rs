@@ -30,6 +30,7 @@ use primitives::offchain::{
 	Timestamp,
 	StorageKind,
 	OpaqueNetworkState,
+	DhtRecord,
 };
 
 /// Pending request.
@@ -69,6 +70,8 @@ pub struct State {
 	pub local_storage: client::in_mem::OffchainStorage,
 	/// A vector of transactions submitted from the runtime.
 	pub transactions: Vec<Vec<u8>>,
+	/// Records published in the DHT, by key.
+	pub dht: BTreeMap<Vec<u8>, Vec<DhtRecord>>,
 }
 
 impl State {
@@ -304,4 +307,25 @@ impl offchain::Externalities for TestOffchainExt {
 			Err(HttpError::IoError)
 		}
 	}
+
+	fn dht_put(
+		&mut self,
+		key: &[u8],
+		record: DhtRecord,
+		_deadline: Option<Timestamp>
+	) -> Result<(), ()> {
+		if !record.verify(key) {
+			return Err(());
+		}
+		self.0.write().dht.entry(key.to_vec()).or_default().push(record);
+		Ok(())
+	}
+
+	fn dht_get(
+		&mut self,
+		key: &[u8],
+		_deadline: Option<Timestamp>
+	) -> Result<Vec<DhtRecord>, ()> {
+		Ok(self.0.read().dht.get(key).cloned().unwrap_or_default())
+	}
 }
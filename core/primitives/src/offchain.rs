@@ -18,7 +18,7 @@
 
 use codec::{Encode, Decode};
 use rstd::{prelude::{Vec, Box}, convert::TryFrom};
-use crate::RuntimeDebug;
+use crate::{RuntimeDebug, sr25519};
 
 pub use crate::crypto::KeyTypeId;
 
@@ -178,7 +178,7 @@ impl OpaqueMultiaddr {
 }
 
 /// Opaque timestamp type
-#[derive(Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Default, RuntimeDebug)]
+#[derive(Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Default, Encode, Decode, RuntimeDebug)]
 pub struct Timestamp(u64);
 
 /// Duration type
@@ -224,6 +224,47 @@ impl Timestamp {
 	}
 }
 
+/// Maximum time a DHT record can stay valid for, counted from the moment it is published.
+pub const MAX_DHT_RECORD_TTL: Duration = Duration(36 * 60 * 60 * 1000);
+
+/// A signed and expiring record stored in the DHT.
+///
+/// The record is signed by `signer` over the DHT key it is stored under, its value and its
+/// expiration time (see `DhtRecord::signing_payload`). Nodes refuse to publish and drop on
+/// retrieval records whose signature doesn't match or that have expired.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct DhtRecord {
+	/// The value of the record.
+	pub value: Vec<u8>,
+	/// The moment after which the record must no longer be used.
+	pub expires_at: Timestamp,
+	/// The key that signed the record.
+	pub signer: sr25519::Public,
+	/// Signature of `signing_payload` by `signer`.
+	pub signature: sr25519::Signature,
+}
+
+impl DhtRecord {
+	/// Returns the payload that has to be signed in order to store `value` under `key`
+	/// until `expires_at`.
+	pub fn signing_payload(key: &[u8], value: &[u8], expires_at: Timestamp) -> Vec<u8> {
+		(&b"dht_record"[..], key, value, expires_at).encode()
+	}
+
+	/// Returns true if the record is no longer valid at `now`.
+	pub fn is_expired(&self, now: Timestamp) -> bool {
+		self.expires_at <= now
+	}
+
+	/// Returns true if the record carries a valid signature for being stored under `key`.
+	#[cfg(feature = "std")]
+	pub fn verify(&self, key: &[u8]) -> bool {
+		use crate::crypto::Pair as _;
+		let payload = Self::signing_payload(key, &self.value, self.expires_at);
+		sr25519::Pair::verify(&self.signature, &payload, &self.signer)
+	}
+}
+
 /// Execution context extra capabilities.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u8)]
@@ -242,6 +283,8 @@ pub enum Capability {
 	OffchainWorkerDbRead = 32,
 	/// Access to offchain worker DB (writes).
 	OffchainWorkerDbWrite = 64,
+	/// Access to the network's DHT.
+	Dht = 128,
 }
 
 /// A set of capabilities
@@ -459,6 +502,34 @@ pub trait Externalities: Send {
 		deadline: Option<Timestamp>
 	) -> Result<usize, HttpError>;
 
+	/// Publish a signed record in the DHT under the given key.
+	///
+	/// Blocks until the record has been stored by the network or `deadline` is reached.
+	/// Passing `None` as a deadline blocks until the network gives up.
+	///
+	/// Returns an error if:
+	/// - The record's signature is invalid, or the record has expired or expires too far in the
+	///   future.
+	/// - The network failed to store the record before the deadline.
+	fn dht_put(
+		&mut self,
+		key: &[u8],
+		record: DhtRecord,
+		deadline: Option<Timestamp>
+	) -> Result<(), ()>;
+
+	/// Fetch the records stored in the DHT under the given key.
+	///
+	/// Blocks until the network answers or `deadline` is reached. Only records with a valid
+	/// signature that haven't expired are returned.
+	/// Passing `None` as a deadline blocks until the network gives up.
+	///
+	/// Returns an error if the deadline is reached.
+	fn dht_get(
+		&mut self,
+		key: &[u8],
+		deadline: Option<Timestamp>
+	) -> Result<Vec<DhtRecord>, ()>;
 }
 impl<T: Externalities + ?Sized> Externalities for Box<T> {
 	fn is_validator(&self) -> bool {
@@ -536,6 +607,14 @@ impl<T: Externalities + ?Sized> Externalities for Box<T> {
 	) -> Result<usize, HttpError> {
 		(&mut **self).http_response_read_body(request_id, buffer, deadline)
 	}
+
+	fn dht_put(&mut self, key: &[u8], record: DhtRecord, deadline: Option<Timestamp>) -> Result<(), ()> {
+		(&mut **self).dht_put(key, record, deadline)
+	}
+
+	fn dht_get(&mut self, key: &[u8], deadline: Option<Timestamp>) -> Result<Vec<DhtRecord>, ()> {
+		(&mut **self).dht_get(key, deadline)
+	}
 }
 /// An `OffchainExternalities` implementation with limited capabilities.
 pub struct LimitedExternalities<T> {
@@ -653,6 +732,16 @@ impl<T: Externalities> Externalities for LimitedExternalities<T> {
 		self.check(Capability::Http, "http_response_read_body");
 		self.externalities.http_response_read_body(request_id, buffer, deadline)
 	}
+
+	fn dht_put(&mut self, key: &[u8], record: DhtRecord, deadline: Option<Timestamp>) -> Result<(), ()> {
+		self.check(Capability::Dht, "dht_put");
+		self.externalities.dht_put(key, record, deadline)
+	}
+
+	fn dht_get(&mut self, key: &[u8], deadline: Option<Timestamp>) -> Result<Vec<DhtRecord>, ()> {
+		self.check(Capability::Dht, "dht_get");
+		self.externalities.dht_get(key, deadline)
+	}
 }
 
 #[cfg(feature = "std")]
@@ -693,5 +782,27 @@ mod tests {
 		assert!(!none.has(Capability::TransactionPool));
 		assert!(all.has(Capability::TransactionPool));
 		assert!(!some.has(Capability::TransactionPool));
+		assert!(all.has(Capability::Dht));
+		assert!(!some.has(Capability::Dht));
+	}
+
+	#[test]
+	fn dht_record_signature() {
+		use crate::crypto::Pair as _;
+
+		let pair = sr25519::Pair::from_seed(&[1u8; 32]);
+		let expires_at = Timestamp::from_unix_millis(1_000);
+		let record = DhtRecord {
+			value: b"endpoint".to_vec(),
+			expires_at,
+			signer: pair.public(),
+			signature: pair.sign(&DhtRecord::signing_payload(b"key", b"endpoint", expires_at)),
+		};
+
+		assert!(record.verify(b"key"));
+		assert!(!record.verify(b"other key"));
+		assert!(!DhtRecord { value: b"other".to_vec(), ..record.clone() }.verify(b"key"));
+		assert!(!record.is_expired(Timestamp::from_unix_millis(999)));
+		assert!(record.is_expired(Timestamp::from_unix_millis(1_000)));
 	}
 }
@@ -0,0 +1,62 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! DHT RPC module errors.
+
+use jsonrpc_core as rpc;
+
+/// DHT RPC Result type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// DHT RPC future Result type.
+pub type FutureResult<T> = Box<dyn rpc::futures::Future<Item = T, Error = Error> + Send>;
+
+/// DHT RPC errors.
+#[derive(Debug, derive_more::Display, derive_more::From)]
+pub enum Error {
+	/// The signer or signature of the record is malformed.
+	#[display(fmt = "Malformed record: {}", _0)]
+	MalformedRecord(String),
+	/// The record was refused, or the network failed to process the request.
+	#[display(fmt = "DHT request failed: {}", _0)]
+	RequestFailed(String),
+	/// Publishing records is not allowed on this interface.
+	#[display(fmt = "{}", _0)]
+	UnsafeRpcCalled(crate::policy::UnsafeRpcError),
+}
+
+impl std::error::Error for Error {}
+
+/// Base code for all DHT errors.
+const BASE_ERROR: i64 = 5000;
+
+impl From<Error> for rpc::Error {
+	fn from(e: Error) -> Self {
+		match e {
+			Error::MalformedRecord(_) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 1),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::RequestFailed(_) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 2),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::UnsafeRpcCalled(e) => e.into(),
+		}
+	}
+}
@@ -0,0 +1,55 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Substrate DHT API.
+
+pub mod error;
+
+use jsonrpc_derive::rpc;
+use primitives::Bytes;
+use serde::{Serialize, Deserialize};
+
+use self::error::FutureResult;
+
+pub use self::gen_client::Client as DhtClient;
+
+/// A signed and expiring DHT record.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Record {
+	/// The value of the record.
+	pub value: Bytes,
+	/// Unix timestamp in milliseconds after which the record is no longer valid.
+	pub expires_at: u64,
+	/// The sr25519 public key that signed the record.
+	pub signer: Bytes,
+	/// The sr25519 signature of the record by `signer`.
+	pub signature: Bytes,
+}
+
+/// Substrate DHT RPC API
+#[rpc]
+pub trait DhtApi {
+	/// Fetch the records stored under the given key.
+	///
+	/// Records with an invalid signature or that have expired are not returned.
+	#[rpc(name = "dht_getRecords")]
+	fn get_records(&self, key: Bytes) -> FutureResult<Vec<Record>>;
+
+	/// Publish a signed record under the given key.
+	#[rpc(name = "dht_putRecord")]
+	fn put_record(&self, key: Bytes, record: Record) -> FutureResult<()>;
+}
@@ -0,0 +1,54 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Policy for the RPC methods that are unsafe to expose publicly.
+//!
+//! Such methods take a [`DenyUnsafe`] and refuse to run when it is `Yes`, answering as if they
+//! didn't exist.
+
+use jsonrpc_core as rpc;
+
+/// Whether the methods that are unsafe to expose publicly must be denied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DenyUnsafe {
+	/// Deny the unsafe methods.
+	Yes,
+	/// Allow the unsafe methods.
+	No,
+}
+
+impl DenyUnsafe {
+	/// Returns an error if the unsafe methods are denied.
+	pub fn check_if_safe(self) -> Result<(), UnsafeRpcError> {
+		match self {
+			DenyUnsafe::Yes => Err(UnsafeRpcError),
+			DenyUnsafe::No => Ok(()),
+		}
+	}
+}
+
+/// An unsafe method was called while the unsafe methods are denied.
+#[derive(Debug, derive_more::Display)]
+#[display(fmt = "RPC method is unsafe to be called publicly.")]
+pub struct UnsafeRpcError;
+
+impl std::error::Error for UnsafeRpcError {}
+
+impl From<UnsafeRpcError> for rpc::Error {
+	fn from(_: UnsafeRpcError) -> rpc::Error {
+		rpc::Error::method_not_found()
+	}
+}
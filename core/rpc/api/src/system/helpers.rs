@@ -87,6 +87,18 @@ pub enum NodeRole {
 	UnknownRole(u8)
 }
 
+/// Outcome of reloading the configuration of a subsystem.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadOutcome {
+	/// Name of the subsystem
+	pub subsystem: String,
+	/// Whether the new configuration has been applied
+	pub applied: bool,
+	/// What has been applied, or why it couldn't be
+	pub message: String,
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -116,4 +128,16 @@ mod tests {
 			r#"{"peerId":"2","roles":"a","protocolVersion":2,"bestHash":5,"bestNumber":6}"#,
 		);
 	}
+
+	#[test]
+	fn should_serialize_reload_outcome() {
+		assert_eq!(
+			::serde_json::to_string(&ReloadOutcome {
+				subsystem: "txpool".into(),
+				applied: true,
+				message: "a".into(),
+			}).unwrap(),
+			r#"{"subsystem":"txpool","applied":true,"message":"a"}"#,
+		);
+	}
 }
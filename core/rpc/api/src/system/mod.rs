@@ -24,7 +24,7 @@ use jsonrpc_derive::rpc;
 
 use self::error::Result;
 
-pub use self::helpers::{Properties, SystemInfo, Health, PeerInfo, NodeRole};
+pub use self::helpers::{Properties, SystemInfo, Health, PeerInfo, NodeRole, ReloadOutcome};
 pub use self::gen_client::Client as SystemClient;
 
 /// Substrate system RPC API
@@ -68,4 +68,12 @@ pub trait SystemApi<Hash, Number> {
 	/// Returns the roles the node is running as.
 	#[rpc(name = "system_nodeRoles", returns = "Vec<NodeRole>")]
 	fn system_node_roles(&self) -> Receiver<Vec<NodeRole>>;
+
+	/// Reloads the reloadable part of the node configuration from the configuration file the
+	/// node was started with, and returns what each subsystem applied.
+	///
+	/// **Warning**: This is an administrative call, it is refused unless the unsafe RPC methods
+	/// are allowed.
+	#[rpc(name = "system_reloadConfig", returns = "Vec<ReloadOutcome>")]
+	fn system_reload_config(&self) -> jsonrpc_core::BoxFuture<Vec<ReloadOutcome>>;
 }
@@ -0,0 +1,104 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Substrate DHT API.
+
+#[cfg(test)]
+mod tests;
+
+use std::{convert::TryFrom, sync::Arc, time::SystemTime};
+use futures03::future::{FutureExt, TryFutureExt};
+use primitives::{Bytes, offchain::{DhtRecord, Timestamp}, sr25519};
+use rpc::futures::future::result;
+use api::DenyUnsafe;
+
+use self::error::{Error, FutureResult};
+
+pub use api::dht::*;
+
+/// DHT API implementation
+pub struct Dht {
+	client: Arc<network::DhtClient>,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl Dht {
+	/// Creates new `Dht` on top of the given network DHT client.
+	///
+	/// Publishing records is refused when `deny_unsafe` is `Yes`.
+	pub fn new(client: Arc<network::DhtClient>, deny_unsafe: DenyUnsafe) -> Self {
+		Dht {
+			client,
+			deny_unsafe,
+		}
+	}
+}
+
+impl DhtApi for Dht {
+	fn get_records(&self, key: Bytes) -> FutureResult<Vec<Record>> {
+		Box::new(self.client
+			.get_records(&key, now())
+			.map_ok(|records| records.into_iter().map(into_rpc_record).collect())
+			.map_err(|e| Error::RequestFailed(e.to_string()))
+			.boxed()
+			.compat())
+	}
+
+	fn put_record(&self, key: Bytes, record: Record) -> FutureResult<()> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())));
+		}
+		let record = match from_rpc_record(record) {
+			Ok(record) => record,
+			Err(err) => return Box::new(result(Err(err))),
+		};
+		Box::new(self.client
+			.put_record(&key, record, now())
+			.map_err(|e| Error::RequestFailed(e.to_string()))
+			.boxed()
+			.compat())
+	}
+}
+
+fn now() -> Timestamp {
+	let millis = SystemTime::now()
+		.duration_since(SystemTime::UNIX_EPOCH)
+		.map(|d| d.as_millis() as u64)
+		.unwrap_or(0);
+	Timestamp::from_unix_millis(millis)
+}
+
+fn into_rpc_record(record: DhtRecord) -> Record {
+	Record {
+		value: record.value.into(),
+		expires_at: record.expires_at.unix_millis(),
+		signer: record.signer.as_ref().to_vec().into(),
+		signature: record.signature.as_ref().to_vec().into(),
+	}
+}
+
+fn from_rpc_record(record: Record) -> Result<DhtRecord, Error> {
+	let signer = sr25519::Public::try_from(&record.signer[..])
+		.map_err(|_| Error::MalformedRecord("signer must be 32 bytes".into()))?;
+	let signature = sr25519::Signature::try_from(&record.signature[..])
+		.map_err(|_| Error::MalformedRecord("signature must be 64 bytes".into()))?;
+	Ok(DhtRecord {
+		value: record.value.to_vec(),
+		expires_at: Timestamp::from_unix_millis(record.expires_at),
+		signer,
+		signature,
+	})
+}
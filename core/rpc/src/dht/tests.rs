@@ -0,0 +1,104 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::*;
+
+use assert_matches::assert_matches;
+use network::{DhtEvent, DhtNetwork, dht_client::Key};
+use parking_lot::Mutex;
+use primitives::{crypto::Pair, offchain::Duration};
+use rpc::futures::Future;
+
+#[derive(Default)]
+struct TestNetwork {
+	puts: Mutex<Vec<Key>>,
+}
+
+impl DhtNetwork for TestNetwork {
+	fn get_value(&self, _key: &Key) {}
+
+	fn put_value(&self, key: Key, _value: Vec<u8>) {
+		self.puts.lock().push(key);
+	}
+}
+
+fn signed_record(key: &[u8], value: &[u8]) -> Record {
+	let pair = sr25519::Pair::from_seed(&[3u8; 32]);
+	let expires_at = now().add(Duration::from_millis(60_000));
+	let signature = pair.sign(&DhtRecord::signing_payload(key, value, expires_at));
+	Record {
+		value: value.to_vec().into(),
+		expires_at: expires_at.unix_millis(),
+		signer: pair.public().as_ref().to_vec().into(),
+		signature: signature.as_ref().to_vec().into(),
+	}
+}
+
+#[test]
+fn should_put_record() {
+	let network = Arc::new(TestNetwork::default());
+	let client = Arc::new(network::DhtClient::new(network.clone()));
+	let dht = Dht::new(client.clone(), DenyUnsafe::No);
+
+	let put = dht.put_record(b"key".to_vec().into(), signed_record(b"key", b"value"));
+	client.on_event(&DhtEvent::ValuePut(network.puts.lock()[0].clone()));
+
+	assert_matches!(put.wait(), Ok(()));
+}
+
+#[test]
+fn should_reject_malformed_record() {
+	let network = Arc::new(TestNetwork::default());
+	let dht = Dht::new(Arc::new(network::DhtClient::new(network.clone())), DenyUnsafe::No);
+
+	let mut record = signed_record(b"key", b"value");
+	record.signer = vec![1, 2, 3].into();
+
+	assert_matches!(
+		dht.put_record(b"key".to_vec().into(), record).wait(),
+		Err(Error::MalformedRecord(_))
+	);
+	assert!(network.puts.lock().is_empty());
+}
+
+#[test]
+fn should_reject_record_signed_for_another_key() {
+	let network = Arc::new(TestNetwork::default());
+	let dht = Dht::new(Arc::new(network::DhtClient::new(network.clone())), DenyUnsafe::No);
+
+	assert_matches!(
+		dht.put_record(b"key".to_vec().into(), signed_record(b"other", b"value")).wait(),
+		Err(Error::RequestFailed(_))
+	);
+}
+
+#[test]
+fn records_round_trip_through_rpc_format() {
+	let record = signed_record(b"key", b"value");
+	assert_eq!(into_rpc_record(from_rpc_record(record.clone()).unwrap()), record);
+}
+
+#[test]
+fn should_deny_put_record_when_unsafe_is_denied() {
+	let network = Arc::new(TestNetwork::default());
+	let dht = Dht::new(Arc::new(network::DhtClient::new(network.clone())), DenyUnsafe::Yes);
+
+	assert_matches!(
+		dht.put_record(b"key".to_vec().into(), signed_record(b"key", b"value")).wait(),
+		Err(Error::UnsafeRpcCalled(_))
+	);
+	assert!(network.puts.lock().is_empty());
+}
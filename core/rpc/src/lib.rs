@@ -22,11 +22,12 @@
 
 mod metadata;
 
-pub use api::Subscriptions;
+pub use api::{DenyUnsafe, Subscriptions};
 pub use self::metadata::Metadata;
 pub use rpc::IoHandlerExtension as RpcExtension;
 
 pub mod author;
 pub mod chain;
+pub mod dht;
 pub mod state;
 pub mod system;
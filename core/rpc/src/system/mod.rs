@@ -20,18 +20,19 @@
 mod tests;
 
 use futures03::{channel::{mpsc, oneshot}, compat::Compat};
-use api::Receiver;
+use api::{DenyUnsafe, Receiver};
 use sr_primitives::traits::{self, Header as HeaderT};
 use self::error::Result;
 
 pub use api::system::*;
-pub use self::helpers::{Properties, SystemInfo, Health, PeerInfo, NodeRole};
+pub use self::helpers::{Properties, SystemInfo, Health, PeerInfo, NodeRole, ReloadOutcome};
 pub use self::gen_client::Client as SystemClient;
 
 /// System API implementation
 pub struct System<B: traits::Block> {
 	info: SystemInfo,
 	send_back: mpsc::UnboundedSender<Request<B>>,
+	deny_unsafe: DenyUnsafe,
 }
 
 /// Request to be processed.
@@ -43,7 +44,9 @@ pub enum Request<B: traits::Block> {
 	/// Must return the state of the network.
 	NetworkState(oneshot::Sender<rpc::Value>),
 	/// Must return the node role.
-	NodeRoles(oneshot::Sender<Vec<NodeRole>>)
+	NodeRoles(oneshot::Sender<Vec<NodeRole>>),
+	/// Must reload the configuration and return what has been applied.
+	ReloadConfig(oneshot::Sender<Vec<ReloadOutcome>>),
 }
 
 impl<B: traits::Block> System<B> {
@@ -51,13 +54,17 @@ impl<B: traits::Block> System<B> {
 	///
 	/// The `send_back` will be used to transmit some of the requests. The user is responsible for
 	/// reading from that channel and answering the requests.
+	///
+	/// Reloading the configuration is refused when `deny_unsafe` is `Yes`.
 	pub fn new(
 		info: SystemInfo,
-		send_back: mpsc::UnboundedSender<Request<B>>
+		send_back: mpsc::UnboundedSender<Request<B>>,
+		deny_unsafe: DenyUnsafe,
 	) -> Self {
 		System {
 			info,
 			send_back,
+			deny_unsafe,
 		}
 	}
 }
@@ -102,4 +109,13 @@ impl<B: traits::Block> SystemApi<B::Hash, <B::Header as HeaderT>::Number> for Sy
 		let _ = self.send_back.unbounded_send(Request::NodeRoles(tx));
 		Receiver(Compat::new(rx))
 	}
+
+	fn system_reload_config(&self) -> rpc::BoxFuture<Vec<ReloadOutcome>> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(rpc::futures::future::err(err.into()));
+		}
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::ReloadConfig(tx));
+		Box::new(Receiver(Compat::new(rx)))
+	}
 }
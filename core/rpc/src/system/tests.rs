@@ -42,6 +42,10 @@ impl Default for Status {
 }
 
 fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
+	api_with_policy(sync, DenyUnsafe::No)
+}
+
+fn api_with_policy<T: Into<Option<Status>>>(sync: T, deny_unsafe: DenyUnsafe) -> System<Block> {
 	let status = sync.into().unwrap_or_default();
 	let should_have_peers = !status.is_dev;
 	let (tx, rx) = mpsc::unbounded();
@@ -83,6 +87,13 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 				Request::NodeRoles(sender) => {
 					let _ = sender.send(vec![NodeRole::Authority]);
 				}
+				Request::ReloadConfig(sender) => {
+					let _ = sender.send(vec![ReloadOutcome {
+						subsystem: "txpool".into(),
+						applied: true,
+						message: "ready: 1 transactions".into(),
+					}]);
+				}
 			};
 
 			future::ready(())
@@ -93,10 +104,12 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 		impl_version: "0.2.0".into(),
 		chain_name: "testchain".into(),
 		properties: Default::default(),
-	}, tx)
+	}, tx, deny_unsafe)
 }
 
-fn wait_receiver<T>(rx: Receiver<T>) -> T {
+fn wait_receiver<F>(rx: F) -> F::Item
+	where F: rpc::futures::Future, F::Error: std::fmt::Debug
+{
 	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
 	runtime.block_on(rx).unwrap()
 }
@@ -231,4 +244,24 @@ fn system_node_roles() {
 		wait_receiver(api(None).system_node_roles()),
 		vec![NodeRole::Authority]
 	);
-}
\ No newline at end of file
+}
+#[test]
+fn system_reload_config() {
+	assert_eq!(
+		wait_receiver(api(None).system_reload_config()),
+		vec![ReloadOutcome {
+			subsystem: "txpool".into(),
+			applied: true,
+			message: "ready: 1 transactions".into(),
+		}]
+	);
+}
+
+#[test]
+fn system_reload_config_is_denied_when_unsafe_is_denied() {
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+	assert_eq!(
+		runtime.block_on(api_with_policy(None, DenyUnsafe::Yes).system_reload_config()),
+		Err(rpc::Error::method_not_found())
+	);
+}
@@ -17,10 +17,16 @@
 use crate::{Service, NetworkStatus, NetworkState, error::{self, Error}, DEFAULT_PROTOCOL_ID};
 use crate::{SpawnTaskHandle, start_rpc_servers, build_network_future, TransactionPoolAdapter};
 use crate::status_sinks;
-use crate::config::{Configuration, DatabaseConfig};
+use crate::reload::{self, ConfigReloader};
+use crate::chain_ops::{
+	dry_run_upgrade, replay_blocks, session_keys_status, ReplayExecution, ReplayReport, SessionKeyStatus,
+	UpgradeDryRunReport,
+};
+use crate::config::{Configuration, DatabaseConfig, PrometheusConfig};
+use crate::metrics::ServiceMetrics;
 use client::{
 	BlockchainEvents, Client, runtime_api,
-	backend::RemoteBackend, light::blockchain::RemoteBlockchain,
+	backend::{IntegrityReport, RemoteBackend}, light::blockchain::RemoteBlockchain,
 };
 use chain_spec::{RuntimeGenesis, Extension};
 use codec::{Decode, Encode, IoReader};
@@ -34,17 +40,19 @@ use futures03::{
 };
 use keystore::{Store as Keystore};
 use log::{info, warn};
-use network::{FinalityProofProvider, OnDemand, NetworkService, NetworkStateInfo, DhtEvent};
+use network::{FinalityProofProvider, OnDemand, NetworkService, NetworkStateInfo, DhtEvent, DhtClient};
 use network::{config::BoxFinalityProofRequestBuilder, specialization::NetworkSpecialization};
 use parking_lot::{Mutex, RwLock};
-use primitives::{Blake2Hasher, H256, Hasher};
+use primitives::{Blake2Hasher, H256, Hasher, crypto::KeyTypeId};
 use rpc;
 use sr_primitives::generic::BlockId;
 use sr_primitives::traits::{
-	Block as BlockT, Extrinsic, ProvideRuntimeApi, NumberFor, One, Zero, Header, SaturatedConversion
+	Block as BlockT, Extrinsic, ProvideRuntimeApi, NumberFor, One, Zero, Header, SaturatedConversion,
+	Saturating,
 };
 use substrate_executor::{NativeExecutor, NativeExecutionDispatch};
-use std::{io::{Read, Write, Seek}, marker::PhantomData, sync::Arc, sync::atomic::AtomicBool};
+use std::{io::{Read, Write, Seek}, marker::PhantomData, sync::Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
 use sysinfo::{get_current_pid, ProcessExt, System, SystemExt};
 use tel::{telemetry, SUBSTRATE_INFO};
 use transaction_pool::txpool::{self, ChainApi, Pool as TransactionPool};
@@ -83,10 +91,37 @@ pub struct ServiceBuilder<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImp
 	transaction_pool: Arc<TExPool>,
 	rpc_extensions: TRpc,
 	remote_backend: Option<Arc<dyn RemoteBlockchain<TBl>>>,
-	dht_event_tx: Option<mpsc::Sender<DhtEvent>>,
+	dht_event_tx: Option<futures03::channel::mpsc::Sender<DhtEvent>>,
+	tasks: TaskChannels,
 	marker: PhantomData<(TBl, TRtApi)>,
 }
 
+/// The channel the background tasks of the service are sent on, along with its exit signal.
+///
+/// It is created with the builder, so that tasks can be spawned before the service is built (e.g.
+/// by the subscriptions of RPC extensions).
+struct TaskChannels {
+	to_spawn_tx: mpsc::UnboundedSender<Box<dyn Future<Item = (), Error = ()> + Send>>,
+	to_spawn_rx: mpsc::UnboundedReceiver<Box<dyn Future<Item = (), Error = ()> + Send>>,
+	signal: exit_future::Signal,
+	exit: exit_future::Exit,
+}
+
+impl TaskChannels {
+	fn new() -> Self {
+		let (signal, exit) = exit_future::signal();
+		// List of asynchronous tasks to spawn. We collect them, then spawn them all at once.
+		let (to_spawn_tx, to_spawn_rx) =
+			mpsc::unbounded::<Box<dyn Future<Item = (), Error = ()> + Send>>();
+
+		TaskChannels { to_spawn_tx, to_spawn_rx, signal, exit }
+	}
+
+	fn spawn_handle(&self) -> SpawnTaskHandle {
+		SpawnTaskHandle { sender: self.to_spawn_tx.clone(), on_exit: self.exit.clone() }
+	}
+}
+
 /// Full client type.
 type TFullClient<TBl, TRtApi, TExecDisp> = Client<
 	TFullBackend<TBl>,
@@ -160,10 +195,13 @@ where TGen: RuntimeGenesis, TCSExt: Extension {
 			config.keystore_password.clone()
 		)?;
 
-		let executor = NativeExecutor::<TExecDisp>::new(
+		let mut executor = NativeExecutor::<TExecDisp>::new(
 			config.wasm_method,
 			config.default_heap_pages,
 		);
+		if let Some(registry) = config.prometheus_registry() {
+			executor = executor.with_prometheus_registry(registry);
+		}
 
 		let fork_blocks = config.chain_spec
 			.extensions()
@@ -177,11 +215,14 @@ where TGen: RuntimeGenesis, TCSExt: Extension {
 				state_cache_child_ratio:
 					config.state_cache_child_ratio.map(|v| (v, 100)),
 				pruning: config.pruning.clone(),
+				retention: config.retention.clone(),
 				source: match &config.database {
-					DatabaseConfig::Path { path, cache_size } =>
+					DatabaseConfig::Path { path, cache_size, backend, columns } =>
 						client_db::DatabaseSettingsSrc::Path {
 							path: path.clone(),
 							cache_size: cache_size.clone().map(|u| u as usize),
+							backend: *backend,
+							columns: columns.clone(),
 						},
 					DatabaseConfig::Custom(db) =>
 						client_db::DatabaseSettingsSrc::Custom(db.clone()),
@@ -215,6 +256,7 @@ where TGen: RuntimeGenesis, TCSExt: Extension {
 			rpc_extensions: Default::default(),
 			remote_backend: None,
 			dht_event_tx: None,
+			tasks: TaskChannels::new(),
 			marker: PhantomData,
 		})
 	}
@@ -244,10 +286,13 @@ where TGen: RuntimeGenesis, TCSExt: Extension {
 			config.keystore_password.clone()
 		)?;
 
-		let executor = NativeExecutor::<TExecDisp>::new(
+		let mut executor = NativeExecutor::<TExecDisp>::new(
 			config.wasm_method,
 			config.default_heap_pages,
 		);
+		if let Some(registry) = config.prometheus_registry() {
+			executor = executor.with_prometheus_registry(registry);
+		}
 
 		let db_storage = {
 			let db_settings = client_db::DatabaseSettings {
@@ -255,11 +300,14 @@ where TGen: RuntimeGenesis, TCSExt: Extension {
 				state_cache_child_ratio:
 					config.state_cache_child_ratio.map(|v| (v, 100)),
 				pruning: config.pruning.clone(),
+				retention: config.retention.clone(),
 				source: match &config.database {
-					DatabaseConfig::Path { path, cache_size } =>
+					DatabaseConfig::Path { path, cache_size, backend, columns } =>
 						client_db::DatabaseSettingsSrc::Path {
 							path: path.clone(),
 							cache_size: cache_size.clone().map(|u| u as usize),
+							backend: *backend,
+							columns: columns.clone(),
 						},
 					DatabaseConfig::Custom(db) =>
 						client_db::DatabaseSettingsSrc::Custom(db.clone()),
@@ -293,6 +341,7 @@ where TGen: RuntimeGenesis, TCSExt: Extension {
 			rpc_extensions: Default::default(),
 			remote_backend: Some(remote_blockchain),
 			dht_event_tx: None,
+			tasks: TaskChannels::new(),
 			marker: PhantomData,
 		})
 	}
@@ -317,6 +366,16 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 		self.select_chain.as_ref()
 	}
 
+	/// Returns a reference to the keystore that was opened by this builder.
+	pub fn keystore(&self) -> Arc<RwLock<Keystore>> {
+		self.keystore.clone()
+	}
+
+	/// Returns whether the RPC methods that are unsafe to expose publicly must be denied.
+	pub fn deny_unsafe_rpc(&self) -> rpc::DenyUnsafe {
+		self.config.deny_unsafe_rpc()
+	}
+
 	/// Defines which head-of-chain strategy to use.
 	pub fn with_opt_select_chain<USc>(
 		self,
@@ -342,6 +401,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			rpc_extensions: self.rpc_extensions,
 			remote_backend: self.remote_backend,
 			dht_event_tx: self.dht_event_tx,
+			tasks: self.tasks,
 			marker: self.marker,
 		})
 	}
@@ -385,6 +445,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			rpc_extensions: self.rpc_extensions,
 			remote_backend: self.remote_backend,
 			dht_event_tx: self.dht_event_tx,
+			tasks: self.tasks,
 			marker: self.marker,
 		})
 	}
@@ -412,6 +473,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			rpc_extensions: self.rpc_extensions,
 			remote_backend: self.remote_backend,
 			dht_event_tx: self.dht_event_tx,
+			tasks: self.tasks,
 			marker: self.marker,
 		})
 	}
@@ -454,6 +516,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			rpc_extensions: self.rpc_extensions,
 			remote_backend: self.remote_backend,
 			dht_event_tx: self.dht_event_tx,
+			tasks: self.tasks,
 			marker: self.marker,
 		})
 	}
@@ -520,6 +583,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			rpc_extensions: self.rpc_extensions,
 			remote_backend: self.remote_backend,
 			dht_event_tx: self.dht_event_tx,
+			tasks: self.tasks,
 			marker: self.marker,
 		})
 	}
@@ -567,17 +631,26 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			rpc_extensions: self.rpc_extensions,
 			remote_backend: self.remote_backend,
 			dht_event_tx: self.dht_event_tx,
+			tasks: self.tasks,
 			marker: self.marker,
 		})
 	}
 
 	/// Defines the RPC extensions to use.
+	///
+	/// The given `rpc::Subscriptions` can be used by the extensions to drive their subscriptions.
 	pub fn with_rpc_extensions<URpc>(
 		self,
-		rpc_ext_builder: impl FnOnce(Arc<TCl>, Arc<TExPool>, Arc<Backend>) -> URpc
+		rpc_ext_builder: impl FnOnce(Arc<TCl>, Arc<TExPool>, Arc<Backend>, rpc::Subscriptions) -> URpc
 	) -> Result<ServiceBuilder<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
 		TNetP, TExPool, URpc, Backend>, Error> {
-		let rpc_extensions = rpc_ext_builder(self.client.clone(), self.transaction_pool.clone(), self.backend.clone());
+		let subscriptions = rpc::Subscriptions::new(Arc::new(self.tasks.spawn_handle()));
+		let rpc_extensions = rpc_ext_builder(
+			self.client.clone(),
+			self.transaction_pool.clone(),
+			self.backend.clone(),
+			subscriptions,
+		);
 
 		Ok(ServiceBuilder {
 			config: self.config,
@@ -594,6 +667,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			rpc_extensions,
 			remote_backend: self.remote_backend,
 			dht_event_tx: self.dht_event_tx,
+			tasks: self.tasks,
 			marker: self.marker,
 		})
 	}
@@ -602,7 +676,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 	/// module.
 	pub fn with_dht_event_tx(
 		self,
-		dht_event_tx: mpsc::Sender<DhtEvent>,
+		dht_event_tx: futures03::channel::mpsc::Sender<DhtEvent>,
 	) -> Result<ServiceBuilder<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
 								TNetP, TExPool, TRpc, Backend>, Error> {
 		Ok(ServiceBuilder {
@@ -620,6 +694,7 @@ impl<TBl, TRtApi, TCfg, TGen, TCSExt, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TNet
 			rpc_extensions: self.rpc_extensions,
 			remote_backend: self.remote_backend,
 			dht_event_tx: Some(dht_event_tx),
+			tasks: self.tasks,
 			marker: self.marker,
 		})
 	}
@@ -664,6 +739,73 @@ pub trait ServiceBuilderRevert {
 		&self,
 		blocks: NumberFor<Self::Block>
 	) -> Result<(), Error>;
+
+	/// Performs a revert of up to `blocks` blocks, including finalized ones, stopping at the
+	/// first block whose state is still available. `revert_aux` is called with the target
+	/// block before anything is removed and must return the consensus data kept in the aux
+	/// store (e.g. authority sets and epochs) rewound accordingly, without writing it. It is
+	/// written in the same transaction as the reverted blocks.
+	fn unsafe_revert_chain<F>(
+		&self,
+		blocks: NumberFor<Self::Block>,
+		revert_aux: F,
+	) -> Result<(), Error> where
+		F: FnOnce(
+			<Self::Block as BlockT>::Hash,
+			NumberFor<Self::Block>,
+		) -> Result<Vec<(Vec<u8>, Option<Vec<u8>>)>, Error>;
+}
+
+/// Implemented on `ServiceBuilder`. Allows checking the integrity of the database once you have
+/// given all the required components to the builder.
+pub trait ServiceBuilderCheckDb: ServiceBuilderRevert {
+	/// Checks the data of the last `blocks` blocks of the best chain.
+	fn check_db(
+		&self,
+		blocks: NumberFor<Self::Block>
+	) -> Result<IntegrityReport<NumberFor<Self::Block>>, Error>;
+}
+
+/// Implemented on `ServiceBuilder`. Allows trying out a runtime upgrade against the state of the
+/// chain once you have given all the required components to the builder.
+pub trait ServiceBuilderDryRunUpgrade {
+	/// Type of block of the builder.
+	type Block: BlockT;
+
+	/// Executes the `blocks` blocks following `at` under both the current runtime and `code`,
+	/// as if `code` had been enacted at `at`, and compares the outcomes.
+	fn dry_run_upgrade(
+		&self,
+		code: &[u8],
+		at: <Self::Block as BlockT>::Hash,
+		blocks: u32,
+	) -> Result<UpgradeDryRunReport<NumberFor<Self::Block>>, Error>;
+}
+
+/// Implemented on `ServiceBuilder`. Allows re-executing blocks of the chain once you have given
+/// all the required components to the builder.
+pub trait ServiceBuilderReplayBlocks {
+	/// Type of block of the builder.
+	type Block: BlockT;
+
+	/// Re-executes the blocks `from..=to` of the best chain with the given runtimes and reports
+	/// the blocks that don't yield the expected results.
+	fn replay_blocks(
+		&self,
+		from: NumberFor<Self::Block>,
+		to: NumberFor<Self::Block>,
+		execution: ReplayExecution,
+	) -> Result<ReplayReport<NumberFor<Self::Block>>, Error>;
+}
+
+/// Implemented on `ServiceBuilder`. Allows looking up session keys in the state of the chain once
+/// you have given all the required components to the builder.
+pub trait ServiceBuilderSessionKeys {
+	/// Returns the on-chain status of the given session keys, as `(key type, raw public key)`.
+	fn session_keys_status(
+		&self,
+		keys: &[(KeyTypeId, Vec<u8>)],
+	) -> Result<Vec<SessionKeyStatus>, Error>;
 }
 
 impl<
@@ -732,6 +874,102 @@ where
 		let client = &self.client;
 		revert_chain!(client, blocks)
 	}
+
+	fn unsafe_revert_chain<F>(
+		&self,
+		blocks: NumberFor<TBl>,
+		revert_aux: F,
+	) -> Result<(), Error> where
+		F: FnOnce(TBl::Hash, NumberFor<TBl>) -> Result<Vec<(Vec<u8>, Option<Vec<u8>>)>, Error>,
+	{
+		let client = &self.client;
+		let backend = &self.backend;
+		unsafe_revert_chain!(client, backend, blocks, revert_aux)
+	}
+}
+
+impl<TBl, TRtApi, TCfg, TGen, TCSExt, TBackend, TExec, TFchr, TSc, TImpQu, TFprb, TFpp, TNetP, TExPool, TRpc>
+	ServiceBuilderCheckDb for ServiceBuilder<TBl, TRtApi, TCfg, TGen, TCSExt, Client<TBackend, TExec, TBl, TRtApi>,
+		TFchr, TSc, TImpQu, TFprb, TFpp, TNetP, TExPool, TRpc, TBackend>
+where
+	TBl: BlockT<Hash = <Blake2Hasher as Hasher>::Out>,
+	TBackend: 'static + client::backend::Backend<TBl, Blake2Hasher> + Send,
+	TExec: 'static + client::CallExecutor<TBl, Blake2Hasher> + Send + Sync + Clone
+{
+	fn check_db(
+		&self,
+		blocks: NumberFor<TBl>
+	) -> Result<IntegrityReport<NumberFor<TBl>>, Error> {
+		info!("Checking the last {} blocks of the database", blocks);
+		Ok(self.client.check_integrity(blocks)?)
+	}
+}
+
+impl<TBl, TRtApi, TCfg, TGen, TCSExt, TBackend, TExec, TFchr, TSc, TImpQu, TFprb, TFpp, TNetP, TExPool, TRpc>
+	ServiceBuilderDryRunUpgrade for ServiceBuilder<TBl, TRtApi, TCfg, TGen, TCSExt,
+		Client<TBackend, TExec, TBl, TRtApi>, TFchr, TSc, TImpQu, TFprb, TFpp, TNetP, TExPool, TRpc, TBackend>
+where
+	TBl: BlockT<Hash = <Blake2Hasher as Hasher>::Out>,
+	TBackend: 'static + client::backend::Backend<TBl, Blake2Hasher> + Send,
+	TExec: 'static + client::CallExecutor<TBl, Blake2Hasher> + Send + Sync + Clone
+{
+	type Block = TBl;
+
+	fn dry_run_upgrade(
+		&self,
+		code: &[u8],
+		at: TBl::Hash,
+		blocks: u32,
+	) -> Result<UpgradeDryRunReport<NumberFor<TBl>>, Error> {
+		info!("Dry running the runtime upgrade at {} over {} blocks", at, blocks);
+		dry_run_upgrade(
+			&self.client,
+			code,
+			at,
+			blocks,
+			self.config.wasm_method,
+			self.config.default_heap_pages,
+		)
+	}
+}
+
+impl<TBl, TRtApi, TCfg, TGen, TCSExt, TBackend, TExec, TFchr, TSc, TImpQu, TFprb, TFpp, TNetP, TExPool, TRpc>
+	ServiceBuilderReplayBlocks for ServiceBuilder<TBl, TRtApi, TCfg, TGen, TCSExt,
+		Client<TBackend, TExec, TBl, TRtApi>, TFchr, TSc, TImpQu, TFprb, TFpp, TNetP, TExPool, TRpc, TBackend>
+where
+	TBl: BlockT<Hash = <Blake2Hasher as Hasher>::Out>,
+	TBackend: 'static + client::backend::Backend<TBl, Blake2Hasher> + Send,
+	TExec: 'static + client::CallExecutor<TBl, Blake2Hasher> + Send + Sync + Clone
+{
+	type Block = TBl;
+
+	fn replay_blocks(
+		&self,
+		from: NumberFor<TBl>,
+		to: NumberFor<TBl>,
+		execution: ReplayExecution,
+	) -> Result<ReplayReport<NumberFor<TBl>>, Error> {
+		info!("Replaying blocks #{}..#{} with {:?} execution", from, to, execution);
+		replay_blocks(&self.client, from, to, execution)
+	}
+}
+
+impl<TBl, TRtApi, TCfg, TGen, TCSExt, TBackend, TExec, TFchr, TSc, TImpQu, TFprb, TFpp, TNetP, TExPool, TRpc>
+	ServiceBuilderSessionKeys for ServiceBuilder<TBl, TRtApi, TCfg, TGen, TCSExt,
+		Client<TBackend, TExec, TBl, TRtApi>, TFchr, TSc, TImpQu, TFprb, TFpp, TNetP, TExPool, TRpc, TBackend>
+where
+	TBl: BlockT<Hash = <Blake2Hasher as Hasher>::Out>,
+	TBackend: 'static + client::backend::Backend<TBl, Blake2Hasher> + Send,
+	TExec: 'static + client::CallExecutor<TBl, Blake2Hasher> + Send + Sync + Clone,
+	Client<TBackend, TExec, TBl, TRtApi>: ProvideRuntimeApi,
+	<Client<TBackend, TExec, TBl, TRtApi> as ProvideRuntimeApi>::Api: session::SessionKeysOwnership<TBl>,
+{
+	fn session_keys_status(
+		&self,
+		keys: &[(KeyTypeId, Vec<u8>)],
+	) -> Result<Vec<SessionKeyStatus>, Error> {
+		session_keys_status(&self.client, keys)
+	}
 }
 
 impl<TBl, TRtApi, TCfg, TGen, TCSExt, TBackend, TExec, TSc, TImpQu, TNetP, TExPoolApi, TRpc>
@@ -801,6 +1039,7 @@ ServiceBuilder<
 			rpc_extensions,
 			remote_backend,
 			dht_event_tx,
+			tasks: TaskChannels { to_spawn_tx, to_spawn_rx, signal, exit },
 		} = self;
 
 		session::generate_initial_session_keys(
@@ -808,12 +1047,6 @@ ServiceBuilder<
 			config.dev_key_seed.clone().map(|s| vec![s]).unwrap_or_default()
 		)?;
 
-		let (signal, exit) = exit_future::signal();
-
-		// List of asynchronous tasks to spawn. We collect them, then spawn them all at once.
-		let (to_spawn_tx, to_spawn_rx) =
-			mpsc::unbounded::<Box<dyn Future<Item = (), Error = ()> + Send>>();
-
 		let import_queue = Box::new(import_queue);
 		let chain_info = client.info().chain;
 
@@ -868,8 +1101,11 @@ ServiceBuilder<
 		let network = network_mut.service().clone();
 		let network_status_sinks = Arc::new(Mutex::new(status_sinks::StatusSinks::new()));
 
+		// Offchain workers are also created when they are disabled but can be enabled by reloading
+		// the configuration.
 		let offchain_storage = backend.offchain_storage();
-		let offchain_workers = match (config.offchain_worker, offchain_storage) {
+		let offchain_enabled = Arc::new(AtomicBool::new(config.offchain_worker));
+		let offchain_workers = match (config.offchain_worker || config.reload_config.is_some(), offchain_storage) {
 			(true, Some(db)) => {
 				Some(Arc::new(offchain::OffchainWorkers::new(client.clone(), db)))
 			},
@@ -880,13 +1116,17 @@ ServiceBuilder<
 			_ => None,
 		};
 
+		let dht_client = Arc::new(DhtClient::new(network.clone()));
+
 		{
 			// block notifications
 			let txpool = Arc::downgrade(&transaction_pool);
 			let wclient = Arc::downgrade(&client);
 			let offchain = offchain_workers.as_ref().map(Arc::downgrade);
+			let offchain_enabled = offchain_enabled.clone();
 			let to_spawn_tx_ = to_spawn_tx.clone();
 			let network_state_info: Arc<dyn NetworkStateInfo + Send + Sync> = network.clone();
+			let dht_client = dht_client.clone();
 			let is_validator = config.roles.is_authority();
 
 			let events = client.import_notification_stream()
@@ -905,9 +1145,17 @@ ServiceBuilder<
 						let _ = to_spawn_tx_.unbounded_send(future);
 					}
 
-					let offchain = offchain.as_ref().and_then(|o| o.upgrade());
+					let offchain = offchain.as_ref()
+						.filter(|_| offchain_enabled.load(Ordering::Relaxed))
+						.and_then(|o| o.upgrade());
 					if let (Some(txpool), Some(offchain)) = (txpool, offchain) {
-						let future = offchain.on_block_imported(&number, &txpool, network_state_info.clone(), is_validator)
+						let future = offchain.on_block_imported(
+							&number,
+							&txpool,
+							network_state_info.clone(),
+							dht_client.clone(),
+							is_validator,
+						)
 							.map(|()| Ok(()));
 						let _ = to_spawn_tx_.unbounded_send(Box::new(Compat::new(future)));
 					}
@@ -942,9 +1190,13 @@ ServiceBuilder<
 			let _ = to_spawn_tx.unbounded_send(Box::new(events));
 		}
 
-		// Periodically notify the telemetry.
+		// Periodically notify the telemetry and update the Prometheus metrics.
 		let transaction_pool_ = transaction_pool.clone();
 		let client_ = client.clone();
+		let mut metrics = match config.prometheus_registry() {
+			Some(registry) => Some(ServiceMetrics::register(registry).map_err(|e| Error::Prometheus(e.into()))?),
+			None => None,
+		};
 		let mut sys = System::new();
 		let self_pid = get_current_pid().ok();
 		let (state_tx, state_rx) = mpsc::unbounded::<(NetworkStatus<_>, NetworkState)>();
@@ -989,6 +1241,10 @@ ServiceBuilder<
 				"used_state_cache_size" => used_state_cache_size,
 			);
 
+			if let Some(metrics) = metrics.as_mut() {
+				metrics.update(&info, &net_status, &txpool_status);
+			}
+
 			Ok(())
 		}).select(exit.clone()).then(|_| Ok(()));
 		let _ = to_spawn_tx.unbounded_send(Box::new(tel_task));
@@ -1009,7 +1265,7 @@ ServiceBuilder<
 		// RPC
 		let (system_rpc_tx, system_rpc_rx) = futures03::channel::mpsc::unbounded();
 		let gen_handler = || {
-			use rpc::{chain, state, author, system};
+			use rpc::{chain, state, author, system, dht};
 
 			let system_info = rpc::system::SystemInfo {
 				chain_name: config.chain_spec.name().into(),
@@ -1053,32 +1309,33 @@ ServiceBuilder<
 				subscriptions,
 				keystore.clone(),
 			);
-			let system = system::System::new(system_info, system_rpc_tx.clone());
+			let system = system::System::new(
+				system_info,
+				system_rpc_tx.clone(),
+				config.deny_unsafe_rpc(),
+			);
+			let dht = dht::Dht::new(dht_client.clone(), config.deny_unsafe_rpc());
 
 			rpc_servers::rpc_handler((
 				state::StateApi::to_delegate(state),
 				chain::ChainApi::to_delegate(chain),
 				author::AuthorApi::to_delegate(author),
 				system::SystemApi::to_delegate(system),
+				dht::DhtApi::to_delegate(dht),
 				rpc_extensions.clone(),
 			))
 		};
 		let rpc_handlers = gen_handler();
 		let rpc = start_rpc_servers(&config, gen_handler)?;
 
+		// Prometheus exporter
+		if let Some(PrometheusConfig { port, registry }) = config.prometheus_config.clone() {
+			let future = prometheus::init_prometheus(port, registry)?;
+			let _ = to_spawn_tx.unbounded_send(Box::new(future
+				.select(exit.clone())
+				.then(|_| Ok(()))));
+		}
 
-		let _ = to_spawn_tx.unbounded_send(Box::new(build_network_future(
-			config.roles,
-			network_mut,
-			client.clone(),
-			network_status_sinks.clone(),
-			system_rpc_rx,
-			has_bootnodes,
-			dht_event_tx,
-		)
-			.map_err(|_| ())
-			.select(exit.clone())
-			.then(|_| Ok(()))));
 
 		let telemetry_connection_sinks: Arc<Mutex<Vec<mpsc::UnboundedSender<()>>>> = Default::default();
 
@@ -1123,6 +1380,60 @@ ServiceBuilder<
 			telemetry
 		});
 
+		// Configuration reloading
+		let config_reloader = config.reload_config.clone().map(|path| {
+			let offchain_enabled = offchain_workers.as_ref().map(|_| offchain_enabled.clone());
+			let is_authority = config.roles.is_authority();
+			Arc::new(ConfigReloader::new(path)
+				.with_subsystem("log", reload::log_filter(config.log_filter_reload.clone()))
+				.with_subsystem("network", reload::reserved_nodes(
+					network.clone(),
+					&config.network.reserved_nodes,
+					&config.network.sentry_nodes,
+				))
+				.with_subsystem("txpool", reload::transaction_pool(transaction_pool.clone()))
+				.with_subsystem("offchain", reload::offchain_worker(offchain_enabled, is_authority))
+				.with_subsystem("telemetry", reload::telemetry(telemetry.clone())))
+		});
+
+		// The `SIGHUP` handler is installed once the service runs, so that it replaces the one
+		// installed by `ctrlc` for terminating the node.
+		#[cfg(unix)]
+		{
+			if let Some(reloader) = config_reloader.clone() {
+				let task = futures::future::lazy(reload::sighup::install)
+					.map_err(|e| warn!("Configuration won't be reloaded on SIGHUP: {}", e))
+					.and_then(move |()| {
+						tokio_timer::Interval::new_interval(std::time::Duration::from_secs(1))
+							.map_err(|e| warn!("SIGHUP watcher stopped: {:?}", e))
+							.for_each(move |_| {
+								if reload::sighup::take() {
+									reloader.reload();
+								}
+								Ok(())
+							})
+					})
+					.select(exit.clone())
+					.then(|_| Ok(()));
+				let _ = to_spawn_tx.unbounded_send(Box::new(task));
+			}
+		}
+
+		let _ = to_spawn_tx.unbounded_send(Box::new(build_network_future(
+			config.roles,
+			network_mut,
+			client.clone(),
+			network_status_sinks.clone(),
+			system_rpc_rx,
+			config_reloader,
+			has_bootnodes,
+			dht_client.clone(),
+			dht_event_tx,
+		)
+			.map_err(|_| ())
+			.select(exit.clone())
+			.then(|_| Ok(()))));
+
 		Ok(Service {
 			client,
 			network,
@@ -1141,6 +1452,7 @@ ServiceBuilder<
 			_offchain_workers: offchain_workers,
 			_telemetry_on_connect_sinks: telemetry_connection_sinks.clone(),
 			keystore,
+			prometheus_registry: config.prometheus_registry().cloned(),
 			marker: PhantomData::<TBl>,
 		})
 	}
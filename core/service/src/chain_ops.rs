@@ -18,6 +18,18 @@
 
 use crate::error;
 use chain_spec::{ChainSpec, RuntimeGenesis, Extension};
+use client::{Client, backend::Backend, CallExecutor, ExecutionStrategy};
+use codec::{Decode, Encode};
+use primitives::{
+	Blake2Hasher, H256, NeverNativeValue, storage::well_known_keys, traits::Externalities, twox_128,
+	crypto::KeyTypeId,
+};
+use session::SessionKeysOwnership;
+use sr_primitives::{ApplyResult, generic::BlockId};
+use sr_primitives::traits::{Block as BlockT, Header as HeaderT, NumberFor, One, ProvideRuntimeApi, Zero};
+use state_machine::{Backend as StateBackend, Ext, InMemoryChangesTrieStorage, OverlayedChanges};
+use substrate_executor::{RuntimeVersion, WasmExecutionMethod, DEFAULT_HEAP_PAGES};
+use std::collections::{BTreeMap, BTreeSet};
 
 /// Defines the logic for an operation exporting blocks within a range.
 #[macro_export]
@@ -223,6 +235,51 @@ macro_rules! revert_chain {
 }}
 }
 
+#[macro_export]
+/// Revert the best chain by up to `$blocks` blocks, finalized blocks included. The
+/// target is raised to the first block whose state is still available and
+/// `$revert_aux` is called with it to compute the rewound auxiliary data of the
+/// consensus engines, which is written along with the reverted blocks.
+macro_rules! unsafe_revert_chain {
+($client:ident, $backend:ident, $blocks:ident, $revert_aux:ident) => {{
+	let info = $client.info().chain;
+	let mut number = info.best_number.saturating_sub($blocks);
+	let hash = loop {
+		let hash = $client.header(&BlockId::Number(number))?
+			.ok_or_else(|| format!("Missing canonical block #{}", number))?
+			.hash();
+		if number >= info.best_number || $backend.have_state_at(&hash, number) {
+			break hash;
+		}
+		number += One::one();
+	};
+
+	if number > info.best_number.saturating_sub($blocks) {
+		warn!("State below block #{} has been pruned, reverting to it instead.", number);
+	}
+	if number < info.finalized_number {
+		warn!(
+			"Reverting finalized blocks #{}..#{}. This must be done on every node of the network \
+			at the same time or it will fork off.",
+			number + One::one(), info.finalized_number,
+		);
+	}
+
+	if number == info.best_number {
+		info!("There aren't any blocks to revert.");
+		return Ok(());
+	}
+
+	let aux = $revert_aux(hash, number)?;
+	let reverted = info.best_number - number;
+	$client.unsafe_revert(reverted, aux)?;
+	let info = $client.info().chain;
+
+	info!("Reverted {} blocks. Best: #{} ({})", reverted, info.best_number, info.best_hash);
+	Ok(())
+}}
+}
+
 /// Build a chain spec json
 pub fn build_spec<G, E>(spec: ChainSpec<G, E>, raw: bool) -> error::Result<String> where
 	G: RuntimeGenesis,
@@ -230,3 +287,654 @@ pub fn build_spec<G, E>(spec: ChainSpec<G, E>, raw: bool) -> error::Result<Strin
 {
 	Ok(spec.to_json(raw)?)
 }
+
+/// Outcome of a block executed under both runtimes during a runtime upgrade dry run.
+#[derive(Debug)]
+pub struct DryRunBlock<N> {
+	/// Number of the block.
+	pub number: N,
+	/// Extrinsics that succeeded under the current runtime but failed under the upgraded one,
+	/// as `(index, error)`.
+	pub failed: Vec<(usize, String)>,
+	/// Extrinsics with a different outcome under the upgraded runtime, as
+	/// `(index, current outcome, upgraded outcome)`.
+	pub changed: Vec<(usize, String, String)>,
+	/// Weight of the block's extrinsics under the current and the upgraded runtime. Only known
+	/// for runtimes keeping it in `System::AllExtrinsicsWeight`.
+	pub weight: (Option<u32>, Option<u32>),
+}
+
+/// Report of a runtime upgrade dry run, see [`dry_run_upgrade`].
+#[derive(Debug)]
+pub struct UpgradeDryRunReport<N> {
+	/// Version of the current runtime.
+	pub current_version: Option<RuntimeVersion>,
+	/// Version of the upgraded runtime.
+	pub upgraded_version: Option<RuntimeVersion>,
+	/// Blocks executed under both runtimes.
+	pub blocks: Vec<DryRunBlock<N>>,
+	/// Block the current runtime failed to execute, with the error.
+	pub current_error: Option<(N, String)>,
+	/// Block the upgraded runtime failed to execute, with the error.
+	pub upgraded_error: Option<(N, String)>,
+	/// Storage entries left with a different value by the upgraded runtime once the blocks have
+	/// been executed, child tries included, as `(key, current value, upgraded value)`. The code
+	/// itself is left out.
+	pub storage_diff: Vec<StorageDivergence>,
+}
+
+/// What a runtime did while executing a sequence of blocks.
+struct BlocksExecution<N> {
+	version: Option<RuntimeVersion>,
+	/// Encoded apply results and extrinsics weight of every executed block.
+	blocks: Vec<(Vec<Vec<u8>>, Option<u32>)>,
+	error: Option<(N, String)>,
+	storage: StorageChanges,
+}
+
+/// Execute `blocks` on top of `state` with the given runtime `code`, bypassing the import checks
+/// (e.g. of the state root) so that diverging runtimes can be compared.
+fn execute_blocks<Block: BlockT, S: StateBackend<Blake2Hasher>>(
+	state: &S,
+	code: &[u8],
+	blocks: &[Block],
+	wasm_method: WasmExecutionMethod,
+	default_heap_pages: Option<u64>,
+) -> BlocksExecution<NumberFor<Block>> {
+	let mut overlay = OverlayedChanges::default();
+	let mut execution = BlocksExecution {
+		version: None,
+		blocks: Vec::new(),
+		error: None,
+		storage: StorageChanges::new(),
+	};
+
+	{
+		let mut ext = Ext::new(
+			&mut overlay,
+			state,
+			None::<&InMemoryChangesTrieStorage<Blake2Hasher, u64>>,
+			None,
+		);
+		ext.place_storage(well_known_keys::CODE.to_vec(), Some(code.to_vec()));
+
+		let heap_pages = ext.storage(well_known_keys::HEAP_PAGES)
+			.and_then(|pages| u64::decode(&mut &pages[..]).ok())
+			.or(default_heap_pages)
+			.unwrap_or(DEFAULT_HEAP_PAGES);
+		let call = |ext: &mut _, function: &str, data: &[u8]| {
+			call_in_wasm(ext, function, data, wasm_method, code, heap_pages)
+		};
+
+		execution.version = call(&mut ext, "Core_version", &[])
+			.ok()
+			.and_then(|version| RuntimeVersion::decode(&mut &version[..]).ok());
+
+		// on FRAME-based runtimes the weight is dropped from storage when finalizing the block.
+		let weight_key = twox_128(b"System AllExtrinsicsWeight");
+		for block in blocks {
+			let number = *block.header().number();
+			let result = call(&mut ext, "Core_initialize_block", &block.header().encode())
+				.and_then(|_| block.extrinsics().iter()
+					.map(|xt| call(&mut ext, "BlockBuilder_apply_extrinsic", &xt.encode()))
+					.collect::<Result<Vec<_>, _>>()
+				)
+				.and_then(|results| {
+					let weight = ext.storage(&weight_key)
+						.and_then(|weight| u32::decode(&mut &weight[..]).ok());
+					call(&mut ext, "BlockBuilder_finalize_block", &[]).map(|_| (results, weight))
+				});
+
+			match result {
+				Ok(outcome) => execution.blocks.push(outcome),
+				Err(e) => {
+					execution.error = Some((number, e));
+					break;
+				},
+			}
+		}
+	}
+
+	execution.storage = committed_changes(overlay);
+	execution.storage.remove(&(None, well_known_keys::CODE.to_vec()));
+	execution
+}
+
+fn call_in_wasm<E: Externalities>(
+	ext: &mut E,
+	function: &str,
+	data: &[u8],
+	wasm_method: WasmExecutionMethod,
+	code: &[u8],
+	heap_pages: u64,
+) -> Result<Vec<u8>, String> {
+	substrate_executor::call_in_wasm(function, data, wasm_method, ext, code, heap_pages)
+		.map_err(|e| format!("{} failed: {}", function, e))
+}
+
+fn display_apply_result(result: &[u8]) -> String {
+	match ApplyResult::decode(&mut &result[..]) {
+		Ok(result) => format!("{:?}", result),
+		Err(_) => format!("0x{}", primitives::hexdisplay::HexDisplay::from(&result)),
+	}
+}
+
+/// Check how the runtime `code` would behave if it were enacted at block `at`, by executing the
+/// next `blocks` blocks of the best chain on top of the state of `at` under both the current
+/// runtime and `code`. Each block goes through `on_initialize`, its extrinsics and
+/// `on_finalize`, and the outcomes, weights and resulting storage of both runs are compared.
+///
+/// Nothing is written to the database.
+pub fn dry_run_upgrade<B, E, Block, RA>(
+	client: &Client<B, E, Block, RA>,
+	code: &[u8],
+	at: Block::Hash,
+	blocks: u32,
+	wasm_method: WasmExecutionMethod,
+	default_heap_pages: Option<u64>,
+) -> error::Result<UpgradeDryRunReport<NumberFor<Block>>> where
+	B: Backend<Block, Blake2Hasher>,
+	E: CallExecutor<Block, Blake2Hasher>,
+	Block: BlockT<Hash=H256>,
+{
+	let id = BlockId::Hash(at);
+	let number = *client.header(&id)?
+		.ok_or_else(|| format!("Unknown block {}", at))?
+		.number();
+	if client.block_hash(number)? != Some(at) {
+		return Err(format!("Block {} is not on the best chain", at).into());
+	}
+
+	let mut replayed = Vec::new();
+	let mut next = number + One::one();
+	while replayed.len() < blocks as usize {
+		match client.block(&BlockId::Number(next))? {
+			Some(block) => replayed.push(block.block),
+			None if client.header(&BlockId::Number(next))?.is_some() =>
+				return Err(format!("The body of block #{} is not available", next).into()),
+			None => break,
+		}
+		next += One::one();
+	}
+	if replayed.is_empty() {
+		return Err(format!("There are no blocks after #{} to replay", number).into());
+	}
+
+	let state = client.state_at(&id)?;
+	let current_code = client.code_at(&id)?;
+	let current = execute_blocks(&state, &current_code, &replayed, wasm_method, default_heap_pages);
+	let upgraded = execute_blocks(&state, code, &replayed, wasm_method, default_heap_pages);
+
+	let blocks = replayed.iter()
+		.zip(current.blocks.iter().zip(upgraded.blocks.iter()))
+		.map(|(block, ((current_results, current_weight), (upgraded_results, upgraded_weight)))| {
+			let mut outcome = DryRunBlock {
+				number: *block.header().number(),
+				failed: Vec::new(),
+				changed: Vec::new(),
+				weight: (*current_weight, *upgraded_weight),
+			};
+			let succeeded = |result: &[u8]| ApplyResult::decode(&mut &result[..])
+				.map_or(false, |result| result == Ok(Ok(())));
+			for (index, (current, upgraded)) in current_results.iter().zip(upgraded_results).enumerate() {
+				if succeeded(current) && !succeeded(upgraded) {
+					outcome.failed.push((index, display_apply_result(upgraded)));
+				}
+				if current != upgraded {
+					outcome.changed.push(
+						(index, display_apply_result(current), display_apply_result(upgraded))
+					);
+				}
+			}
+			outcome
+		})
+		.collect();
+
+	let keys = current.storage.keys().chain(upgraded.storage.keys()).collect::<BTreeSet<_>>();
+	let mut storage_diff = Vec::new();
+	for key in keys {
+		let value = |changes: &StorageChanges| -> error::Result<Option<Vec<u8>>> {
+			match changes.get(key) {
+				Some(value) => Ok(value.clone()),
+				None => read_entry(&state, key),
+			}
+		};
+		let (current_value, upgraded_value) = (value(&current.storage)?, value(&upgraded.storage)?);
+		if current_value != upgraded_value {
+			storage_diff.push((key.clone(), current_value, upgraded_value));
+		}
+	}
+
+	Ok(UpgradeDryRunReport {
+		current_version: current.version,
+		upgraded_version: upgraded.version,
+		blocks,
+		current_error: current.error,
+		upgraded_error: upgraded.error,
+		storage_diff,
+	})
+}
+
+/// Runtime used to replay blocks, see [`replay_blocks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayExecution {
+	/// Replay with the native runtime only.
+	Native,
+	/// Replay with the Wasm runtime stored on chain only.
+	Wasm,
+	/// Replay with both runtimes and compare their results.
+	Both,
+}
+
+/// Key of a storage entry, as `(child storage key, key)`. The child storage key is `None` for
+/// the entries of the top trie.
+pub type StorageEntryKey = (Option<Vec<u8>>, Vec<u8>);
+
+/// A storage entry with different values, as `(key, expected value, actual value)`.
+pub type StorageDivergence = (StorageEntryKey, Option<Vec<u8>>, Option<Vec<u8>>);
+
+/// Storage entries written by a block, child tries included.
+type StorageChanges = BTreeMap<StorageEntryKey, Option<Vec<u8>>>;
+
+/// Outcome of replaying a block with a single runtime.
+#[derive(Debug)]
+pub struct ReplayOutcome {
+	/// Whether the native runtime was used rather than the Wasm one.
+	pub native: bool,
+	/// Error the execution failed with.
+	pub error: Option<String>,
+	/// Storage root computed by the execution when it doesn't match the one of the header.
+	pub root_mismatch: Option<H256>,
+	/// First storage entry written by the execution with a different value than in the state of
+	/// the block in the database, as `(key, value in the database, written value)`.
+	pub divergence: Option<StorageDivergence>,
+}
+
+/// A replayed block that didn't yield the expected results.
+#[derive(Debug)]
+pub struct ReplayMismatch<N> {
+	/// Number of the block.
+	pub number: N,
+	/// Hash of the block.
+	pub hash: H256,
+	/// Storage root of the header.
+	pub state_root: H256,
+	/// Outcome of each runtime the block was replayed with.
+	pub outcomes: Vec<ReplayOutcome>,
+	/// First storage entry written differently by the runtimes when replaying with both, as
+	/// `(key, native value, Wasm value)`.
+	pub native_wasm_divergence: Option<StorageDivergence>,
+}
+
+/// Report of a blocks replay, see [`replay_blocks`].
+#[derive(Debug)]
+pub struct ReplayReport<N> {
+	/// Number of replayed blocks.
+	pub replayed: u32,
+	/// Replayed blocks that didn't yield the expected results.
+	pub mismatches: Vec<ReplayMismatch<N>>,
+}
+
+/// Execute `block` on top of `state`, the state of its parent, and return the written storage entries and
+/// the resulting storage root. The runtime functions are called one by one rather than through
+/// `Core_execute_block`, so that no check of the runtime aborts the execution.
+fn replay_block<B, E, Block, RA, S>(
+	client: &Client<B, E, Block, RA>,
+	state: &S,
+	block: &Block,
+	strategy: ExecutionStrategy,
+) -> error::Result<(StorageChanges, H256)> where
+	B: Backend<Block, Blake2Hasher>,
+	E: CallExecutor<Block, Blake2Hasher>,
+	Block: BlockT<Hash=H256>,
+	S: StateBackend<Blake2Hasher>,
+{
+	let mut overlay = OverlayedChanges::default();
+
+	let mut call = |method: &str, data: &[u8]| client.executor()
+		.call_at_state::<_, _, NeverNativeValue, fn() -> _>(
+			state,
+			&mut overlay,
+			method,
+			data,
+			strategy.get_manager(),
+			None,
+			None,
+		)
+		.map(|(_, (_, root), _)| root);
+
+	call("Core_initialize_block", &block.header().encode())?;
+	for xt in block.extrinsics() {
+		call("BlockBuilder_apply_extrinsic", &xt.encode())?;
+	}
+	let root = call("BlockBuilder_finalize_block", &[])?;
+
+	Ok((committed_changes(overlay), root))
+}
+
+/// Commit the prospective changes of `overlay` and return all its changes, child tries included.
+fn committed_changes(mut overlay: OverlayedChanges) -> StorageChanges {
+	overlay.commit_prospective();
+	let (top, children) = overlay.into_committed();
+	top.map(|(key, value)| ((None, key), value))
+		.chain(children.flat_map(|(storage_key, changes)| changes
+			.map(move |(key, value)| ((Some(storage_key.clone()), key), value))
+		))
+		.collect()
+}
+
+fn read_entry<S: StateBackend<Blake2Hasher>>(
+	state: &S,
+	(child, key): &StorageEntryKey,
+) -> error::Result<Option<Vec<u8>>> {
+	match child {
+		Some(storage_key) => state.child_storage(storage_key, key),
+		None => state.storage(key),
+	}.map_err(|e| format!("Failed to read state: {}", e).into())
+}
+
+/// Return the first entry written by a replayed block whose value differs from its value in the
+/// state `state` of the block, child tries included. Only the written entries are read, so entries
+/// that the block changed and the replay didn't write aren't found.
+fn first_divergence_from_state<S: StateBackend<Blake2Hasher>>(
+	state: &S,
+	changes: &StorageChanges,
+) -> error::Result<Option<StorageDivergence>> {
+	for (key, value) in changes {
+		let expected = read_entry(state, key)?;
+		if expected != *value {
+			return Ok(Some((key.clone(), expected, value.clone())));
+		}
+	}
+	Ok(None)
+}
+
+/// Return the first entry whose value differs between `expected` and `changes`, both changes
+/// made on top of `state`.
+fn first_divergence<S: StateBackend<Blake2Hasher>>(
+	state: &S,
+	expected: &StorageChanges,
+	changes: &StorageChanges,
+) -> error::Result<Option<StorageDivergence>> {
+	for key in changes.keys().chain(expected.keys()).collect::<BTreeSet<_>>() {
+		let value = |changes: &StorageChanges| -> error::Result<_> {
+			match changes.get(key) {
+				Some(value) => Ok(value.clone()),
+				None => read_entry(state, key),
+			}
+		};
+		let (expected_value, value) = (value(expected)?, value(changes)?);
+		if expected_value != value {
+			return Ok(Some((key.clone(), expected_value, value)));
+		}
+	}
+	Ok(None)
+}
+
+/// Re-execute the blocks `from..=to` of the best chain with the native runtime, the Wasm runtime or
+/// both, and compare the results with the storage roots of the headers, with the states of the
+/// blocks in the database and, when replaying with both, with each other.
+///
+/// The state of the parent of every replayed block is needed, i.e. this requires an archive node.
+/// The entries written by a replay are only looked up in the state of the block when its storage
+/// root doesn't match. Nothing is written to the database.
+pub fn replay_blocks<B, E, Block, RA>(
+	client: &Client<B, E, Block, RA>,
+	from: NumberFor<Block>,
+	to: NumberFor<Block>,
+	execution: ReplayExecution,
+) -> error::Result<ReplayReport<NumberFor<Block>>> where
+	B: Backend<Block, Blake2Hasher>,
+	E: CallExecutor<Block, Blake2Hasher>,
+	Block: BlockT<Hash=H256>,
+{
+	if from.is_zero() || to < from {
+		return Err("Invalid block range specified".into());
+	}
+
+	let strategies = match execution {
+		ReplayExecution::Native => vec![ExecutionStrategy::NativeWhenPossible],
+		ReplayExecution::Wasm => vec![ExecutionStrategy::AlwaysWasm],
+		ReplayExecution::Both => vec![ExecutionStrategy::NativeWhenPossible, ExecutionStrategy::AlwaysWasm],
+	};
+
+	let mut report = ReplayReport { replayed: 0, mismatches: Vec::new() };
+	let mut number = from;
+	while number <= to {
+		let block = client.block(&BlockId::Number(number))?
+			.ok_or_else(|| format!("Block #{} is not available", number))?
+			.block;
+		let (hash, header) = (block.header().hash(), block.header().clone());
+		let parent = BlockId::Hash(*header.parent_hash());
+		let state = client.state_at(&parent).map_err(|_| format!(
+			"State of block #{} is not available, replaying requires an archive node",
+			number - One::one(),
+		))?;
+
+		let mut mismatch = ReplayMismatch {
+			number,
+			hash,
+			state_root: *header.state_root(),
+			outcomes: Vec::new(),
+			native_wasm_divergence: None,
+		};
+		let mut results = Vec::new();
+		let mut block_state = None;
+		for strategy in &strategies {
+			let native = *strategy != ExecutionStrategy::AlwaysWasm;
+			let mut outcome = ReplayOutcome { native, error: None, root_mismatch: None, divergence: None };
+
+			// the native runtime is silently replaced by the Wasm one when it isn't compatible.
+			let onchain_version = client.runtime_version_at(&parent)?;
+			let native_version = client.executor().native_runtime_version();
+			if native && !native_version.map_or(false, |v| v.runtime_version.can_call_with(&onchain_version)) {
+				outcome.error = Some(format!(
+					"Native runtime {:?} can't execute the on-chain runtime {}-{}",
+					native_version.map(|v| &v.runtime_version),
+					onchain_version.spec_name,
+					onchain_version.spec_version,
+				));
+				mismatch.outcomes.push(outcome);
+				continue;
+			}
+
+			match replay_block(client, &state, &block, *strategy) {
+				Ok((changes, root)) => {
+					if root != mismatch.state_root {
+						outcome.root_mismatch = Some(root);
+						if block_state.is_none() {
+							block_state = Some(client.state_at(&BlockId::Hash(hash))?);
+						}
+						if let Some(block_state) = &block_state {
+							outcome.divergence = first_divergence_from_state(block_state, &changes)?;
+						}
+					}
+					results.push(changes);
+				},
+				Err(e) => outcome.error = Some(format!("{}", e)),
+			}
+			mismatch.outcomes.push(outcome);
+		}
+
+		if let [native, wasm] = &results[..] {
+			mismatch.native_wasm_divergence = first_divergence(&state, native, wasm)?;
+		}
+
+		let failed = mismatch.native_wasm_divergence.is_some() || mismatch.outcomes.iter()
+			.any(|outcome| outcome.error.is_some() || outcome.root_mismatch.is_some());
+		if failed {
+			report.mismatches.push(mismatch);
+		}
+
+		report.replayed += 1;
+		number += One::one();
+	}
+
+	Ok(report)
+}
+
+/// On-chain status of a session key, as returned by `session_keys_status`.
+#[derive(Debug)]
+pub struct SessionKeyStatus {
+	/// Key type of the key.
+	pub key_type: KeyTypeId,
+	/// Raw public key.
+	pub public: Vec<u8>,
+	/// Encoded id of the validator the key is registered for, if any.
+	pub owner: Option<Vec<u8>>,
+	/// Whether the key is used by its owner in the current session.
+	pub active: bool,
+	/// Whether the key is queued to be used by its owner in the next session.
+	pub queued: bool,
+}
+
+/// Look up the given session keys at the best block, through the `SessionKeysOwnership` runtime API.
+pub fn session_keys_status<B, E, Block, RA>(
+	client: &Client<B, E, Block, RA>,
+	keys: &[(KeyTypeId, Vec<u8>)],
+) -> error::Result<Vec<SessionKeyStatus>> where
+	B: Backend<Block, Blake2Hasher>,
+	E: CallExecutor<Block, Blake2Hasher>,
+	Block: BlockT<Hash=H256>,
+	Client<B, E, Block, RA>: ProvideRuntimeApi,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: SessionKeysOwnership<Block>,
+{
+	let at = BlockId::Hash(client.info().chain.best_hash);
+	if !client.runtime_version_at(&at)?.has_api::<dyn SessionKeysOwnership<Block>>() {
+		return Err(error::Error::Other("The runtime doesn't support looking up session keys".into()));
+	}
+
+	let owners = client.runtime_api().key_owners(&at, keys.to_vec())?;
+	if owners.len() != keys.len() {
+		return Err(error::Error::Other("The runtime didn't look up every session key".into()));
+	}
+
+	Ok(keys.iter().zip(owners).map(|((key_type, public), ownership)| SessionKeyStatus {
+		key_type: *key_type,
+		public: public.clone(),
+		active: ownership.as_ref().map_or(false, |ownership| ownership.active),
+		queued: ownership.as_ref().map_or(false, |ownership| ownership.queued),
+		owner: ownership.map(|ownership| ownership.owner),
+	}).collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::KeyedVec;
+	use primitives::blake2_256;
+	use consensus_common::BlockOrigin;
+	use substrate_test_runtime_client::{prelude::*, runtime::{Transfer, WASM_BINARY}};
+
+	// the test runtime with every occurrence of `from` replaced with `to`, of the same length.
+	fn patched_runtime(from: &[u8], to: &[u8]) -> Vec<u8> {
+		assert_eq!(from.len(), to.len());
+		let mut code = WASM_BINARY.to_vec();
+		let mut patched = 0;
+		for start in 0..code.len() - from.len() {
+			if &code[start..start + from.len()] == from {
+				code[start..start + from.len()].copy_from_slice(to);
+				patched += 1;
+			}
+		}
+		assert!(patched > 0, "{:?} isn't in the test runtime", String::from_utf8_lossy(from));
+		code
+	}
+
+	fn alice_key(prefix: &[u8]) -> Vec<u8> {
+		blake2_256(&AccountKeyring::Alice.to_raw_public().to_keyed_vec(prefix)).to_vec()
+	}
+
+	#[test]
+	fn dry_run_upgrade_reports_regressions_and_storage_changes() {
+		let client = substrate_test_runtime_client::new();
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_transfer(Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Bob.into(),
+			amount: 42,
+			nonce: 0,
+		}).unwrap();
+		let block = builder.bake().unwrap();
+		client.import(BlockOrigin::Own, block).unwrap();
+		let genesis = client.block_hash(0).unwrap().unwrap();
+		let dry_run = |code: &[u8]| {
+			dry_run_upgrade(&client, code, genesis, 10, WasmExecutionMethod::Interpreted, None).unwrap()
+		};
+
+		// the same runtime behaves the same.
+		let report = dry_run(WASM_BINARY);
+		assert!(report.current_error.is_none() && report.upgraded_error.is_none());
+		assert_eq!(report.blocks.len(), 1);
+		assert!(report.blocks[0].failed.is_empty());
+		assert!(report.blocks[0].changed.is_empty());
+		assert!(report.storage_diff.is_empty());
+
+		// an upgrade keeping the nonces under other keys.
+		let report = dry_run(&patched_runtime(b"nonce:", b"nonxe:"));
+		assert!(report.blocks[0].failed.is_empty());
+		assert!(report.blocks[0].changed.is_empty());
+		let mut expected = vec![
+			((None, alice_key(b"nonce:")), Some(1u64.encode()), None),
+			((None, alice_key(b"nonxe:")), None, Some(1u64.encode())),
+		];
+		expected.sort();
+		assert_eq!(report.storage_diff, expected);
+
+		// an upgrade keeping the balances under other keys, where Alice can't pay anymore.
+		let report = dry_run(&patched_runtime(b"balance:", b"balanxe:"));
+		assert_eq!(report.blocks[0].failed.len(), 1);
+		assert_eq!(report.blocks[0].failed[0].0, 0);
+		assert_eq!(report.blocks[0].changed.len(), 1);
+		assert!(!report.storage_diff.is_empty());
+	}
+
+	#[test]
+	fn replay_compares_with_the_state_of_the_block() {
+		let client = substrate_test_runtime_client::new();
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_transfer(Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Bob.into(),
+			amount: 42,
+			nonce: 0,
+		}).unwrap();
+		builder.push_storage_change(b"replayed".to_vec(), Some(b"value".to_vec())).unwrap();
+		builder.push_storage_change(well_known_keys::HEAP_PAGES.to_vec(), None).unwrap();
+		let block = builder.bake().unwrap();
+		let hash = block.header().hash();
+		client.import(BlockOrigin::Own, block.clone()).unwrap();
+
+		let report = replay_blocks(&client, 1, 1, ReplayExecution::Wasm).unwrap();
+		assert_eq!(report.replayed, 1);
+		assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+
+		let parent = client.state_at(&BlockId::Number(0)).unwrap();
+		let state = client.state_at(&BlockId::Hash(hash)).unwrap();
+		let top = |key: &[u8]| (None, key.to_vec());
+
+		let (changes, root) = replay_block(&client, &parent, &block, ExecutionStrategy::AlwaysWasm).unwrap();
+		assert_eq!(root, *block.header().state_root());
+		assert_eq!(changes.get(&top(b"replayed")), Some(&Some(b"value".to_vec())));
+		assert_eq!(changes.get(&top(well_known_keys::HEAP_PAGES)), Some(&None));
+		assert_eq!(changes.get(&top(&alice_key(b"nonce:"))), Some(&Some(1u64.encode())));
+		assert_eq!(first_divergence_from_state(&state, &changes).unwrap(), None);
+
+		// an entry written differently by the replay.
+		let mut different = changes.clone();
+		different.insert(top(b"replayed"), Some(b"other".to_vec()));
+		assert_eq!(
+			first_divergence_from_state(&state, &different).unwrap(),
+			Some((top(b"replayed"), Some(b"value".to_vec()), Some(b"other".to_vec()))),
+		);
+
+		// an entry of a child trie written by the replay only.
+		let mut extra = changes;
+		let child = (Some(b":child_storage:default:replay".to_vec()), b"key".to_vec());
+		extra.insert(child.clone(), Some(b"value".to_vec()));
+		assert_eq!(
+			first_divergence_from_state(&state, &extra).unwrap(),
+			Some((child, None, Some(b"value".to_vec()))),
+		);
+	}
+}
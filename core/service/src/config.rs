@@ -17,9 +17,13 @@
 //! Service configuration.
 
 pub use client::ExecutionStrategies;
-pub use client_db::{kvdb::KeyValueDB, PruningMode};
+pub use client_db::{
+	kvdb::KeyValueDB, PruningMode, DatabaseBackend, ColumnConfig, ColumnsConfig, Compression,
+	KeepBlocks, KeepJustifications, RetentionPolicy,
+};
 pub use network::config::{ExtTransport, NetworkConfiguration, Roles};
 pub use substrate_executor::WasmExecutionMethod;
+pub use prometheus::Registry as PrometheusRegistry;
 
 use std::{path::PathBuf, net::SocketAddr, sync::Arc};
 use transaction_pool;
@@ -28,6 +32,9 @@ use primitives::crypto::Protected;
 use target_info::Target;
 use tel::TelemetryEndpoints;
 
+/// Applies a log filter, given in the same format as `RUST_LOG`.
+pub type LogFilterReload = Arc<dyn Fn(&str) -> Result<(), String> + Send + Sync>;
+
 /// Service configuration.
 #[derive(Clone)]
 pub struct Configuration<C, G, E = NoExtension> {
@@ -55,6 +62,8 @@ pub struct Configuration<C, G, E = NoExtension> {
 	pub state_cache_child_ratio: Option<usize>,
 	/// Pruning settings.
 	pub pruning: PruningMode,
+	/// Retention of the block bodies, justifications and changes tries.
+	pub retention: RetentionPolicy,
 	/// Chain configuration.
 	pub chain_spec: ChainSpec<G, E>,
 	/// Custom configuration.
@@ -73,15 +82,24 @@ pub struct Configuration<C, G, E = NoExtension> {
 	pub rpc_ws_max_connections: Option<usize>,
 	/// CORS settings for HTTP & WS servers. `None` if all origins are allowed.
 	pub rpc_cors: Option<Vec<String>>,
+	/// Which RPC methods are exposed.
+	pub rpc_methods: RpcMethods,
 	/// Telemetry service URL. `None` if disabled.
 	pub telemetry_endpoints: Option<TelemetryEndpoints>,
 	/// External WASM transport for the telemetry. If `Some`, when connection to a telemetry
 	/// endpoint, this transport will be tried in priority before all others.
 	pub telemetry_external_transport: Option<ExtTransport>,
+	/// Prometheus exporter configuration. `None` if disabled.
+	pub prometheus_config: Option<PrometheusConfig>,
 	/// The default number of 64KB pages to allocate for Wasm execution
 	pub default_heap_pages: Option<u64>,
 	/// Should offchain workers be executed.
 	pub offchain_worker: bool,
+	/// Configuration file the reloadable options are read again from on `SIGHUP` or when
+	/// `system_reloadConfig` is called. `None` if reloading is disabled.
+	pub reload_config: Option<PathBuf>,
+	/// Applies a new log filter when the configuration is reloaded.
+	pub log_filter_reload: Option<LogFilterReload>,
 	/// Sentry mode is enabled, the node's role is AUTHORITY but it should not
 	/// actively participate in consensus (i.e. no keystores should be passed to
 	/// consensus modules).
@@ -90,6 +108,8 @@ pub struct Configuration<C, G, E = NoExtension> {
 	pub force_authoring: bool,
 	/// Disable GRANDPA when running in validator mode
 	pub disable_grandpa: bool,
+	/// Names of the voting rules restricting GRANDPA votes, the default rules if empty.
+	pub grandpa_voting_rules: Vec<String>,
 	/// Node keystore's password
 	pub keystore_password: Option<Protected<String>>,
 	/// Development key seed.
@@ -100,6 +120,43 @@ pub struct Configuration<C, G, E = NoExtension> {
 	pub dev_key_seed: Option<String>,
 }
 
+/// Which RPC methods are exposed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RpcMethods {
+	/// Expose every method if the RPC servers only listen on local interfaces, only the safe
+	/// ones otherwise.
+	Auto,
+	/// Only expose the methods that are safe to expose publicly.
+	Safe,
+	/// Expose every method.
+	Unsafe,
+}
+
+impl Default for RpcMethods {
+	fn default() -> Self {
+		RpcMethods::Auto
+	}
+}
+
+/// Configuration of the Prometheus exporter.
+#[derive(Clone)]
+pub struct PrometheusConfig {
+	/// Address the metrics are served on.
+	pub port: SocketAddr,
+	/// Registry the subsystems register their metrics on.
+	pub registry: PrometheusRegistry,
+}
+
+impl PrometheusConfig {
+	/// Create a new config serving the metrics on `port`, with a fresh registry.
+	pub fn new_with_default_registry(port: SocketAddr) -> Self {
+		PrometheusConfig {
+			port,
+			registry: prometheus::new_registry(),
+		}
+	}
+}
+
 /// Configuration of the database of the client.
 #[derive(Clone)]
 pub enum DatabaseConfig {
@@ -109,6 +166,10 @@ pub enum DatabaseConfig {
 		path: PathBuf,
 		/// Cache Size for internal database in MiB
 		cache_size: Option<u32>,
+		/// Key-value store to use.
+		backend: DatabaseBackend,
+		/// Settings of the database columns, by name.
+		columns: ColumnsConfig,
 	},
 
 	/// A custom implementation of an already-open database.
@@ -136,24 +197,32 @@ impl<C, G, E> Configuration<C, G, E> where
 			database: DatabaseConfig::Path {
 				path: Default::default(),
 				cache_size: Default::default(),
+				backend: Default::default(),
+				columns: Default::default(),
 			},
 			state_cache_size: Default::default(),
 			state_cache_child_ratio: Default::default(),
 			custom: Default::default(),
 			pruning: PruningMode::default(),
+			retention: Default::default(),
 			wasm_method: WasmExecutionMethod::Interpreted,
 			execution_strategies: Default::default(),
 			rpc_http: None,
 			rpc_ws: None,
 			rpc_ws_max_connections: None,
 			rpc_cors: Some(vec![]),
+			rpc_methods: Default::default(),
 			telemetry_endpoints: None,
 			telemetry_external_transport: None,
+			prometheus_config: None,
 			default_heap_pages: None,
 			offchain_worker: Default::default(),
+			reload_config: None,
+			log_filter_reload: None,
 			sentry_mode: false,
 			force_authoring: false,
 			disable_grandpa: false,
+			grandpa_voting_rules: Vec::new(),
 			keystore_password: None,
 			dev_key_seed: None,
 		};
@@ -177,6 +246,23 @@ impl<C, G, E> Configuration<C, G, E> {
 		format!("{}/v{}", self.impl_name, self.full_version())
 	}
 
+	/// Whether the RPC methods that are unsafe to expose publicly must be denied.
+	pub fn deny_unsafe_rpc(&self) -> rpc::DenyUnsafe {
+		let is_local = |addr: &Option<SocketAddr>| addr.map_or(true, |addr| addr.ip().is_loopback());
+		match self.rpc_methods {
+			RpcMethods::Unsafe => rpc::DenyUnsafe::No,
+			RpcMethods::Safe => rpc::DenyUnsafe::Yes,
+			RpcMethods::Auto if is_local(&self.rpc_http) && is_local(&self.rpc_ws) =>
+				rpc::DenyUnsafe::No,
+			RpcMethods::Auto => rpc::DenyUnsafe::Yes,
+		}
+	}
+
+	/// Registry to register Prometheus metrics on, if the exporter is enabled.
+	pub fn prometheus_registry(&self) -> Option<&PrometheusRegistry> {
+		self.prometheus_config.as_ref().map(|config| &config.registry)
+	}
+
 	/// Generate a PathBuf to sub in the chain configuration directory
 	/// if given
 	pub fn in_chain_config_dir(&self, sub: &str) -> Option<PathBuf> {
@@ -37,6 +37,8 @@ pub enum Error {
 	Network(network::error::Error),
 	/// Keystore error.
 	Keystore(keystore::Error),
+	/// Prometheus exporter error.
+	Prometheus(prometheus::Error),
 	/// Best chain selection strategy is missing.
 	#[display(fmt="Best chain selection strategy (SelectChain) is not provided.")]
 	SelectChainRequired,
@@ -58,6 +60,7 @@ impl std::error::Error for Error {
 			Error::Consensus(ref err) => Some(err),
 			Error::Network(ref err) => Some(err),
 			Error::Keystore(ref err) => Some(err),
+			Error::Prometheus(ref err) => Some(err),
 			_ => None,
 		}
 	}
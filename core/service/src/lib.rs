@@ -25,6 +25,8 @@ pub mod chain_ops;
 pub mod error;
 
 mod builder;
+mod metrics;
+mod reload;
 mod status_sinks;
 
 use std::io;
@@ -54,8 +56,16 @@ use sr_primitives::generic::BlockId;
 use sr_primitives::traits::NumberFor;
 
 pub use self::error::Error;
-pub use self::builder::{ServiceBuilder, ServiceBuilderExport, ServiceBuilderImport, ServiceBuilderRevert};
-pub use config::{Configuration, Roles, PruningMode};
+pub use self::builder::{
+	ServiceBuilder, ServiceBuilderExport, ServiceBuilderImport, ServiceBuilderRevert, ServiceBuilderCheckDb,
+	ServiceBuilderDryRunUpgrade, ServiceBuilderReplayBlocks, ServiceBuilderSessionKeys,
+};
+pub use self::chain_ops::{
+	DryRunBlock, UpgradeDryRunReport, ReplayExecution, ReplayOutcome, ReplayMismatch, ReplayReport,
+	SessionKeyStatus, session_keys_status,
+};
+pub use self::reload::{ConfigReloader, ReloadableConfig, read_config_file};
+pub use config::{Configuration, Roles, PruningMode, RpcMethods};
 pub use chain_spec::{ChainSpec, Properties, RuntimeGenesis, Extension as ChainSpecExtension};
 pub use transaction_pool::txpool::{
 	self, Pool as TransactionPool, Options as TransactionPoolOptions, ChainApi, IntoPoolError
@@ -102,6 +112,7 @@ pub struct Service<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> {
 	_telemetry_on_connect_sinks: Arc<Mutex<Vec<mpsc::UnboundedSender<()>>>>,
 	_offchain_workers: Option<Arc<TOc>>,
 	keystore: keystore::KeyStorePtr,
+	prometheus_registry: Option<prometheus::Registry>,
 	marker: PhantomData<TBl>,
 }
 
@@ -168,6 +179,9 @@ pub trait AbstractService: 'static + Future<Item = (), Error = Error> +
 	/// Returns the keystore that stores keys.
 	fn keystore(&self) -> keystore::KeyStorePtr;
 
+	/// Returns the registry to register Prometheus metrics on, if the exporter is enabled.
+	fn prometheus_registry(&self) -> Option<prometheus::Registry>;
+
 	/// Starts an RPC query.
 	///
 	/// The query is passed as a string and must be a JSON text similar to what an HTTP client
@@ -233,6 +247,10 @@ where
 		self.keystore.clone()
 	}
 
+	fn prometheus_registry(&self) -> Option<prometheus::Registry> {
+		self.prometheus_registry.clone()
+	}
+
 	fn spawn_task(&self, task: impl Future<Item = (), Error = ()> + Send + 'static) {
 		let task = task.select(self.on_exit()).then(|_| Ok(()));
 		let _ = self.to_spawn_tx.unbounded_send(Box::new(task));
@@ -350,8 +368,10 @@ fn build_network_future<
 	client: Arc<C>,
 	status_sinks: Arc<Mutex<status_sinks::StatusSinks<(NetworkStatus<B>, NetworkState)>>>,
 	rpc_rx: futures03::channel::mpsc::UnboundedReceiver<rpc::system::Request<B>>,
+	config_reloader: Option<Arc<ConfigReloader>>,
 	should_have_peers: bool,
-	dht_event_tx: Option<mpsc::Sender<DhtEvent>>,
+	dht_client: Arc<network::DhtClient>,
+	dht_event_tx: Option<futures03::channel::mpsc::Sender<DhtEvent>>,
 ) -> impl Future<Item = (), Error = ()> {
 	// Compatibility shim while we're transitioning to stable Futures.
 	// See https://github.com/paritytech/substrate/issues/3099
@@ -420,6 +440,17 @@ fn build_network_future<
 
 					let _ = sender.send(node_roles);
 				}
+				rpc::system::Request::ReloadConfig(sender) => {
+					let outcomes = match config_reloader {
+						Some(ref reloader) => reloader.reload(),
+						None => vec![rpc::system::ReloadOutcome {
+							subsystem: "config".into(),
+							applied: false,
+							message: "The node has been started without a configuration file".into(),
+						}],
+					};
+					let _ = sender.send(outcomes);
+				}
 			};
 		}
 
@@ -442,10 +473,10 @@ fn build_network_future<
 		while let Ok(Async::Ready(Some(Event::Dht(event)))) = network.poll().map_err(|err| {
 			warn!(target: "service", "Error in network: {:?}", err);
 		}) {
-			// Given that core/authority-discovery is the only upper stack consumer of Dht events at the moment, all Dht
-			// events are being passed on to the authority-discovery module. In the future there might be multiple
-			// consumers of these events. In that case this would need to be refactored to properly dispatch the events,
-			// e.g. via a subscriber model.
+			// Events answering requests of the DHT client (offchain workers and RPC) are resolved here, the
+			// remaining ones are for core/authority-discovery. Both consumers ignore events for keys they didn't
+			// request.
+			dht_client.on_event(&event);
 			if let Some(Err(e)) = dht_event_tx.as_ref().map(|c| c.clone().try_send(event)) {
 				if e.is_full() {
 					warn!(target: "service", "Dht event channel to authority discovery is full, dropping event.");
@@ -0,0 +1,104 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Prometheus metrics of the service, updated along with the informant.
+
+use client::{ClientInfo, backend::StateCacheStats};
+use network::SyncState;
+use prometheus::{Gauge, IntGauge, IntGaugeVec, Opts, PrometheusError, Registry, register};
+use sr_primitives::traits::{Block as BlockT, SaturatedConversion};
+use transaction_pool::txpool::Status as PoolStatus;
+
+use crate::NetworkStatus;
+
+/// Metrics of the chain, the network and the transaction pool.
+pub(crate) struct ServiceMetrics {
+	block_height: IntGaugeVec,
+	peers: IntGauge,
+	sync_peers: IntGauge,
+	is_major_syncing: IntGauge,
+	transactions: IntGaugeVec,
+	state_cache_size: IntGauge,
+	state_cache_hit_ratio: Gauge,
+	last_state_cache_stats: StateCacheStats,
+}
+
+impl ServiceMetrics {
+	/// Register the metrics on `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(ServiceMetrics {
+			block_height: register(IntGaugeVec::new(
+				Opts::new("block_height", "Height of the best and of the finalized block"),
+				&["status"],
+			)?, registry)?,
+			peers: register(IntGauge::new(
+				"peers_count", "Number of connected peers",
+			)?, registry)?,
+			sync_peers: register(IntGauge::new(
+				"sync_peers_count", "Number of peers participating in syncing",
+			)?, registry)?,
+			is_major_syncing: register(IntGauge::new(
+				"is_major_syncing", "Whether the node is downloading blocks, 1 if it is",
+			)?, registry)?,
+			transactions: register(IntGaugeVec::new(
+				Opts::new("transaction_pool_transactions", "Number of transactions in the pool"),
+				&["status"],
+			)?, registry)?,
+			state_cache_size: register(IntGauge::new(
+				"state_cache_used_bytes", "Memory used by the state cache",
+			)?, registry)?,
+			state_cache_hit_ratio: register(Gauge::new(
+				"state_cache_hit_ratio", "Ratio of state reads served by the state cache since the last update",
+			)?, registry)?,
+			last_state_cache_stats: Default::default(),
+		})
+	}
+
+	/// Update the metrics with the latest status of the node.
+	pub fn update<B: BlockT>(
+		&mut self,
+		info: &ClientInfo<B>,
+		net_status: &NetworkStatus<B>,
+		txpool_status: &PoolStatus,
+	) {
+		self.block_height.with_label_values(&["best"])
+			.set(info.chain.best_number.saturated_into::<u64>() as i64);
+		self.block_height.with_label_values(&["finalized"])
+			.set(info.chain.finalized_number.saturated_into::<u64>() as i64);
+
+		self.peers.set(net_status.num_connected_peers as i64);
+		self.sync_peers.set(i64::from(net_status.num_sync_peers));
+		self.is_major_syncing.set(match net_status.sync_state {
+			SyncState::Idle => 0,
+			SyncState::Downloading => 1,
+		});
+
+		self.transactions.with_label_values(&["ready"]).set(txpool_status.ready as i64);
+		self.transactions.with_label_values(&["future"]).set(txpool_status.future as i64);
+
+		if let Some(size) = info.used_state_cache_size {
+			self.state_cache_size.set(size as i64);
+		}
+		if let Some(stats) = info.state_cache_stats {
+			let hits = stats.hits.saturating_sub(self.last_state_cache_stats.hits);
+			let misses = stats.misses.saturating_sub(self.last_state_cache_stats.misses);
+			if hits + misses > 0 {
+				self.state_cache_hit_ratio.set(hits as f64 / (hits + misses) as f64);
+			}
+			self.last_state_cache_stats = stats;
+		}
+	}
+}
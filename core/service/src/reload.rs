@@ -0,0 +1,431 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reloading part of the configuration of a running node.
+//!
+//! The reloadable options are read again from the configuration file given with `--config`
+//! whenever the node receives `SIGHUP` or `system_reloadConfig` is called. Options missing from
+//! the file are left untouched, the other options of the file are only read on startup, and every
+//! subsystem reports what it applied.
+
+use std::{collections::HashSet, fs, path::{Path, PathBuf}, sync::Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use log::{info, warn};
+use network::{NetworkService, PeerId, config::parse_str_addr, specialization::NetworkSpecialization};
+use parking_lot::Mutex;
+use primitives::H256;
+use rpc::system::ReloadOutcome;
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+use sr_primitives::traits::Block as BlockT;
+use transaction_pool::txpool::{self, ChainApi, Pool as TransactionPool};
+
+use crate::config::LogFilterReload;
+
+/// Reads the options of a configuration file, keyed by the long names of the flags setting them.
+///
+/// The file is in TOML, or in JSON if its extension is `.json`.
+pub fn read_config_file(path: &Path) -> Result<Map<String, Value>, String> {
+	let content = fs::read_to_string(path)
+		.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+	let value: Value = if path.extension().map_or(false, |ext| ext == "json") {
+		serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))?
+	} else {
+		toml::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))?
+	};
+
+	match value {
+		Value::Object(options) => Ok(options),
+		_ => Err(format!("Invalid {}: expected a table of options", path.display())),
+	}
+}
+
+/// Options that can be changed without restarting the node.
+///
+/// The names and formats match the command line flags setting them.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ReloadableConfig {
+	/// Log filter, in the same format as `RUST_LOG`.
+	pub log: Option<String>,
+	/// Reserved nodes, replacing the current ones.
+	#[serde(default, deserialize_with = "one_or_many")]
+	pub reserved_nodes: Option<Vec<String>>,
+	/// Maximum number of transactions in the transaction pool.
+	pub pool_limit: Option<usize>,
+	/// Maximum size of all transactions in the pool, in kilobytes.
+	pub pool_kbytes: Option<usize>,
+	/// When offchain workers should be executed: `Always`, `Never` or `WhenValidating`.
+	pub offchain_worker: Option<String>,
+	/// Telemetry endpoints, as `URL VERBOSITY`, replacing the current ones.
+	#[serde(default, deserialize_with = "one_or_many")]
+	pub telemetry_url: Option<Vec<String>>,
+}
+
+impl ReloadableConfig {
+	/// Reads the reloadable options of a configuration file, ignoring the other ones.
+	pub fn from_file(path: &Path) -> Result<Self, String> {
+		serde_json::from_value(Value::Object(read_config_file(path)?))
+			.map_err(|e| format!("Invalid {}: {}", path.display(), e))
+	}
+}
+
+/// Deserializes a list of values which, as for a flag given once, may be a single value.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+	#[derive(Deserialize)]
+	#[serde(untagged)]
+	enum OneOrMany {
+		One(String),
+		Many(Vec<String>),
+	}
+
+	Ok(Option::<OneOrMany>::deserialize(deserializer)?.map(|values| match values {
+		OneOrMany::One(value) => vec![value],
+		OneOrMany::Many(values) => values,
+	}))
+}
+
+/// Applies the options handled by a subsystem. Returns `None` if none of them is set.
+type Subsystem = Box<dyn Fn(&ReloadableConfig) -> Option<Result<String, String>> + Send + Sync>;
+
+/// Reloads the configuration file and hands it over to the subsystems.
+pub struct ConfigReloader {
+	path: PathBuf,
+	subsystems: Vec<(&'static str, Subsystem)>,
+}
+
+impl ConfigReloader {
+	/// Creates a reloader reading the given file, without any subsystem.
+	pub fn new(path: PathBuf) -> Self {
+		ConfigReloader {
+			path,
+			subsystems: Vec::new(),
+		}
+	}
+
+	/// Registers a subsystem.
+	pub fn with_subsystem(
+		mut self,
+		name: &'static str,
+		apply: impl Fn(&ReloadableConfig) -> Option<Result<String, String>> + Send + Sync + 'static,
+	) -> Self {
+		self.subsystems.push((name, Box::new(apply)));
+		self
+	}
+
+	/// Reads the configuration file and applies it, returning the outcome for every subsystem
+	/// whose options are set in the file.
+	pub fn reload(&self) -> Vec<ReloadOutcome> {
+		info!("Reloading configuration from {}", self.path.display());
+
+		let config = match ReloadableConfig::from_file(&self.path) {
+			Ok(config) => config,
+			Err(err) => {
+				warn!("Configuration not reloaded: {}", err);
+				return vec![ReloadOutcome {
+					subsystem: "config".into(),
+					applied: false,
+					message: err,
+				}];
+			}
+		};
+
+		self.subsystems.iter()
+			.filter_map(|(name, apply)| apply(&config).map(|res| (name, res)))
+			.map(|(name, res)| {
+				let (applied, message) = match res {
+					Ok(message) => {
+						info!("Reloaded {}: {}", name, message);
+						(true, message)
+					},
+					Err(message) => {
+						warn!("Failed to reload {}: {}", name, message);
+						(false, message)
+					},
+				};
+				ReloadOutcome { subsystem: name.to_string(), applied, message }
+			})
+			.collect()
+	}
+}
+
+/// Subsystem applying the log filter.
+pub(crate) fn log_filter(
+	reload: Option<LogFilterReload>,
+) -> impl Fn(&ReloadableConfig) -> Option<Result<String, String>> {
+	move |config| {
+		let filter = config.log.as_ref()?;
+		Some(match reload {
+			Some(ref reload) => reload(filter).map(|()| format!("Log filter set to `{}`", filter)),
+			None => Err("The logger doesn't support changing the filter".into()),
+		})
+	}
+}
+
+/// Subsystem replacing the reserved nodes, starting from the given ones.
+///
+/// A validator behind sentry nodes only accepts its sentry nodes as reserved nodes. The nodes are
+/// all checked before any of them is applied.
+pub(crate) fn reserved_nodes<B, S>(
+	network: Arc<NetworkService<B, S, H256>>,
+	initial: &[String],
+	sentry_nodes: &[String],
+) -> impl Fn(&ReloadableConfig) -> Option<Result<String, String>> where
+	B: BlockT + 'static,
+	S: NetworkSpecialization<B>,
+{
+	let parse_peer_ids = |addrs: &[String]| addrs.iter()
+		.filter_map(|addr| parse_str_addr(addr).ok())
+		.map(|(peer_id, _)| peer_id)
+		.collect::<HashSet<PeerId>>();
+	let current = Mutex::new(parse_peer_ids(initial));
+	let sentries = parse_peer_ids(sentry_nodes);
+
+	move |config| {
+		let nodes = config.reserved_nodes.as_ref()?;
+		let peers = match parse_reserved_nodes(nodes, &sentries) {
+			Ok(peers) => peers,
+			Err(err) => return Some(Err(err)),
+		};
+
+		let mut current = current.lock();
+		let removed = current.difference(&peers).cloned().collect::<Vec<_>>();
+		for peer_id in &removed {
+			network.remove_reserved_peer(peer_id.clone());
+			current.remove(peer_id);
+		}
+		let mut added = 0;
+		for node in nodes {
+			match network.add_reserved_peer(node.clone()) {
+				Ok(()) => if let Ok((peer_id, _)) = parse_str_addr(node) {
+					if current.insert(peer_id) {
+						added += 1;
+					}
+				},
+				Err(err) => return Some(Err(format!(
+					"Invalid reserved node {}: {}, {} reserved nodes added and {} removed before it",
+					node, err, added, removed.len(),
+				))),
+			}
+		}
+
+		Some(Ok(format!("{} reserved nodes added, {} removed", added, removed.len())))
+	}
+}
+
+/// Parses reserved nodes, which must be sentry nodes if `sentries` isn't empty.
+fn parse_reserved_nodes(nodes: &[String], sentries: &HashSet<PeerId>) -> Result<HashSet<PeerId>, String> {
+	let mut peers = HashSet::new();
+	for node in nodes {
+		let (peer_id, _) = parse_str_addr(node)
+			.map_err(|err| format!("Invalid reserved node {}: {:?}", node, err))?;
+		if !sentries.is_empty() && !sentries.contains(&peer_id) {
+			return Err(format!(
+				"Reserved node {} isn't a sentry node, a validator behind sentry nodes only connects \
+				to them", node,
+			));
+		}
+		peers.insert(peer_id);
+	}
+	Ok(peers)
+}
+
+/// Subsystem changing the limits of the transaction pool.
+///
+/// The future queue gets a tenth of the limits, as when they are set on the command line.
+pub(crate) fn transaction_pool<PoolApi: ChainApi>(
+	pool: Arc<TransactionPool<PoolApi>>,
+) -> impl Fn(&ReloadableConfig) -> Option<Result<String, String>> {
+	move |config| {
+		if config.pool_limit.is_none() && config.pool_kbytes.is_none() {
+			return None;
+		}
+
+		let mut options: txpool::Options = pool.options();
+		if let Some(limit) = config.pool_limit {
+			options.ready.count = limit;
+			options.future.count = limit / 10;
+		}
+		if let Some(kbytes) = config.pool_kbytes {
+			options.ready.total_bytes = kbytes * 1024;
+			options.future.total_bytes = kbytes * 1024 / 10;
+		}
+		let message = format!(
+			"limited to {} transactions and {} kB, ",
+			options.ready.count,
+			options.ready.total_bytes / 1024,
+		);
+		let dropped = pool.set_options(options);
+
+		Some(Ok(format!("{}{} transactions dropped", message, dropped)))
+	}
+}
+
+/// Subsystem enabling or disabling the offchain workers.
+///
+/// `enabled` is `None` if the backend doesn't support offchain workers. `WhenValidating` enables
+/// them if `is_authority` is set.
+pub(crate) fn offchain_worker(
+	enabled: Option<Arc<AtomicBool>>,
+	is_authority: bool,
+) -> impl Fn(&ReloadableConfig) -> Option<Result<String, String>> {
+	move |config| {
+		let enable = match config.offchain_worker.as_ref()?.to_lowercase().as_str() {
+			"always" => true,
+			"never" => false,
+			"whenvalidating" => is_authority,
+			other => return Some(Err(format!(
+				"Invalid offchain-worker `{}`, expected Always, Never or WhenValidating", other,
+			))),
+		};
+		Some(match enabled {
+			Some(ref enabled) => {
+				enabled.store(enable, Ordering::Relaxed);
+				Ok(if enable { "enabled" } else { "disabled" }.into())
+			},
+			None => Err("Offchain workers aren't supported by the backend".into()),
+		})
+	}
+}
+
+/// Subsystem replacing the telemetry endpoints.
+pub(crate) fn telemetry(
+	telemetry: Option<tel::Telemetry>,
+) -> impl Fn(&ReloadableConfig) -> Option<Result<String, String>> {
+	move |config| {
+		let urls = config.telemetry_url.as_ref()?;
+		let telemetry = match telemetry {
+			Some(ref telemetry) => telemetry,
+			None => return Some(Err("Telemetry was disabled on startup, a restart is required".into())),
+		};
+
+		let endpoints = match urls.iter().map(|url| parse_telemetry_endpoint(url)).collect() {
+			Ok(endpoints) => endpoints,
+			Err(err) => return Some(Err(err)),
+		};
+		Some(telemetry.set_endpoints(tel::TelemetryEndpoints::new(endpoints))
+			.map(|urls| format!("reporting to [{}]", urls.join(", "))))
+	}
+}
+
+/// Parses a telemetry endpoint given as `URL VERBOSITY`, the verbosity defaulting to 0.
+fn parse_telemetry_endpoint(s: &str) -> Result<(String, u8), String> {
+	match s.find(' ') {
+		None => Ok((s.to_owned(), 0)),
+		Some(pos) => {
+			let verbosity = s[pos + 1..].parse()
+				.map_err(|e| format!("Invalid verbosity of telemetry URL {}: {}", &s[..pos], e))?;
+			Ok((s[..pos].to_owned(), verbosity))
+		}
+	}
+}
+
+/// Watching for `SIGHUP`.
+#[cfg(unix)]
+pub(crate) mod sighup {
+	use std::sync::atomic::{AtomicBool, Ordering};
+
+	static RECEIVED: AtomicBool = AtomicBool::new(false);
+
+	extern "C" fn on_sighup(_: libc::c_int) {
+		RECEIVED.store(true, Ordering::SeqCst);
+	}
+
+	/// Installs the `SIGHUP` handler, replacing any previous one.
+	pub fn install() -> Result<(), String> {
+		let handler = on_sighup as extern "C" fn(libc::c_int) as libc::sighandler_t;
+		if unsafe { libc::signal(libc::SIGHUP, handler) } == libc::SIG_ERR {
+			return Err(format!("Failed to install SIGHUP handler: {}", std::io::Error::last_os_error()));
+		}
+		Ok(())
+	}
+
+	/// Returns whether `SIGHUP` has been received since the last call.
+	pub fn take() -> bool {
+		RECEIVED.swap(false, Ordering::SeqCst)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_toml_and_json() {
+		let dir = tempdir::TempDir::new("reload-config").unwrap();
+		let toml_path = dir.path().join("node.toml");
+		fs::write(&toml_path, "log = \"sync=trace\"\npool-limit = 10\ntelemetry-url = [\"ws://a 1\"]\n").unwrap();
+		let json_path = dir.path().join("node.json");
+		fs::write(&json_path, r#"{"log":"sync=trace","pool-limit":10,"telemetry-url":"ws://a 1"}"#).unwrap();
+
+		let expected = ReloadableConfig {
+			log: Some("sync=trace".into()),
+			pool_limit: Some(10),
+			telemetry_url: Some(vec!["ws://a 1".into()]),
+			..Default::default()
+		};
+		assert_eq!(ReloadableConfig::from_file(&toml_path).unwrap(), expected);
+		assert_eq!(ReloadableConfig::from_file(&json_path).unwrap(), expected);
+	}
+
+	#[test]
+	fn ignores_options_that_cannot_be_reloaded() {
+		let dir = tempdir::TempDir::new("reload-config").unwrap();
+		let path = dir.path().join("node.toml");
+		fs::write(&path, "port = 30333\nvalidator = true\npool-kbytes = 20480\n").unwrap();
+
+		assert_eq!(ReloadableConfig::from_file(&path).unwrap(), ReloadableConfig {
+			pool_kbytes: Some(20480),
+			..Default::default()
+		});
+	}
+
+	#[test]
+	fn checks_every_reserved_node() {
+		let node = |peer_id: &PeerId| format!("/ip4/127.0.0.1/tcp/30333/p2p/{}", peer_id.to_base58());
+		let (sentry, other) = (PeerId::random(), PeerId::random());
+		let nodes = vec![node(&sentry), node(&other)];
+
+		let parsed = parse_reserved_nodes(&nodes, &HashSet::new()).unwrap();
+		assert_eq!(parsed, vec![sentry.clone(), other.clone()].into_iter().collect());
+		assert!(parse_reserved_nodes(&[node(&sentry), "/ip4/127.0.0.1/tcp/30333".into()], &HashSet::new())
+			.is_err());
+
+		let sentries = Some(sentry.clone()).into_iter().collect();
+		assert_eq!(parse_reserved_nodes(&nodes[..1], &sentries).unwrap(), sentries);
+		assert!(parse_reserved_nodes(&nodes, &sentries).unwrap_err().contains("isn't a sentry node"));
+	}
+
+	#[test]
+	fn reports_only_subsystems_set_in_the_file() {
+		let dir = tempdir::TempDir::new("reload-config").unwrap();
+		let path = dir.path().join("node.toml");
+		fs::write(&path, "port = 30333\noffchain-worker = \"Never\"\n").unwrap();
+
+		let enabled = Arc::new(AtomicBool::new(true));
+		let reloader = ConfigReloader::new(path)
+			.with_subsystem("log", log_filter(None))
+			.with_subsystem("offchain", offchain_worker(Some(enabled.clone()), true));
+
+		assert_eq!(reloader.reload(), vec![ReloadOutcome {
+			subsystem: "offchain".into(),
+			applied: true,
+			message: "disabled".into(),
+		}]);
+		assert!(!enabled.load(Ordering::Relaxed));
+	}
+}
@@ -154,6 +154,8 @@ fn node_config<G, E: Clone> (
 		out_peers: 450,
 		reserved_nodes: vec![],
 		non_reserved_mode: NonReservedPeerMode::Accept,
+		sentry_nodes: vec![],
+		protected_validators: vec![],
 		client_version: "network/test/0.1".to_owned(),
 		node_name: "unknown".to_owned(),
 		transport: TransportConfig::Normal {
@@ -175,11 +177,14 @@ fn node_config<G, E: Clone> (
 		config_dir: Some(root.clone()),
 		database: DatabaseConfig::Path {
 			path: root.join("db"),
-			cache_size: None
+			cache_size: None,
+			backend: Default::default(),
+			columns: Default::default(),
 		},
 		state_cache_size: 16777216,
 		state_cache_child_ratio: None,
 		pruning: Default::default(),
+		retention: Default::default(),
 		chain_spec: (*spec).clone(),
 		custom: Default::default(),
 		name: format!("Node {}", index),
@@ -189,13 +194,18 @@ fn node_config<G, E: Clone> (
 		rpc_ws: None,
 		rpc_ws_max_connections: None,
 		rpc_cors: None,
+		rpc_methods: Default::default(),
 		telemetry_endpoints: None,
 		telemetry_external_transport: None,
+		prometheus_config: None,
 		default_heap_pages: None,
 		offchain_worker: false,
+		reload_config: None,
+		log_filter_reload: None,
 		sentry_mode: false,
 		force_authoring: false,
 		disable_grandpa: false,
+		grandpa_voting_rules: Vec::new(),
 		dev_key_seed: key_seed,
 	}
 }
@@ -19,6 +19,8 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use rstd::vec::Vec;
+use codec::{Encode, Decode};
+use primitives::crypto::KeyTypeId;
 
 #[cfg(feature = "std")]
 use sr_primitives::traits::{ProvideRuntimeApi, Block as BlockT};
@@ -37,6 +39,25 @@ client::decl_runtime_apis! {
 		/// Returns the concatenated SCALE encoded public keys.
 		fn generate_session_keys(seed: Option<Vec<u8>>) -> Vec<u8>;
 	}
+
+	/// Lookup of the owners of session keys.
+	pub trait SessionKeysOwnership {
+		/// Returns, for each given `(key type, raw public key)`, the validator the key is
+		/// registered for and whether it is in use, or `None` if it isn't registered.
+		fn key_owners(keys: Vec<(KeyTypeId, Vec<u8>)>) -> Vec<Option<KeyOwnership>>;
+	}
+}
+
+/// Ownership of a registered session key, as returned by `SessionKeysOwnership::key_owners`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct KeyOwnership {
+	/// Encoded id of the validator the key is registered for.
+	pub owner: Vec<u8>,
+	/// Whether the key is used by the validator in the current session.
+	pub active: bool,
+	/// Whether the key is queued to be used by the validator in the next session.
+	pub queued: bool,
 }
 
 /// Generate the initial session keys with the given seeds and store them in
@@ -32,6 +32,7 @@ use primitives::{
 	crypto::KeyTypeId, ed25519, sr25519, H256,
 	offchain::{
 		Timestamp, HttpRequestId, HttpRequestStatus, HttpError, StorageKind, OpaqueNetworkState,
+		DhtRecord,
 	},
 	LogLevel,
 };
@@ -369,6 +370,19 @@ export_api! {
 			buffer: &mut [u8],
 			deadline: Option<Timestamp>,
 		) -> Result<usize, HttpError>;
+
+		/// Publish a signed record in the DHT under given key.
+		///
+		/// Returns an error if the record is invalid or if it couldn't be stored
+		/// before the deadline.
+		/// Passing `None` as a deadline blocks until the network gives up.
+		fn dht_put(key: &[u8], record: DhtRecord, deadline: Option<Timestamp>) -> Result<(), ()>;
+
+		/// Fetch valid, non-expired records stored in the DHT under given key.
+		///
+		/// Returns an error if the deadline is reached.
+		/// Passing `None` as a deadline blocks until the network gives up.
+		fn dht_get(key: &[u8], deadline: Option<Timestamp>) -> Result<Vec<DhtRecord>, ()>;
 	}
 }
 
@@ -462,6 +462,25 @@ impl OffchainApi for () {
 			ext.http_response_read_body(request_id, buffer, deadline)
 		}, "http_response_read_body can be called only in the offchain worker context")
 	}
+
+	fn dht_put(
+		key: &[u8],
+		record: offchain::DhtRecord,
+		deadline: Option<offchain::Timestamp>,
+	) -> Result<(), ()> {
+		with_offchain(|ext| {
+			ext.dht_put(key, record, deadline)
+		}, "dht_put can be called only in the offchain worker context")
+	}
+
+	fn dht_get(
+		key: &[u8],
+		deadline: Option<offchain::Timestamp>,
+	) -> Result<Vec<offchain::DhtRecord>, ()> {
+		with_offchain(|ext| {
+			ext.dht_get(key, deadline)
+		}, "dht_get can be called only in the offchain worker context")
+	}
 }
 
 impl Api for () {}
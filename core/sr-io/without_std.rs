@@ -21,7 +21,7 @@ pub use rstd::{mem, slice};
 use core::{intrinsics, panic::PanicInfo};
 use rstd::{vec::Vec, cell::Cell, convert::TryInto};
 use primitives::offchain;
-use codec::Decode;
+use codec::{Encode, Decode};
 
 #[cfg(not(feature = "no_panic_handler"))]
 #[panic_handler]
@@ -580,6 +580,37 @@ pub mod ext {
 			buffer_len: u32,
 			deadline: u64,
 		) -> u32;
+
+		/// Publish a signed record in the DHT.
+		///
+		/// `record` is a parity-scale-codec encoded `DhtRecord`.
+		/// Passing `0` as deadline blocks until the network gives up.
+		///
+		/// # Returns
+		/// - `0` if the record has been stored
+		/// - `1` if the record is invalid or couldn't be stored
+		fn ext_dht_put(
+			key: *const u8,
+			key_len: u32,
+			record: *const u8,
+			record_len: u32,
+			deadline: u64,
+		) -> u32;
+
+		/// Fetch records stored in the DHT.
+		///
+		/// Passing `0` as deadline blocks until the network gives up.
+		///
+		/// # Returns
+		///
+		/// A pointer to parity-scale-codec encoded `Vec<DhtRecord>`.
+		/// In case of failure (deadline reached) `written_out` is set to `u32::max_value`.
+		fn ext_dht_get(
+			key: *const u8,
+			key_len: u32,
+			deadline: u64,
+			written_out: *mut u32,
+		) -> *mut u8;
 	}
 }
 
@@ -1225,6 +1256,49 @@ impl OffchainApi for () {
 			Ok(res as usize)
 		}
 	}
+
+	fn dht_put(
+		key: &[u8],
+		record: offchain::DhtRecord,
+		deadline: Option<offchain::Timestamp>,
+	) -> Result<(), ()> {
+		let record = record.encode();
+		let result = unsafe {
+			ext_dht_put.get()(
+				key.as_ptr(),
+				key.len() as u32,
+				record.as_ptr(),
+				record.len() as u32,
+				deadline.map_or(0, |x| x.unix_millis()),
+			)
+		};
+
+		match result {
+			0 => Ok(()),
+			_ => Err(()),
+		}
+	}
+
+	fn dht_get(
+		key: &[u8],
+		deadline: Option<offchain::Timestamp>,
+	) -> Result<Vec<offchain::DhtRecord>, ()> {
+		let mut len = 0u32;
+		let raw_result = unsafe {
+			let ptr = ext_dht_get.get()(
+				key.as_ptr(),
+				key.len() as u32,
+				deadline.map_or(0, |x| x.unix_millis()),
+				&mut len,
+			);
+
+			from_raw_parts(ptr, len)
+		};
+
+		raw_result
+			.and_then(|raw| codec::Decode::decode(&mut &*raw).ok())
+			.ok_or(())
+	}
 }
 
 unsafe fn from_raw_parts(ptr: *mut u8, len: u32) -> Option<Vec<u8>> {
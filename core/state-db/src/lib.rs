@@ -189,6 +189,7 @@ fn to_meta_key<S: Codec>(suffix: &[u8], data: &S) -> Vec<u8> {
 	buffer
 }
 
+#[derive(Clone)]
 struct StateDbSync<BlockHash: Hash, Key: Hash> {
 	mode: PruningMode,
 	non_canonical: NonCanonicalOverlay<BlockHash, Key>,
@@ -331,6 +332,50 @@ impl<BlockHash: Hash, Key: Hash> StateDbSync<BlockHash, Key> {
 		}
 	}
 
+	/// Revert the last canonical block, once all the non-canonical blocks have been reverted.
+	/// `parent_hash` is the hash of its parent, which becomes the last canonical block.
+	/// Returns a database commit or `None` if the state of the parent isn't available.
+	/// For archive an empty commit set is returned.
+	pub fn revert_canonical(&mut self, parent_hash: &BlockHash) -> Option<CommitSet<Key>> {
+		match self.mode {
+			PruningMode::ArchiveAll => {
+				Some(CommitSet::default())
+			},
+			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) => {
+				if self.pruning.as_ref().map_or(false, |pruning| !pruning.can_revert_canonical(parent_hash)) {
+					return None;
+				}
+				let mut commit = self.non_canonical.revert_canonical(parent_hash)?;
+				if let Some(ref mut pruning) = self.pruning {
+					pruning.revert_canonical(&mut commit);
+				}
+				Some(commit)
+			},
+		}
+	}
+
+	/// Number of blocks that can be reverted in a row with `revert_one` and, if `canonical`
+	/// is set, `revert_canonical`. `None` if there's no limit.
+	pub fn revertible_blocks(&self, canonical: bool) -> Option<u64> {
+		match self.mode {
+			PruningMode::ArchiveAll => None,
+			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) => {
+				let non_canonical = self.non_canonical.revertible_levels();
+				if !canonical {
+					return Some(non_canonical);
+				}
+				let canonical = match &self.pruning {
+					Some(pruning) => std::cmp::min(
+						self.non_canonical.revertible_canonical(),
+						pruning.revertible_canonical(),
+					),
+					None => self.non_canonical.revertible_canonical(),
+				};
+				Some(non_canonical + canonical)
+			},
+		}
+	}
+
 	pub fn pin(&mut self, hash: &BlockHash) -> Result<(), PinError> {
 		match self.mode {
 			PruningMode::ArchiveAll => Ok(()),
@@ -447,6 +492,46 @@ impl<BlockHash: Hash, Key: Hash> StateDb<BlockHash, Key> {
 		self.db.write().revert_one()
 	}
 
+	/// Revert the last canonical block, once all the non-canonical blocks have been reverted.
+	/// Returns a database commit or `None` if the state of its parent isn't available.
+	/// For archive an empty commit set is returned.
+	pub fn revert_canonical(&self, parent_hash: &BlockHash) -> Option<CommitSet<Key>> {
+		self.db.write().revert_canonical(parent_hash)
+	}
+
+	/// Number of blocks that can be reverted in a row with `revert_one` and, if `canonical`
+	/// is set, `revert_canonical`. `None` if there's no limit.
+	pub fn revertible_blocks(&self, canonical: bool) -> Option<u64> {
+		self.db.read().revertible_blocks(canonical)
+	}
+
+	/// Revert the blocks whose parents are `parent_hashes`, starting from the best one,
+	/// the non-canonical blocks first and then the canonical ones.
+	///
+	/// `commit` is given the database commits of all the reverts, and the in-memory state
+	/// is only changed if it succeeds. Returns `Ok(false)` and changes nothing if one of the
+	/// blocks can't be reverted. The state is copied while the reverts are prepared, so this
+	/// is meant for offline use.
+	pub fn revert<E>(
+		&self,
+		parent_hashes: &[BlockHash],
+		commit: impl FnOnce(Vec<CommitSet<Key>>) -> Result<(), E>,
+	) -> Result<bool, E> {
+		let mut db = self.db.write();
+		let mut reverted = db.clone();
+		let mut commits = Vec::with_capacity(parent_hashes.len());
+		for parent_hash in parent_hashes {
+			match reverted.revert_one().or_else(|| reverted.revert_canonical(parent_hash)) {
+				Some(revert) => commits.push(revert),
+				None => return Ok(false),
+			}
+		}
+
+		commit(commits)?;
+		*db = reverted;
+		Ok(true)
+	}
+
 	/// Returns last finalized block number.
 	pub fn best_canonical(&self) -> Option<u64> {
 		return self.db.read().best_canonical()
@@ -589,6 +674,54 @@ mod tests {
 		assert!(db.data_eq(&make_db(&[1, 21, 3, 921, 922, 93, 94])));
 	}
 
+	#[test]
+	fn revert_canonical_within_window() {
+		let (mut db, sdb) = make_test_db(PruningMode::Constrained(Constraints {
+			max_blocks: Some(2),
+			max_mem: None,
+		}));
+		assert!(sdb.revert_canonical(&H256::from_low_u64_be(21)).is_none());
+		assert_eq!(sdb.revertible_blocks(false), Some(1));
+		assert_eq!(sdb.revertible_blocks(true), Some(2));
+		db.commit(&sdb.revert_one().unwrap());
+		sdb.apply_pending();
+		db.commit(&sdb.revert_canonical(&H256::from_low_u64_be(21)).unwrap());
+		sdb.apply_pending();
+		assert_eq!(sdb.best_canonical(), Some(2));
+		assert!(!sdb.is_pruned(&H256::from_low_u64_be(21), 2));
+		assert!(db.data_eq(&make_db(&[1, 21, 3, 921, 922, 93, 94])));
+
+		// the state of block 1 is pruned already.
+		assert_eq!(sdb.revertible_blocks(true), Some(0));
+		assert!(sdb.revert_canonical(&H256::from_low_u64_be(1)).is_none());
+	}
+
+	#[test]
+	fn revert_only_changes_memory_once_committed() {
+		let (mut db, sdb) = make_test_db(PruningMode::Constrained(Constraints {
+			max_blocks: Some(2),
+			max_mem: None,
+		}));
+		let parents = [H256::from_low_u64_be(3), H256::from_low_u64_be(21)];
+
+		assert_eq!(sdb.revert(&parents, |_| Err(())), Err(()));
+		assert_eq!(sdb.best_canonical(), Some(3));
+		assert_eq!(sdb.revertible_blocks(true), Some(2));
+
+		// block 1 can't be reverted as well, so nothing is.
+		let too_many = [parents[0], parents[1], H256::from_low_u64_be(1)];
+		assert_eq!(sdb.revert(&too_many, |_| -> Result<(), ()> { unreachable!() }), Ok(false));
+		assert_eq!(sdb.revertible_blocks(true), Some(2));
+
+		assert_eq!(sdb.revert(&parents, |commits| -> Result<(), ()> {
+			commits.iter().for_each(|commit| db.commit(commit));
+			Ok(())
+		}), Ok(true));
+		assert_eq!(sdb.best_canonical(), Some(2));
+		assert_eq!(sdb.revertible_blocks(true), Some(0));
+		assert!(db.data_eq(&make_db(&[1, 21, 3, 921, 922, 93, 94])));
+	}
+
 	#[test]
 	fn detects_incompatible_mode() {
 		let mut db = make_db(&[]);
@@ -30,6 +30,7 @@ const NON_CANONICAL_JOURNAL: &[u8] = b"noncanonical_journal";
 const LAST_CANONICAL: &[u8] = b"last_canonical";
 
 /// See module documentation.
+#[derive(Clone)]
 pub struct NonCanonicalOverlay<BlockHash: Hash, Key: Hash> {
 	last_canonicalized: Option<(BlockHash, u64)>,
 	levels: VecDeque<Vec<BlockOverlay<BlockHash, Key>>>,
@@ -52,6 +53,7 @@ fn to_journal_key(block: u64, index: u64) -> Vec<u8> {
 	to_meta_key(NON_CANONICAL_JOURNAL, &(block, index))
 }
 
+#[derive(Clone)]
 #[cfg_attr(test, derive(PartialEq, Debug))]
 struct BlockOverlay<BlockHash: Hash, Key: Hash> {
 	hash: BlockHash,
@@ -391,6 +393,38 @@ impl<BlockHash: Hash, Key: Hash> NonCanonicalOverlay<BlockHash, Key> {
 		})
 	}
 
+	/// Number of levels which can be reverted with `revert_one`.
+	pub fn revertible_levels(&self) -> u64 {
+		self.levels.len() as u64
+	}
+
+	/// Number of canonicalized blocks which can be reverted with `revert_canonical` once all
+	/// the levels are reverted.
+	pub fn revertible_canonical(&self) -> u64 {
+		if !self.pending_canonicalizations.is_empty() {
+			return 0;
+		}
+		self.last_canonicalized.as_ref().map_or(0, |&(_, number)| number)
+	}
+
+	/// Revert the last canonicalized block, making `parent_hash` the last canonicalized block.
+	/// Only possible once all the non-canonical blocks have been reverted. Returns commit set
+	/// that updates the journal meta data or `None` if not possible.
+	pub fn revert_canonical(&mut self, parent_hash: &BlockHash) -> Option<CommitSet<Key>> {
+		if !self.levels.is_empty() || !self.pending_canonicalizations.is_empty() {
+			return None;
+		}
+		let number = match self.last_canonicalized {
+			Some((_, number)) if number > 0 => number,
+			_ => return None,
+		};
+		let last_canonicalized = (parent_hash.clone(), number - 1);
+		let mut commit = CommitSet::default();
+		commit.meta.inserted.push((to_meta_key(LAST_CANONICAL, &()), last_canonicalized.encode()));
+		self.last_canonicalized = Some(last_canonicalized);
+		Some(commit)
+	}
+
 	fn revert_insertions(&mut self) {
 		self.pending_insertions.reverse();
 		for hash in self.pending_insertions.drain(..) {
@@ -31,6 +31,7 @@ const LAST_PRUNED: &[u8] = b"last_pruned";
 const PRUNING_JOURNAL: &[u8] = b"pruning_journal";
 
 /// See module documentation.
+#[derive(Clone)]
 pub struct RefWindow<BlockHash: Hash, Key: Hash> {
 	/// A queue of keys that should be deleted for each block in the pruning window.
 	death_rows: VecDeque<DeathRow<BlockHash, Key>>,
@@ -46,7 +47,7 @@ pub struct RefWindow<BlockHash: Hash, Key: Hash> {
 	pending_prunings: usize,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct DeathRow<BlockHash: Hash, Key: Hash> {
 	hash: BlockHash,
 	journal_key: Vec<u8>,
@@ -153,6 +154,41 @@ impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 		}
 	}
 
+	/// Check that the most recent block of the window can be removed with `revert_canonical`,
+	/// which requires the state of its parent `parent_hash` to still be in the window.
+	pub fn can_revert_canonical(&self, parent_hash: &BlockHash) -> bool {
+		self.pending_canonicalizations == 0 && self.pending_prunings == 0 &&
+			self.death_rows.len() >= 2 &&
+			self.death_rows[self.death_rows.len() - 2].hash == *parent_hash
+	}
+
+	/// Number of the most recent blocks which can be removed from the window in a row with
+	/// `revert_canonical`.
+	pub fn revertible_canonical(&self) -> u64 {
+		if self.pending_canonicalizations != 0 || self.pending_prunings != 0 {
+			return 0;
+		}
+		self.death_rows.len().saturating_sub(1) as u64
+	}
+
+	/// Remove the most recent block from the window. The nodes it deleted are kept and the
+	/// nodes it inserted are left in the database, since they might be shared with the
+	/// state of older blocks. Adds changes to `commit`.
+	pub fn revert_canonical(&mut self, commit: &mut CommitSet<Key>) {
+		if let Some(reverted) = self.death_rows.pop_back() {
+			trace!(target: "state-db", "Reverting {:?} ({} deleted)", reverted.hash, reverted.deleted.len());
+			let reverted_block = self.pending_number + self.death_rows.len() as u64;
+			for k in reverted.deleted.iter() {
+				if self.death_index.get(k) == Some(&reverted_block) {
+					self.death_index.remove(k);
+				}
+			}
+			commit.meta.deleted.push(reverted.journal_key);
+		} else {
+			warn!(target: "state-db", "Trying to revert when there's nothing to revert");
+		}
+	}
+
 	/// Add a change set to the window. Creates a journal record and pushes it to `commit`
 	pub fn note_canonical(&mut self, hash: &BlockHash, commit: &mut CommitSet<Key>) {
 		trace!(target: "state-db", "Adding to pruning window: {:?} ({} inserted, {} deleted)", hash, commit.data.inserted.len(), commit.data.deleted.len());
@@ -234,6 +270,37 @@ mod tests {
 		assert!(pruning.pending_canonicalizations == 0);
 	}
 
+	#[test]
+	fn revert_canonical_keeps_deleted_nodes() {
+		let mut db = make_db(&[1, 2, 3]);
+		let mut pruning: RefWindow<H256, H256> = RefWindow::new(&db).unwrap();
+		let h1 = H256::random();
+		let mut commit = make_commit(&[4], &[1]);
+		pruning.note_canonical(&h1, &mut commit);
+		db.commit(&commit);
+		let h2 = H256::random();
+		let mut commit = make_commit(&[5], &[2]);
+		pruning.note_canonical(&h2, &mut commit);
+		db.commit(&commit);
+		pruning.apply_pending();
+		assert!(!pruning.can_revert_canonical(&h2));
+		assert!(pruning.can_revert_canonical(&h1));
+
+		let mut commit = CommitSet::default();
+		pruning.revert_canonical(&mut commit);
+		db.commit(&commit);
+		assert!(!pruning.have_block(&h2));
+		assert!(!pruning.can_revert_canonical(&h1));
+		check_journal(&pruning, &db);
+
+		let mut commit = CommitSet::default();
+		pruning.prune_one(&mut commit);
+		db.commit(&commit);
+		pruning.apply_pending();
+		assert!(db.data_eq(&make_db(&[2, 3, 4, 5])));
+		assert!(pruning.death_index.is_empty());
+	}
+
 	#[test]
 	fn prune_one() {
 		let mut db = make_db(&[1, 2, 3]);
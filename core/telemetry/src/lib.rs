@@ -99,6 +99,11 @@ impl TelemetryEndpoints {
 	pub fn new(endpoints: Vec<(String, u8)>) -> Self {
 		TelemetryEndpoints(endpoints)
 	}
+
+	/// Returns the URLs of the servers with their verbosity.
+	pub fn endpoints(&self) -> &[(String, u8)] {
+		&self.0
+	}
 }
 
 /// Log levels.
@@ -132,6 +137,8 @@ struct TelemetryInner {
 	worker: worker::TelemetryWorker,
 	/// Receives log entries for them to be dispatched to the worker.
 	receiver: mpsc::Receiver<slog_async::AsyncRecord>,
+	/// Kept around so that the worker can be rebuilt when the endpoints change.
+	wasm_external_transport: Option<wasm_ext::ExtTransport>,
 }
 
 /// Implements `slog::Drain`.
@@ -145,14 +152,7 @@ struct TelemetryDrain {
 /// Please be careful to not call this function twice in the same program. The `slog` crate
 /// doesn't provide any way of knowing whether a global logger has already been registered.
 pub fn init_telemetry(config: TelemetryConfig) -> Telemetry {
-	// Build the list of telemetry endpoints.
-	let mut endpoints = Vec::new();
-	for &(ref url, verbosity) in &config.endpoints.0 {
-		match url_to_multiaddr(url) {
-			Ok(addr) => endpoints.push((addr, verbosity)),
-			Err(err) => warn!(target: "telemetry", "Invalid telemetry URL {}: {}", url, err),
-		}
-	}
+	let endpoints = parse_endpoints(&config.endpoints).0;
 
 	let (sender, receiver) = mpsc::channel(16);
 	let guard = {
@@ -163,13 +163,49 @@ pub fn init_telemetry(config: TelemetryConfig) -> Telemetry {
 
 	Telemetry {
 		inner: Arc::new(Mutex::new(TelemetryInner {
-			worker: worker::TelemetryWorker::new(endpoints, config.wasm_external_transport),
+			worker: worker::TelemetryWorker::new(endpoints, config.wasm_external_transport.clone()),
 			receiver,
+			wasm_external_transport: config.wasm_external_transport,
 		})),
 		_guard: Arc::new(guard),
 	}
 }
 
+impl Telemetry {
+	/// Replaces the telemetry servers we are talking to.
+	///
+	/// Connections to the previous endpoints are closed. Returns the URLs that were accepted,
+	/// or an error listing the invalid ones, in which case nothing is changed.
+	pub fn set_endpoints(&self, endpoints: TelemetryEndpoints) -> Result<Vec<String>, String> {
+		let (addrs, errors) = parse_endpoints(&endpoints);
+		if !errors.is_empty() {
+			return Err(errors.join(", "));
+		}
+
+		let mut inner = self.inner.lock();
+		let transport = inner.wasm_external_transport.clone();
+		inner.worker = worker::TelemetryWorker::new(addrs, transport);
+		Ok(endpoints.0.into_iter().map(|(url, _)| url).collect())
+	}
+}
+
+/// Parses the URLs of the given endpoints, returning the valid addresses and an error message
+/// for each invalid one.
+fn parse_endpoints(endpoints: &TelemetryEndpoints) -> (Vec<(Multiaddr, u8)>, Vec<String>) {
+	let mut addrs = Vec::new();
+	let mut errors = Vec::new();
+	for &(ref url, verbosity) in &endpoints.0 {
+		match url_to_multiaddr(url) {
+			Ok(addr) => addrs.push((addr, verbosity)),
+			Err(err) => {
+				warn!(target: "telemetry", "Invalid telemetry URL {}: {}", url, err);
+				errors.push(format!("Invalid telemetry URL {}: {}", url, err));
+			}
+		}
+	}
+	(addrs, errors)
+}
+
 /// Event generated when polling the worker.
 #[derive(Debug)]
 pub enum TelemetryEvent {
@@ -398,6 +398,7 @@ impl srml_babe::Trait for Runtime {
 	// are manually adding the digests. normally in this situation you'd use
 	// srml_babe::SameAuthoritiesForever.
 	type EpochChangeTrigger = srml_babe::ExternalTrigger;
+	type HandleEquivocation = ();
 }
 
 /// Adds one to the given input and returns the final result.
@@ -619,10 +620,17 @@ cfg_if! {
 							.into_iter().map(|x|(x, 1)).collect(),
 						randomness: <srml_babe::Module<Runtime>>::randomness(),
 						secondary_slots: true,
+						secondary_vrf_slots: false,
 					}
 				}
 			}
 
+			impl fg_primitives::GrandpaVotingRuleApi<Block> for Runtime {
+				fn max_vote_target() -> Option<u64> {
+					system::max_vote_target()
+				}
+			}
+
 			impl offchain_primitives::OffchainWorkerApi<Block> for Runtime {
 				fn offchain_worker(block: u64) {
 					let ex = Extrinsic::IncludeData(block.encode());
@@ -835,10 +843,17 @@ cfg_if! {
 							.into_iter().map(|x|(x, 1)).collect(),
 						randomness: <srml_babe::Module<Runtime>>::randomness(),
 						secondary_slots: true,
+						secondary_vrf_slots: false,
 					}
 				}
 			}
 
+			impl fg_primitives::GrandpaVotingRuleApi<Block> for Runtime {
+				fn max_vote_target() -> Option<u64> {
+					system::max_vote_target()
+				}
+			}
+
 			impl offchain_primitives::OffchainWorkerApi<Block> for Runtime {
 				fn offchain_worker(block: u64) {
 					let ex = Extrinsic::IncludeData(block.encode());
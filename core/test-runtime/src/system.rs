@@ -35,6 +35,10 @@ use primitives::storage::well_known_keys;
 const NONCE_OF: &[u8] = b"nonce:";
 const BALANCE_OF: &[u8] = b"balance:";
 
+/// The storage key of the highest block number GRANDPA may vote on, which tests set with a
+/// storage change extrinsic. Votes aren't restricted if it isn't set.
+pub const MAX_VOTE_TARGET_KEY: &[u8] = b":test:max_vote_target";
+
 decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {}
 }
@@ -89,6 +93,10 @@ pub fn take_block_number() -> Option<BlockNumber> {
 	Number::take()
 }
 
+pub fn max_vote_target() -> Option<BlockNumber> {
+	storage::unhashed::get(MAX_VOTE_TARGET_KEY)
+}
+
 #[derive(Copy, Clone)]
 enum Mode {
 	Verify,
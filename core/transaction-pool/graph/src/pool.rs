@@ -304,6 +304,17 @@ impl<B: ChainApi> Pool<B> {
 		self.validated_pool.status()
 	}
 
+	/// Returns the limits currently enforced by the pool.
+	pub fn options(&self) -> Options {
+		self.validated_pool.options()
+	}
+
+	/// Replaces the pool limits, returning the number of transactions dropped
+	/// to fit into the new ones.
+	pub fn set_options(&self, options: Options) -> usize {
+		self.validated_pool.set_options(options).len()
+	}
+
 	/// Returns transaction hash
 	pub fn hash_of(&self, xt: &ExtrinsicFor<B>) -> ExHash<B> {
 		self.validated_pool.api().hash_and_length(xt).0
@@ -649,6 +660,35 @@ mod tests {
 		assert!(!pool.validated_pool.rotator().is_banned(&hash2));
 	}
 
+	#[test]
+	fn should_enforce_limits_when_options_change() {
+		// given
+		let pool = pool();
+		let hash1 = block_on(pool.submit_one(&BlockId::Number(0), uxt(Transfer {
+			from: AccountId::from_h256(H256::from_low_u64_be(1)),
+			to: AccountId::from_h256(H256::from_low_u64_be(2)),
+			amount: 5,
+			nonce: 1,
+		}))).unwrap();
+		assert_eq!(pool.status().future, 1);
+
+		// when
+		let limit = Limit {
+			count: 0,
+			total_bytes: 0,
+		};
+		let dropped = pool.set_options(Options {
+			ready: limit.clone(),
+			future: limit.clone(),
+		});
+
+		// then
+		assert_eq!(dropped, 1);
+		assert_eq!(pool.status().future, 0);
+		assert_eq!(pool.options().future.count, 0);
+		assert!(pool.validated_pool.rotator().is_banned(&hash1));
+	}
+
 	#[test]
 	fn should_error_if_reject_immediately() {
 		// given
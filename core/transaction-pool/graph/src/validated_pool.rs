@@ -63,7 +63,7 @@ pub type ValidatedTransactionFor<B> = ValidatedTransaction<
 /// Pool that deals with validated transactions.
 pub(crate) struct ValidatedPool<B: ChainApi> {
 	api: B,
-	options: Options,
+	options: RwLock<Options>,
 	listener: RwLock<Listener<ExHash<B>, BlockHash<B>>>,
 	pool: RwLock<base::BasePool<
 		ExHash<B>,
@@ -78,7 +78,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 	pub fn new(options: Options, api: B) -> Self {
 		ValidatedPool {
 			api,
-			options,
+			options: RwLock::new(options),
 			listener: Default::default(),
 			pool: Default::default(),
 			import_notification_sinks: Default::default(),
@@ -86,6 +86,19 @@ impl<B: ChainApi> ValidatedPool<B> {
 		}
 	}
 
+	/// Returns the limits currently enforced by the pool.
+	pub fn options(&self) -> Options {
+		self.options.read().clone()
+	}
+
+	/// Replaces the pool limits.
+	///
+	/// Transactions exceeding the new limits are dropped immediately.
+	pub fn set_options(&self, options: Options) -> HashSet<ExHash<B>> {
+		*self.options.write() = options;
+		self.enforce_limits()
+	}
+
 	/// Bans given set of hashes.
 	pub fn ban(&self, now: &std::time::Instant, hashes: impl IntoIterator<Item=ExHash<B>>) {
 		self.rotator.ban(now, hashes)
@@ -139,8 +152,9 @@ impl<B: ChainApi> ValidatedPool<B> {
 
 	fn enforce_limits(&self) -> HashSet<ExHash<B>> {
 		let status = self.pool.read().status();
-		let ready_limit = &self.options.ready;
-		let future_limit = &self.options.future;
+		let options = self.options.read().clone();
+		let ready_limit = &options.ready;
+		let future_limit = &options.future;
 
 		debug!(target: "txpool", "Pool Status: {:?}", status);
 
@@ -187,6 +187,21 @@ impl<H, N, V> ForkTree<H, N, V> where
 		Ok(true)
 	}
 
+	/// Revert the tree to the given block number, removing all nodes with a greater number
+	/// along with their descendents. The best finalized number is reset, since nodes at or
+	/// below the given number might have to be imported again.
+	pub fn revert(&mut self, number: &N) {
+		fn revert_nodes<H, N: Ord, V>(nodes: &mut Vec<Node<H, N, V>>, number: &N) {
+			nodes.retain(|node| node.number <= *number);
+			for node in nodes.iter_mut() {
+				revert_nodes(&mut node.children, number);
+			}
+		}
+
+		revert_nodes(&mut self.roots, number);
+		self.best_finalized_number = None;
+	}
+
 	/// Iterates over the existing roots in the tree.
 	pub fn roots(&self) -> impl Iterator<Item=(&H, &N, &V)> {
 		self.roots.iter().map(|node| (&node.hash, &node.number, &node.data))
@@ -721,6 +736,20 @@ mod test {
 		(tree, is_descendent_of)
 	}
 
+	#[test]
+	fn revert_removes_nodes_above_number() {
+		let (mut tree, is_descendent_of) = test_fork_tree();
+
+		tree.revert(&3);
+
+		let mut nodes: Vec<_> = tree.iter().map(|(h, _, _)| *h).collect();
+		nodes.sort();
+		assert_eq!(nodes, vec!["A", "B", "C", "F", "G", "H", "J", "K"]);
+
+		// reverted nodes can be imported again.
+		assert_eq!(tree.import("D", 4, (), &is_descendent_of), Ok(false));
+	}
+
 	#[test]
 	fn import_doesnt_revert() {
 		let (mut tree, is_descendent_of) = test_fork_tree();
@@ -0,0 +1,134 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Prometheus metrics of the node.
+//!
+//! The service creates a single [`Registry`] and hands it to the subsystems,
+//! which register their own metrics on it with [`register`] and keep the
+//! returned handles to update them. [`init_prometheus`] serves the metrics of
+//! the registry in the Prometheus text format at `/metrics`.
+//!
+//! Metrics are cheap to clone handles, so a subsystem which isn't given a
+//! registry simply doesn't create any.
+
+#![warn(missing_docs)]
+
+use std::net::SocketAddr;
+
+use futures::Future;
+use hyper::{Body, Request, Response, Server, StatusCode, header::CONTENT_TYPE, service::service_fn_ok};
+use log::{error, info};
+use prometheus::{Encoder, TextEncoder, TEXT_FORMAT};
+
+pub use prometheus::{
+	Error as PrometheusError, Registry, Opts, HistogramOpts,
+	Gauge, GaugeVec, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+	core::Collector,
+};
+
+/// Prometheus exporter errors.
+#[derive(Debug, derive_more::Display, derive_more::From)]
+pub enum Error {
+	/// The HTTP server couldn't be started.
+	#[display(fmt = "Prometheus server failed: {}", _0)]
+	Hyper(hyper::Error),
+	/// Encoding the metrics failed.
+	#[display(fmt = "Encoding metrics failed: {}", _0)]
+	Prometheus(PrometheusError),
+}
+
+impl std::error::Error for Error {}
+
+/// Create a registry whose metrics are all prefixed with `substrate_`.
+pub fn new_registry() -> Registry {
+	Registry::new_custom(Some("substrate".into()), None)
+		.expect("the prefix is a valid metric name; qed")
+}
+
+/// Register `metric` on `registry` and return it, so that it can be updated.
+pub fn register<T: Clone + Collector + 'static>(metric: T, registry: &Registry) -> Result<T, PrometheusError> {
+	registry.register(Box::new(metric.clone()))?;
+	Ok(metric)
+}
+
+/// Encode all the metrics of `registry` in the Prometheus text format.
+pub fn encode(registry: &Registry) -> Result<Vec<u8>, Error> {
+	let mut buffer = Vec::new();
+	TextEncoder::new().encode(&registry.gather(), &mut buffer)?;
+	Ok(buffer)
+}
+
+/// Start serving the metrics of `registry` on `address`.
+///
+/// The returned future runs the HTTP server and must be spawned on a tokio runtime.
+pub fn init_prometheus(
+	address: SocketAddr,
+	registry: Registry,
+) -> Result<impl Future<Item = (), Error = ()>, Error> {
+	let server = Server::try_bind(&address)?;
+	info!("Prometheus server started at {}", address);
+
+	let make_service = move || {
+		let registry = registry.clone();
+		service_fn_ok(move |request: Request<Body>| serve_request(&request, &registry))
+	};
+
+	Ok(server.serve(make_service).map_err(|e| error!("Prometheus server error: {}", e)))
+}
+
+fn serve_request(request: &Request<Body>, registry: &Registry) -> Response<Body> {
+	let (status, content_type, body) = if request.uri().path() != "/metrics" {
+		(StatusCode::NOT_FOUND, "text/plain", Body::from("Not found."))
+	} else {
+		match encode(registry) {
+			Ok(metrics) => (StatusCode::OK, TEXT_FORMAT, Body::from(metrics)),
+			Err(e) => {
+				error!("Failed to serve metrics: {}", e);
+				(StatusCode::INTERNAL_SERVER_ERROR, "text/plain", Body::from(e.to_string()))
+			},
+		}
+	};
+
+	let mut response = Response::new(body);
+	*response.status_mut() = status;
+	response.headers_mut().insert(CONTENT_TYPE, hyper::header::HeaderValue::from_static(content_type));
+	response
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn registered_metrics_are_served_with_the_prefix() {
+		let registry = new_registry();
+		let gauge = register(IntGauge::new("best_height", "Best block height").unwrap(), &registry).unwrap();
+		gauge.set(42);
+
+		// the same metric can't be registered twice.
+		assert!(register(IntGauge::new("best_height", "Best block height").unwrap(), &registry).is_err());
+
+		let request = Request::get("/metrics").body(Body::empty()).unwrap();
+		let response = serve_request(&request, &registry);
+		assert_eq!(response.status(), StatusCode::OK);
+
+		let metrics = String::from_utf8(encode(&registry).unwrap()).unwrap();
+		assert!(metrics.contains("substrate_best_height 42"));
+
+		let request = Request::get("/").body(Body::empty()).unwrap();
+		assert_eq!(serve_request(&request, &registry).status(), StatusCode::NOT_FOUND);
+	}
+}
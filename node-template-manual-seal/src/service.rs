@@ -0,0 +1,118 @@
+//! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
+//!
+//! Blocks are sealed by the local node, either as soon as transactions are imported or
+//! on `engine_createBlock` RPC calls, and finalized on import or on `engine_finalizeBlock`
+//! RPC calls. The runtime has no consensus module, and blocks received from the network
+//! are rejected, so this is for development on a single node only.
+
+use substrate_service::{error::{Error as ServiceError}, AbstractService, Configuration};
+use runtime::{self, GenesisConfig, opaque::Block};
+use network::{construct_simple_protocol};
+use substrate_executor::native_executor_instance;
+pub use substrate_executor::NativeExecutor;
+use futures03::{FutureExt, TryFutureExt};
+
+// Our native executor instance.
+native_executor_instance!(
+	pub Executor,
+	runtime::api::dispatch,
+	runtime::native_version,
+);
+
+construct_simple_protocol! {
+	/// Demo protocol attachment for substrate.
+	pub struct NodeProtocol where Block = Block { }
+}
+
+/// Starts a `ServiceBuilder` for a full service.
+///
+/// Use this macro if you don't actually need the full service, but just the builder in order to
+/// be able to perform chain operations.
+macro_rules! new_full_start {
+	($config:expr) => {{
+		let inherent_data_providers = inherents::InherentDataProviders::new();
+
+		let builder = substrate_service::ServiceBuilder::new_full::<
+			runtime::opaque::Block, runtime::RuntimeApi, crate::service::Executor
+		>($config)?
+			.with_select_chain(|_config, backend| {
+				Ok(substrate_client::LongestChain::new(backend.clone()))
+			})?
+			.with_transaction_pool(|config, client|
+				Ok(transaction_pool::txpool::Pool::new(config, transaction_pool::FullChainApi::new(client)))
+			)?
+			.with_import_queue(|config, client, _select_chain, _transaction_pool| {
+				Ok(manual_seal::import_queue(Box::new(client), config.prometheus_registry()))
+			})?;
+
+		(builder, inherent_data_providers)
+	}}
+}
+
+/// Builds a new service for a full client, which seals a block as soon as transactions
+/// are imported if `instant` is set, and on `engine_createBlock` RPC calls otherwise.
+pub fn new_full<C: Send + Default + 'static>(
+	config: Configuration<C, GenesisConfig>,
+	instant: bool,
+	instant_finality: bool,
+) -> Result<impl AbstractService, ServiceError> {
+	type RpcExtension = jsonrpc_core::IoHandler<substrate_rpc::Metadata>;
+
+	let (builder, inherent_data_providers) = new_full_start!(config);
+	inherent_data_providers
+		.register_provider(timestamp::InherentDataProvider)
+		.map_err(|e| format!("{:?}", e))?;
+
+	let (commands_sink, commands_stream) = futures03::channel::mpsc::channel(1024);
+
+	let service = builder
+		.with_rpc_extensions(|_client, _pool, _backend, _subscriptions| -> RpcExtension {
+			use manual_seal::rpc::ManualSealApi;
+
+			let mut io = RpcExtension::default();
+			if !instant {
+				io.extend_with(ManualSealApi::to_delegate(manual_seal::rpc::ManualSeal::new(
+					commands_sink,
+				)));
+			}
+			io
+		})?
+		.with_network_protocol(|_| Ok(NodeProtocol::new()))?
+		.build()?;
+
+	let proposer = basic_authorship::ProposerFactory {
+		client: service.client(),
+		transaction_pool: service.transaction_pool(),
+	};
+	let client = service.client();
+	let select_chain = service.select_chain()
+		.ok_or(ServiceError::SelectChainRequired)?;
+
+	let seal = if instant {
+		manual_seal::run_instant_seal(
+			Box::new(client.clone()),
+			proposer,
+			client,
+			service.transaction_pool(),
+			select_chain,
+			inherent_data_providers,
+			instant_finality,
+		).boxed()
+	} else {
+		manual_seal::run_manual_seal(
+			Box::new(client.clone()),
+			proposer,
+			client,
+			service.transaction_pool(),
+			commands_stream,
+			select_chain,
+			inherent_data_providers,
+		).boxed()
+	};
+
+	// the sealing task is considered essential, i.e. if it fails we take
+	// down the service with it.
+	service.spawn_essential_task(seal.unit_error().compat());
+
+	Ok(service)
+}
@@ -0,0 +1,24 @@
+/// A module letting the chain choose the GRANDPA committee which finalizes the
+/// blocks produced by miners.
+///
+/// Here the committee is simply set by root, e.g. through `sudo`. A real chain would
+/// rather elect it, e.g. from the stakers or from the authors of recent blocks.
+
+use support::{decl_module, dispatch::Result};
+use system::ensure_root;
+use grandpa::AuthorityList;
+
+/// The module's configuration trait.
+pub trait Trait: grandpa::Trait {}
+
+decl_module! {
+	/// The module declaration.
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		/// Schedule a change of the GRANDPA committee, enacted `delay` blocks after the
+		/// block this call is included in is finalized.
+		pub fn set_committee(origin, committee: AuthorityList, delay: T::BlockNumber) -> Result {
+			ensure_root(origin)?;
+			<grandpa::Module<T>>::schedule_change(committee, delay, None)
+		}
+	}
+}
@@ -0,0 +1,25 @@
+//! Substrate Node Template CLI library, proof of work variant.
+
+#![warn(missing_docs)]
+#![warn(unused_extern_crates)]
+
+mod chain_spec;
+#[macro_use]
+mod service;
+mod cli;
+
+pub use substrate_cli::{VersionInfo, IntoExit, error};
+
+fn main() -> Result<(), cli::error::Error> {
+	let version = VersionInfo {
+		name: "Substrate PoW Node",
+		commit: env!("VERGEN_SHA_SHORT"),
+		version: env!("CARGO_PKG_VERSION"),
+		executable_name: "node-template-pow",
+		author: "Anonymous",
+		description: "Template Node with proof of work and GRANDPA finality",
+		support_url: "support.anonymous.an",
+	};
+
+	cli::run(std::env::args(), cli::Exit, version)
+}
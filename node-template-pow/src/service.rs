@@ -0,0 +1,214 @@
+//! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
+//!
+//! Blocks are mined with a proof of work, and finalized by the GRANDPA committee chosen
+//! by the runtime. The PoW block import wraps the GRANDPA block import, so that GRANDPA
+//! sees every block and tracks changes of the committee, while the PoW block import
+//! picks the best chain by total difficulty among the chains containing the last
+//! finalized block.
+
+use std::sync::Arc;
+use std::time::Duration;
+use runtime::{self, GenesisConfig, opaque::Block};
+use substrate_service::{error::{Error as ServiceError}, AbstractService, Configuration};
+use network::{construct_simple_protocol};
+use substrate_executor::native_executor_instance;
+pub use substrate_executor::NativeExecutor;
+use grandpa::{self, FinalityProofProvider as GrandpaFinalityProofProvider};
+use pow::algorithms::Sha3Pow;
+use pow::difficulty::{Lwma, RetargetParams};
+use primitives::U256;
+
+// Our native executor instance.
+native_executor_instance!(
+	pub Executor,
+	runtime::api::dispatch,
+	runtime::native_version,
+);
+
+construct_simple_protocol! {
+	/// Demo protocol attachment for substrate.
+	pub struct NodeProtocol where Block = Block { }
+}
+
+/// Number of hashes the CPU miner computes before checking for new work.
+const MINING_ROUND: u32 = 10_000;
+
+/// Number of CPU mining threads.
+const MINING_THREADS: usize = 1;
+
+/// The proof of work algorithm, retargeting the difficulty to the block time of the runtime.
+pub fn pow_algorithm<C>(client: Arc<C>) -> Sha3Pow<C, Lwma> {
+	Sha3Pow::new(client, Lwma(RetargetParams {
+		target_block_time: runtime::MILLISECS_PER_BLOCK,
+		window: 60,
+		initial_difficulty: U256::from(1_000_000),
+		min_difficulty: U256::from(1_000),
+	}))
+}
+
+/// Starts a `ServiceBuilder` for a full service.
+///
+/// Use this macro if you don't actually need the full service, but just the builder in order to
+/// be able to perform chain operations.
+macro_rules! new_full_start {
+	($config:expr) => {{
+		let mut import_setup = None;
+		let inherent_data_providers = inherents::InherentDataProviders::new();
+
+		let builder = substrate_service::ServiceBuilder::new_full::<
+			runtime::opaque::Block, runtime::RuntimeApi, crate::service::Executor
+		>($config)?
+			.with_select_chain(|_config, backend| {
+				Ok(substrate_client::LongestChain::new(backend.clone()))
+			})?
+			.with_transaction_pool(|config, client|
+				Ok(transaction_pool::txpool::Pool::new(config, transaction_pool::FullChainApi::new(client)))
+			)?
+			.with_import_queue(|config, client, mut select_chain, _transaction_pool| {
+				let select_chain = select_chain.take()
+					.ok_or_else(|| substrate_service::Error::SelectChainRequired)?;
+
+				let (grandpa_block_import, grandpa_link) =
+					grandpa::block_import::<_, _, _, runtime::RuntimeApi, _>(
+						client.clone(), &*client, select_chain.clone()
+					)?;
+
+				let pow_block_import = pow::PowBlockImport::new(
+					grandpa_block_import.clone(),
+					client.clone(),
+					crate::service::pow_algorithm(client.clone()),
+					Some(select_chain),
+				);
+
+				let import_queue = pow::import_queue(
+					Box::new(pow_block_import.clone()),
+					Some(Box::new(grandpa_block_import)),
+					client.clone(),
+					crate::service::pow_algorithm(client),
+					0,
+					inherent_data_providers.clone(),
+					config.prometheus_registry(),
+				)?;
+
+				import_setup = Some((pow_block_import, grandpa_link));
+
+				Ok(import_queue)
+			})?;
+
+		(builder, import_setup, inherent_data_providers)
+	}}
+}
+
+/// Builds a new service for a full client.
+pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisConfig>)
+	-> Result<impl AbstractService, ServiceError>
+{
+	let is_authority = config.roles.is_authority();
+	let name = config.name.clone();
+	let disable_grandpa = config.disable_grandpa;
+	let grandpa_voting_rules = config.grandpa_voting_rules.clone();
+
+	// sentry nodes announce themselves as authorities to the network
+	// and should run the same protocols authorities do, but it should
+	// never actively participate in any consensus process.
+	let participates_in_consensus = is_authority && !config.sentry_mode;
+
+	let (builder, mut import_setup, inherent_data_providers) = new_full_start!(config);
+
+	let (block_import, grandpa_link) =
+		import_setup.take()
+			.expect("Link Half and Block Import are present for Full Services or setup failed before. qed");
+
+	let service = builder.with_network_protocol(|_| Ok(NodeProtocol::new()))?
+		.with_finality_proof_provider(|client, backend|
+			Ok(Arc::new(GrandpaFinalityProofProvider::new(backend, client)) as _)
+		)?
+		.build()?;
+
+	if participates_in_consensus {
+		let proposer = basic_authorship::ProposerFactory {
+			client: service.client(),
+			transaction_pool: service.transaction_pool(),
+		};
+
+		let client = service.client();
+		let select_chain = service.select_chain()
+			.ok_or(ServiceError::SelectChainRequired)?;
+
+		// the miner runs on its own threads, external miners can't connect
+		// to this node as the mining handle isn't exposed over RPC.
+		let _mining_handle = pow::start_mine(
+			Box::new(block_import),
+			client.clone(),
+			pow_algorithm(client),
+			proposer,
+			None,
+			MINING_ROUND,
+			MINING_THREADS,
+			service.network(),
+			Duration::from_secs(5),
+			Some(select_chain),
+			inherent_data_providers.clone(),
+		);
+	}
+
+	// if the node isn't actively participating in consensus then it doesn't
+	// need a keystore, regardless of which protocol we use below.
+	let keystore = if participates_in_consensus {
+		Some(service.keystore())
+	} else {
+		None
+	};
+
+	let grandpa_config = grandpa::Config {
+		// FIXME #1578 make this available through chainspec
+		gossip_duration: Duration::from_millis(333),
+		justification_period: 512,
+		name: Some(name),
+		observer_enabled: true,
+		keystore,
+		is_authority,
+	};
+
+	match (is_authority, disable_grandpa) {
+		(false, false) => {
+			// start the lightweight GRANDPA observer
+			service.spawn_task(grandpa::run_grandpa_observer(
+				grandpa_config,
+				grandpa_link,
+				service.network(),
+				service.on_exit(),
+			)?);
+		},
+		(true, false) => {
+			// start the full GRANDPA voter, which only votes if the keystore
+			// holds the key of a member of the committee.
+			let voting_rules = grandpa::VotingRulesBuilder::from_names(&grandpa_voting_rules)
+				.map_err(|name| format!("Unknown GRANDPA voting rule: {}", name))?;
+
+			let voter_config = grandpa::GrandpaParams {
+				config: grandpa_config,
+				link: grandpa_link,
+				network: service.network(),
+				inherent_data_providers: inherent_data_providers.clone(),
+				on_exit: service.on_exit(),
+				telemetry_on_connect: Some(service.telemetry_on_connect_stream()),
+				voting_rule: voting_rules.build(),
+				prometheus_registry: service.prometheus_registry(),
+			};
+
+			// the GRANDPA voter task is considered infallible, i.e.
+			// if it fails we take down the service with it.
+			service.spawn_essential_task(grandpa::run_grandpa_voter(voter_config)?);
+		},
+		(_, true) => {
+			grandpa::setup_disabled_grandpa(
+				service.client(),
+				&inherent_data_providers,
+				service.network(),
+			)?;
+		},
+	}
+
+	Ok(service)
+}
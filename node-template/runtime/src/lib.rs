@@ -161,10 +161,12 @@ impl system::Trait for Runtime {
 
 impl aura::Trait for Runtime {
 	type AuthorityId = AuraId;
+	type HandleEquivocation = ();
 }
 
 impl grandpa::Trait for Runtime {
 	type Event = Event;
+	type HandleEquivocation = ();
 }
 
 impl indices::Trait for Runtime {
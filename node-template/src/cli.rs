@@ -45,8 +45,19 @@ pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
 		ParseAndPrepare::ImportBlocks(cmd) => cmd.run_with_builder(|config: Config<_>|
 			Ok(new_full_start!(config).0), load_spec, exit),
 		ParseAndPrepare::PurgeChain(cmd) => cmd.run(load_spec),
-		ParseAndPrepare::RevertChain(cmd) => cmd.run_with_builder(|config: Config<_>|
+		ParseAndPrepare::RevertChain(cmd) => cmd.run_with_builder_and_revert_aux(
+			|config: Config<_>| Ok(new_full_start!(config).0),
+			|builder, hash, number| Ok(grandpa::revert_aux(&**builder.client(), hash, number)?),
+			load_spec,
+		),
+		ParseAndPrepare::CheckDb(cmd) => cmd.run_with_builder(|config: Config<_>|
 			Ok(new_full_start!(config).0), load_spec),
+		ParseAndPrepare::DryRunUpgrade(cmd) => cmd.run_with_builder(|config: Config<_>|
+			Ok(new_full_start!(config).0), load_spec),
+		ParseAndPrepare::ReplayBlocks(cmd) => cmd.run_with_builder(|config: Config<_>|
+			Ok(new_full_start!(config).0), load_spec),
+		ParseAndPrepare::PrintConfig(cmd) => cmd.run::<NoCustom, _, _, _>(load_spec),
+		ParseAndPrepare::Key(cmd) => cmd.run::<NoCustom, _, _, _>(load_spec),
 		ParseAndPrepare::CustomCommand(_) => Ok(())
 	}?;
 
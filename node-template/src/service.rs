@@ -43,7 +43,7 @@ macro_rules! new_full_start {
 			.with_transaction_pool(|config, client|
 				Ok(transaction_pool::txpool::Pool::new(config, transaction_pool::FullChainApi::new(client)))
 			)?
-			.with_import_queue(|_config, client, mut select_chain, transaction_pool| {
+			.with_import_queue(|config, client, mut select_chain, transaction_pool| {
 				let select_chain = select_chain.take()
 					.ok_or_else(|| substrate_service::Error::SelectChainRequired)?;
 
@@ -60,6 +60,7 @@ macro_rules! new_full_start {
 					client,
 					inherent_data_providers.clone(),
 					Some(transaction_pool),
+					config.prometheus_registry(),
 				)?;
 
 				import_setup = Some((grandpa_block_import, grandpa_link));
@@ -79,6 +80,7 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 	let force_authoring = config.force_authoring;
 	let name = config.name.clone();
 	let disable_grandpa = config.disable_grandpa;
+	let grandpa_voting_rules = config.grandpa_voting_rules.clone();
 
 	// sentry nodes announce themselves as authorities to the network
 	// and should run the same protocols authorities do, but it should
@@ -107,7 +109,15 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 		let select_chain = service.select_chain()
 			.ok_or(ServiceError::SelectChainRequired)?;
 
-		let aura = aura::start_aura::<_, _, _, _, _, AuraPair, _, _, _>(
+		// without GRANDPA nothing is ever finalized, backing off would stall the chain.
+		let backoff_authoring_blocks = if disable_grandpa {
+			None
+		} else {
+			Some(aura::BackoffAuthoringOnFinalizedHeadLagging::default()
+				.with_prometheus_registry(service.prometheus_registry().as_ref()))
+		};
+
+		let aura = aura::start_aura::<_, _, _, _, _, AuraPair, _, _, _, _>(
 			aura::SlotDuration::get_or_compute(&*client)?,
 			client,
 			select_chain,
@@ -117,6 +127,7 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 			inherent_data_providers.clone(),
 			force_authoring,
 			service.keystore(),
+			backoff_authoring_blocks,
 		)?;
 
 		// the AURA authoring task is considered essential, i.e. if it
@@ -154,6 +165,9 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 		},
 		(true, false) => {
 			// start the full GRANDPA voter
+			let voting_rules = grandpa::VotingRulesBuilder::from_names(&grandpa_voting_rules)
+				.map_err(|name| format!("Unknown GRANDPA voting rule: {}", name))?;
+
 			let voter_config = grandpa::GrandpaParams {
 				config: grandpa_config,
 				link: grandpa_link,
@@ -161,7 +175,8 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 				inherent_data_providers: inherent_data_providers.clone(),
 				on_exit: service.on_exit(),
 				telemetry_on_connect: Some(service.telemetry_on_connect_stream()),
-				voting_rule: grandpa::VotingRulesBuilder::default().build(),
+				voting_rule: voting_rules.build(),
+				prometheus_registry: service.prometheus_registry(),
 			};
 
 			// the GRANDPA voter task is considered infallible, i.e.
@@ -193,7 +208,7 @@ pub fn new_light<C: Send + Default + 'static>(config: Configuration<C, GenesisCo
 		.with_transaction_pool(|config, client|
 			Ok(TransactionPool::new(config, transaction_pool::FullChainApi::new(client)))
 		)?
-		.with_import_queue_and_fprb(|_config, client, backend, fetcher, _select_chain, _tx_pool| {
+		.with_import_queue_and_fprb(|config, client, backend, fetcher, _select_chain, _tx_pool| {
 			let fetch_checker = fetcher
 				.map(|fetcher| fetcher.checker().clone())
 				.ok_or_else(|| "Trying to start light import queue without active fetch checker")?;
@@ -212,6 +227,7 @@ pub fn new_light<C: Send + Default + 'static>(config: Configuration<C, GenesisCo
 				client,
 				inherent_data_providers.clone(),
 				None,
+				config.prometheus_registry(),
 			)?;
 
 			Ok((import_queue, finality_proof_request_builder))
@@ -137,7 +137,24 @@ pub fn run<I, T, E>(args: I, exit: E, version: substrate_cli::VersionInfo) -> er
 		ParseAndPrepare::ImportBlocks(cmd) => cmd.run_with_builder(|config: Config<_, _>|
 			Ok(new_full_start!(config).0), load_spec, exit),
 		ParseAndPrepare::PurgeChain(cmd) => cmd.run(load_spec),
-		ParseAndPrepare::RevertChain(cmd) => cmd.run_with_builder(|config: Config<_, _>|
+		ParseAndPrepare::RevertChain(cmd) => cmd.run_with_builder_and_revert_aux(
+			|config: Config<_, _>| Ok(new_full_start!(config).0),
+			|builder, hash, number| {
+				let client = &**builder.client();
+				let mut aux = grandpa::revert_aux(client, hash, number)?;
+				aux.extend(babe::revert_aux(client, hash, number)?);
+				Ok(aux)
+			},
+			load_spec,
+		),
+		ParseAndPrepare::CheckDb(cmd) => cmd.run_with_builder(|config: Config<_, _>|
+			Ok(new_full_start!(config).0), load_spec),
+		ParseAndPrepare::DryRunUpgrade(cmd) => cmd.run_with_builder(|config: Config<_, _>|
+			Ok(new_full_start!(config).0), load_spec),
+		ParseAndPrepare::ReplayBlocks(cmd) => cmd.run_with_builder(|config: Config<_, _>|
+			Ok(new_full_start!(config).0), load_spec),
+		ParseAndPrepare::PrintConfig(cmd) => cmd.run::<NoCustom, _, _, _>(load_spec),
+		ParseAndPrepare::Key(cmd) => cmd.run_with_builder(|config: Config<_, _>|
 			Ok(new_full_start!(config).0), load_spec),
 		ParseAndPrepare::CustomCommand(CustomSubcommands::Factory(cli_args)) => {
 			let mut config: Config<_, _> = substrate_cli::create_config_with_db_path(
@@ -55,6 +55,7 @@ macro_rules! new_full_start {
 	($config:expr) => {{
 		type RpcExtension = jsonrpc_core::IoHandler<substrate_rpc::Metadata>;
 		let mut import_setup = None;
+		let mut rpc_setup = None;
 		let inherent_data_providers = inherents::InherentDataProviders::new();
 
 		let builder = substrate_service::ServiceBuilder::new_full::<
@@ -66,7 +67,7 @@ macro_rules! new_full_start {
 			.with_transaction_pool(|config, client|
 				Ok(transaction_pool::txpool::Pool::new(config, transaction_pool::FullChainApi::new(client)))
 			)?
-			.with_import_queue(|_config, client, mut select_chain, _transaction_pool| {
+			.with_import_queue(|config, client, mut select_chain, _transaction_pool| {
 				let select_chain = select_chain.take()
 					.ok_or_else(|| substrate_service::Error::SelectChainRequired)?;
 				let (grandpa_block_import, grandpa_link) = grandpa::block_import(
@@ -91,13 +92,42 @@ macro_rules! new_full_start {
 					client.clone(),
 					client,
 					inherent_data_providers.clone(),
+					config.prometheus_registry(),
 				)?;
 
+				rpc_setup = Some((
+					grandpa_link.shared_voter_state(),
+					grandpa_link.justification_stream(),
+					babe_link.clone(),
+				));
 				import_setup = Some((block_import, grandpa_link, babe_link));
 				Ok(import_queue)
-			})?
-			.with_rpc_extensions(|client, pool, _backend| -> RpcExtension {
-				node_rpc::create(client, pool)
+			})?;
+
+		let keystore = builder.keystore();
+		let deny_unsafe = builder.deny_unsafe_rpc();
+		let builder = builder
+			.with_rpc_extensions(|client, pool, _backend, subscriptions| -> RpcExtension {
+				use grandpa_rpc::GrandpaApi;
+				use babe_rpc::BabeApi;
+
+				let (voter_state, justification_stream, babe_link) = rpc_setup.take()
+					.expect("GRANDPA and BABE links are set up by the import queue; qed");
+
+				let mut io = node_rpc::create(client.clone(), pool);
+				io.extend_with(BabeApi::to_delegate(babe_rpc::Babe::new(
+					client.clone(),
+					babe_link,
+					keystore,
+					deny_unsafe,
+				)));
+				io.extend_with(GrandpaApi::to_delegate(grandpa_rpc::Grandpa::new(
+					client,
+					voter_state,
+					justification_stream,
+					subscriptions,
+				)));
+				io
 			})?;
 
 		(builder, import_setup, inherent_data_providers)
@@ -110,19 +140,23 @@ macro_rules! new_full_start {
 /// concrete types instead.
 macro_rules! new_full {
 	($config:expr, $with_startup_data: expr) => {{
-		use futures::sync::mpsc;
+		use futures03::{channel::mpsc, future::{FutureExt, TryFutureExt}};
 		use network::DhtEvent;
 
 		let (
 			is_authority,
 			force_authoring,
 			name,
-			disable_grandpa
+			disable_grandpa,
+			grandpa_voting_rules,
+			behind_sentries,
 		) = (
 			$config.roles.is_authority(),
 			$config.force_authoring,
 			$config.name.clone(),
-			$config.disable_grandpa
+			$config.disable_grandpa,
+			$config.grandpa_voting_rules.clone(),
+			!$config.network.sentry_nodes.is_empty(),
 		);
 
 		// sentry nodes announce themselves as authorities to the network
@@ -136,7 +170,7 @@ macro_rules! new_full {
 		// back-pressure. Authority discovery is triggering one event per authority within the current authority set.
 		// This estimates the authority set size to be somewhere below 10 000 thereby setting the channel buffer size to
 		// 10 000.
-		let (dht_event_tx, _dht_event_rx) =
+		let (dht_event_tx, dht_event_rx) =
 			mpsc::channel::<DhtEvent>(10_000);
 
 		let service = builder.with_network_protocol(|_| Ok(crate::service::NodeProtocol::new()))?
@@ -161,6 +195,14 @@ macro_rules! new_full {
 			let select_chain = service.select_chain()
 				.ok_or(substrate_service::Error::SelectChainRequired)?;
 
+			// without GRANDPA nothing is ever finalized, backing off would stall the chain.
+			let backoff_authoring_blocks = if disable_grandpa {
+				None
+			} else {
+				Some(babe::BackoffAuthoringOnFinalizedHeadLagging::default()
+					.with_prometheus_registry(service.prometheus_registry().as_ref()))
+			};
+
 			let babe_config = babe::BabeParams {
 				keystore: service.keystore(),
 				client,
@@ -171,10 +213,22 @@ macro_rules! new_full {
 				inherent_data_providers: inherent_data_providers.clone(),
 				force_authoring,
 				babe_link,
+				backoff_authoring_blocks,
 			};
 
 			let babe = babe::start_babe(babe_config)?;
 			service.spawn_essential_task(babe);
+
+			// a validator behind sentry nodes stays off the DHT, so that its address isn't
+			// revealed to the network.
+			if !behind_sentries {
+				let authority_discovery = authority_discovery::AuthorityDiscovery::new(
+					service.client(),
+					service.network(),
+					dht_event_rx,
+				);
+				service.spawn_task(authority_discovery.unit_error().compat());
+			}
 		}
 
 		// if the node isn't actively participating in consensus then it doesn't
@@ -207,6 +261,9 @@ macro_rules! new_full {
 			},
 			(true, false) => {
 				// start the full GRANDPA voter
+				let voting_rules = grandpa::VotingRulesBuilder::from_names(&grandpa_voting_rules)
+					.map_err(|name| format!("Unknown GRANDPA voting rule: {}", name))?;
+
 				let grandpa_config = grandpa::GrandpaParams {
 					config: config,
 					link: grandpa_link,
@@ -214,7 +271,8 @@ macro_rules! new_full {
 					inherent_data_providers: inherent_data_providers.clone(),
 					on_exit: service.on_exit(),
 					telemetry_on_connect: Some(service.telemetry_on_connect_stream()),
-					voting_rule: grandpa::VotingRulesBuilder::default().build(),
+					voting_rule: voting_rules.build(),
+					prometheus_registry: service.prometheus_registry(),
 				};
 				// the GRANDPA voter task is considered infallible, i.e.
 				// if it fails we take down the service with it.
@@ -288,7 +346,7 @@ pub fn new_light<C: Send + Default + 'static>(config: NodeConfiguration<C>)
 		.with_transaction_pool(|config, client|
 			Ok(TransactionPool::new(config, transaction_pool::FullChainApi::new(client)))
 		)?
-		.with_import_queue_and_fprb(|_config, client, backend, fetcher, _select_chain, _tx_pool| {
+		.with_import_queue_and_fprb(|config, client, backend, fetcher, _select_chain, _tx_pool| {
 			let fetch_checker = fetcher
 				.map(|fetcher| fetcher.checker().clone())
 				.ok_or_else(|| "Trying to start light import queue without active fetch checker")?;
@@ -318,6 +376,7 @@ pub fn new_light<C: Send + Default + 'static>(config: NodeConfiguration<C>)
 				client.clone(),
 				client,
 				inherent_data_providers.clone(),
+				config.prometheus_registry(),
 			)?;
 
 			Ok((import_queue, finality_proof_request_builder))
@@ -326,7 +385,7 @@ pub fn new_light<C: Send + Default + 'static>(config: NodeConfiguration<C>)
 		.with_finality_proof_provider(|client, backend|
 			Ok(Arc::new(GrandpaFinalityProofProvider::new(backend, client)) as _)
 		)?
-		.with_rpc_extensions(|client, pool, _backend| -> RpcExtension {
+		.with_rpc_extensions(|client, pool, _backend, _subscriptions| -> RpcExtension {
 			node_rpc::create(client, pool)
 		})?
 		.build()?;
@@ -931,6 +931,30 @@ mod tests {
 		client.import(BlockOrigin::Own, block).unwrap();
 	}
 
+	#[test]
+	fn authority_discovery_api_returns_genesis_session_keys() {
+		use authority_discovery_primitives::{AuthorityDiscoveryApi, AuthorityId};
+		use node_testing::client::{
+			TestClientBuilderExt, TestClientBuilder, Ed25519Keyring, Sr25519Keyring,
+		};
+		use sr_primitives::{generic::BlockId, traits::ProvideRuntimeApi};
+
+		let client = TestClientBuilder::new().build();
+		let mut authorities = client.runtime_api().authorities(&BlockId::number(0)).unwrap();
+		authorities.sort_by(|a, b| a.0.cmp(&b.0));
+
+		let mut expected = [
+			(Ed25519Keyring::Alice, Sr25519Keyring::Alice),
+			(Ed25519Keyring::Bob, Sr25519Keyring::Bob),
+			(Ed25519Keyring::Charlie, Sr25519Keyring::Charlie),
+		].iter()
+			.map(|(ed, sr)| AuthorityId(to_session_keys(ed, sr).authority_discovery.encode()))
+			.collect::<Vec<_>>();
+		expected.sort_by(|a, b| a.0.cmp(&b.0));
+
+		assert_eq!(authorities, expected);
+	}
+
 
 	#[test]
 	fn fee_multiplier_increases_and_decreases_on_big_weight() {
@@ -21,6 +21,7 @@
 #![recursion_limit="256"]
 
 use rstd::prelude::*;
+use codec::{Encode, Decode};
 use support::{
 	construct_runtime, parameter_types, traits::{SplitTwoWays, Currency, Randomness}
 };
@@ -48,6 +49,9 @@ use primitives::OpaqueMetadata;
 use grandpa::AuthorityList as GrandpaAuthorityList;
 use grandpa::fg_primitives;
 use im_online::sr25519::{AuthorityId as ImOnlineId};
+use authority_discovery_primitives::sr25519::{
+	AuthorityId as AuthorityDiscoveryId, AuthoritySignature as AuthorityDiscoverySignature,
+};
 use transaction_payment_rpc_runtime_api::RuntimeDispatchInfo;
 use contracts_rpc_runtime_api::ContractExecResult;
 use system::offchain::TransactionSubmitter;
@@ -81,8 +85,8 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	// and set impl_version to equal spec_version. If only runtime
 	// implementation changes and behavior does not, then leave spec_version as
 	// is and increment impl_version.
-	spec_version: 193,
-	impl_version: 193,
+	spec_version: 200,
+	impl_version: 200,
 	apis: RUNTIME_API_VERSIONS,
 };
 
@@ -144,6 +148,7 @@ impl babe::Trait for Runtime {
 	type EpochDuration = EpochDuration;
 	type ExpectedBlockTime = ExpectedBlockTime;
 	type EpochChangeTrigger = babe::ExternalTrigger;
+	type HandleEquivocation = babe::EquivocationHandler<Call, SubmitTransaction, Offences>;
 }
 
 impl indices::Trait for Runtime {
@@ -205,7 +210,10 @@ parameter_types! {
 impl authorship::Trait for Runtime {
 	type FindAuthor = session::FindAccountFromAuthorIndex<Self, Babe>;
 	type UncleGenerations = UncleGenerations;
-	type FilterUncle = ();
+	type FilterUncle = authorship::OnePerAuthorPerHeight<
+		session::VerifySealFromAuthorIndex<Self, Babe>,
+		BlockNumber,
+	>;
 	type EventHandler = (Staking, ImOnline);
 }
 
@@ -214,6 +222,21 @@ impl_opaque_keys! {
 		pub grandpa: Grandpa,
 		pub babe: Babe,
 		pub im_online: ImOnline,
+		pub authority_discovery: AuthorityDiscovery,
+	}
+}
+
+/// Whether the given session key is used in the current session by the module it is for.
+fn is_current_session_key(key_type: sr_primitives::KeyTypeId, public: &[u8]) -> bool {
+	use sr_primitives::key_types;
+
+	match key_type {
+		key_types::GRANDPA => Grandpa::grandpa_authorities().iter().any(|(id, _)| id.as_ref() == public),
+		key_types::BABE => Babe::authorities().iter().any(|(id, _)| id.as_ref() == public),
+		key_types::IM_ONLINE => ImOnline::keys().iter().any(|id| id.as_ref() == public),
+		key_types::AUTHORITY_DISCOVERY =>
+			AuthorityDiscovery::authorities().iter().any(|id| id.as_ref() == public),
+		_ => false,
 	}
 }
 
@@ -432,6 +455,10 @@ impl im_online::Trait for Runtime {
 	type SessionDuration = SessionDuration;
 }
 
+impl authority_discovery::Trait for Runtime {
+	type AuthorityId = AuthorityDiscoveryId;
+}
+
 impl offences::Trait for Runtime {
 	type Event = Event;
 	type IdentificationTuple = session::historical::IdentificationTuple<Self>;
@@ -440,6 +467,7 @@ impl offences::Trait for Runtime {
 
 impl grandpa::Trait for Runtime {
 	type Event = Event;
+	type HandleEquivocation = grandpa::EquivocationHandler<Call, SubmitTransaction, Offences>;
 }
 
 parameter_types! {
@@ -507,7 +535,7 @@ construct_runtime!(
 	{
 		System: system::{Module, Call, Storage, Config, Event},
 		Utility: utility::{Module, Call, Event},
-		Babe: babe::{Module, Call, Storage, Config, Inherent(Timestamp)},
+		Babe: babe::{Module, Call, Storage, Config, Inherent(Timestamp), ValidateUnsigned},
 		Timestamp: timestamp::{Module, Call, Storage, Inherent},
 		Authorship: authorship::{Module, Call, Storage, Inherent},
 		Indices: indices,
@@ -521,11 +549,12 @@ construct_runtime!(
 		Elections: elections_phragmen::{Module, Call, Storage, Event<T>},
 		TechnicalMembership: membership::<Instance1>::{Module, Call, Storage, Event<T>, Config<T>},
 		FinalityTracker: finality_tracker::{Module, Call, Inherent},
-		Grandpa: grandpa::{Module, Call, Storage, Config, Event},
+		Grandpa: grandpa::{Module, Call, Storage, Config, Event, ValidateUnsigned},
 		Treasury: treasury::{Module, Call, Storage, Config, Event<T>},
 		Contracts: contracts,
 		Sudo: sudo,
 		ImOnline: im_online::{Module, Call, Storage, Event<T>, ValidateUnsigned, Config<T>},
+		AuthorityDiscovery: authority_discovery::{Module, Call, Config<T>},
 		Offences: offences::{Module, Call, Storage, Event},
 		RandomnessCollectiveFlip: randomness_collective_flip::{Module, Call, Storage},
 		Nicks: nicks::{Module, Call, Storage, Event<T>},
@@ -636,10 +665,47 @@ impl_runtime_apis! {
 				genesis_authorities: Babe::authorities(),
 				randomness: Babe::randomness(),
 				secondary_slots: true,
+				// only read at genesis, so this only applies to chains started with it.
+				secondary_vrf_slots: true,
 			}
 		}
 	}
 
+	impl authority_discovery_primitives::AuthorityDiscoveryApi<Block> for Runtime {
+		fn authorities() -> Vec<authority_discovery_primitives::AuthorityId> {
+			AuthorityDiscovery::authorities().into_iter()
+				.map(|id| authority_discovery_primitives::AuthorityId(id.encode()))
+				.collect()
+		}
+
+		fn sign(payload: &Vec<u8>) -> Option<(
+			authority_discovery_primitives::Signature,
+			authority_discovery_primitives::AuthorityId,
+		)> {
+			AuthorityDiscovery::sign(payload).map(|(signature, id)| (
+				authority_discovery_primitives::Signature(signature.encode()),
+				authority_discovery_primitives::AuthorityId(id.encode()),
+			))
+		}
+
+		fn verify(
+			payload: &Vec<u8>,
+			signature: &authority_discovery_primitives::Signature,
+			authority_id: &authority_discovery_primitives::AuthorityId,
+		) -> bool {
+			let signature = match AuthorityDiscoverySignature::decode(&mut &signature.0[..]) {
+				Ok(signature) => signature,
+				Err(_) => return false,
+			};
+			let authority_id = match AuthorityDiscoveryId::decode(&mut &authority_id.0[..]) {
+				Ok(authority_id) => authority_id,
+				Err(_) => return false,
+			};
+
+			AuthorityDiscovery::verify(payload, signature, authority_id)
+		}
+	}
+
 	impl system_rpc_runtime_api::AccountNonceApi<Block, AccountId, Index> for Runtime {
 		fn account_nonce(account: AccountId) -> Index {
 			System::account_nonce(account)
@@ -703,6 +769,25 @@ impl_runtime_apis! {
 			SessionKeys::generate(seed)
 		}
 	}
+
+	impl substrate_session::SessionKeysOwnership<Block> for Runtime {
+		fn key_owners(
+			keys: Vec<(sr_primitives::KeyTypeId, Vec<u8>)>,
+		) -> Vec<Option<substrate_session::KeyOwnership>> {
+			let queued = Session::queued_keys();
+			keys.into_iter()
+				.map(|(key_type, public)| Session::key_owner(key_type, &public)
+					.map(|owner| substrate_session::KeyOwnership {
+						active: is_current_session_key(key_type, &public),
+						queued: queued.iter().any(|(validator, keys)|
+							*validator == owner && keys.get_raw(key_type) == &public[..]
+						),
+						owner: owner.encode(),
+					})
+				)
+				.collect()
+		}
+	}
 }
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,72 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Handling of Aura equivocation reports.
+//!
+//! Blocks authored by the same authority in the same slot are detected on import and queued
+//! in the offchain local storage. The offchain worker of this module picks them up, proves
+//! that the offender is a validator of the current session and submits both proofs as an
+//! unsigned `report_equivocation` transaction. Once included, the offence is reported
+//! through `ReportOffence` so that it can be slashed. The reporting itself is shared with the
+//! other consensus modules, see `session::equivocation`.
+//!
+//! Only equivocations of the current session can be reported, since key ownership can only
+//! be proven for the current session.
+
+use sr_staking_primitives::{SessionIndex, offence::Kind};
+use session::{equivocation::{ReportEquivocation, HandleEquivocation, EquivocationOffence}, historical};
+use substrate_consensus_aura_primitives::{EquivocationProof, EQUIVOCATIONS_KEY};
+
+use crate::{Call, Module, Trait};
+
+/// An equivocation proof for the given runtime.
+pub type EquivocationProofOf<T> = EquivocationProof<<T as system::Trait>::Header, <T as Trait>::AuthorityId>;
+
+/// The key ownership proof type used by the given runtime.
+pub type KeyOwnerProofOf<T> = <<T as Trait>::HandleEquivocation as HandleEquivocation<T, Module<T>>>::KeyOwnerProof;
+
+/// An Aura equivocation offence of the given runtime.
+pub type AuraEquivocationOffence<T> = EquivocationOffence<Module<T>, historical::IdentificationTuple<T>>;
+
+impl<T: Trait> ReportEquivocation for Module<T> {
+	const OFFENCE_ID: Kind = *b"aura:equivocatio";
+	const LOG_TARGET: &'static str = "aura";
+	const QUEUE_KEY: &'static [u8] = EQUIVOCATIONS_KEY;
+
+	type Key = T::AuthorityId;
+	type Proof = EquivocationProofOf<T>;
+	type TimeSlot = u64;
+	type Call = Call<T>;
+
+	fn offender(equivocation: &Self::Proof) -> &T::AuthorityId {
+		&equivocation.offender
+	}
+
+	fn time_slot(equivocation: &Self::Proof) -> u64 {
+		equivocation.slot_number
+	}
+
+	fn check(equivocation: &Self::Proof) -> bool {
+		equivocation.slot_number >= Self::session_start_slot() && equivocation.check()
+	}
+
+	fn key_owner_sessions(
+		_equivocation: &Self::Proof,
+		current: SessionIndex,
+	) -> Option<(SessionIndex, SessionIndex)> {
+		Some((current, current))
+	}
+}
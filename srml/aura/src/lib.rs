@@ -29,6 +29,11 @@
 //!
 //! - `slot_duration` - Determine the Aura slot-duration based on the Timestamp module configuration.
 //!
+//! ### Dispatchable Functions
+//!
+//! - `report_equivocation` - Report two blocks authored by the same authority in the same slot.
+//!   Submitted as an unsigned transaction by the offchain worker, see the `equivocation` module.
+//!
 //! ## Related Modules
 //!
 //! - [Timestamp](../srml_timestamp/index.html): The Timestamp module is used in Aura to track
@@ -50,13 +55,15 @@ pub use timestamp;
 use rstd::{result, prelude::*};
 use codec::{Encode, Decode};
 use support::{
-	decl_storage, decl_module, Parameter, traits::{Get, FindAuthor},
+	decl_storage, decl_module, Parameter, traits::{Get, FindAuthor, VerifySeal},
 	ConsensusEngineId,
 };
 use sr_primitives::{
 	RuntimeAppPublic,
 	traits::{SaturatedConversion, Saturating, Zero, Member, IsMember}, generic::DigestItem,
+	transaction_validity::{TransactionValidity, InvalidTransaction},
 };
+use system::ensure_none;
 use timestamp::OnTimestampSet;
 #[cfg(feature = "std")]
 use timestamp::TimestampInherentData;
@@ -65,9 +72,13 @@ use inherents::{RuntimeString, InherentIdentifier, InherentData, ProvideInherent
 use inherents::{InherentDataProviders, ProvideInherentData};
 use substrate_consensus_aura_primitives::{AURA_ENGINE_ID, ConsensusLog, AuthorityIndex};
 
+mod equivocation;
 mod mock;
 mod tests;
 
+pub use session::equivocation::{HandleEquivocation, EquivocationHandler};
+pub use self::equivocation::{EquivocationProofOf, KeyOwnerProofOf, AuraEquivocationOffence};
+
 /// The Aura inherent identifier.
 pub const INHERENT_IDENTIFIER: InherentIdentifier = *b"auraslot";
 
@@ -143,6 +154,11 @@ impl ProvideInherentData for InherentDataProvider {
 pub trait Trait: timestamp::Trait {
 	/// The identifier type for an authority.
 	type AuthorityId: Member + Parameter + RuntimeAppPublic + Default;
+
+	/// Verifies and reports slot equivocations of Aura authorities.
+	///
+	/// Use `()` to ignore equivocations, or `EquivocationHandler` to report them as offences.
+	type HandleEquivocation: HandleEquivocation<Self, Module<Self>>;
 }
 
 decl_storage! {
@@ -152,6 +168,10 @@ decl_storage! {
 
 		/// The current authorities
 		pub Authorities get(fn authorities): Vec<T::AuthorityId>;
+
+		/// The first slot of the current session. Only equivocations from this slot onwards
+		/// can be reported.
+		SessionStartSlot get(fn session_start_slot): u64;
 	}
 	add_extra_genesis {
 		config(authorities): Vec<T::AuthorityId>;
@@ -160,7 +180,29 @@ decl_storage! {
 }
 
 decl_module! {
-	pub struct Module<T: Trait> for enum Call where origin: T::Origin { }
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		/// Report two blocks authored by the same authority in the same slot, along with the
+		/// proof that the offender's key belongs to a validator of the current session.
+		///
+		/// This is submitted as an unsigned transaction by the offchain worker, the proofs are
+		/// checked by `validate_unsigned` before the transaction enters the pool.
+		fn report_equivocation(
+			origin,
+			equivocation: EquivocationProofOf<T>,
+			key_owner_proof: KeyOwnerProofOf<T>
+		) {
+			ensure_none(origin)?;
+
+			session::equivocation::report::<T, Self, T::HandleEquivocation>(equivocation, key_owner_proof)?;
+		}
+
+		// Runs after every block.
+		fn offchain_worker(_now: T::BlockNumber) {
+			session::equivocation::submit_queued_reports::<T, Self, T::HandleEquivocation>(
+				Call::report_equivocation
+			);
+		}
+	}
 }
 
 impl<T: Trait> Module<T> {
@@ -199,6 +241,14 @@ impl<T: Trait> session::OneSessionHandler<T::AccountId> for Module<T> {
 	fn on_new_session<'a, I: 'a>(changed: bool, validators: I, _queued_validators: I)
 		where I: Iterator<Item=(&'a T::AccountId, T::AuthorityId)>
 	{
+		// the session changes before the timestamp of this block is set, so it starts
+		// right after the slot of the previous block.
+		let slot_duration = Self::slot_duration();
+		if !slot_duration.is_zero() {
+			let last_slot = (Self::last() / slot_duration).saturated_into::<u64>();
+			SessionStartSlot::put(last_slot + 1);
+		}
+
 		// instant changes
 		if changed {
 			let next_authorities = validators.map(|(_, k)| k).collect::<Vec<_>>();
@@ -236,6 +286,32 @@ impl<T: Trait> FindAuthor<u32> for Module<T> {
 	}
 }
 
+/// Verifies that a header, typically an uncle, is sealed by the authority expected to author
+/// in its slot among the current authorities, and returns the index of that authority.
+///
+/// Headers of slots before the current session are rejected, as their authorities are no
+/// longer known, and so are headers of slots after the current one.
+impl<T: Trait> VerifySeal<T::Header, u32> for Module<T> {
+	fn verify_seal(header: &T::Header) -> result::Result<Option<u32>, &'static str> {
+		let (slot_number, author) = substrate_consensus_aura_primitives::check_header_seal(
+			header.clone(),
+			&Self::authorities(),
+		).ok_or("header is not sealed by the Aura authority of its slot")?;
+
+		let slot_duration = Self::slot_duration();
+		let current_slot = if slot_duration.is_zero() {
+			0
+		} else {
+			(Self::last() / slot_duration).saturated_into::<u64>()
+		};
+		if slot_number < Self::session_start_slot() || slot_number > current_slot {
+			return Err("header slot is not in the current session");
+		}
+
+		Ok(Some(author))
+	}
+}
+
 impl<T: Trait> IsMember<T::AuthorityId> for Module<T> {
 	fn is_member(authority_id: &T::AuthorityId) -> bool {
 		Self::authorities()
@@ -271,6 +347,22 @@ impl<T: Trait> Module<T> {
 	}
 }
 
+#[allow(deprecated)]
+impl<T: Trait> support::unsigned::ValidateUnsigned for Module<T> {
+	type Call = Call<T>;
+
+	fn validate_unsigned(call: &Self::Call) -> TransactionValidity {
+		if let Call::report_equivocation(equivocation, key_owner_proof) = call {
+			session::equivocation::validate_report::<T, Self, T::HandleEquivocation>(
+				equivocation,
+				key_owner_proof,
+			)
+		} else {
+			InvalidTransaction::Call.into()
+		}
+	}
+}
+
 impl<T: Trait> OnTimestampSet<T::Moment> for Module<T> {
 	fn on_timestamp_set(moment: T::Moment) {
 		Self::on_timestamp_set(moment, Self::slot_duration())
@@ -18,15 +18,17 @@
 
 #![cfg(test)]
 
-use crate::{Trait, Module, GenesisConfig};
-use substrate_consensus_aura_primitives::ed25519::AuthorityId;
+use std::cell::RefCell;
+use crate::{Trait, Module, GenesisConfig, Call, EquivocationHandler};
+use substrate_consensus_aura_primitives::ed25519::{AuthorityId, AuthorityPair};
 use sr_primitives::{
-	traits::IdentityLookup, Perbill,
-	testing::{Header, UintAuthorityId},
+	traits::{IdentityLookup, ConvertInto}, Perbill, impl_opaque_keys,
+	testing::{Header, UintAuthorityId, TestXt},
 };
-use support::{impl_outer_origin, parameter_types};
+use sr_staking_primitives::{SessionIndex, offence::{OnOffenceHandler, OffenceDetails}};
+use support::{impl_outer_origin, impl_outer_event, parameter_types};
 use runtime_io;
-use primitives::H256;
+use primitives::{H256, Pair};
 
 impl_outer_origin!{
 	pub enum Origin for Test {}
@@ -54,7 +56,7 @@ impl system::Trait for Test {
 	type AccountId = u64;
 	type Lookup = IdentityLookup<Self::AccountId>;
 	type Header = Header;
-	type Event = ();
+	type Event = TestEvent;
 	type BlockHashCount = BlockHashCount;
 	type MaximumBlockWeight = MaximumBlockWeight;
 	type AvailableBlockRatio = AvailableBlockRatio;
@@ -70,6 +72,81 @@ impl timestamp::Trait for Test {
 
 impl Trait for Test {
 	type AuthorityId = AuthorityId;
+	type HandleEquivocation = EquivocationHandler<Call<Test>, SubmitTransaction, Offences>;
+}
+
+impl_opaque_keys! {
+	pub struct TestSessionKeys {
+		pub aura: Aura,
+	}
+}
+
+thread_local! {
+	pub static SLASHES: RefCell<Vec<(Vec<u64>, Perbill)>> = RefCell::new(Vec::new());
+}
+
+pub struct TestOnSessionEnding;
+impl session::OnSessionEnding<u64> for TestOnSessionEnding {
+	fn on_session_ending(_ending_index: SessionIndex, _will_apply_at: SessionIndex) -> Option<Vec<u64>> {
+		None
+	}
+}
+
+impl session::historical::OnSessionEnding<u64, u64> for TestOnSessionEnding {
+	fn on_session_ending(_ending_index: SessionIndex, _will_apply_at: SessionIndex)
+		-> Option<(Vec<u64>, Vec<(u64, u64)>)>
+	{
+		None
+	}
+}
+
+parameter_types! {
+	pub const Period: u64 = 1;
+	pub const Offset: u64 = 0;
+	pub const DisabledValidatorsThreshold: Perbill = Perbill::from_percent(33);
+}
+
+impl session::Trait for Test {
+	type ShouldEndSession = session::PeriodicSessions<Period, Offset>;
+	type OnSessionEnding = session::historical::NoteHistoricalRoot<Test, TestOnSessionEnding>;
+	type SessionHandler = (Aura,);
+	type ValidatorId = u64;
+	type ValidatorIdOf = ConvertInto;
+	type Keys = TestSessionKeys;
+	type Event = ();
+	type SelectInitialValidators = ();
+	type DisabledValidatorsThreshold = DisabledValidatorsThreshold;
+}
+
+impl session::historical::Trait for Test {
+	type FullIdentification = u64;
+	type FullIdentificationOf = ConvertInto;
+}
+
+/// Records the slash fractions applied to offenders.
+pub struct TestOnOffenceHandler;
+impl OnOffenceHandler<u64, (u64, u64)> for TestOnOffenceHandler {
+	fn on_offence(offenders: &[OffenceDetails<u64, (u64, u64)>], slash_fraction: &[Perbill]) {
+		SLASHES.with(|s| s.borrow_mut().extend(
+			offenders.iter().zip(slash_fraction).map(|(details, fraction)| {
+				(vec![details.offender.0], *fraction)
+			})
+		));
+	}
+}
+
+impl offences::Trait for Test {
+	type Event = TestEvent;
+	type IdentificationTuple = (u64, u64);
+	type OnOffenceHandler = TestOnOffenceHandler;
+}
+
+type SubmitTransaction = system::offchain::TransactionSubmitter<(), Call<Test>, TestXt<Call<Test>, ()>>;
+
+impl_outer_event!{
+	pub enum TestEvent for Test {
+		offences,
+	}
 }
 
 pub fn new_test_ext(authorities: Vec<u64>) -> runtime_io::TestExternalities {
@@ -80,4 +157,31 @@ pub fn new_test_ext(authorities: Vec<u64>) -> runtime_io::TestExternalities {
 	t.into()
 }
 
+/// The Aura key pair of the given validator.
+pub fn validator_pair(validator: u64) -> AuthorityPair {
+	AuthorityPair::from_seed(&[validator as u8; 32])
+}
+
+/// Build externalities with a session module whose genesis validators are the given accounts,
+/// each with its own Aura key pair.
+pub fn new_test_ext_with_session(validators: Vec<u64>) -> runtime_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	session::GenesisConfig::<Test> {
+		keys: validators.into_iter()
+			.map(|v| (v, TestSessionKeys { aura: validator_pair(v).public() }))
+			.collect(),
+	}.assimilate_storage(&mut t).unwrap();
+	t.into()
+}
+
+/// Move to the next session, keeping the validator set.
+pub fn advance_session() {
+	System::set_block_number(System::block_number() + 1);
+	Session::rotate_session();
+}
+
+pub type System = system::Module<Test>;
 pub type Aura = Module<Test>;
+pub type Session = session::Module<Test>;
+pub type Historical = session::historical::Module<Test>;
+pub type Offences = offences::Module<Test>;
@@ -18,7 +18,15 @@
 
 #![cfg(test)]
 
-use crate::mock::{Aura, new_test_ext};
+use codec::Encode;
+use primitives::{H256, Pair};
+use sr_primitives::{Perbill, testing::{Digest, DigestItem, Header}, traits::Header as _};
+use support::{assert_ok, assert_noop};
+use substrate_consensus_aura_primitives::{AURA_ENGINE_ID, EquivocationProof};
+use crate::{Call, EquivocationProofOf, KeyOwnerProofOf};
+use crate::mock::{
+	Aura, Historical, Test, SLASHES, new_test_ext, new_test_ext_with_session, validator_pair, advance_session,
+};
 
 #[test]
 fn initial_values() {
@@ -27,3 +35,147 @@ fn initial_values() {
 		assert_eq!(Aura::authorities().len(), 4);
 	});
 }
+
+#[test]
+fn equivocation_proofs_are_checked() {
+	use substrate_consensus_aura_primitives::ed25519::AuthorityPair;
+
+	let offender = AuthorityPair::from_seed(&[1; 32]);
+	let header = |slot_number: u64, parent| {
+		let digest = Digest { logs: vec![DigestItem::PreRuntime(AURA_ENGINE_ID, slot_number.encode())] };
+		let mut header = Header::new(1, Default::default(), Default::default(), H256::repeat_byte(parent), digest);
+		let signature = offender.sign(header.hash().as_ref());
+		header.digest_mut().push(DigestItem::Seal(AURA_ENGINE_ID, signature.encode()));
+		header
+	};
+	let equivocation = |slot_number, first, second| EquivocationProof {
+		offender: offender.public(),
+		slot_number,
+		first_header: first,
+		second_header: second,
+	};
+
+	new_test_ext(vec![0, 1, 2, 3]).execute_with(|| {
+		assert!(equivocation(5, header(5, 1), header(5, 2)).check());
+
+		// the same block twice isn't an equivocation.
+		assert!(!equivocation(5, header(5, 1), header(5, 1)).check());
+
+		// both blocks must claim the slot of the proof.
+		assert!(!equivocation(5, header(5, 1), header(6, 2)).check());
+
+		// and be sealed by the offender.
+		let proof = EquivocationProof {
+			offender: AuthorityPair::from_seed(&[2; 32]).public(),
+			..equivocation(5, header(5, 1), header(5, 2))
+		};
+		assert!(!proof.check());
+	});
+}
+
+/// A header of block 1 at the given slot, sealed by the given validator.
+fn sealed_header(validator: u64, slot_number: u64, parent: u8) -> Header {
+	let digest = Digest { logs: vec![DigestItem::PreRuntime(AURA_ENGINE_ID, slot_number.encode())] };
+	let mut header = Header::new(1, Default::default(), Default::default(), H256::repeat_byte(parent), digest);
+	let signature = validator_pair(validator).sign(header.hash().as_ref());
+	header.digest_mut().push(DigestItem::Seal(AURA_ENGINE_ID, signature.encode()));
+	header
+}
+
+fn equivocation_proof(offender: u64, slot_number: u64) -> EquivocationProofOf<Test> {
+	EquivocationProof {
+		offender: validator_pair(offender).public(),
+		slot_number,
+		first_header: sealed_header(offender, slot_number, 1),
+		second_header: sealed_header(offender, slot_number, 2),
+	}
+}
+
+fn key_owner_proof(validator: u64) -> KeyOwnerProofOf<Test> {
+	use support::traits::KeyOwnerProofSystem;
+
+	let key = validator_pair(validator).public();
+	Historical::prove((primitives::crypto::key_types::AURA, key)).unwrap()
+}
+
+#[test]
+fn equivocation_reports_are_slashed() {
+	use sr_primitives::transaction_validity::{InvalidTransaction, TransactionValidityError};
+	use support::unsigned::ValidateUnsigned;
+
+	new_test_ext_with_session(vec![1, 2, 3, 4]).execute_with(|| {
+		let call = Call::report_equivocation(equivocation_proof(1, 5), key_owner_proof(1));
+		assert!(Aura::validate_unsigned(&call).is_ok());
+
+		// the key ownership proof must be for the offender.
+		let call = Call::report_equivocation(equivocation_proof(1, 5), key_owner_proof(2));
+		assert_eq!(
+			Aura::validate_unsigned(&call),
+			Err(TransactionValidityError::from(InvalidTransaction::BadProof)),
+		);
+
+		assert_ok!(Aura::report_equivocation(
+			system::RawOrigin::None.into(),
+			equivocation_proof(1, 5),
+			key_owner_proof(1),
+		));
+
+		// (3 * 1 / 4)^2 of the stake of the offender is slashed.
+		assert_eq!(
+			SLASHES.with(|s| s.borrow().clone()),
+			vec![(vec![1], Perbill::from_parts(562_500_000))],
+		);
+	});
+}
+
+#[test]
+fn only_equivocations_of_the_current_session_can_be_reported() {
+	new_test_ext_with_session(vec![1, 2, 3, 4]).execute_with(|| {
+		let proof_of_previous_session = key_owner_proof(1);
+		advance_session();
+		assert_eq!(Aura::session_start_slot(), 1);
+
+		let report = |slot_number, proof| Aura::report_equivocation(
+			system::RawOrigin::None.into(),
+			equivocation_proof(1, slot_number),
+			proof,
+		);
+
+		assert_noop!(report(0, key_owner_proof(1)), "Invalid equivocation proof.");
+		assert_noop!(report(5, proof_of_previous_session), "Invalid key ownership proof.");
+		assert_ok!(report(5, key_owner_proof(1)));
+	});
+}
+
+#[test]
+fn uncles_must_be_sealed_by_the_authority_of_their_slot() {
+	use session::VerifySealFromAuthorIndex;
+	use support::traits::VerifySeal;
+
+	new_test_ext_with_session(vec![1, 2, 3, 4]).execute_with(|| {
+		// the current slot is 5.
+		Aura::on_timestamp_set(10, Aura::slot_duration());
+
+		// slot 5 belongs to the second authority, the key of validator 2.
+		let uncle = sealed_header(2, 5, 1);
+		assert_eq!(Aura::verify_seal(&uncle), Ok(Some(1)));
+		assert_eq!(VerifySealFromAuthorIndex::<Test, Aura>::verify_seal(&uncle), Ok(Some(2)));
+		assert_eq!(Aura::verify_seal(&sealed_header(1, 4, 1)), Ok(Some(0)));
+
+		// headers sealed by another authority than the one of their slot are rejected.
+		assert!(Aura::verify_seal(&sealed_header(1, 5, 1)).is_err());
+
+		// and so are unsealed headers.
+		let mut unsealed = sealed_header(2, 5, 1);
+		unsealed.digest_mut().pop();
+		assert!(Aura::verify_seal(&unsealed).is_err());
+
+		// and headers of future slots.
+		assert!(Aura::verify_seal(&sealed_header(2, 9, 1)).is_err());
+
+		// and headers of previous sessions.
+		advance_session();
+		assert_eq!(Aura::session_start_slot(), 6);
+		assert!(Aura::verify_seal(&sealed_header(1, 4, 1)).is_err());
+	});
+}
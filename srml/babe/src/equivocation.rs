@@ -0,0 +1,78 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Handling of BABE equivocation reports.
+//!
+//! Blocks authored by the same authority in the same slot are detected on import and queued
+//! in the offchain local storage. The offchain worker of this module picks them up, proves
+//! that the offender is a validator of the current session and submits both proofs as an
+//! unsigned `report_equivocation` transaction. Once included, the offence is reported
+//! through `ReportOffence` so that it can be slashed. The reporting itself is shared with the
+//! other consensus modules, see `session::equivocation`.
+//!
+//! Only equivocations of the current epoch can be reported, since epochs and sessions
+//! are coupled and key ownership can only be proven for the current session.
+
+use sr_staking_primitives::{SessionIndex, offence::Kind};
+use support::traits::Get;
+use session::{equivocation::{ReportEquivocation, HandleEquivocation, EquivocationOffence}, historical};
+use babe_primitives::{EquivocationProof, SlotNumber, EQUIVOCATIONS_KEY};
+
+use crate::{AuthorityId, Call, Module, Trait};
+
+/// An equivocation proof for the given runtime.
+pub type EquivocationProofOf<T> = EquivocationProof<<T as system::Trait>::Header>;
+
+/// The key ownership proof type used by the given runtime.
+pub type KeyOwnerProofOf<T> = <<T as Trait>::HandleEquivocation as HandleEquivocation<T, Module<T>>>::KeyOwnerProof;
+
+/// A BABE equivocation offence of the given runtime.
+pub type BabeEquivocationOffence<T> = EquivocationOffence<Module<T>, historical::IdentificationTuple<T>>;
+
+impl<T: Trait> ReportEquivocation for Module<T> {
+	const OFFENCE_ID: Kind = *b"babe:equivocatio";
+	const LOG_TARGET: &'static str = "babe";
+	const QUEUE_KEY: &'static [u8] = EQUIVOCATIONS_KEY;
+
+	type Key = AuthorityId;
+	type Proof = EquivocationProofOf<T>;
+	type TimeSlot = SlotNumber;
+	type Call = Call<T>;
+
+	fn offender(equivocation: &Self::Proof) -> &AuthorityId {
+		&equivocation.offender
+	}
+
+	fn time_slot(equivocation: &Self::Proof) -> SlotNumber {
+		equivocation.slot_number
+	}
+
+	fn check(equivocation: &Self::Proof) -> bool {
+		let epoch_start = Self::current_epoch_start();
+		let in_current_epoch = equivocation.slot_number >= epoch_start &&
+			equivocation.slot_number < epoch_start + T::EpochDuration::get();
+
+		in_current_epoch && equivocation.check()
+	}
+
+	fn key_owner_sessions(
+		_equivocation: &Self::Proof,
+		current: SessionIndex,
+	) -> Option<(SessionIndex, SessionIndex)> {
+		// the current epoch is the current session.
+		Some((current, current))
+	}
+}
@@ -23,14 +23,12 @@
 pub use timestamp;
 
 use rstd::{result, prelude::*};
-use support::{decl_storage, decl_module, traits::FindAuthor, traits::Get};
+use support::{decl_storage, decl_module, traits::{FindAuthor, Get, VerifySeal}};
 use timestamp::OnTimestampSet;
-use sr_primitives::{generic::DigestItem, ConsensusEngineId, Perbill};
+use sr_primitives::{generic::DigestItem, ConsensusEngineId};
 use sr_primitives::traits::{IsMember, SaturatedConversion, Saturating, RandomnessBeacon};
-use sr_staking_primitives::{
-	SessionIndex,
-	offence::{Offence, Kind},
-};
+use sr_primitives::transaction_validity::{TransactionValidity, InvalidTransaction};
+use system::ensure_none;
 #[cfg(feature = "std")]
 use timestamp::TimestampInherentData;
 use codec::{Encode, Decode};
@@ -43,12 +41,17 @@ use babe_primitives::{
 };
 pub use babe_primitives::{AuthorityId, VRF_OUTPUT_LENGTH, PUBLIC_KEY_LENGTH};
 
+mod equivocation;
+
 #[cfg(all(feature = "std", test))]
 mod tests;
 
 #[cfg(all(feature = "std", test))]
 mod mock;
 
+pub use session::equivocation::{HandleEquivocation, EquivocationHandler};
+pub use self::equivocation::{EquivocationProofOf, KeyOwnerProofOf, BabeEquivocationOffence};
+
 /// The BABE inherent identifier.
 pub const INHERENT_IDENTIFIER: InherentIdentifier = *b"babeslot";
 
@@ -138,6 +141,11 @@ pub trait Trait: timestamp::Trait {
 	/// Typically, the `ExternalTrigger` type should be used. An internal trigger should only be used
 	/// when no other module is responsible for changing authority set.
 	type EpochChangeTrigger: EpochChangeTrigger;
+
+	/// Verifies and reports slot equivocations of BABE authorities.
+	///
+	/// Use `()` to ignore equivocations, or `EquivocationHandler` to report them as offences.
+	type HandleEquivocation: HandleEquivocation<Self, Module<Self>>;
 }
 
 /// Trigger an epoch change, if any should take place.
@@ -262,6 +270,28 @@ decl_module! {
 				Self::deposit_vrf_output(&vrf_output);
 			}
 		}
+
+		/// Report two blocks authored by the same authority in the same slot, along with the
+		/// proof that the offender's key belongs to a validator of the current session.
+		///
+		/// This is submitted as an unsigned transaction by the offchain worker, the proofs are
+		/// checked by `validate_unsigned` before the transaction enters the pool.
+		fn report_equivocation(
+			origin,
+			equivocation: EquivocationProofOf<T>,
+			key_owner_proof: KeyOwnerProofOf<T>
+		) {
+			ensure_none(origin)?;
+
+			session::equivocation::report::<T, Self, T::HandleEquivocation>(equivocation, key_owner_proof)?;
+		}
+
+		// Runs after every block.
+		fn offchain_worker(_now: T::BlockNumber) {
+			session::equivocation::submit_queued_reports::<T, Self, T::HandleEquivocation>(
+				Call::report_equivocation
+			);
+		}
 	}
 }
 
@@ -286,6 +316,8 @@ impl<T: Trait> FindAuthor<u32> for Module<T> {
 						authority_index,
 					RawBabePreDigest::Secondary { authority_index, .. } =>
 						authority_index,
+					RawBabePreDigest::SecondaryVRF { authority_index, .. } =>
+						authority_index,
 				});
 			}
 		}
@@ -294,6 +326,24 @@ impl<T: Trait> FindAuthor<u32> for Module<T> {
 	}
 }
 
+/// Verifies that a header, typically an uncle, is sealed by an authority of the current epoch
+/// in a slot of the current epoch, and returns the index of that authority.
+///
+/// Headers of previous epochs are rejected, as their authorities are no longer known.
+impl<T: Trait> VerifySeal<T::Header, u32> for Module<T> {
+	fn verify_seal(header: &T::Header) -> result::Result<Option<u32>, &'static str> {
+		let pre_digest = babe_primitives::check_header_seal(header.clone(), &Self::authorities())
+			.ok_or("header is not sealed by a BABE authority of the current epoch")?;
+
+		let slot_number = pre_digest.slot_number();
+		if slot_number < Self::current_epoch_start() || slot_number > CurrentSlot::get() {
+			return Err("header slot is not in the current epoch");
+		}
+
+		Ok(Some(pre_digest.authority_index()))
+	}
+}
+
 impl<T: Trait> IsMember<AuthorityId> for Module<T> {
 	fn is_member(authority_id: &AuthorityId) -> bool {
 		<Module<T>>::authorities()
@@ -314,53 +364,6 @@ impl<T: Trait> session::ShouldEndSession<T::BlockNumber> for Module<T> {
 	}
 }
 
-// TODO [slashing]: @marcio use this, remove the dead_code annotation.
-/// A BABE equivocation offence report.
-///
-/// When a validator released two or more blocks at the same slot.
-#[allow(dead_code)]
-struct BabeEquivocationOffence<FullIdentification> {
-	/// A babe slot number in which this incident happened.
-	slot: u64,
-	/// The session index in which the incident happened.
-	session_index: SessionIndex,
-	/// The size of the validator set at the time of the offence.
-	validator_set_count: u32,
-	/// The authority that produced the equivocation.
-	offender: FullIdentification,
-}
-
-impl<FullIdentification: Clone> Offence<FullIdentification> for BabeEquivocationOffence<FullIdentification> {
-	const ID: Kind = *b"babe:equivocatio";
-	type TimeSlot = u64;
-
-	fn offenders(&self) -> Vec<FullIdentification> {
-		vec![self.offender.clone()]
-	}
-
-	fn session_index(&self) -> SessionIndex {
-		self.session_index
-	}
-
-	fn validator_set_count(&self) -> u32 {
-		self.validator_set_count
-	}
-
-	fn time_slot(&self) -> Self::TimeSlot {
-		self.slot
-	}
-
-	fn slash_fraction(
-		offenders_count: u32,
-		validator_set_count: u32,
-	) -> Perbill {
-		// the formula is min((3k / n)^2, 1)
-		let x = Perbill::from_rational_approximation(3 * offenders_count, validator_set_count);
-		// _ ^ 2
-		x.square()
-	}
-}
-
 impl<T: Trait> Module<T> {
 	/// Determine the BABE slot duration based on the Timestamp module configuration.
 	pub fn slot_duration() -> T::Moment {
@@ -499,14 +502,10 @@ impl<T: Trait> Module<T> {
 
 			CurrentSlot::put(digest.slot_number());
 
-			if let RawBabePreDigest::Primary { vrf_output, .. } = digest {
-				// place the VRF output into the `Initialized` storage item
-				// and it'll be put onto the under-construction randomness
-				// later, once we've decided which epoch this block is in.
-				Some(vrf_output)
-			} else {
-				None
-			}
+			// place the VRF output of primary and secondary VRF slots into the
+			// `Initialized` storage item and it'll be put onto the under-construction
+			// randomness later, once we've decided which epoch this block is in.
+			digest.vrf_output().cloned()
 		});
 
 		Initialized::put(maybe_vrf);
@@ -542,6 +541,22 @@ impl<T: Trait> Module<T> {
 	}
 }
 
+#[allow(deprecated)]
+impl<T: Trait> support::unsigned::ValidateUnsigned for Module<T> {
+	type Call = Call<T>;
+
+	fn validate_unsigned(call: &Self::Call) -> TransactionValidity {
+		if let Call::report_equivocation(equivocation, key_owner_proof) = call {
+			session::equivocation::validate_report::<T, Self, T::HandleEquivocation>(
+				equivocation,
+				key_owner_proof,
+			)
+		} else {
+			InvalidTransaction::Call.into()
+		}
+	}
+}
+
 impl<T: Trait> OnTimestampSet<T::Moment> for Module<T> {
 	fn on_timestamp_set(_moment: T::Moment) { }
 }
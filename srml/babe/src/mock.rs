@@ -17,15 +17,17 @@
 //! Test utilities
 #![allow(dead_code, unused_imports)]
 
-use super::{Trait, Module, GenesisConfig};
-use babe_primitives::AuthorityId;
+use std::cell::RefCell;
+use super::{Trait, Module, GenesisConfig, Call, EquivocationHandler};
+use babe_primitives::{AuthorityId, AuthorityPair};
 use sr_primitives::{
-	traits::IdentityLookup, Perbill, testing::{Header, UintAuthorityId}, impl_opaque_keys,
+	traits::{IdentityLookup, ConvertInto}, Perbill, testing::{Header, UintAuthorityId, TestXt}, impl_opaque_keys,
 };
+use sr_staking_primitives::offence::{OnOffenceHandler, OffenceDetails};
 use sr_version::RuntimeVersion;
-use support::{impl_outer_origin, parameter_types};
+use support::{impl_outer_origin, impl_outer_event, parameter_types};
 use runtime_io;
-use primitives::{H256, Blake2Hasher};
+use primitives::{H256, Blake2Hasher, Pair};
 
 impl_outer_origin!{
 	pub enum Origin for Test {}
@@ -60,16 +62,22 @@ impl system::Trait for Test {
 	type AccountId = DummyValidatorId;
 	type Lookup = IdentityLookup<Self::AccountId>;
 	type Header = Header;
-	type Event = ();
+	type Event = TestEvent;
 	type BlockHashCount = BlockHashCount;
 	type MaximumBlockWeight = MaximumBlockWeight;
 	type AvailableBlockRatio = AvailableBlockRatio;
 	type MaximumBlockLength = MaximumBlockLength;
 }
 
+impl_outer_event! {
+	pub enum TestEvent for Test {
+		offences,
+	}
+}
+
 impl_opaque_keys! {
 	pub struct MockSessionKeys {
-		pub dummy: UintAuthorityId,
+		pub babe: Babe,
 	}
 }
 
@@ -77,14 +85,43 @@ impl session::Trait for Test {
 	type Event = ();
 	type ValidatorId = <Self as system::Trait>::AccountId;
 	type ShouldEndSession = Babe;
-	type SessionHandler = (Babe,Babe,);
+	type SessionHandler = (Babe,);
 	type OnSessionEnding = ();
-	type ValidatorIdOf = ();
+	type ValidatorIdOf = ConvertInto;
 	type SelectInitialValidators = ();
 	type Keys = MockSessionKeys;
 	type DisabledValidatorsThreshold = DisabledValidatorsThreshold;
 }
 
+impl session::historical::Trait for Test {
+	type FullIdentification = u64;
+	type FullIdentificationOf = ConvertInto;
+}
+
+thread_local! {
+	pub static SLASHES: RefCell<Vec<(Vec<u64>, Perbill)>> = RefCell::new(Vec::new());
+}
+
+/// Records the slash fractions applied to offenders.
+pub struct TestOnOffenceHandler;
+impl OnOffenceHandler<u64, (u64, u64)> for TestOnOffenceHandler {
+	fn on_offence(offenders: &[OffenceDetails<u64, (u64, u64)>], slash_fraction: &[Perbill]) {
+		SLASHES.with(|s| s.borrow_mut().extend(
+			offenders.iter().zip(slash_fraction).map(|(details, fraction)| {
+				(vec![details.offender.0], *fraction)
+			})
+		));
+	}
+}
+
+impl offences::Trait for Test {
+	type Event = TestEvent;
+	type IdentificationTuple = (u64, u64);
+	type OnOffenceHandler = TestOnOffenceHandler;
+}
+
+type SubmitTransaction = system::offchain::TransactionSubmitter<(), Call<Test>, TestXt<Call<Test>, ()>>;
+
 impl timestamp::Trait for Test {
 	type Moment = u64;
 	type OnTimestampSet = Babe;
@@ -95,6 +132,7 @@ impl Trait for Test {
 	type EpochDuration = EpochDuration;
 	type ExpectedBlockTime = ExpectedBlockTime;
 	type EpochChangeTrigger = crate::ExternalTrigger;
+	type HandleEquivocation = EquivocationHandler<Call<Test>, SubmitTransaction, Offences>;
 }
 
 pub fn new_test_ext(authorities: Vec<DummyValidatorId>) -> runtime_io::TestExternalities {
@@ -105,5 +143,24 @@ pub fn new_test_ext(authorities: Vec<DummyValidatorId>) -> runtime_io::TestExter
 	t.into()
 }
 
+/// The BABE key pair of the given validator.
+pub fn validator_pair(validator: u64) -> AuthorityPair {
+	AuthorityPair::from_seed(&[validator as u8; 32])
+}
+
+/// Build externalities with a session module whose genesis validators are the given accounts,
+/// each with its own BABE key pair.
+pub fn new_test_ext_with_session(validators: Vec<u64>) -> runtime_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	session::GenesisConfig::<Test> {
+		keys: validators.into_iter()
+			.map(|v| (v, MockSessionKeys { babe: validator_pair(v).public() }))
+			.collect(),
+	}.assimilate_storage(&mut t).unwrap();
+	t.into()
+}
+
 pub type System = system::Module<Test>;
 pub type Babe = Module<Test>;
+pub type Historical = session::historical::Module<Test>;
+pub type Offences = offences::Module<Test>;
@@ -17,8 +17,10 @@
 //! Consensus extension module tests for BABE consensus.
 
 use super::*;
-use mock::{new_test_ext, Babe, Test};
-use sr_primitives::{traits::OnFinalize, testing::{Digest, DigestItem}};
+use mock::{new_test_ext, new_test_ext_with_session, validator_pair, Babe, Historical, Test, SLASHES};
+use primitives::{H256, Pair};
+use sr_primitives::{Perbill, traits::{OnFinalize, Header as _}, testing::{Digest, DigestItem, Header}};
+use support::{assert_ok, assert_noop};
 use session::ShouldEndSession;
 
 const EMPTY_RANDOMNESS: [u8; 32] = [
@@ -116,6 +118,31 @@ fn first_block_epoch_zero_start() {
 	})
 }
 
+#[test]
+fn secondary_vrf_outputs_are_included_in_randomness() {
+	new_test_ext(vec![0, 1, 2, 3]).execute_with(|| {
+		let secondary_vrf = [2; 32];
+		let digest_data = babe_primitives::RawBabePreDigest::SecondaryVRF {
+			authority_index: 1,
+			slot_number: 100,
+			vrf_output: secondary_vrf,
+			vrf_proof: [0xff; 64],
+		};
+		let log = DigestItem::PreRuntime(BABE_ENGINE_ID, digest_data.encode());
+		let pre_digest = Digest { logs: vec![log] };
+
+		System::initialize(&1, &Default::default(), &Default::default(), &pre_digest);
+		Babe::on_finalize(1);
+		System::finalize();
+
+		assert_eq!(UnderConstruction::get(0), vec![secondary_vrf]);
+		assert_eq!(
+			Babe::find_author((&[(BABE_ENGINE_ID, &digest_data.encode()[..])]).into_iter().cloned()),
+			Some(1),
+		);
+	})
+}
+
 #[test]
 fn authority_index() {
 	new_test_ext(vec![0, 1, 2, 3]).execute_with(|| {
@@ -124,3 +151,149 @@ fn authority_index() {
 			"Trivially invalid authorities are ignored")
 	})
 }
+
+/// A header of block 1 at the given slot, authored by the given validator. The header is sealed
+/// by the validator's key when `sign` is set, and with a bogus seal otherwise.
+fn sealed_header(validator: u64, slot_number: u64, parent: u8, sign: bool) -> Header {
+	sealed_header_by_index(0, validator, slot_number, parent, sign)
+}
+
+/// Like `sealed_header`, with a pre-digest claiming the given authority index.
+fn sealed_header_by_index(
+	authority_index: babe_primitives::AuthorityIndex,
+	validator: u64,
+	slot_number: u64,
+	parent: u8,
+	sign: bool,
+) -> Header {
+	let digest = make_pre_digest(authority_index, slot_number, [1; 32], [0xff; 64]);
+	let mut header = Header::new(1, Default::default(), Default::default(), H256::repeat_byte(parent), digest);
+	let signature = if sign {
+		validator_pair(validator).sign(header.hash().as_ref()).encode()
+	} else {
+		vec![0; 64]
+	};
+	header.digest_mut().push(DigestItem::Seal(BABE_ENGINE_ID, signature));
+	header
+}
+
+fn equivocation_proof(offender: u64, slot_number: u64, first: Header, second: Header) -> EquivocationProofOf<Test> {
+	babe_primitives::EquivocationProof {
+		offender: validator_pair(offender).public(),
+		slot_number,
+		first_header: first,
+		second_header: second,
+	}
+}
+
+fn key_owner_proof(validator: u64) -> KeyOwnerProofOf<Test> {
+	use support::traits::KeyOwnerProofSystem;
+
+	let key = validator_pair(validator).public();
+	Historical::prove((primitives::crypto::key_types::BABE, key)).unwrap()
+}
+
+#[test]
+fn equivocation_reports_with_invalid_proofs_are_rejected() {
+	use sr_primitives::transaction_validity::TransactionValidityError;
+	use support::unsigned::ValidateUnsigned;
+
+	new_test_ext_with_session(vec![1, 2, 3]).execute_with(|| {
+		let report = |slot_number, first, second| Call::report_equivocation(
+			equivocation_proof(1, slot_number, first, second),
+			key_owner_proof(1),
+		);
+		let bad_proof = Err(TransactionValidityError::from(InvalidTransaction::BadProof));
+
+		// the same header twice isn't an equivocation.
+		let call = report(1, sealed_header(1, 1, 1, true), sealed_header(1, 1, 1, true));
+		assert_eq!(Babe::validate_unsigned(&call), bad_proof);
+
+		// headers that aren't sealed by the offender aren't either.
+		let call = report(1, sealed_header(1, 1, 1, false), sealed_header(1, 1, 2, false));
+		assert_eq!(Babe::validate_unsigned(&call), bad_proof);
+		let call = report(1, sealed_header(2, 1, 1, true), sealed_header(2, 1, 2, true));
+		assert_eq!(Babe::validate_unsigned(&call), bad_proof);
+
+		// equivocations outside of the current epoch can't be reported.
+		let call = report(100, sealed_header(1, 100, 1, true), sealed_header(1, 100, 2, true));
+		assert_eq!(Babe::validate_unsigned(&call), bad_proof);
+
+		// and the key ownership proof must be for the offender.
+		let call = Call::report_equivocation(
+			equivocation_proof(1, 1, sealed_header(1, 1, 1, true), sealed_header(1, 1, 2, true)),
+			key_owner_proof(2),
+		);
+		assert_eq!(Babe::validate_unsigned(&call), bad_proof);
+
+		let call = report(1, sealed_header(1, 1, 1, true), sealed_header(1, 1, 2, true));
+		assert!(Babe::validate_unsigned(&call).is_ok());
+	});
+}
+
+#[test]
+fn equivocation_reports_are_slashed() {
+	new_test_ext_with_session(vec![1, 2, 3, 4]).execute_with(|| {
+		assert_noop!(
+			Babe::report_equivocation(
+				system::RawOrigin::None.into(),
+				equivocation_proof(1, 1, sealed_header(1, 1, 1, true), sealed_header(1, 1, 2, true)),
+				key_owner_proof(2),
+			),
+			"Invalid key ownership proof.",
+		);
+
+		assert_ok!(Babe::report_equivocation(
+			system::RawOrigin::None.into(),
+			equivocation_proof(1, 1, sealed_header(1, 1, 1, true), sealed_header(1, 1, 2, true)),
+			key_owner_proof(1),
+		));
+
+		// (3 * 1 / 4)^2 of the stake of the offender is slashed.
+		assert_eq!(
+			SLASHES.with(|s| s.borrow().clone()),
+			vec![(vec![1], Perbill::from_parts(562_500_000))],
+		);
+	});
+}
+
+#[test]
+fn uncles_must_be_sealed_by_a_current_authority() {
+	new_test_ext(vec![0, 1, 2, 3]).execute_with(|| {
+		let header = |digest| Header::new(1, Default::default(), Default::default(), Default::default(), digest);
+		let mut digest = make_pre_digest(0, 1, [1; 32], [0xff; 64]);
+
+		assert!(Babe::verify_seal(&header(digest.clone())).is_err(), "unsealed headers are rejected");
+
+		digest.logs.push(DigestItem::Seal(BABE_ENGINE_ID, vec![0; 64]));
+		assert!(Babe::verify_seal(&header(digest)).is_err(), "invalid seals are rejected");
+
+		let mut digest = make_pre_digest(42, 1, [1; 32], [0xff; 64]);
+		digest.logs.push(DigestItem::Seal(BABE_ENGINE_ID, vec![0; 64]));
+		assert!(Babe::verify_seal(&header(digest)).is_err(), "unknown authorities are rejected");
+	});
+}
+
+#[test]
+fn uncles_sealed_by_a_current_authority_are_accepted() {
+	use session::VerifySealFromAuthorIndex;
+
+	new_test_ext_with_session(vec![1, 2, 3]).execute_with(|| {
+		CurrentSlot::put(5);
+
+		// validator 2 holds the second authority key of the epoch.
+		let uncle = sealed_header_by_index(1, 2, 4, 1, true);
+		assert_eq!(Babe::verify_seal(&uncle), Ok(Some(1)));
+		assert_eq!(VerifySealFromAuthorIndex::<Test, Babe>::verify_seal(&uncle), Ok(Some(2)));
+
+		// the first and the current slot of the epoch are accepted too.
+		assert_eq!(Babe::verify_seal(&sealed_header_by_index(1, 2, 0, 1, true)), Ok(Some(1)));
+		assert_eq!(Babe::verify_seal(&sealed_header_by_index(2, 3, 5, 1, true)), Ok(Some(2)));
+
+		// the seal must match the authority index of the pre-digest.
+		assert!(Babe::verify_seal(&sealed_header_by_index(0, 2, 4, 1, true)).is_err());
+
+		// slots after the current one are rejected.
+		assert!(Babe::verify_seal(&sealed_header_by_index(1, 2, 6, 1, true)).is_err());
+	});
+}
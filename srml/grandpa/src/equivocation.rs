@@ -0,0 +1,85 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Handling of GRANDPA equivocation reports.
+//!
+//! Equivocations detected by a voter are queued in the offchain local storage. The offchain
+//! worker of this module picks them up, proves that the offender was a validator in one of the
+//! sessions of the set the equivocation happened in, and submits both proofs as an unsigned
+//! `report_equivocation` transaction. Once included, the offence is reported through
+//! `ReportOffence` so that it can be slashed. The reporting itself is shared with the other
+//! consensus modules, see `session::equivocation`.
+
+use codec::{Encode, Decode};
+use sr_primitives::RuntimeDebug;
+use sr_staking_primitives::{SessionIndex, offence::Kind};
+use session::{equivocation::{ReportEquivocation, HandleEquivocation, EquivocationOffence}, historical};
+use crate::fg_primitives::{EquivocationProof, EQUIVOCATIONS_KEY, SetId, RoundNumber};
+
+use crate::{AuthorityId, Call, Module, Trait};
+
+/// An equivocation proof for the given runtime.
+pub type EquivocationProofOf<T> = EquivocationProof<<T as system::Trait>::Hash, <T as system::Trait>::BlockNumber>;
+
+/// The key ownership proof type used by the given runtime.
+pub type KeyOwnerProofOf<T> = <<T as Trait>::HandleEquivocation as HandleEquivocation<T, Module<T>>>::KeyOwnerProof;
+
+/// A GRANDPA equivocation offence of the given runtime.
+pub type GrandpaEquivocationOffence<T> = EquivocationOffence<Module<T>, historical::IdentificationTuple<T>>;
+
+impl<T: Trait> ReportEquivocation for Module<T> {
+	const OFFENCE_ID: Kind = *b"grandpa:equivoca";
+	const LOG_TARGET: &'static str = "afg";
+	const QUEUE_KEY: &'static [u8] = EQUIVOCATIONS_KEY;
+
+	type Key = AuthorityId;
+	type Proof = EquivocationProofOf<T>;
+	type TimeSlot = GrandpaTimeSlot;
+	type Call = Call<T>;
+
+	fn offender(equivocation: &Self::Proof) -> &AuthorityId {
+		&equivocation.offender
+	}
+
+	fn time_slot(equivocation: &Self::Proof) -> GrandpaTimeSlot {
+		GrandpaTimeSlot {
+			set_id: equivocation.set_id,
+			round: equivocation.round,
+		}
+	}
+
+	fn check(equivocation: &Self::Proof) -> bool {
+		equivocation.check()
+	}
+
+	fn key_owner_sessions(
+		equivocation: &Self::Proof,
+		_current: SessionIndex,
+	) -> Option<(SessionIndex, SessionIndex)> {
+		// the offender must have been a validator in one of the sessions of the set.
+		Self::sessions_of_set(equivocation.set_id)
+	}
+}
+
+/// A round number and set id which point on the time of an offence.
+#[derive(Copy, Clone, PartialOrd, Ord, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct GrandpaTimeSlot {
+	// The order of these matters for `derive(Ord)`.
+	/// The set id the equivocation happened in.
+	pub set_id: SetId,
+	/// The round the equivocation happened in.
+	pub round: RoundNumber,
+}
@@ -19,8 +19,9 @@
 //! This manages the GRANDPA authority set ready for the native code.
 //! These authorities are only for GRANDPA finality, not for consensus overall.
 //!
-//! In the future, it will also handle misbehavior reports, and on-chain
-//! finality notifications.
+//! Equivocations detected by voters are reported on-chain and turned into
+//! offences, see the `equivocation` module. In the future, it will also handle
+//! on-chain finality notifications.
 //!
 //! For full integration with GRANDPA, the `GrandpaApi` should be implemented.
 //! The necessary items are re-exported via the `fg_primitives` crate.
@@ -34,24 +35,33 @@ use rstd::prelude::*;
 use codec::{self as codec, Encode, Decode, Error};
 use support::{decl_event, decl_storage, decl_module, dispatch::Result, storage};
 use sr_primitives::{
-	generic::{DigestItem, OpaqueDigestItemId}, traits::Zero, Perbill,
-};
-use sr_staking_primitives::{
-	SessionIndex,
-	offence::{Offence, Kind},
+	generic::{DigestItem, OpaqueDigestItemId}, traits::Zero,
+	transaction_validity::{TransactionValidity, InvalidTransaction},
 };
+use sr_staking_primitives::SessionIndex;
 use fg_primitives::{
-	GRANDPA_AUTHORITIES_KEY, GRANDPA_ENGINE_ID, ScheduledChange, ConsensusLog, SetId, RoundNumber,
+	GRANDPA_AUTHORITIES_KEY, GRANDPA_ENGINE_ID, ScheduledChange, ConsensusLog, SetId,
 };
 pub use fg_primitives::{AuthorityId, AuthorityList, AuthorityWeight, VersionedAuthorityList};
-use system::{ensure_signed, DigestOf};
+use system::{ensure_signed, ensure_none, DigestOf};
 
+mod equivocation;
 mod mock;
 mod tests;
 
+pub use session::equivocation::{HandleEquivocation, EquivocationHandler};
+pub use self::equivocation::{
+	EquivocationProofOf, KeyOwnerProofOf, GrandpaEquivocationOffence, GrandpaTimeSlot,
+};
+
 pub trait Trait: system::Trait {
 	/// The event type of this module.
 	type Event: From<Event> + Into<<Self as system::Trait>::Event>;
+
+	/// Verifies and reports equivocations of GRANDPA authorities.
+	///
+	/// Use `()` to ignore equivocations, or `EquivocationHandler` to report them as offences.
+	type HandleEquivocation: HandleEquivocation<Self, Module<Self>>;
 }
 
 /// A stored pending change, old format.
@@ -178,6 +188,28 @@ decl_module! {
 			// FIXME: https://github.com/paritytech/substrate/issues/1112
 		}
 
+		/// Report an equivocation of a GRANDPA authority, along with the proof that the
+		/// offender's key belonged to a validator of the session the equivocation happened in.
+		///
+		/// This is submitted as an unsigned transaction by the offchain worker, the proofs are
+		/// checked by `validate_unsigned` before the transaction enters the pool.
+		fn report_equivocation(
+			origin,
+			equivocation: EquivocationProofOf<T>,
+			key_owner_proof: KeyOwnerProofOf<T>
+		) {
+			ensure_none(origin)?;
+
+			session::equivocation::report::<T, Self, T::HandleEquivocation>(equivocation, key_owner_proof)?;
+		}
+
+		// Runs after every block.
+		fn offchain_worker(_now: T::BlockNumber) {
+			session::equivocation::submit_queued_reports::<T, Self, T::HandleEquivocation>(
+				Call::report_equivocation
+			);
+		}
+
 		fn on_initialize() {
 			#[cfg(feature = "migrate-authorities")]
 			Self::migrate_authorities();
@@ -346,6 +378,23 @@ impl<T: Trait> Module<T> {
 		<system::Module<T>>::deposit_log(log.into());
 	}
 
+	/// The first and last session the given set was responsible for, as far as known yet.
+	///
+	/// A set starts in the session after the last one of the previous set. The genesis set is
+	/// only recorded once the genesis session ends, until then it covers the genesis session.
+	pub fn sessions_of_set(set_id: SetId) -> Option<(SessionIndex, SessionIndex)> {
+		let last = match Self::session_for_set(set_id) {
+			Some(last) => last,
+			None if set_id == 0 => 0,
+			None => return None,
+		};
+		let first = set_id.checked_sub(1)
+			.and_then(Self::session_for_set)
+			.map_or(0, |previous| previous + 1);
+
+		Some((first, last))
+	}
+
 	fn initialize_authorities(authorities: &AuthorityList) {
 		if !authorities.is_empty() {
 			assert!(
@@ -456,55 +505,18 @@ impl<T: Trait> finality_tracker::OnFinalizationStalled<T::BlockNumber> for Modul
 	}
 }
 
-/// A round number and set id which point on the time of an offence.
-#[derive(Copy, Clone, PartialOrd, Ord, Eq, PartialEq, Encode, Decode)]
-struct GrandpaTimeSlot {
-	// The order of these matters for `derive(Ord)`.
-	set_id: SetId,
-	round: RoundNumber,
-}
-
-// TODO [slashing]: Integrate this.
-/// A grandpa equivocation offence report.
-#[allow(dead_code)]
-struct GrandpaEquivocationOffence<FullIdentification> {
-	/// Time slot at which this incident happened.
-	time_slot: GrandpaTimeSlot,
-	/// The session index in which the incident happened.
-	session_index: SessionIndex,
-	/// The size of the validator set at the time of the offence.
-	validator_set_count: u32,
-	/// The authority which produced this equivocation.
-	offender: FullIdentification,
-}
-
-impl<FullIdentification: Clone> Offence<FullIdentification> for GrandpaEquivocationOffence<FullIdentification> {
-	const ID: Kind = *b"grandpa:equivoca";
-	type TimeSlot = GrandpaTimeSlot;
-
-	fn offenders(&self) -> Vec<FullIdentification> {
-		vec![self.offender.clone()]
-	}
+#[allow(deprecated)]
+impl<T: Trait> support::unsigned::ValidateUnsigned for Module<T> {
+	type Call = Call<T>;
 
-	fn session_index(&self) -> SessionIndex {
-		self.session_index
-	}
-
-	fn validator_set_count(&self) -> u32 {
-		self.validator_set_count
-	}
-
-	fn time_slot(&self) -> Self::TimeSlot {
-		self.time_slot
-	}
-
-	fn slash_fraction(
-		offenders_count: u32,
-		validator_set_count: u32,
-	) -> Perbill {
-		// the formula is min((3k / n)^2, 1)
-		let x = Perbill::from_rational_approximation(3 * offenders_count, validator_set_count);
-		// _ ^ 2
-		x.square()
+	fn validate_unsigned(call: &Self::Call) -> TransactionValidity {
+		if let Call::report_equivocation(equivocation, key_owner_proof) = call {
+			session::equivocation::validate_report::<T, Self, T::HandleEquivocation>(
+				equivocation,
+				key_owner_proof,
+			)
+		} else {
+			InvalidTransaction::Call.into()
+		}
 	}
 }
@@ -18,13 +18,19 @@
 
 #![cfg(test)]
 
-use sr_primitives::{Perbill, DigestItem, traits::IdentityLookup, testing::{Header, UintAuthorityId}};
+use std::cell::RefCell;
+use sr_primitives::{
+	Perbill, DigestItem, impl_opaque_keys,
+	traits::{IdentityLookup, ConvertInto},
+	testing::{Header, UintAuthorityId, TestXt},
+};
+use sr_staking_primitives::{SessionIndex, offence::{OnOffenceHandler, OffenceDetails}};
 use runtime_io;
 use support::{impl_outer_origin, impl_outer_event, parameter_types};
-use primitives::H256;
+use primitives::{H256, Pair};
 use codec::{Encode, Decode};
-use crate::{AuthorityId, AuthorityList, GenesisConfig, Trait, Module, ConsensusLog};
-use substrate_finality_grandpa_primitives::GRANDPA_ENGINE_ID;
+use crate::{AuthorityId, AuthorityList, GenesisConfig, Trait, Module, ConsensusLog, Call, EquivocationHandler};
+use substrate_finality_grandpa_primitives::{GRANDPA_ENGINE_ID, AuthorityPair};
 
 impl_outer_origin!{
 	pub enum Origin for Test {}
@@ -40,6 +46,7 @@ pub struct Test;
 
 impl Trait for Test {
 	type Event = TestEvent;
+	type HandleEquivocation = EquivocationHandler<Call<Test>, SubmitTransaction, Offences>;
 }
 parameter_types! {
 	pub const BlockHashCount: u64 = 250;
@@ -65,6 +72,78 @@ impl system::Trait for Test {
 	type Version = ();
 }
 
+impl_opaque_keys! {
+	pub struct TestSessionKeys {
+		pub grandpa: Grandpa,
+	}
+}
+
+thread_local! {
+	pub static NEXT_VALIDATORS: RefCell<Option<Vec<u64>>> = RefCell::new(None);
+	pub static SLASHES: RefCell<Vec<(Vec<u64>, Perbill)>> = RefCell::new(Vec::new());
+}
+
+pub struct TestOnSessionEnding;
+impl session::OnSessionEnding<u64> for TestOnSessionEnding {
+	fn on_session_ending(_ending_index: SessionIndex, _will_apply_at: SessionIndex) -> Option<Vec<u64>> {
+		NEXT_VALIDATORS.with(|v| v.borrow_mut().take())
+	}
+}
+
+impl session::historical::OnSessionEnding<u64, u64> for TestOnSessionEnding {
+	fn on_session_ending(_ending_index: SessionIndex, _will_apply_at: SessionIndex)
+		-> Option<(Vec<u64>, Vec<(u64, u64)>)>
+	{
+		NEXT_VALIDATORS.with(|v| v.borrow_mut().take()).map(|validators| {
+			let full_identification = validators.iter().map(|v| (*v, *v)).collect();
+			(validators, full_identification)
+		})
+	}
+}
+
+parameter_types! {
+	pub const Period: u64 = 1;
+	pub const Offset: u64 = 0;
+	pub const DisabledValidatorsThreshold: Perbill = Perbill::from_percent(33);
+}
+
+impl session::Trait for Test {
+	type ShouldEndSession = session::PeriodicSessions<Period, Offset>;
+	type OnSessionEnding = session::historical::NoteHistoricalRoot<Test, TestOnSessionEnding>;
+	type SessionHandler = (Grandpa,);
+	type ValidatorId = u64;
+	type ValidatorIdOf = ConvertInto;
+	type Keys = TestSessionKeys;
+	type Event = ();
+	type SelectInitialValidators = ();
+	type DisabledValidatorsThreshold = DisabledValidatorsThreshold;
+}
+
+impl session::historical::Trait for Test {
+	type FullIdentification = u64;
+	type FullIdentificationOf = ConvertInto;
+}
+
+/// Records the slash fractions applied to offenders.
+pub struct TestOnOffenceHandler;
+impl OnOffenceHandler<u64, (u64, u64)> for TestOnOffenceHandler {
+	fn on_offence(offenders: &[OffenceDetails<u64, (u64, u64)>], slash_fraction: &[Perbill]) {
+		SLASHES.with(|s| s.borrow_mut().extend(
+			offenders.iter().zip(slash_fraction).map(|(details, fraction)| {
+				(vec![details.offender.0], *fraction)
+			})
+		));
+	}
+}
+
+impl offences::Trait for Test {
+	type Event = TestEvent;
+	type IdentificationTuple = (u64, u64);
+	type OnOffenceHandler = TestOnOffenceHandler;
+}
+
+type SubmitTransaction = system::offchain::TransactionSubmitter<(), Call<Test>, TestXt<Call<Test>, ()>>;
+
 mod grandpa {
 	pub use crate::Event;
 }
@@ -72,6 +151,7 @@ mod grandpa {
 impl_outer_event!{
 	pub enum TestEvent for Test {
 		grandpa,
+		offences,
 	}
 }
 
@@ -89,5 +169,33 @@ pub fn new_test_ext(authorities: Vec<(u64, u64)>) -> runtime_io::TestExternaliti
 	t.into()
 }
 
+/// The GRANDPA key pair of the given validator.
+pub fn validator_pair(validator: u64) -> AuthorityPair {
+	AuthorityPair::from_seed(&[validator as u8; 32])
+}
+
+/// Build externalities with a session module whose genesis validators are the given accounts,
+/// each with its own GRANDPA key pair.
+pub fn new_test_ext_with_session(validators: Vec<u64>) -> runtime_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	session::GenesisConfig::<Test> {
+		keys: validators.into_iter()
+			.map(|v| (v, TestSessionKeys { grandpa: validator_pair(v).public() }))
+			.collect(),
+	}.assimilate_storage(&mut t).unwrap();
+	t.into()
+}
+
+/// Move to the next session, optionally changing the validators which take over two
+/// sessions later.
+pub fn advance_session(next_validators: Option<Vec<u64>>) {
+	NEXT_VALIDATORS.with(|v| *v.borrow_mut() = next_validators);
+	System::set_block_number(System::block_number() + 1);
+	Session::rotate_session();
+}
+
 pub type System = system::Module<Test>;
 pub type Grandpa = Module<Test>;
+pub type Session = session::Module<Test>;
+pub type Historical = session::historical::Module<Test>;
+pub type Offences = offences::Module<Test>;
@@ -18,11 +18,12 @@
 
 #![cfg(test)]
 
-use sr_primitives::{testing::Digest, traits::{Header, OnFinalize}};
+use sr_primitives::{Perbill, testing::Digest, traits::{Header, OnFinalize}};
 use crate::mock::*;
 use system::{EventRecord, Phase};
 use codec::{Decode, Encode};
 use fg_primitives::ScheduledChange;
+use support::{assert_ok, assert_noop};
 use super::*;
 
 #[test]
@@ -309,6 +310,123 @@ fn time_slot_have_sane_ord() {
 	assert!(FIXTURE.windows(2).all(|f| f[0] < f[1]));
 }
 
+fn equivocation_proof(
+	offender: u64,
+	set_id: fg_primitives::SetId,
+	targets: (u8, u8),
+	sign: bool,
+) -> EquivocationProofOf<Test> {
+	use fg_primitives::{AuthoritySignature, SignedVote, VotePhase, localized_vote_payload};
+	use primitives::Pair;
+
+	let pair = validator_pair(offender);
+	let round = 1;
+	let vote = |byte| {
+		let target_hash = primitives::H256::repeat_byte(byte);
+		let signature = if sign {
+			pair.sign(&localized_vote_payload(VotePhase::Prevote, &target_hash, &1u64, round, set_id))
+		} else {
+			AuthoritySignature::decode(&mut &[0u8; 64][..]).unwrap()
+		};
+		SignedVote { target_hash, target_number: 1, signature }
+	};
+
+	fg_primitives::EquivocationProof {
+		set_id,
+		round,
+		phase: VotePhase::Prevote,
+		offender: pair.public(),
+		first: vote(targets.0),
+		second: vote(targets.1),
+	}
+}
+
+fn key_owner_proof(validator: u64) -> KeyOwnerProofOf<Test> {
+	use support::traits::KeyOwnerProofSystem;
+
+	let key = validator_pair(validator).public();
+	Historical::prove((primitives::crypto::key_types::GRANDPA, key)).unwrap()
+}
+
+#[test]
+fn equivocation_reports_with_invalid_proofs_are_rejected() {
+	use sr_primitives::transaction_validity::TransactionValidityError;
+	use support::unsigned::ValidateUnsigned;
+
+	new_test_ext_with_session(vec![1, 2, 3]).execute_with(|| {
+		let bad_proof = Err(TransactionValidityError::from(InvalidTransaction::BadProof));
+
+		// the same vote twice isn't an equivocation.
+		let call = Call::report_equivocation(equivocation_proof(1, 0, (1, 1), true), key_owner_proof(1));
+		assert_eq!(Grandpa::validate_unsigned(&call), bad_proof);
+
+		// votes that aren't signed by the offender aren't either.
+		let call = Call::report_equivocation(equivocation_proof(1, 0, (1, 2), false), key_owner_proof(1));
+		assert_eq!(Grandpa::validate_unsigned(&call), bad_proof);
+
+		// the key ownership proof must be for the offender.
+		let call = Call::report_equivocation(equivocation_proof(1, 0, (1, 2), true), key_owner_proof(2));
+		assert_eq!(Grandpa::validate_unsigned(&call), bad_proof);
+
+		let call = Call::report_equivocation(equivocation_proof(1, 0, (1, 2), true), key_owner_proof(1));
+		assert!(Grandpa::validate_unsigned(&call).is_ok());
+	});
+}
+
+#[test]
+fn equivocation_reports_are_slashed() {
+	new_test_ext_with_session(vec![1, 2, 3, 4]).execute_with(|| {
+		// the proof is generated in the session of the equivocation, but the report is only
+		// included once the set has moved on to later sessions.
+		let proof = key_owner_proof(1);
+		advance_session(None);
+		advance_session(None);
+		assert_eq!(Grandpa::current_set_id(), 0);
+		assert_eq!(Grandpa::sessions_of_set(0), Some((0, 2)));
+
+		assert_ok!(Grandpa::report_equivocation(
+			system::RawOrigin::None.into(),
+			equivocation_proof(1, 0, (1, 2), true),
+			proof,
+		));
+
+		// (3 * 1 / 4)^2 of the stake of the offender is slashed.
+		assert_eq!(
+			SLASHES.with(|s| s.borrow().clone()),
+			vec![(vec![1], Perbill::from_parts(562_500_000))],
+		);
+	});
+}
+
+#[test]
+fn key_ownership_proofs_must_be_from_a_session_of_the_set() {
+	new_test_ext_with_session(vec![1, 2, 3, 4]).execute_with(|| {
+		advance_session(None);
+		let proof_of_set_0 = key_owner_proof(1);
+
+		// the next session is the first one of a new set.
+		CurrentSetId::put(1);
+		advance_session(None);
+		assert_eq!(Grandpa::sessions_of_set(0), Some((0, 1)));
+		assert_eq!(Grandpa::sessions_of_set(1), Some((2, 2)));
+		let proof_of_set_1 = key_owner_proof(1);
+
+		let report = |set_id, proof| Grandpa::report_equivocation(
+			system::RawOrigin::None.into(),
+			equivocation_proof(1, set_id, (1, 2), true),
+			proof,
+		);
+
+		assert_noop!(report(1, proof_of_set_0.clone()), "Invalid key ownership proof.");
+		assert_noop!(report(0, proof_of_set_1.clone()), "Invalid key ownership proof.");
+		assert_noop!(report(2, proof_of_set_1.clone()), "Invalid key ownership proof.");
+
+		assert_ok!(report(0, proof_of_set_0));
+		assert_ok!(report(1, proof_of_set_1));
+		assert_eq!(SLASHES.with(|s| s.borrow().len()), 2);
+	});
+}
+
 #[test]
 #[cfg(feature = "migrate-authorities")]
 fn authorities_migration() {
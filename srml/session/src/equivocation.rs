@@ -0,0 +1,335 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reporting of equivocations of session validators as offences.
+//!
+//! Consensus engines detect equivocations of their authorities on the client side and queue
+//! the proofs in the offchain local storage. The offchain worker of the consensus module takes
+//! the queue, proves that each offender's key belongs to a validator with a historical session
+//! proof and submits both proofs as an unsigned `report_equivocation` transaction. Once
+//! included, the equivocation is reported through `ReportOffence` so that it can be slashed.
+//!
+//! A consensus module describes its equivocations by implementing `ReportEquivocation` and
+//! uses `submit_queued_reports`, `report` and `validate_report` to implement its offchain
+//! worker, its `report_equivocation` call and its `ValidateUnsigned` impl respectively. The
+//! runtime decides what happens with the reports through a `HandleEquivocation` type: `()`
+//! rejects all of them, `EquivocationHandler` reports them as offences.
+
+use rstd::prelude::*;
+use codec::{Codec, Decode, Encode};
+use primitives::offchain::StorageKind;
+use sr_primitives::{KeyTypeId, Perbill, RuntimeAppPublic, RuntimeDebug};
+use sr_primitives::transaction_validity::{
+	TransactionValidity, ValidTransaction, InvalidTransaction, TransactionPriority, TransactionLongevity,
+};
+use sr_staking_primitives::{SessionIndex, offence::{Offence, ReportOffence, Kind}};
+use support::{Parameter, debug, dispatch::Result, traits::KeyOwnerProofSystem};
+use system::offchain::SubmitUnsignedTransaction;
+use crate::historical::{self, IdentificationTuple};
+
+/// The number of blocks an equivocation report stays valid in the transaction pool.
+pub const REPORT_LONGEVITY: TransactionLongevity = 64;
+
+/// The equivocations of the authorities of a consensus module.
+pub trait ReportEquivocation {
+	/// The kind of the offences the equivocations are reported as.
+	const OFFENCE_ID: Kind;
+
+	/// The target of the log messages of the offchain worker.
+	const LOG_TARGET: &'static str;
+
+	/// The offchain local storage key under which the client queues the equivocations, as an
+	/// encoded `Vec<Self::Proof>`.
+	const QUEUE_KEY: &'static [u8];
+
+	/// The session key of an authority.
+	type Key: RuntimeAppPublic + Parameter;
+
+	/// A proof of an equivocation.
+	type Proof: Parameter;
+
+	/// The point in time an equivocation happened at. Only one report per offender and time
+	/// slot is accepted.
+	type TimeSlot: Clone + Codec + Ord + rstd::fmt::Debug;
+
+	/// The `report_equivocation` call of the module.
+	type Call;
+
+	/// The authority that equivocated.
+	fn offender(equivocation: &Self::Proof) -> &Self::Key;
+
+	/// The point in time the equivocation happened at.
+	fn time_slot(equivocation: &Self::Proof) -> Self::TimeSlot;
+
+	/// Check that the proof shows an equivocation, and that it is recent enough to be reported.
+	fn check(equivocation: &Self::Proof) -> bool;
+
+	/// The first and last session in which the offender must have owned its key, given the
+	/// index of the current session.
+	///
+	/// Key ownership proofs from other sessions are rejected, since the key could have been owned
+	/// by a different validator at the time.
+	fn key_owner_sessions(
+		equivocation: &Self::Proof,
+		current: SessionIndex,
+	) -> Option<(SessionIndex, SessionIndex)>;
+}
+
+/// Verification and reporting of the equivocations `E`.
+pub trait HandleEquivocation<T: system::Trait, E: ReportEquivocation> {
+	/// Proof that an authority key belonged to a validator at the time of the equivocation.
+	type KeyOwnerProof: Parameter;
+
+	/// Prove that the given authority key belongs to a validator of the current session.
+	///
+	/// This is only called off-chain, since it may be computationally heavy.
+	fn prove_key_ownership(key: &E::Key) -> Option<Self::KeyOwnerProof>;
+
+	/// Check that the given key ownership proof is valid for the offender of the equivocation.
+	fn check_key_ownership(equivocation: &E::Proof, proof: &Self::KeyOwnerProof) -> bool;
+
+	/// Report the given equivocation as an offence. The equivocation proof has already been
+	/// checked, the key ownership proof must be checked again.
+	fn report_offence(equivocation: &E::Proof, key_owner_proof: Self::KeyOwnerProof) -> Result;
+
+	/// Submit the given `report_equivocation` call as an unsigned transaction.
+	fn submit_report(call: E::Call) -> rstd::result::Result<(), ()>;
+}
+
+impl<T: system::Trait, E: ReportEquivocation> HandleEquivocation<T, E> for () {
+	type KeyOwnerProof = ();
+
+	fn prove_key_ownership(_key: &E::Key) -> Option<()> {
+		None
+	}
+
+	fn check_key_ownership(_equivocation: &E::Proof, _proof: &()) -> bool {
+		false
+	}
+
+	fn report_offence(_equivocation: &E::Proof, _key_owner_proof: ()) -> Result {
+		Err("Equivocation reports are not supported.")
+	}
+
+	fn submit_report(_call: E::Call) -> rstd::result::Result<(), ()> {
+		Err(())
+	}
+}
+
+/// A `HandleEquivocation` implementation for runtimes tracking historical sessions.
+///
+/// Key ownership is proven with a session historical proof, reports are submitted with the
+/// transaction submitter `S` as calls of type `C`, and offences are reported to `R`.
+pub struct EquivocationHandler<C, S, R>(rstd::marker::PhantomData<(C, S, R)>);
+
+impl<T, E, C, S, R> HandleEquivocation<T, E> for EquivocationHandler<C, S, R> where
+	T: historical::Trait,
+	E: ReportEquivocation,
+	C: From<E::Call>,
+	S: SubmitUnsignedTransaction<T, C>,
+	R: ReportOffence<T::AccountId, IdentificationTuple<T>, EquivocationOffence<E, IdentificationTuple<T>>>,
+{
+	type KeyOwnerProof = historical::Proof;
+
+	fn prove_key_ownership(key: &E::Key) -> Option<historical::Proof> {
+		<historical::Module<T>>::prove(session_key::<E>(key))
+	}
+
+	fn check_key_ownership(equivocation: &E::Proof, proof: &historical::Proof) -> bool {
+		identify::<T, E>(equivocation, proof.clone()).is_some()
+	}
+
+	fn report_offence(equivocation: &E::Proof, key_owner_proof: historical::Proof) -> Result {
+		let session_index = key_owner_proof.session();
+		let validator_set_count = <historical::Module<T>>::validator_count(session_index)
+			.ok_or("Session of the key ownership proof is not stored anymore.")?;
+		let offender = identify::<T, E>(equivocation, key_owner_proof)
+			.ok_or("Invalid key ownership proof.")?;
+
+		let offence = EquivocationOffence {
+			time_slot: E::time_slot(equivocation),
+			session_index,
+			validator_set_count,
+			offender,
+		};
+
+		R::report_offence(Vec::new(), offence);
+		Ok(())
+	}
+
+	fn submit_report(call: E::Call) -> rstd::result::Result<(), ()> {
+		S::submit_unsigned(call)
+	}
+}
+
+/// Check the key ownership proof of the offender of an equivocation, returning the full
+/// identification of the validator owning the key.
+fn identify<T: historical::Trait, E: ReportEquivocation>(
+	equivocation: &E::Proof,
+	proof: historical::Proof,
+) -> Option<IdentificationTuple<T>> {
+	let current = <crate::Module<T>>::current_index();
+	let (first, last) = E::key_owner_sessions(equivocation, current)?;
+	if proof.session() < first || proof.session() > last {
+		return None;
+	}
+
+	<historical::Module<T>>::check_proof(session_key::<E>(E::offender(equivocation)), proof)
+}
+
+/// The key type and raw public key of the given authority, as registered in the session module.
+fn session_key<E: ReportEquivocation>(key: &E::Key) -> (KeyTypeId, Vec<u8>) {
+	(<E::Key as RuntimeAppPublic>::ID, key.encode())
+}
+
+/// Take the equivocations queued by the client and submit a report for each of them.
+///
+/// This is meant to be called from the offchain worker of the module, `report_call` builds its
+/// `report_equivocation` call. Equivocations of authorities whose key ownership can't be proven
+/// anymore are dropped.
+pub fn submit_queued_reports<T, E, H>(report_call: impl Fn(E::Proof, H::KeyOwnerProof) -> E::Call) where
+	T: system::Trait,
+	E: ReportEquivocation,
+	H: HandleEquivocation<T, E>,
+{
+	let queued = match runtime_io::local_storage_get(StorageKind::PERSISTENT, E::QUEUE_KEY) {
+		Some(queued) => queued,
+		None => return,
+	};
+
+	let equivocations = match <Vec<E::Proof>>::decode(&mut &queued[..]) {
+		Ok(ref equivocations) if equivocations.is_empty() => return,
+		Ok(equivocations) => equivocations,
+		Err(_) => {
+			debug::native::warn!(target: E::LOG_TARGET, "Dropping undecodable equivocation queue");
+			Vec::new()
+		},
+	};
+
+	// the client might have queued a new equivocation in the meantime, in which case the queue
+	// is left untouched and taken by the next run of the worker.
+	let taken = runtime_io::local_storage_compare_and_set(
+		StorageKind::PERSISTENT,
+		E::QUEUE_KEY,
+		Some(&queued[..]),
+		&Vec::<E::Proof>::new().encode(),
+	);
+	if !taken {
+		return;
+	}
+
+	for equivocation in equivocations {
+		let key_owner_proof = match H::prove_key_ownership(E::offender(&equivocation)) {
+			Some(proof) => proof,
+			None => {
+				debug::native::debug!(
+					target: E::LOG_TARGET,
+					"Unable to prove key ownership of equivocating authority {:?}",
+					E::offender(&equivocation),
+				);
+				continue;
+			},
+		};
+
+		if H::submit_report(report_call(equivocation, key_owner_proof)).is_err() {
+			debug::native::warn!(target: E::LOG_TARGET, "Failed to submit equivocation report");
+		}
+	}
+}
+
+/// Check the proofs of a `report_equivocation` call and report the equivocation.
+pub fn report<T, E, H>(equivocation: E::Proof, key_owner_proof: H::KeyOwnerProof) -> Result where
+	T: system::Trait,
+	E: ReportEquivocation,
+	H: HandleEquivocation<T, E>,
+{
+	if !E::check(&equivocation) {
+		return Err("Invalid equivocation proof.");
+	}
+
+	H::report_offence(&equivocation, key_owner_proof)
+}
+
+/// Validate an unsigned `report_equivocation` transaction before it enters the pool.
+pub fn validate_report<T, E, H>(
+	equivocation: &E::Proof,
+	key_owner_proof: &H::KeyOwnerProof,
+) -> TransactionValidity where
+	T: system::Trait,
+	E: ReportEquivocation,
+	H: HandleEquivocation<T, E>,
+{
+	if !E::check(equivocation) || !H::check_key_ownership(equivocation, key_owner_proof) {
+		return InvalidTransaction::BadProof.into();
+	}
+
+	Ok(ValidTransaction {
+		priority: TransactionPriority::max_value(),
+		requires: vec![],
+		// only one report per offender and time slot is needed.
+		provides: vec![(E::OFFENCE_ID, E::offender(equivocation), E::time_slot(equivocation)).encode()],
+		longevity: REPORT_LONGEVITY,
+		propagate: true,
+	})
+}
+
+/// An equivocation offence of a validator.
+#[derive(RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Clone, PartialEq, Eq))]
+pub struct EquivocationOffence<E: ReportEquivocation, FullIdentification> {
+	/// The point in time the equivocation happened at.
+	time_slot: E::TimeSlot,
+	/// The session index in which the incident happened.
+	session_index: SessionIndex,
+	/// The size of the validator set at the time of the offence.
+	validator_set_count: u32,
+	/// The validator that equivocated.
+	offender: FullIdentification,
+}
+
+impl<E, FullIdentification> Offence<FullIdentification> for EquivocationOffence<E, FullIdentification> where
+	E: ReportEquivocation,
+	FullIdentification: Clone,
+{
+	const ID: Kind = E::OFFENCE_ID;
+	type TimeSlot = E::TimeSlot;
+
+	fn offenders(&self) -> Vec<FullIdentification> {
+		vec![self.offender.clone()]
+	}
+
+	fn session_index(&self) -> SessionIndex {
+		self.session_index
+	}
+
+	fn validator_set_count(&self) -> u32 {
+		self.validator_set_count
+	}
+
+	fn time_slot(&self) -> Self::TimeSlot {
+		self.time_slot.clone()
+	}
+
+	fn slash_fraction(
+		offenders_count: u32,
+		validator_set_count: u32,
+	) -> Perbill {
+		// the formula is min((3k / n)^2, 1)
+		let x = Perbill::from_rational_approximation(3 * offenders_count, validator_set_count);
+		// _ ^ 2
+		x.square()
+	}
+}
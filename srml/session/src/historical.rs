@@ -27,7 +27,7 @@
 
 use rstd::prelude::*;
 use codec::{Encode, Decode};
-use sr_primitives::KeyTypeId;
+use sr_primitives::{KeyTypeId, RuntimeDebug};
 use sr_primitives::traits::{Convert, OpaqueKeys, Hash as HashT};
 use support::{decl_module, decl_storage};
 use support::{Parameter, print};
@@ -95,6 +95,16 @@ impl<T: Trait> Module<T> {
 			}
 		})
 	}
+
+	/// The number of validators in the given session, if the session is the current one or
+	/// one of the historical sessions still stored.
+	pub fn validator_count(session: SessionIndex) -> Option<ValidatorCount> {
+		if session == <SessionModule<T>>::current_index() {
+			Some(<SessionModule<T>>::validators().len() as ValidatorCount)
+		} else {
+			Self::historical_root(session).map(|(_, count)| count)
+		}
+	}
 }
 
 /// Specialization of the crate-level `OnSessionEnding` which returns the old
@@ -267,12 +277,19 @@ impl<T: Trait> ProvingTrie<T> {
 }
 
 /// Proof of ownership of a specific key.
-#[derive(Encode, Decode, Clone)]
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
 pub struct Proof {
 	session: SessionIndex,
 	trie_nodes: Vec<Vec<u8>>,
 }
 
+impl Proof {
+	/// The session the proof was generated in.
+	pub fn session(&self) -> SessionIndex {
+		self.session
+	}
+}
+
 impl<T: Trait, D: AsRef<[u8]>> support::traits::KeyOwnerProofSystem<(KeyTypeId, D)>
 	for Module<T>
 {
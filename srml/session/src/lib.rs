@@ -129,7 +129,7 @@ use support::{
 	dispatch::Result, ConsensusEngineId, decl_module, decl_event,
 	decl_storage,
 };
-use support::{ensure, traits::{OnFreeBalanceZero, Get, FindAuthor}, Parameter};
+use support::{ensure, traits::{OnFreeBalanceZero, Get, FindAuthor, VerifySeal}, Parameter};
 use system::{self, ensure_signed};
 
 #[cfg(test)]
@@ -137,6 +137,8 @@ mod mock;
 
 #[cfg(feature = "historical")]
 pub mod historical;
+#[cfg(feature = "historical")]
+pub mod equivocation;
 
 /// Decides whether the session should be ended.
 pub trait ShouldEndSession<BlockNumber> {
@@ -677,7 +679,8 @@ impl<T: Trait> Module<T> {
 		<NextKeys<T>>::insert(DEDUP_KEY_PREFIX, v, keys);
 	}
 
-	fn key_owner(id: KeyTypeId, key_data: &[u8]) -> Option<T::ValidatorId> {
+	/// Returns the validator the given session key is registered for, if any.
+	pub fn key_owner(id: KeyTypeId, key_data: &[u8]) -> Option<T::ValidatorId> {
 		<KeyOwner<T>>::get(DEDUP_KEY_PREFIX, (id, key_data))
 	}
 
@@ -714,6 +717,27 @@ impl<T: Trait, Inner: FindAuthor<u32>> FindAuthor<T::ValidatorId>
 	}
 }
 
+/// Wraps the seal verification of consensus engines that can recover the
+/// canonical index of an author. This then transforms it into the registering
+/// account-ID of that session key index.
+pub struct VerifySealFromAuthorIndex<T, Inner>(rstd::marker::PhantomData<(T, Inner)>);
+
+impl<T: Trait, Header, Inner: VerifySeal<Header, u32>> VerifySeal<Header, T::ValidatorId>
+	for VerifySealFromAuthorIndex<T, Inner>
+{
+	fn verify_seal(header: &Header) -> rstd::result::Result<Option<T::ValidatorId>, &'static str> {
+		let i = match Inner::verify_seal(header)? {
+			Some(i) => i,
+			None => return Ok(None),
+		};
+
+		let validators = <Module<T>>::validators();
+		validators.get(i as usize)
+			.map(|k| Some(k.clone()))
+			.ok_or("author index is not a validator of the current session")
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
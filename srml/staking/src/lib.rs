@@ -181,6 +181,8 @@
 //! [`Module`](./struct.Module.html) implements
 //! [`authorship::EventHandler`](../srml_authorship/trait.EventHandler.html) to add reward points
 //! to block producer and block producer of referenced uncles.
+//! [`AgeWeightedUncleRewards`](./struct.AgeWeightedUncleRewards.html) can be used instead to reward
+//! uncle producers more, depending on the age of their uncles.
 //!
 //! The validator and its nominator split their reward as following:
 //!
@@ -1469,6 +1471,40 @@ impl<T: Trait + authorship::Trait> authorship::EventHandler<T::AccountId, T::Blo
 	}
 }
 
+/// The reward points of the author of a fresh uncle with `AgeWeightedUncleRewards`.
+const MAX_UNCLE_POINTS: u64 = 10;
+
+/// An [`authorship::EventHandler`](../srml_authorship/trait.EventHandler.html) which rewards
+/// the authors of uncles more than [`Module`](./struct.Module.html) does, but less than the
+/// authors of canonical blocks, and less the older their uncle is.
+///
+/// Block authors earn 20 points, and 2 points for each uncle they reference, like with
+/// `Module`. The author of an uncle which is `age` blocks older than the block referencing it
+/// earns `10 * (UncleGenerations + 1 - age) / (UncleGenerations + 1)` points.
+///
+/// This is opt-in: a runtime uses it as the event handler of the authorship module instead of
+/// `Module`. Only uncles with a known author are rewarded, so the `FilterUncle` of the
+/// authorship module should check the seals of uncles.
+pub struct AgeWeightedUncleRewards<T>(rstd::marker::PhantomData<T>);
+
+impl<T: Trait + authorship::Trait> authorship::EventHandler<T::AccountId, T::BlockNumber>
+	for AgeWeightedUncleRewards<T>
+{
+	fn note_author(author: T::AccountId) {
+		<Module<T> as authorship::EventHandler<_, _>>::note_author(author)
+	}
+	fn note_uncle(author: T::AccountId, age: T::BlockNumber) {
+		let generations = T::UncleGenerations::get().saturated_into::<u64>().saturating_add(1);
+		let freshness = generations.saturating_sub(age.saturated_into::<u64>());
+		let points = MAX_UNCLE_POINTS.saturating_mul(freshness) / generations;
+
+		<Module<T>>::reward_by_ids(vec![
+			(<authorship::Module<T>>::author(), 2),
+			(author, points as u32),
+		])
+	}
+}
+
 /// A `Convert` implementation that finds the stash of the given controller account,
 /// if any.
 pub struct StashOf<T>(rstd::marker::PhantomData<T>);
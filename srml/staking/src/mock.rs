@@ -48,6 +48,7 @@ impl Convert<u128, u64> for CurrencyToVoteHandler {
 thread_local! {
 	static SESSION: RefCell<(Vec<AccountId>, HashSet<AccountId>)> = RefCell::new(Default::default());
 	static EXISTENTIAL_DEPOSIT: RefCell<u64> = RefCell::new(0);
+	static UNCLE_GENERATIONS: RefCell<u64> = RefCell::new(0);
 }
 
 pub struct TestSessionHandler;
@@ -87,6 +88,13 @@ impl Get<u64> for ExistentialDeposit {
 	}
 }
 
+pub struct UncleGenerations;
+impl Get<u64> for UncleGenerations {
+	fn get() -> u64 {
+		UNCLE_GENERATIONS.with(|v| *v.borrow())
+	}
+}
+
 impl_outer_origin!{
 	pub enum Origin for Test {}
 }
@@ -145,7 +153,6 @@ impl balances::Trait for Test {
 parameter_types! {
 	pub const Period: BlockNumber = 1;
 	pub const Offset: BlockNumber = 0;
-	pub const UncleGenerations: u64 = 0;
 	pub const DisabledValidatorsThreshold: Perbill = Perbill::from_percent(25);
 }
 impl session::Trait for Test {
@@ -209,6 +216,7 @@ impl Trait for Test {
 
 pub struct ExtBuilder {
 	existential_deposit: u64,
+	uncle_generations: u64,
 	validator_pool: bool,
 	nominate: bool,
 	validator_count: u32,
@@ -222,6 +230,7 @@ impl Default for ExtBuilder {
 	fn default() -> Self {
 		Self {
 			existential_deposit: 0,
+			uncle_generations: 0,
 			validator_pool: false,
 			nominate: true,
 			validator_count: 2,
@@ -238,6 +247,10 @@ impl ExtBuilder {
 		self.existential_deposit = existential_deposit;
 		self
 	}
+	pub fn uncle_generations(mut self, uncle_generations: u64) -> Self {
+		self.uncle_generations = uncle_generations;
+		self
+	}
 	pub fn validator_pool(mut self, validator_pool: bool) -> Self {
 		self.validator_pool = validator_pool;
 		self
@@ -268,6 +281,7 @@ impl ExtBuilder {
 	}
 	pub fn set_associated_consts(&self) {
 		EXISTENTIAL_DEPOSIT.with(|v| *v.borrow_mut() = self.existential_deposit);
+		UNCLE_GENERATIONS.with(|v| *v.borrow_mut() = self.uncle_generations);
 	}
 	pub fn build(self) -> runtime_io::TestExternalities {
 		self.set_associated_consts();
@@ -1696,6 +1696,27 @@ fn reward_from_authorship_event_handler_works() {
 	})
 }
 
+#[test]
+fn age_weighted_uncle_rewards_depend_on_uncle_age() {
+	ExtBuilder::default().uncle_generations(4).build().execute_with(|| {
+		use authorship::EventHandler;
+
+		assert_eq!(<authorship::Module<Test>>::author(), 11);
+		assert_eq!(<CurrentElected<Test>>::get(), vec![21, 11]);
+
+		AgeWeightedUncleRewards::<Test>::note_author(11);
+		// with 4 uncle generations, a sibling of the parent earns 4/5 of the
+		// uncle reward, and the oldest includable uncle 1/5.
+		AgeWeightedUncleRewards::<Test>::note_uncle(21, 1);
+		AgeWeightedUncleRewards::<Test>::note_uncle(21, 4);
+		// uncles too old to be included earn nothing.
+		AgeWeightedUncleRewards::<Test>::note_uncle(21, 5);
+
+		assert_eq!(CurrentEraPointsEarned::get().individual, vec![8 + 2, 20 + 2 * 3]);
+		assert_eq!(CurrentEraPointsEarned::get().total, 36);
+	})
+}
+
 #[test]
 fn add_reward_points_fns_works() {
 	ExtBuilder::default().build().execute_with(|| {